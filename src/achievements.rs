@@ -0,0 +1,114 @@
+use std::path::Path;
+
+use instant::SystemTime;
+
+use crate::assets;
+use crate::events::GameEvent;
+use crate::profile::Profile;
+
+// 成就系统：条件定义在 data/achievements.json，由事件总线驱动判断
+
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatKind {
+    Kills,
+    Deaths,
+    Headshots,
+    ShotsHit,
+    BestWave,
+    MatchesPlayed,
+    PlayTimeMinutes,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    // 终身统计达到某个值
+    LifetimeStat { stat: StatKind, at_least: u64 },
+    // 一局内的击杀数
+    KillsInMatch { count: u32 },
+    // 一局内不受伤打过第 N 波
+    WaveWithoutDamage { wave: u32 },
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Achievement {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub condition: Condition,
+}
+
+pub struct AchievementTracker {
+    definitions: Vec<Achievement>,
+    // 本局是否受过伤
+    damaged_this_match: bool,
+}
+
+impl AchievementTracker {
+    pub fn new() -> Self {
+        let text = assets::read_to_string_or_builtin(Path::new("data/achievements.json"), include_str!("../data/achievements.json"));
+        let definitions = match serde_json::from_str(&text) {
+            Ok(definitions) => definitions,
+            Err(e) => {
+                eprintln!("成就定义解析失败: {}", e);
+                Vec::new()
+            }
+        };
+        Self { definitions, damaged_this_match: false }
+    }
+
+    // 处理一个事件（需在 Profile::record 之后调用），返回新解锁的成就
+    pub fn evaluate(&mut self, event: &GameEvent, profile: &mut Profile) -> Vec<Achievement> {
+        match event {
+            GameEvent::MatchStarted => self.damaged_this_match = false,
+            GameEvent::DamageTaken { amount } if *amount > 0.0 => self.damaged_this_match = true,
+            _ => {}
+        }
+
+        let mut unlocked = Vec::new();
+        for achievement in &self.definitions {
+            if profile.achievements.contains_key(&achievement.id) {
+                continue;
+            }
+            if self.is_met(&achievement.condition, event, profile) {
+                let timestamp = SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                profile.achievements.insert(achievement.id.clone(), timestamp);
+                unlocked.push(achievement.clone());
+            }
+        }
+        unlocked
+    }
+
+    fn is_met(&self, condition: &Condition, event: &GameEvent, profile: &Profile) -> bool {
+        match condition {
+            Condition::LifetimeStat { stat, at_least } => {
+                let stats = &profile.stats;
+                let value = match stat {
+                    StatKind::Kills => stats.kills,
+                    StatKind::Deaths => stats.deaths,
+                    StatKind::Headshots => stats.headshots,
+                    StatKind::ShotsHit => stats.shots_hit,
+                    StatKind::BestWave => stats.best_wave as u64,
+                    StatKind::MatchesPlayed => stats.matches_played,
+                    StatKind::PlayTimeMinutes => (stats.play_time_secs / 60.0) as u64,
+                };
+                value >= *at_least
+            }
+            Condition::KillsInMatch { count } => {
+                profile.current_match.as_ref().is_some_and(|m| m.kills >= *count)
+            }
+            Condition::WaveWithoutDamage { wave } => {
+                matches!(event, GameEvent::WaveCleared { wave: cleared } if cleared >= wave)
+                    && !self.damaged_this_match
+            }
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.definitions.len()
+    }
+}
@@ -0,0 +1,76 @@
+use glam::Vec3;
+
+use crate::enemy::Inspection;
+use crate::ui::UiBatch;
+
+// AI 调试面板：聊天框里输入 /inspect 打开，准星对着敌人时显示它的状态、血量、感知（距离、角度、视线）
+// 和最近发生的事，到玩家的路线用调试线画在场景里
+
+const PANEL_WIDTH: f32 = 330.0;
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const TITLE_COLOR: [f32; 4] = [0.4, 0.85, 1.0, 0.95];
+const TEXT_COLOR: [f32; 4] = [0.9, 1.0, 0.9, 0.95];
+const DIM_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 0.9];
+const YES_COLOR: [f32; 4] = [0.3, 1.0, 0.4, 0.9];
+const NO_COLOR: [f32; 4] = [1.0, 0.3, 0.25, 0.9];
+// 场景里路线的颜色
+pub const PATH_COLOR: [f32; 3] = [0.2, 0.8, 1.0];
+
+pub struct AiInspector {
+    pub open: bool,
+    inspection: Option<Inspection>,
+}
+
+impl AiInspector {
+    pub fn new() -> Self {
+        Self { open: false, inspection: None }
+    }
+
+    // 每帧调用，没有瞄准敌人时为 None
+    pub fn update(&mut self, inspection: Option<Inspection>) {
+        self.inspection = inspection.filter(|_| self.open);
+    }
+
+    // 要画的路线，面板关着或者没瞄准敌人时为空
+    pub fn path(&self) -> &[Vec3] {
+        self.inspection.as_ref().map_or(&[], |inspection| &inspection.path)
+    }
+
+    pub fn draw(&self, ui: &mut UiBatch) {
+        if !self.open {
+            return;
+        }
+        let scale = 2.0;
+        let line = UiBatch::line_height(scale) + 2.0;
+        let (x, y) = (10.0, 160.0);
+        let Some(inspection) = &self.inspection else {
+            ui.rect(x - 6.0, y - 6.0, PANEL_WIDTH, line + 12.0, PANEL_COLOR);
+            ui.text(x, y, scale, DIM_COLOR, "AIM AT AN ENEMY TO INSPECT");
+            return;
+        };
+        let mut lines = vec![
+            (TITLE_COLOR, format!("AI {}", inspection.state)),
+            (TEXT_COLOR, format!("HEALTH {:.0}/{:.0}", inspection.health, inspection.max_health)),
+            (TEXT_COLOR, format!("DISTANCE {:.1}M ANGLE {:.0}", inspection.distance, inspection.angle)),
+            (TEXT_COLOR, format!("SIGHT RANGE {:.0}M", inspection.sight_range)),
+            if inspection.line_of_sight { (YES_COLOR, "SEES PLAYER".to_string()) } else { (NO_COLOR, "NO LINE OF SIGHT".to_string()) },
+            match inspection.path.len() {
+                0 => (NO_COLOR, "NO PATH TO PLAYER".to_string()),
+                cells => (TEXT_COLOR, format!("PATH {} CELLS", cells - 1)),
+            },
+        ];
+        if inspection.burning > 0.0 {
+            lines.push((TEXT_COLOR, format!("BURNING {:.1}S", inspection.burning)));
+        }
+        if inspection.stunned > 0.0 {
+            lines.push((TEXT_COLOR, format!("STUNNED {:.1}S", inspection.stunned)));
+        }
+        lines.push((DIM_COLOR, "LAST EVENTS".to_string()));
+        lines.extend(inspection.events.iter().map(|(age, event)| (TEXT_COLOR, format!("{:>5.1}S {}", age, event))));
+
+        ui.rect(x - 6.0, y - 6.0, PANEL_WIDTH, lines.len() as f32 * line + 12.0, PANEL_COLOR);
+        for (i, (color, text)) in lines.iter().enumerate() {
+            ui.text(x, y + i as f32 * line, scale, *color, text);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use glam::Vec3;
+
+use crate::collision::{self, Aabb, WallCollider};
+use crate::crosshair::AIM_RANGE;
+use crate::settings::AimAssistLevel;
+
+// 手柄的辅助瞄准：准星扫过敌人时视角转得慢一些（摩擦），准星在敌人附近的小锥形范围内时
+// 玩家在转动视角或移动的同时把准星往敌人身上带一点（吸附）。
+// 敌人的大小用碰撞盒投影到视角上的角度范围来算，被墙挡住的敌人不算
+
+// 准星在投影范围的这个倍数以内时有摩擦，比身体稍大一圈
+const FRICTION_ZONE: f32 = 1.5;
+// 吸附的锥形范围（弧度，约 6 度）
+const MAGNET_CONE: f32 = 0.1;
+
+// 每种强度的摩擦（视角速度减少的比例）和吸附速度（每秒修正角度误差的比例）
+fn strength(level: AimAssistLevel) -> (f32, f32) {
+    match level {
+        AimAssistLevel::Off => (0.0, 0.0),
+        AimAssistLevel::Low => (0.25, 1.5),
+        AimAssistLevel::Medium => (0.4, 3.0),
+        AimAssistLevel::High => (0.55, 4.5),
+    }
+}
+
+// 这一帧的辅助：摩擦和吸附的角速度（弧度每秒，只在玩家有操作时生效）
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Assist {
+    pub friction: f32,
+    pub pull_yaw: f32,
+    pub pull_pitch: f32,
+    // 辅助的是鼠标还是右摇杆
+    pub mouse: bool,
+}
+
+// 和 Camera::forward 相同的约定：yaw 为 0 时朝 -Z，pitch 向上为正
+fn angles(dir: Vec3) -> (f32, f32) {
+    ((-dir.x).atan2(-dir.z), dir.y.clamp(-1.0, 1.0).asin())
+}
+
+// 角度差限制在 -PI 到 PI
+fn wrap(angle: f32) -> f32 {
+    let tau = std::f32::consts::TAU;
+    (angle + std::f32::consts::PI).rem_euclid(tau) - std::f32::consts::PI
+}
+
+pub fn compute(
+    level: AimAssistLevel,
+    colliders: &[WallCollider],
+    targets: &[Aabb],
+    origin: Vec3,
+    yaw: f32,
+    pitch: f32,
+    mouse: bool,
+) -> Option<Assist> {
+    if level == AimAssistLevel::Off {
+        return None;
+    }
+    let (friction_strength, magnet_strength) = strength(level);
+    let mut assist = Assist { mouse, ..Assist::default() };
+    // 吸附只找最近的那个敌人（相对投影大小的角度误差最小）
+    let mut best: Option<(f32, f32, f32)> = None;
+    for aabb in targets {
+        let center = (aabb.min + aabb.max) * 0.5;
+        let to = center - origin;
+        let distance = to.length();
+        if !(0.01..=AIM_RANGE).contains(&distance) {
+            continue;
+        }
+        let dir = to / distance;
+        let blocked = collision::raycast_all(colliders, origin, dir, distance).first().is_some_and(|hit| hit.distance < distance - 0.1);
+        if blocked {
+            continue;
+        }
+        let half = (aabb.max - aabb.min) * 0.5;
+        let radius_yaw = half.x.max(half.z).atan2(distance);
+        let radius_pitch = half.y.atan2(distance);
+        let (target_yaw, target_pitch) = angles(dir);
+        let error_yaw = wrap(target_yaw - yaw);
+        let error_pitch = target_pitch - pitch;
+        // 在投影出来的椭圆里的相对位置，小于 1 表示准星在碰撞盒上
+        let inside = ((error_yaw / radius_yaw).powi(2) + (error_pitch / radius_pitch).powi(2)).sqrt();
+        if inside < FRICTION_ZONE {
+            assist.friction = friction_strength;
+        }
+        let angle = (error_yaw * error_yaw + error_pitch * error_pitch).sqrt();
+        if angle < MAGNET_CONE.max(radius_yaw) && best.is_none_or(|(best_inside, _, _)| inside < best_inside) {
+            best = Some((inside, error_yaw, error_pitch));
+        }
+    }
+    if let Some((_, error_yaw, error_pitch)) = best {
+        assist.pull_yaw = error_yaw * magnet_strength;
+        assist.pull_pitch = error_pitch * magnet_strength;
+    }
+    Some(assist)
+}
@@ -0,0 +1,236 @@
+use glam::{Mat4, Quat, Vec3};
+
+use crate::collision::{Aabb, WallCollider};
+use crate::map::Map;
+use crate::model::BoxInstance;
+
+// 警报面板：立在地上的柱子，顶上有一盏红色的信号灯。敌人在附近发现玩家、或者玩家走进面板的绊线（触发区域）时拉响，
+// 响着的时候周期性地响警笛、天花板灯闪红光，隔一段时间在面板附近的敌人出生点叫来一批增援，
+// 直到玩家走过去按使用键把面板关掉。一块面板叫来的增援活着的太多时跳过这一批，不会越叫越多。
+// 关掉的面板不会再响，换地图时重新生成
+
+// 敌人在面板这个距离以内发现玩家时拉响
+const SPOT_RADIUS: f32 = 25.0;
+// 警笛的间隔（秒），和警笛声的长度差不多
+const SIREN_INTERVAL: f32 = 1.6;
+// 拉响后多久来第一批增援，之后的间隔（秒），每批几个敌人
+const FIRST_WAVE_DELAY: f32 = 6.0;
+const REINFORCEMENT_INTERVAL: f32 = 25.0;
+pub const REINFORCEMENT_COUNT: usize = 2;
+// 一块面板叫来的增援活着这么多个时不再叫
+const MAX_ALIVE_REINFORCEMENTS: usize = 4;
+// 红光每秒闪几次
+const FLASH_RATE: f32 = 1.5;
+
+// 柱子、面板和信号灯的尺寸和高度
+const POST_HALF: f32 = 0.12;
+const POST_HEIGHT: f32 = 1.2;
+const PANEL_SIZE: Vec3 = Vec3::new(0.45, 0.55, 0.12);
+const BEACON_SIZE: f32 = 0.16;
+const POST_COLOR: [f32; 3] = [0.25, 0.25, 0.27];
+const PANEL_COLOR: [f32; 3] = [0.75, 0.72, 0.6];
+const BEACON_OFF: [f32; 3] = [0.25, 0.03, 0.03];
+const BEACON_ON: [f32; 3] = [1.0, 0.08, 0.05];
+const DISABLED_COLOR: [f32; 3] = [0.1, 0.1, 0.1];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PanelState {
+    Armed,
+    // 下一声警笛和下一批增援的剩余时间
+    Ringing { siren: f32, reinforcements: f32 },
+    Disabled,
+}
+
+struct Panel {
+    // 柱子脚下（格子中心）
+    position: Vec3,
+    tripwires: Vec<char>,
+    state: PanelState,
+}
+
+// 这一步面板要做的事
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlarmEvent {
+    Siren(Vec3),
+    // 面板编号和位置
+    Reinforce(usize, Vec3),
+}
+
+pub struct AlarmSystem {
+    panels: Vec<Panel>,
+    // 红光闪烁的相位
+    time: f32,
+}
+
+impl AlarmSystem {
+    pub fn new(map: &Map) -> Self {
+        let panels = map
+            .alarms
+            .iter()
+            .map(|alarm| Panel { position: map.cell_center(alarm.col, alarm.row), tripwires: alarm.tripwires.clone(), state: PanelState::Armed })
+            .collect();
+        Self { panels, time: 0.0 }
+    }
+
+    // 玩家走进了触发区域 id：以它为绊线的面板拉响，返回是否有面板新拉响
+    pub fn trip(&mut self, id: char) -> bool {
+        let tripped: Vec<usize> = (0..self.panels.len()).filter(|&index| self.panels[index].tripwires.contains(&id)).collect();
+        // 每块都要拉响，不能短路
+        let rang: Vec<bool> = tripped.into_iter().map(|index| self.ring(index)).collect();
+        rang.contains(&true)
+    }
+
+    // 敌人在 position 发现了玩家：离他最近的、SPOT_RADIUS 以内的面板拉响
+    pub fn spotted(&mut self, position: Vec3) -> bool {
+        let nearest = self
+            .panels
+            .iter()
+            .enumerate()
+            .filter(|(_, panel)| panel.state != PanelState::Disabled && panel.position.distance(position) <= SPOT_RADIUS)
+            .min_by(|a, b| a.1.position.distance(position).total_cmp(&b.1.position.distance(position)))
+            .map(|(index, _)| index);
+        nearest.is_some_and(|index| self.ring(index))
+    }
+
+    // 已经在响或者关掉了时返回 false
+    fn ring(&mut self, index: usize) -> bool {
+        let panel = &mut self.panels[index];
+        if panel.state != PanelState::Armed {
+            return false;
+        }
+        println!("警报拉响 ({:.1}, {:.1})", panel.position.x, panel.position.z);
+        panel.state = PanelState::Ringing { siren: 0.0, reinforcements: FIRST_WAVE_DELAY };
+        true
+    }
+
+    pub fn is_ringing(&self) -> bool {
+        self.panels.iter().any(|panel| matches!(panel.state, PanelState::Ringing { .. }))
+    }
+
+    // alive_reinforcements(编号) 是这块面板叫来的增援还活着几个
+    pub fn update(&mut self, dt: f32, alive_reinforcements: impl Fn(usize) -> usize) -> Vec<AlarmEvent> {
+        self.time += dt;
+        let mut events = Vec::new();
+        for (index, panel) in self.panels.iter_mut().enumerate() {
+            let PanelState::Ringing { siren, reinforcements } = &mut panel.state else { continue };
+            *siren -= dt;
+            if *siren <= 0.0 {
+                *siren += SIREN_INTERVAL;
+                events.push(AlarmEvent::Siren(panel.position));
+            }
+            *reinforcements -= dt;
+            if *reinforcements <= 0.0 {
+                *reinforcements += REINFORCEMENT_INTERVAL;
+                if alive_reinforcements(index) < MAX_ALIVE_REINFORCEMENTS {
+                    events.push(AlarmEvent::Reinforce(index, panel.position));
+                }
+            }
+        }
+        events
+    }
+
+    // 红光的强度（0 到 1），没有面板在响时为 0
+    pub fn flash(&self) -> f32 {
+        if !self.is_ringing() {
+            return 0.0;
+        }
+        0.5 - 0.5 * (self.time * FLASH_RATE * std::f32::consts::TAU).cos()
+    }
+
+    // 玩家关掉面板，已经关掉时返回 false
+    pub fn disable(&mut self, index: usize) -> bool {
+        let Some(panel) = self.panels.get_mut(index).filter(|panel| panel.state != PanelState::Disabled) else { return false };
+        println!("关掉了警报面板 ({:.1}, {:.1})", panel.position.x, panel.position.z);
+        panel.state = PanelState::Disabled;
+        true
+    }
+
+    pub fn is_disabled(&self, index: usize) -> bool {
+        self.panels.get(index).is_none_or(|panel| panel.state == PanelState::Disabled)
+    }
+
+    pub fn colliders(&self) -> Vec<WallCollider> {
+        self.panels.iter().map(|panel| WallCollider::square(panel.position, POST_HALF, POST_HEIGHT + PANEL_SIZE.y)).collect()
+    }
+
+    // 准星能对准的面板（包括关掉的，提示里显示已经关掉）
+    pub fn aim_boxes(&self) -> Vec<(usize, Aabb)> {
+        let half = Vec3::new(PANEL_SIZE.x * 0.5, 0.0, PANEL_SIZE.x * 0.5);
+        self.panels
+            .iter()
+            .enumerate()
+            .map(|(index, panel)| (index, Aabb { min: panel.position - half, max: panel.position + half + Vec3::Y * (POST_HEIGHT + PANEL_SIZE.y + BEACON_SIZE) }))
+            .collect()
+    }
+
+    pub fn boxes(&self, out: &mut Vec<BoxInstance>) {
+        let on = self.flash() > 0.5;
+        for index in 0..self.panels.len() {
+            self.panel_boxes(index, on, out);
+        }
+    }
+
+    // 一块面板的方块（描边也用）
+    pub fn panel_boxes(&self, index: usize, on: bool, out: &mut Vec<BoxInstance>) {
+        let Some(panel) = self.panels.get(index) else { return };
+        let mut push = |size: Vec3, height: f32, color: [f32; 3]| {
+            let center = panel.position + Vec3::Y * height;
+            out.push(BoxInstance { transform: Mat4::from_scale_rotation_translation(size, Quat::IDENTITY, center), color });
+        };
+        push(Vec3::new(POST_HALF * 2.0, POST_HEIGHT, POST_HALF * 2.0), POST_HEIGHT * 0.5, POST_COLOR);
+        push(PANEL_SIZE, POST_HEIGHT + PANEL_SIZE.y * 0.5, PANEL_COLOR);
+        let beacon = match panel.state {
+            PanelState::Armed => BEACON_OFF,
+            PanelState::Ringing { .. } if on => BEACON_ON,
+            PanelState::Ringing { .. } => BEACON_OFF,
+            PanelState::Disabled => DISABLED_COLOR,
+        };
+        push(Vec3::splat(BEACON_SIZE), POST_HEIGHT + PANEL_SIZE.y + BEACON_SIZE * 0.5, beacon);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alarms() -> AlarmSystem {
+        let panel = |x: f32, tripwires: Vec<char>| Panel { position: Vec3::new(x, 0.0, 0.0), tripwires, state: PanelState::Armed };
+        AlarmSystem { panels: vec![panel(0.0, vec!['a']), panel(40.0, Vec::new())], time: 0.0 }
+    }
+
+    #[test]
+    fn tripwires_and_sightings_ring_panels() {
+        let mut alarms = alarms();
+        assert!(!alarms.trip('b'));
+        assert!(alarms.trip('a'));
+        assert!(!alarms.trip('a'));
+        // 最近的第一块已经在响，第二块离得太远
+        assert!(!alarms.spotted(Vec3::new(10.0, 0.0, 0.0)));
+        assert!(alarms.spotted(Vec3::new(35.0, 0.0, 0.0)));
+        // 关掉的面板不会再响
+        assert!(alarms.disable(1));
+        assert!(!alarms.disable(1));
+        assert!(!alarms.spotted(Vec3::new(40.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn ringing_panels_call_reinforcements_until_disabled() {
+        let mut alarms = alarms();
+        alarms.trip('a');
+        assert_eq!(alarms.update(0.1, |_| 0), [AlarmEvent::Siren(Vec3::ZERO)]);
+        let reinforcements = |events: &[AlarmEvent]| events.iter().filter(|event| matches!(event, AlarmEvent::Reinforce(0, _))).count();
+        let mut events = Vec::new();
+        for _ in 0..(FIRST_WAVE_DELAY / 0.1) as usize {
+            events.extend(alarms.update(0.1, |_| 0));
+        }
+        assert_eq!(reinforcements(&events), 1);
+        // 叫来的增援活着的太多时跳过这一批，死掉一些后下一批照常来
+        assert_eq!(reinforcements(&alarms.update(REINFORCEMENT_INTERVAL, |_| MAX_ALIVE_REINFORCEMENTS)), 0);
+        assert!(alarms.is_ringing());
+        assert_eq!(reinforcements(&alarms.update(REINFORCEMENT_INTERVAL, |_| MAX_ALIVE_REINFORCEMENTS - 1)), 1);
+        alarms.disable(0);
+        assert!(!alarms.is_ringing());
+        assert_eq!(alarms.flash(), 0.0);
+        assert!(alarms.update(REINFORCEMENT_INTERVAL, |_| 0).is_empty());
+    }
+}
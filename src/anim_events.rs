@@ -0,0 +1,114 @@
+use glam::Vec3;
+
+// 动画事件：动画的进度（0 到 1）上打了标签的关键帧，例如门闩打开、门板升到顶。
+// 动画每次前进时把这一步越过的关键帧作为事件发出来，声音、碰撞体开关这些跟着动画走的东西在事件里处理，
+// 不用各自按时间或者进度阈值去猜动画到了哪里。正着放和倒着放分别有自己的关键帧
+
+// 关键帧在哪个方向播放时触发
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    pub at: f32,
+    pub tag: &'static str,
+    pub direction: Direction,
+}
+
+impl Keyframe {
+    pub const fn forward(at: f32, tag: &'static str) -> Self {
+        Self { at, tag, direction: Direction::Forward }
+    }
+
+    pub const fn backward(at: f32, tag: &'static str) -> Self {
+        Self { at, tag, direction: Direction::Backward }
+    }
+}
+
+// 进度从 from 变到 to 时越过的关键帧，按越过的先后顺序。正着放时包括 to 不包括 from，倒着放时也一样，
+// 停在关键帧上再反向时不会重复触发
+pub fn crossed(keys: &[Keyframe], from: f32, to: f32) -> Vec<&'static str> {
+    let mut keys: Vec<&Keyframe> = if to > from {
+        keys.iter().filter(|key| key.direction == Direction::Forward && from < key.at && key.at <= to).collect()
+    } else if to < from {
+        keys.iter().filter(|key| key.direction == Direction::Backward && to <= key.at && key.at < from).collect()
+    } else {
+        Vec::new()
+    };
+    keys.sort_by(|a, b| if to > from { a.at.total_cmp(&b.at) } else { b.at.total_cmp(&a.at) });
+    keys.into_iter().map(|key| key.tag).collect()
+}
+
+// 发出事件的动画
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    // 门实体的编号（Entity::index）
+    Door(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimEvent {
+    pub tag: &'static str,
+    pub source: Source,
+    // 声音从哪里发出
+    pub position: Vec3,
+}
+
+// 和 EventBus 一样，动画在模拟里发出，每次模拟后统一分给各个系统
+pub struct AnimEvents {
+    queue: Vec<AnimEvent>,
+}
+
+impl AnimEvents {
+    pub fn new() -> Self {
+        Self { queue: Vec::new() }
+    }
+
+    // 把 from 到 to 之间越过的关键帧都发出去
+    pub fn advance(&mut self, keys: &[Keyframe], from: f32, to: f32, source: Source, position: Vec3) {
+        for tag in crossed(keys, from, to) {
+            self.queue.push(AnimEvent { tag, source, position });
+        }
+    }
+
+    pub fn drain(&mut self) -> Vec<AnimEvent> {
+        std::mem::take(&mut self.queue)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYS: [Keyframe; 4] = [
+        Keyframe::forward(0.1, "start"),
+        Keyframe::forward(1.0, "end"),
+        Keyframe::forward(0.5, "half"),
+        Keyframe::backward(0.0, "closed"),
+    ];
+
+    #[test]
+    fn fires_in_playback_order() {
+        assert_eq!(crossed(&KEYS, 0.0, 1.0), ["start", "half", "end"]);
+        assert_eq!(crossed(&KEYS, 1.0, 0.0), ["closed"]);
+        assert!(crossed(&KEYS, 0.5, 0.5).is_empty());
+    }
+
+    #[test]
+    fn stepping_fires_each_key_once() {
+        let mut fired = Vec::new();
+        let mut progress = 0.0f32;
+        while progress < 1.0 {
+            let next = (progress + 0.1).min(1.0);
+            fired.extend(crossed(&KEYS, progress, next));
+            progress = next;
+        }
+        assert_eq!(fired, ["start", "half", "end"]);
+        // 停在 0.5 上反向再正向，half 只在越过时触发
+        assert!(crossed(&KEYS, 0.5, 0.4).is_empty());
+        assert_eq!(crossed(&KEYS, 0.4, 0.6), ["half"]);
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use glam::Vec3;
+use instant::Instant;
+
+use crate::camera;
+use crate::snapshot::PlayerState;
+
+// 主机对客户端发来的状态和射击做的基本检查：移动速度、瞬移、射速和开枪位置。
+// 不合理的输入直接丢掉；每次违规记一笔分数，分数随时间慢慢消掉，攒够了就踢出去。
+// 违规次数记在 HTTP 的 /metrics 里，日志里也会打印
+
+// 斜着冲刺时前后和左右的速度叠加，再留一些余量给网络抖动和跳跃落地
+const MAX_SPEED: f32 = camera::WALK_SPEED * camera::SPRINT_MULTIPLIER * std::f32::consts::SQRT_2 * 1.5;
+// 两次状态之间允许多出来的距离，包挤在一起到达时时间差几乎是 0
+const MOVE_SLACK: f32 = 1.0;
+// 一次状态里活着移动超过这个距离算瞬移，重生不算
+const TELEPORT_DISTANCE: f32 = 10.0;
+// 开枪位置和主机记录的位置最多差这么多（米），客户端的位置最多晚一个状态包
+const ORIGIN_TOLERANCE: f32 = 2.0;
+// 一秒内允许的射击次数是武器射速的这么多倍，再多加两发（网络抖动时包会挤在一起）
+const FIRE_RATE_TOLERANCE: f32 = 1.25;
+const FIRE_WINDOW: Duration = Duration::from_secs(1);
+
+// 每种违规的分数，攒到 KICK_SCORE 就踢出去；每秒消掉 SCORE_DECAY 分
+const KICK_SCORE: f32 = 10.0;
+const SCORE_DECAY: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    Speed,
+    Teleport,
+    FireRate,
+    ShotOrigin,
+    DeadShot,
+}
+
+const VIOLATIONS: [Violation; 5] = [Violation::Speed, Violation::Teleport, Violation::FireRate, Violation::ShotOrigin, Violation::DeadShot];
+
+impl Violation {
+    pub fn name(self) -> &'static str {
+        match self {
+            Violation::Speed => "speed",
+            Violation::Teleport => "teleport",
+            Violation::FireRate => "fire_rate",
+            Violation::ShotOrigin => "shot_origin",
+            Violation::DeadShot => "dead_shot",
+        }
+    }
+
+    fn score(self) -> f32 {
+        match self {
+            Violation::Speed => 1.0,
+            Violation::Teleport => 4.0,
+            Violation::FireRate => 1.0,
+            Violation::ShotOrigin => 2.0,
+            Violation::DeadShot => 2.0,
+        }
+    }
+
+    fn index(self) -> usize {
+        VIOLATIONS.iter().position(|violation| *violation == self).unwrap_or(0)
+    }
+}
+
+static VIOLATION_COUNTS: [AtomicU64; 5] = [const { AtomicU64::new(0) }; 5];
+static KICKS: AtomicU64 = AtomicU64::new(0);
+
+// 检查的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    // 丢掉这个输入
+    Reject(Violation),
+    // 丢掉这个输入并踢出玩家
+    Kick(Violation),
+}
+
+// 一个客户端的检查状态
+pub struct Guard {
+    // 上一次接受的状态和收到的时间
+    last_state: Option<(PlayerState, Instant)>,
+    fires: VecDeque<Instant>,
+    score: f32,
+    score_time: Instant,
+}
+
+impl Guard {
+    pub fn new(now: Instant) -> Self {
+        Self { last_state: None, fires: VecDeque::new(), score: 0.0, score_time: now }
+    }
+
+    // 主机记录的这个玩家的状态（最后一次通过检查的）
+    pub fn state(&self) -> Option<PlayerState> {
+        self.last_state.map(|(state, _)| state)
+    }
+
+    pub fn check_move(&mut self, player: &str, state: PlayerState, now: Instant) -> Verdict {
+        let Some((last, time)) = self.last_state else {
+            self.last_state = Some((state, now));
+            return Verdict::Accept;
+        };
+        // 死了以后重生在出生点，位置可以任意跳
+        if last.alive && state.alive {
+            let moved = (state.position() - last.position()) * Vec3::new(1.0, 0.0, 1.0);
+            let distance = moved.length();
+            let elapsed = now.duration_since(time).as_secs_f32();
+            if distance > TELEPORT_DISTANCE {
+                return self.flag(player, Violation::Teleport, now);
+            }
+            if distance > MAX_SPEED * elapsed + MOVE_SLACK {
+                return self.flag(player, Violation::Speed, now);
+            }
+        }
+        self.last_state = Some((state, now));
+        Verdict::Accept
+    }
+
+    // fire_interval 是客户端报告的武器的射击间隔
+    pub fn check_fire(&mut self, player: &str, origin: Vec3, fire_interval: f32, now: Instant) -> Verdict {
+        let Some((state, _)) = self.last_state else { return Verdict::Reject(Violation::ShotOrigin) };
+        if !state.alive {
+            return self.flag(player, Violation::DeadShot, now);
+        }
+        if origin.distance(state.position()) > ORIGIN_TOLERANCE {
+            return self.flag(player, Violation::ShotOrigin, now);
+        }
+        while self.fires.front().is_some_and(|fired| now.duration_since(*fired) > FIRE_WINDOW) {
+            self.fires.pop_front();
+        }
+        let allowed = FIRE_WINDOW.as_secs_f32() / fire_interval.max(0.01) * FIRE_RATE_TOLERANCE + 2.0;
+        if self.fires.len() as f32 >= allowed {
+            return self.flag(player, Violation::FireRate, now);
+        }
+        self.fires.push_back(now);
+        Verdict::Accept
+    }
+
+    fn flag(&mut self, player: &str, violation: Violation, now: Instant) -> Verdict {
+        let elapsed = now.duration_since(self.score_time).as_secs_f32();
+        self.score = (self.score - elapsed * SCORE_DECAY).max(0.0) + violation.score();
+        self.score_time = now;
+        VIOLATION_COUNTS[violation.index()].fetch_add(1, Ordering::Relaxed);
+        eprintln!("反作弊: 玩家 {} 违规 {}，累计 {:.1} 分", player, violation.name(), self.score);
+        if self.score >= KICK_SCORE {
+            KICKS.fetch_add(1, Ordering::Relaxed);
+            eprintln!("反作弊: 踢出玩家 {}", player);
+            return Verdict::Kick(violation);
+        }
+        Verdict::Reject(violation)
+    }
+}
+
+// /metrics 里反作弊的部分（Prometheus 文本格式）
+#[cfg(feature = "http-server")]
+pub fn metrics_text() -> String {
+    let mut text = String::from("# HELP anti_cheat_violations_total Rejected client inputs on the host, by check\n# TYPE anti_cheat_violations_total counter\n");
+    for violation in VIOLATIONS {
+        let count = VIOLATION_COUNTS[violation.index()].load(Ordering::Relaxed);
+        text.push_str(&format!("anti_cheat_violations_total{{kind=\"{}\"}} {}\n", violation.name(), count));
+    }
+    text.push_str("# HELP anti_cheat_kicks_total Players kicked by the anti-cheat checks\n# TYPE anti_cheat_kicks_total counter\n");
+    text.push_str(&format!("anti_cheat_kicks_total {}\n", KICKS.load(Ordering::Relaxed)));
+    text
+}
@@ -0,0 +1,100 @@
+use std::borrow::Cow;
+use std::io;
+use std::path::Path;
+
+// 游戏资源文件（maps/ 下的地图、预制件和光照贴图，luts/ 下的调色表，data/ 下的配置和纹理）的读取。
+// 桌面版先读启用的 mod 里的（见 mods.rs），再读散装的文件，都没有再从资源包里读（见 pak.rs）；
+// 浏览器里没有文件系统，启动时先用 fetch 把这些文件下载到内存（见 web.rs），之后按同样的路径读取。
+// 设置、按键和玩家档案这些用户文件不经过这里，浏览器里读写失败时使用默认值
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    if let Some(modded) = crate::mods::find(path) {
+        return std::fs::read(modded);
+    }
+    match std::fs::read(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => crate::pak::installed().and_then(|pak| pak.read(path)).unwrap_or(Err(e)),
+        result => result,
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    fetched::get(path).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} 没有下载", path.display())))
+}
+
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    String::from_utf8(read(path)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+// 编译进程序的文件，散装的和包里的都没有时使用
+pub fn read_or_builtin(path: &Path, builtin: &'static [u8]) -> Cow<'static, [u8]> {
+    match read(path) {
+        Ok(bytes) => Cow::Owned(bytes),
+        Err(e) => {
+            if e.kind() != io::ErrorKind::NotFound {
+                eprintln!("无法读取 {}，使用内置的版本: {}", path.display(), e);
+            }
+            Cow::Borrowed(builtin)
+        }
+    }
+}
+
+pub fn read_to_string_or_builtin(path: &Path, builtin: &'static str) -> Cow<'static, str> {
+    match read_or_builtin(path, builtin.as_bytes()) {
+        Cow::Borrowed(_) => Cow::Borrowed(builtin),
+        Cow::Owned(bytes) => match String::from_utf8(bytes) {
+            Ok(text) => Cow::Owned(text),
+            Err(e) => {
+                eprintln!("{} 不是 UTF-8，使用内置的版本: {}", path.display(), e);
+                Cow::Borrowed(builtin)
+            }
+        },
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub use fetched::preload;
+
+#[cfg(target_arch = "wasm32")]
+mod fetched {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+
+    // 路径 -> 文件内容
+    static FILES: Mutex<Option<HashMap<PathBuf, Vec<u8>>>> = Mutex::new(None);
+
+    pub fn get(path: &Path) -> Option<Vec<u8>> {
+        FILES.lock().unwrap().as_ref()?.get(path).cloned()
+    }
+
+    // 从页面所在的目录下载这些文件。不存在的（404）跳过，读取时回退到内置的版本
+    pub async fn preload(paths: Vec<PathBuf>) {
+        let window = web_sys::window().expect("没有 window 对象");
+        let mut files = HashMap::new();
+        for path in paths {
+            let url = path.to_string_lossy().replace('\\', "/");
+            match fetch(&window, &url).await {
+                Ok(bytes) => {
+                    files.insert(path, bytes);
+                }
+                Err(e) => log::info!("没有下载 {}: {:?}", url, e),
+            }
+        }
+        log::info!("下载了 {} 个资源文件", files.len());
+        *FILES.lock().unwrap() = Some(files);
+    }
+
+    async fn fetch(window: &web_sys::Window, url: &str) -> Result<Vec<u8>, JsValue> {
+        let response: web_sys::Response = JsFuture::from(window.fetch_with_str(url)).await?.dyn_into()?;
+        if !response.ok() {
+            return Err(JsValue::from_str(&format!("HTTP {}", response.status())));
+        }
+        let buffer = JsFuture::from(response.array_buffer()?).await?;
+        Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+    }
+}
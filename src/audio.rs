@@ -0,0 +1,350 @@
+use glam::Vec3;
+use rodio::{OutputStream, OutputStreamHandle, Source};
+use rodio::buffer::SamplesBuffer;
+use rodio::source::ChannelVolume;
+
+use crate::collision::{self, WallCollider};
+
+// 声音系统：程序生成的音效 + 基于墙体遮挡的衰减和低通滤波
+
+const SAMPLE_RATE: u32 = 44100;
+
+// 距离衰减的参考距离，以及超出后不再播放的距离
+const REFERENCE_DISTANCE: f32 = 3.0;
+const MAX_AUDIBLE_DISTANCE: f32 = 45.0;
+
+// 每隔一面墙的音量系数，以及被遮挡时的低通截止频率
+const OCCLUSION_GAIN_PER_WALL: f32 = 0.4;
+const OCCLUDED_CUTOFF_HZ: u32 = 1200;
+const MIN_CUTOFF_HZ: u32 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SoundKind {
+    Footstep,
+    Drip,
+    // 受到爆炸、火焰、电击伤害，也用于场景危害的环境声
+    Blast,
+    Burn,
+    Zap,
+    // 夜视仪的电流声，一段一秒，首尾相接
+    Hum,
+    // 门闩的咔哒声，门板升到顶时的撞击声
+    Latch,
+    Clunk,
+    // 警报面板的警笛，一段一声
+    Siren,
+}
+
+// 动画事件对应的声音，没有声音的事件（例如只开关碰撞体的）为 None
+pub fn event_sound(tag: &str) -> Option<SoundKind> {
+    match tag {
+        "door_unlatch" | "door_latch" => Some(SoundKind::Latch),
+        "door_stop" => Some(SoundKind::Clunk),
+        _ => None,
+    }
+}
+
+// 听者（即相机）的位置和朝向
+pub struct Listener {
+    pub position: Vec3,
+    pub right: Vec3,
+}
+
+impl Listener {
+    pub fn from_camera(camera: &crate::camera::Camera) -> Self {
+        Self {
+            position: camera.position,
+            right: Vec3::new(camera.yaw.cos(), 0.0, -camera.yaw.sin()),
+        }
+    }
+}
+
+// 遮挡计算结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Occlusion {
+    pub gain: f32,
+    pub cutoff_hz: Option<u32>,
+}
+
+// 根据听者和声源之间隔了几面墙计算遮挡
+pub fn occlusion(colliders: &[WallCollider], listener: Vec3, emitter: Vec3) -> Occlusion {
+    let walls = collision::count_occluders(colliders, listener, emitter);
+    if walls == 0 {
+        return Occlusion { gain: 1.0, cutoff_hz: None };
+    }
+    Occlusion {
+        gain: OCCLUSION_GAIN_PER_WALL.powi(walls as i32),
+        cutoff_hz: Some((OCCLUDED_CUTOFF_HZ / walls as u32).max(MIN_CUTOFF_HZ)),
+    }
+}
+
+pub struct AudioSystem {
+    // OutputStream 必须一直持有，否则声音会立刻停止
+    output: Option<(OutputStream, OutputStreamHandle)>,
+    footstep: Vec<f32>,
+    drip: Vec<f32>,
+    blast: Vec<f32>,
+    burn: Vec<f32>,
+    zap: Vec<f32>,
+    hum: Vec<f32>,
+    latch: Vec<f32>,
+    clunk: Vec<f32>,
+    siren: Vec<f32>,
+    rng: u32,
+}
+
+impl AudioSystem {
+    pub fn new() -> Self {
+        let output = match OutputStream::try_default() {
+            Ok(output) => Some(output),
+            Err(e) => {
+                eprintln!("无法打开音频设备，声音已禁用: {}", e);
+                None
+            }
+        };
+        let mut rng = 0x1234_5678;
+        Self {
+            output,
+            footstep: synth_footstep(&mut rng),
+            drip: synth_drip(),
+            blast: synth_blast(&mut rng),
+            burn: synth_burn(&mut rng),
+            zap: synth_zap(&mut rng),
+            hum: synth_hum(&mut rng),
+            latch: synth_latch(&mut rng),
+            clunk: synth_clunk(&mut rng),
+            siren: synth_siren(),
+            rng,
+        }
+    }
+
+    fn samples(&self, kind: SoundKind) -> &[f32] {
+        match kind {
+            SoundKind::Footstep => &self.footstep,
+            SoundKind::Drip => &self.drip,
+            SoundKind::Blast => &self.blast,
+            SoundKind::Burn => &self.burn,
+            SoundKind::Zap => &self.zap,
+            SoundKind::Hum => &self.hum,
+            SoundKind::Latch => &self.latch,
+            SoundKind::Clunk => &self.clunk,
+            SoundKind::Siren => &self.siren,
+        }
+    }
+
+    // 在世界中的某个位置播放声音
+    pub fn play_at(&mut self, kind: SoundKind, position: Vec3, listener: &Listener, colliders: &[WallCollider]) {
+        let Some((_, handle)) = &self.output else { return };
+
+        let offset = position - listener.position;
+        let distance = offset.length();
+        if distance > MAX_AUDIBLE_DISTANCE {
+            return;
+        }
+
+        let occlusion = occlusion(colliders, listener.position, position);
+        let attenuation = 1.0 / (1.0 + distance / REFERENCE_DISTANCE);
+
+        // 简单的等功率声像
+        let pan = if distance > 0.01 { (offset / distance).dot(listener.right).clamp(-1.0, 1.0) } else { 0.0 };
+        let angle = (pan + 1.0) * std::f32::consts::FRAC_PI_4;
+        let left = angle.cos();
+        let right = angle.sin();
+
+        // 每次播放时稍微改变音调，避免重复感
+        let pitch = 0.9 + 0.2 * next_random(&mut self.rng);
+
+        let buffer = SamplesBuffer::new(1, SAMPLE_RATE, self.samples(kind).to_vec()).speed(pitch);
+        let source: Box<dyn Source<Item = f32> + Send> = match occlusion.cutoff_hz {
+            Some(cutoff) => Box::new(buffer.low_pass(cutoff)),
+            None => Box::new(buffer),
+        };
+        let gain = attenuation * occlusion.gain;
+        let source = ChannelVolume::new(source, vec![left * gain, right * gain]);
+
+        if let Err(e) = handle.play_raw(source.convert_samples()) {
+            eprintln!("播放声音失败: {}", e);
+        }
+    }
+
+    // 玩家自己身上的声音（夜视仪等）：不衰减、不遮挡，也不变调
+    pub fn play_local(&self, kind: SoundKind, gain: f32) {
+        let Some((_, handle)) = &self.output else { return };
+        let buffer = SamplesBuffer::new(1, SAMPLE_RATE, self.samples(kind).to_vec()).amplify(gain);
+        if let Err(e) = handle.play_raw(buffer.convert_samples()) {
+            eprintln!("播放声音失败: {}", e);
+        }
+    }
+}
+
+// xorshift 随机数，返回 0..1
+fn next_random(state: &mut u32) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    (*state >> 8) as f32 / (1u32 << 24) as f32
+}
+
+// 脚步声：快速衰减的低频噪声
+fn synth_footstep(rng: &mut u32) -> Vec<f32> {
+    let len = (SAMPLE_RATE as f32 * 0.09) as usize;
+    let mut samples = Vec::with_capacity(len);
+    let mut lowpassed = 0.0;
+    for i in 0..len {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let noise = next_random(rng) * 2.0 - 1.0;
+        lowpassed += (noise - lowpassed) * 0.15;
+        let thump = (t * 90.0 * std::f32::consts::TAU).sin() * 0.5;
+        samples.push((lowpassed * 1.5 + thump) * (-t * 45.0).exp() * 0.6);
+    }
+    samples
+}
+
+// 水滴声：快速下滑的正弦
+fn synth_drip() -> Vec<f32> {
+    let len = (SAMPLE_RATE as f32 * 0.12) as usize;
+    let mut samples = Vec::with_capacity(len);
+    let mut phase = 0.0f32;
+    for i in 0..len {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let freq = 1400.0 - 700.0 * (t / 0.12);
+        phase += freq / SAMPLE_RATE as f32 * std::f32::consts::TAU;
+        samples.push(phase.sin() * (-t * 35.0).exp() * 0.5);
+    }
+    samples
+}
+
+// 爆炸：低沉的噪声加上下滑的低频，衰减较慢
+fn synth_blast(rng: &mut u32) -> Vec<f32> {
+    let len = (SAMPLE_RATE as f32 * 0.6) as usize;
+    let mut samples = Vec::with_capacity(len);
+    let mut lowpassed = 0.0;
+    for i in 0..len {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let noise = next_random(rng) * 2.0 - 1.0;
+        lowpassed += (noise - lowpassed) * 0.05;
+        let rumble = (t * (60.0 - 30.0 * t) * std::f32::consts::TAU).sin() * 0.6;
+        samples.push((lowpassed * 3.0 + rumble) * (-t * 6.0).exp() * 0.7);
+    }
+    samples
+}
+
+// 火焰：稀疏的噼啪声叠在轻微的嘶嘶声上
+fn synth_burn(rng: &mut u32) -> Vec<f32> {
+    let len = (SAMPLE_RATE as f32 * 0.35) as usize;
+    let mut samples = Vec::with_capacity(len);
+    let mut crackle = 0.0f32;
+    for i in 0..len {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        if next_random(rng) < 0.002 {
+            crackle = 1.0;
+        }
+        crackle *= 0.97;
+        let hiss = (next_random(rng) * 2.0 - 1.0) * 0.08;
+        let envelope = (t / 0.03).min(1.0) * (1.0 - t / 0.35);
+        samples.push((hiss + crackle * (next_random(rng) * 2.0 - 1.0)) * envelope * 0.5);
+    }
+    samples
+}
+
+// 电击：带噪声的方波嗡嗡声
+fn synth_zap(rng: &mut u32) -> Vec<f32> {
+    let len = (SAMPLE_RATE as f32 * 0.18) as usize;
+    let mut samples = Vec::with_capacity(len);
+    for i in 0..len {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let buzz = if (t * 120.0).fract() < 0.5 { 0.3 } else { -0.3 };
+        let noise = (next_random(rng) * 2.0 - 1.0) * 0.25;
+        samples.push((buzz + noise) * (-t * 18.0).exp() * 0.5);
+    }
+    samples
+}
+
+// 夜视仪：低频电流声加上很轻的高频啸叫，频率都是整数赫兹，一秒的片段首尾相接没有断点
+fn synth_hum(rng: &mut u32) -> Vec<f32> {
+    let len = SAMPLE_RATE as usize;
+    let mut samples = Vec::with_capacity(len);
+    let mut lowpassed = 0.0;
+    for i in 0..len {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let noise = next_random(rng) * 2.0 - 1.0;
+        lowpassed += (noise - lowpassed) * 0.1;
+        let buzz = (t * 120.0 * std::f32::consts::TAU).sin() * 0.25 + (t * 240.0 * std::f32::consts::TAU).sin() * 0.1;
+        let whine = (t * 7800.0 * std::f32::consts::TAU).sin() * 0.03;
+        samples.push((buzz + whine + lowpassed * 0.1) * 0.4);
+    }
+    samples
+}
+
+// 门闩：两下很短的高频金属咔哒声
+fn synth_latch(rng: &mut u32) -> Vec<f32> {
+    let len = (SAMPLE_RATE as f32 * 0.08) as usize;
+    let mut samples = Vec::with_capacity(len);
+    for i in 0..len {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let click = |start: f32| if t >= start { (-(t - start) * 250.0).exp() } else { 0.0 };
+        let ring = (t * 2300.0 * std::f32::consts::TAU).sin() * 0.4;
+        let noise = (next_random(rng) * 2.0 - 1.0) * 0.6;
+        samples.push((ring + noise) * (click(0.0) + click(0.035) * 0.7) * 0.5);
+    }
+    samples
+}
+
+// 门板撞到顶：低沉的闷响加上一点金属的余音
+fn synth_clunk(rng: &mut u32) -> Vec<f32> {
+    let len = (SAMPLE_RATE as f32 * 0.3) as usize;
+    let mut samples = Vec::with_capacity(len);
+    let mut lowpassed = 0.0;
+    for i in 0..len {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let noise = next_random(rng) * 2.0 - 1.0;
+        lowpassed += (noise - lowpassed) * 0.08;
+        let thud = (t * 70.0 * std::f32::consts::TAU).sin() * (-t * 20.0).exp();
+        let ring = (t * 520.0 * std::f32::consts::TAU).sin() * (-t * 9.0).exp() * 0.15;
+        samples.push((thud + ring + lowpassed * (-t * 30.0).exp()) * 0.6);
+    }
+    samples
+}
+
+// 警笛：音调先升后降的方波，两头淡入淡出
+fn synth_siren() -> Vec<f32> {
+    let duration = 1.5;
+    let len = (SAMPLE_RATE as f32 * duration) as usize;
+    let mut samples = Vec::with_capacity(len);
+    let mut phase = 0.0f32;
+    for i in 0..len {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let sweep = (t / duration * std::f32::consts::PI).sin();
+        let freq = 600.0 + 500.0 * sweep;
+        phase += freq / SAMPLE_RATE as f32 * std::f32::consts::TAU;
+        let square = if phase.sin() >= 0.0 { 1.0 } else { -1.0 };
+        let envelope = (t / 0.05).min(1.0) * ((duration - t) / 0.1).min(1.0);
+        samples.push((square * 0.6 + phase.sin() * 0.4) * envelope * 0.35);
+    }
+    samples
+}
+
+// 场景中周期性发声的环境声源（例如漏水的管道）
+pub struct AmbientEmitter {
+    pub position: Vec3,
+    pub kind: SoundKind,
+    interval: f32,
+    timer: f32,
+}
+
+impl AmbientEmitter {
+    pub fn new(position: Vec3, kind: SoundKind, interval: f32) -> Self {
+        Self { position, kind, interval, timer: interval }
+    }
+
+    // 返回 true 表示这一帧应该发声
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.timer -= dt;
+        if self.timer <= 0.0 {
+            self.timer += self.interval;
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+// 手柄的摇杆和扳机：哪个物理轴控制移动和视角，以及每个轴的死区、饱和点和响应曲线。
+// 按手柄的 GUID 分别保存在 input.json 里，换手柄时各用各的设置；读取手柄事件在 gamepad.rs
+
+// 摇杆轴控制的东西，x 向右、y 向前/向上
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AxisTarget {
+    MoveX,
+    MoveY,
+    LookX,
+    LookY,
+}
+
+impl AxisTarget {
+    pub const ALL: [AxisTarget; 4] = [AxisTarget::MoveX, AxisTarget::MoveY, AxisTarget::LookX, AxisTarget::LookY];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AxisTarget::MoveX => "MOVE X",
+            AxisTarget::MoveY => "MOVE Y",
+            AxisTarget::LookX => "LOOK X",
+            AxisTarget::LookY => "LOOK Y",
+        }
+    }
+}
+
+// 手柄上的物理轴。扳机的范围是 0 到 1，当作轴用时不再触发它原来的按键操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AxisSource {
+    LeftStickX,
+    LeftStickY,
+    RightStickX,
+    RightStickY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl AxisSource {
+    const ALL: [AxisSource; 6] = [
+        AxisSource::LeftStickX,
+        AxisSource::LeftStickY,
+        AxisSource::RightStickX,
+        AxisSource::RightStickY,
+        AxisSource::LeftTrigger,
+        AxisSource::RightTrigger,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AxisSource::LeftStickX => "LEFT X",
+            AxisSource::LeftStickY => "LEFT Y",
+            AxisSource::RightStickX => "RIGHT X",
+            AxisSource::RightStickY => "RIGHT Y",
+            AxisSource::LeftTrigger => "L TRIGGER",
+            AxisSource::RightTrigger => "R TRIGGER",
+        }
+    }
+
+    fn next(self) -> Self {
+        let index = AxisSource::ALL.iter().position(|source| *source == self).unwrap_or(0);
+        AxisSource::ALL[(index + 1) % AxisSource::ALL.len()]
+    }
+}
+
+// 设置界面里一个轴可以调的各项，按显示顺序
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AxisField {
+    Source,
+    Invert,
+    Deadzone,
+    Saturation,
+    Exponent,
+}
+
+impl AxisField {
+    pub const ALL: [AxisField; 5] = [AxisField::Source, AxisField::Invert, AxisField::Deadzone, AxisField::Saturation, AxisField::Exponent];
+}
+
+// 设置界面里依次切换的值
+const DEADZONES: [f32; 7] = [0.0, 0.05, 0.1, 0.15, 0.2, 0.25, 0.3];
+const SATURATIONS: [f32; 4] = [0.7, 0.8, 0.9, 1.0];
+const EXPONENTS: [f32; 4] = [1.0, 1.5, 2.0, 3.0];
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct AxisBinding {
+    pub source: AxisSource,
+    pub invert: bool,
+    // 推得比死区小时当作没推
+    pub deadzone: f32,
+    // 推到这里就算推到底
+    pub saturation: f32,
+    // 大于 1 时小幅度推动更精细
+    pub exponent: f32,
+}
+
+impl AxisBinding {
+    fn new(source: AxisSource) -> Self {
+        Self { source, invert: false, deadzone: 0.1, saturation: 1.0, exponent: 1.0 }
+    }
+
+    // 物理轴的读数 -> 游戏用的值，范围 -1 到 1
+    #[cfg(feature = "gamepad")]
+    pub fn apply(&self, raw: f32) -> f32 {
+        let range = (self.saturation - self.deadzone).max(0.01);
+        let amount = ((raw.abs() - self.deadzone) / range).clamp(0.0, 1.0).powf(self.exponent);
+        let value = amount.copysign(raw);
+        if self.invert { -value } else { value }
+    }
+
+    fn cycle(&mut self, field: AxisField) {
+        match field {
+            AxisField::Source => self.source = self.source.next(),
+            AxisField::Invert => self.invert = !self.invert,
+            AxisField::Deadzone => self.deadzone = next_value(&DEADZONES, self.deadzone),
+            AxisField::Saturation => self.saturation = next_value(&SATURATIONS, self.saturation),
+            AxisField::Exponent => self.exponent = next_value(&EXPONENTS, self.exponent),
+        }
+    }
+
+    // 设置界面里显示的值
+    pub fn describe(&self, field: AxisField) -> String {
+        match field {
+            AxisField::Source => self.source.name().to_string(),
+            AxisField::Invert => if self.invert { "YES" } else { "NO" }.to_string(),
+            AxisField::Deadzone => format!("{:.2}", self.deadzone),
+            AxisField::Saturation => format!("{:.2}", self.saturation),
+            AxisField::Exponent => format!("{:.1}", self.exponent),
+        }
+    }
+}
+
+// 比当前值大的下一档，到头后回到第一档。配置文件里手写的值也能接上
+fn next_value(values: &[f32], current: f32) -> f32 {
+    values.iter().copied().find(|value| *value > current + 0.001).unwrap_or(values[0])
+}
+
+// 一个手柄的全部轴设置
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct GamepadAxes {
+    pub axes: BTreeMap<AxisTarget, AxisBinding>,
+}
+
+impl Default for GamepadAxes {
+    fn default() -> Self {
+        let axes = [
+            (AxisTarget::MoveX, AxisSource::LeftStickX),
+            (AxisTarget::MoveY, AxisSource::LeftStickY),
+            (AxisTarget::LookX, AxisSource::RightStickX),
+            (AxisTarget::LookY, AxisSource::RightStickY),
+        ];
+        Self { axes: axes.into_iter().map(|(target, source)| (target, AxisBinding::new(source))).collect() }
+    }
+}
+
+impl GamepadAxes {
+    // 配置文件里缺少的轴用默认值
+    pub fn get(&self, target: AxisTarget) -> AxisBinding {
+        self.axes.get(&target).copied().unwrap_or_else(|| GamepadAxes::default().axes[&target])
+    }
+
+    pub fn cycle(&mut self, target: AxisTarget, field: AxisField) {
+        let mut binding = self.get(target);
+        binding.cycle(field);
+        self.axes.insert(target, binding);
+    }
+
+    // 这个物理轴控制的东西
+    #[cfg(feature = "gamepad")]
+    pub fn targets(&self, source: AxisSource) -> Vec<(AxisTarget, AxisBinding)> {
+        AxisTarget::ALL.iter().map(|target| (*target, self.get(*target))).filter(|(_, binding)| binding.source == source).collect()
+    }
+}
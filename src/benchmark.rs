@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+
+use glam::Vec3;
+use instant::SystemTime;
+
+use crate::camera::Camera;
+use crate::map::Map;
+use crate::paths;
+
+// --benchmark 模式：相机沿地图里的 camera_path 路线（Catmull-Rom 样条连成一圈）飞行 DURATION 秒，
+// 记下每一帧的时间，结束后在存档目录的 benchmarks/ 下写出 JSON 汇总和 CSV 逐帧数据，用来比较改动前后的性能
+
+// 飞完一圈的时间（秒）
+pub const DURATION: f32 = 60.0;
+const EYE_HEIGHT: f32 = 1.8;
+
+pub struct Benchmark {
+    map: String,
+    gpu: String,
+    path: Vec<Vec3>,
+    // 按模拟的时间推进，飞行路线和帧率无关
+    elapsed: f32,
+    // 每帧的时间（毫秒）
+    frame_times: Vec<f32>,
+}
+
+#[derive(serde::Serialize)]
+struct Report<'a> {
+    map: &'a str,
+    gpu: &'a str,
+    // 开始的时间（Unix 秒）
+    timestamp: u64,
+    frames: usize,
+    seconds: f32,
+    average_fps: f32,
+    // 最慢的 1% 帧的平均帧率
+    low_1_percent_fps: f32,
+    frame_ms_min: f32,
+    frame_ms_median: f32,
+    frame_ms_p95: f32,
+    frame_ms_p99: f32,
+    frame_ms_max: f32,
+}
+
+impl Benchmark {
+    pub fn new(map: &Map, gpu: &str) -> Self {
+        let mut cells = map.camera_path.clone();
+        if cells.len() < 2 {
+            // 没有写路线的地图在出生点和重生点之间飞，路线可能穿墙
+            eprintln!("地图 {} 没有 camera_path，改为经过出生点和重生点", map.name);
+            cells = map.respawn_cells();
+        }
+        let mut path: Vec<Vec3> = cells.iter().map(|(col, row)| map.cell_center(*col, *row) + Vec3::Y * EYE_HEIGHT).collect();
+        if path.len() < 2 {
+            let spawn = map.spawn_point() + Vec3::Y * EYE_HEIGHT;
+            path = vec![spawn + Vec3::new(-2.0, 0.0, 0.0), spawn + Vec3::new(2.0, 0.0, 0.0)];
+        }
+        println!("开始性能测试: {}，{} 个路径点，{} 秒", map.title, path.len(), DURATION);
+        Self { map: map.name.clone(), gpu: gpu.to_string(), path, elapsed: 0.0, frame_times: Vec::new() }
+    }
+
+    // 模拟一次，把相机放到路线上的位置，看向前进的方向
+    pub fn update(&mut self, camera: &mut Camera, dt: f32) {
+        self.elapsed += dt;
+        let t = (self.elapsed / DURATION).min(1.0) * self.path.len() as f32;
+        let position = self.sample(t);
+        let ahead = self.sample(t + 0.05) - position;
+        camera.position = position;
+        if ahead.length_squared() > 1e-8 {
+            let ahead = ahead.normalize();
+            // forward() 是 yaw 转过的 -z
+            camera.yaw = (-ahead.x).atan2(-ahead.z);
+            camera.pitch = 0.0;
+        }
+    }
+
+    // 记下一帧的时间（秒），飞完一圈后返回 true
+    pub fn record(&mut self, frame_time: f32) -> bool {
+        // 刚开始的几帧包含加载和编译管线，不计入
+        if self.elapsed > 0.5 {
+            self.frame_times.push(frame_time * 1000.0);
+        }
+        self.finished()
+    }
+
+    pub fn finished(&self) -> bool {
+        self.elapsed >= DURATION
+    }
+
+    pub fn progress_text(&self) -> String {
+        format!("BENCHMARK {:.0}/{:.0}s", self.elapsed.min(DURATION), DURATION)
+    }
+
+    // 闭合的 Catmull-Rom 样条，t 的整数部分是路径点的序号
+    fn sample(&self, t: f32) -> Vec3 {
+        let n = self.path.len();
+        let segment = t.floor() as usize;
+        let f = t - t.floor();
+        let point = |offset: usize| self.path[(segment + offset + n - 1) % n];
+        let (p0, p1, p2, p3) = (point(0), point(1), point(2), point(3));
+        0.5 * (2.0 * p1 + (p2 - p0) * f + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * f * f + (3.0 * p1 - p0 - 3.0 * p2 + p3) * f * f * f)
+    }
+
+    fn report(&self, timestamp: u64) -> Report<'_> {
+        let mut sorted = self.frame_times.clone();
+        sorted.sort_by(f32::total_cmp);
+        let percentile = |p: f32| sorted.get(((sorted.len() as f32 * p) as usize).min(sorted.len().saturating_sub(1))).copied().unwrap_or(0.0);
+        let total: f32 = sorted.iter().sum();
+        let slowest = &sorted[sorted.len() - sorted.len().div_ceil(100)..];
+        let fps = |ms: f32| if ms > 0.0 { 1000.0 / ms } else { 0.0 };
+        Report {
+            map: &self.map,
+            gpu: &self.gpu,
+            timestamp,
+            frames: sorted.len(),
+            seconds: total / 1000.0,
+            average_fps: fps(total / sorted.len().max(1) as f32),
+            low_1_percent_fps: fps(slowest.iter().sum::<f32>() / slowest.len().max(1) as f32),
+            frame_ms_min: sorted.first().copied().unwrap_or(0.0),
+            frame_ms_median: percentile(0.5),
+            frame_ms_p95: percentile(0.95),
+            frame_ms_p99: percentile(0.99),
+            frame_ms_max: sorted.last().copied().unwrap_or(0.0),
+        }
+    }
+
+    // 在存档目录下写出 benchmarks/<地图>-<时间>.json 和 .csv，返回 JSON 的路径
+    pub fn write_report(&self) -> Result<PathBuf, String> {
+        let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let report = self.report(timestamp);
+        println!(
+            "性能测试结束: {} 帧，平均 {:.1} FPS，1% low {:.1} FPS，帧时间中位数 {:.2} ms，p99 {:.2} ms",
+            report.frames, report.average_fps, report.low_1_percent_fps, report.frame_ms_median, report.frame_ms_p99
+        );
+        let dir = paths::benchmarks();
+        std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        let stem = dir.join(format!("{}-{}", self.map, timestamp));
+        let json = stem.with_extension("json");
+        let text = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+        std::fs::write(&json, text).map_err(|e| format!("{}: {}", json.display(), e))?;
+        let mut csv = String::from("frame,ms\n");
+        for (i, ms) in self.frame_times.iter().enumerate() {
+            csv.push_str(&format!("{},{:.3}\n", i, ms));
+        }
+        let csv_path = stem.with_extension("csv");
+        std::fs::write(&csv_path, csv).map_err(|e| format!("{}: {}", csv_path.display(), e))?;
+        Ok(json)
+    }
+}
@@ -0,0 +1,237 @@
+use glam::Mat4;
+use wgpu::util::DeviceExt;
+
+use crate::camera::CameraUniform;
+use crate::gpu_memory;
+use crate::lighting::LightingUniform;
+use crate::texture::Texture;
+
+// 场景着色器的三组绑定，所有场景管线共用同一个管线布局：
+// 0 每帧：相机、时间、光照参数，反射探针（立方体贴图、采样器、探针参数），点光源和分簇结果，以及光照贴图
+// 1 每种材质：纹理、采样器和材质参数
+// 2 每个物体：模型矩阵和颜色
+// 新的 uniform 加进对应的结构体或组里，不需要改各个管线的布局
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FrameUniform {
+    camera: CameraUniform,
+    // 游戏开始以来的秒数
+    time: f32,
+    _padding: [f32; 3],
+    lighting: LightingUniform,
+}
+
+unsafe impl bytemuck::Pod for FrameUniform {}
+unsafe impl bytemuck::Zeroable for FrameUniform {}
+
+impl FrameUniform {
+    pub fn new(camera: CameraUniform, time: f32, lighting: LightingUniform) -> Self {
+        Self { camera, time, _padding: [0.0; 3], lighting }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MaterialUniform {
+    // 墙体等材质的颜色，顶点着色的物体不使用
+    color: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for MaterialUniform {}
+unsafe impl bytemuck::Zeroable for MaterialUniform {}
+
+impl MaterialUniform {
+    pub fn new(color: [f32; 3]) -> Self {
+        Self { color: [color[0], color[1], color[2], 0.0] }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct ObjectUniform {
+    model: [[f32; 4]; 4],
+    // 与顶点颜色相乘
+    color: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for ObjectUniform {}
+unsafe impl bytemuck::Zeroable for ObjectUniform {}
+
+fn uniform_entry(binding: u32, visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn texture_entry(binding: u32, view_dimension: wgpu::TextureViewDimension) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn storage_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn sampler_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    }
+}
+
+pub struct Layouts {
+    pub frame: wgpu::BindGroupLayout,
+    pub material: wgpu::BindGroupLayout,
+    pub object: wgpu::BindGroupLayout,
+}
+
+impl Layouts {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let frame = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                uniform_entry(0, wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT),
+                texture_entry(1, wgpu::TextureViewDimension::Cube),
+                sampler_entry(2),
+                uniform_entry(3, wgpu::ShaderStages::FRAGMENT),
+                storage_entry(4),
+                storage_entry(5),
+                texture_entry(6, wgpu::TextureViewDimension::D3),
+                sampler_entry(7),
+            ],
+            label: Some("frame_bind_group_layout"),
+        });
+        let material = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture_entry(0, wgpu::TextureViewDimension::D2),
+                sampler_entry(1),
+                uniform_entry(2, wgpu::ShaderStages::FRAGMENT),
+            ],
+            label: Some("material_bind_group_layout"),
+        });
+        let object = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[uniform_entry(0, wgpu::ShaderStages::VERTEX)],
+            label: Some("object_bind_group_layout"),
+        });
+        Self { frame, material, object }
+    }
+
+    pub fn pipeline_layout(&self, device: &wgpu::Device) -> wgpu::PipelineLayout {
+        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Scene Pipeline Layout"),
+            bind_group_layouts: &[&self.frame, &self.material, &self.object],
+            push_constant_ranges: &[],
+        })
+    }
+}
+
+// 反射探针的资源，放在每帧的组里
+pub struct Environment<'a> {
+    pub view: &'a wgpu::TextureView,
+    pub sampler: &'a wgpu::Sampler,
+    pub probe: &'a wgpu::Buffer,
+}
+
+// 点光源、分簇结果和光照贴图，也在每帧的组里，见 lighting.rs
+pub struct Lights<'a> {
+    pub lights: &'a wgpu::Buffer,
+    pub clusters: &'a wgpu::Buffer,
+    pub lightmap: &'a wgpu::TextureView,
+    pub lightmap_sampler: &'a wgpu::Sampler,
+}
+
+pub struct FrameBinding {
+    pub buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl FrameBinding {
+    pub fn new(device: &wgpu::Device, layouts: &Layouts, environment: &Environment, lights: &Lights, label: &str) -> Self {
+        let buffer = gpu_memory::buffer_init(device, gpu_memory::Category::Frame, &wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Buffer", label)),
+            contents: bytemuck::cast_slice(&[FrameUniform::new(CameraUniform::new(), 0.0, LightingUniform::unlit())]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layouts.frame,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(environment.view) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::Sampler(environment.sampler) },
+                wgpu::BindGroupEntry { binding: 3, resource: environment.probe.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 4, resource: lights.lights.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 5, resource: lights.clusters.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 6, resource: wgpu::BindingResource::TextureView(lights.lightmap) },
+                wgpu::BindGroupEntry { binding: 7, resource: wgpu::BindingResource::Sampler(lights.lightmap_sampler) },
+            ],
+            label: Some(label),
+        });
+        Self { buffer, bind_group }
+    }
+}
+
+pub struct MaterialBinding {
+    pub buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl MaterialBinding {
+    pub fn new(device: &wgpu::Device, layouts: &Layouts, texture: &Texture, color: [f32; 3], label: &str) -> Self {
+        let buffer = gpu_memory::buffer_init(device, gpu_memory::Category::Frame, &wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Material Buffer", label)),
+            contents: bytemuck::cast_slice(&[MaterialUniform::new(color)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layouts.material,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&texture.view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&texture.sampler) },
+                wgpu::BindGroupEntry { binding: 2, resource: buffer.as_entire_binding() },
+            ],
+            label: Some(label),
+        });
+        Self { buffer, bind_group }
+    }
+}
+
+// 物体的绑定组；地图几何体已经在世界坐标里，共用一个单位矩阵的
+// （只有几十字节，缓冲区交给绑定组持有，不计入显存统计）
+pub fn create_object_bind_group(device: &wgpu::Device, layouts: &Layouts, model: Mat4, color: [f32; 3], label: &str) -> wgpu::BindGroup {
+    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(&format!("{} Buffer", label)),
+        contents: bytemuck::cast_slice(&[ObjectUniform { model: model.to_cols_array_2d(), color: [color[0], color[1], color[2], 1.0] }]),
+        usage: wgpu::BufferUsages::UNIFORM,
+    });
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &layouts.object,
+        entries: &[wgpu::BindGroupEntry { binding: 0, resource: buffer.as_entire_binding() }],
+        label: Some(label),
+    })
+}
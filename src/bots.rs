@@ -0,0 +1,128 @@
+use glam::Vec3;
+
+use crate::snapshot::PlayerState;
+
+// 联机主机上补人数的机器人（server.toml 的 bot_fill）：在地图的出生点和重生点附近左右横移，
+// 对别人来说和普通玩家一样出现在快照里，可以被打死，过几秒在另一个点重生。机器人不开枪
+
+const HEALTH: f32 = 100.0;
+const RESPAWN_DELAY: f32 = 5.0;
+// 左右横移的幅度（米）和速度（弧度每秒），幅度小于半格，不会走进墙里
+const STRAFE: f32 = 0.9;
+const STRAFE_SPEED: f32 = 1.6;
+const EYE_HEIGHT: f32 = 1.8;
+const NAMES: [&str; 8] = ["ALPHA", "BRAVO", "CHARLIE", "DELTA", "ECHO", "FOXTROT", "GOLF", "HOTEL"];
+
+struct Bot {
+    player_id: u32,
+    name: String,
+    home: Vec3,
+    yaw: f32,
+    phase: f32,
+    health: f32,
+    // 死亡后到重生的剩余时间
+    respawn: Option<f32>,
+}
+
+impl Bot {
+    fn position(&self) -> Vec3 {
+        let right = Vec3::new(self.yaw.cos(), 0.0, self.yaw.sin());
+        self.home + right * self.phase.sin() * STRAFE
+    }
+}
+
+// 机器人的编号和名字
+type Roster = Vec<(u32, String)>;
+
+pub struct Bots {
+    bots: Vec<Bot>,
+    // 脚下的位置，每换一张地图由主机设置
+    spawns: Vec<Vec3>,
+    next_spawn: usize,
+}
+
+impl Bots {
+    pub fn new() -> Self {
+        Self { bots: Vec::new(), spawns: Vec::new(), next_spawn: 0 }
+    }
+
+    // 换了地图，所有机器人回到新地图的出生点
+    pub fn set_spawns(&mut self, spawns: Vec<Vec3>) {
+        self.spawns = spawns;
+        self.next_spawn = 0;
+        for index in 0..self.bots.len() {
+            let home = self.pick_spawn();
+            let bot = &mut self.bots[index];
+            bot.home = home;
+            bot.health = HEALTH;
+            bot.respawn = None;
+        }
+    }
+
+    fn pick_spawn(&mut self) -> Vec3 {
+        if self.spawns.is_empty() {
+            return Vec3::ZERO;
+        }
+        let spawn = self.spawns[self.next_spawn % self.spawns.len()];
+        self.next_spawn += 1;
+        spawn + Vec3::Y * EYE_HEIGHT
+    }
+
+    // 补到 count 个机器人，多了从后面去掉。next_id 是主机分配玩家编号的计数，返回 (加入的, 去掉的) 编号和名字
+    pub fn fill(&mut self, count: usize, next_id: &mut u32) -> (Roster, Roster) {
+        let mut joined = Vec::new();
+        while self.bots.len() < count && !self.spawns.is_empty() {
+            let home = self.pick_spawn();
+            let name = format!("BOT {}", NAMES[self.bots.len() % NAMES.len()]);
+            let yaw = fastrand::f32() * std::f32::consts::TAU;
+            joined.push((*next_id, name.clone()));
+            self.bots.push(Bot { player_id: *next_id, name, home, yaw, phase: fastrand::f32() * std::f32::consts::TAU, health: HEALTH, respawn: None });
+            *next_id += 1;
+        }
+        let extra = self.bots.len().saturating_sub(count);
+        let left = self.bots.drain(self.bots.len() - extra..).map(|bot| (bot.player_id, bot.name)).collect();
+        (joined, left)
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for index in 0..self.bots.len() {
+            let bot = &mut self.bots[index];
+            bot.phase += STRAFE_SPEED * dt;
+            let Some(remaining) = &mut bot.respawn else { continue };
+            *remaining -= dt;
+            if *remaining <= 0.0 {
+                let home = self.pick_spawn();
+                let bot = &mut self.bots[index];
+                bot.home = home;
+                bot.health = HEALTH;
+                bot.respawn = None;
+            }
+        }
+    }
+
+    pub fn states(&self) -> Vec<(u32, PlayerState)> {
+        self.bots
+            .iter()
+            .map(|bot| (bot.player_id, PlayerState::new(bot.position(), bot.yaw, 0.0, bot.health.max(0.0), 0.0, bot.respawn.is_none())))
+            .collect()
+    }
+
+    pub fn contains(&self, player_id: u32) -> bool {
+        self.bots.iter().any(|bot| bot.player_id == player_id)
+    }
+
+    pub fn name(&self, player_id: u32) -> Option<&str> {
+        self.bots.iter().find(|bot| bot.player_id == player_id).map(|bot| bot.name.as_str())
+    }
+
+    // 打中机器人，返回 true 表示这一下打死了
+    pub fn damage(&mut self, player_id: u32, amount: f32) -> bool {
+        let Some(bot) = self.bots.iter_mut().find(|bot| bot.player_id == player_id && bot.respawn.is_none()) else { return false };
+        bot.health -= amount;
+        if bot.health > 0.0 {
+            return false;
+        }
+        bot.respawn = Some(RESPAWN_DELAY);
+        true
+    }
+}
@@ -1,283 +1,612 @@
-use winit::event::{WindowEvent, KeyboardInput, ElementState, VirtualKeyCode};
-use gilrs::{GamepadId, EventType, Button, Axis};
-use std::time::Duration;
-use glam::{Vec3, Mat4, Quat};
-use std::f32::consts::PI;
-
-#[repr(C)]
-#[derive(Debug, Copy, Clone)]
-pub struct CameraUniform {
-    view_proj: [[f32; 4]; 4],
-    view_position: [f32; 4],
-}
-
-// Manual implementation of bytemuck traits
-unsafe impl bytemuck::Pod for CameraUniform {}
-unsafe impl bytemuck::Zeroable for CameraUniform {}
-
-impl CameraUniform {
-    pub fn new() -> Self {
-        Self {
-            view_proj: Mat4::IDENTITY.to_cols_array_2d(),
-            view_position: [0.0, 0.0, 0.0, 1.0],
-        }
-    }
-
-    pub fn update_view_proj(&mut self, camera: &Camera, aspect: f32) {
-        self.view_position = [camera.position.x, camera.position.y, camera.position.z, 1.0];
-        let view = camera.calc_view();
-        let proj = camera.calc_projection(aspect);
-        self.view_proj = (proj * view).to_cols_array_2d();
-    }
-}
-
-pub struct Camera {
-    pub position: Vec3,
-    pub yaw: f32,   // Horizontal rotation (left/right)
-    pub pitch: f32,  // Vertical rotation (up/down)
-}
-
-impl Camera {
-    pub fn new(position: (f32, f32, f32), yaw: f32, pitch: f32) -> Self {
-        Self {
-            position: Vec3::new(position.0, position.1, position.2),
-            yaw,
-            pitch,
-        }
-    }
-
-    pub fn calc_view(&self) -> Mat4 {
-        // First rotate around Y axis (yaw)
-        let yaw_rotation = Quat::from_rotation_y(self.yaw);
-        
-        // Then rotate around X axis (pitch)
-        let pitch_rotation = Quat::from_rotation_x(self.pitch);
-        
-        // Combine rotations
-        let rotation = yaw_rotation * pitch_rotation;
-        
-        // Calculate view matrix
-        let view = Mat4::from_rotation_translation(
-            rotation,
-            self.position,
-        );
-        
-        // Invert the view matrix
-        view.inverse()
-    }
-
-    pub fn calc_projection(&self, aspect: f32) -> Mat4 {
-        Mat4::perspective_rh(
-            70.0 * (PI / 180.0), // 70 degree FOV
-            aspect,
-            0.1,  // near plane
-            100.0, // far plane
-        )
-    }
-}
-
-pub struct CameraController {
-    speed: f32,
-    sensitivity: f32,
-    forward: bool,
-    backward: bool,
-    left: bool,
-    right: bool,
-    // Controller state
-    left_stick_x: f32,
-    left_stick_y: f32,
-    right_stick_x: f32,
-    right_stick_y: f32,
-    mouse_move_x: f32,
-    mouse_move_y: f32,
-    is_jumping: bool,     // 添加跳跃状态
-    velocity_y: f32,      // 垂直速度
-    ground_level: f32,    // 地面高度
-}
-
-impl CameraController {
-    pub fn new(speed: f32, sensitivity: f32) -> Self {
-        Self {
-            speed,
-            sensitivity,
-            forward: false,
-            backward: false,
-            left: false,
-            right: false,
-            left_stick_x: 0.0,
-            left_stick_y: 0.0,
-            right_stick_x: 0.0,
-            right_stick_y: 0.0,
-            mouse_move_x: 0.0,
-            mouse_move_y: 0.0,
-            is_jumping: false,
-            velocity_y: 0.0,
-            ground_level: 1.8,
-        }
-    }
-
-    pub fn process_keyboard(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            WindowEvent::KeyboardInput {
-                input: KeyboardInput {
-                    state,
-                    virtual_keycode: Some(keycode),
-                    ..
-                },
-                ..
-            } => {
-                let is_pressed = *state == ElementState::Pressed;
-                match keycode {
-                    VirtualKeyCode::W => {
-                        self.forward = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::S => {
-                        self.backward = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::A => {
-                        self.left = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::D => {
-                        self.right = is_pressed;
-                        true
-                    }
-                    VirtualKeyCode::Space => {
-                        if is_pressed && !self.is_jumping {
-                            self.is_jumping = true;
-                            self.velocity_y = 8.0; // 初始跳跃速度
-                        }
-                        true
-                    }
-                    _ => false,
-                }
-            }
-            _ => false,
-        }
-    }
-
-    pub fn process_mouse(&mut self, dx: f64, dy: f64) {
-        // Convert to f32 and apply sensitivity
-        let dx = dx as f32 * self.sensitivity;
-        let dy = dy as f32 * self.sensitivity;
-        
-        // Update camera rotation (yaw and pitch will be applied to the camera in update_camera)
-        self.mouse_move_x = -dx * 0.7; // Invert X axis to fix reversed mouse direction
-        self.mouse_move_y = -dy * 0.7; // Invert Y axis for intuitive control
-    }
-
-    pub fn process_controller(&mut self, _id: &GamepadId, event: &EventType) {
-        match event {
-            EventType::ButtonPressed(button, _) => {
-                match button {
-                    Button::DPadUp => self.forward = true,
-                    Button::DPadDown => self.backward = true,
-                    Button::DPadLeft => self.left = true,
-                    Button::DPadRight => self.right = true,
-                    Button::South => {
-                        if !self.is_jumping {
-                            self.is_jumping = true;
-                            self.velocity_y = 8.0; // 初始跳跃速度
-                        }
-                    },
-                    _ => {},
-                }
-            },
-            EventType::ButtonReleased(button, _) => {
-                match button {
-                    Button::DPadUp => self.forward = false,
-                    Button::DPadDown => self.backward = false,
-                    Button::DPadLeft => self.left = false,
-                    Button::DPadRight => self.right = false,
-                    _ => {},
-                }
-            },
-            EventType::AxisChanged(axis, value, _) => {
-                match axis {
-                    Axis::LeftStickX => self.left_stick_x = *value,
-                    Axis::LeftStickY => self.left_stick_y = *value,
-                    Axis::RightStickX => {
-                        let dx = *value;  // 将摇杆值转换为类似鼠标的增量
-                        self.right_stick_x = -dx * self.sensitivity * 0.7;
-                    },
-                    Axis::RightStickY => {
-                        let dy = *value;
-                        self.right_stick_y = dy * self.sensitivity * 0.7;
-                    },
-                    _ => {},
-                }
-            },
-            _ => {},
-        }
-    }
-
-    pub fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
-        // Convert duration to seconds for smooth movement
-        let dt = dt.as_secs_f32();
-        
-        // 处理跳跃物理
-        if self.is_jumping {
-            self.velocity_y -= 20.0 * dt; // 重力加速度
-            camera.position.y += self.velocity_y * dt;
-            
-            // 检查是否落地
-            if camera.position.y <= self.ground_level {
-                camera.position.y = self.ground_level;
-                self.is_jumping = false;
-                self.velocity_y = 0.0;
-            }
-        }
-        
-        // Calculate forward and right vectors based on camera's current orientation
-        let forward = Vec3::new(
-            camera.yaw.sin(),
-            0.0,
-            camera.yaw.cos(),
-        ).normalize();
-        
-        let right = Vec3::new(
-            (camera.yaw - PI/2.0).sin(),
-            0.0,
-            (camera.yaw - PI/2.0).cos(),
-        ).normalize();
-        
-        // Process keyboard/D-pad movement
-        if self.forward {
-            camera.position -= forward * self.speed * dt;
-        }
-        if self.backward {
-            camera.position += forward * self.speed * dt;
-        }
-        if self.right {
-            camera.position -= right * self.speed * dt;
-        }
-        if self.left {
-            camera.position += right * self.speed * dt;
-        }
-        
-        // Process controller left stick movement
-        if self.left_stick_x.abs() > 0.1 || self.left_stick_y.abs() > 0.1 {
-            camera.position -= right * self.left_stick_x * self.speed * dt;
-            camera.position -= forward * self.left_stick_y * self.speed * dt;
-        }
-        
-        // Process mouse/controller right stick for camera rotation
-        camera.yaw += self.right_stick_x * self.sensitivity * dt * 2.0;
-        camera.pitch += self.right_stick_y * self.sensitivity * dt * 2.0;
-        camera.yaw += self.mouse_move_x * self.sensitivity * dt * 2.0;
-        camera.pitch += self.mouse_move_y * self.sensitivity * dt * 2.0;
-        
-        self.mouse_move_x = 0.0;
-        self.mouse_move_y = 0.0;
-        
-        // Clamp pitch to avoid camera flipping
-        camera.pitch = camera.pitch.clamp(-PI/2.0 + 0.1, PI/2.0 - 0.1);
-        
-        // Ensure camera doesn't go below the floor
-        if camera.position.y < 1.0 {
-            camera.position.y = 1.0;
-        }
-    }
-}
\ No newline at end of file
+use std::time::Duration;
+use glam::{Vec3, Mat4, Quat};
+use std::f32::consts::PI;
+
+#[cfg(feature = "gamepad")]
+use crate::axes::AxisTarget;
+use crate::aim_assist::Assist;
+use crate::collision::{self, WallCollider};
+use crate::input::Action;
+
+// 默认的近、远裁剪面，多层的大地图可以在设置里调远
+pub const DEFAULT_NEAR: f32 = 0.1;
+pub const DEFAULT_FAR: f32 = 100.0;
+// 垂直视角（度）
+pub const FOV_DEGREES: f32 = 70.0;
+
+// 投影方式，每个相机（也就是每个用它的渲染通道）自己选
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    // 透视投影，垂直视角（度）
+    Perspective { fov_degrees: f32 },
+    // 正交投影（俯视模式、编辑器、场景里的 UI），height 是画面高度对应的世界尺寸（米），宽度按画面比例
+    Orthographic { height: f32 },
+}
+
+impl Projection {
+    // 反向深度：把近、远裁剪面对调传入，近处的深度是 1，远处是 0
+    pub fn matrix(self, aspect: f32, near: f32, far: f32) -> Mat4 {
+        match self {
+            Projection::Perspective { fov_degrees } => Mat4::perspective_rh(fov_degrees * (PI / 180.0), aspect, far, near),
+            Projection::Orthographic { height } => {
+                let (half_w, half_h) = (height * aspect / 2.0, height / 2.0);
+                Mat4::orthographic_rh(-half_w, half_w, -half_h, half_h, far, near)
+            }
+        }
+    }
+
+    // 透视投影的垂直视角（弧度），正交投影为 None
+    pub fn fov(self) -> Option<f32> {
+        match self {
+            Projection::Perspective { fov_degrees } => Some(fov_degrees.to_radians()),
+            Projection::Orthographic { .. } => None,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    view_position: [f32; 4],
+}
+
+// Manual implementation of bytemuck traits
+unsafe impl bytemuck::Pod for CameraUniform {}
+unsafe impl bytemuck::Zeroable for CameraUniform {}
+
+impl CameraUniform {
+    pub fn new() -> Self {
+        Self {
+            view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            view_position: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+
+    // 不经过 Camera 的视角（例如反射探针的六个面）
+    pub fn from_view_proj(view_proj: Mat4, position: Vec3) -> Self {
+        Self { view_proj: view_proj.to_cols_array_2d(), view_position: position.extend(1.0).to_array() }
+    }
+
+    pub fn update_view_proj(&mut self, camera: &Camera, aspect: f32) {
+        self.view_position = [camera.position.x, camera.position.y, camera.position.z, 1.0];
+        let view = camera.calc_view();
+        let proj = camera.calc_projection(aspect);
+        self.view_proj = (proj * view).to_cols_array_2d();
+    }
+}
+
+#[derive(Clone)]
+pub struct Camera {
+    pub position: Vec3,
+    pub yaw: f32,   // Horizontal rotation (left/right)
+    pub pitch: f32,  // Vertical rotation (up/down)
+    pub roll: f32,   // 绕视线的旋转，正值向左歪
+    // 裁剪面，来自设置
+    pub near: f32,
+    pub far: f32,
+    pub projection: Projection,
+}
+
+impl Camera {
+    pub fn new(position: (f32, f32, f32), yaw: f32, pitch: f32) -> Self {
+        Self {
+            position: Vec3::new(position.0, position.1, position.2),
+            yaw,
+            pitch,
+            roll: 0.0,
+            near: DEFAULT_NEAR,
+            far: DEFAULT_FAR,
+            projection: Projection::Perspective { fov_degrees: FOV_DEGREES },
+        }
+    }
+
+    // 从 ceiling 高度往下看 target 周围 span 米的正交相机（调试用的俯视模式）
+    pub fn top_down(target: Vec3, ceiling: f32, span: f32) -> Self {
+        Self {
+            position: Vec3::new(target.x, ceiling, target.z),
+            yaw: 0.0,
+            pitch: -PI / 2.0,
+            roll: 0.0,
+            near: DEFAULT_NEAR,
+            far: ceiling + 1.0,
+            projection: Projection::Orthographic { height: span },
+        }
+    }
+
+    pub fn calc_view(&self) -> Mat4 {
+        // First rotate around Y axis (yaw)
+        let yaw_rotation = Quat::from_rotation_y(self.yaw);
+        
+        // Then rotate around X axis (pitch)
+        let pitch_rotation = Quat::from_rotation_x(self.pitch);
+        
+        // 最后绕视线方向（相机自己的 Z 轴）转 roll。三个四元数依次相乘，俯仰到头时也不会万向锁
+        let roll_rotation = Quat::from_rotation_z(self.roll);
+
+        // Combine rotations
+        let rotation = yaw_rotation * pitch_rotation * roll_rotation;
+        
+        // Calculate view matrix
+        let view = Mat4::from_rotation_translation(
+            rotation,
+            self.position,
+        );
+        
+        // Invert the view matrix
+        view.inverse()
+    }
+
+    // 不能抬头低头到翻过去
+    pub fn clamp_pitch(&mut self) {
+        self.pitch = self.pitch.clamp(-PI / 2.0 + 0.1, PI / 2.0 - 0.1);
+    }
+
+    // 视线方向（包含俯仰）
+    pub fn forward(&self) -> Vec3 {
+        let rotation = Quat::from_rotation_y(self.yaw) * Quat::from_rotation_x(self.pitch);
+        rotation * Vec3::NEG_Z
+    }
+
+    pub fn calc_projection(&self, aspect: f32) -> Mat4 {
+        self.projection.matrix(aspect, self.near, self.far)
+    }
+}
+
+// 走路速度（米每秒）和冲刺速度倍数，主机的反作弊检查也用它们
+pub const WALK_SPEED: f32 = 4.0;
+pub const SPRINT_MULTIPLIER: f32 = 1.8;
+// 灵敏度为 1 时鼠标移动一个单位转过的角度（弧度），和原来 60 帧时的手感相同。
+// 鼠标的移动量本身就是这一帧里累计的距离，不再乘帧时间，转动的角度和帧率无关
+const MOUSE_RADIANS_PER_COUNT: f32 = 1.4 / 60.0;
+// 鼠标加速：每秒移动的单位数乘上这个系数加到倍数上，倍数最多到 MAX_ACCELERATION
+const ACCELERATION: f32 = 0.0005;
+const MAX_ACCELERATION: f32 = 3.0;
+
+// 视角灵敏度：鼠标和右摇杆分开，水平和垂直也分开，可以单独反转垂直方向。保存在设置里
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct LookSensitivity {
+    pub mouse_x: f32,
+    pub mouse_y: f32,
+    pub invert_mouse_y: bool,
+    pub stick_x: f32,
+    pub stick_y: f32,
+    pub invert_stick_y: bool,
+}
+
+impl Default for LookSensitivity {
+    fn default() -> Self {
+        Self { mouse_x: 1.0, mouse_y: 1.0, invert_mouse_y: false, stick_x: 1.0, stick_y: 1.0, invert_stick_y: false }
+    }
+}
+
+// 画面倾斜（歪头）：左右平移时往移动方向歪一点，受伤时被打得一歪再慢慢回正。
+// 只加在画面用的相机上，不影响瞄准；加起来不超过 MAX_TILT，设置里可以关掉
+const STRAFE_TILT: f32 = 1.5 * PI / 180.0;
+const STRAFE_TILT_SHARPNESS: f32 = 8.0;
+// 受到 DAMAGE_TILT_FULL 点伤害时歪到最大，之后按指数回正
+const DAMAGE_TILT: f32 = 4.0 * PI / 180.0;
+const DAMAGE_TILT_FULL: f32 = 50.0;
+const DAMAGE_TILT_RECOVERY: f32 = 6.0;
+const MAX_TILT: f32 = 5.0 * PI / 180.0;
+// 自由相机（拍照）的转动速度（弧度每秒）和最大角度
+const FREE_ROLL_SPEED: f32 = 1.0;
+const MAX_FREE_ROLL: f32 = PI / 2.0;
+
+pub struct CameraTilt {
+    strafe: f32,
+    damage: f32,
+}
+
+impl CameraTilt {
+    pub fn new() -> Self {
+        Self { strafe: 0.0, damage: 0.0 }
+    }
+
+    // strafe 是左右移动的输入，-1 向左到 1 向右
+    pub fn update(&mut self, strafe: f32, dt: f32) {
+        let t = 1.0 - (-STRAFE_TILT_SHARPNESS * dt).exp();
+        self.strafe += (-strafe * STRAFE_TILT - self.strafe) * t;
+        self.damage *= (-DAMAGE_TILT_RECOVERY * dt).exp();
+    }
+
+    // 受伤时随机往一边歪，伤害越大歪得越多
+    pub fn hit(&mut self, amount: f32) {
+        let side = if fastrand::bool() { 1.0 } else { -1.0 };
+        self.damage = side * DAMAGE_TILT * (amount / DAMAGE_TILT_FULL).min(1.0);
+    }
+
+    pub fn roll(&self) -> f32 {
+        (self.strafe + self.damage).clamp(-MAX_TILT, MAX_TILT)
+    }
+
+    // 重生、换地图时回正
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+// 画面震动：附近爆炸时积累强度，强度的平方乘上几组不同频率的正弦晃动偏航、俯仰和倾斜，再按时间线性衰减。
+// 和倾斜一样只加在画面用的相机上，设置里关掉画面倾斜时也不震
+const SHAKE_YAW: f32 = 2.0 * PI / 180.0;
+const SHAKE_PITCH: f32 = 1.5 * PI / 180.0;
+const SHAKE_ROLL: f32 = 2.5 * PI / 180.0;
+const SHAKE_FREQUENCY: f32 = 25.0;
+// 每秒衰减的强度
+const SHAKE_DECAY: f32 = 1.5;
+
+pub struct CameraShake {
+    trauma: f32,
+    time: f32,
+}
+
+impl CameraShake {
+    pub fn new() -> Self {
+        Self { trauma: 0.0, time: 0.0 }
+    }
+
+    // amount 为 0 到 1，叠加后不超过 1
+    pub fn add(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount.max(0.0)).min(1.0);
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.trauma = (self.trauma - SHAKE_DECAY * dt).max(0.0);
+        self.time += dt;
+    }
+
+    // 加在偏航、俯仰和倾斜上的角度
+    pub fn offset(&self) -> (f32, f32, f32) {
+        let strength = self.trauma * self.trauma;
+        let t = self.time * SHAKE_FREQUENCY;
+        let yaw = ((t * 1.0).sin() + (t * 2.3 + 1.7).sin() * 0.5) / 1.5;
+        let pitch = ((t * 1.3 + 0.5).sin() + (t * 2.9 + 2.1).sin() * 0.5) / 1.5;
+        let roll = ((t * 0.9 + 3.1).sin() + (t * 2.1 + 0.3).sin() * 0.5) / 1.5;
+        (yaw * SHAKE_YAW * strength, pitch * SHAKE_PITCH * strength, roll * SHAKE_ROLL * strength)
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+// 第三人称相机挂在眼睛后上方的偏移（米）：向后、向上、向右（越过右肩）
+const BOOM_BACK: f32 = 3.0;
+const BOOM_UP: f32 = 0.4;
+const BOOM_RIGHT: f32 = 0.6;
+// 相机当作这么大的球，离墙至少这么远，近裁剪面不会穿进墙里
+const BOOM_RADIUS: f32 = 0.25;
+pub const DEFAULT_BOOM_SPRING: f32 = 120.0;
+
+// 第三人称相机的吊臂：从眼睛往后上方用球扫一遍，碰到墙时相机收到墙前面。
+// 长度按临界阻尼的弹簧跟上扫出来的长度，贴着墙移动、离开墙角时平滑地收放而不是一下跳过去
+pub struct CameraBoom {
+    length: f32,
+    velocity: f32,
+    // 弹簧系数，越大跟得越紧，来自设置
+    pub spring: f32,
+}
+
+impl CameraBoom {
+    pub fn new(spring: f32) -> Self {
+        Self { length: 0.0, velocity: 0.0, spring }
+    }
+
+    // 返回这一帧第三人称画面用的相机，eye 是第一人称的相机
+    pub fn update(&mut self, eye: &Camera, colliders: &[WallCollider], dt: f32) -> Camera {
+        let rotation = Quat::from_rotation_y(eye.yaw) * Quat::from_rotation_x(eye.pitch);
+        let offset = rotation * Vec3::new(BOOM_RIGHT, BOOM_UP, BOOM_BACK);
+        let max_length = offset.length();
+        let dir = offset / max_length;
+        let target = collision::spherecast(colliders, eye.position, dir, BOOM_RADIUS, max_length).unwrap_or(max_length);
+        // 半隐式欧拉积分的临界阻尼弹簧，步长太大时分几步走，系数再大也不会发散
+        let steps = (dt * self.spring.sqrt() / 0.5).ceil().max(1.0);
+        let step = dt / steps;
+        for _ in 0..steps as usize {
+            let acceleration = self.spring * (target - self.length) - 2.0 * self.spring.sqrt() * self.velocity;
+            self.velocity += acceleration * step;
+            self.length += self.velocity * step;
+        }
+        self.length = self.length.clamp(0.0, max_length);
+        let mut camera = eye.clone();
+        camera.position = eye.position + dir * self.length;
+        camera
+    }
+
+    // 切到第三人称、重生时从眼睛的位置伸出去
+    pub fn reset(&mut self) {
+        self.length = 0.0;
+        self.velocity = 0.0;
+    }
+}
+
+// 设置界面里依次切换的灵敏度
+const SENSITIVITY_STEPS: [f32; 9] = [0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 2.0, 2.5, 3.0];
+
+// 比当前值大的下一档，到头后回到第一档
+pub fn next_sensitivity(current: f32) -> f32 {
+    SENSITIVITY_STEPS.iter().copied().find(|step| *step > current + 0.001).unwrap_or(SENSITIVITY_STEPS[0])
+}
+
+pub struct CameraController {
+    speed: f32,
+    // 设置界面改了以后直接换掉，不用重启
+    pub look: LookSensitivity,
+    forward: bool,
+    backward: bool,
+    left: bool,
+    right: bool,
+    sprinting: bool,
+    // 被炸到或电击后暂时不能冲刺，由玩家的状态设置
+    pub sprint_blocked: bool,
+    // 自由相机的左右转动（拍照用）
+    roll_left: bool,
+    roll_right: bool,
+    // Controller state
+    left_stick_x: f32,
+    left_stick_y: f32,
+    right_stick_x: f32,
+    right_stick_y: f32,
+    // 上一帧以来累计的鼠标移动量，和上一帧实际用的移动量（平滑用）
+    mouse_move_x: f32,
+    mouse_move_y: f32,
+    last_mouse_x: f32,
+    last_mouse_y: f32,
+    // 设置里的鼠标平滑（和上一帧取平均）和鼠标加速，默认都关
+    pub mouse_smoothing: bool,
+    pub mouse_acceleration: bool,
+    is_jumping: bool,     // 添加跳跃状态
+    velocity_y: f32,      // 垂直速度
+    ground_level: f32,    // 地面高度
+}
+
+impl CameraController {
+    pub fn new(speed: f32, look: LookSensitivity) -> Self {
+        Self {
+            speed,
+            look,
+            forward: false,
+            backward: false,
+            left: false,
+            right: false,
+            sprinting: false,
+            sprint_blocked: false,
+            roll_left: false,
+            roll_right: false,
+            left_stick_x: 0.0,
+            left_stick_y: 0.0,
+            right_stick_x: 0.0,
+            right_stick_y: 0.0,
+            mouse_move_x: 0.0,
+            mouse_move_y: 0.0,
+            last_mouse_x: 0.0,
+            last_mouse_y: 0.0,
+            mouse_smoothing: false,
+            mouse_acceleration: false,
+            is_jumping: false,
+            velocity_y: 0.0,
+            ground_level: 1.8,
+        }
+    }
+
+    // 是否站在地面上（没有在跳跃）
+    pub fn is_grounded(&self) -> bool {
+        !self.is_jumping
+    }
+
+    // 按键绑定解析后的移动操作，返回 false 表示不是移动相关的操作
+    pub fn set_action(&mut self, action: Action, is_pressed: bool) -> bool {
+        match action {
+            Action::MoveForward => self.forward = is_pressed,
+            Action::MoveBack => self.backward = is_pressed,
+            Action::MoveLeft => self.left = is_pressed,
+            Action::MoveRight => self.right = is_pressed,
+            Action::Sprint => self.sprinting = is_pressed,
+            Action::RollLeft => self.roll_left = is_pressed,
+            Action::RollRight => self.roll_right = is_pressed,
+            Action::Jump => {
+                if is_pressed && !self.is_jumping {
+                    self.is_jumping = true;
+                    self.velocity_y = 8.0; // 初始跳跃速度
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // 一帧里可能收到多次鼠标移动，先累计起来，在 update_look 里一起转动视角
+    pub fn process_mouse(&mut self, dx: f64, dy: f64) {
+        self.mouse_move_x += dx as f32;
+        self.mouse_move_y += dy as f32;
+    }
+
+    // 手柄摇杆经过响应曲线后的值（见 axes.rs）：移动和视角，x 向右、y 向前/向上
+    #[cfg(feature = "gamepad")]
+    pub fn set_gamepad_axis(&mut self, target: AxisTarget, value: f32) {
+        match target {
+            AxisTarget::MoveX => self.left_stick_x = value,
+            AxisTarget::MoveY => self.left_stick_y = value,
+            // 将摇杆值转换为类似鼠标的增量
+            AxisTarget::LookX => self.right_stick_x = -value * 0.7,
+            AxisTarget::LookY => self.right_stick_y = value * 0.7,
+        }
+    }
+
+    // 触屏的虚拟摇杆，和手柄的左摇杆一样：x 向右、y 向前
+    pub fn set_move_stick(&mut self, x: f32, y: f32) {
+        self.left_stick_x = x;
+        self.left_stick_y = y;
+    }
+
+    // 手柄交给菜单时松开方向键、摇杆回中，关上菜单后不会继续移动
+    #[cfg(feature = "gamepad")]
+    pub fn release_controller(&mut self) {
+        self.forward = false;
+        self.backward = false;
+        self.left = false;
+        self.right = false;
+        self.left_stick_x = 0.0;
+        self.left_stick_y = 0.0;
+        self.right_stick_x = 0.0;
+        self.right_stick_y = 0.0;
+    }
+
+    // 松开所有按键、摇杆回中、落回地面，保留速度和视角的设置（确定性测试每轮开始时用）
+    pub fn reset_motion(&mut self) {
+        *self = Self { look: self.look, mouse_smoothing: self.mouse_smoothing, mouse_acceleration: self.mouse_acceleration, ..Self::new(self.speed, self.look) };
+    }
+
+    pub fn update_camera(&mut self, camera: &mut Camera, dt: Duration) {
+        // Convert duration to seconds for smooth movement
+        let dt = dt.as_secs_f32();
+        
+        // 处理跳跃物理
+        if self.is_jumping {
+            self.velocity_y -= 20.0 * dt; // 重力加速度
+            camera.position.y += self.velocity_y * dt;
+            
+            // 检查是否落地
+            if camera.position.y <= self.ground_level {
+                camera.position.y = self.ground_level;
+                self.is_jumping = false;
+                self.velocity_y = 0.0;
+            }
+        }
+        
+        // Calculate forward and right vectors based on camera's current orientation
+        let forward = Vec3::new(
+            camera.yaw.sin(),
+            0.0,
+            camera.yaw.cos(),
+        ).normalize();
+        
+        let right = Vec3::new(
+            (camera.yaw - PI/2.0).sin(),
+            0.0,
+            (camera.yaw - PI/2.0).cos(),
+        ).normalize();
+        
+        // 冲刺时移动更快
+        let speed = if self.sprinting && !self.sprint_blocked { self.speed * SPRINT_MULTIPLIER } else { self.speed };
+        
+        // Process keyboard/D-pad movement
+        if self.forward {
+            camera.position -= forward * speed * dt;
+        }
+        if self.backward {
+            camera.position += forward * speed * dt;
+        }
+        if self.right {
+            camera.position -= right * speed * dt;
+        }
+        if self.left {
+            camera.position += right * speed * dt;
+        }
+        
+        // Process controller left stick movement
+        // 手柄的死区已经在 axes.rs 的响应曲线里处理，触屏摇杆松开时正好是 0
+        if self.left_stick_x != 0.0 || self.left_stick_y != 0.0 {
+            camera.position -= right * self.left_stick_x * speed * dt;
+            camera.position -= forward * self.left_stick_y * speed * dt;
+        }
+        
+        // Ensure camera doesn't go below the floor
+        if camera.position.y < 1.0 {
+            camera.position.y = 1.0;
+        }
+    }
+
+    // 观战的自由相机：沿视线方向飞，不受重力和墙体影响
+    pub fn fly(&self, camera: &mut Camera, dt: Duration) {
+        let dt = dt.as_secs_f32();
+        let speed = if self.sprinting { self.speed * SPRINT_MULTIPLIER * 2.0 } else { self.speed * 2.0 };
+        let forward = camera.forward();
+        let right = forward.cross(Vec3::Y).normalize_or_zero();
+        let mut forward_amount = self.left_stick_y;
+        let mut right_amount = self.left_stick_x;
+        if self.forward {
+            forward_amount += 1.0;
+        }
+        if self.backward {
+            forward_amount -= 1.0;
+        }
+        if self.right {
+            right_amount += 1.0;
+        }
+        if self.left {
+            right_amount -= 1.0;
+        }
+        camera.position += (forward * forward_amount + right * right_amount) * speed * dt;
+        let roll = (self.roll_left as i32 - self.roll_right as i32) as f32;
+        camera.roll = (camera.roll + roll * FREE_ROLL_SPEED * dt).clamp(-MAX_FREE_ROLL, MAX_FREE_ROLL);
+    }
+
+    // 左右移动的输入，-1 向左到 1 向右，画面倾斜用
+    // 按着冲刺键在移动（被禁止冲刺时不算），消耗体力用
+    pub fn is_sprinting(&self) -> bool {
+        let moving = self.forward || self.backward || self.left || self.right || self.left_stick_x != 0.0 || self.left_stick_y != 0.0;
+        self.sprinting && !self.sprint_blocked && moving
+    }
+
+    pub fn strafe_input(&self) -> f32 {
+        (self.right as i32 - self.left as i32) as f32 + self.left_stick_x
+    }
+
+    // 视角在每个渲染帧更新（移动在固定频率的模拟里），高刷新率的显示器上转动视角也是流畅的
+    // assist 是辅助瞄准（见 aim_assist.rs），只作用在它对应的鼠标或者右摇杆上
+    pub fn update_look(&mut self, camera: &mut Camera, dt: Duration, assist: Option<Assist>) {
+        let dt = dt.as_secs_f32();
+        let (stick_friction, mouse_friction) = match assist {
+            Some(assist) if assist.mouse => (0.0, assist.friction),
+            Some(assist) => (assist.friction, 0.0),
+            None => (0.0, 0.0),
+        };
+
+        // Process mouse/controller right stick for camera rotation
+        let look = self.look;
+        let stick_y = if look.invert_stick_y { -look.stick_y } else { look.stick_y };
+        camera.yaw += self.right_stick_x * look.stick_x * dt * 2.0 * (1.0 - stick_friction);
+        camera.pitch += self.right_stick_y * stick_y * dt * 2.0 * (1.0 - stick_friction);
+        let (dx, dy) = self.take_mouse_move(dt);
+        // Invert both axes: moving the mouse right/up turns right/up
+        let mouse_y = if look.invert_mouse_y { -look.mouse_y } else { look.mouse_y };
+        camera.yaw -= dx * look.mouse_x * MOUSE_RADIANS_PER_COUNT * (1.0 - mouse_friction);
+        camera.pitch -= dy * mouse_y * MOUSE_RADIANS_PER_COUNT * (1.0 - mouse_friction);
+
+        // 吸附只在玩家转动视角或者移动时生效，站着不动时不会自己瞄准
+        if let Some(assist) = assist {
+            let looking = if assist.mouse { dx != 0.0 || dy != 0.0 } else { self.right_stick_x != 0.0 || self.right_stick_y != 0.0 };
+            let moving = self.left_stick_x != 0.0 || self.left_stick_y != 0.0 || self.forward || self.backward || self.left || self.right;
+            if looking || moving {
+                camera.yaw += assist.pull_yaw * dt;
+                camera.pitch += assist.pull_pitch * dt;
+            }
+        }
+        
+        // Clamp pitch to avoid camera flipping
+        camera.clamp_pitch();
+    }
+
+    // 取出这一帧累计的鼠标移动量，按设置做平滑和加速
+    fn take_mouse_move(&mut self, dt: f32) -> (f32, f32) {
+        let (mut dx, mut dy) = (self.mouse_move_x, self.mouse_move_y);
+        self.mouse_move_x = 0.0;
+        self.mouse_move_y = 0.0;
+        if self.mouse_smoothing {
+            let (raw_x, raw_y) = (dx, dy);
+            dx = (dx + self.last_mouse_x) * 0.5;
+            dy = (dy + self.last_mouse_y) * 0.5;
+            self.last_mouse_x = raw_x;
+            self.last_mouse_y = raw_y;
+        }
+        if self.mouse_acceleration && dt > 0.0 {
+            let speed = (dx * dx + dy * dy).sqrt() / dt;
+            let factor = (1.0 + speed * ACCELERATION).min(MAX_ACCELERATION);
+            dx *= factor;
+            dy *= factor;
+        }
+        (dx, dy)
+    }
+}
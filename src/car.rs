@@ -0,0 +1,115 @@
+use glam::{Mat4, Quat, Vec3};
+
+use crate::collision::WallCollider;
+use crate::map::Map;
+use crate::model::{self, BoxInstance, Model};
+
+// 停车场里停着的车：用立方体拼成车身、车舱和轮子，车漆和车窗有环境反射；
+// 车身也是碰撞体，挡路、挡子弹
+
+const LENGTH: f32 = 4.2;
+const WIDTH: f32 = 1.8;
+// 车身底部离地的高度（轮子露在下面）
+const CLEARANCE: f32 = 0.3;
+const BODY_HEIGHT: f32 = 0.7;
+const CABIN_HEIGHT: f32 = 0.55;
+
+const WINDOW_COLOR: [f32; 3] = [0.05, 0.07, 0.1];
+const TIRE_COLOR: [f32; 3] = [0.04, 0.04, 0.04];
+const BUMPER_COLOR: [f32; 3] = [0.12, 0.12, 0.13];
+
+// 反射强度（正对着看时，斜着看会更强）
+const PAINT_REFLECTIVITY: f32 = 0.25;
+const WINDOW_REFLECTIVITY: f32 = 0.5;
+const CHROME_REFLECTIVITY: f32 = 0.7;
+
+fn paint_color(name: &str) -> Option<[f32; 3]> {
+    match name {
+        "red" => Some([0.6, 0.05, 0.05]),
+        "blue" => Some([0.06, 0.15, 0.5]),
+        "white" => Some([0.85, 0.85, 0.83]),
+        "black" => Some([0.03, 0.03, 0.035]),
+        "silver" => Some([0.55, 0.56, 0.58]),
+        "yellow" => Some([0.8, 0.65, 0.05]),
+        _ => None,
+    }
+}
+
+struct Car {
+    center: Vec3,
+    along_x: bool,
+    paint: [f32; 3],
+}
+
+impl Car {
+    // 车的局部坐标：x 为车头方向，z 为侧面
+    fn rotation(&self) -> Quat {
+        if self.along_x {
+            Quat::IDENTITY
+        } else {
+            Quat::from_rotation_y(std::f32::consts::FRAC_PI_2)
+        }
+    }
+
+    fn part(&self, size: Vec3, offset: Vec3, color: [f32; 3]) -> BoxInstance {
+        let rotation = self.rotation();
+        BoxInstance {
+            transform: Mat4::from_scale_rotation_translation(size, rotation, self.center + rotation * offset),
+            color,
+        }
+    }
+
+    fn boxes(&self, out: &mut Vec<(BoxInstance, f32)>) {
+        let body_y = CLEARANCE + BODY_HEIGHT / 2.0;
+        let cabin_y = CLEARANCE + BODY_HEIGHT + CABIN_HEIGHT / 2.0;
+        out.push((self.part(Vec3::new(LENGTH, BODY_HEIGHT, WIDTH), Vec3::Y * body_y, self.paint), PAINT_REFLECTIVITY));
+        // 车舱偏后，车窗是略小一圈的深色玻璃，车顶盖在上面
+        let cabin_offset = Vec3::new(-0.3, cabin_y, 0.0);
+        out.push((self.part(Vec3::new(2.1, CABIN_HEIGHT, WIDTH - 0.1), cabin_offset, WINDOW_COLOR), WINDOW_REFLECTIVITY));
+        out.push((
+            self.part(Vec3::new(1.7, 0.06, WIDTH - 0.14), cabin_offset + Vec3::Y * (CABIN_HEIGHT / 2.0), self.paint),
+            PAINT_REFLECTIVITY,
+        ));
+        for x in [-1.0, 1.0] {
+            out.push((
+                self.part(Vec3::new(0.12, 0.18, WIDTH + 0.04), Vec3::new(x * LENGTH / 2.0, CLEARANCE + 0.12, 0.0), BUMPER_COLOR),
+                CHROME_REFLECTIVITY,
+            ));
+            for z in [-1.0, 1.0] {
+                let wheel = Vec3::new(x * (LENGTH / 2.0 - 0.75), 0.33, z * (WIDTH / 2.0 - 0.15));
+                out.push((self.part(Vec3::new(0.66, 0.66, 0.3), wheel, TIRE_COLOR), 0.0));
+            }
+        }
+    }
+
+    fn collider(&self) -> WallCollider {
+        let tangent = self.rotation() * Vec3::X;
+        let normal = self.rotation() * Vec3::Z;
+        let start = self.center - tangent * (LENGTH / 2.0) - normal * (WIDTH / 2.0);
+        let end = start + tangent * LENGTH;
+        WallCollider::new(start.into(), end.into(), CLEARANCE + BODY_HEIGHT + CABIN_HEIGHT, WIDTH)
+    }
+}
+
+// 地图中所有车的模型（合并成一个）和碰撞体，未知的车漆颜色只打印警告
+pub fn build(device: &wgpu::Device, map: &Map) -> (Option<Model>, Vec<WallCollider>) {
+    let cars: Vec<Car> = map
+        .cars
+        .iter()
+        .filter_map(|spawn| {
+            let Some(paint) = paint_color(&spawn.paint) else {
+                eprintln!("地图 {} 中的车使用了未知的颜色: {}", map.name, spawn.paint);
+                return None;
+            };
+            Some(Car { center: map.cell_center(spawn.col, spawn.row), along_x: spawn.along_x, paint })
+        })
+        .collect();
+    if cars.is_empty() {
+        return (None, Vec::new());
+    }
+    let mut boxes = Vec::new();
+    for car in &cars {
+        car.boxes(&mut boxes);
+    }
+    (Some(model::create_glossy_boxes(device, &boxes)), cars.iter().map(Car::collider).collect())
+}
@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+
+use glam::{Mat4, Quat, Vec3};
+
+use crate::collision::WallCollider;
+use crate::model::BoxInstance;
+use crate::weapon::AmmoType;
+
+// 弹壳：开枪时从枪的右侧抛出，带着旋转划一道弧线落地，在地上弹几下后停下。
+// 停下的弹壳留在地上，数量超过设置里的上限时最早落地的先消失。和其他动态物体一起用方块画
+
+const GRAVITY: f32 = 9.8;
+// 弹跳时保留的速度比例，以及落地时地面的摩擦
+const BOUNCE: f32 = 0.35;
+const FRICTION: f32 = 0.6;
+// 落地时竖直速度低于这个值就不再弹起
+const REST_SPEED: f32 = 0.6;
+// 在地上一直弹个不停的话，抛出这么久以后也算停下（秒）
+const MAX_FLIGHT: f32 = 3.0;
+const RADIUS: f32 = 0.01;
+const BRASS: [f32; 3] = [0.85, 0.65, 0.25];
+const SHELL: [f32; 3] = [0.7, 0.12, 0.1];
+
+// 设置界面依次切换的上限，0 为落地就消失
+const LIMIT_STEPS: [usize; 5] = [0, 32, 128, 256, 512];
+pub const DEFAULT_LIMIT: usize = 128;
+
+// 比当前值大的下一档，到头后回到第一档
+pub fn next_limit(current: usize) -> usize {
+    LIMIT_STEPS.iter().copied().find(|step| *step > current).unwrap_or(LIMIT_STEPS[0])
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Casing {
+    position: Vec3,
+    previous: Vec3,
+    velocity: Vec3,
+    rotation: Quat,
+    spin: Vec3,
+    flight: f32,
+    size: Vec3,
+    color: [f32; 3],
+}
+
+impl Casing {
+    fn transform(&self, position: Vec3) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.size, self.rotation, position + Vec3::Y * self.size.y * 0.5)
+    }
+}
+
+pub struct Casings {
+    flying: Vec<Casing>,
+    // 按落地的先后排列
+    resting: VecDeque<Casing>,
+}
+
+impl Casings {
+    pub fn new() -> Self {
+        Self { flying: Vec::new(), resting: VecDeque::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.flying.clear();
+        self.resting.clear();
+    }
+
+    // 从 position（枪的抛壳口）往视线的右上方抛出，钉子之类的没有弹壳
+    pub fn eject(&mut self, ammo: AmmoType, position: Vec3, forward: Vec3) {
+        let (size, color) = match ammo {
+            AmmoType::Nails => return,
+            AmmoType::Pistol => (Vec3::new(0.012, 0.012, 0.02), BRASS),
+            AmmoType::Rifle => (Vec3::new(0.012, 0.012, 0.045), BRASS),
+            AmmoType::Shells => (Vec3::new(0.022, 0.022, 0.06), SHELL),
+        };
+        let right = forward.cross(Vec3::Y).normalize_or_zero();
+        let jitter = Vec3::new(fastrand::f32() - 0.5, fastrand::f32() - 0.5, fastrand::f32() - 0.5) * 0.6;
+        let spin = Vec3::new(fastrand::f32() - 0.5, fastrand::f32() - 0.5, fastrand::f32() - 0.5) * 40.0;
+        let rotation = Quat::from_rotation_arc(Vec3::Z, forward.try_normalize().unwrap_or(Vec3::Z));
+        self.flying.push(Casing {
+            position,
+            previous: position,
+            velocity: right * 2.2 + Vec3::Y * 1.8 - forward * 0.3 + jitter,
+            rotation,
+            spin,
+            flight: 0.0,
+            size,
+            color,
+        });
+    }
+
+    // limit 是地上最多留几个
+    pub fn update(&mut self, dt: f32, colliders: &[WallCollider], limit: usize) {
+        let mut landed = Vec::new();
+        self.flying.retain_mut(|casing| {
+            casing.previous = casing.position;
+            casing.flight += dt;
+            casing.velocity.y -= GRAVITY * dt;
+            let mut position = casing.position + casing.velocity * dt;
+            // 碰到墙时横向速度反弹
+            for collider in colliders {
+                let resolved = collider.resolve_collision(position, RADIUS);
+                if resolved != position {
+                    casing.velocity.x *= -BOUNCE;
+                    casing.velocity.z *= -BOUNCE;
+                    position = resolved;
+                }
+            }
+            casing.rotation = (Quat::from_scaled_axis(casing.spin * dt) * casing.rotation).normalize();
+            if position.y <= 0.0 {
+                position.y = 0.0;
+                if -casing.velocity.y < REST_SPEED || casing.flight > MAX_FLIGHT {
+                    casing.position = position;
+                    casing.previous = position;
+                    // 躺平（长边水平），保留绕竖直轴的朝向
+                    let (yaw, _, _) = casing.rotation.to_euler(glam::EulerRot::YXZ);
+                    casing.rotation = Quat::from_rotation_y(yaw);
+                    landed.push(*casing);
+                    return false;
+                }
+                casing.velocity.y = -casing.velocity.y * BOUNCE;
+                casing.velocity.x *= FRICTION;
+                casing.velocity.z *= FRICTION;
+                casing.spin *= FRICTION;
+            }
+            casing.position = position;
+            true
+        });
+        self.resting.extend(landed);
+        while self.resting.len() > limit {
+            self.resting.pop_front();
+        }
+    }
+
+    pub fn boxes(&self, alpha: f32, out: &mut Vec<BoxInstance>) {
+        for casing in &self.flying {
+            out.push(BoxInstance { transform: casing.transform(casing.previous.lerp(casing.position, alpha)), color: casing.color });
+        }
+        for casing in &self.resting {
+            out.push(BoxInstance { transform: casing.transform(casing.position), color: casing.color });
+        }
+    }
+}
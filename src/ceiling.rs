@@ -0,0 +1,159 @@
+use glam::Vec3;
+
+use crate::collision::{Aabb, WallCollider};
+use crate::geometry::{self, TexCoords};
+use crate::gpu_memory;
+use crate::map::{self, Map};
+use crate::mesh::MeshBuilder;
+use crate::model::Model;
+
+// 天花板上的杂物：沿车道走的管道、沿长墙挂的通风管、灯位上吊着的日光灯。
+// 全部由几何构建函数按地图生成，合成一个顶点着色的模型。跳起来头顶能碰到的才加碰撞体，
+// 更高的只是装饰，不参与移动和射击的检测
+
+// 跳到最高点时头顶的高度（眼睛高度 1.8，跳起约 1.6 米，再加上头顶）
+const HEAD_REACH: f32 = 3.5;
+
+// 管道：离车道中线的距离、中心高度、半径和颜色，在通风管上面穿过
+const PIPES: [(f32, f32, f32, [f32; 3]); 2] = [
+    (0.3, 3.87, 0.08, [0.42, 0.5, 0.44]),
+    (-0.3, 3.9, 0.05, [0.55, 0.28, 0.2]),
+];
+// 管道每隔这么远有一个吊卡
+const PIPE_HANGER_SPACING: f32 = 3.0;
+
+// 通风管：只沿着足够长的墙，截面宽和离墙线的距离
+const DUCT_MIN_LENGTH: f32 = 10.0;
+const DUCT_WIDTH: f32 = 0.8;
+const DUCT_OFFSET: f32 = 0.9;
+const DUCT_BOTTOM: f32 = 3.35;
+const DUCT_TOP: f32 = 3.75;
+const DUCT_COLOR: [f32; 3] = [0.7, 0.72, 0.74];
+
+// 日光灯：灯罩的长、高、宽和底面高度，灯管在灯罩下面
+const FIXTURE_SIZE: Vec3 = Vec3::new(1.2, 0.08, 0.22);
+const FIXTURE_BOTTOM: f32 = 3.3;
+const FIXTURE_COLOR: [f32; 3] = [0.35, 0.36, 0.38];
+const TUBE_COLOR: [f32; 3] = [1.0, 1.0, 0.92];
+
+const ROD_RADIUS: f32 = 0.015;
+const ROD_COLOR: [f32; 3] = [0.3, 0.3, 0.3];
+
+pub fn build(device: &wgpu::Device, map: &Map) -> (Option<Model>, Vec<WallCollider>) {
+    let mut mesh = MeshBuilder::new();
+    let mut colliders = Vec::new();
+    for run in map.lane_runs() {
+        pipes(&mut mesh, Vec3::from(run.start), Vec3::from(run.end));
+    }
+    for segment in map.wall_segments() {
+        let (start, end) = (Vec3::from(segment.start), Vec3::from(segment.end));
+        if (end - start).length() >= DUCT_MIN_LENGTH {
+            if let Some(side) = floor_side(map, start, end) {
+                duct(&mut mesh, &mut colliders, start + side * DUCT_OFFSET, end + side * DUCT_OFFSET);
+            }
+        }
+    }
+    for (col, row) in map.light_cells() {
+        fixture(&mut mesh, &mut colliders, map.cell_center(col, row));
+    }
+    if mesh.vertices.is_empty() {
+        return (None, colliders);
+    }
+    (Some(mesh.build(device, "ceiling_props", [0.0, 0.0, 0.0], false, gpu_memory::Category::Map)), colliders)
+}
+
+// 悬挂物体的碰撞体：底面够低才加
+fn hanging_collider(colliders: &mut Vec<WallCollider>, start: Vec3, end: Vec3, width: f32, bottom: f32, top: f32) {
+    if bottom >= HEAD_REACH {
+        return;
+    }
+    // 碰撞体从线段沿法向量延伸一个厚度，线段挪到一侧，碰撞体正好以 start-end 为中线
+    let tangent = (end - start).normalize_or_zero();
+    let normal = Vec3::new(-tangent.z, 0.0, tangent.x);
+    let shift = -normal * (width / 2.0);
+    colliders.push(WallCollider::hanging((start + shift).to_array(), (end + shift).to_array(), bottom, top, width));
+}
+
+fn at_height(position: Vec3, y: f32) -> Vec3 {
+    Vec3::new(position.x, y, position.z)
+}
+
+// 从 position 往上到天花板的细吊杆
+fn rod(mesh: &mut MeshBuilder, position: Vec3) {
+    geometry::cylinder(mesh, position, at_height(position, map::WALL_HEIGHT), ROD_RADIUS, 4, ROD_COLOR);
+}
+
+fn pipes(mesh: &mut MeshBuilder, start: Vec3, end: Vec3) {
+    let tangent = (end - start).normalize_or_zero();
+    let side = tangent.cross(Vec3::Y);
+    // 两端各伸出半个格子，接到墙上
+    let (start, end) = (start - tangent * (map::CELL_SIZE / 2.0), end + tangent * (map::CELL_SIZE / 2.0));
+    for (offset, height, radius, color) in PIPES {
+        let (a, b) = (at_height(start, height) + side * offset, at_height(end, height) + side * offset);
+        geometry::cylinder(mesh, a, b, radius, 8, color);
+        let hangers = ((b - a).length() / PIPE_HANGER_SPACING) as u32;
+        for i in 1..=hangers {
+            let position = a + tangent * (i as f32 * PIPE_HANGER_SPACING - PIPE_HANGER_SPACING / 2.0);
+            rod(mesh, position + Vec3::Y * radius);
+        }
+    }
+}
+
+// 墙线的哪一边是地面：两边都能走的内墙取法向量一侧，都不能走的返回 None
+fn floor_side(map: &Map, start: Vec3, end: Vec3) -> Option<Vec3> {
+    let tangent = (end - start).normalize_or_zero();
+    let normal = Vec3::new(-tangent.z, 0.0, tangent.x);
+    let middle = (start + end) / 2.0;
+    [normal, -normal].into_iter().find(|side| {
+        map.world_to_cell(middle + *side * (DUCT_OFFSET + map::CELL_SIZE / 2.0))
+            .is_some_and(|(col, row)| map.cell(col, row).is_walkable())
+    })
+}
+
+fn duct(mesh: &mut MeshBuilder, colliders: &mut Vec<WallCollider>, start: Vec3, end: Vec3) {
+    let tangent = (end - start).normalize_or_zero();
+    let side = tangent.cross(Vec3::Y);
+    let height = DUCT_TOP - DUCT_BOTTOM;
+    let center = (start + end) / 2.0 + Vec3::Y * (DUCT_BOTTOM + height / 2.0);
+    let transform = geometry::box_transform(center, end - start, Vec3::Y * height, side * DUCT_WIDTH);
+    geometry::cuboid(mesh, transform, DUCT_COLOR, 0.0, TexCoords::NONE);
+    // 接缝处的箍带，每两米一圈
+    let bands = ((end - start).length() / 2.0) as u32;
+    for i in 1..bands {
+        let band = at_height(start, center.y) + tangent * (i as f32 * 2.0);
+        let transform = geometry::box_transform(band, tangent * 0.06, Vec3::Y * (height + 0.02), side * (DUCT_WIDTH + 0.02));
+        geometry::cuboid(mesh, transform, [0.55, 0.56, 0.58], 0.0, TexCoords::NONE);
+    }
+    hanging_collider(colliders, start, end, DUCT_WIDTH, DUCT_BOTTOM, DUCT_TOP);
+}
+
+// 灯位格子上日光灯灯管的中心，灯管的半长
+pub fn tube_center(cell_center: Vec3) -> Vec3 {
+    at_height(cell_center, FIXTURE_BOTTOM - 0.03)
+}
+
+pub const TUBE_HALF_LENGTH: f32 = FIXTURE_SIZE.x / 2.0 - 0.05;
+pub const TUBE_RADIUS: f32 = 0.03;
+
+// 灯罩和灯管的包围盒，打灯用
+pub fn fixture_bounds(cell_center: Vec3) -> Aabb {
+    let half = FIXTURE_SIZE / 2.0;
+    let center = at_height(cell_center, FIXTURE_BOTTOM + half.y);
+    Aabb { min: center - half - Vec3::Y * (TUBE_RADIUS * 2.0), max: center + half }
+}
+
+// 沿 x 方向的日光灯：灯罩、下面发亮的灯管和两根吊杆
+fn fixture(mesh: &mut MeshBuilder, colliders: &mut Vec<WallCollider>, cell_center: Vec3) {
+    let center = at_height(cell_center, FIXTURE_BOTTOM + FIXTURE_SIZE.y / 2.0);
+    let housing = geometry::box_transform(center, Vec3::X * FIXTURE_SIZE.x, Vec3::Y * FIXTURE_SIZE.y, Vec3::Z * FIXTURE_SIZE.z);
+    geometry::cuboid(mesh, housing, FIXTURE_COLOR, 0.0, TexCoords::NONE);
+    let tube = Vec3::X * TUBE_HALF_LENGTH;
+    let tube_center = tube_center(cell_center);
+    geometry::cylinder(mesh, tube_center - tube, tube_center + tube, TUBE_RADIUS, 6, TUBE_COLOR);
+    for end in [-1.0, 1.0] {
+        let position = center + Vec3::X * (end * FIXTURE_SIZE.x * 0.4) + Vec3::Y * (FIXTURE_SIZE.y / 2.0);
+        rod(mesh, position);
+    }
+    let half = Vec3::X * (FIXTURE_SIZE.x / 2.0);
+    hanging_collider(colliders, cell_center - half, cell_center + half, FIXTURE_SIZE.z, FIXTURE_BOTTOM - 0.06, FIXTURE_BOTTOM + FIXTURE_SIZE.y);
+}
@@ -0,0 +1,225 @@
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+use instant::Instant;
+
+use crate::ui::UiBatch;
+
+// 聊天消息显示多久后开始淡出，以及淡出用时
+const MESSAGE_VISIBLE: Duration = Duration::from_secs(8);
+const MESSAGE_FADE: Duration = Duration::from_secs(2);
+const MAX_HISTORY: usize = 50;
+const VISIBLE_LINES: usize = 8;
+const MAX_INPUT_LEN: usize = 120;
+
+const TEXT_SCALE: f32 = 2.0;
+const NAME_COLOR: [f32; 3] = [1.0, 0.85, 0.2];
+const TEXT_COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+const SYSTEM_COLOR: [f32; 3] = [0.6, 0.8, 1.0];
+
+pub struct ChatMessage {
+    pub sender: String,
+    pub text: String,
+    received: Instant,
+    system: bool,
+}
+
+// 聊天过滤钩子：返回 None 表示丢弃整条消息
+pub trait ChatFilter {
+    fn filter(&self, text: &str) -> Option<String>;
+}
+
+// 简单的屏蔽词过滤，命中的单词替换为星号
+pub struct WordFilter {
+    words: Vec<String>,
+}
+
+impl WordFilter {
+    pub fn new(words: &[&str]) -> Self {
+        Self {
+            words: words.iter().map(|w| w.to_lowercase()).collect(),
+        }
+    }
+}
+
+impl ChatFilter for WordFilter {
+    fn filter(&self, text: &str) -> Option<String> {
+        let filtered: Vec<String> = text
+            .split(' ')
+            .map(|word| {
+                let bare: String = word.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase();
+                if !bare.is_empty() && self.words.contains(&bare) {
+                    "*".repeat(word.chars().count())
+                } else {
+                    word.to_string()
+                }
+            })
+            .collect();
+        Some(filtered.join(" "))
+    }
+}
+
+// 输入框提交后的结果
+pub enum ChatCommand {
+    Send(String),
+    Mute(String),
+    Unmute(String),
+    // 切换到 maps/ 下的另一张地图
+    LoadMap(String),
+    // 打开或关闭 AI 调试面板
+    Inspect,
+}
+
+pub struct Chat {
+    pub open: bool,
+    input: String,
+    // 按 T 打开聊天时会紧跟一个 't' 字符事件，需要吞掉
+    skip_next_char: bool,
+    history: VecDeque<ChatMessage>,
+    muted: HashSet<String>,
+    filters: Vec<Box<dyn ChatFilter>>,
+}
+
+impl Chat {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            input: String::new(),
+            skip_next_char: false,
+            history: VecDeque::new(),
+            muted: HashSet::new(),
+            filters: vec![Box::new(WordFilter::new(&["fuck", "shit", "cunt", "bitch"]))],
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.open = true;
+        self.skip_next_char = true;
+        self.input.clear();
+    }
+
+    pub fn cancel(&mut self) {
+        self.open = false;
+        self.input.clear();
+    }
+
+    pub fn handle_char(&mut self, c: char) {
+        if std::mem::take(&mut self.skip_next_char) {
+            return;
+        }
+        if !c.is_control() && self.input.chars().count() < MAX_INPUT_LEN {
+            self.input.push(c);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+    }
+
+    // 按回车提交，以 / 开头的是命令
+    pub fn submit(&mut self) -> Option<ChatCommand> {
+        self.open = false;
+        let input = std::mem::take(&mut self.input);
+        let input = input.trim();
+        if input.is_empty() {
+            return None;
+        }
+        if let Some(name) = input.strip_prefix("/mute ") {
+            return Some(ChatCommand::Mute(name.trim().to_string()));
+        }
+        if let Some(name) = input.strip_prefix("/unmute ") {
+            return Some(ChatCommand::Unmute(name.trim().to_string()));
+        }
+        if input == "/inspect" {
+            return Some(ChatCommand::Inspect);
+        }
+        if let Some(name) = input.strip_prefix("/map ") {
+            return Some(ChatCommand::LoadMap(name.trim().to_string()));
+        }
+        Some(ChatCommand::Send(input.to_string()))
+    }
+
+    pub fn mute(&mut self, name: &str) {
+        self.muted.insert(name.to_string());
+        self.push_system(format!("muted {}", name));
+    }
+
+    pub fn unmute(&mut self, name: &str) {
+        if self.muted.remove(name) {
+            self.push_system(format!("unmuted {}", name));
+        }
+    }
+
+    // 收到一条玩家消息（本地或网络），经过静音和过滤后加入历史
+    pub fn receive(&mut self, sender: &str, text: &str) {
+        if self.muted.contains(sender) {
+            return;
+        }
+        let mut text = text.to_string();
+        for filter in &self.filters {
+            match filter.filter(&text) {
+                Some(filtered) => text = filtered,
+                None => return,
+            }
+        }
+        self.push(ChatMessage { sender: sender.to_string(), text, received: Instant::now(), system: false });
+    }
+
+    // 系统消息（加入/离开等），不经过过滤
+    pub fn push_system(&mut self, text: String) {
+        self.push(ChatMessage { sender: String::new(), text, received: Instant::now(), system: true });
+    }
+
+    fn push(&mut self, message: ChatMessage) {
+        self.history.push_back(message);
+        while self.history.len() > MAX_HISTORY {
+            self.history.pop_front();
+        }
+    }
+
+    // 消息的透明度：打开聊天时全部可见，否则按时间淡出
+    fn message_alpha(&self, message: &ChatMessage, now: Instant) -> f32 {
+        if self.open {
+            return 1.0;
+        }
+        let age = now.duration_since(message.received);
+        if age <= MESSAGE_VISIBLE {
+            1.0
+        } else {
+            let fade = (age - MESSAGE_VISIBLE).as_secs_f32() / MESSAGE_FADE.as_secs_f32();
+            (1.0 - fade).max(0.0)
+        }
+    }
+
+    pub fn draw(&self, ui: &mut UiBatch) {
+        let now = Instant::now();
+        let line = UiBatch::line_height(TEXT_SCALE);
+        let x = 10.0;
+        let input_y = ui.height() - 120.0;
+
+        if self.open {
+            let width = 640.0f32.min(ui.width() - 20.0);
+            ui.rect(x - 4.0, input_y - 4.0, width, line + 4.0, [0.0, 0.0, 0.0, 0.6]);
+            ui.text(x, input_y, TEXT_SCALE, [1.0, 1.0, 1.0, 1.0], &format!("say: {}_", self.input));
+        }
+
+        let mut y = input_y - line - 4.0;
+        for message in self.history.iter().rev().take(VISIBLE_LINES) {
+            let alpha = self.message_alpha(message, now);
+            if alpha <= 0.0 {
+                continue;
+            }
+            if message.system {
+                let [r, g, b] = SYSTEM_COLOR;
+                ui.text(x, y, TEXT_SCALE, [r, g, b, alpha], &message.text);
+            } else {
+                let [r, g, b] = NAME_COLOR;
+                let name = format!("{}: ", message.sender);
+                ui.text(x, y, TEXT_SCALE, [r, g, b, alpha], &name);
+                let [r, g, b] = TEXT_COLOR;
+                ui.text(x + UiBatch::text_width(&name, TEXT_SCALE), y, TEXT_SCALE, [r, g, b, alpha], &message.text);
+            }
+            y -= line;
+        }
+    }
+}
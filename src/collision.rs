@@ -1,166 +1,537 @@
-use glam::Vec3;
-
-// 墙体碰撞信息结构体
-pub struct WallCollider {
-    // 墙体的起点和终点坐标
-    start: Vec3,
-    end: Vec3,
-    // 墙体的高度
-    height: f32,
-    // 墙体的厚度
-    thickness: f32,
-    // 墙体的法向量（垂直于墙面的方向）
-    normal: Vec3,
-}
-
-impl WallCollider {
-    // 从墙体的起点和终点创建碰撞器
-    pub fn new(start: [f32; 3], end: [f32; 3], height: f32, thickness: f32) -> Self {
-        // 计算墙体方向和长度
-        let dx = end[0] - start[0];
-        let dz = end[2] - start[2];
-        
-        // 计算墙体的法向量（垂直于墙面）
-        let length = (dx*dx + dz*dz).sqrt();
-        let nx = -dz / length;
-        let nz = dx / length;
-        
-        Self {
-            start: Vec3::new(start[0], start[1], start[2]),
-            end: Vec3::new(end[0], end[1], end[2]),
-            height,
-            thickness,
-            normal: Vec3::new(nx, 0.0, nz),
-        }
-    }
-    
-    // 检测点是否与墙体碰撞
-    pub fn check_collision(&self, position: Vec3, radius: f32) -> bool {
-        // 如果点的高度超过墙体高度，则不碰撞
-        if position.y > self.height {
-            return false;
-        }
-        
-        // 计算点到墙体线段的最近点
-        let wall_vec = Vec3::new(
-            self.end.x - self.start.x,
-            0.0,
-            self.end.z - self.start.z
-        );
-        let wall_length_squared = wall_vec.length_squared();
-        
-        // 计算点到墙体起点的向量
-        let point_to_start = Vec3::new(
-            position.x - self.start.x,
-            0.0,
-            position.z - self.start.z
-        );
-        
-        // 计算投影比例（点在墙体线段上的投影位置）
-        let t = (point_to_start.dot(wall_vec) / wall_length_squared).clamp(0.0, 1.0);
-        
-        // 计算墙体线段上的最近点
-        let closest_point = Vec3::new(
-            self.start.x + t * wall_vec.x,
-            0.0,
-            self.start.z + t * wall_vec.z
-        );
-        
-        // 计算点到墙体的距离向量
-        let distance_vec = Vec3::new(
-            position.x - closest_point.x,
-            0.0,
-            position.z - closest_point.z
-        );
-        
-        // 计算点到墙体的距离
-        let distance = distance_vec.length();
-        
-        // 检查点是否在墙体的两侧
-        let dot_product = distance_vec.dot(self.normal);
-        
-        // 如果点在墙体正面且距离小于半径，或者点在墙体背面且距离小于(半径+墙体厚度)，则发生碰撞
-        if (dot_product >= 0.0 && distance < radius) || 
-           (dot_product < 0.0 && distance < radius + self.thickness) {
-            return true;
-        }
-        
-        false
-    }
-    
-    // 计算碰撞响应（返回调整后的位置）
-    pub fn resolve_collision(&self, position: Vec3, radius: f32) -> Vec3 {
-        // 如果没有碰撞，直接返回原位置
-        if !self.check_collision(position, radius) {
-            return position;
-        }
-        
-        // 计算点到墙体线段的最近点
-        let wall_vec = Vec3::new(
-            self.end.x - self.start.x,
-            0.0,
-            self.end.z - self.start.z
-        );
-        let wall_length_squared = wall_vec.length_squared();
-        
-        // 计算点到墙体起点的向量
-        let point_to_start = Vec3::new(
-            position.x - self.start.x,
-            0.0,
-            position.z - self.start.z
-        );
-        
-        // 计算投影比例
-        let t = (point_to_start.dot(wall_vec) / wall_length_squared).clamp(0.0, 1.0);
-        
-        // 计算墙体线段上的最近点
-        let closest_point = Vec3::new(
-            self.start.x + t * wall_vec.x,
-            0.0,
-            self.start.z + t * wall_vec.z
-        );
-        
-        // 计算点到墙体的距离向量
-        let distance_vec = Vec3::new(
-            position.x - closest_point.x,
-            0.0,
-            position.z - closest_point.z
-        );
-        
-        // 计算点到墙体的距离
-        let distance = distance_vec.length();
-        
-        // 检查点是否在墙体的两侧
-        let dot_product = distance_vec.dot(self.normal);
-        
-        // 根据点在墙体的哪一侧来调整位置
-        if dot_product >= 0.0 {
-            // 点在墙体正面
-            if distance < radius {
-                // 计算需要移动的距离
-                let move_distance = radius - distance;
-                // 沿着距离向量的方向移动
-                let move_dir = distance_vec.normalize();
-                return position + move_dir * move_distance;
-            }
-        } else {
-            // 点在墙体背面
-            if distance < radius + self.thickness {
-                // 计算需要移动的距离
-                let move_distance = radius + self.thickness - distance;
-                // 沿着距离向量的方向移动
-                let move_dir = distance_vec.normalize();
-                return position + move_dir * move_distance;
-            }
-        }
-        
-        position
-    }
-}
-
-// 创建墙体碰撞器的辅助函数，直接从create_wall函数的参数创建
-pub fn create_wall_collider(start: [f32; 3], end: [f32; 3], height: f32) -> WallCollider {
-    // 使用与create_wall函数相同的墙体厚度
-    let thickness = 0.3; // 30cm thickness
-    WallCollider::new(start, end, height, thickness)
-}
\ No newline at end of file
+use glam::Vec3;
+
+// 墙体碰撞信息结构体
+#[derive(Clone)]
+pub struct WallCollider {
+    // 墙体的起点和终点坐标
+    start: Vec3,
+    end: Vec3,
+    // 墙体的高度（顶面）
+    height: f32,
+    // 底面的高度，悬挂在空中的物体（管道、灯具）不为 0
+    bottom: f32,
+    // 墙体的厚度
+    thickness: f32,
+    // 墙体的法向量（垂直于墙面的方向）
+    normal: Vec3,
+}
+
+impl WallCollider {
+    // 从墙体的起点和终点创建碰撞器
+    pub fn new(start: [f32; 3], end: [f32; 3], height: f32, thickness: f32) -> Self {
+        // 计算墙体方向和长度
+        let dx = end[0] - start[0];
+        let dz = end[2] - start[2];
+        
+        // 计算墙体的法向量（垂直于墙面）
+        let length = (dx*dx + dz*dz).sqrt();
+        let nx = -dz / length;
+        let nz = dx / length;
+        
+        Self {
+            start: Vec3::new(start[0], start[1], start[2]),
+            end: Vec3::new(end[0], end[1], end[2]),
+            height,
+            bottom: 0.0,
+            thickness,
+            normal: Vec3::new(nx, 0.0, nz),
+        }
+    }
+
+    // 悬挂的物体：只在 bottom 到 top 之间挡路、挡子弹
+    pub fn hanging(start: [f32; 3], end: [f32; 3], bottom: f32, top: f32, thickness: f32) -> Self {
+        Self { bottom, ..Self::new(start, end, top, thickness) }
+    }
+    
+    // 竖直的方柱（柱子）：中心在 center，边长 2 * half
+    pub fn square(center: Vec3, half: f32, height: f32) -> Self {
+        // 沿 +x 的线段，法向量为 +z，碰撞体向 +z 延伸一个厚度
+        let start = center + Vec3::new(-half, 0.0, -half);
+        let end = center + Vec3::new(half, 0.0, -half);
+        Self::new(start.to_array(), end.to_array(), height, half * 2.0)
+    }
+
+    // 碰撞体占据的长方体，作用于中心在原点的单位立方体（调试显示用）：
+    // 和墙的模型一样，从墙格子的线沿法向量延伸一个厚度
+    pub fn box_transform(&self) -> glam::Mat4 {
+        let along = self.end - self.start;
+        let (start, end) = (Vec3::new(self.start.x, self.bottom, self.start.z), Vec3::new(self.end.x, self.bottom, self.end.z));
+        let size = self.height - self.bottom;
+        let center = (start + end) / 2.0 + self.normal * (self.thickness / 2.0) + Vec3::Y * (size / 2.0);
+        crate::geometry::box_transform(center, along, Vec3::Y * size, self.normal * self.thickness)
+    }
+
+    // 检测点是否与墙体碰撞
+    pub fn check_collision(&self, position: Vec3, radius: f32) -> bool {
+        // 如果点的高度超过墙体高度，则不碰撞；悬挂的物体把位置当作半径为 radius 的球，头顶碰到才算
+        if position.y > self.height || position.y + radius < self.bottom {
+            return false;
+        }
+        
+        // 计算点到墙体线段的最近点
+        let wall_vec = Vec3::new(
+            self.end.x - self.start.x,
+            0.0,
+            self.end.z - self.start.z
+        );
+        let wall_length_squared = wall_vec.length_squared();
+        
+        // 计算点到墙体起点的向量
+        let point_to_start = Vec3::new(
+            position.x - self.start.x,
+            0.0,
+            position.z - self.start.z
+        );
+        
+        // 计算投影比例（点在墙体线段上的投影位置）
+        let t = (point_to_start.dot(wall_vec) / wall_length_squared).clamp(0.0, 1.0);
+        
+        // 计算墙体线段上的最近点
+        let closest_point = Vec3::new(
+            self.start.x + t * wall_vec.x,
+            0.0,
+            self.start.z + t * wall_vec.z
+        );
+        
+        // 计算点到墙体的距离向量
+        let distance_vec = Vec3::new(
+            position.x - closest_point.x,
+            0.0,
+            position.z - closest_point.z
+        );
+        
+        // 计算点到墙体的距离
+        let distance = distance_vec.length();
+        
+        // 检查点是否在墙体的两侧：墙体沿法向量有厚度，法向量反方向的一侧是正面
+        let dot_product = distance_vec.dot(self.normal);
+        
+        // 如果点在墙体正面且距离小于半径，或者点在墙体背面（里面）且距离小于(半径+墙体厚度)，则发生碰撞
+        if (dot_product <= 0.0 && distance < radius) || 
+           (dot_product > 0.0 && distance < radius + self.thickness) {
+            return true;
+        }
+        
+        false
+    }
+    
+    // 计算碰撞响应（返回调整后的位置）
+    pub fn resolve_collision(&self, position: Vec3, radius: f32) -> Vec3 {
+        // 如果没有碰撞，直接返回原位置
+        if !self.check_collision(position, radius) {
+            return position;
+        }
+        
+        // 计算点到墙体线段的最近点
+        let wall_vec = Vec3::new(
+            self.end.x - self.start.x,
+            0.0,
+            self.end.z - self.start.z
+        );
+        let wall_length_squared = wall_vec.length_squared();
+        
+        // 计算点到墙体起点的向量
+        let point_to_start = Vec3::new(
+            position.x - self.start.x,
+            0.0,
+            position.z - self.start.z
+        );
+        
+        // 计算投影比例
+        let t = (point_to_start.dot(wall_vec) / wall_length_squared).clamp(0.0, 1.0);
+        
+        // 计算墙体线段上的最近点
+        let closest_point = Vec3::new(
+            self.start.x + t * wall_vec.x,
+            0.0,
+            self.start.z + t * wall_vec.z
+        );
+        
+        // 计算点到墙体的距离向量
+        let distance_vec = Vec3::new(
+            position.x - closest_point.x,
+            0.0,
+            position.z - closest_point.z
+        );
+        
+        // 计算点到墙体的距离
+        let distance = distance_vec.length();
+        
+        // 检查点是否在墙体的两侧（和 check_collision 一样，法向量反方向的一侧是正面）
+        let dot_product = distance_vec.dot(self.normal);
+        
+        // 根据点在墙体的哪一侧来调整位置
+        if dot_product <= 0.0 {
+            // 点在墙体正面
+            if distance < radius {
+                // 计算需要移动的距离
+                let move_distance = radius - distance;
+                // 在墙体的长度范围内沿法向量的反方向移动（贴着线时距离向量太短，方向算不准），
+                // 在两端之外沿着距离向量的方向移动
+                let move_dir = if t > 0.0 && t < 1.0 { -self.normal } else { distance_vec.try_normalize().unwrap_or(-self.normal) };
+                return position + move_dir * move_distance;
+            }
+        } else {
+            // 点在墙体背面（或者已经进到墙体里面）
+            if distance < radius + self.thickness {
+                // 在墙体的长度范围内并且离正面更近时从正面推出去，不会一下穿过整面墙
+                let through_front = distance + radius;
+                if t > 0.0 && t < 1.0 && through_front < radius + self.thickness - distance {
+                    return position - self.normal * through_front;
+                }
+                // 计算需要移动的距离
+                let move_distance = radius + self.thickness - distance;
+                // 和正面一样，长度范围内沿法向量、两端之外沿着距离向量的方向移动
+                let move_dir = if t > 0.0 && t < 1.0 { self.normal } else { distance_vec.normalize() };
+                return position + move_dir * move_distance;
+            }
+        }
+        
+        position
+    }
+}
+
+// 创建墙体碰撞器的辅助函数，直接从create_wall函数的参数创建
+pub fn create_wall_collider(start: [f32; 3], end: [f32; 3], height: f32) -> WallCollider {
+    // 使用与create_wall函数相同的墙体厚度
+    let thickness = 0.3; // 30cm thickness
+    WallCollider::new(start, end, height, thickness)
+}
+
+
+// 射线与墙体的交点：进入和离开墙体的距离及对应表面的法向量
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    pub distance: f32,
+    pub exit_distance: f32,
+    pub normal: Vec3,
+    pub exit_normal: Vec3,
+}
+
+impl WallCollider {
+    // 射线与墙体（起点-终点为正面，沿法向量方向有厚度的长方体）求交
+    // dir 需要是单位向量，只返回在 max_distance 以内进入墙体的交点
+    pub fn raycast(&self, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<RayHit> {
+        let wall_vec = Vec3::new(self.end.x - self.start.x, 0.0, self.end.z - self.start.z);
+        let length = wall_vec.length();
+        if length <= f32::EPSILON {
+            return None;
+        }
+        let tangent = wall_vec / length;
+
+        // 转换到墙体的局部坐标：u 沿墙体方向，v 沿法向量，y 为高度
+        let rel = origin - self.start;
+        let axes = [tangent, self.normal, Vec3::Y];
+        let local_origin = [rel.dot(tangent), rel.dot(self.normal), origin.y - self.bottom];
+        let local_dir = [dir.dot(tangent), dir.dot(self.normal), dir.y];
+        let extent = [length, self.thickness, self.height - self.bottom];
+
+        // slab 方法求射线进入和离开长方体的参数，同时记录对应的表面
+        let mut t_enter = 0.0f32;
+        let mut t_exit = f32::INFINITY;
+        // 起点在墙体内部时没有进入面，法向量取射线反方向
+        let mut normal = -dir;
+        let mut exit_normal = dir;
+        for i in 0..3 {
+            if local_dir[i].abs() < 1e-6 {
+                if local_origin[i] < 0.0 || local_origin[i] > extent[i] {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (0.0 - local_origin[i]) / local_dir[i];
+            let t1 = (extent[i] - local_origin[i]) / local_dir[i];
+            // 沿正方向前进时从 0 面进入、从 extent 面离开
+            let sign = local_dir[i].signum();
+            if t0.min(t1) > t_enter {
+                t_enter = t0.min(t1);
+                normal = axes[i] * -sign;
+            }
+            if t0.max(t1) < t_exit {
+                t_exit = t0.max(t1);
+                exit_normal = axes[i] * sign;
+            }
+            if t_enter > t_exit {
+                return None;
+            }
+        }
+
+        if t_enter > max_distance {
+            return None;
+        }
+        Some(RayHit { distance: t_enter, exit_distance: t_exit, normal, exit_normal })
+    }
+}
+
+// 射线穿过的所有墙体，按距离从近到远排序
+pub fn raycast_all(colliders: &[WallCollider], origin: Vec3, dir: Vec3, max_distance: f32) -> Vec<RayHit> {
+    let mut hits: Vec<RayHit> = colliders
+        .iter()
+        .filter_map(|collider| collider.raycast(origin, dir, max_distance))
+        .collect();
+    hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+    hits
+}
+
+impl WallCollider {
+    // 球沿射线扫过时第一次碰到墙体的距离（球心走过的距离），起点就碰到时为 0。
+    // 把墙体的长方体向外扩大一个半径再做射线求交，棱角处比真实的圆角稍微保守一点
+    pub fn spherecast(&self, origin: Vec3, dir: Vec3, radius: f32, max_distance: f32) -> Option<f32> {
+        let wall_vec = Vec3::new(self.end.x - self.start.x, 0.0, self.end.z - self.start.z);
+        let length = wall_vec.length();
+        if length <= f32::EPSILON {
+            return None;
+        }
+        let tangent = wall_vec / length;
+        // 和 raycast 一样，长方体从起点-终点的线沿法向量延伸一个厚度
+        let start = self.start - tangent * radius - self.normal * radius;
+        let end = self.end + tangent * radius - self.normal * radius;
+        let inflated = Self {
+            start,
+            end,
+            height: self.height + radius,
+            bottom: self.bottom - radius,
+            thickness: self.thickness + radius * 2.0,
+            normal: self.normal,
+        };
+        inflated.raycast(origin, dir, max_distance).map(|hit| hit.distance)
+    }
+}
+
+// 球扫过所有墙体时最先碰到的距离，没有碰到时为 None
+pub fn spherecast(colliders: &[WallCollider], origin: Vec3, dir: Vec3, radius: f32, max_distance: f32) -> Option<f32> {
+    colliders
+        .iter()
+        .filter_map(|collider| collider.spherecast(origin, dir, radius, max_distance))
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+// 统计两点之间隔着多少面墙（用于声音遮挡等）
+pub fn count_occluders(colliders: &[WallCollider], from: Vec3, to: Vec3) -> usize {
+    let delta = to - from;
+    let distance = delta.length();
+    if distance <= f32::EPSILON {
+        return 0;
+    }
+    raycast_all(colliders, from, delta / distance, distance).len()
+}
+
+// 轴对齐包围盒（敌人等的命中判定）
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    // 返回射线进入包围盒的距离（起点在内部时为 0）
+    pub fn raycast(&self, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<f32> {
+        self.raycast_normal(origin, dir, max_distance).map(|(distance, _)| distance)
+    }
+
+    // 同 raycast，另外返回进入的那个面的法向量；起点在内部时为 -dir
+    pub fn raycast_normal(&self, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<(f32, Vec3)> {
+        let mut t_enter = 0.0f32;
+        let mut t_exit = max_distance;
+        let mut normal = -dir;
+        for i in 0..3 {
+            if dir[i].abs() < 1e-6 {
+                if origin[i] < self.min[i] || origin[i] > self.max[i] {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (self.min[i] - origin[i]) / dir[i];
+            let t1 = (self.max[i] - origin[i]) / dir[i];
+            if t0.min(t1) > t_enter {
+                t_enter = t0.min(t1);
+                normal = Vec3::ZERO;
+                normal[i] = -dir[i].signum();
+            }
+            t_exit = t_exit.min(t0.max(t1));
+            if t_enter > t_exit {
+                return None;
+            }
+        }
+        Some((t_enter, normal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 浮点误差的容许范围
+    const EPSILON: f32 = 1e-4;
+
+    // 沿 +x 的墙：法向量 +z，正面在 z = 0 朝 -z，墙体占 z = 0..0.3（和墙的模型一样）
+    fn wall() -> WallCollider {
+        WallCollider::new([0.0, 0.0, 0.0], [4.0, 0.0, 0.0], 3.0, 0.3)
+    }
+
+    #[test]
+    fn normal_points_left_of_the_wall_direction() {
+        assert!((wall().normal - Vec3::Z).length() < EPSILON);
+        let reversed = WallCollider::new([4.0, 0.0, 0.0], [0.0, 0.0, 0.0], 3.0, 0.3);
+        assert!((reversed.normal - Vec3::NEG_Z).length() < EPSILON);
+        let along_z = WallCollider::new([0.0, 0.0, 0.0], [0.0, 0.0, 2.0], 3.0, 0.3);
+        assert!((along_z.normal - Vec3::NEG_X).length() < EPSILON);
+    }
+
+    #[test]
+    fn thickness_extends_along_the_normal() {
+        let wall = wall();
+        // 正面只有半径，背面是半径加厚度
+        assert!(wall.check_collision(Vec3::new(2.0, 1.0, -0.2), 0.25));
+        assert!(!wall.check_collision(Vec3::new(2.0, 1.0, -0.3), 0.25));
+        assert!(wall.check_collision(Vec3::new(2.0, 1.0, 0.5), 0.25));
+        assert!(!wall.check_collision(Vec3::new(2.0, 1.0, 0.6), 0.25));
+        // 调试显示的长方体和碰撞的是同一侧
+        let center = wall.box_transform().transform_point3(Vec3::ZERO);
+        assert!((center - Vec3::new(2.0, 1.5, 0.15)).length() < EPSILON);
+    }
+
+    #[test]
+    fn height_and_hanging_bottom_limit_collisions() {
+        let wall = wall();
+        assert!(!wall.check_collision(Vec3::new(2.0, 3.1, -0.1), 0.25));
+        let pipe = WallCollider::hanging([0.0, 0.0, 0.0], [4.0, 0.0, 0.0], 2.0, 2.4, 0.3);
+        // 位置当作球：头顶碰到底面才算
+        assert!(!pipe.check_collision(Vec3::new(2.0, 1.7, -0.1), 0.25));
+        assert!(pipe.check_collision(Vec3::new(2.0, 1.8, -0.1), 0.25));
+        assert!(!pipe.check_collision(Vec3::new(2.0, 2.5, -0.1), 0.25));
+    }
+
+    #[test]
+    fn resolve_pushes_out_of_the_nearest_face() {
+        let wall = wall();
+        let radius = 0.25;
+        // 正面：推到正好一个半径
+        let front = wall.resolve_collision(Vec3::new(2.0, 1.0, -0.1), radius);
+        assert!((front - Vec3::new(2.0, 1.0, -radius)).length() < EPSILON);
+        // 背面：推到背面外一个半径
+        let back = wall.resolve_collision(Vec3::new(2.0, 1.0, 0.4), radius);
+        assert!((back - Vec3::new(2.0, 1.0, 0.3 + radius)).length() < EPSILON);
+        // 刚穿过正面：从正面推回去，不穿过整面墙
+        let inside = wall.resolve_collision(Vec3::new(2.0, 1.0, 0.05), radius);
+        assert!((inside - Vec3::new(2.0, 1.0, -radius)).length() < EPSILON);
+        // 正好在线上：沿法向量的反方向推出去，不会得到 NaN
+        let on_line = wall.resolve_collision(Vec3::new(2.0, 1.0, 0.0), radius);
+        assert!((on_line - Vec3::new(2.0, 1.0, -radius)).length() < EPSILON);
+        // 没碰到时位置不变
+        let clear = Vec3::new(2.0, 1.0, -1.0);
+        assert_eq!(wall.resolve_collision(clear, radius), clear);
+    }
+
+    #[test]
+    fn resolve_grazing_the_line_pushes_along_the_normal() {
+        // 斜着的墙：贴着线时距离向量只剩浮点误差，方向不能用来推
+        let (start, end) = (Vec3::new(-1.3, 0.0, 0.7), Vec3::new(2.9, 0.0, 5.3));
+        let wall = WallCollider::new(start.to_array(), end.to_array(), 3.0, 0.3);
+        let tangent = (end - start).normalize();
+        let radius = 0.25;
+        for t in [0.1, 0.37, 0.5, 0.81] {
+            let on_line = start + (end - start) * t + Vec3::Y;
+            let along = (on_line - start).dot(tangent);
+            // 正面贴着线：推到正好一个半径，沿墙的位置不变
+            for offset in [-1e-6, -1e-7, 0.0, 1e-7, 1e-6] {
+                let resolved = wall.resolve_collision(on_line + wall.normal * offset, radius);
+                assert!(((resolved - start).dot(wall.normal) + radius).abs() < EPSILON, "t {} offset {}: {:?}", t, offset, resolved);
+                assert!(((resolved - start).dot(tangent) - along).abs() < EPSILON, "t {} offset {}: {:?}", t, offset, resolved);
+            }
+            // 背面贴着推出去的边界：推到正好半径加厚度
+            for offset in [0.3 + radius - 1e-6, 0.3 + radius - 1e-3] {
+                let resolved = wall.resolve_collision(on_line + wall.normal * offset, radius);
+                assert!(((resolved - start).dot(wall.normal) - 0.3 - radius).abs() < EPSILON, "t {} offset {}: {:?}", t, offset, resolved);
+                assert!(((resolved - start).dot(tangent) - along).abs() < EPSILON, "t {} offset {}: {:?}", t, offset, resolved);
+            }
+        }
+    }
+
+    #[test]
+    fn resolve_rounds_the_wall_ends() {
+        let wall = wall();
+        let resolved = wall.resolve_collision(Vec3::new(4.1, 1.0, -0.1), 0.25);
+        let offset = resolved - Vec3::new(4.0, 1.0, 0.0);
+        assert!((offset.length() - 0.25).abs() < EPSILON);
+        assert!(offset.x > 0.0 && offset.z < 0.0);
+    }
+
+    #[test]
+    fn raycast_agrees_with_the_collision_side() {
+        let wall = wall();
+        // 从正面打进去：在 z = 0 进入，z = 0.3 离开
+        let hit = wall.raycast(Vec3::new(2.0, 1.0, -5.0), Vec3::Z, 100.0).unwrap();
+        assert!((hit.distance - 5.0).abs() < EPSILON);
+        assert!((hit.exit_distance - 5.3).abs() < EPSILON);
+        assert!((hit.normal - Vec3::NEG_Z).length() < EPSILON);
+        assert!((hit.exit_normal - Vec3::Z).length() < EPSILON);
+        // 从背面打进去
+        let hit = wall.raycast(Vec3::new(2.0, 1.0, 5.0), Vec3::NEG_Z, 100.0).unwrap();
+        assert!((hit.distance - 4.7).abs() < EPSILON);
+        assert!((hit.normal - Vec3::Z).length() < EPSILON);
+        // 射线进入的位置正好是碰撞开始的位置
+        let entry = Vec3::new(2.0, 1.0, 5.0) + Vec3::NEG_Z * (hit.distance - 0.01);
+        assert!(!wall.check_collision(entry, 0.0) && wall.check_collision(entry + Vec3::NEG_Z * 0.02, 0.0));
+        // 从墙边、墙顶上过去，和超过最远距离的都打不到
+        assert!(wall.raycast(Vec3::new(5.0, 1.0, -5.0), Vec3::Z, 100.0).is_none());
+        assert!(wall.raycast(Vec3::new(2.0, 3.5, -5.0), Vec3::Z, 100.0).is_none());
+        assert!(wall.raycast(Vec3::new(2.0, 1.0, -5.0), Vec3::Z, 4.0).is_none());
+    }
+
+    #[test]
+    fn square_pillar_is_centered() {
+        let center = Vec3::new(1.0, 0.0, 1.0);
+        let pillar = WallCollider::square(center, 0.5, 3.0);
+        let box_center = pillar.box_transform().transform_point3(Vec3::ZERO);
+        assert!((box_center - Vec3::new(1.0, 1.5, 1.0)).length() < EPSILON);
+        for dir in [Vec3::X, Vec3::NEG_X, Vec3::Z, Vec3::NEG_Z] {
+            let hit = pillar.raycast(center + Vec3::Y - dir * 5.0, dir, 100.0).unwrap();
+            assert!((hit.distance - 4.5).abs() < EPSILON, "{:?}: {}", dir, hit.distance);
+            // 贴着柱子四边的点被推到离柱子至少一个半径的地方（两侧的棱角是圆的，可能斜着推开）
+            let resolved = pillar.resolve_collision(center + Vec3::Y - dir * 0.6, 0.25);
+            let outside = ((resolved - center).abs() - Vec3::new(0.5, f32::INFINITY, 0.5)).max(Vec3::ZERO);
+            assert!(outside.length() >= 0.25 - EPSILON, "{:?}: {:?}", dir, resolved);
+        }
+    }
+
+    #[test]
+    fn spherecast_stops_a_radius_before_the_face() {
+        let wall = wall();
+        let front = wall.spherecast(Vec3::new(2.0, 1.0, -5.0), Vec3::Z, 0.5, 100.0).unwrap();
+        assert!((front - 4.5).abs() < EPSILON);
+        let back = wall.spherecast(Vec3::new(2.0, 1.0, 5.0), Vec3::NEG_Z, 0.5, 100.0).unwrap();
+        assert!((back - 4.2).abs() < EPSILON);
+    }
+
+    // 随机的墙和位置：resolve 之后不再和墙相交，也不会移动超过半径加厚度，高度不变。
+    // COLLISION_FUZZ_ITERATIONS 和 COLLISION_FUZZ_SEED 环境变量可以跑更多次或者换一组随机数
+    #[test]
+    fn fuzz_resolve_never_leaves_a_position_inside_a_wall() {
+        let env = |name: &str, default: u64| std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default);
+        let iterations = env("COLLISION_FUZZ_ITERATIONS", 20_000);
+        let seed = env("COLLISION_FUZZ_SEED", 0x5eed);
+        let mut rng = fastrand::Rng::with_seed(seed);
+        let mut range = |min: f32, max: f32| min + rng.f32() * (max - min);
+        for i in 0..iterations {
+            let start = Vec3::new(range(-10.0, 10.0), 0.0, range(-10.0, 10.0));
+            let angle = range(0.0, std::f32::consts::TAU);
+            let end = start + Vec3::new(angle.cos(), 0.0, angle.sin()) * range(0.2, 8.0);
+            let (height, thickness, radius) = (range(0.5, 4.0), range(0.05, 1.0), range(0.05, 0.8));
+            let wall = WallCollider::new(start.to_array(), end.to_array(), height, thickness);
+            let middle = (start + end) / 2.0;
+            let reach = (end - start).length() / 2.0 + radius + thickness + 0.5;
+            let position = middle + Vec3::new(range(-reach, reach), range(0.0, height), range(-reach, reach));
+
+            let resolved = wall.resolve_collision(position, radius);
+            let case = format!("iteration {} (seed {}): wall {:?} -> {:?} thickness {} radius {} at {:?} -> {:?}", i, seed, start, end, thickness, radius, position, resolved);
+            assert!(resolved.is_finite(), "{}", case);
+            assert_eq!(resolved.y, position.y, "{}", case);
+            assert!(!wall.check_collision(resolved, radius - EPSILON), "still inside: {}", case);
+            assert!((resolved - position).length() <= radius + thickness + EPSILON, "teleported: {}", case);
+            if !wall.check_collision(position, radius) {
+                assert_eq!(resolved, position, "{}", case);
+            }
+        }
+    }
+}
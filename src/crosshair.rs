@@ -0,0 +1,108 @@
+use glam::Vec3;
+
+use crate::collision::{self, Aabb, WallCollider};
+use crate::ui::UiBatch;
+
+// 准星：每帧从屏幕中心做一次射线检测，结果同时给准星显示和交互系统使用，
+// 避免各自重复检测
+
+// 准星检测的最远距离
+pub const AIM_RANGE: f32 = 100.0;
+// 可交互物体只在这个距离内才算瞄准到
+pub const INTERACT_RANGE: f32 = 2.5;
+
+const NEUTRAL_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.85];
+const ENEMY_COLOR: [f32; 4] = [1.0, 0.2, 0.2, 0.95];
+const FRIENDLY_COLOR: [f32; 4] = [0.3, 1.0, 0.4, 0.95];
+const INTERACT_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 0.95];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AimKind {
+    Enemy,
+    Friendly,
+    Interactable(Interaction),
+}
+
+// 可交互物体的种类，决定按使用键时做什么
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interaction {
+    Pickup,
+    Door,
+    Alarm,
+}
+
+// 可被瞄准的物体：种类、调用者的编号和包围盒
+#[derive(Debug, Clone, Copy)]
+pub struct AimCandidate {
+    pub kind: AimKind,
+    pub id: usize,
+    pub aabb: Aabb,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AimTarget {
+    pub kind: AimKind,
+    pub id: usize,
+    pub distance: f32,
+}
+
+// 找到射线上最近的、没有被墙挡住的物体
+pub fn cast(colliders: &[WallCollider], candidates: &[AimCandidate], origin: Vec3, dir: Vec3) -> Option<AimTarget> {
+    let wall_distance = collision::raycast_all(colliders, origin, dir, AIM_RANGE)
+        .first()
+        .map_or(AIM_RANGE, |hit| hit.distance);
+    candidates
+        .iter()
+        .filter_map(|candidate| {
+            let range = match candidate.kind {
+                AimKind::Interactable(_) => INTERACT_RANGE.min(wall_distance),
+                _ => wall_distance,
+            };
+            candidate
+                .aabb
+                .raycast(origin, dir, range)
+                .map(|distance| AimTarget { kind: candidate.kind, id: candidate.id, distance })
+        })
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
+
+// 普通状态为白色十字；瞄准敌人时变红并收拢成 X 形，瞄准友军时变绿并加上外框，
+// 瞄准可交互物体时显示一个手形图标
+pub fn draw(ui: &mut UiBatch, target: Option<&AimTarget>) {
+    let (cx, cy) = (ui.width() / 2.0, ui.height() / 2.0);
+    let kind = target.map(|target| target.kind);
+    match kind {
+        None => draw_cross(ui, cx, cy, 6.0, NEUTRAL_COLOR),
+        Some(AimKind::Enemy) => {
+            draw_cross(ui, cx, cy, 3.0, ENEMY_COLOR);
+            // 四角的短斜线
+            for (dx, dy) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+                for step in 0..4 {
+                    let offset = 9.0 + step as f32 * 2.0;
+                    ui.rect(cx + dx * offset - 1.0, cy + dy * offset - 1.0, 2.0, 2.0, ENEMY_COLOR);
+                }
+            }
+        }
+        Some(AimKind::Friendly) => {
+            draw_cross(ui, cx, cy, 6.0, FRIENDLY_COLOR);
+            ui.rect_outline(cx - 18.0, cy - 18.0, 36.0, 36.0, 2.0, FRIENDLY_COLOR);
+        }
+        Some(AimKind::Interactable(_)) => {
+            // 手形图标：手掌和四根手指
+            ui.rect(cx - 7.0, cy - 2.0, 14.0, 10.0, INTERACT_COLOR);
+            for finger in 0..4 {
+                ui.rect(cx - 7.0 + finger as f32 * 4.0, cy - 10.0, 2.0, 8.0, INTERACT_COLOR);
+            }
+            ui.rect(cx + 7.0, cy - 2.0, 4.0, 2.0, INTERACT_COLOR);
+        }
+    }
+}
+
+fn draw_cross(ui: &mut UiBatch, cx: f32, cy: f32, gap: f32, color: [f32; 4]) {
+    let (length, thickness) = (8.0, 2.0);
+    ui.rect(cx - gap - length, cy - thickness / 2.0, length, thickness, color);
+    ui.rect(cx + gap, cy - thickness / 2.0, length, thickness, color);
+    ui.rect(cx - thickness / 2.0, cy - gap - length, thickness, length, color);
+    ui.rect(cx - thickness / 2.0, cy + gap, thickness, length, color);
+    ui.rect(cx - 1.0, cy - 1.0, 2.0, 2.0, color);
+}
@@ -0,0 +1,239 @@
+use glam::{Vec2, Vec3};
+
+use crate::bindings::{Layouts, MaterialBinding};
+use crate::draw_list;
+use crate::geometry::{self, TexCoords};
+use crate::gpu_memory;
+use crate::map::{self, Map};
+use crate::mesh::MeshBuilder;
+use crate::model::{self, Model};
+use crate::texture;
+
+// 镂空材质：铁丝网、排水沟格栅等很薄的透视隔断。片段着色器按纹理透明度裁剪
+// （与贴花共用 model_type 2），和不透明物体一样写深度，不需要排序；
+// 每种材质可以设为双面，双面材质用不剔除背面的管线绘制
+
+// 铁丝网碰撞体的厚度
+pub const FENCE_THICKNESS: f32 = 0.1;
+
+const TEXTURE_SIZE: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Material {
+    ChainLink,
+    Grate,
+}
+
+impl Material {
+    const ALL: [Material; 2] = [Material::ChainLink, Material::Grate];
+
+    // 铁丝网两面都看得到；格栅只会从上面看
+    pub fn double_sided(self) -> bool {
+        match self {
+            Material::ChainLink => true,
+            Material::Grate => false,
+        }
+    }
+
+    fn color(self) -> [f32; 3] {
+        match self {
+            Material::ChainLink => [0.72, 0.74, 0.76],
+            Material::Grate => [0.28, 0.28, 0.3],
+        }
+    }
+
+    // 一张纹理在世界中覆盖的边长（米）
+    fn tile_size(self) -> f32 {
+        match self {
+            Material::ChainLink => 0.5,
+            Material::Grate => map::CELL_SIZE,
+        }
+    }
+
+    // 白色纹理（绘制时乘以材质颜色），透明度低于 0.5 的像素被裁剪
+    fn generate_texture(self) -> image::RgbaImage {
+        let mut image = image::RgbaImage::new(TEXTURE_SIZE, TEXTURE_SIZE);
+        for y in 0..TEXTURE_SIZE {
+            for x in 0..TEXTURE_SIZE {
+                let solid = match self {
+                    // 两组斜线交织成菱形网眼，纹理边缘正好接上
+                    Material::ChainLink => {
+                        let period = TEXTURE_SIZE / 2;
+                        (x + y) % period < 3 || (x + TEXTURE_SIZE - y) % period < 3
+                    }
+                    // 外框加一排竖条
+                    Material::Grate => {
+                        let inner = 4..TEXTURE_SIZE - 4;
+                        !inner.contains(&x) || !inner.contains(&y) || x % 12 < 4
+                    }
+                };
+                if solid {
+                    // 线条上交替明暗，远看不那么死板
+                    let shade = if (x + y) % 2 == 0 { 255 } else { 225 };
+                    image.put_pixel(x, y, image::Rgba([shade, shade, shade, 255]));
+                }
+            }
+        }
+        image
+    }
+}
+
+struct MaterialBatch {
+    material: Material,
+    binding: MaterialBinding,
+    // 当前地图中使用这种材质的所有几何体
+    model: Option<Model>,
+}
+
+pub struct CutoutSet {
+    batches: Vec<MaterialBatch>,
+    // 双面材质的管线（单面材质使用场景管线）
+    double_sided_pipeline: wgpu::RenderPipeline,
+}
+
+impl CutoutSet {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layouts: &Layouts,
+        pipeline_layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let batches = Material::ALL
+            .iter()
+            .map(|&material| {
+                let label = format!("{:?}", material);
+                let texture = texture::Texture::from_image(
+                    device,
+                    queue,
+                    &image::DynamicImage::ImageRgba8(material.generate_texture()),
+                    Some(&label),
+                )
+                .expect("无法创建镂空材质纹理");
+                // 顶点着色，材质颜色不使用
+                let binding = MaterialBinding::new(device, layouts, &texture, [1.0; 3], &label);
+                MaterialBatch { material, binding, model: None }
+            })
+            .collect();
+        Self { batches, double_sided_pipeline: create_double_sided_pipeline(device, pipeline_layout, shader, format) }
+    }
+
+    // 根据地图重新生成几何体：铁丝网格子和排水沟格栅
+    pub fn load(&mut self, device: &wgpu::Device, map: &Map) {
+        for batch in &mut self.batches {
+            let mut mesh = MeshBuilder::new();
+            match batch.material {
+                Material::ChainLink => {
+                    for segment in map.fence_segments() {
+                        fence_quad(&mut mesh, &segment, batch.material);
+                    }
+                }
+                Material::Grate => {
+                    for (col, row) in map.grate_cells() {
+                        grate_quads(&mut mesh, map.cell_center(col, row), batch.material);
+                    }
+                }
+            }
+            batch.model = (!mesh.vertices.is_empty()).then(|| mesh.build(device, &format!("{:?}", batch.material), [0.0, 0.0, 0.0], false, gpu_memory::Category::Map));
+        }
+    }
+
+    // 换地图时释放旧的几何体，材质保留
+    pub fn unload(&mut self) {
+        for batch in &mut self.batches {
+            batch.model = None;
+        }
+    }
+
+    // 当前地图的镂空几何体，交给静态合批
+    pub fn meshes(&self) -> impl Iterator<Item = (draw_list::Pipeline, draw_list::Material, &Model)> {
+        self.batches.iter().enumerate().filter_map(|(index, batch)| {
+            let pipeline = if batch.material.double_sided() { draw_list::Pipeline::DoubleSided } else { draw_list::Pipeline::Scene };
+            batch.model.as_ref().map(|model| (pipeline, draw_list::Material::Cutout(index), model))
+        })
+    }
+
+    pub fn bind_groups(&self) -> Vec<&wgpu::BindGroup> {
+        self.batches.iter().map(|batch| &batch.binding.bind_group).collect()
+    }
+
+    pub fn double_sided_pipeline(&self) -> &wgpu::RenderPipeline {
+        &self.double_sided_pipeline
+    }
+}
+
+// 纹理坐标按世界尺寸平铺：min 和 max 是以米为单位的纹理矩形
+fn cutout_tex(min: [f32; 2], max: [f32; 2], material: Material) -> TexCoords {
+    let tile = material.tile_size();
+    TexCoords::Rect([min[0] / tile, min[1] / tile], [max[0] / tile, max[1] / tile])
+}
+
+// 铁丝网：位于碰撞体厚度中间的一个面，双面绘制
+fn fence_quad(mesh: &mut MeshBuilder, segment: &map::WallSegment, material: Material) {
+    let (start, end) = (Vec3::from(segment.start), Vec3::from(segment.end));
+    let tangent = (end - start).normalize_or_zero();
+    let normal = Vec3::new(-tangent.z, 0.0, tangent.x);
+    // 纹理 u 坐标从世界坐标算，相邻的格子能接上
+    let (u0, u1) = (start.dot(tangent), end.dot(tangent));
+    let height = map::WALL_HEIGHT;
+    let center = (start + end) / 2.0 + normal * (FENCE_THICKNESS / 2.0) + Vec3::Y * (height / 2.0);
+    let tex = cutout_tex([u0, 0.0], [u1, height], material);
+    geometry::quad(mesh, center, (end - start) / 2.0, Vec3::Y * (height / 2.0), material.color(), 2.0, tex);
+}
+
+// 格栅：地面上一个黑色的坑，上面盖着朝上的格栅
+fn grate_quads(mesh: &mut MeshBuilder, center: Vec3, material: Material) {
+    let size = Vec2::splat(map::CELL_SIZE);
+    geometry::plane(mesh, Vec3::new(center.x, 0.004, center.z), size, true, [0.02, 0.02, 0.02], 0.0, TexCoords::NONE);
+    let tex = cutout_tex([0.0, 0.0], [map::CELL_SIZE, map::CELL_SIZE], material);
+    geometry::plane(mesh, Vec3::new(center.x, 0.016, center.z), size, true, material.color(), 2.0, tex);
+}
+
+fn create_double_sided_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Double Sided Cutout Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[model::ModelVertex::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::Texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: texture::Texture::DEPTH_COMPARE,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
@@ -0,0 +1,133 @@
+// 伤害类型：子弹、近战、爆炸、火焰和电击。每种实体对各类型有自己的抗性（受到伤害的倍数），
+// 火焰会让目标持续燃烧掉血，电击会让目标短暂僵直。武器的类型在 data/weapons.json，场景危害的在 data/hazards.json
+
+// 燃烧持续多久（秒），燃烧期间的总伤害是点燃那一下伤害的多少倍
+const BURN_TIME: f32 = 3.0;
+const BURN_TOTAL: f32 = 0.6;
+// 电击僵直多久（秒），伤害低于 STUN_FULL 时按比例缩短
+const STUN_TIME: f32 = 0.8;
+const STUN_FULL: f32 = 20.0;
+// 被炸到和电击僵直结束以后一段时间内不能冲刺（秒）
+const BLAST_WINDED: f32 = 2.0;
+const STUN_WINDED: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DamageType {
+    #[default]
+    Bullet,
+    // 刀和枪托，不吃子弹的抗性
+    Melee,
+    Explosive,
+    Fire,
+    Electric,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Damage {
+    pub amount: f32,
+    pub kind: DamageType,
+}
+
+impl Damage {
+    pub fn new(amount: f32, kind: DamageType) -> Self {
+        Self { amount, kind }
+    }
+}
+
+// 各类型受到伤害的倍数，1 为正常，0 为免疫。数据文件里没写的类型为 1
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Resistances {
+    pub bullet: f32,
+    pub melee: f32,
+    pub explosive: f32,
+    pub fire: f32,
+    pub electric: f32,
+}
+
+impl Default for Resistances {
+    fn default() -> Self {
+        Self { bullet: 1.0, melee: 1.0, explosive: 1.0, fire: 1.0, electric: 1.0 }
+    }
+}
+
+impl Resistances {
+    pub fn scale(&self, kind: DamageType) -> f32 {
+        let scale = match kind {
+            DamageType::Bullet => self.bullet,
+            DamageType::Melee => self.melee,
+            DamageType::Explosive => self.explosive,
+            DamageType::Fire => self.fire,
+            DamageType::Electric => self.electric,
+        };
+        scale.max(0.0)
+    }
+
+    // 抗性折算后的伤害
+    pub fn apply(&self, damage: Damage) -> Damage {
+        Damage { amount: damage.amount * self.scale(damage.kind), ..damage }
+    }
+}
+
+// 持续的状态：燃烧（每秒掉血）、电击僵直（不能移动、敌人不转身）和不能冲刺，都是剩余的秒数
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatusEffects {
+    burning: f32,
+    burn_per_second: f32,
+    stunned: f32,
+    winded: f32,
+}
+
+impl StatusEffects {
+    // 受到一次（抗性折算后的）伤害，火焰重新点燃，电击刷新僵直；伤害为 0（免疫）时没有效果
+    pub fn apply(&mut self, damage: Damage) {
+        if damage.amount <= 0.0 {
+            return;
+        }
+        match damage.kind {
+            DamageType::Fire => {
+                self.burn_per_second = self.burn_per_second.max(damage.amount * BURN_TOTAL / BURN_TIME);
+                self.burning = BURN_TIME;
+            }
+            DamageType::Electric => {
+                self.stunned = self.stunned.max(STUN_TIME * (damage.amount / STUN_FULL).min(1.0));
+                self.winded = self.winded.max(self.stunned + STUN_WINDED);
+            }
+            DamageType::Explosive => self.winded = self.winded.max(BLAST_WINDED),
+            DamageType::Bullet | DamageType::Melee => {}
+        }
+    }
+
+    // 返回这一帧燃烧造成的伤害（火焰类型，不会再次点燃）
+    pub fn update(&mut self, dt: f32) -> Option<Damage> {
+        self.stunned = (self.stunned - dt).max(0.0);
+        self.winded = (self.winded - dt).max(0.0);
+        if self.burning <= 0.0 {
+            return None;
+        }
+        let time = dt.min(self.burning);
+        let damage = Damage::new(self.burn_per_second * time, DamageType::Fire);
+        self.burning -= time;
+        if self.burning <= 0.0 {
+            self.burn_per_second = 0.0;
+        }
+        Some(damage)
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.stunned > 0.0
+    }
+
+    pub fn burning(&self) -> f32 {
+        self.burning
+    }
+
+    pub fn stunned(&self) -> f32 {
+        self.stunned
+    }
+
+    pub fn winded(&self) -> f32 {
+        self.winded
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::VecDeque;
+
+use glam::Vec3;
+
+use crate::model::{self, Model};
+use crate::weapon::{Impact, ImpactKind};
+
+// 贴花：弹孔（纯色小圆片）和地面上的血迹、焦痕（来自贴花图集的贴图），超过上限时最早的先消失
+
+const MAX_DECALS: usize = 256;
+// 贴花离表面的距离，避免深度冲突；地面血迹叠在一起时逐个抬高一点
+const SURFACE_OFFSET: f32 = 0.01;
+const SPLATTER_OFFSET: f32 = 0.012;
+const ENTRY_COLOR: [f32; 3] = [0.06, 0.06, 0.06];
+// 出口的弹孔更大，周围是崩开的混凝土
+const EXIT_COLOR: [f32; 3] = [0.45, 0.41, 0.36];
+const SPLATTER_COLOR: [f32; 3] = [0.45, 0.02, 0.02];
+// 爆炸和火焰在地面上留下的焦痕
+const SCORCH_COLOR: [f32; 3] = [0.05, 0.045, 0.04];
+
+// 贴花图集：ATLAS_CELLS x ATLAS_CELLS 个格子，每格一种血迹形状
+pub const ATLAS_CELLS: u32 = 2;
+const ATLAS_CELL_SIZE: u32 = 128;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Decal {
+    pub position: Vec3,
+    pub normal: Vec3,
+    pub radius: f32,
+    // 绕法向量的旋转（弧度）
+    pub rotation: f32,
+    pub color: [f32; 3],
+    // 使用图集中的哪一格，None 为纯色圆片
+    pub atlas_cell: Option<u32>,
+}
+
+pub struct DecalSet {
+    decals: VecDeque<Decal>,
+    model: Option<Model>,
+    dirty: bool,
+    // 地面血迹的数量，用于错开高度
+    splatter_count: u32,
+}
+
+impl DecalSet {
+    pub fn new() -> Self {
+        Self { decals: VecDeque::new(), model: None, dirty: false, splatter_count: 0 }
+    }
+
+    fn push(&mut self, decal: Decal) {
+        self.decals.push_back(decal);
+        while self.decals.len() > MAX_DECALS {
+            self.decals.pop_front();
+        }
+        self.dirty = true;
+    }
+
+    // 伤害越高弹孔越大
+    pub fn add_impact(&mut self, impact: &Impact) {
+        let base = 0.03 + impact.damage * 0.0006;
+        let (radius, color) = match impact.kind {
+            ImpactKind::Entry => (base, ENTRY_COLOR),
+            ImpactKind::Exit => (base * 1.8, EXIT_COLOR),
+        };
+        self.push(Decal {
+            position: impact.point + impact.normal * SURFACE_OFFSET,
+            normal: impact.normal,
+            radius,
+            rotation: 0.0,
+            color,
+            atlas_cell: None,
+        });
+    }
+
+    // 受伤的实体脚下的血迹：随机位置偏移、旋转、大小和形状
+    pub fn add_splatter(&mut self, position: Vec3, size: f32) {
+        self.add_ground(position, size, SPLATTER_COLOR);
+    }
+
+    // 焦痕和血迹用同样的形状，颜色是黑色
+    pub fn add_scorch(&mut self, position: Vec3, size: f32) {
+        self.add_ground(position, size, SCORCH_COLOR);
+    }
+
+    fn add_ground(&mut self, position: Vec3, size: f32, color: [f32; 3]) {
+        let offset = Vec3::new(fastrand::f32() - 0.5, 0.0, fastrand::f32() - 0.5) * size;
+        // 反复叠加的血迹交替使用几个高度，避免深度冲突
+        let height = SPLATTER_OFFSET + (self.splatter_count % 4) as f32 * 0.002;
+        self.splatter_count += 1;
+        self.push(Decal {
+            position: Vec3::new(position.x + offset.x, height, position.z + offset.z),
+            normal: Vec3::Y,
+            radius: size * (0.6 + fastrand::f32() * 0.8),
+            rotation: fastrand::f32() * std::f32::consts::TAU,
+            color,
+            atlas_cell: Some(fastrand::u32(0..ATLAS_CELLS * ATLAS_CELLS)),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.decals.clear();
+        self.model = None;
+        self.dirty = false;
+    }
+
+    // 贴花有变化时重新生成网格
+    pub fn update(&mut self, device: &wgpu::Device) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        self.model = if self.decals.is_empty() {
+            None
+        } else {
+            Some(model::create_decals(device, self.decals.iter()))
+        };
+    }
+
+    pub fn model(&self) -> Option<&Model> {
+        self.model.as_ref()
+    }
+}
+
+// 图集中某一格的纹理坐标范围（向内缩一点，避免采样到相邻格子）
+pub fn atlas_uv(cell: u32) -> ([f32; 2], [f32; 2]) {
+    let size = 1.0 / ATLAS_CELLS as f32;
+    let inset = 1.0 / (ATLAS_CELLS * ATLAS_CELL_SIZE) as f32;
+    let (x, y) = ((cell % ATLAS_CELLS) as f32 * size, (cell / ATLAS_CELLS) as f32 * size);
+    ([x + inset, y + inset], [x + size - inset, y + size - inset])
+}
+
+// 程序生成血迹图集：每格是一团不规则的圆形加上周围飞溅的小点，
+// 白色（由顶点颜色着色），形状外 alpha 为 0
+pub fn generate_atlas() -> image::RgbaImage {
+    let size = ATLAS_CELLS * ATLAS_CELL_SIZE;
+    let mut image = image::RgbaImage::new(size, size);
+    for cell in 0..ATLAS_CELLS * ATLAS_CELLS {
+        // 固定种子，每次启动的形状相同
+        let mut rng = fastrand::Rng::with_seed(cell as u64 + 1);
+        let half = ATLAS_CELL_SIZE as f32 / 2.0;
+
+        // 主体：半径随角度起伏
+        let lobes: Vec<(f32, f32)> = (0..5).map(|_| (rng.f32() * 0.15, rng.f32() * std::f32::consts::TAU)).collect();
+        // 周围的小血点：(中心, 半径)
+        let droplets: Vec<((f32, f32), f32)> = (0..12)
+            .map(|_| {
+                let angle = rng.f32() * std::f32::consts::TAU;
+                let distance = half * (0.55 + rng.f32() * 0.4);
+                ((half + angle.cos() * distance, half + angle.sin() * distance), 2.0 + rng.f32() * 5.0)
+            })
+            .collect();
+
+        let (ox, oy) = ((cell % ATLAS_CELLS) * ATLAS_CELL_SIZE, (cell / ATLAS_CELLS) * ATLAS_CELL_SIZE);
+        for y in 0..ATLAS_CELL_SIZE {
+            for x in 0..ATLAS_CELL_SIZE {
+                let (dx, dy) = (x as f32 + 0.5 - half, y as f32 + 0.5 - half);
+                let angle = dy.atan2(dx);
+                let wobble: f32 = lobes.iter().enumerate().map(|(i, (amount, phase))| amount * ((i + 2) as f32 * angle + phase).sin()).sum();
+                let radius = half * 0.45 * (1.0 + wobble);
+                let distance = (dx * dx + dy * dy).sqrt();
+                let inside = distance < radius
+                    || droplets.iter().any(|((cx, cy), r)| {
+                        let (ddx, ddy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                        ddx * ddx + ddy * ddy < r * r
+                    });
+                // 中心颜色深一些
+                let shade = (200.0 + 55.0 * (distance / half).min(1.0)) as u8;
+                let pixel = if inside { [shade, shade, shade, 255] } else { [0, 0, 0, 0] };
+                image.put_pixel(ox + x, oy + y, image::Rgba(pixel));
+            }
+        }
+    }
+    image
+}
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use instant::{Instant, SystemTime};
+
+use crate::net::KillInfo;
+use crate::paths;
+use crate::snapshot::{self, PlayerState, Snapshot, SnapshotHistory};
+use crate::spectate;
+use crate::ui::UiBatch;
+
+// 比赛录像：主机把发出去的快照和加入、离开、聊天这些事件写进 demos/ 下的 .demo 文件，
+// 菜单里选一个回放：用观战的跟随和自由相机看，可以暂停、快进和拖动时间轴。
+// 格式（小端）：
+//   "TRAEDEMO", 版本 u32, 头部长度 u32, 头部 (JSON)
+//   之后每一帧 [时间 (毫秒) u32, 类型 u8, 长度 u32, 内容]
+//   快照帧的内容是相对上一个快照帧的增量（见 snapshot.rs），事件帧是 JSON
+
+const MAGIC: &[u8; 8] = b"TRAEDEMO";
+// 版本 2 的快照里加了护甲
+const VERSION: u32 = 2;
+const FRAME_SNAPSHOT: u8 = 0;
+const FRAME_EVENT: u8 = 1;
+// 只保留最新的这么多个录像，菜单里一屏放得下
+const MAX_DEMOS: usize = 8;
+// 左右方向键一次跳多少秒，上下方向键在这些速度之间切换
+pub const SEEK_STEP: f32 = 5.0;
+const SPEEDS: [f32; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+
+const BAR_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const PROGRESS_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 0.9];
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DemoHeader {
+    pub map: String,
+    pub mode: String,
+    pub host_id: u32,
+    pub host_name: String,
+    // 开始录制的时间（Unix 秒）
+    pub started: u64,
+}
+
+impl DemoHeader {
+    pub fn new(map: &str, mode: &str, host_id: u32, host_name: &str) -> Self {
+        let started = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        Self { map: map.to_string(), mode: mode.to_string(), host_id, host_name: host_name.to_string(), started }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum DemoEvent {
+    PlayerJoined { player_id: u32, name: String },
+    PlayerLeft { player_id: u32, name: String },
+    Chat { name: String, text: String },
+    Kill(KillInfo),
+}
+
+// 主机端：边玩边写，结束时（主机关闭）刷到磁盘
+pub struct Recorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    start: Instant,
+    last_snapshot: Option<Snapshot>,
+}
+
+impl Recorder {
+    // 打不开文件时不录，不影响联机
+    pub fn start(header: DemoHeader) -> Option<Self> {
+        let dir = paths::demos();
+        prune(&dir);
+        let path = dir.join(format!("{}_{}.demo", header.started, header.map));
+        let result = std::fs::create_dir_all(&dir).and_then(|_| {
+            let mut writer = BufWriter::new(File::create(&path)?);
+            let json = serde_json::to_vec(&header).map_err(io::Error::from)?;
+            writer.write_all(MAGIC)?;
+            writer.write_all(&VERSION.to_le_bytes())?;
+            writer.write_all(&(json.len() as u32).to_le_bytes())?;
+            writer.write_all(&json)?;
+            Ok(writer)
+        });
+        match result {
+            Ok(writer) => {
+                println!("录像保存到 {}", path.display());
+                Some(Self { path, writer, start: Instant::now(), last_snapshot: None })
+            }
+            Err(e) => {
+                eprintln!("无法录像 {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    pub fn snapshot(&mut self, snapshot: &Snapshot) {
+        let bytes = snapshot::encode(snapshot, self.last_snapshot.as_ref());
+        self.frame(FRAME_SNAPSHOT, &bytes);
+        self.last_snapshot = Some(snapshot.clone());
+    }
+
+    pub fn event(&mut self, event: &DemoEvent) {
+        match serde_json::to_vec(event) {
+            Ok(json) => self.frame(FRAME_EVENT, &json),
+            Err(e) => eprintln!("录像事件序列化失败: {}", e),
+        }
+    }
+
+    fn frame(&mut self, kind: u8, bytes: &[u8]) {
+        let time = self.start.elapsed().as_millis() as u32;
+        let result = self
+            .writer
+            .write_all(&time.to_le_bytes())
+            .and_then(|_| self.writer.write_all(&[kind]))
+            .and_then(|_| self.writer.write_all(&(bytes.len() as u32).to_le_bytes()))
+            .and_then(|_| self.writer.write_all(bytes));
+        if let Err(e) = result {
+            eprintln!("写录像失败 {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        match self.writer.flush() {
+            Ok(()) => println!("录像已保存: {}", self.path.display()),
+            Err(e) => eprintln!("写录像失败 {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+// 删掉最旧的录像，给新的腾出位置
+fn prune(dir: &Path) {
+    let demos = list_in(dir);
+    for old in demos.iter().skip(MAX_DEMOS - 1) {
+        if let Err(e) = std::fs::remove_file(old) {
+            eprintln!("无法删除旧录像 {}: {}", old.display(), e);
+        }
+    }
+}
+
+fn list_in(dir: &Path) -> Vec<PathBuf> {
+    let mut demos: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "demo"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    // 文件名以开始时间开头，新的在前
+    demos.sort();
+    demos.reverse();
+    demos
+}
+
+// 所有录像，新的在前
+pub fn list() -> Vec<PathBuf> {
+    list_in(&paths::demos())
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// 读进内存的录像，快照已经解码成每个玩家的状态
+pub struct Demo {
+    pub header: DemoHeader,
+    // (秒, 这一刻所有玩家的状态)
+    snapshots: Vec<(f32, Vec<(u32, PlayerState)>)>,
+    events: Vec<(f32, DemoEvent)>,
+    names: HashMap<u32, String>,
+}
+
+impl Demo {
+    pub fn load(path: &Path) -> io::Result<Demo> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        let mut reader = Reader { bytes: &bytes, at: 0 };
+        if reader.take(8)? != MAGIC {
+            return Err(invalid(format!("{} 不是录像文件", path.display())));
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(invalid(format!("不支持的录像版本 {}", version)));
+        }
+        let header_len = reader.u32()? as usize;
+        let header: DemoHeader = serde_json::from_slice(reader.take(header_len)?).map_err(|e| invalid(e.to_string()))?;
+
+        let mut history = SnapshotHistory::new();
+        let mut snapshots = Vec::new();
+        let mut events = Vec::new();
+        let mut names = HashMap::from([(header.host_id, header.host_name.clone())]);
+        while !reader.done() {
+            // 主机异常退出时最后一帧可能不完整，前面的照常回放
+            let Ok((time, kind, payload)) = reader.frame() else { break };
+            let time = time as f32 / 1000.0;
+            match kind {
+                FRAME_SNAPSHOT => {
+                    let snapshot = snapshot::decode(payload, &history).map_err(|e| invalid(format!("快照解码失败: {:?}", e)))?;
+                    snapshots.push((time, snapshot.players()));
+                    history.push(snapshot);
+                }
+                FRAME_EVENT => {
+                    let event: DemoEvent = serde_json::from_slice(payload).map_err(|e| invalid(e.to_string()))?;
+                    if let DemoEvent::PlayerJoined { player_id, name } = &event {
+                        names.insert(*player_id, name.clone());
+                    }
+                    events.push((time, event));
+                }
+                _ => {}
+            }
+        }
+        Ok(Demo { header, snapshots, events, names })
+    }
+
+    pub fn duration(&self) -> f32 {
+        let last_snapshot = self.snapshots.last().map_or(0.0, |(time, _)| *time);
+        let last_event = self.events.last().map_or(0.0, |(time, _)| *time);
+        last_snapshot.max(last_event)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> io::Result<&'a [u8]> {
+        let slice = self.bytes.get(self.at..self.at + count).ok_or_else(|| invalid("录像文件不完整".to_string()))?;
+        self.at += count;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn frame(&mut self) -> io::Result<(u32, u8, &'a [u8])> {
+        let time = self.u32()?;
+        let kind = self.take(1)?[0];
+        let len = self.u32()? as usize;
+        Ok((time, kind, self.take(len)?))
+    }
+
+    fn done(&self) -> bool {
+        self.at >= self.bytes.len()
+    }
+}
+
+// 正在回放的录像
+pub struct Playback {
+    pub demo: Demo,
+    time: f32,
+    pub paused: bool,
+    speed: usize,
+    // 下一个要交出去的事件
+    next_event: usize,
+}
+
+impl Playback {
+    pub fn new(demo: Demo) -> Self {
+        Self { demo, time: 0.0, paused: false, speed: 2, next_event: 0 }
+    }
+
+    // 推进时间，返回这段时间里发生的事件；放到结尾时自动暂停
+    pub fn update(&mut self, dt: f32) -> Vec<DemoEvent> {
+        if !self.paused {
+            self.time += dt * SPEEDS[self.speed];
+        }
+        let duration = self.demo.duration();
+        if self.time >= duration {
+            self.time = duration;
+            self.paused = true;
+        }
+        let mut passed = Vec::new();
+        while let Some((time, event)) = self.demo.events.get(self.next_event) {
+            if *time > self.time {
+                break;
+            }
+            passed.push(event.clone());
+            self.next_event += 1;
+        }
+        passed
+    }
+
+    // 往前或往后跳，跳过的事件不再交出去
+    pub fn seek(&mut self, seconds: f32) {
+        self.time = (self.time + seconds).clamp(0.0, self.demo.duration());
+        self.next_event = self.demo.events.partition_point(|(time, _)| *time <= self.time);
+        if self.time < self.demo.duration() && seconds < 0.0 {
+            self.paused = false;
+        }
+    }
+
+    pub fn change_speed(&mut self, faster: bool) {
+        self.speed = if faster { (self.speed + 1).min(SPEEDS.len() - 1) } else { self.speed.saturating_sub(1) };
+    }
+
+    // 当前时刻所有玩家的状态，在前后两个快照之间插值
+    pub fn players(&self) -> Vec<(u32, PlayerState)> {
+        let snapshots = &self.demo.snapshots;
+        let index = snapshots.partition_point(|(time, _)| *time <= self.time);
+        let Some((before_time, before)) = index.checked_sub(1).and_then(|i| snapshots.get(i)) else {
+            return snapshots.first().map(|(_, players)| players.clone()).unwrap_or_default();
+        };
+        let Some((after_time, after)) = snapshots.get(index) else { return before.clone() };
+        let t = ((self.time - before_time) / (after_time - before_time).max(f32::EPSILON)).clamp(0.0, 1.0);
+        before
+            .iter()
+            .map(|(player_id, a)| {
+                let b = after.iter().find(|(id, _)| id == player_id).map(|(_, b)| b);
+                match b {
+                    Some(b) if a.alive && b.alive => {
+                        let yaw = a.yaw + spectate::wrap_angle(b.yaw - a.yaw) * t;
+                        let state = PlayerState::new(a.position().lerp(b.position(), t), yaw, a.pitch + (b.pitch - a.pitch) * t, a.health, a.armor, true);
+                        (*player_id, state)
+                    }
+                    _ => (*player_id, *a),
+                }
+            })
+            .collect()
+    }
+
+    pub fn player_name(&self, player_id: u32) -> String {
+        self.demo.names.get(&player_id).cloned().unwrap_or_else(|| format!("player #{}", player_id))
+    }
+
+    // 屏幕底部的时间轴
+    pub fn draw(&self, ui: &mut UiBatch) {
+        let (width, height) = (ui.width(), ui.height());
+        let (bar_x, bar_y, bar_w, bar_h) = (width * 0.2, height - 30.0, width * 0.6, 10.0);
+        let duration = self.demo.duration().max(f32::EPSILON);
+        ui.rect(bar_x, bar_y, bar_w, bar_h, BAR_COLOR);
+        ui.rect(bar_x, bar_y, bar_w * (self.time / duration).min(1.0), bar_h, PROGRESS_COLOR);
+        let state = if self.paused { "PAUSED".to_string() } else { format!("x{}", SPEEDS[self.speed]) };
+        let text = format!("DEMO {}  {} / {}  {}", self.demo.header.map.to_uppercase(), clock(self.time), clock(duration), state);
+        ui.text_centered(width / 2.0, bar_y - 24.0, 2.0, TEXT_COLOR, &text);
+        ui.text_centered(width / 2.0, bar_y + 14.0, 1.5, TEXT_COLOR, "LEFT/RIGHT seek   UP/DOWN speed   BACKSPACE stop");
+    }
+}
+
+fn clock(seconds: f32) -> String {
+    let seconds = seconds as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
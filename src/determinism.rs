@@ -0,0 +1,266 @@
+use std::path::{Path, PathBuf};
+
+use glam::Vec3;
+use instant::SystemTime;
+
+use crate::input::Action;
+use crate::paths;
+
+// --determinism 模式：在固定频率的模拟上按同一段脚本输入跑两轮，每次模拟后算一遍状态的校验和，
+// 两轮逐次比较，找出第一次不一样的地方。录像回放和锁步联机都要求同样的输入得到同样的结果，
+// 模拟里用了没有固定种子的随机数、依赖任务执行顺序或者帧率的代码都会在这里露出来。
+// 结果写在存档目录的 determinism/ 下，--determinism-baseline 传入另一个版本写出的结果时也和它比较
+
+// 每轮模拟的次数（30 秒）和每个渲染帧跑几次，测试比实际时间快
+pub const TICKS: usize = 1800;
+pub const TICKS_PER_FRAME: usize = 30;
+// 每轮开始时全局随机数的种子，脚本用另一个种子生成
+pub const SEED: u64 = 0x5eed_cafe;
+const SCRIPT_SEED: u64 = 0x1dea;
+// 脚本里每段动作持续的模拟次数
+const SEGMENT_TICKS: (usize, usize) = (20, 90);
+// 每次模拟转动视角的最大角度（弧度）
+const MAX_TURN: f32 = 0.04;
+const MAX_PITCH: f32 = 0.5;
+
+const MOVES: [Option<Action>; 5] = [None, Some(Action::MoveForward), Some(Action::MoveBack), Some(Action::MoveLeft), Some(Action::MoveRight)];
+// 每段开始时按一下的操作和概率
+const TAPS: [(Action, f32); 4] = [(Action::Jump, 0.2), (Action::Grenade, 0.1), (Action::Reload, 0.1), (Action::NextWeapon, 0.05)];
+
+// 一次模拟的输入：按住的操作和这一次视角转到的角度
+#[derive(Debug, Clone, PartialEq)]
+pub struct TickInput {
+    pub held: Vec<Action>,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+// 固定种子生成的脚本：一段一段地走动、冲刺、开火和转动视角，段落开头偶尔跳跃、扔手雷、换弹、换武器。
+// 脚本不看游戏状态，两轮和两个版本拿到的输入完全一样
+pub fn script(ticks: usize) -> Vec<TickInput> {
+    let mut rng = fastrand::Rng::with_seed(SCRIPT_SEED);
+    let mut inputs = Vec::with_capacity(ticks);
+    let (mut yaw, mut pitch) = (0.0f32, 0.0f32);
+    while inputs.len() < ticks {
+        let length = rng.usize(SEGMENT_TICKS.0..=SEGMENT_TICKS.1);
+        let movement = MOVES[rng.usize(..MOVES.len())];
+        let sprint = rng.f32() < 0.3;
+        let fire = rng.f32() < 0.4;
+        let turn = (rng.f32() * 2.0 - 1.0) * MAX_TURN;
+        let target_pitch = (rng.f32() * 2.0 - 1.0) * MAX_PITCH;
+        let taps: Vec<Action> = TAPS.iter().filter(|(_, chance)| rng.f32() < *chance).map(|(action, _)| *action).collect();
+        for i in 0..length.min(ticks - inputs.len()) {
+            yaw += turn;
+            pitch += (target_pitch - pitch) * 0.1;
+            let mut held: Vec<Action> = movement.into_iter().collect();
+            if sprint {
+                held.push(Action::Sprint);
+            }
+            if fire {
+                held.push(Action::Fire);
+            }
+            if i == 0 {
+                held.extend(&taps);
+            }
+            inputs.push(TickInput { held, yaw, pitch });
+        }
+    }
+    inputs
+}
+
+// 64 位 FNV-1a。浮点数按位比较，-0.0 和 0.0、不同的 NaN 都算不一样
+pub struct Checksum(u64);
+
+impl Checksum {
+    pub fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        for byte in value.to_le_bytes() {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_u32(value.to_bits());
+    }
+
+    pub fn write_vec3(&mut self, value: Vec3) {
+        self.write_f32(value.x);
+        self.write_f32(value.y);
+        self.write_f32(value.z);
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// 两组校验和第一次不一样的模拟序号，长度不同时较短的那组结束的地方也算
+pub fn first_mismatch(a: &[u64], b: &[u64]) -> Option<usize> {
+    a.iter().zip(b).position(|(a, b)| a != b).or((a.len() != b.len()).then(|| a.len().min(b.len())))
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct Report {
+    map: String,
+    seed: u64,
+    ticks: usize,
+    // 开始的时间（Unix 秒）
+    timestamp: u64,
+    // 两轮之间、和对比的版本之间第一次不一样的模拟序号，一样时没有
+    run_mismatch: Option<usize>,
+    baseline_mismatch: Option<usize>,
+    checksums: Vec<u64>,
+}
+
+// 读出另一个版本写的结果里的校验和
+pub fn load_baseline(path: &Path) -> Result<Vec<u64>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let report: Report = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(report.checksums)
+}
+
+pub enum Step {
+    Continue,
+    // 第一轮跑完，重新加载地图开始第二轮
+    Restart,
+    Finished,
+}
+
+pub struct DeterminismTest {
+    pub map: String,
+    inputs: Vec<TickInput>,
+    runs: Vec<Vec<u64>>,
+    baseline: Option<Vec<u64>>,
+}
+
+impl DeterminismTest {
+    pub fn new(map: &str, baseline: Option<Vec<u64>>) -> Self {
+        println!("开始确定性测试: {}，每轮 {} 次模拟", map, TICKS);
+        Self { map: map.to_string(), inputs: script(TICKS), runs: vec![Vec::new()], baseline }
+    }
+
+    // 这一次模拟要用的输入
+    pub fn input(&self) -> &TickInput {
+        let tick = self.runs.last().map_or(0, Vec::len);
+        &self.inputs[tick.min(self.inputs.len() - 1)]
+    }
+
+    // 记下这次模拟后的校验和
+    pub fn record(&mut self, checksum: u64) -> Step {
+        let Some(run) = self.runs.last_mut() else { return Step::Finished };
+        run.push(checksum);
+        if run.len() < self.inputs.len() {
+            return Step::Continue;
+        }
+        if self.runs.len() < 2 {
+            self.runs.push(Vec::new());
+            return Step::Restart;
+        }
+        Step::Finished
+    }
+
+    pub fn finished(&self) -> bool {
+        self.runs.len() == 2 && self.runs[1].len() >= self.inputs.len()
+    }
+
+    fn run_mismatch(&self) -> Option<usize> {
+        first_mismatch(&self.runs[0], self.runs.get(1).map_or(&[], Vec::as_slice))
+    }
+
+    fn baseline_mismatch(&self) -> Option<usize> {
+        self.baseline.as_ref().and_then(|baseline| first_mismatch(baseline, &self.runs[0]))
+    }
+
+    // 两轮一样并且和对比的版本一样
+    pub fn passed(&self) -> bool {
+        self.run_mismatch().is_none() && self.baseline_mismatch().is_none()
+    }
+
+    pub fn progress_text(&self) -> String {
+        let tick = self.runs.last().map_or(0, Vec::len);
+        format!("DETERMINISM RUN {}/2 TICK {}/{}", self.runs.len(), tick, self.inputs.len())
+    }
+
+    // 在存档目录下写出 determinism/<地图>-<时间>.json，返回路径
+    pub fn write_report(&self) -> Result<PathBuf, String> {
+        let timestamp = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let report = Report {
+            map: self.map.clone(),
+            seed: SEED,
+            ticks: self.inputs.len(),
+            timestamp,
+            run_mismatch: self.run_mismatch(),
+            baseline_mismatch: self.baseline_mismatch(),
+            checksums: self.runs[0].clone(),
+        };
+        match report.run_mismatch {
+            Some(tick) => println!("确定性测试失败: 两轮在第 {} 次模拟后不一样", tick),
+            None => println!("两轮 {} 次模拟的校验和完全一样", report.ticks),
+        }
+        if self.baseline.is_some() {
+            match report.baseline_mismatch {
+                Some(tick) => println!("确定性测试失败: 和对比的版本在第 {} 次模拟后不一样", tick),
+                None => println!("和对比的版本完全一样"),
+            }
+        }
+        let dir = paths::determinism();
+        std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        let path = dir.join(format!("{}-{}.json", self.map, timestamp));
+        let text = serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?;
+        std::fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_is_the_same_every_time() {
+        let a = script(TICKS);
+        assert_eq!(a.len(), TICKS);
+        assert_eq!(a, script(TICKS));
+        assert!(a.iter().any(|input| input.held.contains(&Action::Fire)));
+        assert!(a.iter().any(|input| input.held.contains(&Action::Grenade)));
+        assert!(a.iter().all(|input| input.pitch.abs() <= MAX_PITCH));
+    }
+
+    #[test]
+    fn checksum_tells_signed_zero_apart() {
+        let sum = |value: f32| {
+            let mut checksum = Checksum::new();
+            checksum.write_f32(value);
+            checksum.finish()
+        };
+        assert_eq!(sum(1.5), sum(1.5));
+        assert_ne!(sum(0.0), sum(-0.0));
+    }
+
+    #[test]
+    fn first_mismatch_finds_the_earliest_tick() {
+        assert_eq!(first_mismatch(&[1, 2, 3], &[1, 2, 3]), None);
+        assert_eq!(first_mismatch(&[1, 2, 3], &[1, 5, 6]), Some(1));
+        assert_eq!(first_mismatch(&[1, 2, 3], &[1, 2]), Some(2));
+    }
+
+    #[test]
+    fn two_runs_then_finished() {
+        let mut test = DeterminismTest::new("test", None);
+        for _ in 0..TICKS - 1 {
+            assert!(matches!(test.record(7), Step::Continue));
+        }
+        assert!(matches!(test.record(7), Step::Restart));
+        for _ in 0..TICKS - 1 {
+            assert!(matches!(test.record(7), Step::Continue));
+        }
+        assert!(!test.finished());
+        assert!(matches!(test.record(8), Step::Finished));
+        assert!(test.finished() && !test.passed());
+        assert_eq!(test.run_mismatch(), Some(TICKS - 1));
+    }
+}
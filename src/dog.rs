@@ -0,0 +1,230 @@
+use glam::Vec3;
+
+use crate::collision::WallCollider;
+use crate::ecs::{self, Collider, Transform, World};
+use crate::geometry::{self, TexCoords};
+use crate::gpu_memory;
+use crate::map::Map;
+use crate::mesh::MeshBuilder;
+use crate::model::Model;
+use crate::nav::DistanceField;
+
+// 狗：不伤人也不会受伤，在停车场里按导航格子闲逛。玩家走近时跟在后面，
+// 听到枪声就往远离枪声的方向跑一阵。画成一直朝向相机的贴图（dog.png），小地图上也显示
+
+// 贴图的宽高（图片是 4:3）
+const SPRITE_SIZE: (f32, f32) = (1.4, 1.05);
+// 走路时一蹦一跳
+const HOP_HEIGHT: f32 = 0.08;
+const HOP_RATE: f32 = 9.0;
+const RADIUS: f32 = 0.3;
+
+const WANDER_SPEED: f32 = 1.5;
+const FOLLOW_SPEED: f32 = 3.5;
+const FLEE_SPEED: f32 = 6.0;
+// 玩家进入这个距离开始跟随，超出 LOSE_RADIUS 后不再跟
+const FOLLOW_RADIUS: f32 = 6.0;
+const LOSE_RADIUS: f32 = 10.0;
+// 跟随时停在玩家身边的距离
+const HEEL_DISTANCE: f32 = 1.5;
+// 能听到枪声的距离和逃跑的时长（秒）
+const HEARING_RADIUS: f32 = 25.0;
+const FLEE_TIME: f32 = 4.0;
+// 闲逛时目标格子离当前格子的最大距离（格子），到达后停下来的时间范围（秒）
+const WANDER_RANGE: usize = 8;
+const PAUSE_TIME: (f32, f32) = (1.0, 4.0);
+// 卡住（被车或关着的门挡住）这么久后换一个目标
+const STUCK_TIME: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    // 走向 target，到了以后停 pause 秒再选下一个
+    Wander { target: (usize, usize), pause: f32 },
+    Follow,
+    // 远离 from 格子
+    Flee { from: (usize, usize), time: f32 },
+}
+
+// 导航用的距离场，起点格子不变时不重新计算
+struct Route {
+    source: (usize, usize),
+    field: DistanceField,
+}
+
+// 狗的实体组件，位置在 Transform 里
+pub struct Dog {
+    state: State,
+    route: Option<Route>,
+    // 走路的相位，停下时归零
+    hop: f32,
+    stuck: f32,
+}
+
+impl Dog {
+    fn field(&mut self, map: &Map, source: (usize, usize)) -> &DistanceField {
+        if self.route.as_ref().is_none_or(|route| route.source != source) {
+            self.route = Some(Route { source, field: DistanceField::compute(map, &[source]) });
+        }
+        &self.route.as_ref().unwrap().field
+    }
+
+    // 下一步要去的格子：走向 source 时选距离更小的邻居，逃离时选更大的，没有更好的返回 None
+    fn next_cell(&mut self, map: &Map, position: Vec3, source: (usize, usize), away: bool) -> Option<(usize, usize)> {
+        let (col, row) = map.world_to_cell(position)?;
+        let field = self.field(map, source);
+        let here = field.distance(col, row)?;
+        let neighbours = [(col.wrapping_sub(1), row), (col + 1, row), (col, row.wrapping_sub(1)), (col, row + 1)];
+        let reachable = neighbours.into_iter().filter_map(|(col, row)| Some(((col, row), field.distance(col, row)?)));
+        if away {
+            reachable.filter(|(_, distance)| *distance > here).max_by_key(|(_, distance)| *distance).map(|(cell, _)| cell)
+        } else {
+            reachable.filter(|(_, distance)| *distance < here).min_by_key(|(_, distance)| *distance).map(|(cell, _)| cell)
+        }
+    }
+
+    // 离当前格子不太远、走得到的随机格子
+    fn pick_wander_target(&mut self, map: &Map, position: Vec3) -> (usize, usize) {
+        let Some((col, row)) = map.world_to_cell(position) else {
+            return (0, 0);
+        };
+        for _ in 0..10 {
+            let target = (
+                (col + fastrand::usize(0..=WANDER_RANGE * 2)).saturating_sub(WANDER_RANGE),
+                (row + fastrand::usize(0..=WANDER_RANGE * 2)).saturating_sub(WANDER_RANGE),
+            );
+            if map.cell(target.0, target.1).is_walkable() && self.field(map, target).distance(col, row).is_some() {
+                return target;
+            }
+        }
+        (col, row)
+    }
+
+    fn wander(&mut self, map: &Map, position: Vec3) {
+        let target = self.pick_wander_target(map, position);
+        let pause = PAUSE_TIME.0 + fastrand::f32() * (PAUSE_TIME.1 - PAUSE_TIME.0);
+        self.state = State::Wander { target, pause };
+        self.stuck = 0.0;
+    }
+
+    fn update(&mut self, position: &mut Vec3, dt: f32, map: &Map, player_position: Vec3, colliders: &[WallCollider]) {
+        let to_player = Vec3::new(player_position.x - position.x, 0.0, player_position.z - position.z);
+        let player_cell = map.world_to_cell(player_position);
+
+        // 状态切换
+        match &mut self.state {
+            State::Flee { time, .. } => {
+                *time -= dt;
+                if *time <= 0.0 {
+                    self.wander(map, *position);
+                }
+            }
+            State::Follow if to_player.length() > LOSE_RADIUS || player_cell.is_none() => self.wander(map, *position),
+            State::Wander { .. } if to_player.length() < FOLLOW_RADIUS && player_cell.is_some() => self.state = State::Follow,
+            _ => {}
+        }
+
+        // 这一帧要走向的位置和速度
+        let (goal, speed) = match self.state {
+            State::Wander { target, pause } => match self.next_cell(map, *position, target, false) {
+                Some(next) => (Some(map.cell_center(next.0, next.1)), WANDER_SPEED),
+                None => {
+                    // 到了目标格子，停一会儿
+                    if pause > dt {
+                        self.state = State::Wander { target, pause: pause - dt };
+                    } else {
+                        self.wander(map, *position);
+                    }
+                    (None, 0.0)
+                }
+            },
+            // 到了玩家身边就停下
+            State::Follow if to_player.length() <= HEEL_DISTANCE => (None, 0.0),
+            State::Follow => {
+                let next = player_cell.and_then(|cell| self.next_cell(map, *position, cell, false));
+                // 和玩家在同一个格子时直接靠过去
+                let goal = next.map(|next| map.cell_center(next.0, next.1)).unwrap_or(*position + to_player);
+                (Some(goal), FOLLOW_SPEED)
+            }
+            State::Flee { from, .. } => (self.next_cell(map, *position, from, true).map(|next| map.cell_center(next.0, next.1)), FLEE_SPEED),
+        };
+
+        let Some(goal) = goal else {
+            self.hop = 0.0;
+            return;
+        };
+        let offset = Vec3::new(goal.x - position.x, 0.0, goal.z - position.z);
+        let step = offset.clamp_length_max(speed * dt);
+        let previous = *position;
+        // 碰撞体按离地一点的位置检测，悬挂在高处的东西挡不住狗
+        let mut moved_to = *position + step + Vec3::Y * RADIUS;
+        for collider in colliders {
+            moved_to = collider.resolve_collision(moved_to, RADIUS);
+        }
+        *position = Vec3::new(moved_to.x, 0.0, moved_to.z);
+        self.hop += dt * HOP_RATE;
+
+        // 几乎没走动时算卡住，闲逛时换一个目标
+        let moved = (*position - previous).length();
+        self.stuck = if moved < step.length() * 0.2 { self.stuck + dt } else { 0.0 };
+        if self.stuck > STUCK_TIME && matches!(self.state, State::Wander { .. }) {
+            self.wander(map, *position);
+        }
+    }
+}
+
+// 每只狗一个实体，包围盒和贴图一样大，准星对着时算友军
+pub fn spawn(world: &mut World, map: &Map) {
+    let half = Vec3::new(SPRITE_SIZE.0 / 2.0, 0.0, SPRITE_SIZE.0 / 2.0);
+    for &(col, row) in &map.dogs {
+        let position = map.cell_center(col, row);
+        let mut dog = Dog { state: State::Follow, route: None, hop: 0.0, stuck: 0.0 };
+        dog.wander(map, position);
+        let entity = world.spawn();
+        world.transforms.insert(entity, Transform { position, yaw: 0.0 });
+        world.colliders.insert(entity, Collider { min: -half, max: half + Vec3::Y * SPRITE_SIZE.1 });
+        world.dogs.insert(entity, dog);
+    }
+}
+
+pub fn positions(world: &World) -> Vec<Vec3> {
+    world.dogs.iter().filter_map(|(entity, _)| world.transforms.get(entity)).map(|transform| transform.position).collect()
+}
+
+// 枪声：听得到的狗从枪声的格子往远处跑
+pub fn hear_gunshot(world: &mut World, map: &Map, position: Vec3) {
+    let Some(from) = map.world_to_cell(position) else {
+        return;
+    };
+    let World { transforms, dogs, .. } = world;
+    for (entity, dog) in dogs.iter_mut() {
+        if transforms.get(entity).is_some_and(|transform| (transform.position - position).length() < HEARING_RADIUS) {
+            dog.state = State::Flee { from, time: FLEE_TIME };
+        }
+    }
+}
+
+pub fn update_system(world: &mut World, dt: f32, map: &Map, player_position: Vec3, colliders: &[WallCollider]) {
+    let World { transforms, dogs, .. } = world;
+    for (entity, dog) in dogs.iter_mut() {
+        if let Some(transform) = transforms.get_mut(entity) {
+            dog.update(&mut transform.position, dt, map, player_position, colliders);
+        }
+    }
+}
+
+// 朝向相机的贴图，每帧按相机位置重新生成。按透明度裁剪（model_type 2），用关卡材质里的 dog.png
+pub fn model(world: &World, device: &wgpu::Device, alpha: f32, camera_position: Vec3) -> Option<Model> {
+    // 没有狗时不生成模型
+    world.dogs.iter().next()?;
+    let mut mesh = MeshBuilder::new();
+    for (entity, dog) in world.dogs.iter() {
+        let Some(Transform { position, .. }) = ecs::interpolated(world, entity, alpha) else { continue };
+        let toward = Vec3::new(camera_position.x - position.x, 0.0, camera_position.z - position.z).try_normalize().unwrap_or(Vec3::Z);
+        // half_u × half_v 朝向相机
+        let half_u = Vec3::Y.cross(toward) * (SPRITE_SIZE.0 / 2.0);
+        let half_v = Vec3::Y * (SPRITE_SIZE.1 / 2.0);
+        let center = position + half_v + Vec3::Y * (dog.hop.sin().abs() * HOP_HEIGHT);
+        geometry::quad(&mut mesh, center, half_u, half_v, [1.0; 3], 2.0, TexCoords::FULL);
+    }
+    Some(mesh.build(device, "dogs", [0.0, 0.0, 0.0], false, gpu_memory::Category::Dynamic))
+}
@@ -0,0 +1,161 @@
+use glam::{Mat4, Quat, Vec3};
+
+use crate::anim_events::{AnimEvent, AnimEvents, Keyframe, Source};
+use crate::collision::WallCollider;
+use crate::ecs::{Collider, Render, Transform, World};
+use crate::map::{self, KeyColor, Map};
+use crate::model::BoxInstance;
+
+// 门：地图中的 D（普通门）和 1-3（锁门）格子，对准后按使用键开关，
+// 锁门需要对应颜色的钥匙卡，打开过一次后不再上锁。开关的声音和碰撞体跟着门板动画的关键帧走
+
+const THICKNESS: f32 = 0.2;
+// 墙体线段止于格子中心，所以门洞有两个格子宽
+const WIDTH: f32 = map::CELL_SIZE * 2.0;
+// 开门时门板向上收起的高度比例（留一截在门洞上方）
+const LIFT: f32 = 0.85;
+const OPEN_SPEED: f32 = 1.5;
+// 门板动画的关键帧：开门时先松开门闩，升过一半多以后可以通过，升到顶时撞一下；关门时降到这里挡住路，落到底时锁上门闩
+const TRACK: [Keyframe; 5] = [
+    Keyframe::forward(0.02, "door_unlatch"),
+    Keyframe::forward(0.6, "door_clear"),
+    Keyframe::forward(1.0, "door_stop"),
+    Keyframe::backward(0.6, "door_block"),
+    Keyframe::backward(0.0, "door_latch"),
+];
+
+const DOOR_COLOR: [f32; 3] = [0.4, 0.42, 0.45];
+
+#[derive(Debug, Clone)]
+pub struct Door {
+    pub col: usize,
+    pub row: usize,
+    pub lock: Option<KeyColor>,
+    // 门洞底部的中心，门板的位置（Transform）从这里往上升
+    center: Vec3,
+    // 0 为关闭，1 为完全打开
+    open: f32,
+    opening: bool,
+    // 有没有碰撞体，由 door_clear 和 door_block 事件切换
+    blocking: bool,
+}
+
+impl Door {
+    pub fn is_open(&self) -> bool {
+        self.opening
+    }
+
+    fn panel_center(&self) -> Vec3 {
+        self.center + Vec3::Y * (map::WALL_HEIGHT * (0.5 + self.open * LIFT))
+    }
+}
+
+// 使用门的结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DoorUse {
+    Opened,
+    Closed,
+    // 缺少的钥匙卡
+    Locked(KeyColor),
+}
+
+// 每扇门一个实体：位置是门板的中心，沿 z 轴的门绕 y 轴转 90 度。模型是门板和锁的颜色条，包围盒用于准星检测
+pub fn spawn(world: &mut World, map: &Map) {
+    for (col, row, lock) in map.doors() {
+        let door = Door { col, row, lock, center: map.cell_center(col, row), open: 0.0, opening: false, blocking: true };
+        let yaw = if map.runs_along_x(col, row) { 0.0 } else { std::f32::consts::FRAC_PI_2 };
+        let entity = world.spawn();
+        world.transforms.insert(entity, Transform { position: door.panel_center(), yaw });
+        world.renders.insert(entity, Render { boxes: boxes(lock) });
+        // 比碰撞体稍厚，保证射线先碰到包围盒
+        let half = Quat::from_rotation_y(yaw) * Vec3::new(WIDTH * 0.5, 0.0, THICKNESS * 0.5 + 0.02);
+        let half = Vec3::new(half.x.abs(), map::WALL_HEIGHT * 0.5, half.z.abs());
+        world.colliders.insert(entity, Collider { min: -half, max: half });
+        world.doors.insert(entity, door);
+    }
+}
+
+// 准星、交互提示里的门，编号是 Entity::index
+pub fn get(world: &World, index: usize) -> Option<&Door> {
+    world.doors.get(world.resolve(index)?)
+}
+
+// 开关门，锁门在持有钥匙卡（has_key 返回 true）时解锁
+pub fn use_door(world: &mut World, index: usize, has_key: impl Fn(KeyColor) -> bool) -> Option<DoorUse> {
+    let entity = world.resolve(index)?;
+    let door = world.doors.get_mut(entity)?;
+    if let Some(key) = door.lock {
+        if !has_key(key) {
+            return Some(DoorUse::Locked(key));
+        }
+        println!("用 {} 钥匙卡打开了门 ({}, {})", key.name(), door.col, door.row);
+        door.lock = None;
+        if let Some(render) = world.renders.get_mut(entity) {
+            render.boxes = boxes(None);
+        }
+    }
+    door.opening = !door.opening;
+    Some(if door.opening { DoorUse::Opened } else { DoorUse::Closed })
+}
+
+// 门板移动，越过的关键帧发到 events
+pub fn update_system(world: &mut World, dt: f32, events: &mut AnimEvents) {
+    let World { transforms, doors, .. } = world;
+    for (entity, door) in doors.iter_mut() {
+        let target = if door.opening { 1.0 } else { 0.0 };
+        let step = OPEN_SPEED * dt;
+        let from = door.open;
+        door.open = if door.open < target { (door.open + step).min(target) } else { (door.open - step).max(target) };
+        if let Some(transform) = transforms.get_mut(entity) {
+            transform.position = door.panel_center();
+        }
+        events.advance(&TRACK, from, door.open, Source::Door(entity.index()), door.panel_center());
+    }
+}
+
+// 门自己订阅的事件：门板升过或者降到关键帧时开关碰撞体
+pub fn on_event(world: &mut World, event: &AnimEvent) {
+    let Source::Door(index) = event.source;
+    let Some(door) = world.resolve(index).and_then(|entity| world.doors.get_mut(entity)) else { return };
+    match event.tag {
+        "door_clear" => door.blocking = false,
+        "door_block" => door.blocking = true,
+        _ => {}
+    }
+}
+
+// 关着的门的碰撞体
+pub fn colliders(world: &World) -> impl Iterator<Item = WallCollider> + '_ {
+    world.doors.iter().filter(|(_, door)| door.blocking).filter_map(|(entity, door)| {
+        let rotation = Quat::from_rotation_y(world.transforms.get(entity)?.yaw);
+        let tangent = rotation * Vec3::X;
+        let normal = rotation * Vec3::Z;
+        let start = door.center - tangent * (WIDTH * 0.5) - normal * (THICKNESS * 0.5);
+        let end = start + tangent * WIDTH;
+        Some(WallCollider::new(start.into(), end.into(), map::WALL_HEIGHT, THICKNESS))
+    })
+}
+
+// 小地图用的所有门
+pub fn doors(world: &World) -> Vec<Door> {
+    world.doors.iter().map(|(_, door)| door.clone()).collect()
+}
+
+// 门板，锁门两面各有一条钥匙卡颜色的横条
+fn boxes(lock: Option<KeyColor>) -> Vec<BoxInstance> {
+    let mut boxes = vec![BoxInstance {
+        transform: Mat4::from_scale(Vec3::new(WIDTH, map::WALL_HEIGHT, THICKNESS)),
+        color: DOOR_COLOR,
+    }];
+    if let Some(key) = lock {
+        boxes.push(BoxInstance {
+            transform: Mat4::from_scale_rotation_translation(
+                Vec3::new(WIDTH * 0.9, 0.2, THICKNESS + 0.04),
+                Quat::IDENTITY,
+                Vec3::Y * (1.4 - map::WALL_HEIGHT * 0.5),
+            ),
+            color: key.color(),
+        });
+    }
+    boxes
+}
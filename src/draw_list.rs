@@ -0,0 +1,240 @@
+use std::ops::Range;
+
+
+use crate::gpu_memory;
+use crate::model::Model;
+
+// 不透明物体的绘制列表：每帧收集绘制命令，按管线 → 材质（第 1 组绑定）→ 网格排序，
+// 执行时只在状态变化时切换。地图的静态几何体在加载时合并进一个大缓冲区（StaticBatch），
+// 同一管线和材质的网格是一段连续的间接绘制参数，支持 MULTI_DRAW_INDIRECT 时一次画完
+
+// 枚举的顺序就是排序的顺序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Pipeline {
+    Scene,
+    // 不剔除背面（双面的镂空材质）
+    DoubleSided,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Material {
+    // 场景默认的纹理
+    Level,
+    DecalAtlas,
+    // 敌人的贴图（正面和背面）
+    EnemySprites,
+    // 地面标记图集（车位线、箭头、编号）
+    FloorMarkings,
+    // 镂空材质，编号与 CutoutSet 中的顺序一致
+    Cutout(usize),
+}
+
+// 把管线和材质编号对应到实际的 wgpu 对象
+pub struct Resources<'a> {
+    pub scene: &'a wgpu::RenderPipeline,
+    pub double_sided: &'a wgpu::RenderPipeline,
+    pub level: &'a wgpu::BindGroup,
+    pub decal_atlas: &'a wgpu::BindGroup,
+    pub enemy_sprites: &'a wgpu::BindGroup,
+    pub floor_markings: &'a wgpu::BindGroup,
+    pub cutouts: Vec<&'a wgpu::BindGroup>,
+}
+
+impl<'a> Resources<'a> {
+    fn pipeline(&self, pipeline: Pipeline) -> &'a wgpu::RenderPipeline {
+        match pipeline {
+            Pipeline::Scene => self.scene,
+            Pipeline::DoubleSided => self.double_sided,
+        }
+    }
+
+    fn material(&self, material: Material) -> &'a wgpu::BindGroup {
+        match material {
+            Material::Level => self.level,
+            Material::DecalAtlas => self.decal_atlas,
+            Material::EnemySprites => self.enemy_sprites,
+            Material::FloorMarkings => self.floor_markings,
+            Material::Cutout(index) => self.cutouts[index],
+        }
+    }
+}
+
+// 静态几何体中一组管线和材质相同的网格
+struct BatchGroup {
+    pipeline: Pipeline,
+    material: Material,
+    // 在间接绘制参数缓冲区中的范围
+    draws: Range<u32>,
+}
+
+// 与 wgpu 的 DrawIndexedIndirect 参数布局一致
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct DrawIndexedArgs {
+    index_count: u32,
+    instance_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+}
+
+unsafe impl bytemuck::Pod for DrawIndexedArgs {}
+unsafe impl bytemuck::Zeroable for DrawIndexedArgs {}
+
+pub struct StaticBatch {
+    vertex_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    index_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    indirect_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    // 不支持多重间接绘制时逐个调用 draw_indexed
+    args: Vec<DrawIndexedArgs>,
+    groups: Vec<BatchGroup>,
+    multi_draw: bool,
+}
+
+impl StaticBatch {
+    // 在显存里把各个模型的顶点和索引复制到一起，索引仍然相对于各自的起始顶点
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, mut meshes: Vec<(Pipeline, Material, &Model)>) -> Self {
+        meshes.retain(|(_, _, model)| model.num_indices > 0);
+        meshes.sort_by_key(|(pipeline, material, _)| (*pipeline, *material));
+
+        let vertex_size: u64 = meshes.iter().map(|(_, _, model)| model.vertex_buffer.size()).sum();
+        let index_size: u64 = meshes.iter().map(|(_, _, model)| model.index_buffer.size()).sum();
+        let vertex_buffer = gpu_memory::buffer(device, gpu_memory::Category::Map, &wgpu::BufferDescriptor {
+            label: Some("Static Batch Vertex Buffer"),
+            size: vertex_size.max(4),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let index_buffer = gpu_memory::buffer(device, gpu_memory::Category::Map, &wgpu::BufferDescriptor {
+            label: Some("Static Batch Index Buffer"),
+            size: index_size.max(4),
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Static Batch Encoder") });
+        let mut args = Vec::with_capacity(meshes.len());
+        let mut groups: Vec<BatchGroup> = Vec::new();
+        let (mut vertex_offset, mut index_offset) = (0, 0);
+        for (pipeline, material, model) in &meshes {
+            encoder.copy_buffer_to_buffer(&model.vertex_buffer, 0, &vertex_buffer, vertex_offset, model.vertex_buffer.size());
+            encoder.copy_buffer_to_buffer(&model.index_buffer, 0, &index_buffer, index_offset, model.index_buffer.size());
+            let draw = args.len() as u32;
+            args.push(DrawIndexedArgs {
+                index_count: model.num_indices,
+                instance_count: 1,
+                first_index: (index_offset / std::mem::size_of::<u16>() as u64) as u32,
+                base_vertex: (vertex_offset / std::mem::size_of::<crate::model::ModelVertex>() as u64) as i32,
+                first_instance: 0,
+            });
+            match groups.last_mut() {
+                Some(group) if group.pipeline == *pipeline && group.material == *material => group.draws.end = draw + 1,
+                _ => groups.push(BatchGroup { pipeline: *pipeline, material: *material, draws: draw..draw + 1 }),
+            }
+            vertex_offset += model.vertex_buffer.size();
+            index_offset += model.index_buffer.size();
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let indirect_buffer = gpu_memory::buffer_init(device, gpu_memory::Category::Map, &wgpu::util::BufferInitDescriptor {
+            label: Some("Static Batch Indirect Buffer"),
+            contents: if args.is_empty() { &[0; 4] } else { bytemuck::cast_slice(&args) },
+            usage: wgpu::BufferUsages::INDIRECT,
+        });
+        let multi_draw = device.features().contains(wgpu::Features::MULTI_DRAW_INDIRECT);
+        println!(
+            "静态合批: {} 个网格合并为 {} 组{}",
+            args.len(),
+            groups.len(),
+            if multi_draw { "（多重间接绘制）" } else { "" }
+        );
+        Self { vertex_buffer, index_buffer, indirect_buffer, args, groups, multi_draw }
+    }
+
+    fn draw_group<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, group: &BatchGroup) {
+        if self.multi_draw {
+            let stride = std::mem::size_of::<DrawIndexedArgs>() as u64;
+            render_pass.multi_draw_indexed_indirect(&self.indirect_buffer, group.draws.start as u64 * stride, group.draws.len() as u32);
+        } else {
+            for args in &self.args[group.draws.start as usize..group.draws.end as usize] {
+                render_pass.draw_indexed(args.first_index..args.first_index + args.index_count, args.base_vertex, 0..1);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Mesh<'a> {
+    Model(&'a Model),
+    // 静态合批中的一组
+    Batch(&'a StaticBatch, usize),
+}
+
+impl Mesh<'_> {
+    // 同一状态下先画合批，再按缓冲区排列单独的模型
+    fn sort_key(&self) -> (u8, usize) {
+        match self {
+            Mesh::Batch(_, group) => (0, *group),
+            Mesh::Model(model) => (1, *model as *const Model as usize),
+        }
+    }
+}
+
+struct Command<'a> {
+    pipeline: Pipeline,
+    material: Material,
+    mesh: Mesh<'a>,
+}
+
+pub struct DrawList<'a> {
+    commands: Vec<Command<'a>>,
+}
+
+impl<'a> DrawList<'a> {
+    pub fn new() -> Self {
+        Self { commands: Vec::new() }
+    }
+
+    pub fn push(&mut self, pipeline: Pipeline, material: Material, model: &'a Model) {
+        self.commands.push(Command { pipeline, material, mesh: Mesh::Model(model) });
+    }
+
+    pub fn push_batch(&mut self, batch: &'a StaticBatch) {
+        for (index, group) in batch.groups.iter().enumerate() {
+            self.commands.push(Command { pipeline: group.pipeline, material: group.material, mesh: Mesh::Batch(batch, index) });
+        }
+    }
+
+    // 排序后执行，之后第 1 组绑定和管线处于最后一条命令的状态
+    pub fn execute(mut self, render_pass: &mut wgpu::RenderPass<'a>, resources: &Resources<'a>) {
+        self.commands.sort_by_key(|command| (command.pipeline, command.material, command.mesh.sort_key()));
+        let mut pipeline = None;
+        let mut material = None;
+        // 当前绑定的顶点缓冲区
+        let mut buffers: Option<*const wgpu::Buffer> = None;
+        for command in &self.commands {
+            if pipeline != Some(command.pipeline) {
+                render_pass.set_pipeline(resources.pipeline(command.pipeline));
+                pipeline = Some(command.pipeline);
+            }
+            if material != Some(command.material) {
+                render_pass.set_bind_group(1, resources.material(command.material), &[]);
+                material = Some(command.material);
+            }
+            match command.mesh {
+                Mesh::Model(model) => {
+                    model.draw(render_pass);
+                    buffers = Some(&*model.vertex_buffer as *const wgpu::Buffer);
+                }
+                Mesh::Batch(batch, group) => {
+                    if buffers != Some(&*batch.vertex_buffer as *const wgpu::Buffer) {
+                        render_pass.set_vertex_buffer(0, batch.vertex_buffer.slice(..));
+                        render_pass.set_index_buffer(batch.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                        buffers = Some(&*batch.vertex_buffer as *const wgpu::Buffer);
+                    }
+                    batch.draw_group(render_pass, &batch.groups[group]);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use glam::{Mat4, Quat, Vec3};
+
+use crate::collision::{Aabb, WallCollider};
+use crate::damage::StatusEffects;
+use crate::dog::Dog;
+use crate::door::Door;
+use crate::enemy_ai::Brain;
+use crate::model::BoxInstance;
+use crate::pickup::Pickup;
+use crate::practice::Target;
+use crate::projectile::Projectile;
+
+// 轻量的实体组件：实体是带代数的编号，每种组件一个按实体编号存放的数组。
+// 敌人、拾取物、门、狗和飞行中的投射物都是实体，渲染、物理、命中检测按组件统一遍历，各自的模块只管自己特有的逻辑（敌人的 AI、捡起拾取物）
+
+const GRAVITY: f32 = 9.8;
+const BOUNCE: f32 = 0.3;
+const FRICTION: f32 = 4.0;
+// 速度低于这个值并且在地上时停下不再模拟
+const REST_SPEED: f32 = 0.05;
+// 一次模拟里移动超过这个距离时不插值
+const TELEPORT_DISTANCE: f32 = 2.0;
+
+// 编号可以重复使用，代数区分同一个编号上先后出现的实体
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    // 准星、命中检测里的目标编号，同一帧内用 World::resolve 换回实体
+    pub fn index(self) -> usize {
+        self.index as usize
+    }
+}
+
+// 一种组件：下标是实体编号，记下所属实体的代数，实体删除后留下的旧组件不会被误用
+#[derive(Clone)]
+pub struct Components<T> {
+    slots: Vec<Option<(u32, T)>>,
+}
+
+impl<T> Components<T> {
+    fn new() -> Self {
+        Self { slots: Vec::new() }
+    }
+
+    pub fn insert(&mut self, entity: Entity, value: T) {
+        let index = entity.index();
+        if self.slots.len() <= index {
+            self.slots.resize_with(index + 1, || None);
+        }
+        self.slots[index] = Some((entity.generation, value));
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let slot = self.slots.get_mut(entity.index())?;
+        match slot {
+            Some((generation, _)) if *generation == entity.generation => slot.take().map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        match self.slots.get(entity.index())? {
+            Some((generation, value)) if *generation == entity.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        match self.slots.get_mut(entity.index())? {
+            Some((generation, value)) if *generation == entity.generation => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.as_ref().map(|(generation, value)| (Entity { index: index as u32, generation: *generation }, value))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.slots.iter_mut().enumerate().filter_map(|(index, slot)| {
+            slot.as_mut().map(|(generation, value)| (Entity { index: index as u32, generation: *generation }, value))
+        })
+    }
+}
+
+// 位置和绕 y 轴的朝向
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    pub position: Vec3,
+    pub yaw: f32,
+}
+
+impl Transform {
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_rotation_translation(Quat::from_rotation_y(self.yaw), self.position)
+    }
+
+    // 从 previous 到 self 之间的位置和朝向，t 从 0 到 1。一次模拟里移动太远的（瞬移、重生）不插值
+    fn interpolate(&self, previous: &Transform, t: f32) -> Transform {
+        if previous.position.distance_squared(self.position) > TELEPORT_DISTANCE * TELEPORT_DISTANCE {
+            return *self;
+        }
+        Transform { position: previous.position.lerp(self.position, t), yaw: previous.yaw + (self.yaw - previous.yaw) * t }
+    }
+}
+
+// 由方块组成的模型，方块的变换相对于 Transform
+pub struct Render {
+    pub boxes: Vec<BoxInstance>,
+}
+
+// 命中和准星检测用的包围盒，相对于位置（不随朝向旋转）
+#[derive(Debug, Clone, Copy)]
+pub struct Collider {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Health {
+    pub current: f32,
+}
+
+// 受重力影响、会撞墙和弹跳的物体
+#[derive(Debug, Clone, Copy)]
+pub struct Body {
+    pub velocity: Vec3,
+    // 绕 y 轴的转速
+    pub spin: f32,
+    // 撞墙时的半径
+    pub radius: f32,
+    // 停在地上时位置的高度
+    pub floor: f32,
+}
+
+impl Body {
+    fn resting(&self, position: Vec3) -> bool {
+        position.y <= self.floor + 1e-3 && self.velocity.length() < REST_SPEED
+    }
+}
+
+// 由 AI 控制的实体（敌人），home 是出生的位置，brain 是状态机和计时器（见 enemy_ai.rs）。
+// 警报叫来的增援 reinforcement 是叫来它的面板编号，死了不再出现
+#[derive(Debug, Clone)]
+pub struct Ai {
+    pub home: Vec3,
+    pub brain: Brain,
+    pub reinforcement: Option<usize>,
+}
+
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    pub transforms: Components<Transform>,
+    pub renders: Components<Render>,
+    pub colliders: Components<Collider>,
+    pub healths: Components<Health>,
+    pub bodies: Components<Body>,
+    pub ais: Components<Ai>,
+    // 燃烧、僵直这些持续状态
+    pub statuses: Components<StatusEffects>,
+    pub pickups: Components<Pickup>,
+    // 练习场的靶子
+    pub targets: Components<Target>,
+    pub doors: Components<Door>,
+    pub dogs: Components<Dog>,
+    pub projectiles: Components<Projectile>,
+    // 上一次模拟结束时的位置，渲染时在它和当前位置之间插值
+    previous: Components<Transform>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            generations: Vec::new(),
+            alive: Vec::new(),
+            free: Vec::new(),
+            transforms: Components::new(),
+            renders: Components::new(),
+            colliders: Components::new(),
+            healths: Components::new(),
+            bodies: Components::new(),
+            ais: Components::new(),
+            statuses: Components::new(),
+            pickups: Components::new(),
+            targets: Components::new(),
+            doors: Components::new(),
+            dogs: Components::new(),
+            projectiles: Components::new(),
+            previous: Components::new(),
+        }
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        match self.free.pop() {
+            Some(index) => {
+                self.alive[index as usize] = true;
+                Entity { index, generation: self.generations[index as usize] }
+            }
+            None => {
+                self.generations.push(0);
+                self.alive.push(true);
+                Entity { index: self.generations.len() as u32 - 1, generation: 0 }
+            }
+        }
+    }
+
+    // 删除实体和它的所有组件，编号留给之后的实体
+    pub fn despawn(&mut self, entity: Entity) {
+        if self.resolve(entity.index()) != Some(entity) {
+            return;
+        }
+        self.transforms.remove(entity);
+        self.renders.remove(entity);
+        self.colliders.remove(entity);
+        self.healths.remove(entity);
+        self.bodies.remove(entity);
+        self.ais.remove(entity);
+        self.statuses.remove(entity);
+        self.pickups.remove(entity);
+        self.targets.remove(entity);
+        self.doors.remove(entity);
+        self.dogs.remove(entity);
+        self.projectiles.remove(entity);
+        self.previous.remove(entity);
+        self.alive[entity.index()] = false;
+        self.generations[entity.index()] += 1;
+        self.free.push(entity.index);
+    }
+
+    // 换地图时调用：删除所有实体和组件。编号都放回空闲列表、代数加一，
+    // 别处留着的旧实体（准星目标、敌人管理器里的列表）不会被当成新地图上的实体
+    pub fn unload(&mut self) {
+        for index in 0..self.alive.len() {
+            if let Some(entity) = self.resolve(index) {
+                self.despawn(entity);
+            }
+        }
+        self.transforms = Components::new();
+        self.renders = Components::new();
+        self.colliders = Components::new();
+        self.healths = Components::new();
+        self.bodies = Components::new();
+        self.ais = Components::new();
+        self.statuses = Components::new();
+        self.pickups = Components::new();
+        self.targets = Components::new();
+        self.doors = Components::new();
+        self.dogs = Components::new();
+        self.projectiles = Components::new();
+        self.previous = Components::new();
+    }
+
+    // 分配过的实体编号个数（包括已删除的）
+    #[cfg(test)]
+    pub fn capacity(&self) -> usize {
+        self.generations.len()
+    }
+
+    // 每次模拟开始前调用，记下插值的起点
+    pub fn store_previous(&mut self) {
+        self.previous = self.transforms.clone();
+    }
+
+    // 编号上当前活着的实体
+    pub fn resolve(&self, index: usize) -> Option<Entity> {
+        self.alive
+            .get(index)
+            .copied()
+            .unwrap_or(false)
+            .then(|| Entity { index: index as u32, generation: self.generations[index] })
+    }
+}
+
+// 渲染：所有带模型的实体的方块。alpha 是在上一次和这一次模拟之间的位置，新出现的实体直接用当前位置。
+// 敌人（带 Ai 的实体）画成贴图（见 enemy_sprites.rs），这里跳过，它们的方块只用于描边
+pub fn render_system(world: &World, alpha: f32, out: &mut Vec<BoxInstance>) {
+    for (entity, _) in world.renders.iter().filter(|(entity, _)| world.ais.get(*entity).is_none()) {
+        entity_boxes(world, entity, alpha, out);
+    }
+}
+
+// 一个实体的方块（描边等只画某几个实体时用），没有模型时什么都不加
+pub fn entity_boxes(world: &World, entity: Entity, alpha: f32, out: &mut Vec<BoxInstance>) {
+    let (Some(render), Some(transform)) = (world.renders.get(entity), interpolated(world, entity, alpha)) else {
+        return;
+    };
+    let root = transform.matrix();
+    out.extend(render.boxes.iter().map(|part| BoxInstance { transform: root * part.transform, color: part.color }));
+}
+
+// 渲染用的位置和朝向，在上一次和这一次模拟之间插值
+pub fn interpolated(world: &World, entity: Entity, alpha: f32) -> Option<Transform> {
+    let transform = world.transforms.get(entity)?;
+    Some(match world.previous.get(entity) {
+        Some(previous) => transform.interpolate(previous, alpha),
+        None => *transform,
+    })
+}
+
+// 带某种组件（例如 Health、Pickup）的实体的包围盒，编号是 Entity::index
+pub fn hitboxes<T>(world: &World, with: &Components<T>) -> Vec<(usize, Aabb)> {
+    world
+        .colliders
+        .iter()
+        .filter(|(entity, _)| with.get(*entity).is_some())
+        .filter_map(|(entity, collider)| {
+            let position = world.transforms.get(entity)?.position;
+            Some((entity.index(), Aabb { min: position + collider.min, max: position + collider.max }))
+        })
+        .collect()
+}
+
+// 物理：重力、撞墙反弹，落地后弹一下，在地上滑动时受摩擦减速
+pub fn physics_system(world: &mut World, dt: f32, colliders: &[WallCollider]) {
+    let World { transforms, bodies, .. } = world;
+    for (entity, body) in bodies.iter_mut() {
+        let Some(transform) = transforms.get_mut(entity) else {
+            continue;
+        };
+        if body.resting(transform.position) {
+            continue;
+        }
+        body.velocity.y -= GRAVITY * dt;
+        transform.position += body.velocity * dt;
+        transform.yaw += body.spin * dt;
+
+        // 撞墙时去掉朝向墙的速度并反弹一点
+        for collider in colliders {
+            let resolved = collider.resolve_collision(transform.position, body.radius);
+            let push = resolved - transform.position;
+            if push.length_squared() > 1e-8 {
+                let normal = push.normalize();
+                let into = body.velocity.dot(normal);
+                if into < 0.0 {
+                    body.velocity -= normal * into * (1.0 + BOUNCE);
+                }
+                transform.position = resolved;
+            }
+        }
+
+        if transform.position.y <= body.floor {
+            transform.position.y = body.floor;
+            if body.velocity.y < 0.0 {
+                body.velocity.y = -body.velocity.y * BOUNCE;
+            }
+            if body.velocity.y < 0.5 {
+                body.velocity.y = 0.0;
+            }
+            let slow = (1.0 - FRICTION * dt).max(0.0);
+            body.velocity.x *= slow;
+            body.velocity.z *= slow;
+            body.spin *= slow;
+        }
+    }
+}
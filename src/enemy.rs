@@ -0,0 +1,690 @@
+use std::collections::{HashMap, VecDeque};
+use std::f32::consts::FRAC_PI_2;
+use std::path::Path;
+
+use glam::{Mat4, Quat, Vec3};
+
+use crate::assets;
+use crate::collision::{self, WallCollider};
+use crate::damage::{Damage, DamageType, Resistances, StatusEffects};
+use crate::ecs::{Ai, Collider, Entity, Health, Render, Transform, World};
+use crate::enemy_ai::{self, AiState, Brain, Senses};
+use crate::loot::{LootItem, LootTable};
+use crate::map::Map;
+use crate::model::BoxInstance;
+use crate::nav::{self, DistanceField};
+use crate::particles::{Burst, Particle, ParticleSystem};
+use crate::schema;
+
+// 敌人：站在地图的 E 格子上，被打死后播放死亡动画/布娃娃或炸成碎块，尸体过一段时间沉入地面。
+// 活着的敌人是 ecs 里的实体（Transform、Render、Collider、Health、Ai），死后删除，到时间在出生点重新生成
+// 活着的敌人按 enemy_ai 的状态机站岗、巡逻、追击玩家和开枪，开的枪由调用者取走结算
+
+// 高于这个高度的命中算爆头
+const HEAD_HEIGHT: f32 = 1.4;
+pub const HEADSHOT_MULTIPLIER: f32 = 2.0;
+const HALF_WIDTH: f32 = 0.35;
+const HEIGHT: f32 = 1.75;
+// 死亡动画时长（秒）
+const FALL_TIME: f32 = 0.6;
+const CRUMPLE_TIME: f32 = 0.5;
+
+const LEGS_COLOR: [f32; 3] = [0.15, 0.18, 0.3];
+const TORSO_COLOR: [f32; 3] = [0.6, 0.12, 0.1];
+const HEAD_COLOR: [f32; 3] = [0.85, 0.65, 0.5];
+const BLOOD_COLOR: [f32; 3] = [0.5, 0.0, 0.0];
+// 关闭血腥内容时用灰色的碎屑代替血雾
+const DUST_COLOR: [f32; 3] = [0.55, 0.55, 0.5];
+// 爆炸的烟、火焰的火星和电击的电火花
+const SMOKE_COLOR: [f32; 3] = [0.3, 0.3, 0.3];
+const EMBER_COLOR: [f32; 3] = [1.0, 0.45, 0.1];
+const ARC_COLOR: [f32; 3] = [0.5, 0.75, 1.0];
+// 燃烧的敌人每秒冒出几次火星
+const BURN_EMBER_RATE: f32 = 12.0;
+// 波次模式下每个出生点刷出敌人的间隔（秒）
+const WAVE_SPAWN_INTERVAL: f32 = 4.0;
+// AI 调试面板：所有敌人一共保留的最近事件数，和每个敌人显示的条数
+const MAX_AI_EVENTS: usize = 128;
+const INSPECT_EVENTS: usize = 5;
+// 眼睛的高度，判断能不能看到玩家；开枪时枪口的高度
+const EYE_HEIGHT: f32 = 1.57;
+const TORSO_HEIGHT: f32 = 1.25;
+// 能发现玩家的距离：玩家全亮时和全暗时（见 stealth.rs），中间按亮度插值
+const SIGHT_RANGE: f32 = 30.0;
+const DARK_SIGHT_RANGE: f32 = 6.0;
+
+// 巡逻和追击的速度（米/秒），追到这个距离以内不再靠近
+const PATROL_SPEED: f32 = 1.4;
+const CHASE_SPEED: f32 = 3.0;
+const CHASE_STOP: f32 = 2.0;
+// 巡逻时离格子中心这么近算到了
+const ARRIVE_DISTANCE: f32 = 0.3;
+// 拉直路线时往前看几个格子
+const SMOOTH_LOOKAHEAD: usize = 6;
+// 敌人开枪的伤害、命中率，打偏时子弹离玩家多远
+pub const SHOT_DAMAGE: f32 = 8.0;
+const HIT_CHANCE: f32 = 0.4;
+const MISS_SPREAD: f32 = 0.8;
+// 增援出来以后不用看到玩家也朝玩家追多久（秒）
+const REINFORCEMENT_HUNT: f32 = 20.0;
+
+pub fn sight_range(exposure: f32) -> f32 {
+    DARK_SIGHT_RANGE + (SIGHT_RANGE - DARK_SIGHT_RANGE) * exposure.clamp(0.0, 1.0)
+}
+
+// 身体各部分（脚底为原点的局部坐标）：中心、尺寸、颜色。活着的敌人的贴图按同样的比例生成
+pub const BODY_PARTS: [(Vec3, Vec3, [f32; 3]); 3] = [
+    (Vec3::new(0.0, 0.45, 0.0), Vec3::new(0.5, 0.9, 0.3), LEGS_COLOR),
+    (Vec3::new(0.0, 1.15, 0.0), Vec3::new(0.7, 0.5, 0.4), TORSO_COLOR),
+    (Vec3::new(0.0, 1.57, 0.0), Vec3::new(0.3, 0.3, 0.3), HEAD_COLOR),
+];
+
+// data/enemies.json 的格式版本
+const ENEMIES_VERSION: u32 = 1;
+
+// data/enemies.json
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct EnemyConfig {
+    pub health: f32,
+    // 死亡后多久在出生格子上重新出现（秒）
+    pub respawn_time: f32,
+    // 尸体保留多久后开始沉入地面（秒）
+    pub corpse_fade_time: f32,
+    pub corpse_sink_time: f32,
+    // 尸体数量上限，超过时最早的尸体立即开始沉入地面
+    pub max_corpses: usize,
+    // 子弹击杀时使用布娃娃（而不是死亡动画）的概率
+    pub ragdoll_chance: f32,
+    // 敌人拿的武器（data/weapons.json 中的 id），死亡时按概率掉落
+    pub weapon: String,
+    pub weapon_drop_chance: f32,
+    // 各伤害类型的倍数
+    #[serde(default)]
+    pub resistances: Resistances,
+}
+
+impl EnemyConfig {
+    pub fn load() -> Self {
+        let text = assets::read_to_string_or_builtin(Path::new("data/enemies.json"), include_str!("../data/enemies.json"));
+        schema::parse_json(&text, ENEMIES_VERSION)
+            .unwrap_or_else(|e| panic!("敌人配置解析失败 (data/enemies.json): {}", e))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DeathStyle {
+    FallBackward,
+    FallForward,
+    Crumple,
+    // 简化的布娃娃：整个身体绕脚底倒下，受击方向决定倒向和初速度
+    Ragdoll { axis: Vec3, angle: f32, angular_velocity: f32, velocity: Vec3 },
+}
+
+struct Corpse {
+    position: Vec3,
+    yaw: f32,
+    style: DeathStyle,
+    time: f32,
+}
+
+impl Corpse {
+    // 尸体的旋转和纵向缩放（相对于脚底）
+    fn pose(&self) -> (Quat, f32) {
+        let eased = |duration: f32| {
+            let t = (self.time / duration).min(1.0);
+            t * t
+        };
+        match self.style {
+            DeathStyle::FallBackward => (Quat::from_rotation_x(FRAC_PI_2 * eased(FALL_TIME)), 1.0),
+            DeathStyle::FallForward => (Quat::from_rotation_x(-FRAC_PI_2 * eased(FALL_TIME)), 1.0),
+            DeathStyle::Crumple => {
+                let t = eased(CRUMPLE_TIME);
+                (Quat::from_rotation_z(0.3 * t), 1.0 - 0.65 * t)
+            }
+            // 布娃娃的旋转轴是世界坐标，需要抵消身体朝向
+            DeathStyle::Ragdoll { axis, angle, .. } => {
+                (Quat::from_rotation_y(-self.yaw) * Quat::from_axis_angle(axis, angle) * Quat::from_rotation_y(self.yaw), 1.0)
+            }
+        }
+    }
+}
+
+// AI 调试面板里一个敌人的信息
+#[derive(Debug, Clone)]
+pub struct Inspection {
+    pub state: &'static str,
+    pub health: f32,
+    pub max_health: f32,
+    // 走到玩家的路线（拉直后的拐点，离地一点），走不到时为空
+    pub path: Vec<Vec3>,
+    // 感知：到玩家的距离、玩家偏离正面的角度（度）、中间有没有墙，和现在能发现玩家的距离
+    pub distance: f32,
+    pub angle: f32,
+    pub line_of_sight: bool,
+    pub sight_range: f32,
+    pub burning: f32,
+    pub stunned: f32,
+    // 最近发生的事和多少秒以前，新的在前
+    pub events: Vec<(f32, String)>,
+}
+
+// 敌人开的一枪，由调用者取走画曳光、结算伤害
+#[derive(Debug, Clone, Copy)]
+pub struct Shot {
+    pub from: Vec3,
+    pub to: Vec3,
+    pub hit: bool,
+}
+
+// 一次命中的结果
+#[derive(Debug, Clone, Copy)]
+pub struct HitResult {
+    // 敌人脚下的位置（用于地面血迹）
+    pub position: Vec3,
+    pub headshot: bool,
+    pub killed: bool,
+}
+
+pub struct EnemyManager {
+    config: EnemyConfig,
+    loot: LootTable,
+    // 地图上的出生点
+    homes: Vec<Vec3>,
+    // 死掉的敌人（波次模式下是还没刷出来的敌人）：出生点和出现前的剩余时间
+    respawns: Vec<(Vec3, f32)>,
+    // 波次模式下死了不再重新出现
+    waves: bool,
+    corpses: VecDeque<Corpse>,
+    particles: ParticleSystem,
+    // 燃烧致死的敌人，由调用者取走记成击杀
+    status_kills: Vec<HitResult>,
+    // AI 调试面板用：敌人的最近事件和发生的时间
+    time: f32,
+    ai_events: VecDeque<(Entity, f32, String)>,
+    // 关闭时没有血雾和碎尸
+    pub gore: bool,
+    // 上一次模拟时能发现玩家的距离
+    sight_range: f32,
+    // 导航用的距离场：追击时到玩家所在格子的，巡逻时到各个巡逻点的（巡逻点不多，都留着）
+    chase_field: Option<((usize, usize), DistanceField)>,
+    patrol_fields: HashMap<(usize, usize), DistanceField>,
+    shots: Vec<Shot>,
+    // 这一步刚发现玩家的敌人的位置（拉响附近的警报）
+    sightings: Vec<Vec3>,
+}
+
+impl EnemyManager {
+    pub fn new(world: &mut World, map: &Map, gore: bool) -> Self {
+        let mut manager = Self {
+            config: EnemyConfig::load(),
+            loot: LootTable::load(),
+            homes: map.enemy_spawns(),
+            respawns: Vec::new(),
+            waves: false,
+            corpses: VecDeque::new(),
+            particles: ParticleSystem::new(),
+            status_kills: Vec::new(),
+            time: 0.0,
+            ai_events: VecDeque::new(),
+            gore,
+            sight_range: SIGHT_RANGE,
+            chase_field: None,
+            patrol_fields: HashMap::new(),
+            shots: Vec::new(),
+            sightings: Vec::new(),
+        };
+        for home in manager.homes.clone() {
+            manager.spawn(world, map, home);
+        }
+        manager
+    }
+
+    // 进入波次模式：清掉现有的敌人，之后由 spawn_wave 刷出
+    pub fn start_waves(&mut self, world: &mut World) {
+        let enemies: Vec<Entity> = world.ais.iter().map(|(entity, _)| entity).collect();
+        for entity in enemies {
+            world.despawn(entity);
+        }
+        self.respawns.clear();
+        self.waves = true;
+    }
+
+    // 一波敌人轮流排在各个出生点上，每个出生点隔一段时间出来一个
+    pub fn spawn_wave(&mut self, count: usize) {
+        for (i, home) in self.homes.iter().cycle().take(count).enumerate() {
+            self.respawns.push((*home, (i / self.homes.len()) as f32 * WAVE_SPAWN_INTERVAL));
+        }
+    }
+
+    // 还活着的和还没刷出来的敌人数
+    pub fn remaining(&self, world: &World) -> usize {
+        world.ais.iter().count() + self.respawns.len()
+    }
+
+    fn spawn(&mut self, world: &mut World, map: &Map, home: Vec3) -> Entity {
+        let entity = world.spawn();
+        world.transforms.insert(entity, Transform { position: home, yaw: 0.0 });
+        let mut boxes = Vec::new();
+        push_body(&mut boxes, Vec3::ZERO, Quat::IDENTITY, 1.0);
+        world.renders.insert(entity, Render { boxes });
+        let half = Vec3::new(HALF_WIDTH, 0.0, HALF_WIDTH);
+        world.colliders.insert(entity, Collider { min: -half, max: half + Vec3::Y * HEIGHT });
+        world.healths.insert(entity, Health { current: self.config.health });
+        let waypoints = map.world_to_cell(home).map_or_else(Vec::new, |cell| enemy_ai::patrol_route(map, cell));
+        world.ais.insert(entity, Ai { home, brain: Brain::new(waypoints), reinforcement: None });
+        world.statuses.insert(entity, StatusEffects::default());
+        self.log(entity, "SPAWNED".to_string());
+        entity
+    }
+
+    // 警报叫来的增援：从离 near 最近的几个出生点出来，直接朝玩家追过去，死了不再出现。panel 是叫来它们的面板
+    pub fn reinforce(&mut self, world: &mut World, map: &Map, panel: usize, near: Vec3, count: usize) {
+        let mut homes = self.homes.clone();
+        homes.sort_by(|a, b| a.distance(near).total_cmp(&b.distance(near)));
+        for home in homes.into_iter().cycle().take(count) {
+            let entity = self.spawn(world, map, home);
+            if let Some(ai) = world.ais.get_mut(entity) {
+                ai.reinforcement = Some(panel);
+                ai.brain.alert(REINFORCEMENT_HUNT);
+            }
+            self.log(entity, "REINFORCEMENT".to_string());
+        }
+    }
+
+    fn log(&mut self, entity: Entity, event: String) {
+        self.ai_events.push_back((entity, self.time, event));
+        if self.ai_events.len() > MAX_AI_EVENTS {
+            self.ai_events.pop_front();
+        }
+    }
+
+    // 击杀后随机掉落的东西，currency 为 false 时不掉钱
+    pub fn roll_loot(&self, currency: bool) -> Vec<LootItem> {
+        self.loot.roll(currency)
+    }
+
+    // 击杀后是否掉落武器，返回武器 id
+    pub fn roll_weapon_drop(&self) -> Option<&str> {
+        (fastrand::f32() < self.config.weapon_drop_chance).then_some(self.config.weapon.as_str())
+    }
+
+    // 面板 panel 叫来的增援还活着几个
+    pub fn alive_reinforcements(&self, world: &World, panel: usize) -> usize {
+        world.ais.iter().filter(|(_, ai)| ai.reinforcement == Some(panel)).count()
+    }
+
+    pub fn alive_positions(&self, world: &World) -> Vec<Vec3> {
+        world.ais.iter().filter_map(|(entity, _)| world.transforms.get(entity)).map(|transform| transform.position).collect()
+    }
+
+    // AI 调试面板：编号为 index 的命中盒是活着的敌人时返回它的状态、到玩家的路线、感知和最近的事件
+    pub fn inspect(&self, world: &World, map: &Map, colliders: &[WallCollider], index: usize, player_position: Vec3) -> Option<Inspection> {
+        let entity = world.resolve(index)?;
+        let transform = world.transforms.get(entity)?;
+        let status = world.statuses.get(entity).copied().unwrap_or_default();
+        let state = if status.is_stunned() { "STUNNED" } else { world.ais.get(entity)?.brain.state.name() };
+
+        let cells = match (map.world_to_cell(transform.position), map.world_to_cell(player_position)) {
+            (Some(from), Some(to)) => DistanceField::compute(map, &[to]).path(from),
+            _ => Vec::new(),
+        };
+        let path = if cells.is_empty() {
+            Vec::new()
+        } else {
+            let mut points = vec![Vec3::new(transform.position.x, 0.0, transform.position.z)];
+            points.extend(cells.into_iter().skip(1).map(|(col, row)| map.cell_center(col, row)));
+            points.push(Vec3::new(player_position.x, 0.0, player_position.z));
+            // 和走路时一样拉直
+            nav::string_pull(&points, |from, to| nav::walkable_line(map, colliders, from, to, HALF_WIDTH))
+                .into_iter()
+                .map(|point| point + Vec3::Y * 0.05)
+                .collect()
+        };
+
+        let eye = transform.position + Vec3::Y * EYE_HEIGHT;
+        let to_player = player_position - transform.position;
+        let flat = Vec3::new(to_player.x, 0.0, to_player.z);
+        // 模型的正面是旋转后的 -z
+        let forward = Quat::from_rotation_y(transform.yaw) * Vec3::NEG_Z;
+        let angle = if flat.length_squared() > 1e-6 { forward.angle_between(flat).to_degrees() } else { 0.0 };
+        let events = self
+            .ai_events
+            .iter()
+            .rev()
+            .filter(|(owner, _, _)| *owner == entity)
+            .take(INSPECT_EVENTS)
+            .map(|(_, time, event)| (self.time - time, event.clone()))
+            .collect();
+        Some(Inspection {
+            state,
+            health: world.healths.get(entity).map_or(0.0, |health| health.current),
+            max_health: self.config.health,
+            path,
+            distance: to_player.length(),
+            angle,
+            line_of_sight: collision::count_occluders(colliders, eye, player_position) == 0,
+            sight_range: self.sight_range,
+            burning: status.burning(),
+            stunned: status.stunned(),
+            events,
+        })
+    }
+
+    // 对敌人造成伤害，index 是命中盒的编号（ecs::hitboxes），dir 为伤害的方向（子弹飞行方向或爆炸中心指向敌人）
+    // point 和 normal 是命中点和打进去的那个面的法向量，dir 是子弹的方向
+    pub fn damage(&mut self, world: &mut World, index: usize, damage: Damage, point: Vec3, normal: Vec3, dir: Vec3) -> Option<HitResult> {
+        let entity = world.resolve(index)?;
+        let position = world.transforms.get(entity)?.position;
+        let headshot = damage.kind == DamageType::Bullet && point.y - position.y >= HEAD_HEIGHT;
+        let amount = if headshot { damage.amount * HEADSHOT_MULTIPLIER } else { damage.amount };
+        let damage = self.config.resistances.apply(Damage { amount, ..damage });
+        if let Some(status) = world.statuses.get_mut(entity) {
+            status.apply(damage);
+        }
+        let kind = format!("{:?}", damage.kind).to_uppercase();
+        self.log(entity, format!("{} {:.0} {}", if headshot { "HEADSHOT" } else { "HIT" }, damage.amount, kind));
+        self.hit_particles(damage.kind, point, normal, dir);
+        self.hurt(world, entity, damage, dir, headshot)
+    }
+
+    // 扣血（已经算过抗性），死了就换成尸体，到时间重生
+    fn hurt(&mut self, world: &mut World, entity: Entity, damage: Damage, dir: Vec3, headshot: bool) -> Option<HitResult> {
+        let Transform { position, yaw } = *world.transforms.get(entity)?;
+        let Ai { home, reinforcement, .. } = *world.ais.get(entity)?;
+        let health = world.healths.get_mut(entity)?;
+        health.current -= damage.amount;
+        if health.current > 0.0 {
+            return Some(HitResult { position, headshot, killed: false });
+        }
+        world.despawn(entity);
+        if !self.waves && reinforcement.is_none() {
+            self.respawns.push((home, self.config.respawn_time));
+        }
+        match damage.kind {
+            DamageType::Explosive if self.gore => self.spawn_gibs(position, yaw, dir),
+            _ => self.spawn_corpse(position, yaw, dir, headshot),
+        }
+        Some(HitResult { position, headshot, killed: true })
+    }
+
+    // 命中点的粒子：子弹和近战是顺着攻击方向的血雾（或灰色碎屑），其他类型是烟、火星和从被打的面弹出来的电火花
+    fn hit_particles(&mut self, kind: DamageType, point: Vec3, normal: Vec3, dir: Vec3) {
+        let (count, direction, speed, color) = match kind {
+            DamageType::Bullet | DamageType::Melee => (6, dir, 3.0, if self.gore { BLOOD_COLOR } else { DUST_COLOR }),
+            DamageType::Explosive => (10, Vec3::Y, 1.5, SMOKE_COLOR),
+            DamageType::Fire => (8, Vec3::Y, 2.0, EMBER_COLOR),
+            DamageType::Electric => (10, normal, 4.0, ARC_COLOR),
+        };
+        self.particles.burst(point, Burst { count, direction, speed, spread: 0.5, size: 0.04, life: 0.6, color });
+    }
+
+    // 燃烧致死的敌人
+    pub fn take_status_kills(&mut self) -> Vec<HitResult> {
+        std::mem::take(&mut self.status_kills)
+    }
+
+    // 子弹击杀：随机选择死亡动画，或者按受击方向倒下的布娃娃
+    fn spawn_corpse(&mut self, position: Vec3, yaw: f32, dir: Vec3, headshot: bool) {
+        let push = Vec3::new(dir.x, 0.0, dir.z).normalize_or_zero();
+        let style = if push != Vec3::ZERO && (headshot || fastrand::f32() < self.config.ragdoll_chance) {
+            DeathStyle::Ragdoll {
+                // 身体向受击方向倒下
+                axis: Vec3::Y.cross(push).normalize(),
+                angle: 0.0,
+                angular_velocity: if headshot { 3.0 } else { 1.5 },
+                velocity: push * 1.5,
+            }
+        } else {
+            // 面向玩家时，子弹从正面打来，向后倒的概率更高
+            match fastrand::u32(0..4) {
+                0 | 1 => DeathStyle::FallBackward,
+                2 => DeathStyle::FallForward,
+                _ => DeathStyle::Crumple,
+            }
+        };
+        self.corpses.push_back(Corpse { position, yaw, style, time: 0.0 });
+
+        // 超过上限时最早的尸体立即开始沉入地面
+        let excess = self.corpses.len().saturating_sub(self.config.max_corpses);
+        for corpse in self.corpses.iter_mut().take(excess) {
+            corpse.time = corpse.time.max(self.config.corpse_fade_time);
+        }
+    }
+
+    // 爆炸击杀：身体各部分炸成碎块，加上大量血雾
+    fn spawn_gibs(&mut self, position: Vec3, yaw: f32, dir: Vec3) {
+        let rotation = Quat::from_rotation_y(yaw);
+        for (center, size, color) in BODY_PARTS {
+            // 每个部分分成两块
+            for _ in 0..2 {
+                let outward = (dir + crate::particles::random_unit_vector() * 0.8 + Vec3::Y).normalize_or_zero();
+                self.particles.spawn(Particle {
+                    position: position + rotation * center,
+                    velocity: outward * (4.0 + fastrand::f32() * 4.0),
+                    size: size * Vec3::new(0.7, 0.5, 0.7),
+                    rotation,
+                    spin: crate::particles::random_unit_vector() * 8.0,
+                    color,
+                    life: self.config.corpse_fade_time,
+                    max_life: self.config.corpse_fade_time,
+                });
+            }
+        }
+        self.particles.burst(position + Vec3::Y, Burst {
+            count: 40,
+            direction: Vec3::Y,
+            speed: 6.0,
+            spread: 1.0,
+            size: 0.06,
+            life: 1.2,
+            color: BLOOD_COLOR,
+        });
+    }
+
+    // 这一步敌人开的枪
+    pub fn take_shots(&mut self) -> Vec<Shot> {
+        std::mem::take(&mut self.shots)
+    }
+
+    // 这一步刚发现玩家（从站着或巡逻转为追击）的敌人的位置
+    pub fn take_sightings(&mut self) -> Vec<Vec3> {
+        std::mem::take(&mut self.sightings)
+    }
+
+    // sight_range 是这一次能发现玩家的距离（见 sight_range），player_position 是玩家眼睛的位置
+    pub fn update(&mut self, world: &mut World, map: &Map, colliders: &[WallCollider], dt: f32, player_position: Vec3, sight_range: f32) {
+        self.sight_range = sight_range;
+        self.time += dt;
+        for (_, timer) in &mut self.respawns {
+            *timer -= dt;
+        }
+        let (ready, waiting) = self.respawns.drain(..).partition(|(_, timer)| *timer <= 0.0);
+        self.respawns = waiting;
+        // 出生点上还站着敌人时等他死了再出来
+        for (home, timer) in ready {
+            let occupied = world.ais.iter().any(|(entity, _)| {
+                world.transforms.get(entity).is_some_and(|transform| transform.position.distance(home) < HALF_WIDTH * 2.0)
+            });
+            if occupied {
+                self.respawns.push((home, timer));
+            } else {
+                self.spawn(world, map, home);
+            }
+        }
+
+        // 燃烧掉血
+        let mut burns = Vec::new();
+        for (entity, status) in world.statuses.iter_mut() {
+            if let Some(burn) = status.update(dt) {
+                burns.push((entity, self.config.resistances.apply(burn)));
+            }
+        }
+        for (entity, burn) in burns {
+            let Some(position) = world.transforms.get(entity).map(|transform| transform.position) else { continue };
+            if fastrand::f32() < BURN_EMBER_RATE * dt {
+                let point = position + Vec3::new(fastrand::f32() - 0.5, 0.3 + fastrand::f32() * 1.3, fastrand::f32() - 0.5) * Vec3::new(0.6, 1.0, 0.6);
+                self.particles.burst(point, Burst { count: 2, direction: Vec3::Y, speed: 1.0, spread: 0.4, size: 0.03, life: 0.5, color: EMBER_COLOR });
+            }
+            if let Some(result) = self.hurt(world, entity, burn, Vec3::ZERO, false).filter(|result| result.killed) {
+                self.status_kills.push(result);
+            }
+        }
+
+        self.think(world, map, colliders, dt, player_position, sight_range);
+
+        for corpse in &mut self.corpses {
+            corpse.time += dt;
+            if let DeathStyle::Ragdoll { angle, angular_velocity, velocity, .. } = &mut corpse.style {
+                // 越倒越快（重力矩与倾角的正弦成正比），倒地后停止
+                *angular_velocity += 12.0 * angle.sin().max(0.05) * dt;
+                *angle += *angular_velocity * dt;
+                if *angle >= FRAC_PI_2 {
+                    *angle = FRAC_PI_2;
+                    *angular_velocity = 0.0;
+                }
+                corpse.position += *velocity * dt;
+                *velocity *= (1.0 - 4.0 * dt).max(0.0);
+            }
+        }
+        let lifetime = self.config.corpse_fade_time + self.config.corpse_sink_time;
+        self.corpses.retain(|corpse| corpse.time < lifetime);
+
+        self.particles.update(dt);
+    }
+
+    // AI：每个敌人按感知推进状态机，然后按状态站着、走向巡逻点、追向玩家或者开火。电击僵直时不动
+    fn think(&mut self, world: &mut World, map: &Map, colliders: &[WallCollider], dt: f32, player_position: Vec3, sight_range: f32) {
+        let player_cell = map.world_to_cell(player_position);
+        let entities: Vec<Entity> = world.ais.iter().map(|(entity, _)| entity).collect();
+        for entity in entities {
+            if world.statuses.get(entity).is_some_and(|status| status.is_stunned()) {
+                continue;
+            }
+            let (Some(transform), Some(ai)) = (world.transforms.get_mut(entity), world.ais.get_mut(entity)) else { continue };
+            let eye = transform.position + Vec3::Y * EYE_HEIGHT;
+            let to_player = Vec3::new(player_position.x - transform.position.x, 0.0, player_position.z - transform.position.z);
+            let senses = Senses {
+                distance: to_player.length(),
+                line_of_sight: to_player.length() <= sight_range && collision::count_occluders(colliders, eye, player_position) == 0,
+                sight_range,
+            };
+            let arrived = ai.brain.state == AiState::Patrol && ai.brain.waypoint().is_none_or(|waypoint| {
+                let field = self.patrol_fields.entry(waypoint).or_insert_with(|| DistanceField::compute(map, &[waypoint]));
+                arrived_at(transform.position, map, field)
+            });
+            let before = ai.brain.state;
+            let changed = ai.brain.tick(dt, senses, arrived);
+            if matches!(before, AiState::Idle | AiState::Patrol) && changed == Some(AiState::Chase) {
+                self.sightings.push(transform.position);
+            }
+
+            let start = transform.position;
+            let walking = match ai.brain.state {
+                AiState::Idle => false,
+                AiState::Patrol => match ai.brain.waypoint().and_then(|waypoint| self.patrol_fields.get(&waypoint)) {
+                    Some(field) => walk(transform, map, field, None, PATROL_SPEED, dt, colliders),
+                    None => false,
+                },
+                AiState::Chase if senses.distance <= CHASE_STOP => false,
+                AiState::Chase => match player_cell {
+                    Some(cell) => {
+                        if self.chase_field.as_ref().is_none_or(|(source, _)| *source != cell) {
+                            self.chase_field = Some((cell, DistanceField::compute(map, &[cell])));
+                        }
+                        let field = &self.chase_field.as_ref().unwrap().1;
+                        walk(transform, map, field, Some(player_position), CHASE_SPEED, dt, colliders)
+                    }
+                    None => false,
+                },
+                AiState::Attack => {
+                    transform.yaw = (-to_player.x).atan2(-to_player.z);
+                    if ai.brain.fire() {
+                        let forward = Quat::from_rotation_y(transform.yaw) * Vec3::NEG_Z;
+                        let from = transform.position + Vec3::Y * TORSO_HEIGHT + forward * 0.4;
+                        let hit = fastrand::f32() < HIT_CHANCE;
+                        let to = if hit { player_position } else { player_position + crate::particles::random_unit_vector() * MISS_SPREAD };
+                        self.shots.push(Shot { from, to, hit });
+                    }
+                    false
+                }
+            };
+            // 巡逻时卡住太久就换下一个巡逻点
+            let blocked = walking && start.distance(transform.position) < PATROL_SPEED * dt * 0.2;
+            if ai.brain.walked(dt, blocked) && ai.brain.state == AiState::Patrol {
+                ai.brain.tick(0.0, Senses { line_of_sight: false, ..senses }, true);
+            }
+            if let Some(state) = changed {
+                self.log(entity, state.name().to_string());
+            }
+        }
+    }
+
+    // 尸体和碎块（活着的敌人由 enemy_sprites 画成贴图）
+    pub fn boxes(&self) -> Vec<BoxInstance> {
+        let mut boxes = Vec::new();
+        for corpse in &self.corpses {
+            let (pose, scale_y) = corpse.pose();
+            // 开始消失后沉入地面
+            let sink = ((corpse.time - self.config.corpse_fade_time) / self.config.corpse_sink_time).clamp(0.0, 1.0);
+            let position = corpse.position - Vec3::Y * sink * 0.8;
+            push_body(&mut boxes, position, Quat::from_rotation_y(corpse.yaw) * pose, scale_y);
+        }
+        self.particles.boxes(&mut boxes);
+        boxes
+    }
+}
+
+// 已经走到了距离场起点格子的中心
+fn arrived_at(position: Vec3, map: &Map, field: &DistanceField) -> bool {
+    match map.world_to_cell(position) {
+        Some((col, row)) => match field.distance(col, row) {
+            Some(0) => Vec3::new(position.x, 0.0, position.z).distance(map.cell_center(col, row)) < ARRIVE_DISTANCE,
+            Some(_) => false,
+            // 走不到时也算到了，换下一个巡逻点
+            None => true,
+        },
+        None => true,
+    }
+}
+
+// 沿距离场往起点走一步，面向走的方向，最后走向 target（没有时是起点格子的中心）。
+// 前面几个格子的路线拉直以后走，空地上斜着走过去。返回是否在走
+fn walk(transform: &mut Transform, map: &Map, field: &DistanceField, target: Option<Vec3>, speed: f32, dt: f32, colliders: &[WallCollider]) -> bool {
+    let Some(cell) = map.world_to_cell(transform.position) else { return false };
+    let cells = field.path(cell);
+    let Some(&end) = cells.last() else { return false };
+    let mut points: Vec<Vec3> = vec![Vec3::new(transform.position.x, 0.0, transform.position.z)];
+    points.extend(cells.iter().skip(1).take(SMOOTH_LOOKAHEAD).map(|&(col, row)| map.cell_center(col, row)));
+    if cells.len() <= SMOOTH_LOOKAHEAD + 1 {
+        points.push(target.unwrap_or_else(|| map.cell_center(end.0, end.1)));
+    }
+    let goal = points[nav::shortcut(&points, |from, to| nav::walkable_line(map, colliders, from, to, HALF_WIDTH))];
+    let offset = Vec3::new(goal.x - transform.position.x, 0.0, goal.z - transform.position.z);
+    if offset.length() < 1e-3 {
+        return false;
+    }
+    let step = offset.clamp_length_max(speed * dt);
+    // 碰撞体按离地一点的位置检测，和狗一样
+    let mut position = transform.position + step + Vec3::Y * HALF_WIDTH;
+    for collider in colliders {
+        position = collider.resolve_collision(position, HALF_WIDTH);
+    }
+    transform.position = Vec3::new(position.x, transform.position.y, position.z);
+    transform.yaw = (-step.x).atan2(-step.z);
+    true
+}
+
+// 联机时其他玩家的身体，暂时和敌人用同一个模型
+pub fn push_player(boxes: &mut Vec<BoxInstance>, feet: Vec3, yaw: f32) {
+    push_body(boxes, feet, Quat::from_rotation_y(yaw), 1.0);
+}
+
+fn push_body(boxes: &mut Vec<BoxInstance>, position: Vec3, rotation: Quat, scale_y: f32) {
+    let root = Mat4::from_rotation_translation(rotation, position) * Mat4::from_scale(Vec3::new(1.0, scale_y, 1.0));
+    for (center, size, color) in BODY_PARTS {
+        boxes.push(BoxInstance {
+            transform: root * Mat4::from_scale_rotation_translation(size, Quat::IDENTITY, center),
+            color,
+        });
+    }
+}
@@ -0,0 +1,204 @@
+use crate::map::Map;
+use crate::nav::DistanceField;
+
+// 敌人的状态机：站着（Idle）→ 巡逻（Patrol）→ 追击（Chase）→ 开火（Attack）。
+// 每个模拟步按到玩家的距离和中间有没有墙切换一次状态，计时器（站着的剩余时间、看不到玩家多久、开火间隔）
+// 都按步长倒数，不依赖帧率。巡逻点是出生点附近走得到的空格子，出生时随机选好，依次走过去
+
+// 站着不动的时间范围（秒）
+const IDLE_TIME: (f32, f32) = (1.5, 4.0);
+// 看不到玩家这么久后放弃追击（秒），追击时朝玩家最后所在的格子走
+const LOSE_TIME: f32 = 4.0;
+// 开火的距离和间隔（秒），刚进入开火状态时先瞄一会儿
+const ATTACK_RANGE: f32 = 14.0;
+const ATTACK_INTERVAL: f32 = 1.2;
+const AIM_TIME: f32 = 0.5;
+// 巡逻点离出生点的最远步数、最少步数和数量
+const PATROL_RANGE: u32 = 6;
+const PATROL_MIN: u32 = 2;
+const PATROL_POINTS: usize = 3;
+// 走路时卡住（被车或关着的门挡住）这么久后放弃这个巡逻点（秒）
+const STUCK_TIME: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AiState {
+    Idle,
+    Patrol,
+    Chase,
+    Attack,
+}
+
+impl AiState {
+    pub fn name(self) -> &'static str {
+        match self {
+            AiState::Idle => "IDLE",
+            AiState::Patrol => "PATROL",
+            AiState::Chase => "CHASE",
+            AiState::Attack => "ATTACK",
+        }
+    }
+}
+
+// 这一步对玩家的感知
+#[derive(Debug, Clone, Copy)]
+pub struct Senses {
+    pub distance: f32,
+    pub line_of_sight: bool,
+    // 现在能发现玩家的距离（暗处更近，见 enemy::sight_range）
+    pub sight_range: f32,
+}
+
+impl Senses {
+    fn sees_player(&self) -> bool {
+        self.line_of_sight && self.distance <= self.sight_range
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Brain {
+    pub state: AiState,
+    // Idle：还要站多久；Chase：看不到玩家多久了
+    timer: f32,
+    // 下一次能开火的剩余时间
+    cooldown: f32,
+    waypoints: Vec<(usize, usize)>,
+    next_waypoint: usize,
+    // 走路时几乎没走动的时间
+    stuck: f32,
+}
+
+impl Brain {
+    pub fn new(waypoints: Vec<(usize, usize)>) -> Self {
+        Self { state: AiState::Idle, timer: idle_time(), cooldown: 0.0, waypoints, next_waypoint: 0, stuck: 0.0 }
+    }
+
+    // 不用看到玩家也追 time 秒（警报叫来的增援）
+    pub fn alert(&mut self, time: f32) {
+        self.state = AiState::Chase;
+        self.timer = LOSE_TIME - time;
+    }
+
+    // 巡逻时要去的格子，没有巡逻点时为 None
+    pub fn waypoint(&self) -> Option<(usize, usize)> {
+        self.waypoints.get(self.next_waypoint).copied()
+    }
+
+    // 推进一步。arrived：巡逻时已经走到了巡逻点。状态变了时返回新状态
+    pub fn tick(&mut self, dt: f32, senses: Senses, arrived: bool) -> Option<AiState> {
+        self.cooldown = (self.cooldown - dt).max(0.0);
+        let sees = senses.sees_player();
+        let next = match self.state {
+            AiState::Idle | AiState::Patrol if sees => AiState::Chase,
+            AiState::Idle => {
+                self.timer -= dt;
+                if self.timer <= 0.0 && !self.waypoints.is_empty() { AiState::Patrol } else { AiState::Idle }
+            }
+            AiState::Patrol if arrived => {
+                self.next_waypoint = (self.next_waypoint + 1) % self.waypoints.len();
+                AiState::Idle
+            }
+            AiState::Patrol => AiState::Patrol,
+            AiState::Chase if sees && senses.distance <= ATTACK_RANGE => AiState::Attack,
+            AiState::Chase if sees => AiState::Chase,
+            AiState::Chase => {
+                self.timer += dt;
+                if self.timer >= LOSE_TIME { AiState::Idle } else { AiState::Chase }
+            }
+            AiState::Attack if sees && senses.distance <= ATTACK_RANGE => AiState::Attack,
+            AiState::Attack => AiState::Chase,
+        };
+        if next == self.state {
+            if sees && self.state == AiState::Chase {
+                self.timer = 0.0;
+            }
+            return None;
+        }
+        self.state = next;
+        self.timer = match next {
+            AiState::Idle => idle_time(),
+            _ => 0.0,
+        };
+        if next == AiState::Attack {
+            self.cooldown = self.cooldown.max(AIM_TIME);
+        }
+        Some(next)
+    }
+
+    // 记下这一步走了多少，blocked 为几乎没走动。卡住太久时返回 true，巡逻时当作已经到了
+    pub fn walked(&mut self, dt: f32, blocked: bool) -> bool {
+        self.stuck = if blocked { self.stuck + dt } else { 0.0 };
+        if self.stuck < STUCK_TIME {
+            return false;
+        }
+        self.stuck = 0.0;
+        true
+    }
+
+    // 开火状态下间隔到了就开一枪
+    pub fn fire(&mut self) -> bool {
+        if self.state != AiState::Attack || self.cooldown > 0.0 {
+            return false;
+        }
+        self.cooldown = ATTACK_INTERVAL;
+        true
+    }
+}
+
+fn idle_time() -> f32 {
+    IDLE_TIME.0 + fastrand::f32() * (IDLE_TIME.1 - IDLE_TIME.0)
+}
+
+// 出生点附近随机几个走得到的空格子，最后回到出生点
+pub fn patrol_route(map: &Map, home: (usize, usize)) -> Vec<(usize, usize)> {
+    let field = DistanceField::compute(map, &[home]);
+    let mut candidates: Vec<(usize, usize)> = (0..map.height)
+        .flat_map(|row| (0..map.width).map(move |col| (col, row)))
+        .filter(|&(col, row)| field.distance(col, row).is_some_and(|distance| (PATROL_MIN..=PATROL_RANGE).contains(&distance)))
+        .collect();
+    fastrand::shuffle(&mut candidates);
+    candidates.truncate(PATROL_POINTS);
+    if !candidates.is_empty() {
+        candidates.push(home);
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn senses(distance: f32, line_of_sight: bool) -> Senses {
+        Senses { distance, line_of_sight, sight_range: 30.0 }
+    }
+
+    #[test]
+    fn idles_then_patrols_the_waypoints() {
+        let mut brain = Brain::new(vec![(1, 1), (2, 2)]);
+        let far = senses(50.0, true);
+        assert_eq!(brain.tick(IDLE_TIME.1 + 0.1, far, false), Some(AiState::Patrol));
+        assert_eq!(brain.waypoint(), Some((1, 1)));
+        assert_eq!(brain.tick(0.1, far, false), None);
+        assert_eq!(brain.tick(0.1, far, true), Some(AiState::Idle));
+        assert_eq!(brain.waypoint(), Some((2, 2)));
+        // 没有巡逻点时一直站着
+        let mut guard = Brain::new(Vec::new());
+        assert_eq!(guard.tick(IDLE_TIME.1 + 0.1, far, false), None);
+    }
+
+    #[test]
+    fn chases_attacks_and_gives_up() {
+        let mut brain = Brain::new(Vec::new());
+        // 隔着墙看不到
+        assert_eq!(brain.tick(0.1, senses(5.0, false), false), None);
+        assert_eq!(brain.tick(0.1, senses(20.0, true), false), Some(AiState::Chase));
+        assert_eq!(brain.tick(0.1, senses(10.0, true), false), Some(AiState::Attack));
+        // 先瞄一会儿再开火，之后按间隔
+        assert!(!brain.fire());
+        brain.tick(AIM_TIME, senses(10.0, true), false);
+        assert!(brain.fire());
+        assert!(!brain.fire());
+        assert_eq!(brain.tick(0.1, senses(10.0, false), false), Some(AiState::Chase));
+        assert_eq!(brain.tick(LOSE_TIME - 0.5, senses(10.0, false), false), None);
+        assert_eq!(brain.tick(1.0, senses(10.0, false), false), Some(AiState::Idle));
+    }
+}
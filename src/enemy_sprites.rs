@@ -0,0 +1,113 @@
+use glam::{Quat, Vec3};
+
+use crate::ecs::{self, World};
+use crate::enemy::BODY_PARTS;
+use crate::geometry::{self, TexCoords};
+use crate::gpu_memory;
+use crate::mesh::MeshBuilder;
+use crate::model::Model;
+
+// 活着的敌人画成一直朝向相机的贴图（和狗一样只绕竖直轴转），在主渲染通道里和场景一起写深度，按透明度裁剪。
+// 贴图按 enemy::BODY_PARTS 的尺寸和颜色程序生成，左半是正面、右半是背面，相机在敌人背后时用背面。
+// 描边和夜视仪的高亮用同样的面片画进 id 纹理，也按透明度裁剪，轮廓跟着贴图走；尸体、布娃娃和碎块还是方块
+
+// 每米多少像素，一帧的宽高（像素）
+const PIXELS_PER_METER: f32 = 40.0;
+const FRAME_WIDTH: u32 = 28;
+const FRAME_HEIGHT: u32 = 70;
+// 两腿之间的缝、腰带和护目镜（离脚底的高度，米）
+const LEG_GAP: f32 = 0.04;
+const CROTCH: f32 = 0.75;
+const BELT: (f32, f32) = (0.9, 0.98);
+const VISOR: (f32, f32) = (1.56, 1.63);
+const BELT_COLOR: [f32; 3] = [0.08, 0.06, 0.05];
+const VISOR_COLOR: [f32; 3] = [0.05, 0.07, 0.1];
+
+// 线性颜色转成 sRGB 纹理里存的值，采样出来和方块的顶点颜色一样
+fn encode(linear: f32) -> u8 {
+    (linear.clamp(0.0, 1.0).powf(1.0 / 2.2) * 255.0).round() as u8
+}
+
+// 一帧里 (u, h) 处的颜色：u 是离中线的距离，h 是离脚底的高度（米），不在身体上时为 None
+fn body_color(u: f32, h: f32, back: bool) -> Option<[f32; 3]> {
+    let (center, size, color) = BODY_PARTS.iter().find(|(center, size, _)| u.abs() <= size.x / 2.0 && (h - center.y).abs() <= size.y / 2.0)?;
+    if h < CROTCH && u.abs() < LEG_GAP / 2.0 {
+        return None;
+    }
+    if (BELT.0..BELT.1).contains(&h) {
+        return Some(BELT_COLOR);
+    }
+    if !back && (VISOR.0..VISOR.1).contains(&h) {
+        return Some(VISOR_COLOR);
+    }
+    // 两边暗一些，像圆柱；每个部分的上下边缘也压暗一点，分得清头、身体和腿
+    let across = u / (size.x / 2.0);
+    let along = (h - center.y) / (size.y / 2.0);
+    let shade = (1.0 - across * across * 0.45) * (1.0 - along.powi(8) * 0.3) * if back { 0.85 } else { 1.0 };
+    Some(color.map(|c| c * shade))
+}
+
+// 正面和背面两帧并排，身体外 alpha 为 0
+pub fn generate_texture() -> image::RgbaImage {
+    let mut image = image::RgbaImage::new(FRAME_WIDTH * 2, FRAME_HEIGHT);
+    for frame in 0..2 {
+        for y in 0..FRAME_HEIGHT {
+            for x in 0..FRAME_WIDTH {
+                let u = (x as f32 + 0.5 - FRAME_WIDTH as f32 / 2.0) / PIXELS_PER_METER;
+                let h = (FRAME_HEIGHT as f32 - y as f32 - 0.5) / PIXELS_PER_METER;
+                let pixel = match body_color(u, h, frame == 1) {
+                    Some(color) => [encode(color[0]), encode(color[1]), encode(color[2]), 255],
+                    None => [0, 0, 0, 0],
+                };
+                image.put_pixel(frame * FRAME_WIDTH + x, y, image::Rgba(pixel));
+            }
+        }
+    }
+    image
+}
+
+// 所有活着的敌人的面片，每帧按相机位置重新生成，用 model_type 2（按透明度裁剪）
+pub fn quads(world: &World, alpha: f32, camera_position: Vec3) -> MeshBuilder {
+    let mut mesh = MeshBuilder::new();
+    let size = Vec3::new(FRAME_WIDTH as f32, FRAME_HEIGHT as f32, 0.0) / PIXELS_PER_METER;
+    // 向里收半个像素，线性过滤不会采样到另一帧
+    let inset = 0.5 / (FRAME_WIDTH * 2) as f32;
+    for (entity, _) in world.ais.iter() {
+        let Some(transform) = ecs::interpolated(world, entity, alpha) else { continue };
+        let toward = Vec3::new(camera_position.x - transform.position.x, 0.0, camera_position.z - transform.position.z).try_normalize().unwrap_or(Vec3::Z);
+        let facing = Quat::from_rotation_y(transform.yaw) * Vec3::NEG_Z;
+        let back = facing.dot(toward) < 0.0;
+        let min_u = if back { 0.5 } else { 0.0 };
+        let half_u = Vec3::Y.cross(toward) * (size.x / 2.0);
+        let half_v = Vec3::Y * (size.y / 2.0);
+        let tex = TexCoords::Rect([min_u + inset, 0.0], [min_u + 0.5 - inset, 1.0]);
+        geometry::quad(&mut mesh, transform.position + half_v, half_u, half_v, [1.0; 3], 2.0, tex);
+    }
+    mesh
+}
+
+pub fn model(device: &wgpu::Device, world: &World, alpha: f32, camera_position: Vec3) -> Option<Model> {
+    let mesh = quads(world, alpha, camera_position);
+    (!mesh.vertices.is_empty()).then(|| mesh.build(device, "enemy_sprites", [0.0, 0.0, 0.0], false, gpu_memory::Category::Dynamic))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texture_covers_the_body_boxes() {
+        let image = generate_texture();
+        let alpha = |x: u32, y: u32| image.get_pixel(x, y)[3];
+        // 脚底正中是两腿之间的缝，稍微偏一点是腿；头顶上面、肩膀外面是空的
+        let (center, feet) = (FRAME_WIDTH / 2, FRAME_HEIGHT - 1);
+        assert_eq!(alpha(center, feet), 0);
+        assert_eq!(alpha(center + 4, feet), 255);
+        assert_eq!(alpha(center, 0), 0);
+        assert_eq!(alpha(center, FRAME_HEIGHT - 46), 255);
+        assert_eq!(alpha(0, FRAME_HEIGHT - 60), 0);
+        // 背面没有护目镜
+        let visor = FRAME_HEIGHT - (1.6 * PIXELS_PER_METER) as u32;
+        assert_ne!(image.get_pixel(center, visor), image.get_pixel(FRAME_WIDTH + center, visor));
+    }
+}
@@ -0,0 +1,40 @@
+use crate::input::Action;
+
+// 游戏事件总线：各个系统发出事件，统计、成就等系统在每帧统一处理
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    // 开始一局（单机开始游戏、创建或加入联机）
+    MatchStarted,
+    // 一局结束（退出、断开连接）
+    MatchEnded,
+    ShotFired,
+    ShotHit { headshot: bool },
+    Kill { headshot: bool },
+    Death,
+    DamageTaken { amount: f32 },
+    WaveCleared { wave: u32 },
+    // 玩家进入地图中的触发区域
+    TriggerEntered { id: char },
+    // 玩家按下了某个绑定的操作键
+    ActionPerformed { action: Action },
+}
+
+pub struct EventBus {
+    queue: Vec<GameEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self { queue: Vec::new() }
+    }
+
+    pub fn emit(&mut self, event: GameEvent) {
+        self.queue.push(event);
+    }
+
+    // 取出这一帧积累的所有事件
+    pub fn drain(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.queue)
+    }
+}
@@ -0,0 +1,193 @@
+use glam::Vec3;
+
+use crate::bindings::{Layouts, MaterialBinding};
+use crate::draw_list;
+use crate::font;
+use crate::geometry::{self, TexCoords};
+use crate::gpu_memory;
+use crate::map::{self, Map, MarkingKind};
+use crate::mesh::MeshBuilder;
+use crate::model::Model;
+use crate::texture;
+
+// 地面标记层：车位分隔线、车道箭头、车位编号和无障碍标志，画在棋盘格地面上面的一层贴花里，
+// 地面网格不用重新贴纹理。形状都来自一张程序生成的标记图集，与血迹一样按透明度裁剪（model_type 2），
+// 跟地图的静态几何体一起合批
+
+// 图集：ATLAS_CELLS x ATLAS_CELLS 个格子
+const ATLAS_CELLS: u32 = 4;
+const ATLAS_CELL_SIZE: u32 = 64;
+
+// 图集中的格子
+const LINE_CELL: u32 = 0;
+const ARROW_CELL: u32 = 1;
+const HANDICAP_CELL: u32 = 2;
+// 数字 0-9 从这一格开始
+const DIGIT_CELL: u32 = 4;
+
+// 离地的高度：在积水之上、格栅之下，叠在底色上的标志再高一点
+const MARKING_HEIGHT: f32 = 0.01;
+const OVERLAY_HEIGHT: f32 = 0.012;
+
+const PAINT_COLOR: [f32; 3] = [0.95, 0.78, 0.12];
+const SYMBOL_COLOR: [f32; 3] = [0.92, 0.92, 0.9];
+const HANDICAP_COLOR: [f32; 3] = [0.1, 0.3, 0.75];
+
+// 分隔线的宽度，每段不超过一个格子长，磨损的花纹不会被拉长
+const LINE_WIDTH: f32 = 0.12;
+// 车道箭头的宽度和长度
+const ARROW_SIZE: (f32, f32) = (0.9, 1.8);
+const HANDICAP_SIZE: f32 = 1.4;
+// 一个数字的宽度和高度
+const DIGIT_SIZE: (f32, f32) = (0.45, 0.63);
+
+pub struct FloorMarkings {
+    binding: MaterialBinding,
+    // 当前地图的所有标记
+    model: Option<Model>,
+}
+
+impl FloorMarkings {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, layouts: &Layouts) -> Self {
+        let texture = texture::Texture::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(generate_atlas()),
+            Some("floor_marking_atlas"),
+        )
+        .expect("无法创建地面标记图集");
+        // 顶点着色，材质颜色不使用
+        let binding = MaterialBinding::new(device, layouts, &texture, [1.0; 3], "floor_markings");
+        Self { binding, model: None }
+    }
+
+    // 根据地图的停车位、车道箭头格子和头部的标注重新生成
+    pub fn load(&mut self, device: &wgpu::Device, map: &Map) {
+        let mut mesh = MeshBuilder::new();
+        for line in map.parking_lines() {
+            bay_line(&mut mesh, Vec3::from(line.start), Vec3::from(line.end));
+        }
+        for (center, direction) in map.lane_arrows() {
+            arrow(&mut mesh, center, direction);
+        }
+        for marking in &map.markings {
+            let center = map.cell_center(marking.col, marking.row);
+            let up = marking.facing.vector();
+            match marking.kind {
+                MarkingKind::Arrow => arrow(&mut mesh, center, up),
+                MarkingKind::Handicap => {
+                    // 蓝色的底色上是白色的轮椅标志
+                    let size = (HANDICAP_SIZE, HANDICAP_SIZE);
+                    stamp(&mut mesh, center, up, size, LINE_CELL, HANDICAP_COLOR, MARKING_HEIGHT);
+                    stamp(&mut mesh, center, up, size, HANDICAP_CELL, SYMBOL_COLOR, OVERLAY_HEIGHT);
+                }
+                MarkingKind::Number(number) => self::number(&mut mesh, center, up, number),
+            }
+        }
+        self.model = (!mesh.vertices.is_empty()).then(|| mesh.build(device, "floor_markings", [0.0, 0.0, 0.0], false, gpu_memory::Category::Map));
+    }
+
+    pub fn unload(&mut self) {
+        self.model = None;
+    }
+
+    // 交给静态合批
+    pub fn mesh(&self) -> Option<(draw_list::Pipeline, draw_list::Material, &Model)> {
+        self.model.as_ref().map(|model| (draw_list::Pipeline::Scene, draw_list::Material::FloorMarkings, model))
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.binding.bind_group
+    }
+}
+
+// 图集中某一格的纹理坐标（向内缩一点，避免采样到相邻格子）
+fn cell_tex(cell: u32) -> TexCoords {
+    let size = 1.0 / ATLAS_CELLS as f32;
+    let inset = 1.0 / (ATLAS_CELLS * ATLAS_CELL_SIZE) as f32;
+    let (x, y) = ((cell % ATLAS_CELLS) as f32 * size, (cell / ATLAS_CELLS) as f32 * size);
+    TexCoords::Rect([x + inset, y + inset], [x + size - inset, y + size - inset])
+}
+
+// 平放在地面上的一格图集：图案的上方朝向 up，size 为宽和长
+fn stamp(mesh: &mut MeshBuilder, center: Vec3, up: Vec3, size: (f32, f32), cell: u32, color: [f32; 3], height: f32) {
+    // 右手边 × 上方 = +Y，从上面看图案不是镜像的
+    let right = up.cross(Vec3::Y);
+    let position = Vec3::new(center.x, height, center.z);
+    geometry::quad(mesh, position, right * (size.0 / 2.0), up * (size.1 / 2.0), color, 2.0, cell_tex(cell));
+}
+
+fn bay_line(mesh: &mut MeshBuilder, start: Vec3, end: Vec3) {
+    let pieces = ((end - start).length() / map::CELL_SIZE).ceil().max(1.0) as u32;
+    let step = (end - start) / pieces as f32;
+    for piece in 0..pieces {
+        let center = start + step * (piece as f32 + 0.5);
+        stamp(mesh, center, step.normalize_or_zero(), (LINE_WIDTH, step.length()), LINE_CELL, PAINT_COLOR, MARKING_HEIGHT);
+    }
+}
+
+fn arrow(mesh: &mut MeshBuilder, center: Vec3, direction: Vec3) {
+    stamp(mesh, center, direction, ARROW_SIZE, ARROW_CELL, PAINT_COLOR, MARKING_HEIGHT);
+}
+
+// 车位编号：数字从左到右排开，整体以格子为中心
+fn number(mesh: &mut MeshBuilder, center: Vec3, up: Vec3, number: u32) {
+    let digits: Vec<u32> = number.to_string().chars().filter_map(|c| c.to_digit(10)).collect();
+    let right = up.cross(Vec3::Y);
+    let first = -(digits.len() as f32 - 1.0) / 2.0;
+    for (i, digit) in digits.into_iter().enumerate() {
+        let position = center + right * ((first + i as f32) * DIGIT_SIZE.0);
+        stamp(mesh, position, up, DIGIT_SIZE, DIGIT_CELL + digit, PAINT_COLOR, MARKING_HEIGHT);
+    }
+}
+
+// 一格里的图案，x、y 为格子内从 0 到 1 的坐标（y 朝下）
+fn cell_shape(cell: u32, x: f32, y: f32) -> bool {
+    let ring = |cx: f32, cy: f32, radius: f32| ((x - cx).powi(2) + (y - cy).powi(2)).sqrt() - radius;
+    match cell {
+        LINE_CELL => true,
+        // 朝上的箭头：上半部分是三角形的箭头，下半部分是箭杆
+        ARROW_CELL => {
+            let from_center = (x - 0.5).abs();
+            (y < 0.5 && from_center * 2.0 <= y) || (y >= 0.5 && from_center < 0.12)
+        }
+        // 轮椅标志：头、躯干、座位、小腿和轮子
+        HANDICAP_CELL => {
+            let head = ring(0.5, 0.16, 0.08) < 0.0;
+            let body = (0.45..0.56).contains(&x) && (0.28..0.6).contains(&y);
+            let seat = (0.45..0.75).contains(&x) && (0.53..0.6).contains(&y);
+            let leg = (0.7..0.78).contains(&x) && (0.53..0.85).contains(&y);
+            let wheel = ring(0.45, 0.68, 0.2).abs() < 0.04;
+            head || body || seat || leg || wheel
+        }
+        // 5x7 点阵字体放大，四周留一点空
+        cell if (DIGIT_CELL..DIGIT_CELL + 10).contains(&cell) => {
+            let glyph = font::glyph(char::from_digit(cell - DIGIT_CELL, 10).unwrap_or('0'));
+            let (col, row) = ((x - 0.1) / 0.8 * font::GLYPH_WIDTH as f32, (y - 0.1) / 0.8 * font::GLYPH_HEIGHT as f32);
+            (0.0..font::GLYPH_WIDTH as f32).contains(&col)
+                && (0.0..font::GLYPH_HEIGHT as f32).contains(&row)
+                && font::pixel(glyph, col as u32, row as u32)
+        }
+        _ => false,
+    }
+}
+
+// 白色的图案（绘制时乘以顶点颜色），图案外和被车轮磨掉的斑点 alpha 为 0
+fn generate_atlas() -> image::RgbaImage {
+    let size = ATLAS_CELLS * ATLAS_CELL_SIZE;
+    let mut image = image::RgbaImage::new(size, size);
+    // 固定种子，每次启动的磨损相同
+    let mut rng = fastrand::Rng::with_seed(7);
+    for cell in 0..ATLAS_CELLS * ATLAS_CELLS {
+        let (ox, oy) = ((cell % ATLAS_CELLS) * ATLAS_CELL_SIZE, (cell / ATLAS_CELLS) * ATLAS_CELL_SIZE);
+        for y in 0..ATLAS_CELL_SIZE {
+            for x in 0..ATLAS_CELL_SIZE {
+                let (fx, fy) = ((x as f32 + 0.5) / ATLAS_CELL_SIZE as f32, (y as f32 + 0.5) / ATLAS_CELL_SIZE as f32);
+                let worn = rng.u32(0..12) == 0;
+                let pixel = if cell_shape(cell, fx, fy) && !worn { [255, 255, 255, 255] } else { [0, 0, 0, 0] };
+                image.put_pixel(ox + x, oy + y, image::Rgba(pixel));
+            }
+        }
+    }
+    image
+}
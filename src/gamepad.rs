@@ -0,0 +1,201 @@
+use crate::axes::AxisSource;
+use crate::input::Action;
+use crate::ui::Nav;
+
+// 手柄（gilrs，gamepad 功能）：游戏操作对应的按键、菜单和物品栏里的按键和摇杆，以及按手柄类型显示的按键图标。
+// 摇杆和扳机怎么控制移动和视角由 axes.rs 的设置决定
+
+// 推过这个值算推了一下，回到 STICK_RELEASE 以内算松开
+const STICK_THRESHOLD: f32 = 0.5;
+const STICK_RELEASE: f32 = 0.3;
+// 一直推着时，第一次重复前的等待和之后每次重复的间隔（秒）
+const REPEAT_DELAY: f32 = 0.4;
+const REPEAT_INTERVAL: f32 = 0.12;
+
+// 手柄的类型，决定提示里按键的叫法
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    Xbox,
+    PlayStation,
+    // Steam Deck 和 Steam 手柄：A/B/X/Y，扳机和肩键按 L2/R2、L1/R1 标
+    Deck,
+}
+
+// USB 厂商编号
+const VENDOR_SONY: u16 = 0x054c;
+const VENDOR_VALVE: u16 = 0x28de;
+
+impl Layout {
+    // 先看厂商编号，拿不到时（部分平台和蓝牙连接）再看名字，都认不出来的按 Xbox 处理
+    pub fn detect(gamepad: &gilrs::Gamepad) -> Layout {
+        match gamepad.vendor_id() {
+            Some(VENDOR_SONY) => return Layout::PlayStation,
+            Some(VENDOR_VALVE) => return Layout::Deck,
+            _ => {}
+        }
+        let name = gamepad.name().to_lowercase();
+        if ["playstation", "dualshock", "dualsense", "sony", "ps4", "ps5"].iter().any(|word| name.contains(word)) {
+            Layout::PlayStation
+        } else if name.contains("steam") {
+            Layout::Deck
+        } else {
+            Layout::Xbox
+        }
+    }
+
+    // 按键在这种手柄上印的字
+    pub fn glyph(self, button: gilrs::Button) -> &'static str {
+        use gilrs::Button;
+        match (self, button) {
+            (Layout::PlayStation, Button::South) => "CROSS",
+            (Layout::PlayStation, Button::East) => "CIRCLE",
+            (Layout::PlayStation, Button::West) => "SQUARE",
+            (Layout::PlayStation, Button::North) => "TRIANGLE",
+            (_, Button::South) => "A",
+            (_, Button::East) => "B",
+            (_, Button::West) => "X",
+            (_, Button::North) => "Y",
+            (Layout::Xbox, Button::LeftTrigger) => "LB",
+            (Layout::Xbox, Button::RightTrigger) => "RB",
+            (Layout::Xbox, Button::LeftTrigger2) => "LT",
+            (Layout::Xbox, Button::RightTrigger2) => "RT",
+            (Layout::Xbox, Button::LeftThumb) => "LS",
+            (Layout::Xbox, Button::RightThumb) => "RS",
+            (Layout::Xbox | Layout::Deck, Button::Select) => "VIEW",
+            (Layout::Xbox | Layout::Deck, Button::Start) => "MENU",
+            (_, Button::LeftTrigger) => "L1",
+            (_, Button::RightTrigger) => "R1",
+            (_, Button::LeftTrigger2) => "L2",
+            (_, Button::RightTrigger2) => "R2",
+            (_, Button::LeftThumb) => "L3",
+            (_, Button::RightThumb) => "R3",
+            (Layout::PlayStation, Button::Select) => "SHARE",
+            (Layout::PlayStation, Button::Start) => "OPTIONS",
+            (_, Button::DPadUp) => "DPAD UP",
+            (_, Button::DPadDown) => "DPAD DOWN",
+            (_, Button::DPadLeft) => "DPAD LEFT",
+            (_, Button::DPadRight) => "DPAD RIGHT",
+            _ => "?",
+        }
+    }
+}
+
+// 保存设置用的手柄 GUID（十六进制），同一型号的手柄相同
+pub fn guid(gamepad: &gilrs::Gamepad) -> String {
+    gamepad.uuid().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// 摇杆和扳机的读数。扳机有的平台报告成轴（LeftZ/RightZ），有的报告成按键的模拟值
+pub fn axis_value(event: &gilrs::EventType) -> Option<(AxisSource, f32)> {
+    match *event {
+        gilrs::EventType::AxisChanged(axis, value, _) => {
+            let source = match axis {
+                gilrs::Axis::LeftStickX => AxisSource::LeftStickX,
+                gilrs::Axis::LeftStickY => AxisSource::LeftStickY,
+                gilrs::Axis::RightStickX => AxisSource::RightStickX,
+                gilrs::Axis::RightStickY => AxisSource::RightStickY,
+                gilrs::Axis::LeftZ => AxisSource::LeftTrigger,
+                gilrs::Axis::RightZ => AxisSource::RightTrigger,
+                _ => return None,
+            };
+            Some((source, value))
+        }
+        gilrs::EventType::ButtonChanged(button, value, _) => Some((trigger_source(button)?, value)),
+        _ => None,
+    }
+}
+
+// 扳机按键对应的轴
+pub fn trigger_source(button: gilrs::Button) -> Option<AxisSource> {
+    match button {
+        gilrs::Button::LeftTrigger2 => Some(AxisSource::LeftTrigger),
+        gilrs::Button::RightTrigger2 => Some(AxisSource::RightTrigger),
+        _ => None,
+    }
+}
+
+// 游戏中手柄按键对应的操作，没有列出的操作（聊天、计分板、小地图、数字键选武器）只能用键盘。
+// 移动主要用左摇杆，见 camera.rs；十字键上下也能前后移动（比赛结束时选地图），左右是夜视仪和商店。肩键切换武器，左扳机扔手雷，按下右摇杆近战
+const ACTION_BUTTONS: [(Action, gilrs::Button); 15] = [
+    (Action::MoveForward, gilrs::Button::DPadUp),
+    (Action::MoveBack, gilrs::Button::DPadDown),
+    (Action::Jump, gilrs::Button::South),
+    (Action::Sprint, gilrs::Button::LeftThumb),
+    (Action::Fire, gilrs::Button::RightTrigger2),
+    (Action::Reload, gilrs::Button::West),
+    (Action::Use, gilrs::Button::North),
+    (Action::Drop, gilrs::Button::East),
+    (Action::Grenade, gilrs::Button::LeftTrigger2),
+    (Action::PrevWeapon, gilrs::Button::LeftTrigger),
+    (Action::NextWeapon, gilrs::Button::RightTrigger),
+    (Action::Inventory, gilrs::Button::Select),
+    (Action::Shop, gilrs::Button::DPadRight),
+    (Action::NightVision, gilrs::Button::DPadLeft),
+    (Action::Melee, gilrs::Button::RightThumb),
+];
+
+pub fn action_for_button(button: gilrs::Button) -> Option<Action> {
+    ACTION_BUTTONS.iter().find(|(_, b)| *b == button).map(|(action, _)| *action)
+}
+
+pub fn button_for_action(action: Action) -> Option<gilrs::Button> {
+    ACTION_BUTTONS.iter().find(|(a, _)| *a == action).map(|(_, button)| *button)
+}
+
+// 方向键移动焦点，A 键确定，B 键和 Start 键返回
+pub fn nav_for_button(button: gilrs::Button) -> Option<Nav> {
+    match button {
+        gilrs::Button::DPadUp => Some(Nav::Up),
+        gilrs::Button::DPadDown => Some(Nav::Down),
+        gilrs::Button::South => Some(Nav::Select),
+        gilrs::Button::East | gilrs::Button::Start => Some(Nav::Back),
+        _ => None,
+    }
+}
+
+// 把左摇杆的上下当作方向键：推一下移动一格，一直推着时按固定间隔继续移动
+pub struct NavStick {
+    held: Option<Nav>,
+    repeat: f32,
+}
+
+impl NavStick {
+    pub fn new() -> Self {
+        Self { held: None, repeat: 0.0 }
+    }
+
+    // 摇杆 y 轴的新位置（向上为正），刚推过阈值时返回一次移动
+    pub fn set(&mut self, value: f32) -> Option<Nav> {
+        let direction = if value > STICK_THRESHOLD {
+            Some(Nav::Up)
+        } else if value < -STICK_THRESHOLD {
+            Some(Nav::Down)
+        } else if value.abs() < STICK_RELEASE {
+            None
+        } else {
+            // 在两个阈值之间保持原来的状态，摇杆抖动时不会连续触发
+            return None;
+        };
+        if direction == self.held {
+            return None;
+        }
+        self.held = direction;
+        self.repeat = REPEAT_DELAY;
+        direction
+    }
+
+    // 每帧调用，推着不放时返回重复的移动
+    pub fn update(&mut self, dt: f32) -> Option<Nav> {
+        let held = self.held?;
+        self.repeat -= dt;
+        if self.repeat > 0.0 {
+            return None;
+        }
+        self.repeat += REPEAT_INTERVAL;
+        Some(held)
+    }
+
+    pub fn release(&mut self) {
+        self.held = None;
+    }
+}
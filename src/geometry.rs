@@ -0,0 +1,194 @@
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::mesh::MeshBuilder;
+
+// 常用形状的构建函数，都追加到 MeshBuilder 里：面的绕序按 mesh.rs 的约定朝外，
+// 顶点带法向量（由构建器从朝外的方向求出）和纹理坐标。墙体、方柱、动态物体、
+// 地面标记和调试显示都用这里的形状，不再各自手写顶点
+
+// 四边形四个角在自身两条轴上的位置，顺序与纹理坐标一一对应
+const CORNERS: [(f32, f32); 4] = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)];
+
+// 四边形的纹理坐标
+#[derive(Debug, Clone, Copy)]
+pub enum TexCoords {
+    // 每个顶点相同（半透明物体的不透明度、有光泽物体的反射强度）
+    Constant([f32; 2]),
+    // 铺满纹理中的一个矩形，v 轴朝下：(-1, -1) 角对应 (min.x, max.y)
+    Rect([f32; 2], [f32; 2]),
+}
+
+impl TexCoords {
+    // 整张纹理
+    pub const FULL: TexCoords = TexCoords::Rect([0.0, 0.0], [1.0, 1.0]);
+    pub const NONE: TexCoords = TexCoords::Constant([0.0, 0.0]);
+
+    fn corners(self) -> [[f32; 2]; 4] {
+        match self {
+            TexCoords::Constant(tex_coords) => [tex_coords; 4],
+            TexCoords::Rect(min, max) => CORNERS.map(|(su, sv)| {
+                let (s, t) = ((su + 1.0) / 2.0, (sv + 1.0) / 2.0);
+                [min[0] + (max[0] - min[0]) * s, max[1] + (min[1] - max[1]) * t]
+            }),
+        }
+    }
+}
+
+// 以 center 为中心的矩形，half_u 和 half_v 是两条半边，朝向 half_u × half_v 一侧
+pub fn quad(mesh: &mut MeshBuilder, center: Vec3, half_u: Vec3, half_v: Vec3, color: [f32; 3], model_type: f32, tex: TexCoords) {
+    let corners = CORNERS.map(|(su, sv)| center + half_u * su + half_v * sv);
+    mesh.quad(corners, half_u.cross(half_v), color, model_type, tex.corners());
+}
+
+// 水平的矩形（地板、天花板、地面标记、积水、格栅），up 为 false 时朝下。
+// 朝上时纹理的 u 沿 +X、v 沿 +Z，从上面看与地图格子的方向一致
+pub fn plane(mesh: &mut MeshBuilder, center: Vec3, size: Vec2, up: bool, color: [f32; 3], model_type: f32, tex: TexCoords) {
+    let (half_x, half_z) = (Vec3::X * size.x / 2.0, Vec3::Z * size.y / 2.0);
+    // X × -Z 朝上
+    let half_v = if up { -half_z } else { half_z };
+    quad(mesh, center, half_x, half_v, color, model_type, tex);
+}
+
+// 单位立方体的六个面：法向量、两条边的方向和明暗
+const BOX_FACES: [(Vec3, Vec3, Vec3, f32); 6] = [
+    (Vec3::X, Vec3::Y, Vec3::Z, 0.8),
+    (Vec3::NEG_X, Vec3::Z, Vec3::Y, 0.8),
+    (Vec3::Y, Vec3::Z, Vec3::X, 1.0),
+    (Vec3::NEG_Y, Vec3::X, Vec3::Z, 0.5),
+    (Vec3::Z, Vec3::X, Vec3::Y, 0.65),
+    (Vec3::NEG_Z, Vec3::Y, Vec3::X, 0.65),
+];
+
+// 长方体：transform 作用于中心在原点的单位立方体，可以带镜像。各面按朝向调暗一些，
+// 没有光照的顶点着色物体也能看出形状（贴纹理的墙体不使用顶点颜色）
+pub fn cuboid(mesh: &mut MeshBuilder, transform: Mat4, color: [f32; 3], model_type: f32, tex: TexCoords) {
+    for (normal, u, v, shade) in BOX_FACES {
+        let corners = CORNERS.map(|(su, sv)| transform.transform_point3((normal + u * su + v * sv) * 0.5));
+        // 变换可能带镜像，朝外的方向也要跟着变换
+        let outward = transform.transform_vector3(normal);
+        mesh.quad(corners, outward, color.map(|c| c * shade), model_type, tex.corners());
+    }
+}
+
+// 以 center 为中心、三条轴分别为 x、y、z 的长方体的变换
+pub fn box_transform(center: Vec3, x: Vec3, y: Vec3, z: Vec3) -> Mat4 {
+    Mat4::from_cols(x.extend(0.0), y.extend(0.0), z.extend(0.0), center.extend(1.0))
+}
+
+// 从 start 到 end 的圆柱（柱子、管道），侧面和两端的圆面按 segments 段拼成
+pub fn cylinder(mesh: &mut MeshBuilder, start: Vec3, end: Vec3, radius: f32, segments: u32, color: [f32; 3]) {
+    let axis = end - start;
+    let (side, other) = axis.normalize_or_zero().any_orthonormal_pair();
+    let rim = |i: u32| {
+        let angle = i as f32 / segments as f32 * std::f32::consts::TAU;
+        (side * angle.cos() + other * angle.sin()) * radius
+    };
+    for i in 0..segments {
+        let (a, b) = (rim(i), rim(i + 1));
+        // 侧面的纹理横向绕一圈
+        let (u0, u1) = (i as f32 / segments as f32, (i + 1) as f32 / segments as f32);
+        mesh.quad([start + a, start + b, end + b, end + a], a + b, color, 0.0, [[u0, 1.0], [u1, 1.0], [u1, 0.0], [u0, 0.0]]);
+        mesh.triangle([end, end + a, end + b], axis, color, 0.0);
+        mesh.triangle([start, start + a, start + b], -axis, color, 0.0);
+    }
+}
+
+// 线框长方体：单位立方体的十二条棱，每条是一根粗细为 width 的细长方体
+pub fn line_box(mesh: &mut MeshBuilder, transform: Mat4, width: f32, color: [f32; 3]) {
+    let corner = |x: f32, y: f32, z: f32| transform.transform_point3(Vec3::new(x, y, z) * 0.5);
+    for a in [-1.0, 1.0] {
+        for b in [-1.0, 1.0] {
+            for (start, end) in [
+                (corner(-1.0, a, b), corner(1.0, a, b)),
+                (corner(a, -1.0, b), corner(a, 1.0, b)),
+                (corner(a, b, -1.0), corner(a, b, 1.0)),
+            ] {
+                line(mesh, start, end, width, color);
+            }
+        }
+    }
+}
+
+// 一条有粗细的线段（截面是正方形的细长方块），调试显示用
+pub fn line(mesh: &mut MeshBuilder, start: Vec3, end: Vec3, width: f32, color: [f32; 3]) {
+    let direction = end - start;
+    let (side, other) = direction.normalize_or_zero().any_orthonormal_pair();
+    // 两端各伸出半个粗细，线与线在拐角处接住
+    let length = direction.length() + width;
+    cuboid(mesh, box_transform((start + end) / 2.0, direction.normalize_or_zero() * length, side * width, other * width), color, 0.0, TexCoords::NONE);
+}
+
+// 表面上的正多边形（纯色贴花），tangent × bitangent 为朝外的方向
+pub fn disc(mesh: &mut MeshBuilder, center: Vec3, tangent: Vec3, bitangent: Vec3, sides: u32, color: [f32; 3]) {
+    let outward = tangent.cross(bitangent);
+    let rim = |i: u32| {
+        let angle = i as f32 / sides as f32 * std::f32::consts::TAU;
+        center + tangent * angle.cos() + bitangent * angle.sin()
+    };
+    for i in 0..sides {
+        mesh.triangle([center, rim(i), rim(i + 1)], outward, color, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plane_faces_the_requested_side() {
+        for up in [true, false] {
+            let mut mesh = MeshBuilder::new();
+            plane(&mut mesh, Vec3::ZERO, Vec2::new(2.0, 3.0), up, [1.0; 3], 0.0, TexCoords::NONE);
+            let expected = if up { Vec3::Y } else { Vec3::NEG_Y };
+            assert!(mesh.triangles().all(|(_, normal)| normal.dot(expected) > 0.99));
+            assert!(mesh.vertices.iter().all(|vertex| Vec3::from(vertex.normal) == expected));
+        }
+    }
+
+    #[test]
+    fn rect_tex_coords_keep_v_pointing_down() {
+        let mut mesh = MeshBuilder::new();
+        quad(&mut mesh, Vec3::ZERO, Vec3::X, Vec3::Y, [1.0; 3], 1.0, TexCoords::FULL);
+        let uv_at = |position: Vec3| mesh.vertices.iter().find(|vertex| Vec3::from(vertex.position) == position).unwrap().tex_coords;
+        assert_eq!(uv_at(Vec3::new(-1.0, -1.0, 0.0)), [0.0, 1.0]);
+        assert_eq!(uv_at(Vec3::new(1.0, 1.0, 0.0)), [1.0, 0.0]);
+    }
+
+    #[test]
+    fn cuboids_point_outward_even_when_mirrored() {
+        let transforms = [
+            Mat4::IDENTITY,
+            Mat4::from_scale_rotation_translation(Vec3::new(0.5, 2.0, 0.3), glam::Quat::from_rotation_y(0.7), Vec3::new(3.0, 1.0, -2.0)),
+            Mat4::from_scale(Vec3::new(-1.0, 1.0, 1.0)),
+        ];
+        for transform in transforms {
+            let mut mesh = MeshBuilder::new();
+            cuboid(&mut mesh, transform, [1.0; 3], 0.0, TexCoords::NONE);
+            mesh.assert_outward();
+        }
+    }
+
+    #[test]
+    fn cylinder_points_outward() {
+        let mut mesh = MeshBuilder::new();
+        let (start, end) = (Vec3::new(1.0, 0.0, -2.0), Vec3::new(3.0, 1.0, 2.0));
+        cylinder(&mut mesh, start, end, 0.4, 12, [1.0; 3]);
+        // 两端的三角形以圆心为顶点，平均位置不在中心，改用轴线中点检查
+        let middle = (start + end) / 2.0;
+        for (center, normal) in mesh.triangles() {
+            assert!(normal.dot(center - middle) > 0.0, "朝内的三角形: 中心 {:?} 法向量 {:?}", center, normal);
+        }
+    }
+
+    #[test]
+    fn line_box_covers_every_edge() {
+        let mut mesh = MeshBuilder::new();
+        line_box(&mut mesh, Mat4::from_scale(Vec3::new(2.0, 4.0, 6.0)), 0.1, [1.0; 3]);
+        assert_eq!(mesh.vertices.len(), 12 * 24);
+        let (min, max) = mesh.vertices.iter().fold((Vec3::MAX, Vec3::MIN), |(min, max), vertex| {
+            (min.min(Vec3::from(vertex.position)), max.max(Vec3::from(vertex.position)))
+        });
+        assert!((max - Vec3::new(1.05, 2.05, 3.05)).abs().max_element() < 1e-4);
+        assert!((min + max).abs().max_element() < 1e-4);
+    }
+}
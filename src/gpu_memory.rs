@@ -0,0 +1,156 @@
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use wgpu::util::DeviceExt;
+
+// 显存占用统计：缓冲区和纹理都通过这里创建，按用途分类记下字节数，释放时减掉。
+// 显示在 F4 的性能面板和 HTTP 的 /metrics 里，反复加载地图后数字持续上涨就是有泄漏。
+// 统计的是申请的大小，驱动实际的对齐和额外开销不算在内
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    // 地图的静态几何体（墙、地面、车、柱子、静态合批）
+    Map,
+    // 每帧重新生成的模型（敌人、狗、调试线框）
+    Dynamic,
+    Decals,
+    Particles,
+    Ui,
+    // 图片纹理、深度缓冲、后处理和反射探针的渲染目标
+    Textures,
+    // 光源、分簇和光照贴图
+    Lighting,
+    // 每帧的 uniform 和上传用的暂存缓冲区
+    Frame,
+}
+
+pub const CATEGORIES: [Category; 8] = [
+    Category::Map,
+    Category::Dynamic,
+    Category::Decals,
+    Category::Particles,
+    Category::Ui,
+    Category::Textures,
+    Category::Lighting,
+    Category::Frame,
+];
+
+impl Category {
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Map => "map",
+            Category::Dynamic => "dynamic",
+            Category::Decals => "decals",
+            Category::Particles => "particles",
+            Category::Ui => "ui",
+            Category::Textures => "textures",
+            Category::Lighting => "lighting",
+            Category::Frame => "frame",
+        }
+    }
+
+    fn index(self) -> usize {
+        CATEGORIES.iter().position(|category| *category == self).unwrap_or(0)
+    }
+}
+
+// 每类的字节数和个数，渲染线程写，HTTP 线程读
+static BYTES: [AtomicU64; CATEGORIES.len()] = [const { AtomicU64::new(0) }; CATEGORIES.len()];
+static COUNTS: [AtomicU64; CATEGORIES.len()] = [const { AtomicU64::new(0) }; CATEGORIES.len()];
+
+// 计入统计的 GPU 资源，drop 时从统计里减掉。可以像 wgpu::Buffer / wgpu::Texture 一样使用
+pub struct Tracked<T> {
+    resource: T,
+    category: Category,
+    bytes: u64,
+}
+
+impl<T> Tracked<T> {
+    fn new(resource: T, category: Category, bytes: u64) -> Self {
+        BYTES[category.index()].fetch_add(bytes, Ordering::Relaxed);
+        COUNTS[category.index()].fetch_add(1, Ordering::Relaxed);
+        Self { resource, category, bytes }
+    }
+}
+
+impl<T> Deref for Tracked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.resource
+    }
+}
+
+impl<T> Drop for Tracked<T> {
+    fn drop(&mut self) {
+        BYTES[self.category.index()].fetch_sub(self.bytes, Ordering::Relaxed);
+        COUNTS[self.category.index()].fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+pub fn buffer(device: &wgpu::Device, category: Category, descriptor: &wgpu::BufferDescriptor) -> Tracked<wgpu::Buffer> {
+    Tracked::new(device.create_buffer(descriptor), category, descriptor.size)
+}
+
+pub fn buffer_init(device: &wgpu::Device, category: Category, descriptor: &wgpu::util::BufferInitDescriptor) -> Tracked<wgpu::Buffer> {
+    let buffer = device.create_buffer_init(descriptor);
+    let bytes = buffer.size();
+    Tracked::new(buffer, category, bytes)
+}
+
+pub fn texture(device: &wgpu::Device, category: Category, descriptor: &wgpu::TextureDescriptor) -> Tracked<wgpu::Texture> {
+    Tracked::new(device.create_texture(descriptor), category, texture_bytes(descriptor))
+}
+
+// 所有 mip 层的大小之和。深度模板这类没有固定块大小的格式按每像素 4 字节算
+fn texture_bytes(descriptor: &wgpu::TextureDescriptor) -> u64 {
+    let (block_width, block_height) = descriptor.format.block_dimensions();
+    let block_bytes = descriptor.format.block_size(None).unwrap_or(4) as u64;
+    let layers = match descriptor.dimension {
+        wgpu::TextureDimension::D3 => 1,
+        _ => descriptor.size.depth_or_array_layers as u64,
+    };
+    (0..descriptor.mip_level_count)
+        .map(|level| {
+            let size = descriptor.size.mip_level_size(level, descriptor.dimension);
+            let blocks = size.width.div_ceil(block_width) as u64 * size.height.div_ceil(block_height) as u64;
+            let depth = if descriptor.dimension == wgpu::TextureDimension::D3 { size.depth_or_array_layers as u64 } else { 1 };
+            blocks * depth * block_bytes * layers * descriptor.sample_count as u64
+        })
+        .sum()
+}
+
+// 当前每类的 (类别, 个数, 字节数)
+pub fn snapshot() -> Vec<(Category, u64, u64)> {
+    CATEGORIES
+        .iter()
+        .map(|category| (*category, COUNTS[category.index()].load(Ordering::Relaxed), BYTES[category.index()].load(Ordering::Relaxed)))
+        .collect()
+}
+
+pub fn total_bytes() -> u64 {
+    BYTES.iter().map(|bytes| bytes.load(Ordering::Relaxed)).sum()
+}
+
+// 面板上显示的大小
+pub fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1 << 20 {
+        format!("{:.1} MB", bytes as f64 / (1 << 20) as f64)
+    } else {
+        format!("{:.1} KB", bytes as f64 / (1 << 10) as f64)
+    }
+}
+
+// /metrics 的内容（Prometheus 文本格式）
+#[cfg(feature = "http-server")]
+pub fn metrics_text() -> String {
+    let mut text = String::from("# HELP gpu_memory_bytes GPU memory allocated by the game, by category\n# TYPE gpu_memory_bytes gauge\n");
+    for (category, _, bytes) in snapshot() {
+        text.push_str(&format!("gpu_memory_bytes{{category=\"{}\"}} {}\n", category.name(), bytes));
+    }
+    text.push_str("# HELP gpu_resources GPU buffers and textures alive, by category\n# TYPE gpu_resources gauge\n");
+    for (category, count, _) in snapshot() {
+        text.push_str(&format!("gpu_resources{{category=\"{}\"}} {}\n", category.name(), count));
+    }
+    text
+}
@@ -0,0 +1,448 @@
+use glam::{Quat, Vec3};
+
+use crate::gpu_memory;
+use crate::particles::{self, Particle, ParticleSystem};
+use crate::texture;
+use crate::upload::FrameUploader;
+
+// 大量的环境粒子（灰尘、从入口飘进来的雨、火星和电火花）：粒子池放在显存里，
+// 由计算着色器模拟，存活的粒子在模拟时写入绘制列表，用间接绘制画出来。
+// 发射器在 CPU 上决定每帧生成哪些新粒子，写进粒子池的环形缓冲区。
+// 显卡不支持计算着色器（或顶点着色器读不了存储缓冲区）时退回 CPU 模拟，粒子数量按比例减少
+
+const POOL_SIZE: u32 = 16384;
+const WORKGROUP_SIZE: u32 = 64;
+const GRAVITY: f32 = 9.8;
+// CPU 模拟时的发射量比例
+const CPU_RATE_SCALE: f32 = 0.05;
+const SPARK_COUNT: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParticleKind {
+    Dust,
+    Rain,
+    Spark,
+    // 火焰的火星，往上飘
+    Ember,
+}
+
+impl ParticleKind {
+    // 与着色器中的 KIND_* 常量对应
+    fn id(self) -> u32 {
+        match self {
+            ParticleKind::Dust => 0,
+            ParticleKind::Rain => 1,
+            ParticleKind::Spark => 2,
+            ParticleKind::Ember => 3,
+        }
+    }
+
+    // 新粒子的初速度、大小、颜色和寿命（带随机变化）
+    fn spawn(self, position: Vec3) -> GpuParticle {
+        let (velocity, size, color, life) = match self {
+            ParticleKind::Dust => (particles::random_unit_vector() * 0.05, 0.012, [0.75, 0.72, 0.65], 4.0 + fastrand::f32() * 4.0),
+            ParticleKind::Rain => (Vec3::new(0.6, -7.0 - fastrand::f32() * 2.0, 0.3), 0.008, [0.65, 0.7, 0.8], 1.5),
+            ParticleKind::Spark => (Vec3::ZERO, 0.015, [1.0, 0.75, 0.3], 0.3 + fastrand::f32() * 0.4),
+            ParticleKind::Ember => {
+                let drift = Vec3::new(fastrand::f32() - 0.5, 0.0, fastrand::f32() - 0.5) * 0.3;
+                (drift + Vec3::Y * (0.4 + fastrand::f32() * 0.6), 0.012, [1.0, 0.45, 0.1], 0.6 + fastrand::f32() * 0.6)
+            }
+        };
+        GpuParticle {
+            position: position.to_array(),
+            life,
+            velocity: velocity.to_array(),
+            max_life: life,
+            color,
+            size,
+            kind: self.id(),
+            _padding: [0; 3],
+        }
+    }
+}
+
+// 持续发射的区域，每秒生成 rate 个粒子，位置在 min 和 max 之间随机
+pub struct Emitter {
+    pub kind: ParticleKind,
+    pub min: Vec3,
+    pub max: Vec3,
+    pub rate: f32,
+    // 为 true 时 min/max 是相对于相机的偏移（玩家周围的灰尘）
+    pub follow_camera: bool,
+    accumulator: f32,
+}
+
+impl Emitter {
+    pub fn new(kind: ParticleKind, min: Vec3, max: Vec3, rate: f32, follow_camera: bool) -> Self {
+        Self { kind, min, max, rate, follow_camera, accumulator: 0.0 }
+    }
+}
+
+// 与着色器中的 Particle 布局一致（vec3 按 16 字节对齐）
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct GpuParticle {
+    position: [f32; 3],
+    life: f32,
+    velocity: [f32; 3],
+    max_life: f32,
+    color: [f32; 3],
+    size: f32,
+    kind: u32,
+    _padding: [u32; 3],
+}
+
+unsafe impl bytemuck::Pod for GpuParticle {}
+unsafe impl bytemuck::Zeroable for GpuParticle {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct SimParams {
+    dt: f32,
+    gravity: f32,
+    count: u32,
+    _padding: u32,
+}
+
+unsafe impl bytemuck::Pod for SimParams {}
+unsafe impl bytemuck::Zeroable for SimParams {}
+
+struct GpuPool {
+    particle_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    params_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    draw_args_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    // 计算着色器写、顶点着色器读的活着的粒子列表，CPU 这边不访问
+    _alive_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    compute_pipeline: wgpu::ComputePipeline,
+    compute_bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+    render_bind_group: wgpu::BindGroup,
+    // 环形缓冲区中下一个新粒子的位置，满了就覆盖最早的粒子
+    next: u32,
+    // 等渲染时一起上传
+    params: SimParams,
+    pending: Vec<GpuParticle>,
+}
+
+enum Backend {
+    Gpu(Box<GpuPool>),
+    Cpu(ParticleSystem),
+}
+
+pub struct ParticleEffects {
+    backend: Backend,
+    emitters: Vec<Emitter>,
+}
+
+// 计算着色器和顶点着色器读存储缓冲区都需要的能力
+pub fn gpu_supported(adapter: &wgpu::Adapter) -> bool {
+    let flags = adapter.get_downlevel_capabilities().flags;
+    flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS | wgpu::DownlevelFlags::VERTEX_STORAGE)
+}
+
+impl ParticleEffects {
+    pub fn new(
+        device: &wgpu::Device,
+        use_gpu: bool,
+        frame_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let backend = if use_gpu {
+            println!("粒子: 使用计算着色器模拟（{} 个）", POOL_SIZE);
+            Backend::Gpu(Box::new(GpuPool::new(device, frame_layout, format)))
+        } else {
+            println!("粒子: 不支持计算着色器，使用 CPU 模拟");
+            Backend::Cpu(ParticleSystem::new())
+        };
+        Self { backend, emitters: Vec::new() }
+    }
+
+    pub fn set_emitters(&mut self, emitters: Vec<Emitter>) {
+        self.emitters = emitters;
+    }
+
+    // 被火焰打中或者在燃烧：往上飘的火星
+    pub fn embers(&mut self, position: Vec3) {
+        let spawns: Vec<GpuParticle> = (0..SPARK_COUNT / 2).map(|_| ParticleKind::Ember.spawn(position + particles::random_unit_vector() * 0.2)).collect();
+        self.spawn(&spawns);
+    }
+
+    // 电击：四散的蓝白色电火花
+    pub fn arcs(&mut self, position: Vec3) {
+        let spawns: Vec<GpuParticle> = (0..SPARK_COUNT)
+            .map(|_| {
+                let mut particle = ParticleKind::Spark.spawn(position);
+                particle.velocity = (particles::random_unit_vector() * (1.5 + fastrand::f32() * 3.0)).to_array();
+                particle.color = [0.55, 0.8, 1.0];
+                particle
+            })
+            .collect();
+        self.spawn(&spawns);
+    }
+
+    // dt 为 0 时（暂停）粒子停住不动
+    pub fn update(&mut self, dt: f32, camera_position: Vec3) {
+        let scale = match self.backend {
+            Backend::Gpu(_) => 1.0,
+            Backend::Cpu(_) => CPU_RATE_SCALE,
+        };
+        let mut spawns = Vec::new();
+        for emitter in &mut self.emitters {
+            emitter.accumulator += emitter.rate * scale * dt;
+            let origin = if emitter.follow_camera { camera_position } else { Vec3::ZERO };
+            while emitter.accumulator >= 1.0 {
+                emitter.accumulator -= 1.0;
+                let t = Vec3::new(fastrand::f32(), fastrand::f32(), fastrand::f32());
+                spawns.push(emitter.kind.spawn(origin + emitter.min + (emitter.max - emitter.min) * t));
+            }
+        }
+        self.spawn(&spawns);
+
+        match &mut self.backend {
+            Backend::Gpu(pool) => pool.params = SimParams { dt, gravity: GRAVITY, count: POOL_SIZE, _padding: 0 },
+            Backend::Cpu(system) => {
+                if dt > 0.0 {
+                    system.update(dt);
+                }
+            }
+        }
+    }
+
+    fn spawn(&mut self, spawns: &[GpuParticle]) {
+        match &mut self.backend {
+            Backend::Gpu(pool) => pool.pending.extend_from_slice(spawns),
+            Backend::Cpu(system) => {
+                for spawn in spawns {
+                    let size = Vec3::new(spawn.size, spawn.size, spawn.size) * 2.0;
+                    system.spawn(Particle {
+                        position: spawn.position.into(),
+                        velocity: spawn.velocity.into(),
+                        // 雨丝画成细长条
+                        size: if spawn.kind == ParticleKind::Rain.id() { size * Vec3::new(1.0, 12.0, 1.0) } else { size },
+                        rotation: Quat::IDENTITY,
+                        spin: Vec3::ZERO,
+                        color: spawn.color,
+                        life: spawn.life,
+                        max_life: spawn.max_life,
+                    });
+                }
+            }
+        }
+    }
+
+    // 在渲染通道之前上传新粒子并记录计算通道
+    pub fn simulate(&mut self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, uploader: &mut FrameUploader) {
+        let Backend::Gpu(pool) = &mut self.backend else { return };
+        pool.upload(queue, encoder, uploader);
+        // 绘制列表每帧从空开始
+        encoder.clear_buffer(&pool.draw_args_buffer, 4, wgpu::BufferSize::new(4));
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Particle Compute Pass") });
+        compute_pass.set_pipeline(&pool.compute_pipeline);
+        compute_pass.set_bind_group(0, &pool.compute_bind_group, &[]);
+        compute_pass.dispatch_workgroups(POOL_SIZE.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    // 在半透明物体之后绘制，会替换管线和第 0、1 组绑定
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, frame_bind_group: &'a wgpu::BindGroup) {
+        let Backend::Gpu(pool) = &self.backend else { return };
+        render_pass.set_pipeline(&pool.render_pipeline);
+        render_pass.set_bind_group(0, frame_bind_group, &[]);
+        render_pass.set_bind_group(1, &pool.render_bind_group, &[]);
+        render_pass.draw_indirect(&pool.draw_args_buffer, 0);
+    }
+
+    // CPU 模拟的粒子和其他动态物体一起用立方体画
+    pub fn boxes(&self, out: &mut Vec<crate::model::BoxInstance>) {
+        if let Backend::Cpu(system) = &self.backend {
+            system.boxes(out);
+        }
+    }
+
+    pub fn clear(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        match &mut self.backend {
+            Backend::Gpu(pool) => {
+                pool.pending.clear();
+                let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Particle Clear Encoder") });
+                encoder.clear_buffer(&pool.particle_buffer, 0, None);
+                queue.submit(std::iter::once(encoder.finish()));
+            }
+            Backend::Cpu(system) => *system = ParticleSystem::new(),
+        }
+    }
+}
+
+impl GpuPool {
+    fn new(device: &wgpu::Device, frame_layout: &wgpu::BindGroupLayout, format: wgpu::TextureFormat) -> Self {
+        let particle_buffer = gpu_memory::buffer(device, gpu_memory::Category::Particles, &wgpu::BufferDescriptor {
+            label: Some("Particle Pool"),
+            size: (POOL_SIZE as usize * std::mem::size_of::<GpuParticle>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let alive_buffer = gpu_memory::buffer(device, gpu_memory::Category::Particles, &wgpu::BufferDescriptor {
+            label: Some("Particle Draw List"),
+            size: (POOL_SIZE as usize * std::mem::size_of::<u32>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let params_buffer = gpu_memory::buffer_init(device, gpu_memory::Category::Particles, &wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Params"),
+            contents: bytemuck::cast_slice(&[SimParams { dt: 0.0, gravity: GRAVITY, count: POOL_SIZE, _padding: 0 }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        // 间接绘制参数：每个粒子 6 个顶点，实例数由计算着色器累加
+        let draw_args_buffer = gpu_memory::buffer_init(device, gpu_memory::Category::Particles, &wgpu::util::BufferInitDescriptor {
+            label: Some("Particle Draw Args"),
+            contents: bytemuck::cast_slice(&[6u32, 0, 0, 0]),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDIRECT | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let storage_entry = |binding: u32, visibility: wgpu::ShaderStages, read_only: bool| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let compute_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(1, wgpu::ShaderStages::COMPUTE, false),
+                storage_entry(2, wgpu::ShaderStages::COMPUTE, false),
+                storage_entry(3, wgpu::ShaderStages::COMPUTE, false),
+            ],
+            label: Some("particle_compute_bind_group_layout"),
+        });
+        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &compute_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: params_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: particle_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: alive_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 3, resource: draw_args_buffer.as_entire_binding() },
+            ],
+            label: Some("particle_compute_bind_group"),
+        });
+        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Particle Simulation Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("particles_sim.wgsl").into()),
+        });
+        let compute_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Particle Compute Pipeline Layout"),
+            bind_group_layouts: &[&compute_layout],
+            push_constant_ranges: &[],
+        });
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Particle Compute Pipeline"),
+            layout: Some(&compute_pipeline_layout),
+            module: &compute_shader,
+            entry_point: "cs_main",
+        });
+
+        let render_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[storage_entry(0, wgpu::ShaderStages::VERTEX, true), storage_entry(1, wgpu::ShaderStages::VERTEX, true)],
+            label: Some("particle_render_bind_group_layout"),
+        });
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &render_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: particle_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: alive_buffer.as_entire_binding() },
+            ],
+            label: Some("particle_render_bind_group"),
+        });
+        let render_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Particle Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("particles.wgsl").into()),
+        });
+        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Particle Render Pipeline Layout"),
+            bind_group_layouts: &[frame_layout, &render_layout],
+            push_constant_ranges: &[],
+        });
+        // 半透明、不写深度、不剔除背面
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Particle Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &render_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &render_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: texture::Texture::DEPTH_COMPARE,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        Self {
+            particle_buffer,
+            _alive_buffer: alive_buffer,
+            params_buffer,
+            draw_args_buffer,
+            compute_pipeline,
+            compute_bind_group,
+            render_pipeline,
+            render_bind_group,
+            next: 0,
+            params: SimParams { dt: 0.0, gravity: GRAVITY, count: POOL_SIZE, _padding: 0 },
+            pending: Vec::new(),
+        }
+    }
+
+    // 模拟参数和新粒子一起上传，新粒子写进环形缓冲区，跨过末尾时分两段写
+    fn upload(&mut self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, uploader: &mut FrameUploader) {
+        uploader.write(queue, encoder, &self.params_buffer, 0, &[self.params]);
+        let spawns = &self.pending[self.pending.len().saturating_sub(POOL_SIZE as usize)..];
+        let stride = std::mem::size_of::<GpuParticle>() as u64;
+        let mut rest = spawns;
+        while !rest.is_empty() {
+            let room = (POOL_SIZE - self.next) as usize;
+            let (chunk, remaining) = rest.split_at(rest.len().min(room));
+            uploader.write(queue, encoder, &self.particle_buffer, self.next as u64 * stride, chunk);
+            self.next = (self.next + chunk.len() as u32) % POOL_SIZE;
+            rest = remaining;
+        }
+        self.pending.clear();
+    }
+}
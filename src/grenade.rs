@@ -0,0 +1,258 @@
+use glam::{Mat4, Quat, Vec3};
+
+use crate::collision::WallCollider;
+use crate::damage::DamageType;
+use crate::icons::Icon;
+use crate::loadout::GrenadeKind;
+use crate::map;
+use crate::model::BoxInstance;
+use crate::ui::UiBatch;
+
+// 手雷：按出生配置里选的种类，每条命带几颗。扔出去以后受重力划出弧线，沿这一步的线段检测墙体、地面和天花板，
+// 碰到就按法向量反弹并损失速度，落地后在地上滚一会儿停下。引信烧完时爆炸，爆炸的伤害和效果由调用者处理
+
+// 每条命带的手雷数量，两次投掷之间的间隔（秒）
+pub const PER_LIFE: usize = 2;
+const THROW_COOLDOWN: f32 = 0.8;
+// 出手速度（米每秒），往上抬的角度让平视扔出去也有弧线
+const THROW_SPEED: f32 = 14.0;
+const THROW_LIFT: f32 = 0.2;
+// 从眼前多远的地方出手
+const THROW_OFFSET: f32 = 0.4;
+const FUSE: f32 = 2.5;
+const GRAVITY: f32 = 9.8;
+const RADIUS: f32 = 0.06;
+// 反弹时法向保留的速度比例，以及切向的摩擦
+const BOUNCE: f32 = 0.45;
+const FRICTION: f32 = 0.7;
+// 落地时法向速度低于这个值就不再弹起，在地上滚的时候每秒损失的速度比例
+const REST_SPEED: f32 = 1.0;
+const ROLL_DRAG: f32 = 2.5;
+// 一步里最多反弹几次，墙角里不会一直来回弹
+const MAX_BOUNCES: usize = 3;
+// 反弹后离开表面的距离，下一步的射线不会从表面里面出发
+const SKIN: f32 = 0.005;
+// 飞行时翻滚的速度（弧度每秒）
+const TUMBLE: f32 = 12.0;
+const SIZE: Vec3 = Vec3::new(0.09, 0.12, 0.09);
+const SHAKE_RANGE: f32 = 3.0;
+
+// 爆炸的伤害、范围和伤害类型：破片是爆炸伤害，燃烧弹点燃目标，震撼弹电击僵直
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Blast {
+    pub damage: f32,
+    pub radius: f32,
+    pub damage_type: DamageType,
+}
+
+pub fn blast(kind: GrenadeKind) -> Blast {
+    match kind {
+        GrenadeKind::Frag => Blast { damage: 120.0, radius: 6.0, damage_type: DamageType::Explosive },
+        GrenadeKind::Incendiary => Blast { damage: 40.0, radius: 4.0, damage_type: DamageType::Fire },
+        GrenadeKind::Stun => Blast { damage: 25.0, radius: 5.0, damage_type: DamageType::Electric },
+    }
+}
+
+// 离爆炸中心 distance 米时受到的伤害比例，中心为 1，范围边缘为 0
+pub fn falloff(blast: &Blast, distance: f32) -> f32 {
+    (1.0 - distance / blast.radius).clamp(0.0, 1.0)
+}
+
+// 画面震动的强度：离得越近震得越厉害，爆炸范围 SHAKE_RANGE 倍以外不震
+pub fn shake(blast: &Blast, distance: f32) -> f32 {
+    (1.0 - distance / (blast.radius * SHAKE_RANGE)).clamp(0.0, 1.0)
+}
+
+fn color(kind: GrenadeKind) -> [f32; 3] {
+    match kind {
+        GrenadeKind::Frag => [0.3, 0.35, 0.2],
+        GrenadeKind::Incendiary => [0.6, 0.15, 0.1],
+        GrenadeKind::Stun => [0.55, 0.6, 0.65],
+    }
+}
+
+// 爆炸火球的颜色：破片和燃烧弹是橙色的火，震撼弹是蓝白色的闪光
+pub fn flash_color(kind: GrenadeKind) -> [f32; 3] {
+    match kind {
+        GrenadeKind::Frag => [1.0, 0.7, 0.3],
+        GrenadeKind::Incendiary => [1.0, 0.45, 0.15],
+        GrenadeKind::Stun => [0.7, 0.85, 1.0],
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Grenade {
+    position: Vec3,
+    // 上一次模拟时的位置，渲染时插值
+    previous: Vec3,
+    velocity: Vec3,
+    fuse: f32,
+    // 翻滚的角度和转轴，落地停下后不再转
+    angle: f32,
+    axis: Vec3,
+    kind: GrenadeKind,
+}
+
+// 一次爆炸
+#[derive(Debug, Clone, Copy)]
+pub struct Explosion {
+    pub position: Vec3,
+    pub kind: GrenadeKind,
+    pub blast: Blast,
+}
+
+pub struct Grenades {
+    grenades: Vec<Grenade>,
+    kind: GrenadeKind,
+    left: usize,
+    cooldown: f32,
+}
+
+impl Grenades {
+    pub fn new() -> Self {
+        Self { grenades: Vec::new(), kind: GrenadeKind::Frag, left: PER_LIFE, cooldown: 0.0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.grenades.is_empty()
+    }
+
+    // 换地图时扔出去还没炸的手雷一起消失
+    pub fn clear(&mut self) {
+        self.grenades.clear();
+    }
+
+    // 重生时按出生配置换种类并补满
+    pub fn refill(&mut self, kind: GrenadeKind) {
+        self.kind = kind;
+        self.left = PER_LIFE;
+        self.cooldown = 0.0;
+    }
+
+    // 从眼睛的位置往视线方向扔一颗，没有手雷或者刚扔过时返回 false
+    pub fn throw(&mut self, eye: Vec3, forward: Vec3) -> bool {
+        if self.left == 0 || self.cooldown > 0.0 {
+            return false;
+        }
+        self.left -= 1;
+        self.cooldown = THROW_COOLDOWN;
+        let dir = (forward + Vec3::Y * THROW_LIFT).normalize_or_zero();
+        let axis = forward.cross(Vec3::Y).try_normalize().unwrap_or(Vec3::X);
+        let position = eye + forward * THROW_OFFSET;
+        self.grenades.push(Grenade {
+            position,
+            previous: position,
+            velocity: dir * THROW_SPEED,
+            fuse: FUSE,
+            angle: 0.0,
+            axis,
+            kind: self.kind,
+        });
+        true
+    }
+
+    // 前进一步，返回这一步里炸开的手雷（没有爆炸时不分配）
+    pub fn update(&mut self, dt: f32, colliders: &[WallCollider]) -> Vec<Explosion> {
+        self.cooldown = (self.cooldown - dt).max(0.0);
+        let mut explosions = Vec::new();
+        self.grenades.retain_mut(|grenade| {
+            grenade.previous = grenade.position;
+            grenade.fuse -= dt;
+            if grenade.fuse <= 0.0 {
+                explosions.push(Explosion { position: grenade.position, kind: grenade.kind, blast: blast(grenade.kind) });
+                return false;
+            }
+            grenade.step(dt, colliders);
+            true
+        });
+        explosions
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Vec3> + '_ {
+        self.grenades.iter().map(|grenade| grenade.position)
+    }
+
+    pub fn boxes(&self, alpha: f32, out: &mut Vec<BoxInstance>) {
+        for grenade in &self.grenades {
+            let position = grenade.previous.lerp(grenade.position, alpha);
+            let rotation = Quat::from_axis_angle(grenade.axis, grenade.angle);
+            out.push(BoxInstance { transform: Mat4::from_scale_rotation_translation(SIZE, rotation, position), color: color(grenade.kind) });
+        }
+    }
+
+    // 右下角弹药上面显示剩下的手雷
+    pub fn draw(&self, ui: &mut UiBatch) {
+        let right = ui.width() - 20.0;
+        let color = if self.left == 0 { [0.6, 0.6, 0.6, 0.7] } else { [1.0, 1.0, 1.0, 0.9] };
+        let text = format!("{} {}", self.left, self.kind.name());
+        let width = UiBatch::text_width(&text, 2.0);
+        let y = ui.height() - 96.0;
+        ui.text(right - width, y, 2.0, color, &text);
+        ui.icon(right - width - 24.0, y - 2.0, 20.0, Icon::Grenade, color);
+    }
+}
+
+impl Grenade {
+    fn step(&mut self, dt: f32, colliders: &[WallCollider]) {
+        self.velocity.y -= GRAVITY * dt;
+        let mut remaining = dt;
+        for _ in 0..MAX_BOUNCES {
+            let step = self.velocity * remaining;
+            let length = step.length();
+            if length <= f32::EPSILON {
+                break;
+            }
+            let dir = step / length;
+            let Some((distance, normal)) = sweep(colliders, self.position, dir, length) else {
+                self.position += step;
+                break;
+            };
+            self.position += dir * distance + normal * SKIN;
+            self.velocity = bounce(self.velocity, normal);
+            remaining *= 1.0 - distance / length;
+        }
+        // 斜着擦过墙角时球心可能离墙不到半径，推出来
+        for collider in colliders {
+            self.position = collider.resolve_collision(self.position, RADIUS);
+        }
+        let grounded = self.position.y <= RADIUS + SKIN * 2.0 && self.velocity.y.abs() <= f32::EPSILON;
+        if grounded {
+            let drag = (1.0 - ROLL_DRAG * dt).max(0.0);
+            self.velocity.x *= drag;
+            self.velocity.z *= drag;
+        } else {
+            self.angle += TUMBLE * dt;
+        }
+    }
+}
+
+// 法向的速度反向并衰减，切向的受摩擦；落地时弹得太低就贴着地面滚
+fn bounce(velocity: Vec3, normal: Vec3) -> Vec3 {
+    let into = velocity.dot(normal);
+    if into >= 0.0 {
+        return velocity;
+    }
+    let tangent = velocity - normal * into;
+    let out = -into * BOUNCE;
+    if normal.y > 0.7 && out < REST_SPEED {
+        return Vec3::new(tangent.x, 0.0, tangent.z);
+    }
+    tangent * FRICTION + normal * out
+}
+
+// 这一步线段上最近的碰撞：球心能走的距离和表面的法向量。地面和天花板按半径往里收，墙体按射线命中点往回退半径
+fn sweep(colliders: &[WallCollider], origin: Vec3, dir: Vec3, length: f32) -> Option<(f32, Vec3)> {
+    let surface = if dir.y < -1e-6 {
+        Some(((RADIUS - origin.y) / dir.y, Vec3::Y))
+    } else if dir.y > 1e-6 {
+        Some(((map::WALL_HEIGHT - RADIUS - origin.y) / dir.y, Vec3::NEG_Y))
+    } else {
+        None
+    };
+    let surface = surface.map(|(distance, normal)| (distance.max(0.0), normal)).filter(|(distance, _)| *distance <= length);
+    let wall = colliders.iter().filter_map(|collider| collider.raycast(origin, dir, length + RADIUS)).map(|hit| {
+        let facing = (-dir.dot(hit.normal)).max(0.2);
+        ((hit.distance - RADIUS / facing).max(0.0), hit.normal)
+    });
+    surface.into_iter().chain(wall.filter(|(distance, _)| *distance <= length)).min_by(|a, b| a.0.total_cmp(&b.0))
+}
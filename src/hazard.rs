@@ -0,0 +1,122 @@
+use glam::Vec3;
+
+use crate::audio::{AmbientEmitter, SoundKind};
+use crate::damage::{Damage, DamageType};
+use crate::gpu_particles::{Emitter, ParticleKind};
+use crate::map::{self, Map};
+use crate::mods;
+
+// 场景危害：地图头部的 "hazard: fire 10 5" 放在格子上，站在上面时按固定间隔受到伤害。
+// 伤害类型和数值定义在 data/hazards.json，mod 可以覆盖或者添加
+
+// data/hazards.json 的格式版本
+const HAZARDS_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct HazardDef {
+    pub id: String,
+    pub damage_type: DamageType,
+    // 每次的伤害和间隔（秒），刚踩上去时立刻受到第一次
+    pub damage: f32,
+    pub interval: f32,
+}
+
+#[derive(serde::Deserialize)]
+struct HazardsFile {
+    hazards: Vec<HazardDef>,
+}
+
+// 游戏自带的加上启用的 mod 里的，id 相同时后加载的替换前面的
+pub fn load_hazards() -> Vec<HazardDef> {
+    mods::load_overlaid(
+        "data/hazards.json",
+        include_str!("../data/hazards.json"),
+        HAZARDS_VERSION,
+        "场景危害",
+        |file: HazardsFile| file.hazards,
+        |hazard| &hazard.id,
+    )
+}
+
+struct Zone {
+    // 格子的中心（地面上）
+    center: Vec3,
+    damage: Damage,
+    interval: f32,
+    // 离下一次伤害的时间，不在格子上时为 0
+    timer: f32,
+}
+
+pub struct HazardSystem {
+    zones: Vec<Zone>,
+}
+
+impl HazardSystem {
+    pub fn new(map: &Map) -> Self {
+        let defs = load_hazards();
+        let mut zones = Vec::new();
+        for spawn in &map.hazards {
+            let Some(def) = defs.iter().find(|def| def.id == spawn.id) else {
+                eprintln!("地图 {} 用了未知的场景危害 {}", map.name, spawn.id);
+                continue;
+            };
+            zones.push(Zone {
+                center: map.cell_center(spawn.col, spawn.row),
+                damage: Damage::new(def.damage, def.damage_type),
+                interval: def.interval.max(0.05),
+                timer: 0.0,
+            });
+        }
+        Self { zones }
+    }
+
+    // position 所在格子上的危害这一帧造成的伤害
+    pub fn update(&mut self, dt: f32, position: Vec3) -> Vec<Damage> {
+        let half = map::CELL_SIZE / 2.0;
+        let mut damage = Vec::new();
+        for zone in &mut self.zones {
+            let inside = (position.x - zone.center.x).abs() <= half && (position.z - zone.center.z).abs() <= half;
+            if !inside {
+                zone.timer = 0.0;
+                continue;
+            }
+            zone.timer -= dt;
+            if zone.timer <= 0.0 {
+                zone.timer += zone.interval;
+                damage.push(zone.damage);
+            }
+        }
+        damage
+    }
+
+    // 火焰冒火星，漏电的地方溅电火花
+    pub fn particle_emitters(&self) -> Vec<Emitter> {
+        let half = Vec3::new(map::CELL_SIZE / 2.0, 0.0, map::CELL_SIZE / 2.0);
+        self.zones
+            .iter()
+            .filter_map(|zone| {
+                let (kind, height, rate) = match zone.damage.kind {
+                    DamageType::Fire => (ParticleKind::Ember, 0.3, 40.0),
+                    DamageType::Electric => (ParticleKind::Spark, 0.5, 10.0),
+                    DamageType::Bullet | DamageType::Melee | DamageType::Explosive => return None,
+                };
+                Some(Emitter::new(kind, zone.center - half, zone.center + half + Vec3::Y * height, rate, false))
+            })
+            .collect()
+    }
+
+    // 火焰噼啪响，电线滋滋响
+    pub fn sound_emitters(&self) -> Vec<AmbientEmitter> {
+        self.zones
+            .iter()
+            .filter_map(|zone| {
+                let (kind, interval) = match zone.damage.kind {
+                    DamageType::Fire => (SoundKind::Burn, 1.1),
+                    DamageType::Electric => (SoundKind::Zap, 1.6),
+                    DamageType::Bullet | DamageType::Melee | DamageType::Explosive => return None,
+                };
+                Some(AmbientEmitter::new(zone.center + Vec3::Y * 0.3, kind, interval))
+            })
+            .collect()
+    }
+}
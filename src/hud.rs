@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use instant::Instant;
+
+use crate::icons::Icon;
+use crate::ui::UiBatch;
+
+// 游戏内 HUD：右上角的击杀提示和提示弹窗（成就解锁等），屏幕中央的短消息（锁门提示等）
+
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+const TOAST_FADE: Duration = Duration::from_millis(600);
+const MAX_TOASTS: usize = 4;
+const MESSAGE_DURATION: Duration = Duration::from_secs(2);
+// 击杀提示：攻击者 → 武器图标 → 被击杀的，新的在下面
+const KILL_DURATION: Duration = Duration::from_secs(5);
+const MAX_KILLS: usize = 5;
+const KILL_ICON_SIZE: f32 = 24.0;
+const KILL_ROW_HEIGHT: f32 = 30.0;
+
+// 击杀提示里名字的颜色：自己、其他玩家、敌人（电脑）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Local,
+    Player,
+    Enemy,
+}
+
+impl Side {
+    fn color(self, alpha: f32) -> [f32; 4] {
+        match self {
+            Side::Local => [1.0, 0.85, 0.2, alpha],
+            Side::Player => [0.9, 0.9, 0.9, alpha],
+            Side::Enemy => [1.0, 0.4, 0.35, alpha],
+        }
+    }
+}
+
+pub struct Kill {
+    // 被地图、调试伤害打死时没有攻击者
+    pub killer: Option<(String, Side)>,
+    pub icon: Icon,
+    pub headshot: bool,
+    pub victim: (String, Side),
+}
+
+struct KillEntry {
+    kill: Kill,
+    created: Instant,
+}
+
+struct Toast {
+    title: String,
+    text: String,
+    created: Instant,
+}
+
+struct Message {
+    text: String,
+    color: [f32; 4],
+    created: Instant,
+}
+
+pub struct Hud {
+    toasts: VecDeque<Toast>,
+    message: Option<Message>,
+    kills: VecDeque<KillEntry>,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Self { toasts: VecDeque::new(), message: None, kills: VecDeque::new() }
+    }
+
+    // 新消息直接替换旧的
+    pub fn show_message(&mut self, text: &str, color: [f32; 4]) {
+        self.message = Some(Message { text: text.to_string(), color, created: Instant::now() });
+    }
+
+    pub fn push_toast(&mut self, title: &str, text: &str) {
+        self.toasts.push_back(Toast {
+            title: title.to_string(),
+            text: text.to_string(),
+            created: Instant::now(),
+        });
+        while self.toasts.len() > MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+    }
+
+    pub fn push_kill(&mut self, kill: Kill) {
+        self.kills.push_back(KillEntry { kill, created: Instant::now() });
+        while self.kills.len() > MAX_KILLS {
+            self.kills.pop_front();
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.toasts.retain(|toast| toast.created.elapsed() < TOAST_DURATION);
+        self.kills.retain(|entry| entry.created.elapsed() < KILL_DURATION);
+        if self.message.as_ref().is_some_and(|message| message.created.elapsed() >= MESSAGE_DURATION) {
+            self.message = None;
+        }
+    }
+
+    pub fn draw(&self, ui: &mut UiBatch) {
+        if let Some(message) = &self.message {
+            let y = ui.height() * 0.35;
+            let width = UiBatch::text_width(&message.text, 3.0) + 32.0;
+            ui.rect((ui.width() - width) / 2.0, y - 12.0, width, 45.0, [0.0, 0.0, 0.0, 0.6]);
+            ui.text_centered(ui.width() / 2.0, y, 3.0, message.color, &message.text);
+        }
+
+        // 击杀提示靠右对齐，提示弹窗排在它下面
+        let right = ui.width() - 16.0;
+        let mut y = 16.0;
+        for entry in &self.kills {
+            let alpha = fade(KILL_DURATION, entry.created);
+            let kill = &entry.kill;
+            let scale = 2.0;
+            let victim_width = UiBatch::text_width(&kill.victim.0, scale);
+            let killer_width = kill.killer.as_ref().map_or(0.0, |(name, _)| UiBatch::text_width(name, scale) + 8.0);
+            let headshot_width = if kill.headshot { UiBatch::text_width("HS", scale) + 6.0 } else { 0.0 };
+            let width = killer_width + KILL_ICON_SIZE + headshot_width + 8.0 + victim_width + 16.0;
+            let mut x = right - width;
+            ui.rect(x, y, width, KILL_ROW_HEIGHT - 4.0, [0.0, 0.0, 0.0, 0.55 * alpha]);
+            x += 8.0;
+            let text_y = y + 5.0;
+            if let Some((name, side)) = &kill.killer {
+                ui.text(x, text_y, scale, side.color(alpha), name);
+                x += killer_width;
+            }
+            ui.icon(x, y + 1.0, KILL_ICON_SIZE, kill.icon, [1.0, 1.0, 1.0, alpha]);
+            x += KILL_ICON_SIZE + 6.0;
+            if kill.headshot {
+                ui.text(x, text_y, scale, [1.0, 0.85, 0.2, alpha], "HS");
+                x += headshot_width;
+            }
+            ui.text(x + 2.0, text_y, scale, kill.victim.1.color(alpha), &kill.victim.0);
+            y += KILL_ROW_HEIGHT;
+        }
+        if !self.kills.is_empty() {
+            y += 8.0;
+        }
+
+        let width = 360.0;
+        let height = 52.0;
+        let x = right - width;
+        for toast in &self.toasts {
+            let alpha = fade(TOAST_DURATION, toast.created);
+
+            ui.rect(x, y, width, height, [0.05, 0.05, 0.1, 0.8 * alpha]);
+            ui.rect(x, y, 4.0, height, [1.0, 0.85, 0.2, alpha]);
+            ui.text(x + 14.0, y + 8.0, 2.0, [1.0, 0.85, 0.2, alpha], &toast.title);
+            ui.text(x + 14.0, y + 30.0, 2.0, [0.9, 0.9, 0.9, alpha], &toast.text);
+            y += height + 8.0;
+        }
+    }
+}
+
+// 最后一段时间淡出
+fn fade(duration: Duration, created: Instant) -> f32 {
+    let remaining = duration.saturating_sub(created.elapsed());
+    (remaining.as_secs_f32() / TOAST_FADE.as_secs_f32()).min(1.0)
+}
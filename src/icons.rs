@@ -0,0 +1,346 @@
+use crate::weapon::WeaponSlot;
+
+// 界面图标图集：16x16 的点阵图标，白色（绘制时用顶点颜色着色）
+// 第 0 格整格为白色，纯色矩形和文字都从这里采样
+
+pub const ICON_SIZE: u32 = 16;
+const ATLAS_CELLS: u32 = 4;
+// 第 0 格的中心
+pub const SOLID_UV: [f32; 2] = [0.5 / ATLAS_CELLS as f32; 2];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Icon {
+    Rifle,
+    Pistol,
+    Shotgun,
+    Ammo,
+    Key,
+    Quest,
+    // 击杀提示里没有武器时（地图伤害等）
+    Skull,
+    // HUD 上的状态：燃烧、电击僵直、重生保护、不能冲刺
+    Flame,
+    Bolt,
+    Shield,
+    NoSprint,
+    // 手雷数量和手雷的击杀提示
+    Grenade,
+    // 近战的击杀提示
+    Knife,
+}
+
+impl Icon {
+    fn cell(self) -> u32 {
+        match self {
+            Icon::Rifle => 1,
+            Icon::Pistol => 2,
+            Icon::Ammo => 3,
+            Icon::Key => 4,
+            Icon::Quest => 5,
+            Icon::Skull => 6,
+            Icon::Flame => 7,
+            Icon::Bolt => 8,
+            Icon::Shield => 9,
+            Icon::NoSprint => 10,
+            Icon::Shotgun => 11,
+            Icon::Grenade => 12,
+            Icon::Knife => 13,
+        }
+    }
+
+    // '#' 为图标像素
+    fn bitmap(self) -> [&'static str; 16] {
+        match self {
+            Icon::Rifle => [
+                "................",
+                "................",
+                "................",
+                "................",
+                "..........#.....",
+                "################",
+                "###############.",
+                "#####.##.#......",
+                "####...##.......",
+                "###....##.......",
+                "##......#.......",
+                "................",
+                "................",
+                "................",
+                "................",
+                "................",
+            ],
+            Icon::Shotgun => [
+                "................",
+                "................",
+                "................",
+                "................",
+                "................",
+                "###############.",
+                "###############.",
+                "#####.#######...",
+                "####...##.......",
+                "###....##.......",
+                "##..............",
+                "................",
+                "................",
+                "................",
+                "................",
+                "................",
+            ],
+            Icon::Pistol => [
+                "................",
+                "................",
+                "................",
+                "...#............",
+                "..############..",
+                "..############..",
+                "..#######.......",
+                "..######.#......",
+                "..#####.##......",
+                "..#####.........",
+                "..####..........",
+                "..####..........",
+                "..####..........",
+                "................",
+                "................",
+                "................",
+            ],
+            Icon::Ammo => [
+                "................",
+                "...#....#....#..",
+                "..###..###..###.",
+                "..###..###..###.",
+                "..###..###..###.",
+                "..###..###..###.",
+                "..###..###..###.",
+                "..###..###..###.",
+                "..###..###..###.",
+                "..###..###..###.",
+                "..###..###..###.",
+                "..###..###..###.",
+                "................",
+                "..###..###..###.",
+                "..###..###..###.",
+                "................",
+            ],
+            Icon::Key => [
+                "................",
+                "................",
+                "................",
+                "..#####.........",
+                ".##...##........",
+                ".#.....#........",
+                ".#.....#########",
+                ".#.....#########",
+                ".#.....#...#.#..",
+                ".##...##...#.#..",
+                "..#####.........",
+                "................",
+                "................",
+                "................",
+                "................",
+                "................",
+            ],
+            Icon::Quest => [
+                "................",
+                "......####......",
+                ".....##..##.....",
+                "....##....##....",
+                "...##......##...",
+                "..##...##...##..",
+                ".##....##....##.",
+                "##.....##.....##",
+                "##.....##.....##",
+                ".##..........##.",
+                "..##...##...##..",
+                "...##..##..##...",
+                "....##....##....",
+                ".....##..##.....",
+                "......####......",
+                "................",
+            ],
+            Icon::Skull => [
+                "................",
+                "....########....",
+                "...##########...",
+                "..############..",
+                "..############..",
+                "..###..##..###..",
+                "..##....#...##..",
+                "..###..##..###..",
+                "..#####..#####..",
+                "...####..####...",
+                "....########....",
+                ".....#.##.#.....",
+                ".....######.....",
+                "................",
+                "................",
+                "................",
+            ],
+            Icon::Flame => [
+                "................",
+                ".......#........",
+                ".......##.......",
+                "......###.......",
+                "......####......",
+                ".....#####..#...",
+                ".....######.##..",
+                "....#########...",
+                "...##########...",
+                "...####.#####...",
+                "..####...####...",
+                "..###.....###...",
+                "..###.....###...",
+                "...###...###....",
+                "....#######.....",
+                "................",
+            ],
+            Icon::Bolt => [
+                "................",
+                "........####....",
+                ".......####.....",
+                "......####......",
+                ".....####.......",
+                "....####........",
+                "...##########...",
+                "...#########....",
+                "........###.....",
+                ".......###......",
+                "......###.......",
+                ".....###........",
+                "....###.........",
+                "...##...........",
+                "..#.............",
+                "................",
+            ],
+            Icon::Shield => [
+                "................",
+                "..############..",
+                "..############..",
+                "..############..",
+                "..############..",
+                "..############..",
+                "..############..",
+                "...##########...",
+                "...##########...",
+                "....########....",
+                "....########....",
+                ".....######.....",
+                "......####......",
+                ".......##.......",
+                "................",
+                "................",
+            ],
+            Icon::NoSprint => [
+                "#...............",
+                ".#..............",
+                "..#..#####......",
+                "...#.#####......",
+                "....######......",
+                ".......###......",
+                ".....#..##......",
+                ".....##..#......",
+                ".....###........",
+                ".....####.......",
+                ".....#####..###.",
+                ".....######..##.",
+                ".....#######..#.",
+                ".....########...",
+                "..............#.",
+                "...............#",
+            ],
+            Icon::Grenade => [
+                "................",
+                "......###.......",
+                ".....#...##.....",
+                ".....#.####.....",
+                "......#####.....",
+                ".....#######....",
+                "....#########...",
+                "...###.#.#.###..",
+                "...###########..",
+                "...###.#.#.###..",
+                "...###########..",
+                "...###.#.#.###..",
+                "....#########...",
+                ".....#######....",
+                "................",
+                "................",
+            ],
+            Icon::Knife => [
+                "................",
+                "................",
+                "................",
+                "................",
+                "................",
+                "...........##...",
+                "#####.#########.",
+                "######.#########",
+                "#####.#########.",
+                "...........#....",
+                "................",
+                "................",
+                "................",
+                "................",
+                "................",
+                "................",
+            ],
+        }
+    }
+
+    // 图标在图集中的纹理坐标（向内缩半个像素，避免线性过滤采样到相邻格子）
+    pub fn uv(self) -> ([f32; 2], [f32; 2]) {
+        let size = 1.0 / ATLAS_CELLS as f32;
+        let inset = 0.5 / (ATLAS_CELLS * ICON_SIZE) as f32;
+        let cell = self.cell();
+        let (x, y) = ((cell % ATLAS_CELLS) as f32 * size, (cell / ATLAS_CELLS) as f32 * size);
+        ([x + inset, y + inset], [x + size - inset, y + size - inset])
+    }
+}
+
+const ALL: [Icon; 13] = [
+    Icon::Rifle,
+    Icon::Pistol,
+    Icon::Shotgun,
+    Icon::Ammo,
+    Icon::Key,
+    Icon::Quest,
+    Icon::Skull,
+    Icon::Flame,
+    Icon::Bolt,
+    Icon::Shield,
+    Icon::NoSprint,
+    Icon::Grenade,
+    Icon::Knife,
+];
+
+// 武器按栏位用步枪、手枪或霰弹枪的图标
+pub fn weapon(slot: WeaponSlot) -> Icon {
+    match slot {
+        WeaponSlot::Primary => Icon::Rifle,
+        WeaponSlot::Secondary => Icon::Pistol,
+        WeaponSlot::Heavy => Icon::Shotgun,
+    }
+}
+
+pub fn generate_atlas() -> image::RgbaImage {
+    let size = ATLAS_CELLS * ICON_SIZE;
+    let mut image = image::RgbaImage::new(size, size);
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            image.put_pixel(x, y, image::Rgba([255, 255, 255, 255]));
+        }
+    }
+    for icon in ALL {
+        let cell = icon.cell();
+        let (ox, oy) = ((cell % ATLAS_CELLS) * ICON_SIZE, (cell / ATLAS_CELLS) * ICON_SIZE);
+        for (y, row) in icon.bitmap().iter().enumerate() {
+            for (x, c) in row.chars().take(ICON_SIZE as usize).enumerate() {
+                if c == '#' {
+                    image.put_pixel(ox + x as u32, oy + y as u32, image::Rgba([255, 255, 255, 255]));
+                }
+            }
+        }
+    }
+    image
+}
@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use winit::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
+
+use crate::axes::GamepadAxes;
+
+// 按键绑定：游戏操作 -> 按键/鼠标按钮，和每个手柄的摇杆设置一起保存在 input.json 中（所在目录见 paths.rs）
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Sprint,
+    Fire,
+    Reload,
+    Use,
+    Drop,
+    // 扔手雷
+    Grenade,
+    // 近战
+    Melee,
+    // 切换到第几个武器栏位，以及按顺序切换
+    Weapon1,
+    Weapon2,
+    Weapon3,
+    NextWeapon,
+    PrevWeapon,
+    Inventory,
+    // 波次模式购买阶段打开商店
+    Shop,
+    // 夜视仪开关
+    NightVision,
+    Minimap,
+    Chat,
+    Scoreboard,
+    // 观战自由相机的倾斜，拍照用
+    RollLeft,
+    RollRight,
+}
+
+impl Action {
+    pub const ALL: [Action; 25] = [
+        Action::MoveForward,
+        Action::MoveBack,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::Sprint,
+        Action::Fire,
+        Action::Reload,
+        Action::Use,
+        Action::Drop,
+        Action::Grenade,
+        Action::Melee,
+        Action::Weapon1,
+        Action::Weapon2,
+        Action::Weapon3,
+        Action::NextWeapon,
+        Action::PrevWeapon,
+        Action::Inventory,
+        Action::Shop,
+        Action::NightVision,
+        Action::Minimap,
+        Action::Chat,
+        Action::Scoreboard,
+        Action::RollLeft,
+        Action::RollRight,
+    ];
+
+    // 配置文件和提示文字中使用的名字
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveForward => "move_forward",
+            Action::MoveBack => "move_back",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::Jump => "jump",
+            Action::Sprint => "sprint",
+            Action::Fire => "fire",
+            Action::Reload => "reload",
+            Action::Use => "use",
+            Action::Drop => "drop",
+            Action::Grenade => "grenade",
+            Action::Melee => "melee",
+            Action::Weapon1 => "weapon_1",
+            Action::Weapon2 => "weapon_2",
+            Action::Weapon3 => "weapon_3",
+            Action::NextWeapon => "next_weapon",
+            Action::PrevWeapon => "prev_weapon",
+            Action::Inventory => "inventory",
+            Action::Shop => "shop",
+            Action::NightVision => "night_vision",
+            Action::Minimap => "minimap",
+            Action::Chat => "chat",
+            Action::Scoreboard => "scoreboard",
+            Action::RollLeft => "roll_left",
+            Action::RollRight => "roll_right",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().copied().find(|action| action.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    // 显示给玩家的按键名
+    pub fn display_name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key).to_uppercase(),
+            Binding::Mouse(MouseButton::Left) => "LMB".to_string(),
+            Binding::Mouse(MouseButton::Right) => "RMB".to_string(),
+            Binding::Mouse(MouseButton::Middle) => "MMB".to_string(),
+            Binding::Mouse(MouseButton::Other(n)) => format!("MOUSE{}", n),
+        }
+    }
+
+    // 从窗口事件中取出按键/鼠标按钮和按下状态
+    pub fn from_event(event: &WindowEvent) -> Option<(Binding, bool)> {
+        match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput { state, virtual_keycode: Some(key), .. },
+                ..
+            } => Some((Binding::Key(*key), *state == ElementState::Pressed)),
+            WindowEvent::MouseInput { state, button, .. } => {
+                Some((Binding::Mouse(*button), *state == ElementState::Pressed))
+            }
+            _ => None,
+        }
+    }
+}
+
+// 最近一次用的输入设备，提示文字和按键设置界面按它显示按键。碰一下另一个设备就立刻切换
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Device {
+    KeyboardMouse,
+    #[cfg(feature = "gamepad")]
+    Gamepad(crate::gamepad::Layout),
+}
+
+impl Device {
+    // 手柄上这个操作的按键图标，键鼠或者手柄没有对应按键时返回 None
+    #[cfg_attr(not(feature = "gamepad"), allow(unused_variables))]
+    pub fn glyph(self, action: Action) -> Option<&'static str> {
+        match self {
+            Device::KeyboardMouse => None,
+            #[cfg(feature = "gamepad")]
+            Device::Gamepad(layout) => crate::gamepad::button_for_action(action).map(|button| layout.glyph(button)),
+        }
+    }
+
+    // 手柄在菜单里确定和返回的按键图标，键鼠返回 None
+    pub fn menu_buttons(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Device::KeyboardMouse => None,
+            #[cfg(feature = "gamepad")]
+            Device::Gamepad(layout) => Some((layout.glyph(gilrs::Button::South), layout.glyph(gilrs::Button::East))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct InputConfig {
+    // 每个操作可以绑定多个按键
+    pub bindings: BTreeMap<Action, Vec<Binding>>,
+    // 手柄 GUID -> 摇杆和扳机的设置，没有记录的手柄用默认设置
+    #[serde(default)]
+    pub gamepads: BTreeMap<String, GamepadAxes>,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        let bindings = [
+            (Action::MoveForward, Binding::Key(VirtualKeyCode::W)),
+            (Action::MoveBack, Binding::Key(VirtualKeyCode::S)),
+            (Action::MoveLeft, Binding::Key(VirtualKeyCode::A)),
+            (Action::MoveRight, Binding::Key(VirtualKeyCode::D)),
+            (Action::Jump, Binding::Key(VirtualKeyCode::Space)),
+            (Action::Sprint, Binding::Key(VirtualKeyCode::LShift)),
+            (Action::Fire, Binding::Mouse(MouseButton::Left)),
+            (Action::Reload, Binding::Key(VirtualKeyCode::R)),
+            (Action::Use, Binding::Key(VirtualKeyCode::E)),
+            (Action::Drop, Binding::Key(VirtualKeyCode::G)),
+            (Action::Grenade, Binding::Key(VirtualKeyCode::H)),
+            (Action::Melee, Binding::Key(VirtualKeyCode::V)),
+            (Action::Weapon1, Binding::Key(VirtualKeyCode::Key1)),
+            (Action::Weapon2, Binding::Key(VirtualKeyCode::Key2)),
+            (Action::Weapon3, Binding::Key(VirtualKeyCode::Key3)),
+            (Action::NextWeapon, Binding::Key(VirtualKeyCode::X)),
+            (Action::PrevWeapon, Binding::Key(VirtualKeyCode::Q)),
+            (Action::Inventory, Binding::Key(VirtualKeyCode::I)),
+            (Action::Shop, Binding::Key(VirtualKeyCode::B)),
+            (Action::NightVision, Binding::Key(VirtualKeyCode::N)),
+            (Action::Minimap, Binding::Key(VirtualKeyCode::M)),
+            (Action::Chat, Binding::Key(VirtualKeyCode::T)),
+            (Action::Scoreboard, Binding::Key(VirtualKeyCode::Tab)),
+            (Action::RollLeft, Binding::Key(VirtualKeyCode::Z)),
+            (Action::RollRight, Binding::Key(VirtualKeyCode::C)),
+        ];
+        Self {
+            bindings: bindings.into_iter().map(|(action, binding)| (action, vec![binding])).collect(),
+            gamepads: BTreeMap::new(),
+        }
+    }
+}
+
+impl InputConfig {
+    // 没有配置文件时使用默认绑定，缺少的操作也补上默认值
+    pub fn load(path: &Path) -> Self {
+        let mut config = match std::fs::read_to_string(path) {
+            Ok(text) => match serde_json::from_str::<InputConfig>(&text) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("按键配置 {} 解析失败，使用默认绑定: {}", path.display(), e);
+                    InputConfig::default()
+                }
+            },
+            Err(_) => InputConfig::default(),
+        };
+        for (action, bindings) in InputConfig::default().bindings {
+            config.bindings.entry(action).or_insert(bindings);
+        }
+        config
+    }
+
+    pub fn save(&self, path: &Path) {
+        match serde_json::to_string_pretty(self) {
+            Ok(text) => {
+                if let Err(e) = std::fs::write(path, text) {
+                    eprintln!("保存按键配置失败 {}: {}", path.display(), e);
+                }
+            }
+            Err(e) => eprintln!("按键配置序列化失败: {}", e),
+        }
+    }
+
+    pub fn gamepad_axes(&self, guid: &str) -> GamepadAxes {
+        self.gamepads.get(guid).cloned().unwrap_or_default()
+    }
+
+    pub fn action_for(&self, binding: Binding) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| *action)
+    }
+
+    // 操作绑定的按键名，多个按键用 "/" 连接。用手柄时显示手柄上的按键
+    pub fn describe(&self, action: Action, device: Device) -> String {
+        if let Some(glyph) = device.glyph(action) {
+            return glyph.to_string();
+        }
+        match self.bindings.get(&action) {
+            Some(bindings) if !bindings.is_empty() => bindings
+                .iter()
+                .map(Binding::display_name)
+                .collect::<Vec<_>>()
+                .join("/"),
+            _ => "UNBOUND".to_string(),
+        }
+    }
+
+    // 把文字中的 {action_name} 替换为绑定的按键
+    pub fn format_prompt(&self, text: &str, device: Device) -> String {
+        let mut result = text.to_string();
+        for action in Action::ALL {
+            let placeholder = format!("{{{}}}", action.name());
+            if result.contains(&placeholder) {
+                result = result.replace(&placeholder, &self.describe(action, device));
+            }
+        }
+        result
+    }
+}
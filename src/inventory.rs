@@ -0,0 +1,311 @@
+use crate::icons::{self, Icon};
+use crate::map::KeyColor;
+use crate::ui::{Nav, UiBatch};
+use crate::weapon::{Weapon, WeaponPerks, WeaponSlot, WeaponStats};
+
+// 玩家的物品栏：主武器、副武器和重武器各一个栏位，加上弹药、钥匙和任务物品，
+// 以及按 I 打开的物品栏界面和右下角的弹药数。数字键和手柄肩键切换武器
+
+const PANEL_COLOR: [f32; 4] = [0.05, 0.05, 0.1, 0.9];
+const HEADER_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const DIM_COLOR: [f32; 4] = [0.55, 0.55, 0.6, 1.0];
+const HIGHLIGHT_COLOR: [f32; 4] = [0.25, 0.35, 0.6, 0.9];
+const ICON_COLOR: [f32; 4] = [0.85, 0.85, 0.8, 1.0];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ItemKind {
+    Ammo,
+    Key,
+    Quest,
+}
+
+impl ItemKind {
+    const ALL: [ItemKind; 3] = [ItemKind::Ammo, ItemKind::Key, ItemKind::Quest];
+
+    fn title(self) -> &'static str {
+        match self {
+            ItemKind::Ammo => "AMMO",
+            ItemKind::Key => "KEYS",
+            ItemKind::Quest => "QUEST ITEMS",
+        }
+    }
+
+    fn icon(self) -> Icon {
+        match self {
+            ItemKind::Ammo => Icon::Ammo,
+            ItemKind::Key => Icon::Key,
+            ItemKind::Quest => Icon::Quest,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub kind: ItemKind,
+    pub name: String,
+    pub count: u32,
+    // 图标颜色（钥匙按颜色区分）
+    pub color: [f32; 4],
+}
+
+pub struct Inventory {
+    slots: [Option<Weapon>; WeaponSlot::COUNT],
+    current: usize,
+    pub items: Vec<Item>,
+    // 天赋的加成，之后捡起的武器也用它
+    perks: WeaponPerks,
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self { slots: [None, None, None], current: 0, items: Vec::new(), perks: WeaponPerks::default() }
+    }
+
+    // 重生或者在商店买了天赋时按天赋设置
+    pub fn set_perks(&mut self, perks: WeaponPerks) {
+        self.perks = perks;
+        for weapon in self.slots.iter_mut().flatten() {
+            weapon.set_perks(perks);
+        }
+    }
+
+    // 手上所有武器的弹匣和备弹装满，都是满的时返回 false
+    pub fn refill(&mut self) -> bool {
+        let mut refilled = false;
+        for weapon in self.slots.iter_mut().flatten() {
+            refilled |= weapon.refill();
+        }
+        refilled
+    }
+
+    pub fn can_add_ammo(&self) -> bool {
+        self.slots.iter().flatten().any(|weapon| !weapon.reserve_full())
+    }
+
+    // 每把武器补几个弹匣的备弹
+    pub fn add_ammo(&mut self, magazines: u32) {
+        for weapon in self.slots.iter_mut().flatten() {
+            let rounds = weapon.magazine() * magazines;
+            weapon.add_reserve(rounds);
+        }
+    }
+
+    pub fn has_weapon(&self, id: &str) -> bool {
+        self.slots.iter().flatten().any(|weapon| weapon.stats.id == id)
+    }
+
+    pub fn current(&self) -> Option<&Weapon> {
+        self.slots[self.current].as_ref()
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut Weapon> {
+        self.slots[self.current].as_mut()
+    }
+
+    // 只有手上的武器冷却和换弹
+    pub fn update(&mut self, dt: f32) {
+        if let Some(weapon) = self.current_mut() {
+            weapon.update(dt);
+        }
+    }
+
+    // 放进对应的栏位并切换过去，栏位里原来的武器被换下来返回给调用者
+    pub fn add(&mut self, stats: WeaponStats) -> Option<WeaponStats> {
+        let index = stats.slot.index();
+        self.current = index;
+        let old = self.slots[index].replace(Weapon::new(stats, self.perks)).map(|weapon| weapon.stats);
+        if let Some(weapon) = self.current_mut() {
+            weapon.raise();
+        }
+        old
+    }
+
+    // 按出生配置换掉所有武器，拿着第一把
+    pub fn equip(&mut self, weapons: Vec<WeaponStats>) {
+        self.slots = [None, None, None];
+        for stats in weapons.into_iter().rev() {
+            self.add(stats);
+        }
+    }
+
+    // 丢掉手上的武器，切换到另一个栏位
+    pub fn drop_current(&mut self) -> Option<WeaponStats> {
+        let dropped = self.slots[self.current].take()?;
+        if let Some(index) = self.slots.iter().position(Option::is_some) {
+            self.current = index;
+        }
+        Some(dropped.stats)
+    }
+
+    pub fn add_keycard(&mut self, key: KeyColor) {
+        if !self.has_keycard(key) {
+            self.items.push(Item { kind: ItemKind::Key, name: keycard_name(key), count: 1, color: key.ui_color() });
+        }
+    }
+
+    pub fn has_keycard(&self, key: KeyColor) -> bool {
+        let name = keycard_name(key);
+        self.items.iter().any(|item| item.kind == ItemKind::Key && item.name == name)
+    }
+
+    // 切换到某个栏位，栏位为空或者已经拿在手上时不切换
+    pub fn select(&mut self, index: usize) -> bool {
+        if index == self.current || !self.slots.get(index).is_some_and(Option::is_some) {
+            return false;
+        }
+        self.current = index;
+        if let Some(weapon) = self.current_mut() {
+            weapon.raise();
+        }
+        true
+    }
+
+    // 切换到下一个（step 为 1）或上一个（step 为 -1）有武器的栏位，循环
+    pub fn cycle(&mut self, step: isize) -> bool {
+        let count = self.slots.len() as isize;
+        (1..count)
+            .map(|offset| (self.current as isize + step * offset).rem_euclid(count) as usize)
+            .find(|index| self.slots[*index].is_some())
+            .is_some_and(|index| self.select(index))
+    }
+
+    // 右下角：手上武器的名字，上面是弹匣里的子弹数和备弹数（不限备弹时是弹匣容量），换弹时显示 RELOADING
+    pub fn draw_ammo(&self, ui: &mut UiBatch) {
+        let right = ui.width() - 20.0;
+        let name = self.current().map_or("UNARMED".to_string(), |weapon| weapon.stats.name.to_uppercase());
+        ui.text(right - UiBatch::text_width(&name, 3.0), ui.height() - 44.0, 3.0, [1.0, 1.0, 1.0, 0.9], &name);
+        let Some(weapon) = self.current().filter(|weapon| weapon.magazine() > 0) else { return };
+        let total = weapon.reserve().unwrap_or(weapon.magazine());
+        let label = weapon.stats.ammo.label();
+        let (ammo, color) = if weapon.is_reloading() {
+            ("RELOADING".to_string(), DIM_COLOR)
+        } else if weapon.ammo() * 4 <= weapon.magazine() {
+            (format!("{} / {} {}", weapon.ammo(), total, label), [1.0, 0.4, 0.3, 1.0])
+        } else {
+            (format!("{} / {} {}", weapon.ammo(), total, label), TEXT_COLOR)
+        };
+        ui.text(right - UiBatch::text_width(&ammo, 2.0), ui.height() - 70.0, 2.0, color, &ammo);
+    }
+
+    // 界面中可以选中的行：先是武器栏位，然后按类别排列的物品（与界面上的顺序一致）
+    fn rows(&self) -> Vec<Row> {
+        let mut rows: Vec<Row> = self
+            .slots
+            .iter()
+            .enumerate()
+            .filter(|(_, weapon)| weapon.is_some())
+            .map(|(index, _)| Row::Weapon(index))
+            .collect();
+        for kind in ItemKind::ALL {
+            rows.extend(self.items.iter().enumerate().filter(|(_, item)| item.kind == kind).map(|(index, _)| Row::Item(index)));
+        }
+        rows
+    }
+}
+
+fn keycard_name(key: KeyColor) -> String {
+    format!("{} keycard", key.name())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Row {
+    Weapon(usize),
+    Item(usize),
+}
+
+pub struct InventoryScreen {
+    pub open: bool,
+    selected: usize,
+}
+
+impl InventoryScreen {
+    pub fn new() -> Self {
+        Self { open: false, selected: 0 }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.selected = 0;
+    }
+
+    // 选中武器时装备它
+    pub fn navigate(&mut self, nav: Nav, inventory: &mut Inventory) {
+        let rows = inventory.rows();
+        let count = rows.len();
+        match nav {
+            Nav::Up if count > 0 => self.selected = (self.selected + count - 1) % count,
+            Nav::Down if count > 0 => self.selected = (self.selected + 1) % count,
+            Nav::Select => {
+                if let Some(Row::Weapon(index)) = rows.get(self.selected) {
+                    inventory.select(*index);
+                }
+            }
+            Nav::Back => self.open = false,
+            _ => {}
+        }
+    }
+
+    pub fn draw(&self, ui: &mut UiBatch, inventory: &Inventory, paused: bool) {
+        let scale = 2.0;
+        let line = 40.0;
+        let icon_size = 32.0;
+        let panel_w = 520.0;
+        // 每类物品为空时也占一行 "NONE"
+        let weapon_lines = inventory.slots.iter().flatten().count();
+        let item_lines: usize = ItemKind::ALL
+            .iter()
+            .map(|kind| inventory.items.iter().filter(|item| item.kind == *kind).count().max(1))
+            .sum();
+        let panel_h = 104.0 + line * (0.5 + 0.75 * ItemKind::ALL.len() as f32 + (weapon_lines + item_lines) as f32);
+        let panel_x = (ui.width() - panel_w) / 2.0;
+        let panel_y = ((ui.height() - panel_h) / 2.0).max(10.0);
+        ui.rect(0.0, 0.0, ui.width(), ui.height(), [0.0, 0.0, 0.0, 0.4]);
+        ui.rect(panel_x, panel_y, panel_w, panel_h, PANEL_COLOR);
+        ui.text_centered(ui.width() / 2.0, panel_y + 16.0, 3.0, TEXT_COLOR, "INVENTORY");
+        if paused {
+            ui.text(panel_x + panel_w - 90.0, panel_y + 20.0, scale, DIM_COLOR, "PAUSED");
+        }
+
+        let rows = inventory.rows();
+        let selected = rows.get(self.selected).copied();
+        let x = panel_x + 24.0;
+        let mut y = panel_y + 56.0;
+        let row_line = |ui: &mut UiBatch, y: &mut f32, row: Row, icon: Icon, color: [f32; 4], name: &str, detail: &str| {
+            if selected == Some(row) {
+                ui.rect(panel_x + 12.0, *y - 4.0, panel_w - 24.0, line, HIGHLIGHT_COLOR);
+            }
+            ui.icon(x, *y, icon_size, icon, color);
+            ui.text(x + icon_size + 12.0, *y + 9.0, scale, TEXT_COLOR, name);
+            ui.text(panel_x + panel_w - 24.0 - UiBatch::text_width(detail, scale), *y + 9.0, scale, DIM_COLOR, detail);
+            *y += line;
+        };
+
+        ui.text(x, y, scale, HEADER_COLOR, "WEAPONS");
+        y += line * 0.5;
+        for (index, weapon) in inventory.slots.iter().enumerate() {
+            let Some(weapon) = weapon else { continue };
+            let icon = icons::weapon(weapon.stats.slot);
+            let detail = if index == inventory.current { "EQUIPPED" } else { "" };
+            row_line(ui, &mut y, Row::Weapon(index), icon, ICON_COLOR, &weapon.stats.name.to_uppercase(), detail);
+        }
+
+        for kind in ItemKind::ALL {
+            y += line * 0.25;
+            ui.text(x, y, scale, HEADER_COLOR, kind.title());
+            y += line * 0.5;
+            let mut empty = true;
+            for (index, item) in inventory.items.iter().enumerate().filter(|(_, item)| item.kind == kind) {
+                empty = false;
+                let count = if item.count > 1 { format!("x{}", item.count) } else { String::new() };
+                row_line(ui, &mut y, Row::Item(index), kind.icon(), item.color, &item.name.to_uppercase(), &count);
+            }
+            if empty {
+                ui.text(x + icon_size + 12.0, y + 9.0, scale, DIM_COLOR, "NONE");
+                y += line;
+            }
+        }
+
+        ui.text(panel_x + 24.0, panel_y + panel_h - 28.0, scale, DIM_COLOR, "ENTER equip   I/ESC close");
+    }
+}
@@ -0,0 +1,81 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+// 小型任务系统：每帧把互不依赖的系统（敌人、物理和狗、粒子、小地图）在 scope 里作为任务提交。
+// scope 返回前等待所有任务完成，这就是渲染之前的同步点，任务里可以借用当前帧的数据。
+// 任务在 f 返回后用 std::thread::scope 执行：最多启动核数减一个工作线程，主线程也从队列里取任务执行；
+// 只有一个核或者只有一个任务时不启动线程，任务直接在主线程上按顺序执行。
+// 任务里的随机数（fastrand）用提交时从当前线程取的种子，结果只取决于提交的顺序，
+// 和任务在哪个线程上、按什么顺序执行无关，主线程帮忙执行任务也不会打乱它自己的随机数（见 determinism.rs）。
+// 音频的混音由 rodio 自己的线程完成，不经过这里
+
+type Job<'scope> = Box<dyn FnOnce() + Send + 'scope>;
+
+pub struct JobPool {
+    // 最多同时使用的工作线程数
+    threads: usize,
+}
+
+impl JobPool {
+    // 工作线程数为核数减一（主线程也执行任务）
+    pub fn new() -> Self {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get()).saturating_sub(1);
+        println!("任务线程: 最多 {} 个", threads);
+        Self { threads }
+    }
+
+    // 在 f 里用 Scope::spawn 提交任务，返回时所有任务都已完成；任务 panic 时在这里重新 panic
+    pub fn scope<'scope, R>(&self, f: impl FnOnce(&Scope<'scope>) -> R) -> R {
+        let scope = Scope { jobs: RefCell::new(Vec::new()) };
+        let result = f(&scope);
+        self.run(scope.jobs.into_inner());
+        result
+    }
+
+    fn run(&self, jobs: Vec<Job<'_>>) {
+        let threads = self.threads.min(jobs.len().saturating_sub(1));
+        if threads == 0 {
+            jobs.into_iter().for_each(|job| job());
+            return;
+        }
+        let queue = Mutex::new(VecDeque::from(jobs));
+        let next = || queue.lock().unwrap().pop_front();
+        // 工作线程 panic 时 std::thread::scope 等其他线程结束后再 panic
+        std::thread::scope(|scope| {
+            for i in 0..threads {
+                std::thread::Builder::new()
+                    .name(format!("job-worker-{}", i))
+                    .spawn_scoped(scope, || {
+                        while let Some(job) = next() {
+                            job();
+                        }
+                    })
+                    .expect("无法创建工作线程");
+            }
+            while let Some(job) = next() {
+                job();
+            }
+        });
+    }
+}
+
+// 用 seed 作为这个线程的随机数种子执行任务，执行完换回原来的
+fn with_seed(seed: u64, job: impl FnOnce()) {
+    let previous = fastrand::get_seed();
+    fastrand::seed(seed);
+    job();
+    fastrand::seed(previous);
+}
+
+pub struct Scope<'scope> {
+    // 提交的任务，f 返回后一起执行
+    jobs: RefCell<Vec<Job<'scope>>>,
+}
+
+impl<'scope> Scope<'scope> {
+    pub fn spawn(&self, job: impl FnOnce() + Send + 'scope) {
+        let seed = fastrand::u64(..);
+        self.jobs.borrow_mut().push(Box::new(move || with_seed(seed, job)));
+    }
+}
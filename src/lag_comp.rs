@@ -0,0 +1,67 @@
+use std::collections::{HashMap, VecDeque};
+
+use glam::Vec3;
+
+use crate::collision::Aabb;
+use crate::snapshot::PlayerState;
+
+// 主机端的延迟补偿：记住每个玩家最近一段时间每个快照时的位置。验证客户端的射击时，
+// 把其他玩家退回到客户端开枪时看到的那个快照，延迟不太大的客户端瞄准了就能打中
+
+// 最多往回退这么多个快照（20 个约 1 秒），客户端报上来的更早的快照按最早的算
+const MAX_REWIND_TICKS: u32 = 20;
+// 快照里是眼睛的位置，命中判定和画身体时换成脚底
+pub const EYE_HEIGHT: f32 = 1.8;
+const HITBOX_HALF_WIDTH: f32 = 0.35;
+const HITBOX_HEIGHT: f32 = 1.75;
+
+// 站在这里的玩家的命中判定范围
+pub fn hitbox(eye: Vec3) -> Aabb {
+    let feet = eye - Vec3::Y * EYE_HEIGHT;
+    Aabb {
+        min: feet - Vec3::new(HITBOX_HALF_WIDTH, 0.0, HITBOX_HALF_WIDTH),
+        max: feet + Vec3::new(HITBOX_HALF_WIDTH, HITBOX_HEIGHT, HITBOX_HALF_WIDTH),
+    }
+}
+
+pub struct History {
+    // 每个玩家的 (快照 tick, 眼睛位置)，死亡时不记录
+    players: HashMap<u32, VecDeque<(u32, Vec3)>>,
+    latest_tick: Option<u32>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self { players: HashMap::new(), latest_tick: None }
+    }
+
+    // 每发一个快照记录一次，不在快照里的玩家（已经离开）一起删掉
+    pub fn record(&mut self, tick: u32, players: &[(u32, PlayerState)]) {
+        self.latest_tick = Some(tick);
+        self.players.retain(|player_id, _| players.iter().any(|(id, _)| id == player_id));
+        for (player_id, state) in players {
+            let samples = self.players.entry(*player_id).or_default();
+            if samples.len() > MAX_REWIND_TICKS as usize {
+                samples.pop_front();
+            }
+            if state.alive {
+                samples.push_back((tick, state.position()));
+            }
+        }
+    }
+
+    // tick 时刻除了 shooter 以外所有玩家的命中判定范围；None 表示客户端还没收到过快照，用最新的位置
+    pub fn rewind(&self, tick: Option<u32>, shooter: u32) -> Vec<(u32, Aabb)> {
+        let Some(latest) = self.latest_tick else { return Vec::new() };
+        let tick = tick.unwrap_or(latest).clamp(latest.saturating_sub(MAX_REWIND_TICKS), latest);
+        self.players
+            .iter()
+            .filter(|(player_id, _)| **player_id != shooter)
+            .filter_map(|(player_id, samples)| {
+                // 那个快照时还活着的玩家才能被打中
+                let (_, eye) = samples.iter().find(|(sample_tick, _)| *sample_tick == tick)?;
+                Some((*player_id, hitbox(*eye)))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,173 @@
+use crate::bindings::Layouts;
+use crate::car;
+use crate::ceiling;
+use crate::collision::{self, WallCollider};
+use crate::cutout::{self, CutoutSet};
+use crate::draw_list::{self, StaticBatch};
+use crate::floor_markings::FloorMarkings;
+use crate::map::{self, Map};
+use crate::model::{self, Model};
+use crate::texture;
+use crate::transparent::TransparentSet;
+
+// 一张地图生成的静态资源：模型、墙体碰撞器、玻璃和积水、铁丝网和格栅、地面标记，以及把它们合在一起的静态合批。
+// 换地图时先 unload 把旧地图的顶点和索引缓冲区全部释放，再生成新的，
+// 别的地方不保存这些资源的引用（静态合批是复制出来的），释放后显存统计里的 map 一类回到加载前
+
+// 玻璃隔断的厚度，与墙体碰撞器一致
+pub const GLASS_THICKNESS: f32 = 0.3;
+
+pub struct Level {
+    pub models: Vec<Model>,
+    pub wall_colliders: Vec<WallCollider>,
+    pub transparent: TransparentSet,
+    // 材质和管线一直保留，几何体随地图重新生成
+    pub cutouts: CutoutSet,
+    pub floor_markings: FloorMarkings,
+    // 没有加载地图时为 None
+    pub static_batch: Option<StaticBatch>,
+}
+
+impl Level {
+    // 还没有地图几何体，之后调用 load
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layouts: &Layouts,
+        pipeline_layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        Self {
+            models: Vec::new(),
+            wall_colliders: Vec::new(),
+            transparent: TransparentSet::new(Vec::new()),
+            cutouts: CutoutSet::new(device, queue, layouts, pipeline_layout, shader, format),
+            floor_markings: FloorMarkings::new(device, queue, layouts),
+            static_batch: None,
+        }
+    }
+
+    // 卸载当前地图，再根据 map 重新生成
+    pub fn load(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, map: &Map, dog_texture: &texture::Texture) {
+        self.unload();
+        let (models, wall_colliders) = build(device, map, dog_texture);
+        self.models = models;
+        self.wall_colliders = wall_colliders;
+        self.transparent = TransparentSet::from_map(device, map, GLASS_THICKNESS);
+        self.cutouts.load(device, map);
+        self.floor_markings.load(device, map);
+        self.static_batch = Some(build_static_batch(device, queue, &self.models, &self.cutouts, &self.floor_markings));
+    }
+
+    // 释放地图的几何体和碰撞器。Vec 的容量也一起释放，旧地图很大时不会一直占着内存
+    pub fn unload(&mut self) {
+        self.static_batch = None;
+        self.models = Vec::new();
+        self.wall_colliders = Vec::new();
+        self.transparent = TransparentSet::new(Vec::new());
+        self.cutouts.unload();
+        self.floor_markings.unload();
+    }
+}
+
+// 根据地图生成模型和墙体碰撞器（玻璃隔断、铁丝网、停着的车和柱子也挡路、挡子弹）
+fn build(device: &wgpu::Device, map: &Map, dog_texture: &texture::Texture) -> (Vec<Model>, Vec<WallCollider>) {
+    let mut models = model::create_parking_garage(device, map, dog_texture);
+    let (cars, car_colliders) = car::build(device, map);
+    models.extend(cars);
+    let (ceiling_props, ceiling_colliders) = ceiling::build(device, map);
+    models.extend(ceiling_props);
+    let wall_colliders = map
+        .wall_segments()
+        .iter()
+        .chain(map.glass_panes().iter())
+        .map(|segment| collision::create_wall_collider(segment.start, segment.end, map::WALL_HEIGHT))
+        .chain(
+            map.fence_segments()
+                .iter()
+                .map(|segment| WallCollider::new(segment.start, segment.end, map::WALL_HEIGHT, cutout::FENCE_THICKNESS)),
+        )
+        .chain(car_colliders)
+        .chain(ceiling_colliders)
+        .chain(
+            map.pillar_cells()
+                .into_iter()
+                .map(|(col, row)| WallCollider::square(map.cell_center(col, row), model::PILLAR_RADIUS, map::WALL_HEIGHT)),
+        )
+        .collect();
+    (models, wall_colliders)
+}
+
+// 地图的模型和镂空几何体合并成一批
+fn build_static_batch(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    models: &[Model],
+    cutouts: &CutoutSet,
+    floor_markings: &FloorMarkings,
+) -> StaticBatch {
+    let meshes = models
+        .iter()
+        .map(|model| (draw_list::Pipeline::Scene, draw_list::Material::Level, model))
+        .chain(cutouts.meshes())
+        .chain(floor_markings.mesh())
+        .collect();
+    StaticBatch::new(device, queue, meshes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::World;
+    use crate::enemy::EnemyManager;
+    use crate::gpu_memory::{self, Category};
+
+    fn map_bytes() -> u64 {
+        gpu_memory::snapshot().into_iter().find(|(category, _, _)| *category == Category::Map).map(|(_, _, bytes)| bytes).unwrap_or(0)
+    }
+
+    // 反复加载同一张地图 100 次：显存统计里的 map 一类每次都和第一次一样，卸载后回到加载前，
+    // ECS 的实体编号被重复使用，不会越来越多
+    #[test]
+    fn reloading_does_not_leak() {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let Some(adapter) = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default())) else {
+            eprintln!("没有可用的显卡，跳过");
+            return;
+        };
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None)).expect("无法创建设备");
+        let format = wgpu::TextureFormat::Rgba8UnormSrgb;
+        let dog_texture = texture::Texture::from_bytes(&device, &queue, include_bytes!("../dog.png"), "dog").expect("无法加载纹理");
+        let layouts = Layouts::new(&device);
+        let pipeline_layout = layouts.pipeline_layout(&device);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+        });
+        let map = Map::load(map::DEFAULT_MAP).expect("无法加载地图");
+
+        let mut level = Level::new(&device, &queue, &layouts, &pipeline_layout, &shader, format);
+        let mut world = World::new();
+        let baseline = map_bytes();
+        level.load(&device, &queue, &map, &dog_texture);
+        EnemyManager::new(&mut world, &map, false);
+        let loaded = map_bytes();
+        let entities = world.capacity();
+        assert!(loaded > baseline);
+
+        for _ in 0..100 {
+            level.load(&device, &queue, &map, &dog_texture);
+            world.unload();
+            EnemyManager::new(&mut world, &map, false);
+            assert_eq!(map_bytes(), loaded);
+            assert_eq!(world.capacity(), entities);
+        }
+
+        level.unload();
+        world.unload();
+        assert_eq!(map_bytes(), baseline);
+        assert!(level.models.is_empty() && level.wall_colliders.is_empty());
+        assert_eq!(world.transforms.iter().count(), 0);
+    }
+}
@@ -0,0 +1,78 @@
+// 光源分簇：每个线程一个簇，求出簇在视图空间的包围盒，记下和它相交的光源
+
+struct PointLight {
+    position: vec3<f32>,
+    radius: f32,
+    color: vec3<f32>,
+    intensity: f32,
+};
+
+struct Lighting {
+    forward: vec4<f32>,
+    // x 近裁剪面 y ln(远/近) zw 画面尺寸
+    clusters: vec4<f32>,
+    // 簇的数量和每簇在列表里占的长度
+    grid: vec4<u32>,
+    ambient: f32,
+    light_count: u32,
+    clustered: u32,
+    baked: u32,
+    lightmap_min: vec4<f32>,
+    lightmap_size: vec4<f32>,
+};
+
+struct CullUniform {
+    view: mat4x4<f32>,
+    lighting: Lighting,
+    // 深度为 1 处画面右上角在视图空间的 x、y
+    projection: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> cull: CullUniform;
+@group(0) @binding(1)
+var<storage, read> lights: array<PointLight>;
+@group(0) @binding(2)
+var<storage, read_write> clusters: array<u32>;
+
+// 第 slice 层的起点深度，深度层按指数划分
+fn slice_depth(slice: u32) -> f32 {
+    let lighting = cull.lighting;
+    return lighting.clusters.x * exp(f32(slice) / f32(lighting.grid.z) * lighting.clusters.y);
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let grid = cull.lighting.grid;
+    let index = id.x;
+    if (index >= grid.x * grid.y * grid.z) {
+        return;
+    }
+    let x = index % grid.x;
+    let y = (index / grid.x) % grid.y;
+    let z = index / (grid.x * grid.y);
+
+    // 屏幕上的一块在 NDC 里的范围，第 0 行在画面顶端
+    let ndc_min = vec2<f32>(f32(x) / f32(grid.x) * 2.0 - 1.0, 1.0 - f32(y + 1u) / f32(grid.y) * 2.0);
+    let ndc_max = vec2<f32>(f32(x + 1u) / f32(grid.x) * 2.0 - 1.0, 1.0 - f32(y) / f32(grid.y) * 2.0);
+    let near = slice_depth(z);
+    let far = slice_depth(z + 1u);
+    // 视图空间里相机看向 -z，x、y 随深度线性变大
+    let corner_min = ndc_min * cull.projection.xy;
+    let corner_max = ndc_max * cull.projection.xy;
+    let box_min = vec3<f32>(min(corner_min * near, corner_min * far), -far);
+    let box_max = vec3<f32>(max(corner_max * near, corner_max * far), -near);
+
+    let base = index * grid.w;
+    var count = 0u;
+    for (var i = 0u; i < cull.lighting.light_count; i = i + 1u) {
+        let light = lights[i];
+        let center = (cull.view * vec4<f32>(light.position, 1.0)).xyz;
+        let offset = clamp(center, box_min, box_max) - center;
+        if (dot(offset, offset) <= light.radius * light.radius && count + 1u < grid.w) {
+            clusters[base + 1u + count] = i;
+            count = count + 1u;
+        }
+    }
+    clusters[base] = count;
+}
@@ -0,0 +1,77 @@
+use glam::{Mat4, Quat, Vec3};
+
+use crate::ceiling;
+use crate::collision::{self, Aabb, WallCollider};
+use crate::map::Map;
+use crate::model::BoxInstance;
+
+// 天花板上的日光灯可以打坏：子弹打中灯罩或灯管、手雷在附近爆炸时灯管碎掉、冒出电火花，
+// 对应的点光源从光源缓冲区里去掉，那一片暗下来，玩家站在那里时敌人看得没那么远（见 stealth.rs）。
+// 编号和 map.light_cells()、lighting::map_lights 一致；换地图时重新生成，灯都是好的
+
+// 打坏的灯管换成烧黑的颜色
+const BROKEN_TUBE_COLOR: [f32; 3] = [0.08, 0.08, 0.07];
+// 手雷炸坏灯的范围是爆炸范围的多少
+const BLAST_REACH: f32 = 0.75;
+
+struct Fixture {
+    // 灯管的中心
+    position: Vec3,
+    bounds: Aabb,
+    broken: bool,
+}
+
+pub struct LightFixtures {
+    fixtures: Vec<Fixture>,
+}
+
+impl LightFixtures {
+    pub fn new(map: &Map) -> Self {
+        let fixtures = map
+            .light_cells()
+            .into_iter()
+            .map(|(col, row)| {
+                let center = map.cell_center(col, row);
+                Fixture { position: ceiling::tube_center(center), bounds: ceiling::fixture_bounds(center), broken: false }
+            })
+            .collect();
+        Self { fixtures }
+    }
+
+    // 射线最先打中的完好的灯和距离
+    pub fn raycast(&self, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<(usize, f32)> {
+        self.fixtures
+            .iter()
+            .enumerate()
+            .filter(|(_, fixture)| !fixture.broken)
+            .filter_map(|(index, fixture)| fixture.bounds.raycast(origin, dir, max_distance).map(|distance| (index, distance)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    // 爆炸范围里、中间没有墙挡着的完好的灯
+    pub fn in_blast(&self, colliders: &[WallCollider], position: Vec3, radius: f32) -> Vec<usize> {
+        self.fixtures
+            .iter()
+            .enumerate()
+            .filter(|(_, fixture)| !fixture.broken && fixture.position.distance(position) <= radius * BLAST_REACH)
+            .filter(|(_, fixture)| collision::count_occluders(colliders, position, fixture.position) == 0)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    // 打坏一盏灯，返回灯管的位置（放火花和声音）；已经坏了时为 None
+    pub fn break_fixture(&mut self, index: usize) -> Option<Vec3> {
+        let fixture = self.fixtures.get_mut(index).filter(|fixture| !fixture.broken)?;
+        fixture.broken = true;
+        println!("打坏了一盏灯 ({:.1}, {:.1})", fixture.position.x, fixture.position.z);
+        Some(fixture.position)
+    }
+
+    // 坏掉的灯管套一层烧黑的外壳，盖住天花板模型里发亮的灯管
+    pub fn boxes(&self, out: &mut Vec<BoxInstance>) {
+        let size = Vec3::new(ceiling::TUBE_HALF_LENGTH * 2.0 + 0.01, ceiling::TUBE_RADIUS * 2.0 + 0.01, ceiling::TUBE_RADIUS * 2.0 + 0.01);
+        for fixture in self.fixtures.iter().filter(|fixture| fixture.broken) {
+            out.push(BoxInstance { transform: Mat4::from_scale_rotation_translation(size, Quat::IDENTITY, fixture.position), color: BROKEN_TUBE_COLOR });
+        }
+    }
+}
@@ -0,0 +1,395 @@
+
+use crate::bindings::Lights;
+use crate::camera::Camera;
+use crate::gpu_memory;
+use crate::lightmap::{self, Lightmap};
+use crate::map::{self, Map};
+use crate::settings::LightingPath;
+use crate::upload::FrameUploader;
+
+// 多光源光照：天花板灯位上的日光灯都是点光源，片段着色器里按 环境光 + 各光源的漫反射 给颜色加明暗。
+// 两条路径由设置选择：forward 每个像素遍历全部光源；clustered 先用计算着色器把视锥按屏幕 16×9 块、
+// 深度 24 层（按指数分层，近处更细）切成簇，每簇记下和它相交的光源，像素只遍历自己所在簇的列表。
+// 光源和簇列表放在每帧的组里（第 0 组的 4、5 号绑定），反射探针拍摄时没有簇，总是走 forward。
+// 地图旁边有烘焙好的光照贴图（--bake-lighting，见 lightmap.rs）时改用贴图（6、7 号绑定），不再逐像素算光源。
+// 灯可以打坏（见 light_fixtures.rs），打坏的灯强度为 0；烘焙过的地图打坏第一盏灯时改回逐像素计算剩下的灯。
+// 警报响着时（见 alarm.rs）所有灯的颜色往红色闪
+
+const MAX_LIGHTS: usize = 256;
+// 簇的数量和每簇最多的光源数；簇列表里每簇占 1 + MAX_LIGHTS_PER_CLUSTER 个 u32，第一个是数量
+const CLUSTER_GRID: [u32; 3] = [16, 9, 24];
+const MAX_LIGHTS_PER_CLUSTER: u32 = 63;
+const WORKGROUP_SIZE: u32 = 64;
+
+// 日光灯管的高度（ceiling.rs 里灯罩的下面）和颜色
+const LIGHT_HEIGHT: f32 = 3.2;
+const LIGHT_COLOR: [f32; 3] = [0.92, 0.97, 1.0];
+const LIGHT_INTENSITY: f32 = 0.6;
+// 光源的影响半径是灯间距的倍数，灯越密越小
+const LIGHT_RADIUS_SCALE: f32 = 1.5;
+// 警报响着时天花板灯闪的红光
+const EMERGENCY_COLOR: [f32; 3] = [1.0, 0.08, 0.04];
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PointLight {
+    pub position: [f32; 3],
+    pub radius: f32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+}
+
+unsafe impl bytemuck::Pod for PointLight {}
+unsafe impl bytemuck::Zeroable for PointLight {}
+
+// 每帧组里的光照参数，放在 FrameUniform 的末尾
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LightingUniform {
+    // 视线方向（求像素的深度，选深度层）
+    forward: [f32; 4],
+    // x 近裁剪面 y ln(远/近) zw 画面尺寸（像素）
+    clusters: [f32; 4],
+    // 簇的数量（xyz）和每簇在列表里占的长度
+    grid: [u32; 4],
+    ambient: f32,
+    light_count: u32,
+    clustered: u32,
+    // 是否使用光照贴图
+    baked: u32,
+    // 光照贴图覆盖的范围：最小角（w 为亮度的量程）和尺寸
+    lightmap_min: [f32; 4],
+    lightmap_size: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for LightingUniform {}
+unsafe impl bytemuck::Zeroable for LightingUniform {}
+
+impl LightingUniform {
+    // 不打光：环境光为 1，没有光源
+    pub fn unlit() -> Self {
+        Self::forward(1.0, 0)
+    }
+
+    fn forward(ambient: f32, light_count: u32) -> Self {
+        Self {
+            forward: [0.0, 0.0, -1.0, 0.0],
+            clusters: [1.0, 1.0, 1.0, 1.0],
+            grid: [CLUSTER_GRID[0], CLUSTER_GRID[1], CLUSTER_GRID[2], MAX_LIGHTS_PER_CLUSTER + 1],
+            ambient,
+            light_count,
+            clustered: 0,
+            baked: 0,
+            lightmap_min: [0.0; 4],
+            lightmap_size: [1.0; 4],
+        }
+    }
+}
+
+// 分簇的计算着色器的参数
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct CullUniform {
+    view: [[f32; 4]; 4],
+    lighting: LightingUniform,
+    // 深度为 1 处画面右上角在视图空间的 x、y（tan(半视角) × 宽高比，tan(半视角)）
+    projection: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for CullUniform {}
+unsafe impl bytemuck::Zeroable for CullUniform {}
+
+// 地图的点光源：ambient 没有设置的地图不打光
+pub fn map_lights(map: &Map) -> Vec<PointLight> {
+    if map.ambient.is_none() {
+        return Vec::new();
+    }
+    let radius = map.light_spacing as f32 * map::CELL_SIZE * LIGHT_RADIUS_SCALE;
+    let mut lights: Vec<PointLight> = map
+        .light_cells()
+        .into_iter()
+        .map(|(col, row)| {
+            let center = map.cell_center(col, row);
+            PointLight { position: [center.x, LIGHT_HEIGHT, center.z], radius, color: LIGHT_COLOR, intensity: LIGHT_INTENSITY }
+        })
+        .collect();
+    if lights.len() > MAX_LIGHTS {
+        eprintln!("地图 {} 有 {} 盏灯，只使用前 {} 盏", map.name, lights.len(), MAX_LIGHTS);
+        lights.truncate(MAX_LIGHTS);
+    }
+    lights
+}
+
+pub struct Lighting {
+    light_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    cluster_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    cull_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    cull_pipeline: Option<(wgpu::ComputePipeline, wgpu::BindGroup)>,
+    // 当前地图的光照贴图，没有时是一张全黑的小贴图
+    lightmap_texture: gpu_memory::Tracked<wgpu::Texture>,
+    lightmap_view: wgpu::TextureView,
+    lightmap_sampler: wgpu::Sampler,
+    // 光照贴图的范围和量程
+    baked: Option<([f32; 4], [f32; 4])>,
+    ambient: f32,
+    light_count: u32,
+    // 当前地图的光源，编号和 map.light_cells() 一致
+    lights: Vec<PointLight>,
+    // 缓冲区里的灯现在有多红（警报），0 为原来的颜色
+    emergency: f32,
+}
+
+impl Lighting {
+    // 不支持计算着色器时没有分簇的管线，总是走 forward
+    pub fn new(device: &wgpu::Device, compute_supported: bool) -> Self {
+        let light_buffer = gpu_memory::buffer(device, gpu_memory::Category::Lighting, &wgpu::BufferDescriptor {
+            label: Some("Light Buffer"),
+            size: (MAX_LIGHTS * std::mem::size_of::<PointLight>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let cluster_count = CLUSTER_GRID.iter().product::<u32>();
+        let cluster_buffer = gpu_memory::buffer(device, gpu_memory::Category::Lighting, &wgpu::BufferDescriptor {
+            label: Some("Light Cluster Buffer"),
+            size: (cluster_count * (MAX_LIGHTS_PER_CLUSTER + 1) * 4) as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let cull_buffer = gpu_memory::buffer_init(device, gpu_memory::Category::Lighting, &wgpu::util::BufferInitDescriptor {
+            label: Some("Light Cull Params"),
+            contents: bytemuck::cast_slice(&[<CullUniform as bytemuck::Zeroable>::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let cull_pipeline = compute_supported.then(|| create_cull_pipeline(device, &light_buffer, &cluster_buffer, &cull_buffer));
+        let lightmap_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let lightmap_texture = black_lightmap(device);
+        Self {
+            light_buffer,
+            cluster_buffer,
+            cull_buffer,
+            cull_pipeline,
+            lightmap_view: lightmap_texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            lightmap_texture,
+            lightmap_sampler,
+            baked: None,
+            ambient: 1.0,
+            light_count: 0,
+            lights: Vec::new(),
+            emergency: 0.0,
+        }
+    }
+
+    // 每帧组里的光源、簇列表和光照贴图。换地图后光照贴图会换，每帧的绑定组要重新创建
+    pub fn resources(&self) -> Lights<'_> {
+        Lights {
+            lights: &self.light_buffer,
+            clusters: &self.cluster_buffer,
+            lightmap: &self.lightmap_view,
+            lightmap_sampler: &self.lightmap_sampler,
+        }
+    }
+
+    // 加载地图时调用，在拍摄反射探针和创建每帧的绑定组之前
+    pub fn load(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, map: &Map) {
+        let lights = map_lights(map);
+        self.ambient = map.ambient.unwrap_or(1.0);
+        self.baked = None;
+        self.light_count = 0;
+        self.lights = lights.clone();
+        self.emergency = 0.0;
+        if lights.is_empty() {
+            self.lightmap_texture = black_lightmap(device);
+            self.lightmap_view = self.lightmap_texture.create_view(&wgpu::TextureViewDescriptor::default());
+            return;
+        }
+        match Lightmap::load(map) {
+            // 烘焙过的地图不再逐像素计算这些光源
+            Some(baked) => {
+                let texture = create_lightmap_texture(device, baked.dims);
+                queue.write_texture(
+                    wgpu::ImageCopyTexture { aspect: wgpu::TextureAspect::All, texture: &texture, mip_level: 0, origin: wgpu::Origin3d::ZERO },
+                    &baked.data,
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(baked.dims[0] * 4),
+                        rows_per_image: Some(baked.dims[1] * 6),
+                    },
+                    texture.size(),
+                );
+                self.lightmap_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                self.lightmap_texture = texture;
+                self.baked = Some((baked.min.extend(baked.range).to_array(), baked.size.extend(0.0).to_array()));
+                println!("光照贴图: {} ({}x{}x{})，环境光 {}", lightmap::path(&map.name).display(), baked.dims[0], baked.dims[1], baked.dims[2], self.ambient);
+            }
+            None => {
+                queue.write_buffer(&self.light_buffer, 0, bytemuck::cast_slice(&lights));
+                self.light_count = lights.len() as u32;
+                self.lightmap_texture = black_lightmap(device);
+            self.lightmap_view = self.lightmap_texture.create_view(&wgpu::TextureViewDescriptor::default());
+                println!("光源: {} 盏，环境光 {}", self.light_count, self.ambient);
+            }
+        }
+    }
+
+    // 当前地图的光源，打坏的强度为 0；不打光的地图为空
+    pub fn lights(&self) -> &[PointLight] {
+        &self.lights
+    }
+
+    // 关掉一盏灯（打坏了），之后的每帧都不再算它的光
+    pub fn break_light(&mut self, queue: &wgpu::Queue, index: usize) {
+        let Some(light) = self.lights.get_mut(index) else { return };
+        light.intensity = 0.0;
+        if self.baked.take().is_some() {
+            println!("灯被打坏，不再使用光照贴图，改为逐像素计算 {} 盏灯", self.lights.len());
+        }
+        self.light_count = self.lights.len() as u32;
+        self.write_lights(queue);
+    }
+
+    // 警报的红光：amount 为 0 时恢复原来的颜色。烘焙过光照贴图的地图不逐像素算灯，看不到红光
+    pub fn set_emergency(&mut self, queue: &wgpu::Queue, amount: f32) {
+        if amount == self.emergency || self.lights.is_empty() {
+            return;
+        }
+        self.emergency = amount;
+        self.write_lights(queue);
+    }
+
+    fn write_lights(&self, queue: &wgpu::Queue) {
+        let tinted: Vec<PointLight> = self
+            .lights
+            .iter()
+            .map(|light| PointLight { color: std::array::from_fn(|i| light.color[i] + (EMERGENCY_COLOR[i] - light.color[i]) * self.emergency), ..*light })
+            .collect();
+        queue.write_buffer(&self.light_buffer, 0, bytemuck::cast_slice(&tinted));
+    }
+
+    // 不分簇时的光照参数，带上光照贴图的范围
+    fn base_uniform(&self) -> LightingUniform {
+        let mut uniform = LightingUniform::forward(self.ambient, self.light_count);
+        if let Some((min, size)) = self.baked {
+            uniform.baked = 1;
+            uniform.lightmap_min = min;
+            uniform.lightmap_size = size;
+        }
+        uniform
+    }
+
+    // 反射探针拍摄时的光照参数（没有簇，遍历全部光源）
+    pub fn capture_uniform(&self) -> LightingUniform {
+        self.base_uniform()
+    }
+
+    // 每帧在场景渲染之前调用：clustered 时先在 encoder 里分簇，返回这一帧的光照参数
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        uploader: &mut FrameUploader,
+        camera: &Camera,
+        width: u32,
+        height: u32,
+        path: LightingPath,
+    ) -> LightingUniform {
+        let mut uniform = self.base_uniform();
+        let Some((pipeline, bind_group)) = &self.cull_pipeline else {
+            return uniform;
+        };
+        // 分簇按透视的视锥切分，正交相机遍历全部光源
+        let Some(fov) = camera.projection.fov() else { return uniform };
+        if path != LightingPath::Clustered || self.light_count == 0 {
+            return uniform;
+        }
+        uniform.forward = camera.forward().extend(0.0).to_array();
+        uniform.clusters = [camera.near, (camera.far / camera.near).ln(), width as f32, height as f32];
+        uniform.clustered = 1;
+        let tan_half = (fov / 2.0).tan();
+        let cull = CullUniform {
+            view: camera.calc_view().to_cols_array_2d(),
+            lighting: uniform,
+            projection: [tan_half * width as f32 / height.max(1) as f32, tan_half, 0.0, 0.0],
+        };
+        uploader.write(queue, encoder, &self.cull_buffer, 0, &[cull]);
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Light Cull Pass") });
+        compute_pass.set_pipeline(pipeline);
+        compute_pass.set_bind_group(0, bind_group, &[]);
+        compute_pass.dispatch_workgroups(CLUSTER_GRID.iter().product::<u32>().div_ceil(WORKGROUP_SIZE), 1, 1);
+        uniform
+    }
+}
+
+// 光照贴图的 3D 纹理：六个方向的格子沿 y 叠在一起，见 lightmap.rs
+fn create_lightmap_texture(device: &wgpu::Device, dims: [u32; 3]) -> gpu_memory::Tracked<wgpu::Texture> {
+    gpu_memory::texture(device, gpu_memory::Category::Lighting, &wgpu::TextureDescriptor {
+        label: Some("lightmap"),
+        size: wgpu::Extent3d { width: dims[0], height: dims[1] * 6, depth_or_array_layers: dims[2] },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D3,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+// 没有光照贴图时绑定的占位（纹理创建时内容为 0）
+fn black_lightmap(device: &wgpu::Device) -> gpu_memory::Tracked<wgpu::Texture> {
+    create_lightmap_texture(device, [1, 1, 1])
+}
+
+fn create_cull_pipeline(
+    device: &wgpu::Device,
+    light_buffer: &wgpu::Buffer,
+    cluster_buffer: &wgpu::Buffer,
+    cull_buffer: &wgpu::Buffer,
+) -> (wgpu::ComputePipeline, wgpu::BindGroup) {
+    let buffer_entry = |binding: u32, ty: wgpu::BufferBindingType| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer { ty, has_dynamic_offset: false, min_binding_size: None },
+        count: None,
+    };
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[
+            buffer_entry(0, wgpu::BufferBindingType::Uniform),
+            buffer_entry(1, wgpu::BufferBindingType::Storage { read_only: true }),
+            buffer_entry(2, wgpu::BufferBindingType::Storage { read_only: false }),
+        ],
+        label: Some("light_cull_bind_group_layout"),
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &layout,
+        entries: &[
+            wgpu::BindGroupEntry { binding: 0, resource: cull_buffer.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 1, resource: light_buffer.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 2, resource: cluster_buffer.as_entire_binding() },
+        ],
+        label: Some("light_cull_bind_group"),
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Light Cull Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("light_cull.wgsl").into()),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Light Cull Pipeline Layout"),
+        bind_group_layouts: &[&layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Light Cull Pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: "cs_main",
+    });
+    (pipeline, bind_group)
+}
+
@@ -0,0 +1,369 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use glam::Vec3;
+use instant::Instant;
+
+use crate::assets;
+use crate::lighting::{self, PointLight};
+use crate::map::{self, Cell, Map};
+
+// 光照贴图：用 --bake-lighting 离线烘焙地图里静态光源的直接光和一次反弹，存在地图旁边
+// （maps/<地图名>.lightmap），运行时作为 3D 纹理采样，不再逐像素计算这些光源。
+// 贴图是覆盖整张地图的格子：水平方向每个地图格子 RESOLUTION×RESOLUTION 格，竖直方向 LAYERS 层，
+// 每格存六个方向（+X -X +Y -Y +Z -Z）的辐照度，着色时按法向量各分量的平方混合。
+// 光线只和地图格子（墙、柱子和地图外算实心）以及地面、天花板求交，车和门这些会动的东西不参与
+
+const MAGIC: &[u8; 4] = b"LMAP";
+const VERSION: u32 = 1;
+// 每个地图格子水平方向的贴图格数，竖直方向的层数
+const RESOLUTION: usize = 2;
+const LAYERS: usize = 8;
+// 每格往四面八方发出的光线数（一次反弹）
+const SAMPLES: usize = 128;
+// 反弹光线最远的距离
+const MAX_BOUNCE_DISTANCE: f32 = 40.0;
+// 存成 8 位时的量程
+const RANGE: f32 = 2.0;
+// 反弹时各表面的反射率
+const FLOOR_ALBEDO: f32 = 0.45;
+const CEILING_ALBEDO: f32 = 0.6;
+const WALL_ALBEDO: f32 = 0.5;
+
+// 六个方向，顺序与纹理里叠放的顺序一致
+const AXES: [Vec3; 6] = [Vec3::X, Vec3::NEG_X, Vec3::Y, Vec3::NEG_Y, Vec3::Z, Vec3::NEG_Z];
+
+pub struct Lightmap {
+    // 贴图格子的数量（x、层数、z）
+    pub dims: [u32; 3],
+    // 覆盖的范围
+    pub min: Vec3,
+    pub size: Vec3,
+    pub range: f32,
+    // 烘焙时地图的指纹，地图改过以后不再使用
+    fingerprint: u64,
+    // RGBA8，x 最快，其次是 y（六个方向的层依次叠放），最后是 z
+    pub data: Vec<u8>,
+}
+
+pub fn path(map_name: &str) -> PathBuf {
+    Path::new("maps").join(format!("{}.lightmap", map_name))
+}
+
+// 地图格子和光源的指纹（FNV-1a）
+fn fingerprint(map: &Map, lights: &[PointLight]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes {
+            hash = (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    feed(&(map.width as u64).to_le_bytes());
+    feed(&(map.height as u64).to_le_bytes());
+    for row in 0..map.height {
+        for col in 0..map.width {
+            feed(format!("{:?}", map.cell(col, row)).as_bytes());
+        }
+    }
+    feed(bytemuck::cast_slice(lights));
+    hash
+}
+
+// 挡光的格子：墙、柱子和地图外。玻璃和铁丝网透光
+fn blocks_light(cell: Cell) -> bool {
+    matches!(cell, Cell::Wall | Cell::Void | Cell::Pillar)
+}
+
+// 和 shader.wgsl 的 point_light 相同的衰减，烘焙出来的亮度和实时计算的一致
+fn falloff(distance: f32, radius: f32) -> f32 {
+    let window = (1.0 - (distance / radius).powi(4)).clamp(0.0, 1.0);
+    window * window / (1.0 + distance * distance / 9.0)
+}
+
+struct Scene<'a> {
+    map: &'a Map,
+    lights: Vec<PointLight>,
+    // 地图的最小角（世界坐标）
+    origin: Vec3,
+}
+
+impl Scene<'_> {
+    fn solid(&self, col: i64, row: i64) -> bool {
+        if col < 0 || row < 0 || col >= self.map.width as i64 || row >= self.map.height as i64 {
+            return true;
+        }
+        blocks_light(self.map.cell(col as usize, row as usize))
+    }
+
+    // 光线碰到的第一个表面：距离和表面的法向量。水平方向在格子里逐格前进，竖直方向是地面和天花板
+    fn trace(&self, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<(f32, Vec3)> {
+        let plane = if dir.y < -1e-6 {
+            Some((-origin.y / dir.y, Vec3::Y))
+        } else if dir.y > 1e-6 {
+            Some(((map::WALL_HEIGHT - origin.y) / dir.y, Vec3::NEG_Y))
+        } else {
+            None
+        };
+        let limit = plane.map_or(max_distance, |(t, _)| t.min(max_distance));
+
+        let local = (origin - self.origin) / map::CELL_SIZE;
+        let position = [local.x, local.z];
+        let direction = [dir.x, dir.z];
+        let mut cell = [local.x.floor() as i64, local.z.floor() as i64];
+        let mut step = [0i64; 2];
+        // 到下一条格子边界的距离，以及每跨过一格增加的距离
+        let mut next = [f32::INFINITY; 2];
+        let mut delta = [f32::INFINITY; 2];
+        for axis in 0..2 {
+            if direction[axis] > 1e-6 {
+                step[axis] = 1;
+                next[axis] = ((cell[axis] + 1) as f32 - position[axis]) * map::CELL_SIZE / direction[axis];
+                delta[axis] = map::CELL_SIZE / direction[axis];
+            } else if direction[axis] < -1e-6 {
+                step[axis] = -1;
+                next[axis] = (position[axis] - cell[axis] as f32) * map::CELL_SIZE / -direction[axis];
+                delta[axis] = map::CELL_SIZE / -direction[axis];
+            }
+        }
+        loop {
+            let axis = if next[0] < next[1] { 0 } else { 1 };
+            let t = next[axis];
+            if t > limit {
+                break;
+            }
+            cell[axis] += step[axis];
+            if self.solid(cell[0], cell[1]) {
+                let normal = if axis == 0 { Vec3::X } else { Vec3::Z } * -step[axis] as f32;
+                return Some((t, normal));
+            }
+            next[axis] += delta[axis];
+        }
+        plane.filter(|(t, _)| *t <= max_distance)
+    }
+
+    // 各光源照到 position 的光：没有被挡住时返回方向（指向光源）和强度
+    fn visible_lights(&self, position: Vec3) -> impl Iterator<Item = (Vec3, Vec3)> + '_ {
+        self.lights.iter().filter_map(move |light| {
+            let to_light = Vec3::from(light.position) - position;
+            let distance = to_light.length();
+            if distance >= light.radius || distance < 1e-4 {
+                return None;
+            }
+            let dir = to_light / distance;
+            if self.trace(position, dir, distance).is_some() {
+                return None;
+            }
+            Some((dir, Vec3::from(light.color) * light.intensity * falloff(distance, light.radius)))
+        })
+    }
+
+    // 表面上一点的直接光照
+    fn direct(&self, position: Vec3, normal: Vec3) -> Vec3 {
+        self.visible_lights(position).map(|(dir, light)| light * normal.dot(dir).max(0.0)).sum()
+    }
+
+    // 一格六个方向的辐照度：直接光加上一次反弹
+    fn irradiance(&self, position: Vec3, rng: &mut fastrand::Rng) -> [Vec3; 6] {
+        let mut result = [Vec3::ZERO; 6];
+        for (dir, light) in self.visible_lights(position) {
+            for (value, axis) in result.iter_mut().zip(AXES) {
+                *value += light * axis.dot(dir).max(0.0);
+            }
+        }
+        // 均匀的球面方向；漫反射表面的辐射度是 反射率 × 辐照度 / π，对球面积分后系数是 4 / 光线数
+        for _ in 0..SAMPLES {
+            let z = rng.f32() * 2.0 - 1.0;
+            let angle = rng.f32() * std::f32::consts::TAU;
+            let r = (1.0 - z * z).sqrt();
+            let dir = Vec3::new(r * angle.cos(), z, r * angle.sin());
+            let Some((t, normal)) = self.trace(position, dir, MAX_BOUNCE_DISTANCE) else {
+                continue;
+            };
+            let albedo = if normal.y > 0.5 {
+                FLOOR_ALBEDO
+            } else if normal.y < -0.5 {
+                CEILING_ALBEDO
+            } else {
+                WALL_ALBEDO
+            };
+            let bounce = self.direct(position + dir * t + normal * 1e-3, normal) * albedo;
+            for (value, axis) in result.iter_mut().zip(AXES) {
+                *value += bounce * axis.dot(dir).max(0.0) * (4.0 / SAMPLES as f32);
+            }
+        }
+        result
+    }
+}
+
+impl Lightmap {
+    // 多线程烘焙：每个线程轮流领一排（同一个 z）贴图格子
+    pub fn bake(map: &Map) -> Lightmap {
+        let lights = lighting::map_lights(map);
+        let size = Vec3::new(map.width as f32 * map::CELL_SIZE, map::WALL_HEIGHT, map.height as f32 * map::CELL_SIZE);
+        let min = Vec3::new(-size.x / 2.0, 0.0, -size.z / 2.0);
+        let scene = Scene { map, lights, origin: min };
+        let (nx, ny, nz) = (map.width * RESOLUTION, LAYERS, map.height * RESOLUTION);
+        let cell = Vec3::new(size.x / nx as f32, size.y / ny as f32, size.z / nz as f32);
+
+        let next_row = AtomicUsize::new(0);
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let mut rows: Vec<(usize, Vec<Option<[Vec3; 6]>>)> = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = Vec::new();
+                        loop {
+                            let z = next_row.fetch_add(1, Ordering::Relaxed);
+                            if z >= nz {
+                                break done;
+                            }
+                            let mut rng = fastrand::Rng::with_seed(z as u64);
+                            let mut row = Vec::with_capacity(nx * ny);
+                            for y in 0..ny {
+                                for x in 0..nx {
+                                    let position = min + Vec3::new(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5) * cell;
+                                    let local = (position - min) / map::CELL_SIZE;
+                                    // 实心格子里的贴图格子之后用旁边的填上
+                                    row.push((!scene.solid(local.x as i64, local.z as i64)).then(|| scene.irradiance(position, &mut rng)));
+                                }
+                            }
+                            done.push((z, row));
+                            if (z + 1).is_multiple_of((nz / 10).max(1)) {
+                                println!("烘焙中: {}/{} 排", z + 1, nz);
+                            }
+                        }
+                    })
+                })
+                .collect();
+            workers.into_iter().flat_map(|worker| worker.join().expect("烘焙线程出错")).collect()
+        });
+        rows.sort_by_key(|(z, _)| *z);
+        let voxels: Vec<Option<[Vec3; 6]>> = rows.into_iter().flat_map(|(_, row)| row).collect();
+
+        // 实心格子取水平方向相邻的空格子的平均，表面附近插值时不会被拉暗
+        let index = |x: usize, y: usize, z: usize| (z * ny + y) * nx + x;
+        let mut data = vec![0u8; nx * ny * 6 * nz * 4];
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    let value = voxels[index(x, y, z)].or_else(|| {
+                        let neighbours: Vec<[Vec3; 6]> = [(x.wrapping_sub(1), z), (x + 1, z), (x, z.wrapping_sub(1)), (x, z + 1)]
+                            .into_iter()
+                            .filter(|(x, z)| *x < nx && *z < nz)
+                            .filter_map(|(x, z)| voxels[index(x, y, z)])
+                            .collect();
+                        (!neighbours.is_empty()).then(|| {
+                            let count = neighbours.len() as f32;
+                            std::array::from_fn(|face| neighbours.iter().map(|faces| faces[face]).sum::<Vec3>() / count)
+                        })
+                    });
+                    let Some(faces) = value else {
+                        continue;
+                    };
+                    for (face, irradiance) in faces.iter().enumerate() {
+                        let offset = ((z * ny * 6 + face * ny + y) * nx + x) * 4;
+                        let encoded = (*irradiance / RANGE).clamp(Vec3::ZERO, Vec3::ONE) * 255.0;
+                        data[offset..offset + 4].copy_from_slice(&[encoded.x.round() as u8, encoded.y.round() as u8, encoded.z.round() as u8, 255]);
+                    }
+                }
+            }
+        }
+
+        Lightmap {
+            dims: [nx as u32, ny as u32, nz as u32],
+            min,
+            size,
+            range: RANGE,
+            fingerprint: fingerprint(map, &scene.lights),
+            data,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut bytes = Vec::with_capacity(self.data.len() + 64);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        for value in self.dims {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in self.min.to_array().into_iter().chain(self.size.to_array()).chain([self.range]) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.fingerprint.to_le_bytes());
+        bytes.extend_from_slice(&self.data);
+        std::fs::write(path, bytes)
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Lightmap, String> {
+        let mut rest = bytes.strip_prefix(MAGIC).ok_or("not a lightmap")?;
+        let mut take = |count: usize| -> Result<&[u8], String> {
+            if rest.len() < count {
+                return Err("file is truncated".to_string());
+            }
+            let (head, tail) = rest.split_at(count);
+            rest = tail;
+            Ok(head)
+        };
+        let mut u32_value = || take(4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+        let version = u32_value()?;
+        if version != VERSION {
+            return Err(format!("unsupported version {}", version));
+        }
+        let dims = [u32_value()?, u32_value()?, u32_value()?];
+        let mut f32_value = || take(4).map(|b| f32::from_le_bytes(b.try_into().unwrap()));
+        let min = Vec3::new(f32_value()?, f32_value()?, f32_value()?);
+        let size = Vec3::new(f32_value()?, f32_value()?, f32_value()?);
+        let range = f32_value()?;
+        let fingerprint = u64::from_le_bytes(take(8)?.try_into().unwrap());
+        let expected = dims.iter().map(|d| *d as usize).product::<usize>() * 6 * 4;
+        let data = take(expected)?.to_vec();
+        Ok(Lightmap { dims, min, size, range, fingerprint, data })
+    }
+
+    // 读取地图旁边的光照贴图，没有或者和地图对不上时返回 None
+    pub fn load(map: &Map) -> Option<Lightmap> {
+        let path = path(&map.name);
+        let bytes = assets::read(&path).ok()?;
+        let lightmap = match Lightmap::parse(&bytes) {
+            Ok(lightmap) => lightmap,
+            Err(e) => {
+                eprintln!("光照贴图 {} 无法读取: {}", path.display(), e);
+                return None;
+            }
+        };
+        if lightmap.fingerprint != fingerprint(map, &lighting::map_lights(map)) {
+            eprintln!("光照贴图 {} 和地图对不上，需要重新烘焙 (--bake-lighting {})", path.display(), map.name);
+            return None;
+        }
+        Some(lightmap)
+    }
+}
+
+// --bake-lighting 模式：烘焙一张地图，存到地图旁边
+pub fn bake_command(map_name: &str) {
+    let map = match Map::load(map_name) {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("无法加载地图 {}: {}", map_name, e);
+            return;
+        }
+    };
+    if lighting::map_lights(&map).is_empty() {
+        eprintln!("地图 {} 没有光源（头部没有 ambient），不需要烘焙", map_name);
+        return;
+    }
+    let start = Instant::now();
+    let lightmap = Lightmap::bake(&map);
+    let path = path(map_name);
+    match lightmap.save(&path) {
+        Ok(()) => println!(
+            "光照贴图已保存: {} ({}x{}x{}，用时 {:.1} 秒)",
+            path.display(),
+            lightmap.dims[0],
+            lightmap.dims[1],
+            lightmap.dims[2],
+            start.elapsed().as_secs_f32()
+        ),
+        Err(e) => eprintln!("保存光照贴图失败 {}: {}", path.display(), e),
+    }
+}
@@ -0,0 +1,335 @@
+use crate::progression::{self, Perk, Progression};
+use crate::ui::{Nav, UiBatch};
+use crate::weapon::{self, WeaponSlot, WeaponStats};
+
+// 出生配置：主武器、副武器、手雷和一个天赋，几套预设保存在档案里。
+// 非波次模式死亡以后（联机时在观战里按跳跃键）打开选择界面，选好了再进场。
+// 联机时客户端把选的配置发给主机，主机按 server.toml 的 allowed_weapons 检查，换掉不允许的武器以后发回来
+
+const PANEL_COLOR: [f32; 4] = [0.05, 0.05, 0.1, 0.9];
+const HEADER_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const DIM_COLOR: [f32; 4] = [0.55, 0.55, 0.6, 1.0];
+const HIGHLIGHT_COLOR: [f32; 4] = [0.25, 0.35, 0.6, 0.9];
+const ERROR_COLOR: [f32; 4] = [1.0, 0.4, 0.3, 1.0];
+
+const PANEL_W: f32 = 600.0;
+const ROW_H: f32 = 34.0;
+const LIST_TOP: f32 = 64.0;
+const FOOTER_H: f32 = 70.0;
+const SCALE: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrenadeKind {
+    #[default]
+    Frag,
+    Incendiary,
+    Stun,
+}
+
+impl GrenadeKind {
+    const ALL: [GrenadeKind; 3] = [GrenadeKind::Frag, GrenadeKind::Incendiary, GrenadeKind::Stun];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GrenadeKind::Frag => "FRAG",
+            GrenadeKind::Incendiary => "INCENDIARY",
+            GrenadeKind::Stun => "STUN",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Loadout {
+    pub name: String,
+    // data/weapons.json 中的武器 id，主武器可以是主武器栏或者重武器栏的
+    pub primary: String,
+    pub secondary: String,
+    pub grenade: GrenadeKind,
+    // 这套配置带的天赋，在档案里的等级上再加一级
+    pub perk: Option<Perk>,
+}
+
+impl Loadout {
+    fn new(name: &str, primary: &str, secondary: &str, grenade: GrenadeKind) -> Self {
+        Self { name: name.to_string(), primary: primary.to_string(), secondary: secondary.to_string(), grenade, perk: None }
+    }
+
+    // 两把武器的属性，找不到的跳过
+    pub fn weapons(&self, weapons: &[WeaponStats]) -> Vec<WeaponStats> {
+        [&self.primary, &self.secondary].into_iter().filter_map(|id| weapon::find_weapon(weapons, id).cloned()).collect()
+    }
+
+    // 加上配置的天赋，只能带已经解锁的，最多到满级
+    pub fn boosted(&self, progression: &Progression) -> Progression {
+        let mut boosted = progression.clone();
+        if let Some(perk) = self.perk.filter(|perk| progression.rank(*perk) > 0) {
+            boosted.perks.insert(perk, (progression.rank(perk) + 1).min(progression::MAX_RANK));
+        }
+        boosted
+    }
+}
+
+fn is_primary(slot: WeaponSlot) -> bool {
+    matches!(slot, WeaponSlot::Primary | WeaponSlot::Heavy)
+}
+
+fn is_secondary(slot: WeaponSlot) -> bool {
+    slot == WeaponSlot::Secondary
+}
+
+// allowed 为空时所有武器都允许
+fn is_allowed(allowed: &[String], id: &str) -> bool {
+    allowed.is_empty() || allowed.iter().any(|allowed| allowed == id)
+}
+
+// 主机对客户端发来的配置的检查：不存在、栏位不对或者不允许的武器换成第一把能用的，都不能用时留空
+pub fn enforce(loadout: &Loadout, weapons: &[WeaponStats], allowed: &[String]) -> Loadout {
+    let pick = |id: &str, fits: fn(WeaponSlot) -> bool| {
+        let valid = |stats: &&WeaponStats| fits(stats.slot) && is_allowed(allowed, &stats.id);
+        match weapon::find_weapon(weapons, id).filter(valid) {
+            Some(stats) => stats.id.clone(),
+            None => weapons.iter().find(valid).map(|stats| stats.id.clone()).unwrap_or_default(),
+        }
+    };
+    Loadout {
+        name: loadout.name.clone(),
+        primary: pick(&loadout.primary, is_primary),
+        secondary: pick(&loadout.secondary, is_secondary),
+        grenade: loadout.grenade,
+        perk: loadout.perk,
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Loadouts {
+    pub presets: Vec<Loadout>,
+    // 当前选中的预设
+    pub selected: usize,
+}
+
+impl Default for Loadouts {
+    fn default() -> Self {
+        Self {
+            presets: vec![
+                Loadout::new("Assault", "rifle", "pistol", GrenadeKind::Frag),
+                Loadout::new("Breacher", "shotgun", "pistol", GrenadeKind::Stun),
+                Loadout::new("Engineer", "rifle", "nailgun", GrenadeKind::Incendiary),
+            ],
+            selected: 0,
+        }
+    }
+}
+
+impl Loadouts {
+    pub fn active(&self) -> Option<&Loadout> {
+        self.presets.get(self.selected)
+    }
+}
+
+// 界面上预设下面可以改的几项
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Primary,
+    Secondary,
+    Grenade,
+    Perk,
+}
+
+impl Field {
+    const ALL: [Field; 4] = [Field::Primary, Field::Secondary, Field::Grenade, Field::Perk];
+
+    fn label(self) -> &'static str {
+        match self {
+            Field::Primary => "PRIMARY",
+            Field::Secondary => "SECONDARY",
+            Field::Grenade => "GRENADE",
+            Field::Perk => "PERK",
+        }
+    }
+}
+
+// id 在 options 里的下一个，到头后回到第一个
+fn next_id(options: Vec<&str>, id: &str) -> String {
+    let next = match options.iter().position(|option| *option == id) {
+        Some(index) => options.get(index + 1).or(options.first()),
+        None => options.first(),
+    };
+    next.map_or_else(|| id.to_string(), |option| option.to_string())
+}
+
+// 在选择界面里做了什么
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoadoutChoice {
+    // 改了预设或者换了选中的预设，要保存档案
+    Changed,
+    Deploy,
+}
+
+pub struct LoadoutScreen {
+    pub open: bool,
+    // 预设、可以改的几项，最后一行是 DEPLOY
+    selected: usize,
+    message: Option<(String, bool)>,
+}
+
+impl LoadoutScreen {
+    pub fn new() -> Self {
+        Self { open: false, selected: 0, message: None }
+    }
+
+    // 打开时选中当前的预设
+    pub fn show(&mut self, loadouts: &Loadouts) {
+        self.open = true;
+        self.selected = loadouts.selected;
+        self.message = None;
+    }
+
+    pub fn set_message(&mut self, message: &str, error: bool) {
+        self.message = Some((message.to_string(), error));
+    }
+
+    fn rows(loadouts: &Loadouts) -> usize {
+        loadouts.presets.len() + Field::ALL.len() + 1
+    }
+
+    fn field(loadouts: &Loadouts, row: usize) -> Option<Field> {
+        row.checked_sub(loadouts.presets.len()).and_then(|index| Field::ALL.get(index).copied())
+    }
+
+    // 执行某一行：预设行选中这个预设，字段行换成下一个选项
+    fn choose(&self, row: usize, loadouts: &mut Loadouts, weapons: &[WeaponStats], progression: &Progression) -> Option<LoadoutChoice> {
+        if row < loadouts.presets.len() {
+            loadouts.selected = row;
+            return Some(LoadoutChoice::Changed);
+        }
+        let Some(field) = Self::field(loadouts, row) else {
+            return (row == Self::rows(loadouts) - 1).then_some(LoadoutChoice::Deploy);
+        };
+        let loadout = loadouts.presets.get_mut(loadouts.selected)?;
+        let ids = |fits: fn(WeaponSlot) -> bool| weapons.iter().filter(|stats| fits(stats.slot)).map(|stats| stats.id.as_str()).collect();
+        match field {
+            Field::Primary => loadout.primary = next_id(ids(is_primary), &loadout.primary),
+            Field::Secondary => loadout.secondary = next_id(ids(is_secondary), &loadout.secondary),
+            Field::Grenade => {
+                let index = GrenadeKind::ALL.iter().position(|kind| *kind == loadout.grenade).unwrap_or(0);
+                loadout.grenade = GrenadeKind::ALL[(index + 1) % GrenadeKind::ALL.len()];
+            }
+            // 没有天赋，然后依次是解锁了的天赋
+            Field::Perk => {
+                let unlocked: Vec<Perk> = Perk::ALL.into_iter().filter(|perk| progression.rank(*perk) > 0).collect();
+                let index = loadout.perk.and_then(|perk| unlocked.iter().position(|unlocked| *unlocked == perk));
+                loadout.perk = match index {
+                    Some(index) => unlocked.get(index + 1).copied(),
+                    None => unlocked.first().copied(),
+                };
+            }
+        }
+        Some(LoadoutChoice::Changed)
+    }
+
+    pub fn navigate(&mut self, nav: Nav, loadouts: &mut Loadouts, weapons: &[WeaponStats], progression: &Progression) -> Option<LoadoutChoice> {
+        let count = Self::rows(loadouts);
+        match nav {
+            Nav::Up => self.selected = (self.selected + count - 1) % count,
+            Nav::Down => self.selected = (self.selected + 1) % count,
+            Nav::Select => return self.choose(self.selected, loadouts, weapons, progression),
+            Nav::Back => self.open = false,
+        }
+        None
+    }
+
+    fn panel_rect(loadouts: &Loadouts, width: f32, height: f32) -> (f32, f32, f32, f32) {
+        let panel_w = PANEL_W.min(width - 20.0);
+        let panel_h = LIST_TOP + ROW_H * Self::rows(loadouts) as f32 + FOOTER_H;
+        ((width - panel_w) / 2.0, ((height - panel_h) / 2.0).max(10.0), panel_w, panel_h)
+    }
+
+    fn row_at(loadouts: &Loadouts, x: f32, y: f32, width: f32, height: f32) -> Option<usize> {
+        let (panel_x, panel_y, panel_w, _) = Self::panel_rect(loadouts, width, height);
+        let top = panel_y + LIST_TOP;
+        if x < panel_x || x > panel_x + panel_w || y < top {
+            return None;
+        }
+        let row = ((y - top) / ROW_H) as usize;
+        (row < Self::rows(loadouts)).then_some(row)
+    }
+
+    pub fn hover(&mut self, loadouts: &Loadouts, x: f32, y: f32, width: f32, height: f32) {
+        if let Some(row) = Self::row_at(loadouts, x, y, width, height) {
+            self.selected = row;
+        }
+    }
+
+    // 点到某一行时选中并执行它，点到面板外面关闭
+    #[allow(clippy::too_many_arguments)]
+    pub fn click(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        loadouts: &mut Loadouts,
+        weapons: &[WeaponStats],
+        progression: &Progression,
+    ) -> Option<LoadoutChoice> {
+        let (panel_x, panel_y, panel_w, panel_h) = Self::panel_rect(loadouts, width, height);
+        if x < panel_x || x > panel_x + panel_w || y < panel_y || y > panel_y + panel_h {
+            self.open = false;
+            return None;
+        }
+        let row = Self::row_at(loadouts, x, y, width, height)?;
+        self.selected = row;
+        self.choose(row, loadouts, weapons, progression)
+    }
+
+    // respawn_in 是还要等多久才能进场
+    pub fn draw(&self, ui: &mut UiBatch, loadouts: &Loadouts, weapons: &[WeaponStats], respawn_in: Option<f32>) {
+        let (panel_x, panel_y, panel_w, panel_h) = Self::panel_rect(loadouts, ui.width(), ui.height());
+        ui.rect(0.0, 0.0, ui.width(), ui.height(), [0.0, 0.0, 0.0, 0.4]);
+        ui.rect(panel_x, panel_y, panel_w, panel_h, PANEL_COLOR);
+        ui.text_centered(ui.width() / 2.0, panel_y + 16.0, 3.0, TEXT_COLOR, "LOADOUT");
+
+        let weapon_name = |id: &str| weapon::find_weapon(weapons, id).map_or_else(|| "NONE".to_string(), |stats| stats.name.to_uppercase());
+        let active = loadouts.active();
+        let text_y = (ROW_H - UiBatch::line_height(SCALE)) / 2.0 + 3.0;
+        let right = panel_x + panel_w - 24.0;
+        for row in 0..Self::rows(loadouts) {
+            let y = panel_y + LIST_TOP + row as f32 * ROW_H;
+            if row == self.selected {
+                ui.rect(panel_x + 12.0, y, panel_w - 24.0, ROW_H, HIGHLIGHT_COLOR);
+            }
+            if let Some(preset) = loadouts.presets.get(row) {
+                let current = row == loadouts.selected;
+                ui.text(panel_x + 24.0, y + text_y, SCALE, if current { HEADER_COLOR } else { TEXT_COLOR }, &preset.name.to_uppercase());
+                if current {
+                    ui.text(right - UiBatch::text_width("SELECTED", SCALE), y + text_y, SCALE, DIM_COLOR, "SELECTED");
+                }
+            } else if let Some(field) = Self::field(loadouts, row) {
+                let value = match (field, active) {
+                    (_, None) => String::new(),
+                    (Field::Primary, Some(loadout)) => weapon_name(&loadout.primary),
+                    (Field::Secondary, Some(loadout)) => weapon_name(&loadout.secondary),
+                    (Field::Grenade, Some(loadout)) => loadout.grenade.name().to_string(),
+                    (Field::Perk, Some(loadout)) => loadout.perk.map_or("NONE", Perk::name).to_string(),
+                };
+                ui.text(panel_x + 48.0, y + text_y, SCALE, DIM_COLOR, field.label());
+                ui.text(right - UiBatch::text_width(&value, SCALE), y + text_y, SCALE, TEXT_COLOR, &value);
+            } else {
+                let deploy = match respawn_in.filter(|seconds| *seconds > 0.0) {
+                    Some(seconds) => format!("DEPLOY IN {}", seconds.ceil() as u32),
+                    None => "DEPLOY".to_string(),
+                };
+                ui.text(panel_x + 24.0, y + text_y, SCALE, HEADER_COLOR, &deploy);
+            }
+        }
+
+        let footer_y = panel_y + panel_h - FOOTER_H + 12.0;
+        if let Some((message, error)) = &self.message {
+            ui.text(panel_x + 24.0, footer_y, SCALE, if *error { ERROR_COLOR } else { TEXT_COLOR }, message);
+        }
+        ui.text(panel_x + 24.0, footer_y + 28.0, SCALE, DIM_COLOR, "ENTER/CLICK change   B/ESC close");
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use crate::assets;
+use crate::schema;
+
+// 敌人死亡时的随机掉落：data/loot.json 里的掉落表，每次击杀抽 rolls 次，
+// 每次按权重选一项（可以是什么都不掉）
+
+// data/loot.json 的格式版本
+const LOOT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LootItem {
+    Nothing,
+    // 手上每把武器补几个弹匣的备弹
+    Ammo { magazines: u32 },
+    Health { amount: f32 },
+    // 波次模式的钱，其他模式不掉
+    Currency { amount: u32 },
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct LootEntry {
+    weight: f32,
+    #[serde(flatten)]
+    item: LootItem,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct LootTable {
+    rolls: u32,
+    entries: Vec<LootEntry>,
+}
+
+impl LootTable {
+    // 文件有错时不掉落
+    pub fn load() -> Self {
+        let text = assets::read_to_string_or_builtin(Path::new("data/loot.json"), include_str!("../data/loot.json"));
+        schema::parse_json(&text, LOOT_VERSION).unwrap_or_else(|e| {
+            eprintln!("掉落表解析失败 (data/loot.json): {}", e);
+            Self { rolls: 0, entries: Vec::new() }
+        })
+    }
+
+    // 一次击杀掉的东西，currency 为 false 时跳过钱
+    pub fn roll(&self, currency: bool) -> Vec<LootItem> {
+        let entries: Vec<&LootEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.weight > 0.0 && (currency || !matches!(entry.item, LootItem::Currency { .. })))
+            .collect();
+        let total: f32 = entries.iter().map(|entry| entry.weight).sum();
+        let mut items = Vec::new();
+        for _ in 0..self.rolls {
+            let mut pick = fastrand::f32() * total;
+            let Some(entry) = entries.iter().find(|entry| {
+                pick -= entry.weight;
+                pick < 0.0
+            }) else {
+                continue;
+            };
+            if entry.item != LootItem::Nothing {
+                items.push(entry.item);
+            }
+        }
+        items
+    }
+}
@@ -1,676 +1,3447 @@
-use winit::{event::*, event_loop::{ControlFlow, EventLoop}, window::{WindowBuilder, Window}};
-use wgpu::util::DeviceExt;
-use std::time::{Duration, Instant};
-use glam::{Vec3, Mat4};
-use gilrs::{Gilrs, Button, Event as GilrsEvent};
-use std::sync::{Arc, Mutex};
-use std::thread;
-
-mod camera;
-mod texture;
-mod model;
-mod collision;
-
-// 添加颜色结构体
-#[derive(Clone, Copy, Debug, serde::Deserialize, serde::Serialize)]
-struct Color {
-    r: f64,
-    g: f64,
-    b: f64,
-}
-
-impl Default for Color {
-    fn default() -> Self {
-        Color {
-            r: 0.5,
-            g: 0.5,
-            b: 0.5,
-        }
-    }
-}
-
-fn main() {
-    env_logger::init();
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new()
-        .with_title("Underground Parking Shooter")
-        .with_inner_size(winit::dpi::PhysicalSize::new(1280, 720))
-        .build(&event_loop)
-        .unwrap();
-    
-    // 创建共享的墙体颜色状态
-    let wall_color = Arc::new(Mutex::new(Color::default()));
-    
-    // 启动HTTP服务器线程
-    let http_wall_color = wall_color.clone();
-    thread::spawn(move || {
-        start_http_server(http_wall_color);
-    });
-    
-    let mut state = pollster::block_on(State::new(&window, wall_color));
-    let mut last_render_time = Instant::now();
-    
-    // Initialize controller support
-    let mut gilrs = Gilrs::new().unwrap();
-    
-    event_loop.run(move |event, _, control_flow| {
-        // Controller input handling
-        while let Some(GilrsEvent { id, event, time }) = gilrs.next_event() {
-            state.input_controller(&id, &event);
-        }
-        
-        match event {
-            Event::WindowEvent {
-                ref event,
-                window_id,
-            } if window_id == window.id() => {
-                if !state.input(event) {
-                    match event {
-                        WindowEvent::CloseRequested
-                        | WindowEvent::KeyboardInput {
-                            input:
-                                KeyboardInput {
-                                    state: ElementState::Pressed,
-                                    virtual_keycode: Some(VirtualKeyCode::Escape),
-                                    ..
-                                },
-                            ..
-                        } => *control_flow = ControlFlow::Exit,
-                        WindowEvent::Resized(physical_size) => {
-                            state.resize(*physical_size);
-                        }
-                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                            state.resize(**new_inner_size);
-                        }
-                        WindowEvent::KeyboardInput {
-                            input: KeyboardInput {
-                                state: ElementState::Pressed,
-                                virtual_keycode: Some(VirtualKeyCode::F),
-                                ..
-                            },
-                            ..
-                        } => {
-                            println!("toggle fullscreen");
-                            // Toggle fullscreen state
-                            state.is_fullscreen = !state.is_fullscreen;
-                            
-                            // Apply fullscreen change
-                            if state.is_fullscreen {
-                                window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
-                            } else {
-                                window.set_fullscreen(None);
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            Event::DeviceEvent {
-                event: DeviceEvent::MouseMotion{ delta, .. },
-                .. 
-            } => {
-                state.process_mouse(delta.0, delta.1);
-            }
-            Event::RedrawRequested(window_id) if window_id == window.id() => {
-                let now = Instant::now();
-                let dt = now - last_render_time;
-                last_render_time = now;
-                state.update(dt);
-                
-                match state.render() {
-                    Ok(_) => {}
-                    Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
-                    Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
-                    Err(e) => eprintln!("Error: {:?}", e),
-                }
-            }
-            Event::MainEventsCleared => {
-                window.request_redraw();
-            }
-            _ => {}
-        }
-    });
-}
-
-// 启动HTTP服务器的函数
-fn start_http_server(wall_color: Arc<Mutex<Color>>) {
-    use warp::Filter;
-    // 创建一个运行时
-    let rt = tokio::runtime::Runtime::new().unwrap();
-    
-    rt.block_on(async {
-        // 创建一个路由处理颜色更新
-        let wall_color_put = wall_color.clone();
-        let color_route = warp::path("color")
-            .and(warp::put())
-            .and(warp::body::json())
-            .map(move |new_color: Color| {
-                let mut color = wall_color_put.lock().unwrap();
-                *color = new_color;
-                warp::reply::json(&*color)
-            });
-        
-        // 获取当前颜色的路由
-        let wall_color_get = wall_color.clone();
-        let get_color = warp::path("color")
-            .and(warp::get())
-            .map(move || {
-                let color = wall_color_get.lock().unwrap();
-                warp::reply::json(&*color)
-            });
-        
-        // 合并路由
-        let routes = color_route.or(get_color);
-        
-        println!("HTTP服务器启动在 http://localhost:3030");
-        println!("使用 PUT /color 更新墙体颜色");
-        println!("使用 GET /color 获取当前墙体颜色");
-        
-        warp::serve(routes).run(([0, 0, 0, 0], 3030)).await;
-    });
-}
-
-// 在 State 结构体中添加墙体颜色的缓冲区和绑定组
-struct State {
-    surface: wgpu::Surface,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    config: wgpu::SurfaceConfiguration,
-    size: winit::dpi::PhysicalSize<u32>,
-    render_pipeline: wgpu::RenderPipeline,
-    camera: camera::Camera,
-    camera_controller: camera::CameraController,
-    camera_uniform: camera::CameraUniform,
-    camera_buffer: wgpu::Buffer,
-    camera_bind_group: wgpu::BindGroup,
-    depth_texture: texture::Texture,
-    models: Vec<model::Model>,
-    is_fullscreen: bool,
-    wall_color: Arc<Mutex<Color>>, // 添加墙体颜色
-    wall_color_buffer: wgpu::Buffer,
-    wall_color_bind_group: wgpu::BindGroup,
-    texture_bind_group: wgpu::BindGroup, // 添加纹理绑定组
-    wall_colliders: Vec<collision::WallCollider>, // 添加墙体碰撞器集合
-}
-
-impl State {
-    async fn new(window: &Window, wall_color: Arc<Mutex<Color>>) -> Self {
-
-        let size = window.inner_size();
-        
-        // Instance is a handle to the GPU
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            dx12_shader_compiler: Default::default(),
-        });
-        
-        // Surface is the part of the window we draw to
-        let surface = unsafe { instance.create_surface(&window) }.unwrap();
-        
-        // Adapter is a handle to the actual graphics card
-        let adapter = instance.request_adapter(
-            &wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            },
-        ).await.unwrap();
-        
-        // Device is used for creating resources and Queue is used for submitting commands
-        let (device, queue) = adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                features: wgpu::Features::empty(),
-                limits: wgpu::Limits::default(),
-                label: None,
-            },
-            None,
-        ).await.unwrap();
-        
-        // Configure the surface
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps.formats.iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
-        
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
-        };
-        
-        surface.configure(&device, &config);
-
-        
-
-        // 加载狗狗纹理
-        let dog_bytes = include_bytes!("../dog.png"); // 确保这个路径正确
-        let dog_texture = texture::Texture::from_bytes(
-            &device,
-            &queue,
-            dog_bytes,
-            "dog_texture"
-        ).expect("无法加载狗狗纹理");
-        
-        // Create depth texture
-        let depth_texture = texture::Texture::create_depth_texture(&device, &config, "depth_texture");
-        
-        // Camera setup
-        let camera = camera::Camera::new((0.0, 1.8, -2.0), 0.0, 0.0); // 将 z 坐标从 0.0 改为 2.0，让相机往前移动一些
-        let camera_controller = camera::CameraController::new(4.0, 1.0);
-        
-        let mut camera_uniform = camera::CameraUniform::new();
-        camera_uniform.update_view_proj(&camera, config.width as f32 / config.height as f32);
-        
-        let camera_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Camera Buffer"),
-                contents: bytemuck::cast_slice(&[camera_uniform]),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            }
-        );
-        
-        let camera_bind_group_layout = device.create_bind_group_layout(
-            &wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    }
-                ],
-                label: Some("camera_bind_group_layout"),
-            }
-        );
-        
-        let camera_bind_group = device.create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                layout: &camera_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: camera_buffer.as_entire_binding(),
-                    }
-                ],
-                label: Some("camera_bind_group"),
-            }
-        );
-        
-        // Create shader module
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
-        
-        
-        // Create models for the parking garage
-        // 修改调用，传递引用
-        let models = model::create_parking_garage(&device, &dog_texture);
-        
-        // 创建墙体碰撞器
-        let mut wall_colliders = Vec::new();
-        
-        // 定义停车场的尺寸（与model.rs中的create_parking_garage函数保持一致）
-        let garage_width = 30.0;
-        let garage_length = 40.0;
-        let wall_height = 4.0;
-        
-        // 前墙（入口处有缺口）
-        wall_colliders.push(collision::create_wall_collider(
-            [-garage_width/2.0, 0.0, -garage_length/2.0],
-            [-5.0, 0.0, -garage_length/2.0],
-            wall_height
-        ));
-        
-        wall_colliders.push(collision::create_wall_collider(
-            [5.0, 0.0, -garage_length/2.0],
-            [garage_width/2.0, 0.0, -garage_length/2.0],
-            wall_height
-        ));
-        
-        // 后墙
-        wall_colliders.push(collision::create_wall_collider(
-            [-garage_width/2.0, 0.0, garage_length/2.0],
-            [garage_width/2.0, 0.0, garage_length/2.0],
-            wall_height
-        ));
-        
-        // 左墙
-        wall_colliders.push(collision::create_wall_collider(
-            [-garage_width/2.0, 0.0, -garage_length/2.0],
-            [-garage_width/2.0, 0.0, garage_length/2.0],
-            wall_height
-        ));
-        
-        // 右墙
-        wall_colliders.push(collision::create_wall_collider(
-            [garage_width/2.0, 0.0, -garage_length/2.0],
-            [garage_width/2.0, 0.0, garage_length/2.0],
-            wall_height
-        ));
-        
-        // 内部墙体1
-        wall_colliders.push(collision::create_wall_collider(
-            [-10.0, 0.0, 0.0],
-            [10.0, 0.0, 0.0],
-            wall_height
-        ));
-        
-        // 内部墙体2
-        wall_colliders.push(collision::create_wall_collider(
-            [0.0, 0.0, 5.0],
-            [0.0, 0.0, 15.0],
-            wall_height
-        ));
-
-        
-        // 创建墙体颜色 uniform 缓冲区
-        let wall_color_data = [0.5f32, 0.5f32, 0.5f32, 0.0f32]; // 初始颜色 + padding
-
-        
-        let wall_color_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Wall Color Buffer"),
-                contents: bytemuck::cast_slice(&wall_color_data),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            }
-        );
-        
-        // 创建墙体颜色绑定组布局
-        let wall_color_bind_group_layout = device.create_bind_group_layout(
-            &wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    }
-                ],
-                label: Some("wall_color_bind_group_layout"),
-            }
-        );
-
-        // 在创建墙体颜色绑定组布局后添加
-        let texture_bind_group_layout = device.create_bind_group_layout(
-            &wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Texture {
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            multisampled: false,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                ],
-                label: Some("texture_bind_group_layout"),
-            }
-        );
-        
-        // 创建纹理绑定组
-        let texture_bind_group = device.create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                layout: &texture_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wgpu::BindingResource::TextureView(&dog_texture.view),
-                    },
-                    wgpu::BindGroupEntry {
-                        binding: 1,
-                        resource: wgpu::BindingResource::Sampler(&dog_texture.sampler),
-                    },
-                ],
-                label: Some("texture_bind_group"),
-            }
-        );
-
-        // 修改渲染管线布局，添加纹理绑定组布局
-        let render_pipeline_layout = device.create_pipeline_layout(
-            &wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[
-                    &camera_bind_group_layout,
-                    &wall_color_bind_group_layout,
-                    &texture_bind_group_layout, // 添加纹理绑定组布局
-                ],
-                push_constant_ranges: &[],
-            }
-        );
-
-        // 创建渲染管线（使用上面创建的布局）
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout), // 使用包含墙体颜色绑定组的布局
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[model::ModelVertex::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: texture::Texture::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
-
-        // 创建墙体颜色绑定组
-        let wall_color_bind_group = device.create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                layout: &wall_color_bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: wall_color_buffer.as_entire_binding(),
-                    }
-                ],
-                label: Some("wall_color_bind_group"),
-            }
-        );
-
-        // 删除第二次创建的 render_pipeline_layout
-
-        Self {
-            surface,
-            device,
-            queue,
-            config,
-            size,
-            render_pipeline,
-            camera,
-            camera_controller,
-            camera_uniform,
-            camera_buffer,
-            camera_bind_group,
-            depth_texture,
-            models,
-            is_fullscreen: false,
-            wall_color, // 添加墙体颜色
-            wall_color_bind_group,
-            wall_color_buffer,
-            texture_bind_group, // 添加纹理绑定组
-            wall_colliders, // 添加墙体碰撞器集合
-        }
-    }
-    
-    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
-            self.depth_texture = texture::Texture::create_depth_texture(
-                &self.device,
-                &self.config,
-                "depth_texture"
-            );
-        }
-    }
-    
-    fn input(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            WindowEvent::KeyboardInput {
-                input: KeyboardInput {
-                    state: ElementState::Pressed,
-                    virtual_keycode: Some(VirtualKeyCode::F),
-                    ..                    
-                },
-                ..                
-            } => {
-                // Return true to indicate we've handled the F key press
-                // The actual fullscreen toggle is handled in the main event loop
-                false
-            }
-            _ => self.camera_controller.process_keyboard(event)
-        }
-    }
-    
-    fn process_mouse(&mut self, dx: f64, dy: f64) {
-        self.camera_controller.process_mouse(dx, dy);
-    }
-    
-    fn input_controller(&mut self, id: &gilrs::GamepadId, event: &gilrs::EventType) {
-        self.camera_controller.process_controller(id, event);
-    }
-    
-    fn update(&mut self, dt: std::time::Duration) {
-        // 更新相机位置
-        self.camera_controller.update_camera(&mut self.camera, dt);
-        
-        // 碰撞检测和响应
-        let player_radius = 0.5; // 玩家碰撞半径
-        let mut position = self.camera.position;
-        
-        // 对每个墙体进行碰撞检测
-        for collider in &self.wall_colliders {
-            position = collider.resolve_collision(position, player_radius);
-        }
-        
-        // 更新相机位置
-        self.camera.position = position;
-        
-        // 更新相机uniform
-        self.camera_uniform.update_view_proj(&self.camera, self.config.width as f32 / self.config.height as f32);
-        self.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[self.camera_uniform]));
-        
-        // 更新墙体颜色（如果有变化）
-        self.update_wall_color();
-    }
-    
-    fn update_wall_color(&mut self) {
-        if let Ok(color) = self.wall_color.lock() {
-            // 更新墙体颜色 uniform 缓冲区
-            let wall_color_data = [
-                color.r as f32,
-                color.g as f32,
-                color.b as f32,
-                0.0f32, // padding
-            ];
-            self.queue.write_buffer(
-                &self.wall_color_buffer,
-                0,
-                bytemuck::cast_slice(&wall_color_data)
-            );
-        }
-    }
-    
-    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
-        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
-        
-        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Render Encoder"),
-        });
-        
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.depth_texture.view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: true,
-                    }),
-                    stencil_ops: None,
-                }),
-            });
-            
-            // 在 render 方法中
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
-            render_pass.set_bind_group(1, &self.wall_color_bind_group, &[]); 
-            render_pass.set_bind_group(2, &self.texture_bind_group, &[]); // 设置纹理绑定组
-            
-            // Render all models
-            for model in &self.models {
-                model.draw(&mut render_pass);
-            }
-        }
-        
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
-        
-        Ok(())
-    }
-}
+use winit::{event::*, event_loop::{ControlFlow, EventLoop}, window::{WindowBuilder, Window}};
+use instant::Instant;
+#[cfg(feature = "gamepad")]
+use gilrs::{Gilrs, Event as GilrsEvent};
+use std::sync::{Arc, Mutex};
+
+mod assets;
+#[cfg(not(target_arch = "wasm32"))]
+mod pak;
+mod bindings;
+mod camera;
+mod texture;
+mod model;
+mod mesh;
+mod geometry;
+mod collision;
+mod ecs;
+mod damage;
+mod font;
+mod ui;
+mod upload;
+mod menu;
+mod net;
+mod server_config;
+mod bots;
+mod net_sim;
+mod net_graph;
+mod snapshot;
+mod demo;
+mod lag_comp;
+mod anti_cheat;
+mod chat;
+mod audio;
+mod events;
+mod profile;
+mod progression;
+mod waves;
+mod night_vision;
+mod achievements;
+mod hud;
+mod match_end;
+mod scoreboard;
+mod input;
+mod axes;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod touch;
+mod jobs;
+mod pacing;
+mod benchmark;
+mod determinism;
+mod gpu_memory;
+mod profiler;
+mod raw_input;
+mod map;
+mod map_import;
+#[cfg(feature = "http-server")]
+mod map_share;
+mod trigger;
+mod hazard;
+mod tutorial;
+mod minimap;
+mod nav;
+mod player;
+mod spectate;
+mod weapon;
+mod decals;
+mod draw_list;
+mod particles;
+mod enemy;
+mod enemy_ai;
+mod enemy_sprites;
+mod ai_debug;
+mod practice;
+mod dog;
+mod schema;
+mod settings;
+mod paths;
+mod mods;
+mod crosshair;
+mod aim_assist;
+mod pickup;
+mod projectile;
+mod grenade;
+mod melee;
+mod casings;
+mod recoil;
+mod tracers;
+mod weapon_fx;
+mod loot;
+mod icons;
+mod inventory;
+mod shop;
+mod loadout;
+mod door;
+mod anim_events;
+mod transparent;
+mod cutout;
+mod floor_markings;
+mod car;
+mod ceiling;
+mod level;
+mod reflection;
+mod lighting;
+mod light_fixtures;
+mod alarm;
+mod stealth;
+mod lightmap;
+mod post;
+mod outline;
+mod gpu_particles;
+#[cfg(target_arch = "wasm32")]
+mod web;
+
+// 每走多远播放一次脚步声
+const FOOTSTEP_STRIDE: f32 = 2.2;
+
+// 档案自动保存间隔（秒）
+const PROFILE_SAVE_INTERVAL: f32 = 60.0;
+
+// 教程完成后多久进入正式地图（秒）
+const TUTORIAL_EXIT_DELAY: f32 = 3.0;
+
+// 俯视画面的高度对应多少米
+const TOP_DOWN_SPAN: f32 = 30.0;
+
+// 添加颜色结构体
+#[derive(Clone, Copy, Debug, serde::Deserialize, serde::Serialize)]
+struct Color {
+    r: f64,
+    g: f64,
+    b: f64,
+}
+
+// POST /damage 的请求体
+#[cfg(feature = "http-server")]
+#[derive(Clone, Copy, Debug, serde::Deserialize, serde::Serialize)]
+struct DamageRequest {
+    amount: f32,
+    #[serde(default)]
+    damage_type: damage::DamageType,
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Color {
+            r: 0.5,
+            g: 0.5,
+            b: 0.5,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    env_logger::init();
+
+    // --bake-lighting [地图名]：离线烘焙光照贴图后退出，不打开窗口
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--bake-lighting") {
+        let name = args.get(index + 1).map_or(map::DEFAULT_MAP, |name| name.as_str());
+        lightmap::bake_command(name);
+        return;
+    }
+    // --import-map-image <图片>：把图片转成 maps/ 下的地图后退出
+    if let Some(index) = args.iter().position(|arg| arg == "--import-map-image") {
+        match args.get(index + 1) {
+            Some(path) => map_import::import_command(std::path::Path::new(path)),
+            None => eprintln!("用法: --import-map-image <图片路径>"),
+        }
+        return;
+    }
+    // --pack [输出路径]：把 maps/、luts/、data/ 等打成发布用的资源包后退出
+    if let Some(index) = args.iter().position(|arg| arg == "--pack") {
+        pak::pack_command(args.get(index + 1).map(|path| path.as_str()));
+        return;
+    }
+    // --portable：设置和存档放在程序旁边，不用系统的用户目录
+    paths::init(&args);
+    mods::init();
+    pollster::block_on(run(args));
+}
+
+// 浏览器里由 wasm-bindgen 生成的 JS 调用 main，先下载资源文件再启动（见 web.rs）
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    web::start();
+}
+
+// 打开窗口，运行游戏直到退出。args 是命令行参数（浏览器里为空）
+async fn run(args: Vec<String>) {
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title("Underground Parking Shooter")
+        .with_inner_size(winit::dpi::PhysicalSize::new(1280, 720))
+        .build(&event_loop)
+        .unwrap();
+    #[cfg(target_arch = "wasm32")]
+    web::attach_canvas(&window);
+    
+    // 创建共享的墙体颜色状态
+    let wall_color = Arc::new(Mutex::new(Color::default()));
+    
+    // 加载玩家档案
+    let profile = Arc::new(Mutex::new(profile::Profile::load(
+        &paths::profile(),
+        &net::local_player_name(),
+    )));
+    
+    // 通过 HTTP 对玩家造成的伤害（调试用），每帧由游戏取出
+    let pending_damage = Arc::new(Mutex::new(Vec::new()));
+    
+    // 启动HTTP服务器线程
+    #[cfg(feature = "http-server")]
+    {
+        let http_wall_color = wall_color.clone();
+        let http_profile = profile.clone();
+        let http_damage = pending_damage.clone();
+        std::thread::spawn(move || {
+            start_http_server(http_wall_color, http_profile, http_damage);
+        });
+    }
+    
+    let mut state = State::new(&window, wall_color, profile, pending_damage).await;
+    // --benchmark [地图名]：沿地图里的路线自动飞行，记录帧时间，写出报告后退出
+    state.net_conditions = net_sim::Conditions::from_args(&args);
+    if let Some(index) = args.iter().position(|arg| arg == "--benchmark") {
+        let name = args.get(index + 1).filter(|name| !name.starts_with("--")).map_or(map::DEFAULT_MAP, |name| name.as_str());
+        state.start_benchmark(name);
+    }
+    // --determinism [地图名] [--determinism-baseline <结果文件>]：同一段脚本输入跑两轮，比较每次模拟后的校验和，写出结果后退出
+    if let Some(index) = args.iter().position(|arg| arg == "--determinism") {
+        let name = args.get(index + 1).filter(|name| !name.starts_with("--")).map_or(map::DEFAULT_MAP, |name| name.as_str());
+        let baseline = args.iter().position(|arg| arg == "--determinism-baseline").and_then(|index| args.get(index + 1)).and_then(|path| {
+            determinism::load_baseline(std::path::Path::new(path)).map_err(|e| eprintln!("无法读取对比的结果: {}", e)).ok()
+        });
+        state.start_determinism(name, baseline);
+    }
+    let mut pacer = pacing::FramePacer::new();
+    
+    // Initialize controller support
+    #[cfg(feature = "gamepad")]
+    let mut gilrs = Gilrs::new().unwrap();
+    
+    event_loop.run(move |event, _, control_flow| {
+        // Controller input handling
+        #[cfg(feature = "gamepad")]
+        while let Some(GilrsEvent { id, event, .. }) = gilrs.next_event() {
+            state.input_controller(&gilrs.gamepad(id), &event);
+        }
+        
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() && !state.input(event) => {
+                match event {
+                    WindowEvent::CloseRequested
+                    | WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => *control_flow = ControlFlow::Exit,
+                    WindowEvent::Resized(physical_size) => {
+                        state.resize(*physical_size);
+                    }
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        state.resize(**new_inner_size);
+                    }
+                    WindowEvent::KeyboardInput {
+                        input: KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::F),
+                            ..
+                        },
+                        ..
+                    } => {
+                        println!("toggle fullscreen");
+                        // Toggle fullscreen state
+                        state.is_fullscreen = !state.is_fullscreen;
+                        
+                        // Apply fullscreen change
+                        if state.is_fullscreen {
+                            window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
+                        } else {
+                            window.set_fullscreen(None);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion{ delta, .. },
+                .. 
+            } => {
+                state.raw_input.record(delta.0, delta.1);
+                state.process_mouse(delta.0, delta.1);
+            }
+            Event::RedrawRequested(window_id) if window_id == window.id() => {
+                // 固定频率的模拟，追上经过的时间后按剩下的比例插值渲染
+                let frame = pacer.advance(state.time_scale.current());
+                if state.determinism.is_some() {
+                    // 确定性测试按脚本跑固定的次数，和经过的时间无关
+                    state.update_determinism();
+                } else {
+                    for _ in 0..frame.ticks {
+                        state.update(pacing::TICK);
+                    }
+                }
+                state.prepare_frame(frame.dt, frame.alpha);
+                if state.quit_requested {
+                    *control_flow = ControlFlow::Exit;
+                }
+                
+                match state.render() {
+                    Ok(_) => {}
+                    Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
+                    Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
+                    Err(e) => eprintln!("Error: {:?}", e),
+                }
+                // 性能测试和确定性测试时不限制帧率
+                if state.benchmark.is_none() && state.determinism.is_none() {
+                    let refresh_rate = window.current_monitor().and_then(|monitor| monitor.refresh_rate_millihertz());
+                    pacer.wait(pacing::frame_time(state.settings.frame_limit, refresh_rate));
+                }
+            }
+            Event::MainEventsCleared => {
+                window.request_redraw();
+            }
+            Event::LoopDestroyed => {
+                state.shutdown();
+                // 确定性测试没通过时返回非零的退出码，脚本里可以直接判断
+                if state.determinism.as_ref().is_some_and(|test| !test.passed()) {
+                    std::process::exit(1);
+                }
+            }
+            _ => {}
+        }
+    });
+}
+
+// 启动HTTP服务器的函数
+#[cfg(feature = "http-server")]
+fn start_http_server(wall_color: Arc<Mutex<Color>>, profile: Arc<Mutex<profile::Profile>>, pending_damage: Arc<Mutex<Vec<damage::Damage>>>) {
+    use warp::Filter;
+    // 创建一个运行时
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    
+    rt.block_on(async {
+        // 创建一个路由处理颜色更新
+        let wall_color_put = wall_color.clone();
+        let color_route = warp::path("color")
+            .and(warp::put())
+            .and(warp::body::json())
+            .map(move |new_color: Color| {
+                let mut color = wall_color_put.lock().unwrap();
+                *color = new_color;
+                warp::reply::json(&*color)
+            });
+        
+        // 获取当前颜色的路由
+        let wall_color_get = wall_color.clone();
+        let get_color = warp::path("color")
+            .and(warp::get())
+            .map(move || {
+                let color = wall_color_get.lock().unwrap();
+                warp::reply::json(&*color)
+            });
+        
+        // 获取玩家档案（终身统计和排行榜）的路由
+        let get_profile = warp::path("profile")
+            .and(warp::get())
+            .map(move || {
+                let profile = profile.lock().unwrap();
+                warp::reply::json(&*profile)
+            });
+        
+        // 对玩家造成伤害的路由（测试重生和重生保护）
+        let damage_route = warp::path("damage")
+            .and(warp::post())
+            .and(warp::body::json())
+            .map(move |request: DamageRequest| {
+                pending_damage.lock().unwrap().push(damage::Damage::new(request.amount, request.damage_type));
+                warp::reply::json(&request)
+            });
+        
+        // 显存占用统计（Prometheus 文本格式），反复加载地图时用来查泄漏；联机时还有反作弊的违规次数
+        let metrics_route = warp::path("metrics")
+            .and(warp::get())
+            .map(|| gpu_memory::metrics_text() + &anti_cheat::metrics_text());
+        
+        // 局域网里分享地图：上传、列表和下载
+        let upload_map = warp::path("maps")
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(warp::body::content_length_limit(map_share::MAX_UPLOAD + 1024))
+            .and(warp::body::json())
+            .map(|upload: map_share::Upload| match map_share::upload(upload) {
+                Ok(info) => warp::reply::with_status(warp::reply::json(&info), warp::http::StatusCode::CREATED),
+                Err(error) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({ "error": error })),
+                    warp::http::StatusCode::BAD_REQUEST,
+                ),
+            });
+        let list_maps = warp::path("maps")
+            .and(warp::path::end())
+            .and(warp::get())
+            .map(|| warp::reply::json(&map_share::list()));
+        let download_map = warp::path!("maps" / String)
+            .and(warp::get())
+            .and_then(|id: String| async move { map_share::download(&id).ok_or_else(warp::reject::not_found) });
+
+        // 重新加载 server.toml，要带管理员 token
+        let reload_config = warp::path!("admin" / "reload")
+            .and(warp::post())
+            .and(warp::header::optional::<String>("authorization"))
+            .map(|authorization: Option<String>| {
+                if !server_config::current().is_admin(authorization.as_deref()) {
+                    return warp::reply::with_status(
+                        warp::reply::json(&serde_json::json!({ "error": "invalid admin token" })),
+                        warp::http::StatusCode::UNAUTHORIZED,
+                    );
+                }
+                match server_config::reload() {
+                    Ok(config) => warp::reply::with_status(warp::reply::json(&*config), warp::http::StatusCode::OK),
+                    Err(error) => warp::reply::with_status(
+                        warp::reply::json(&serde_json::json!({ "error": error })),
+                        warp::http::StatusCode::BAD_REQUEST,
+                    ),
+                }
+            });
+
+        // 合并路由
+        let routes = color_route.or(get_color).or(get_profile).or(damage_route).or(metrics_route)
+            .or(upload_map).or(list_maps).or(download_map).or(reload_config);
+        
+        println!("HTTP服务器启动在 http://localhost:3030");
+        println!("使用 PUT /color 更新墙体颜色");
+        println!("使用 GET /color 获取当前墙体颜色");
+        println!("使用 GET /profile 获取玩家统计");
+        println!("使用 POST /damage 对玩家造成伤害");
+        println!("使用 GET /metrics 获取显存占用和反作弊统计");
+        println!("使用 POST /maps 分享地图，GET /maps 列出分享的地图，GET /maps/{{id}} 下载");
+        println!("使用 POST /admin/reload 重新加载 {}（Authorization: Bearer <token>）", paths::server_config().display());
+        
+        warp::serve(routes).run(([0, 0, 0, 0], 3030)).await;
+    });
+}
+
+// 在 State 结构体中添加墙体颜色的缓冲区和绑定组
+struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    size: winit::dpi::PhysicalSize<u32>,
+    render_pipeline: wgpu::RenderPipeline,
+    transparent_pipeline: wgpu::RenderPipeline, // 半透明物体：混合、不写深度
+    camera: camera::Camera,
+    camera_controller: camera::CameraController,
+    camera_uniform: camera::CameraUniform,
+    frame: bindings::FrameBinding, // 第 0 组：相机、时间、反射探针和光照
+    layouts: bindings::Layouts, // 换地图时重建第 0 组
+    start_time: Instant,
+    depth_texture: texture::Texture,
+    is_fullscreen: bool,
+    wall_color: Arc<Mutex<Color>>, // 添加墙体颜色
+    level_material: bindings::MaterialBinding, // 第 1 组：地图的纹理，颜色是墙体颜色
+    object_bind_group: wgpu::BindGroup, // 第 2 组：地图几何体共用的单位矩阵
+    colliders: Vec<collision::WallCollider>, // 本帧的所有碰撞体（墙体加上关着的门）
+    anim_events: anim_events::AnimEvents, // 这次模拟里动画越过的关键帧
+    ui_renderer: ui::UiRenderer, // 菜单和HUD的2D渲染
+    uploader: upload::FrameUploader, // 每帧数据的合并上传
+    menu: menu::Menu,
+    session: net::Session, // 局域网联机会话
+    chat: chat::Chat,
+    audio: audio::AudioSystem,
+    ambient_emitters: Vec<audio::AmbientEmitter>, // 环境声源
+    hazards: hazard::HazardSystem,
+    footstep_distance: f32, // 距离上一次脚步声走过的距离
+    events: events::EventBus, // 游戏事件总线
+    profile: Arc<Mutex<profile::Profile>>, // 玩家档案（与HTTP服务器共享）
+    profile_save_timer: f32,
+    achievements: achievements::AchievementTracker,
+    hud: hud::Hud,
+    quit_requested: bool,
+    dog_texture: texture::Texture, // 切换地图时重新生成模型用
+    map: map::Map, // 当前地图
+    input_config: input::InputConfig, // 按键绑定
+    input_device: input::Device, // 最近用的键鼠或手柄，提示按它显示按键
+    active_gamepad: Option<(String, String)>, // 最近用的手柄的 GUID 和名字，摇杆设置界面调的是它
+    held_actions: Vec<input::Action>, // 当前按住的操作，用于忽略按键重复
+    triggers: trigger::TriggerSystem,
+    tutorial: tutorial::Tutorial,
+    tutorial_exit_timer: Option<f32>,
+    minimap: minimap::Minimap,
+    minimap_batch: ui::UiBatch, // 任务线程上画好的小地图，渲染时拼到界面里
+    jobs: jobs::JobPool, // 每帧并行执行的模拟任务
+    #[cfg(feature = "gamepad")]
+    nav_stick: gamepad::NavStick, // 菜单和物品栏里把左摇杆当作方向键
+    touch: touch::TouchControls, // 触屏的虚拟摇杆和按钮
+    previous_camera_position: glam::Vec3, // 上一次模拟结束时的相机位置，渲染时插值
+    view_camera: camera::Camera, // 插值后这一帧实际用来画的相机
+    present_modes: Vec<wgpu::PresentMode>, // 显示表面支持的呈现方式，改帧率上限时重新选择
+    adapter_name: String, // 显卡名，写在性能测试报告里
+    benchmark: Option<benchmark::Benchmark>, // --benchmark 模式下相机由它控制
+    determinism: Option<determinism::DeterminismTest>, // --determinism 模式下输入由它的脚本给出
+    player: player::Player,
+    pending_damage: Arc<Mutex<Vec<damage::Damage>>>, // HTTP 服务器收到的伤害
+    death_position: glam::Vec3, // 上一次死亡的位置
+    last_attacker: Option<(u32, String)>, // 联机时最后打中自己的玩家和武器，死亡时报告给主机
+    spectator: spectate::Spectator, // 联机时死亡后和中途加入时观战
+    demo: Option<demo::Playback>, // 正在回放的录像
+    match_summary: Option<match_end::SummaryScreen>, // 联机比赛结束后的总结和换图投票
+    weapon_defs: Vec<weapon::WeaponStats>, // data/weapons.json 中的所有武器
+    inventory: inventory::Inventory,
+    inventory_screen: inventory::InventoryScreen,
+    waves: Option<waves::WaveMode>, // 波次模式，其他时候为 None
+    shop: shop::ShopScreen, // 波次模式购买阶段的商店
+    loadout_screen: loadout::LoadoutScreen, // 死亡以后选出生配置
+    loadout: Option<loadout::Loadout>, // 下次重生用的出生配置（联机时是主机检查过的），None 时保留原来的武器
+    awaiting_loadout: bool, // 联机客户端在等主机检查出生配置
+    cursor: (f32, f32), // 鼠标在窗口中的位置，商店用
+    world: ecs::World, // 敌人、拾取物、门、狗和投射物等实体
+    pickups: pickup::PickupManager,
+    decals: decals::DecalSet, // 弹孔
+    enemies: enemy::EnemyManager,
+    range: practice::PracticeRange, // 练习场的靶子和统计，地图上没有靶子时不显示
+    dog_model: Option<model::Model>, // 朝向相机的狗，每帧重新生成
+    enemy_sprite_model: Option<model::Model>, // 朝向相机的敌人，每帧重新生成
+    dynamic_model: Option<model::Model>, // 敌人、尸体和粒子，每帧重新生成
+    show_colliders: bool, // F3 切换碰撞体的调试显示
+    profiler: profiler::Profiler, // F4 切换性能面板
+    raw_input: raw_input::RawInputGraph, // F6 切换鼠标原始输入面板
+    net_graph: net_graph::NetGraph, // F7 切换网络诊断面板
+    top_down: bool, // F8 切换正交投影的俯视画面
+    tilt: camera::CameraTilt, // 平移和受伤时的画面倾斜
+    shake: camera::CameraShake, // 附近爆炸时的画面震动
+    third_person: bool, // F9 切换越肩的第三人称画面
+    boom: camera::CameraBoom, // 第三人称相机碰到墙时往回收
+    net_conditions: net_sim::Conditions, // 命令行打开的网络模拟，联机时用
+    debug_model: Option<model::Model>, // 碰撞体线框，显示时每帧重新生成
+    ai_inspector: ai_debug::AiInspector, // 聊天框输入 /inspect 切换的 AI 调试面板
+    inspector_model: Option<model::Model>, // 调试面板里敌人到玩家的路线，每帧重新生成
+    decal_material: bindings::MaterialBinding, // 贴花图集（地面血迹）
+    enemy_sprite_material: bindings::MaterialBinding,
+    level: level::Level, // 地图的模型、碰撞器、玻璃、铁丝网和地面标记，以及合并后的静态合批
+    reflection: reflection::ReflectionProbe, // 车漆等有光泽表面的环境反射
+    particle_effects: gpu_particles::ParticleEffects, // 灰尘、雨和火花
+    lighting: lighting::Lighting, // 点光源，按设置分簇
+    light_fixtures: light_fixtures::LightFixtures, // 天花板上可以打坏的灯
+    exposure: stealth::Exposure, // 玩家身上有多亮，决定敌人多远能发现玩家
+    alarms: alarm::AlarmSystem, // 警报面板，响着时叫来增援
+    post: post::PostProcess, // 景深、运动模糊和调色，打开时场景先画到离屏纹理
+    outline: outline::Outline, // 准星对着的可交互物体和被标记的敌人的描边，夜视仪里高亮的敌人
+    night_vision: night_vision::NightVision,
+    time_scale: pacing::TimeScale, // 卡肉和慢动作，只在单机时使用
+    grenades: grenade::Grenades, // 扔出去还没炸的手雷和剩下的数量
+    melee: melee::Melee,
+    tracers: tracers::Tracers, // 即时命中射击的曳光
+    weapon_fx: weapon_fx::WeaponEffects, // 枪口火光和弹着点的火花、灰尘
+    casings: casings::Casings, // 抛出的弹壳和地上的弹壳
+    recoil: recoil::Recoil, // 开枪后视角上抬，慢慢恢复
+    settings: settings::Settings,
+    aim: Option<crosshair::AimTarget>, // 本帧屏幕中心瞄准的物体
+}
+
+impl State {
+    async fn new(
+        window: &Window,
+        wall_color: Arc<Mutex<Color>>,
+        profile: Arc<Mutex<profile::Profile>>,
+        pending_damage: Arc<Mutex<Vec<damage::Damage>>>,
+    ) -> Self {
+
+        let size = window.inner_size();
+        
+        // Instance is a handle to the GPU
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::all(),
+            dx12_shader_compiler: Default::default(),
+        });
+        
+        // Surface is the part of the window we draw to
+        let surface = unsafe { instance.create_surface(&window) }.unwrap();
+        
+        // Adapter is a handle to the actual graphics card
+        let adapter = instance.request_adapter(
+            &wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+                force_fallback_adapter: false,
+            },
+        ).await.unwrap();
+        
+        // Device is used for creating resources and Queue is used for submitting commands
+        let gpu_particles = gpu_particles::gpu_supported(&adapter);
+        // 光源分簇用计算着色器，不支持时只能遍历全部光源
+        let compute_shaders = adapter.get_downlevel_capabilities().flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS);
+        // 有多重间接绘制就用它画合批后的静态几何体
+        let features = adapter.features() & wgpu::Features::MULTI_DRAW_INDIRECT;
+        // 浏览器里的限制由浏览器决定，WebGL2 比默认的小
+        let limits = if cfg!(target_arch = "wasm32") { adapter.limits() } else { wgpu::Limits::default() };
+        let (device, queue) = adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                features,
+                limits,
+                label: None,
+            },
+            None,
+        ).await.unwrap();
+        
+        // Configure the surface
+        let surface_caps = surface.get_capabilities(&adapter);
+        let surface_format = surface_caps.formats.iter()
+            .copied()
+            .find(|f| f.is_srgb())
+            .unwrap_or(surface_caps.formats[0]);
+        let settings = settings::Settings::load(&paths::settings());
+        
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: size.width,
+            height: size.height,
+            present_mode: pacing::present_mode(settings.frame_limit, &surface_caps.present_modes),
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+        };
+        
+        surface.configure(&device, &config);
+
+        
+
+        // 加载狗狗纹理
+        let dog_bytes = assets::read_or_builtin(std::path::Path::new("dog.png"), include_bytes!("../dog.png"));
+        let dog_texture = texture::Texture::from_bytes(
+            &device,
+            &queue,
+            &dog_bytes,
+            "dog_texture"
+        ).expect("无法加载狗狗纹理");
+        
+        // Create depth texture
+        let depth_texture = texture::Texture::create_depth_texture(&device, &config, "depth_texture");
+        
+        // Camera setup
+        let mut camera = camera::Camera::new((0.0, 1.8, -2.0), 0.0, 0.0); // 地图加载后移动到出生点
+        camera.near = settings.near_plane;
+        camera.far = settings.far_plane;
+        let mut camera_controller = camera::CameraController::new(camera::WALK_SPEED, settings.look);
+        camera_controller.mouse_smoothing = settings.mouse_smoothing;
+        camera_controller.mouse_acceleration = settings.mouse_acceleration;
+        
+        let mut camera_uniform = camera::CameraUniform::new();
+        camera_uniform.update_view_proj(&camera, config.width as f32 / config.height as f32);
+        
+        // Create shader module
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
+        });
+        
+        
+        // 加载地图，生成模型和墙体碰撞器
+        let map = map::Map::load(map::DEFAULT_MAP).unwrap_or_else(|e| panic!("无法加载地图 {}: {}", map::DEFAULT_MAP, e));
+        // 场景管线共用的三组绑定
+        let layouts = bindings::Layouts::new(&device);
+        // 地图材质的颜色就是墙体颜色，由 HTTP 接口修改
+        let level_material = bindings::MaterialBinding::new(&device, &layouts, &dog_texture, [0.5, 0.5, 0.5], "level_material");
+        let object_bind_group = bindings::create_object_bind_group(&device, &layouts, glam::Mat4::IDENTITY, [1.0; 3], "world_object");
+
+        // 贴花图集是另一种材质，绘制贴花时替换第 1 组
+        let decal_atlas = texture::Texture::from_image(
+            &device,
+            &queue,
+            &image::DynamicImage::ImageRgba8(decals::generate_atlas()),
+            Some("decal_atlas"),
+        ).expect("无法创建贴花图集");
+        let decal_material = bindings::MaterialBinding::new(&device, &layouts, &decal_atlas, [1.0; 3], "decal_material");
+        // 敌人的贴图也是单独的材质
+        let enemy_sprite_texture = texture::Texture::from_image(
+            &device,
+            &queue,
+            &image::DynamicImage::ImageRgba8(enemy_sprites::generate_texture()),
+            Some("enemy_sprites"),
+        ).expect("无法创建敌人贴图");
+        let enemy_sprite_material = bindings::MaterialBinding::new(&device, &layouts, &enemy_sprite_texture, [1.0; 3], "enemy_sprite_material");
+
+        let render_pipeline_layout = layouts.pipeline_layout(&device);
+
+        // 创建渲染管线（使用上面创建的布局）
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&render_pipeline_layout), // 场景管线共用的布局
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[model::ModelVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: texture::Texture::DEPTH_COMPARE,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let transparent_pipeline = transparent::create_pipeline(&device, &render_pipeline_layout, &shader, config.format);
+        let mut level = level::Level::new(&device, &queue, &layouts, &render_pipeline_layout, &shader, config.format);
+        level.load(&device, &queue, &map, &dog_texture);
+        let mut lighting = lighting::Lighting::new(&device, compute_shaders);
+        lighting.load(&device, &queue, &map);
+        let reflection = reflection::ReflectionProbe::new(&device, &render_pipeline_layout, &shader, config.format);
+        reflection.capture(&device, &queue, &layouts, &map, &level_material.bind_group, &object_bind_group, &level.models, CLEAR_COLOR, &lighting);
+        let frame = bindings::FrameBinding::new(&device, &layouts, &reflection.environment(), &lighting.resources(), "frame");
+        let mut particle_effects = gpu_particles::ParticleEffects::new(&device, gpu_particles, &layouts.frame, config.format);
+        let tracers = tracers::Tracers::new(&device, &layouts.frame, config.format);
+        let weapon_fx = weapon_fx::WeaponEffects::new(&device, &layouts.frame, config.format);
+        let hazards = hazard::HazardSystem::new(&map);
+        particle_effects.set_emitters(particle_emitters(&map, &hazards));
+
+        // 删除第二次创建的 render_pipeline_layout
+
+        let mut post = post::PostProcess::new(&device, config.format);
+        post.set_lut(&device, &queue, map.lut.as_deref());
+        post.configure(&device, &settings, config.width, config.height, &depth_texture.view);
+
+        let outline = outline::Outline::new(&device, config.format, &layouts.material);
+        let ui_renderer = ui::UiRenderer::new(&device, &queue, config.format);
+        let uploader = upload::FrameUploader::new(&device);
+        let weapon_defs = weapon::load_weapons();
+        let mut inventory = inventory::Inventory::new();
+        inventory.add(weapon_defs.first().cloned().expect("没有可用的武器"));
+
+        let mut world = ecs::World::new();
+        let pickups = pickup::PickupManager::new(&mut world, &map, &weapon_defs);
+        let enemies = enemy::EnemyManager::new(&mut world, &map, settings.gore);
+        let range = practice::PracticeRange::new(&mut world, &map);
+        door::spawn(&mut world, &map);
+        dog::spawn(&mut world, &map);
+        let view_camera = camera.clone();
+
+        let mut state = Self {
+            surface,
+            device,
+            queue,
+            config,
+            size,
+            render_pipeline,
+            transparent_pipeline,
+            level,
+            reflection,
+            particle_effects,
+            lighting,
+            post,
+            outline,
+            night_vision: night_vision::NightVision::new(),
+            time_scale: pacing::TimeScale::new(),
+            grenades: grenade::Grenades::new(),
+            melee: melee::Melee::new(),
+            tracers,
+            weapon_fx,
+            casings: casings::Casings::new(),
+            recoil: recoil::Recoil::new(),
+            camera,
+            camera_controller,
+            camera_uniform,
+            frame,
+            layouts,
+            start_time: Instant::now(),
+            depth_texture,
+            is_fullscreen: false,
+            wall_color, // 添加墙体颜色
+            level_material,
+            object_bind_group,
+            colliders: Vec::new(),
+            light_fixtures: light_fixtures::LightFixtures::new(&map),
+            exposure: stealth::Exposure::new(),
+            alarms: alarm::AlarmSystem::new(&map),
+            anim_events: anim_events::AnimEvents::new(),
+            ui_renderer,
+            uploader,
+            menu: menu::Menu::new(),
+            session: net::Session::Offline,
+            chat: chat::Chat::new(),
+            audio: audio::AudioSystem::new(),
+            ambient_emitters: ambient_emitters(&map, &hazards),
+            hazards,
+            footstep_distance: 0.0,
+            events: events::EventBus::new(),
+            profile,
+            profile_save_timer: PROFILE_SAVE_INTERVAL,
+            achievements: achievements::AchievementTracker::new(),
+            hud: hud::Hud::new(),
+            quit_requested: false,
+            input_config: input::InputConfig::load(&paths::input_config()),
+            input_device: input::Device::KeyboardMouse,
+            active_gamepad: None,
+            held_actions: Vec::new(),
+            triggers: trigger::TriggerSystem::new(map.trigger_zones()),
+            tutorial: tutorial::Tutorial::new(map.prompts.clone()),
+            tutorial_exit_timer: None,
+            minimap: minimap::Minimap::new(),
+            minimap_batch: ui::UiBatch::new(size.width, size.height),
+            jobs: jobs::JobPool::new(),
+            #[cfg(feature = "gamepad")]
+            nav_stick: gamepad::NavStick::new(),
+            touch: touch::TouchControls::new(settings.touch_controls),
+            previous_camera_position: glam::Vec3::ZERO,
+            view_camera,
+            present_modes: surface_caps.present_modes,
+            adapter_name: adapter.get_info().name,
+            benchmark: None,
+            determinism: None,
+            player: player::Player::new(),
+            pending_damage,
+            death_position: glam::Vec3::ZERO,
+            last_attacker: None,
+            spectator: spectate::Spectator::new(),
+            demo: None,
+            match_summary: None,
+            pickups,
+            inventory,
+            inventory_screen: inventory::InventoryScreen::new(),
+            waves: None,
+            shop: shop::ShopScreen::new(),
+            loadout_screen: loadout::LoadoutScreen::new(),
+            loadout: None,
+            awaiting_loadout: false,
+            cursor: (0.0, 0.0),
+            weapon_defs,
+            decals: decals::DecalSet::new(),
+            enemies,
+            range,
+            world,
+            dog_model: None,
+            enemy_sprite_model: None,
+            dynamic_model: None,
+            show_colliders: false,
+            profiler: profiler::Profiler::new(),
+            raw_input: raw_input::RawInputGraph::new(),
+            net_graph: net_graph::NetGraph::new(),
+            top_down: false,
+            tilt: camera::CameraTilt::new(),
+            shake: camera::CameraShake::new(),
+            third_person: false,
+            boom: camera::CameraBoom::new(settings.camera_spring),
+            net_conditions: net_sim::Conditions::default(),
+            debug_model: None,
+            ai_inspector: ai_debug::AiInspector::new(),
+            inspector_model: None,
+            decal_material,
+            enemy_sprite_material,
+            settings,
+            aim: None,
+            map,
+            dog_texture,
+        };
+        state.refresh_colliders();
+        state.camera.position = state.map.spawn_point() + glam::Vec3::Y * 1.8;
+        state.apply_perks();
+        state.inventory.refill();
+        state
+    }
+
+    // 切换到另一张地图：重新生成模型、碰撞器和触发区域，玩家回到出生点
+    fn load_map(&mut self, name: &str) {
+        let map = match map::Map::load(name) {
+            Ok(map) => map,
+            Err(e) => {
+                eprintln!("无法加载地图 {}: {}", name, e);
+                self.menu.show();
+                self.menu.set_message(format!("cannot load map: {}", name));
+                return;
+            }
+        };
+        println!("加载地图: {} ({}x{})", map.title, map.width, map.height);
+        // 先释放旧地图的资源再生成新的，显存里不会同时有两张地图
+        self.unload_map();
+        self.level.load(&self.device, &self.queue, &map, &self.dog_texture);
+        self.lighting.load(&self.device, &self.queue, &map);
+        self.reflection.capture(
+            &self.device,
+            &self.queue,
+            &self.layouts,
+            &map,
+            &self.level_material.bind_group,
+            &self.object_bind_group,
+            &self.level.models,
+            CLEAR_COLOR,
+            &self.lighting,
+        );
+        // 光照贴图换成了新的纹理
+        self.frame = bindings::FrameBinding::new(&self.device, &self.layouts, &self.reflection.environment(), &self.lighting.resources(), "frame");
+        door::spawn(&mut self.world, &map);
+        self.light_fixtures = light_fixtures::LightFixtures::new(&map);
+        self.alarms = alarm::AlarmSystem::new(&map);
+        self.refresh_colliders();
+        self.hazards = hazard::HazardSystem::new(&map);
+        self.ambient_emitters = ambient_emitters(&map, &self.hazards);
+        self.triggers = trigger::TriggerSystem::new(map.trigger_zones());
+        self.tutorial = tutorial::Tutorial::new(map.prompts.clone());
+        self.tutorial_exit_timer = None;
+        self.camera.position = map.spawn_point() + glam::Vec3::Y * 1.8;
+        self.camera.yaw = 0.0;
+        self.camera.pitch = 0.0;
+        self.tilt.reset();
+        self.shake.reset();
+        self.recoil.reset();
+        self.player.respawn();
+        self.apply_perks();
+        self.inventory.refill();
+        self.refill_grenades();
+        self.melee.reset();
+        self.particle_effects.set_emitters(particle_emitters(&map, &self.hazards));
+        self.enemies = enemy::EnemyManager::new(&mut self.world, &map, self.settings.gore);
+        self.range = practice::PracticeRange::new(&mut self.world, &map);
+        dog::spawn(&mut self.world, &map);
+        self.pickups = pickup::PickupManager::new(&mut self.world, &map, &self.weapon_defs);
+        self.post.set_lut(&self.device, &self.queue, map.lut.as_deref());
+        self.night_vision.reset();
+        self.time_scale.reset();
+        self.post.set_night_vision(false);
+        self.post.configure(&self.device, &self.settings, self.config.width, self.config.height, &self.depth_texture.view);
+        self.map = map;
+        self.waves = None;
+        self.shop.open = false;
+        self.loadout_screen.open = false;
+        self.awaiting_loadout = false;
+    }
+    
+    // 释放当前地图的几何体、碰撞器、贴花、粒子和所有实体，以及由它们生成的每帧模型
+    fn unload_map(&mut self) {
+        self.level.unload();
+        self.colliders.clear();
+        self.decals.clear();
+        self.particle_effects.clear(&self.device, &self.queue);
+        self.world.unload();
+        self.aim = None;
+        self.dynamic_model = None;
+        self.dog_model = None;
+        self.enemy_sprite_model = None;
+        self.debug_model = None;
+        self.inspector_model = None;
+        self.outline.clear();
+        self.grenades.clear();
+        self.tracers.clear();
+        self.weapon_fx.clear();
+        self.casings.clear();
+    }
+
+    fn start_benchmark(&mut self, map_name: &str) {
+        if self.map.name != map_name {
+            self.load_map(map_name);
+        }
+        self.menu.hide();
+        self.config.present_mode = pacing::present_mode(settings::FrameLimit::Unlimited, &self.present_modes);
+        self.surface.configure(&self.device, &self.config);
+        self.benchmark = Some(benchmark::Benchmark::new(&self.map, &self.adapter_name));
+    }
+
+    fn start_determinism(&mut self, map_name: &str, baseline: Option<Vec<u64>>) {
+        self.determinism = Some(determinism::DeterminismTest::new(map_name, baseline));
+        self.begin_determinism_run();
+    }
+
+    // 每轮开始时松开所有按键、换回开局的武器，固定随机数的种子以后重新加载地图，两轮从同样的状态出发
+    fn begin_determinism_run(&mut self) {
+        let Some(map_name) = self.determinism.as_ref().map(|test| test.map.clone()) else { return };
+        for action in self.held_actions.clone() {
+            self.release_action(action);
+        }
+        self.camera_controller.reset_motion();
+        self.loadout = None;
+        self.inventory = inventory::Inventory::new();
+        self.inventory.add(self.weapon_defs.first().cloned().expect("没有可用的武器"));
+        fastrand::seed(determinism::SEED);
+        self.load_map(&map_name);
+        if self.map.name != map_name {
+            eprintln!("无法开始确定性测试: 地图 {} 加载失败", map_name);
+            self.determinism = None;
+            self.quit_requested = true;
+            return;
+        }
+        self.menu.hide();
+    }
+
+    // 每个渲染帧按脚本的输入模拟 TICKS_PER_FRAME 次，每次之后记下校验和，两轮都跑完时写出结果并退出
+    fn update_determinism(&mut self) {
+        for _ in 0..determinism::TICKS_PER_FRAME {
+            let Some(test) = self.determinism.as_ref().filter(|test| !test.finished()) else { return };
+            let input = test.input().clone();
+            for action in self.held_actions.clone() {
+                if !input.held.contains(&action) {
+                    self.release_action(action);
+                }
+            }
+            for action in input.held {
+                self.press_action(action);
+            }
+            self.camera.yaw = input.yaw;
+            self.camera.pitch = input.pitch;
+            self.update(pacing::TICK);
+            let checksum = self.checksum();
+            let Some(test) = &mut self.determinism else { return };
+            match test.record(checksum) {
+                determinism::Step::Continue => {}
+                determinism::Step::Restart => self.begin_determinism_run(),
+                determinism::Step::Finished => {
+                    match test.write_report() {
+                        Ok(path) => println!("确定性测试结果: {}", path.display()),
+                        Err(e) => eprintln!("无法写出确定性测试结果: {}", e),
+                    }
+                    self.quit_requested = true;
+                    return;
+                }
+            }
+        }
+    }
+
+    // 确定性测试比较的状态：玩家、所有实体（包括狗和飞行中的投射物）和手雷
+    fn checksum(&self) -> u64 {
+        let mut sum = determinism::Checksum::new();
+        sum.write_vec3(self.camera.position);
+        sum.write_f32(self.camera.yaw);
+        sum.write_f32(self.camera.pitch);
+        sum.write_f32(self.player.health);
+        if let Some(weapon) = self.inventory.current() {
+            sum.write_u32(weapon.ammo());
+        }
+        for (entity, transform) in self.world.transforms.iter() {
+            sum.write_u32(entity.index() as u32);
+            sum.write_vec3(transform.position);
+            sum.write_f32(transform.yaw);
+        }
+        for (entity, health) in self.world.healths.iter() {
+            sum.write_u32(entity.index() as u32);
+            sum.write_f32(health.current);
+        }
+        for (_, body) in self.world.bodies.iter() {
+            sum.write_vec3(body.velocity);
+        }
+        for position in self.grenades.positions() {
+            sum.write_vec3(position);
+        }
+        sum.finish()
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.size = new_size;
+            self.config.width = new_size.width;
+            self.config.height = new_size.height;
+            self.surface.configure(&self.device, &self.config);
+            self.depth_texture = texture::Texture::create_depth_texture(
+                &self.device,
+                &self.config,
+                "depth_texture"
+            );
+            self.post.configure(&self.device, &self.settings, self.config.width, self.config.height, &self.depth_texture.view);
+        }
+    }
+    
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        // 确定性测试时只有脚本的输入，关窗口、改大小和全屏照常处理
+        if self.determinism.is_some() {
+            return false;
+        }
+        if let WindowEvent::Touch(touch) = event {
+            return self.touch_input(touch);
+        }
+        if matches!(event, WindowEvent::KeyboardInput { .. } | WindowEvent::MouseInput { .. }) {
+            self.input_device = input::Device::KeyboardMouse;
+        }
+        if self.menu.open {
+            return self.menu_input(event);
+        }
+        if self.chat.open {
+            return self.chat_input(event);
+        }
+        if self.inventory_screen.open {
+            return self.inventory_input(event);
+        }
+        if self.shop.open {
+            return self.shop_input(event);
+        }
+        if self.loadout_screen.open {
+            return self.loadout_input(event);
+        }
+        match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::Escape),
+                    ..
+                },
+                ..
+            } => {
+                // 游戏中按 Esc 打开菜单
+                self.menu.show();
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::F),
+                    ..                    
+                },
+                ..                
+            } => {
+                // Return true to indicate we've handled the F key press
+                // The actual fullscreen toggle is handled in the main event loop
+                false
+            }
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::F3),
+                    ..
+                },
+                ..
+            } => {
+                // 调试：显示碰撞体的线框
+                self.show_colliders = !self.show_colliders;
+                println!("碰撞体调试显示: {}", if self.show_colliders { "开" } else { "关" });
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::F4),
+                    ..
+                },
+                ..
+            } => {
+                // 调试：帧时间和显存占用
+                self.profiler.open = !self.profiler.open;
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::F6),
+                    ..
+                },
+                ..
+            } => {
+                // 调试：鼠标每帧的移动量
+                self.raw_input.open = !self.raw_input.open;
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::F7),
+                    ..
+                },
+                ..
+            } => {
+                // 调试：联机的延迟和丢包
+                self.net_graph.open = !self.net_graph.open;
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::F8),
+                    ..
+                },
+                ..
+            } => {
+                // 调试：从天花板往下看，只换画面用的相机，操作还是原来的视角
+                self.top_down = !self.top_down;
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::F9),
+                    ..
+                },
+                ..
+            } => {
+                // 第三人称：瞄准和射击还是从眼睛出发，只有画面退到肩膀后面
+                self.third_person = !self.third_person;
+                self.boom.reset();
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key @ (VirtualKeyCode::Left | VirtualKeyCode::Right | VirtualKeyCode::Up | VirtualKeyCode::Down | VirtualKeyCode::Back)),
+                    ..
+                },
+                ..
+            } if self.demo.is_some() => {
+                self.demo_key(*key);
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key @ (VirtualKeyCode::Key1 | VirtualKeyCode::Key2 | VirtualKeyCode::Key3 | VirtualKeyCode::Key4)),
+                    ..
+                },
+                ..
+            } if self.match_summary.is_some() => {
+                // 数字键直接投给对应的地图
+                let index = *key as usize - VirtualKeyCode::Key1 as usize;
+                self.vote(Some(index));
+                true
+            }
+            _ => self.action_input(event, true),
+        }
+    }
+
+    // 按键绑定到游戏操作；allow_press 为 false 时只处理松开（菜单、聊天框打开时）
+    fn action_input(&mut self, event: &WindowEvent, allow_press: bool) -> bool {
+        let Some((binding, pressed)) = input::Binding::from_event(event) else { return false };
+        let Some(action) = self.input_config.action_for(binding) else { return false };
+        if !pressed {
+            self.release_action(action);
+            return true;
+        }
+        if !allow_press {
+            return false;
+        }
+        self.press_action(action);
+        true
+    }
+
+    fn release_action(&mut self, action: input::Action) {
+        self.held_actions.retain(|held| *held != action);
+        self.camera_controller.set_action(action, false);
+    }
+
+    fn press_action(&mut self, action: input::Action) {
+        // 按住时的按键重复不算新的操作
+        if !self.held_actions.contains(&action) {
+            self.held_actions.push(action);
+            if self.match_summary.is_some() && self.vote_action(action) {
+                return;
+            }
+            if self.spectator.active && self.spectate_action(action) {
+                return;
+            }
+            match action {
+                input::Action::Chat => self.chat.open(),
+                input::Action::Fire => self.player.end_protection(),
+                input::Action::Melee => self.melee_attack(),
+                input::Action::Minimap => self.minimap.toggle(),
+                input::Action::Use => self.interact(),
+                input::Action::Drop => self.drop_weapon(),
+                input::Action::Grenade => self.throw_grenade(),
+                input::Action::Weapon1 => self.switch_weapon(|inventory| inventory.select(0)),
+                input::Action::Weapon2 => self.switch_weapon(|inventory| inventory.select(1)),
+                input::Action::Weapon3 => self.switch_weapon(|inventory| inventory.select(2)),
+                input::Action::NextWeapon => self.switch_weapon(|inventory| inventory.cycle(1)),
+                input::Action::PrevWeapon => self.switch_weapon(|inventory| inventory.cycle(-1)),
+                input::Action::Reload => {
+                    if let Some(weapon) = self.inventory.current_mut() {
+                        weapon.reload();
+                    }
+                }
+                input::Action::Inventory => self.toggle_inventory(),
+                input::Action::Shop => self.toggle_shop(),
+                input::Action::NightVision => self.toggle_night_vision(),
+                _ => {}
+            }
+            self.events.emit(events::GameEvent::ActionPerformed { action });
+        }
+        self.camera_controller.set_action(action, true);
+    }
+
+    // 比赛结束的画面里前后移动选择地图，跳跃或开火键投票，返回 false 表示这个操作照常处理
+    fn vote_action(&mut self, action: input::Action) -> bool {
+        let Some(screen) = &mut self.match_summary else { return false };
+        match action {
+            input::Action::MoveForward => screen.move_selection(false),
+            input::Action::MoveBack => screen.move_selection(true),
+            input::Action::Jump | input::Action::Fire => self.vote(None),
+            _ => return false,
+        }
+        true
+    }
+
+    fn vote(&mut self, index: Option<usize>) {
+        let Some(choice) = self.match_summary.as_mut().and_then(|screen| screen.vote(index)) else { return };
+        self.session.vote(choice);
+    }
+
+    // 观战时开火、换弹、使用和跳跃键用来换人、切换自由相机和进场，返回 false 表示这个操作照常处理
+    fn spectate_action(&mut self, action: input::Action) -> bool {
+        let players = self.other_players();
+        match action {
+            // 回放录像时跳跃键暂停
+            input::Action::Jump if self.demo.is_some() => {
+                if let Some(demo) = &mut self.demo {
+                    demo.paused = !demo.paused;
+                }
+            }
+            input::Action::Fire => self.spectator.cycle(&players, true),
+            input::Action::Reload => self.spectator.cycle(&players, false),
+            input::Action::Use => self.spectator.toggle_free(&players),
+            input::Action::Jump => {
+                // 死亡时要等重生倒计时结束
+                if self.player.respawn_in().is_some_and(|seconds| seconds > 0.0) {
+                    return true;
+                }
+                if self.loadouts_enabled() {
+                    self.open_loadouts();
+                    return true;
+                }
+                self.spectator.stop();
+                self.respawn();
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // 其他玩家的状态：回放录像时来自录像，联机时来自快照
+    fn other_players(&self) -> Vec<(u32, snapshot::PlayerState)> {
+        match &self.demo {
+            Some(demo) => demo.players(),
+            None => self.session.remote_players(),
+        }
+    }
+
+    fn player_name(&self, player_id: u32) -> String {
+        match &self.demo {
+            Some(demo) => demo.player_name(player_id),
+            None => self.session.player_name(player_id),
+        }
+    }
+
+    // 回放录像时方向键拖动时间轴和改速度，退格键结束回放
+    fn demo_key(&mut self, key: VirtualKeyCode) {
+        let Some(demo) = &mut self.demo else { return };
+        match key {
+            VirtualKeyCode::Left => demo.seek(-demo::SEEK_STEP),
+            VirtualKeyCode::Right => demo.seek(demo::SEEK_STEP),
+            VirtualKeyCode::Up => demo.change_speed(true),
+            VirtualKeyCode::Down => demo.change_speed(false),
+            VirtualKeyCode::Back => self.stop_demo(),
+            _ => {}
+        }
+    }
+
+    fn stop_demo(&mut self) {
+        if self.demo.take().is_some() {
+            self.spectator.stop();
+            self.respawn();
+        }
+    }
+
+    // 触屏：菜单和物品栏里点一下选择，游戏中是虚拟摇杆和按钮
+    fn touch_input(&mut self, touch: &winit::event::Touch) -> bool {
+        let (width, height) = (self.config.width as f32, self.config.height as f32);
+        if self.menu.open || self.inventory_screen.open || self.shop.open || self.loadout_screen.open || self.chat.open {
+            let released = self.touch.release_all();
+            self.apply_touch(released);
+            if touch.phase != winit::event::TouchPhase::Started {
+                return true;
+            }
+            let (x, y) = (touch.location.x as f32, touch.location.y as f32);
+            if self.menu.open {
+                if let Some(action) = self.menu.tap(x, y, width, height, self.session.is_online()) {
+                    self.apply_menu_action(action);
+                }
+            } else if self.inventory_screen.open {
+                self.inventory_screen.navigate(ui::Nav::Back, &mut self.inventory);
+            } else if self.shop.open {
+                if let Some(choice) = self.shop.click(x, y, width, height) {
+                    self.shop_choice(choice);
+                }
+            } else if self.loadout_screen.open {
+                self.loadout_action(|screen, loadouts, weapons, progression| screen.click(x, y, width, height, loadouts, weapons, progression));
+            }
+            return true;
+        }
+        let inputs = self.touch.handle(touch, width, height);
+        self.apply_touch(inputs);
+        true
+    }
+
+    fn apply_touch(&mut self, inputs: Vec<touch::TouchInput>) {
+        for input in inputs {
+            match input {
+                touch::TouchInput::Press(action) => self.press_action(action),
+                touch::TouchInput::Release(action) => self.release_action(action),
+                touch::TouchInput::Move(stick) => self.camera_controller.set_move_stick(stick.x, stick.y),
+                touch::TouchInput::Look(dx, dy) => self.process_mouse(dx, dy),
+            }
+        }
+    }
+    
+    // 物品栏打开时的输入处理，其他游戏操作只处理松开
+    fn inventory_input(&mut self, event: &WindowEvent) -> bool {
+        if let Some((binding, true)) = input::Binding::from_event(event) {
+            if self.input_config.action_for(binding) == Some(input::Action::Inventory) {
+                self.toggle_inventory();
+                return true;
+            }
+        }
+        let nav = match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput { state: ElementState::Pressed, virtual_keycode: Some(keycode), .. },
+                ..
+            } => match keycode {
+                VirtualKeyCode::Up | VirtualKeyCode::W => Some(ui::Nav::Up),
+                VirtualKeyCode::Down | VirtualKeyCode::S => Some(ui::Nav::Down),
+                VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => Some(ui::Nav::Select),
+                VirtualKeyCode::Escape => Some(ui::Nav::Back),
+                _ => None,
+            },
+            _ => None,
+        };
+        match nav {
+            Some(nav) => {
+                self.inventory_screen.navigate(nav, &mut self.inventory);
+                true
+            }
+            None => self.action_input(event, false),
+        }
+    }
+
+    // 打开物品栏时松开所有按住的操作，避免关上后角色还在移动或开火
+    fn toggle_inventory(&mut self) {
+        self.inventory_screen.toggle();
+        if self.inventory_screen.open {
+            for action in std::mem::take(&mut self.held_actions) {
+                self.camera_controller.set_action(action, false);
+            }
+        }
+    }
+
+    // 商店打开时的输入处理：键盘选择、鼠标指向和点击，其他游戏操作只处理松开
+    fn shop_input(&mut self, event: &WindowEvent) -> bool {
+        let (width, height) = (self.config.width as f32, self.config.height as f32);
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = (position.x as f32, position.y as f32);
+                self.shop.hover(self.cursor.0, self.cursor.1, width, height);
+                return true;
+            }
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: winit::event::MouseButton::Left, .. } => {
+                if let Some(choice) = self.shop.click(self.cursor.0, self.cursor.1, width, height) {
+                    self.shop_choice(choice);
+                }
+                return true;
+            }
+            _ => {}
+        }
+        if let Some((binding, true)) = input::Binding::from_event(event) {
+            if self.input_config.action_for(binding) == Some(input::Action::Shop) {
+                self.toggle_shop();
+                return true;
+            }
+        }
+        let nav = match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput { state: ElementState::Pressed, virtual_keycode: Some(keycode), .. },
+                ..
+            } => match keycode {
+                VirtualKeyCode::Up | VirtualKeyCode::W => Some(ui::Nav::Up),
+                VirtualKeyCode::Down | VirtualKeyCode::S => Some(ui::Nav::Down),
+                VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => Some(ui::Nav::Select),
+                VirtualKeyCode::Escape => Some(ui::Nav::Back),
+                _ => None,
+            },
+            _ => None,
+        };
+        match nav {
+            Some(nav) => {
+                if let Some(choice) = self.shop.navigate(nav) {
+                    self.shop_choice(choice);
+                }
+                true
+            }
+            None => self.action_input(event, false),
+        }
+    }
+
+    // 只有波次模式的购买阶段能打开商店，打开时和物品栏一样松开所有按住的操作
+    fn toggle_shop(&mut self) {
+        if !self.shop.open {
+            match &self.waves {
+                Some(waves) if waves.is_buy_phase() => {}
+                Some(_) => {
+                    self.hud.show_message("SHOP OPENS BETWEEN WAVES", [1.0, 0.85, 0.2, 1.0]);
+                    return;
+                }
+                None => return,
+            }
+        }
+        self.shop.toggle();
+        if self.shop.open {
+            for action in std::mem::take(&mut self.held_actions) {
+                self.camera_controller.set_action(action, false);
+            }
+        }
+    }
+
+    // 出生配置界面打开时的输入处理，和商店一样
+    fn loadout_input(&mut self, event: &WindowEvent) -> bool {
+        let (width, height) = (self.config.width as f32, self.config.height as f32);
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = (position.x as f32, position.y as f32);
+                let profile = self.profile.lock().unwrap();
+                self.loadout_screen.hover(&profile.loadouts, self.cursor.0, self.cursor.1, width, height);
+                return true;
+            }
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: winit::event::MouseButton::Left, .. } => {
+                let (x, y) = self.cursor;
+                self.loadout_action(|screen, loadouts, weapons, progression| screen.click(x, y, width, height, loadouts, weapons, progression));
+                return true;
+            }
+            _ => {}
+        }
+        let nav = match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput { state: ElementState::Pressed, virtual_keycode: Some(keycode), .. },
+                ..
+            } => match keycode {
+                VirtualKeyCode::Up | VirtualKeyCode::W => Some(ui::Nav::Up),
+                VirtualKeyCode::Down | VirtualKeyCode::S => Some(ui::Nav::Down),
+                VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => Some(ui::Nav::Select),
+                VirtualKeyCode::Escape => Some(ui::Nav::Back),
+                _ => None,
+            },
+            _ => None,
+        };
+        match nav {
+            Some(nav) => {
+                self.loadout_action(|screen, loadouts, weapons, progression| screen.navigate(nav, loadouts, weapons, progression));
+                true
+            }
+            None => self.action_input(event, false),
+        }
+    }
+
+    // 波次模式用商店里买的武器，回放录像时不进场，这两种情况下不选出生配置
+    fn loadouts_enabled(&self) -> bool {
+        self.waves.is_none() && self.demo.is_none()
+    }
+
+    // 打开出生配置界面，和商店一样松开所有按住的操作
+    fn open_loadouts(&mut self) {
+        self.loadout_screen.show(&self.profile.lock().unwrap().loadouts);
+        for action in std::mem::take(&mut self.held_actions) {
+            self.camera_controller.set_action(action, false);
+        }
+    }
+
+    // 在出生配置界面上操作，改了预设时保存档案
+    fn loadout_action(
+        &mut self,
+        action: impl FnOnce(&mut loadout::LoadoutScreen, &mut loadout::Loadouts, &[weapon::WeaponStats], &progression::Progression) -> Option<loadout::LoadoutChoice>,
+    ) {
+        let choice = {
+            let mut profile = self.profile.lock().unwrap();
+            let profile = &mut *profile;
+            action(&mut self.loadout_screen, &mut profile.loadouts, &self.weapon_defs, &profile.progression)
+        };
+        match choice {
+            Some(loadout::LoadoutChoice::Changed) => self.profile.lock().unwrap().save(&paths::profile()),
+            Some(loadout::LoadoutChoice::Deploy) => self.deploy(),
+            None => {}
+        }
+    }
+
+    // 用选中的出生配置进场：单机直接用，主机按自己的规则检查，客户端发给主机，等它发回来再进场
+    fn deploy(&mut self) {
+        let Some(loadout) = self.profile.lock().unwrap().loadouts.active().cloned() else { return };
+        let allowed = match &mut self.session {
+            net::Session::Client(client) => {
+                client.send_loadout(&loadout);
+                self.awaiting_loadout = true;
+                self.loadout_screen.set_message("WAITING FOR SERVER", false);
+                return;
+            }
+            net::Session::Host(_) => server_config::current().allowed_weapons.clone(),
+            net::Session::Offline => Vec::new(),
+        };
+        self.start_loadout(loadout::enforce(&loadout, &self.weapon_defs, &allowed));
+    }
+
+    // 单机时等重生倒计时结束由 update 重生，观战中的联机玩家马上进场
+    fn start_loadout(&mut self, loadout: loadout::Loadout) {
+        println!("出生配置: {}，{} + {}", loadout.name, loadout.primary, loadout.secondary);
+        self.loadout = Some(loadout);
+        self.loadout_screen.open = false;
+        if self.spectator.active {
+            self.spectator.stop();
+            self.respawn();
+        }
+    }
+
+    fn toggle_night_vision(&mut self) {
+        if !self.player.is_alive() || self.spectator.active {
+            return;
+        }
+        if !self.night_vision.toggle() {
+            self.hud.show_message("NIGHT VISION BATTERY LOW", [0.95, 0.2, 0.15, 1.0]);
+            return;
+        }
+        self.apply_night_vision();
+    }
+
+    // 后处理跟着夜视仪开关，要不要离屏纹理可能变了
+    fn apply_night_vision(&mut self) {
+        self.post.set_night_vision(self.night_vision.is_on());
+        self.post.configure(&self.device, &self.settings, self.config.width, self.config.height, &self.depth_texture.view);
+    }
+
+    // 夜视仪耗电和嗡嗡声，死了就关掉
+    fn update_night_vision(&mut self, dt: f32) {
+        if self.night_vision.is_on() && !self.player.is_alive() {
+            self.night_vision.toggle();
+            self.apply_night_vision();
+        }
+        match self.night_vision.update(dt) {
+            Some(night_vision::NightVisionUpdate::Hum) => self.audio.play_local(audio::SoundKind::Hum, 0.5),
+            Some(night_vision::NightVisionUpdate::Depleted) => {
+                self.hud.show_message("NIGHT VISION BATTERY EMPTY", [0.95, 0.2, 0.15, 1.0]);
+                self.apply_night_vision();
+            }
+            None => {}
+        }
+    }
+
+    // 在商店里买东西或者提前开始下一波
+    fn shop_choice(&mut self, choice: shop::ShopChoice) {
+        let Some(waves) = &mut self.waves else { return };
+        let index = match choice {
+            shop::ShopChoice::StartWave => {
+                waves.ready();
+                return;
+            }
+            shop::ShopChoice::Buy(index) => index,
+        };
+        let Some(item) = self.shop.items.get(index).cloned() else { return };
+        if waves.money() < item.price {
+            self.shop.set_message("NOT ENOUGH MONEY", true);
+            return;
+        }
+        let progression = self.profile.lock().unwrap().progression.clone();
+        let bought = match &item.good {
+            shop::ShopGood::Weapon { weapon } if self.inventory.has_weapon(weapon) => Err("ALREADY OWNED"),
+            shop::ShopGood::Weapon { weapon } => match weapon::find_weapon(&self.weapon_defs, weapon) {
+                Some(stats) => {
+                    self.inventory.add(stats.clone());
+                    Ok(())
+                }
+                None => {
+                    eprintln!("商品 {} 用了未知的武器 {}", item.id, weapon);
+                    Err("NOT AVAILABLE")
+                }
+            },
+            shop::ShopGood::Ammo if self.inventory.refill() => Ok(()),
+            shop::ShopGood::Ammo => Err("AMMO FULL"),
+            shop::ShopGood::Armor { .. } if self.player.armor_full() => Err("ARMOR FULL"),
+            shop::ShopGood::Armor { amount } => {
+                self.player.add_armor(*amount);
+                Ok(())
+            }
+            shop::ShopGood::Perk { perk } if waves.add_perk(&progression, *perk) => Ok(()),
+            shop::ShopGood::Perk { .. } => Err("MAX RANK"),
+        };
+        match bought {
+            Ok(()) => {
+                waves.spend(item.price);
+                println!("买了 {} (${})", item.name, item.price);
+                self.shop.set_message(&format!("BOUGHT {}", item.name.to_uppercase()), false);
+                if matches!(item.good, shop::ShopGood::Perk { .. }) {
+                    self.apply_perks();
+                }
+            }
+            Err(reason) => self.shop.set_message(reason, true),
+        }
+    }
+
+    // 单人游戏时打开物品栏暂停游戏
+    fn is_paused(&self) -> bool {
+        self.inventory_screen.open && !self.session.is_online()
+    }
+
+    // 菜单打开时的输入处理
+    fn menu_input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(keycode),
+                    ..
+                },
+                ..
+            } => {
+                let (handled, action) = self.menu.handle_key(*keycode, self.session.is_online());
+                if let Some(action) = action {
+                    self.apply_menu_action(action);
+                }
+                handled
+            }
+            WindowEvent::ReceivedCharacter(c) => {
+                self.menu.handle_char(*c);
+                true
+            }
+            // 松开按键仍要处理，避免打开菜单时移动状态卡住
+            _ => self.action_input(event, false),
+        }
+    }
+
+    // 聊天框打开时的输入处理
+    fn chat_input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(keycode),
+                    ..
+                },
+                ..
+            } => match keycode {
+                VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                    match self.chat.submit() {
+                        Some(chat::ChatCommand::Send(text)) => {
+                            let name = net::local_player_name();
+                            if self.session.send_chat(&name, &text) {
+                                self.chat.receive(&name, &text);
+                            }
+                        }
+                        Some(chat::ChatCommand::Mute(name)) => self.chat.mute(&name),
+                        Some(chat::ChatCommand::Unmute(name)) => self.chat.unmute(&name),
+                        Some(chat::ChatCommand::LoadMap(name)) => self.load_map(&name),
+                        Some(chat::ChatCommand::Inspect) => {
+                            self.ai_inspector.open = !self.ai_inspector.open;
+                            self.chat.push_system(format!("ai inspector {}", if self.ai_inspector.open { "on" } else { "off" }));
+                        }
+                        None => {}
+                    }
+                    true
+                }
+                VirtualKeyCode::Escape => {
+                    self.chat.cancel();
+                    true
+                }
+                VirtualKeyCode::Back => {
+                    self.chat.backspace();
+                    true
+                }
+                // F 键等全局快捷键在打字时不生效
+                _ => true,
+            },
+            WindowEvent::ReceivedCharacter(c) => {
+                self.chat.handle_char(*c);
+                true
+            }
+            _ => self.action_input(event, false),
+        }
+    }
+
+    fn apply_menu_action(&mut self, action: menu::MenuAction) {
+        // 开始别的游戏时结束回放
+        let starts_game = matches!(
+            action,
+            menu::MenuAction::Play
+                | menu::MenuAction::Tutorial
+                | menu::MenuAction::PracticeRange
+                | menu::MenuAction::Waves
+                | menu::MenuAction::Host
+                | menu::MenuAction::Join(_)
+                | menu::MenuAction::PlayMap(_)
+                | menu::MenuAction::PlayDemo(_)
+        );
+        if starts_game {
+            self.stop_demo();
+            // 离开波次模式时重新加载地图，敌人恢复成死后重新出现
+            if self.waves.is_some() {
+                let name = self.map.name.clone();
+                self.load_map(&name);
+            }
+        }
+        match action {
+            menu::MenuAction::Play => {
+                if self.map.name != map::DEFAULT_MAP {
+                    self.load_map(map::DEFAULT_MAP);
+                }
+                self.events.emit(events::GameEvent::MatchStarted);
+            }
+            menu::MenuAction::Resume => {}
+            menu::MenuAction::Tutorial => self.load_map(map::TUTORIAL_MAP),
+            menu::MenuAction::PracticeRange => self.load_map(map::RANGE_MAP),
+            menu::MenuAction::Waves => {
+                self.load_map(map::DEFAULT_MAP);
+                self.enemies.start_waves(&mut self.world);
+                self.waves = Some(waves::WaveMode::new());
+                println!("开始波次模式");
+                self.events.emit(events::GameEvent::MatchStarted);
+            }
+            menu::MenuAction::PlayDemo(index) => {
+                let Some(path) = demo::list().into_iter().nth(index) else { return };
+                match demo::Demo::load(&path) {
+                    Ok(loaded) => {
+                        println!("回放录像 {}", path.display());
+                        let map = loaded.header.map.clone();
+                        self.load_map(&map);
+                        let playback = demo::Playback::new(loaded);
+                        self.spectator.start(&self.camera, &playback.players());
+                        self.demo = Some(playback);
+                    }
+                    Err(e) => {
+                        eprintln!("无法回放录像 {}: {}", path.display(), e);
+                        self.menu.show();
+                        self.menu.set_message(format!("cannot play demo: {}", e));
+                    }
+                }
+            }
+            menu::MenuAction::UnlockPerk(perk) => {
+                let mut profile = self.profile.lock().unwrap();
+                if profile.progression.unlock(perk) {
+                    println!("解锁天赋: {} {} 级", perk.name(), profile.progression.rank(perk));
+                    profile.save(&paths::profile());
+                    self.menu.set_message("applies on next spawn".to_string());
+                }
+            }
+            menu::MenuAction::PlayMap(index) => {
+                if let Some(name) = mods::maps().get(index) {
+                    self.load_map(name);
+                    self.events.emit(events::GameEvent::MatchStarted);
+                }
+            }
+            menu::MenuAction::Host => {
+                self.session.close();
+                let name = format!("{}'s game", net::local_player_name());
+                match net::NetServer::host(&name, &self.map.name, &server_config::current().mode, self.net_conditions) {
+                    Ok(mut server) => {
+                        server.set_bot_spawns(self.bot_spawns());
+                        self.session = net::Session::Host(server);
+                        self.events.emit(events::GameEvent::MatchStarted);
+                    }
+                    Err(e) => {
+                        self.menu.show();
+                        self.menu.set_message(format!("cannot host: {}", e));
+                    }
+                }
+            }
+            menu::MenuAction::Join(addr) => {
+                self.session.close();
+                match net::NetClient::connect(addr, &net::local_player_name(), self.net_conditions) {
+                    Ok(client) => self.session = net::Session::Client(client),
+                    Err(e) => {
+                        self.menu.show();
+                        self.menu.set_message(format!("cannot connect: {}", e));
+                    }
+                }
+            }
+            menu::MenuAction::Disconnect => {
+                self.session.close();
+                self.events.emit(events::GameEvent::MatchEnded);
+            }
+            menu::MenuAction::ToggleGore => {
+                self.settings.gore = !self.settings.gore;
+                self.enemies.gore = self.settings.gore;
+                self.settings.save(&paths::settings());
+            }
+            menu::MenuAction::ToggleDepthOfField | menu::MenuAction::ToggleMotionBlur | menu::MenuAction::CycleEffectQuality => {
+                match action {
+                    menu::MenuAction::ToggleDepthOfField => self.settings.depth_of_field = !self.settings.depth_of_field,
+                    menu::MenuAction::ToggleMotionBlur => self.settings.motion_blur = !self.settings.motion_blur,
+                    _ => self.settings.effect_quality = self.settings.effect_quality.next(),
+                }
+                self.post.configure(&self.device, &self.settings, self.config.width, self.config.height, &self.depth_texture.view);
+                self.settings.save(&paths::settings());
+            }
+            menu::MenuAction::ToggleCameraTilt => {
+                self.settings.camera_tilt = !self.settings.camera_tilt;
+                self.settings.save(&paths::settings());
+            }
+            menu::MenuAction::ToggleStatusOverlays => {
+                self.settings.status_overlays = !self.settings.status_overlays;
+                self.settings.save(&paths::settings());
+            }
+            menu::MenuAction::ToggleTouchControls => {
+                self.settings.touch_controls = !self.settings.touch_controls;
+                self.touch.enabled = self.settings.touch_controls;
+                self.settings.save(&paths::settings());
+            }
+            menu::MenuAction::CycleAxis(target, field) => {
+                if let Some((guid, _)) = &self.active_gamepad {
+                    self.input_config.gamepads.entry(guid.clone()).or_default().cycle(target, field);
+                    self.input_config.save(&paths::input_config());
+                }
+            }
+            menu::MenuAction::ToggleMouseSmoothing | menu::MenuAction::ToggleMouseAcceleration => {
+                if action == menu::MenuAction::ToggleMouseSmoothing {
+                    self.settings.mouse_smoothing = !self.settings.mouse_smoothing;
+                } else {
+                    self.settings.mouse_acceleration = !self.settings.mouse_acceleration;
+                }
+                self.camera_controller.mouse_smoothing = self.settings.mouse_smoothing;
+                self.camera_controller.mouse_acceleration = self.settings.mouse_acceleration;
+                self.settings.save(&paths::settings());
+            }
+            menu::MenuAction::CycleMouseSensitivityX
+            | menu::MenuAction::CycleMouseSensitivityY
+            | menu::MenuAction::ToggleInvertMouseY
+            | menu::MenuAction::CycleStickSensitivityX
+            | menu::MenuAction::CycleStickSensitivityY
+            | menu::MenuAction::ToggleInvertStickY
+            | menu::MenuAction::CycleAimAssist
+            | menu::MenuAction::ToggleMouseAimAssist => {
+                let look = &mut self.settings.look;
+                match action {
+                    menu::MenuAction::CycleMouseSensitivityX => look.mouse_x = camera::next_sensitivity(look.mouse_x),
+                    menu::MenuAction::CycleMouseSensitivityY => look.mouse_y = camera::next_sensitivity(look.mouse_y),
+                    menu::MenuAction::ToggleInvertMouseY => look.invert_mouse_y = !look.invert_mouse_y,
+                    menu::MenuAction::CycleStickSensitivityX => look.stick_x = camera::next_sensitivity(look.stick_x),
+                    menu::MenuAction::CycleStickSensitivityY => look.stick_y = camera::next_sensitivity(look.stick_y),
+                    menu::MenuAction::ToggleInvertStickY => look.invert_stick_y = !look.invert_stick_y,
+                    menu::MenuAction::CycleAimAssist => self.settings.aim_assist = self.settings.aim_assist.next(),
+                    _ => self.settings.mouse_aim_assist = !self.settings.mouse_aim_assist,
+                }
+                self.camera_controller.look = self.settings.look;
+                self.settings.save(&paths::settings());
+            }
+            menu::MenuAction::CycleShellCasings => {
+                self.settings.shell_casings = casings::next_limit(self.settings.shell_casings);
+                self.settings.save(&paths::settings());
+            }
+            menu::MenuAction::CycleLighting => {
+                self.settings.lighting = self.settings.lighting.next();
+                self.settings.save(&paths::settings());
+            }
+            menu::MenuAction::CycleFrameLimit => {
+                self.settings.frame_limit = self.settings.frame_limit.next();
+                self.config.present_mode = pacing::present_mode(self.settings.frame_limit, &self.present_modes);
+                self.surface.configure(&self.device, &self.config);
+                self.settings.save(&paths::settings());
+            }
+            // 退出时的清理和保存在 shutdown 中完成
+            menu::MenuAction::Quit => self.quit_requested = true,
+        }
+    }
+
+    // 处理网络层事件
+    fn update_network(&mut self) {
+        let state = snapshot::PlayerState::new(
+            self.camera.position,
+            self.camera.yaw,
+            self.camera.pitch,
+            self.player.health,
+            self.player.armor,
+            // 中途加入还在观战的玩家不算在场上
+            self.player.is_alive() && !self.spectator.active,
+        );
+        self.session.set_local_player(state);
+        for event in self.session.poll() {
+            match event {
+                net::NetEvent::PlayerJoined { player_id, name } => {
+                    println!("玩家 {} (#{}) 加入了游戏", name, player_id);
+                    self.chat.push_system(format!("{} joined the game", name));
+                }
+                net::NetEvent::PlayerLeft { player_id, name } => {
+                    println!("玩家 {} (#{}) 离开了游戏", name, player_id);
+                    self.chat.push_system(format!("{} left the game", name));
+                }
+                net::NetEvent::Chat { player_id, name, text } => {
+                    println!("[聊天] {} (#{}): {}", name, player_id, text);
+                    self.chat.receive(&name, &text);
+                }
+                net::NetEvent::Connected { player_id, info } => {
+                    println!("已连接到 {}，地图 {}，模式 {}，玩家编号 {}", info.name, info.map, info.mode, player_id);
+                    self.events.emit(events::GameEvent::MatchStarted);
+                    // 中途加入时先观战，准备好了再进场
+                    self.spectator.start(&self.camera, &self.session.remote_players());
+                }
+                net::NetEvent::PlayerFired { player_id, weapon, origin, dir, targets } => {
+                    let Some(stats) = weapon::find_weapon(&self.weapon_defs, &weapon).cloned() else {
+                        eprintln!("玩家 #{} 使用了未知的武器 {}", player_id, weapon);
+                        continue;
+                    };
+                    let valid = match &mut self.session {
+                        net::Session::Host(server) => server.check_fire(player_id, origin, stats.fire_interval),
+                        _ => false,
+                    };
+                    if valid {
+                        self.resolve_player_shot(player_id, &stats, origin, dir, &targets);
+                    }
+                }
+                net::NetEvent::LoadoutRequested { player_id, loadout } => {
+                    let approved = loadout::enforce(&loadout, &self.weapon_defs, &server_config::current().allowed_weapons);
+                    if approved != loadout {
+                        println!("玩家 #{} 的出生配置不符合规则，改为 {} + {}", player_id, approved.primary, approved.secondary);
+                    }
+                    if let net::Session::Host(server) = &mut self.session {
+                        server.send_loadout(player_id, &approved);
+                    }
+                }
+                net::NetEvent::LoadoutApproved(loadout) => {
+                    if std::mem::take(&mut self.awaiting_loadout) {
+                        let requested = self.profile.lock().unwrap().loadouts.active().cloned();
+                        if requested.as_ref() != Some(&loadout) {
+                            self.hud.show_message("LOADOUT ADJUSTED BY SERVER", [1.0, 0.85, 0.2, 1.0]);
+                        }
+                        self.start_loadout(loadout);
+                    }
+                }
+                net::NetEvent::Damaged { amount, shooter, weapon } => {
+                    println!("被玩家 #{} 击中，伤害 {:.0}", shooter, amount);
+                    let kind = weapon::find_weapon(&self.weapon_defs, &weapon).map_or(damage::DamageType::Bullet, |stats| stats.damage_type);
+                    self.last_attacker = Some((shooter, weapon));
+                    self.pending_damage.lock().unwrap().push(damage::Damage::new(amount, kind));
+                }
+                net::NetEvent::Kill(kill) => self.push_kill(&kill),
+                net::NetEvent::MatchEnded(summary) => {
+                    println!("比赛结束，地图 {}", summary.map);
+                    self.events.emit(events::GameEvent::MatchEnded);
+                    self.match_summary = Some(match_end::SummaryScreen::new(summary));
+                }
+                net::NetEvent::Votes(counts) => {
+                    if let Some(screen) = &mut self.match_summary {
+                        screen.set_counts(counts);
+                    }
+                }
+                net::NetEvent::ChangeMap { map } => {
+                    self.match_summary = None;
+                    self.chat.push_system(format!("next map: {}", map));
+                    self.load_map(&map);
+                    self.spectator.stop();
+                    let spawns = self.bot_spawns();
+                    if let net::Session::Host(server) = &mut self.session {
+                        server.set_bot_spawns(spawns);
+                    }
+                    self.events.emit(events::GameEvent::MatchStarted);
+                }
+                net::NetEvent::HostMigrated { player_id, name } => {
+                    println!("主机离开，{} (#{}) 接手主机", name, player_id);
+                    self.chat.push_system(format!("host left, {} is now hosting", name));
+                }
+                net::NetEvent::BecameHost => {
+                    println!("主机离开，本机接手主机");
+                    self.chat.push_system("host left, you are now hosting".to_string());
+                    let spawns = self.bot_spawns();
+                    if let net::Session::Host(server) = &mut self.session {
+                        server.set_bot_spawns(spawns);
+                    }
+                }
+                net::NetEvent::Disconnected { reason } => {
+                    println!("连接断开: {}", reason);
+                    self.session = net::Session::Offline;
+                    self.awaiting_loadout = false;
+                    self.events.emit(events::GameEvent::MatchEnded);
+                    self.menu.show();
+                    self.menu.set_message(format!("disconnected: {}", reason));
+                }
+            }
+        }
+        // 离开联机（断开、自己退出）时不再观战，死亡的话照常倒计时重生
+        if self.spectator.active && !self.session.is_online() && self.demo.is_none() {
+            self.spectator.stop();
+        }
+        if !self.session.is_online() {
+            self.match_summary = None;
+        }
+    }
+
+    fn process_mouse(&mut self, dx: f64, dy: f64) {
+        if self.determinism.is_some() || self.menu.open || self.inventory_screen.open || self.shop.open || self.loadout_screen.open {
+            return;
+        }
+        self.camera_controller.process_mouse(dx, dy);
+    }
+    
+    #[cfg(feature = "gamepad")]
+    fn input_controller(&mut self, pad: &gilrs::Gamepad, event: &gilrs::EventType) {
+        if self.determinism.is_some() {
+            return;
+        }
+        // 按下按键或者推动摇杆时换成这个手柄的按键图标和摇杆设置，摇杆的轻微漂移不算
+        let touched = match event {
+            gilrs::EventType::ButtonPressed(..) => true,
+            gilrs::EventType::AxisChanged(_, value, _) => value.abs() > 0.5,
+            _ => false,
+        };
+        let guid = gamepad::guid(pad);
+        if touched {
+            self.input_device = input::Device::Gamepad(gamepad::Layout::detect(pad));
+            self.active_gamepad = Some((guid.clone(), pad.name().to_string()));
+        }
+        // 手柄的 Start 键打开菜单，Select 键打开/关闭物品栏
+        if let gilrs::EventType::ButtonPressed(button, _) = event {
+            if !self.menu.open && !self.inventory_screen.open && !self.shop.open && !self.loadout_screen.open {
+                match button {
+                    gilrs::Button::Start => {
+                        self.menu.show();
+                        self.camera_controller.release_controller();
+                        return;
+                    }
+                    gilrs::Button::Select => {
+                        self.toggle_inventory();
+                        return;
+                    }
+                    _ => {}
+                }
+            } else if *button == gilrs::Button::Select && self.inventory_screen.open {
+                self.toggle_inventory();
+                return;
+            } else if gamepad::action_for_button(*button) == Some(input::Action::Shop) && self.shop.open {
+                self.toggle_shop();
+                return;
+            }
+        }
+        if !self.menu.open && !self.inventory_screen.open && !self.shop.open && !self.loadout_screen.open {
+            let axes = self.input_config.gamepad_axes(&guid);
+            // 当作轴用的扳机不再触发它原来的按键操作
+            let button_action = |button: gilrs::Button| {
+                let used_as_axis = gamepad::trigger_source(button).is_some_and(|source| !axes.targets(source).is_empty());
+                if used_as_axis { None } else { gamepad::action_for_button(button) }
+            };
+            match event {
+                gilrs::EventType::ButtonPressed(button, _) => {
+                    if let Some(action) = button_action(*button) {
+                        self.press_action(action);
+                    }
+                }
+                gilrs::EventType::ButtonReleased(button, _) => {
+                    if let Some(action) = button_action(*button) {
+                        self.release_action(action);
+                    }
+                }
+                _ => {}
+            }
+            if let Some((source, value)) = gamepad::axis_value(event) {
+                for (target, binding) in axes.targets(source) {
+                    self.camera_controller.set_gamepad_axis(target, binding.apply(value));
+                }
+            }
+            return;
+        }
+        // 菜单、物品栏、商店或出生配置界面打开时：方向键和左摇杆移动焦点，A 键确定，B 键返回
+        self.camera_controller.release_controller();
+        let nav = match event {
+            gilrs::EventType::ButtonPressed(button, _) => gamepad::nav_for_button(*button),
+            gilrs::EventType::AxisChanged(gilrs::Axis::LeftStickY, value, _) => self.nav_stick.set(*value),
+            _ => None,
+        };
+        if let Some(nav) = nav {
+            self.navigate_ui(nav);
+        }
+    }
+
+    // 手柄操作菜单、物品栏、商店或出生配置界面
+    #[cfg(feature = "gamepad")]
+    fn navigate_ui(&mut self, nav: ui::Nav) {
+        if self.menu.open {
+            if let Some(action) = self.menu.handle_gamepad(nav, self.session.is_online()) {
+                self.apply_menu_action(action);
+            }
+        } else if self.inventory_screen.open {
+            self.inventory_screen.navigate(nav, &mut self.inventory);
+        } else if self.shop.open {
+            if let Some(choice) = self.shop.navigate(nav) {
+                self.shop_choice(choice);
+            }
+        } else if self.loadout_screen.open {
+            self.loadout_action(|screen, loadouts, weapons, progression| screen.navigate(nav, loadouts, weapons, progression));
+        }
+    }
+    
+    // 一次固定时长的模拟（pacing::TICK）
+    fn update(&mut self, dt: std::time::Duration) {
+        let previous_position = self.camera.position;
+        self.previous_camera_position = previous_position;
+        self.world.store_previous();
+
+        // 单人游戏打开物品栏时世界暂停
+        if !self.is_paused() {
+            door::update_system(&mut self.world, dt.as_secs_f32(), &mut self.anim_events);
+            self.dispatch_anim_events();
+            self.refresh_colliders();
+            self.update_player(dt.as_secs_f32());
+
+            // 死亡后等待重生时不能移动
+            if let Some(benchmark) = &mut self.benchmark {
+                benchmark.update(&mut self.camera, dt.as_secs_f32());
+            } else if let Some(camera) = self.spectator.free_camera_mut() {
+                self.camera_controller.fly(camera, dt);
+            } else if self.player.is_alive() && !self.spectator.active && !self.player.is_stunned() {
+                // 更新相机位置
+                self.camera_controller.sprint_blocked = self.player.sprint_blocked();
+                self.player.update_stamina(dt.as_secs_f32(), self.camera_controller.is_sprinting());
+                self.camera_controller.update_camera(&mut self.camera, dt);
+            
+                // 碰撞检测和响应
+                let player_radius = 0.5; // 玩家碰撞半径
+                let mut position = self.camera.position;
+            
+                // 对每个墙体进行碰撞检测
+                for collider in &self.colliders {
+                    position = collider.resolve_collision(position, player_radius);
+                }
+            
+                // 更新相机位置
+                self.camera.position = position;
+
+                // 按住开火键连续射击
+                if self.held_actions.contains(&input::Action::Fire) {
+                    self.fire();
+                }
+            }
+            self.update_projectiles(dt.as_secs_f32());
+            self.update_grenades(dt.as_secs_f32());
+            self.melee.update(dt.as_secs_f32());
+            self.tracers.update(dt.as_secs_f32());
+            self.weapon_fx.update(dt.as_secs_f32());
+            self.casings.update(dt.as_secs_f32(), &self.colliders, self.settings.shell_casings);
+            self.recoil.update(dt.as_secs_f32(), &mut self.camera);
+            self.inventory.update(dt.as_secs_f32());
+            self.decals.update(&self.device);
+        }
+        self.simulate(dt.as_secs_f32());
+        if !self.is_paused() {
+            self.update_waves(dt.as_secs_f32());
+            self.update_night_vision(dt.as_secs_f32());
+        }
+        self.update_audio(previous_position, dt.as_secs_f32());
+
+        self.update_network();
+        self.update_tutorial(dt.as_secs_f32());
+        self.process_events(dt.as_secs_f32());
+    }
+
+    // 每个渲染帧一次：转动视角，在上一次和最新一次模拟之间（alpha）插值出要画的位置，生成这一帧的模型
+    fn prepare_frame(&mut self, dt: std::time::Duration, alpha: f32) {
+        self.profiler.record(dt);
+        self.raw_input.end_frame(dt.as_secs_f32());
+        self.time_scale.update(dt.as_secs_f32());
+        if self.net_graph.open {
+            self.net_graph.update(dt.as_secs_f32(), self.session.stats());
+        }
+        #[cfg(feature = "gamepad")]
+        if self.menu.open || self.inventory_screen.open || self.shop.open || self.loadout_screen.open {
+            if let Some(nav) = self.nav_stick.update(dt.as_secs_f32()) {
+                self.navigate_ui(nav);
+            }
+        } else {
+            self.nav_stick.release();
+        }
+        if let Some(benchmark) = &mut self.benchmark {
+            if benchmark.record(dt.as_secs_f32()) {
+                match benchmark.write_report() {
+                    Ok(path) => println!("性能测试报告: {}", path.display()),
+                    Err(e) => eprintln!("无法写出性能测试报告: {}", e),
+                }
+                self.benchmark = None;
+                self.quit_requested = true;
+            }
+        } else if let Some(camera) = self.spectator.free_camera_mut() {
+            self.camera_controller.update_look(camera, dt, None);
+        } else if !self.is_paused() && self.player.is_alive() && !self.spectator.active && self.determinism.is_none() {
+            let assist = self.aim_assist();
+            self.camera_controller.update_look(&mut self.camera, dt, assist);
+        }
+        // 重生、换地图这种瞬移不插值
+        let mut view = self.camera.clone();
+        if self.previous_camera_position.distance(self.camera.position) < 2.0 {
+            view.position = self.previous_camera_position.lerp(self.camera.position, alpha);
+        }
+        let strafe = if self.is_paused() || !self.player.is_alive() { 0.0 } else { self.camera_controller.strafe_input() };
+        self.tilt.update(strafe, dt.as_secs_f32());
+        self.shake.update(dt.as_secs_f32());
+        if self.settings.camera_tilt {
+            let (yaw, pitch, roll) = self.shake.offset();
+            view.yaw += yaw;
+            view.pitch += pitch;
+            view.roll = self.tilt.roll() + roll;
+        }
+        if self.third_person && self.player.is_alive() {
+            view = self.boom.update(&view, &self.colliders, dt.as_secs_f32());
+        }
+        if let Some(demo) = &mut self.demo {
+            for event in demo.update(dt.as_secs_f32()) {
+                match event {
+                    demo::DemoEvent::PlayerJoined { name, .. } => self.chat.push_system(format!("{} joined the game", name)),
+                    demo::DemoEvent::PlayerLeft { name, .. } => self.chat.push_system(format!("{} left the game", name)),
+                    demo::DemoEvent::Chat { name, text } => self.chat.receive(&name, &text),
+                    demo::DemoEvent::Kill(kill) => self.push_kill(&kill),
+                }
+            }
+        }
+        if self.spectator.active {
+            view = self.spectator.update(&self.other_players(), dt.as_secs_f32());
+        }
+        if self.top_down {
+            view = camera::Camera::top_down(view.position, map::WALL_HEIGHT - 0.1, TOP_DOWN_SPAN);
+        }
+
+        if !self.is_paused() {
+            let mut boxes = self.enemies.boxes();
+            ecs::render_system(&self.world, alpha, &mut boxes);
+            self.light_fixtures.boxes(&mut boxes);
+            self.alarms.boxes(&mut boxes);
+            self.grenades.boxes(alpha, &mut boxes);
+            self.casings.boxes(alpha, &mut boxes);
+            self.particle_effects.boxes(&mut boxes);
+            // 跟随观战的玩家不画，相机在他身体里面
+            let followed = self.spectator.followed();
+            if self.third_person && self.player.is_alive() && !self.spectator.active {
+                enemy::push_player(&mut boxes, self.camera.position - glam::Vec3::Y * lag_comp::EYE_HEIGHT, self.camera.yaw);
+            }
+            for (_, remote) in self.other_players().into_iter().filter(|(player_id, remote)| remote.alive && Some(*player_id) != followed) {
+                enemy::push_player(&mut boxes, remote.position() - glam::Vec3::Y * lag_comp::EYE_HEIGHT, remote.yaw);
+            }
+            self.dynamic_model = (!boxes.is_empty()).then(|| model::create_boxes(&self.device, &boxes));
+            self.dog_model = dog::model(&self.world, &self.device, alpha, view.position);
+            self.enemy_sprite_model = enemy_sprites::model(&self.device, &self.world, alpha, view.position);
+            self.debug_model = self.show_colliders.then(|| model::create_collider_outlines(&self.device, &self.colliders));
+            self.update_aim();
+            self.update_inspector();
+            self.update_outlines(alpha, view.position);
+            if self.settings.depth_of_field {
+                let aim_distance = self.aim.as_ref().map(|aim| aim.distance);
+                let focus = post::focus_target(&self.colliders, aim_distance, view.position, view.forward());
+                self.post.update_focus(dt.as_secs_f32(), focus);
+            }
+        }
+
+        // 更新相机uniform
+        self.camera_uniform.update_view_proj(&view, self.config.width as f32 / self.config.height as f32);
+        self.level.transparent.sort(view.position);
+        self.view_camera = view;
+
+        self.menu.update();
+        self.hud.update();
+    }
+
+    // 互不依赖的系统作为任务并行执行，scope 返回时全部完成（之后才生成模型和渲染）。
+    // 小地图用的是更新之前狗的位置和门的状态
+    fn simulate(&mut self, dt: f32) {
+        let paused = self.is_paused();
+        let dog_positions = dog::positions(&self.world);
+        let doors = door::doors(&self.world);
+        let mut minimap_batch = ui::UiBatch::new(self.config.width, self.config.height);
+        // 玩家站在暗处时敌人看得没那么远
+        self.exposure.update(self.lighting.lights(), &self.colliders, self.camera.position, dt);
+        let sight_range = enemy::sight_range(self.exposure.value());
+        let (world, enemies, range, particle_effects) = (&mut self.world, &mut self.enemies, &mut self.range, &mut self.particle_effects);
+        let (map, colliders, camera) = (&self.map, &self.colliders, &self.camera);
+        self.jobs.scope(|jobs| {
+            if !paused {
+                jobs.spawn(|| {
+                    enemies.update(world, map, colliders, dt, camera.position, sight_range);
+                    range.update(world, dt);
+                    ecs::physics_system(world, dt, colliders);
+                    dog::update_system(world, dt, map, camera.position, colliders);
+                });
+            }
+            jobs.spawn(|| particle_effects.update(if paused { 0.0 } else { dt }, camera.position));
+            jobs.spawn(|| self.minimap.draw(&mut minimap_batch, map, &doors, &dog_positions, camera));
+        });
+        self.minimap_batch = minimap_batch;
+        // 烧死的敌人也算玩家的击杀
+        for kill in self.enemies.take_status_kills() {
+            self.events.emit(events::GameEvent::Kill { headshot: kill.headshot });
+            self.hud.push_kill(hud::Kill {
+                killer: Some((net::local_player_name(), hud::Side::Local)),
+                icon: icons::Icon::Skull,
+                headshot: false,
+                victim: ("ENEMY".to_string(), hud::Side::Enemy),
+            });
+            self.drop_loot(kill.position);
+        }
+        for position in self.enemies.take_sightings() {
+            if self.alarms.spotted(position) {
+                self.alarm_raised();
+            }
+        }
+        if !paused {
+            self.update_alarms(dt);
+        }
+        // 敌人开的枪：枪口火光和曳光，打中时和其他伤害一样在 update_player 里结算
+        for shot in self.enemies.take_shots() {
+            let dir = (shot.to - shot.from).normalize_or_zero();
+            self.weapon_fx.muzzle_flash(shot.from, dir);
+            self.tracers.add(shot.from, shot.to);
+            if shot.hit && !self.spectator.active {
+                self.pending_damage.lock().unwrap().push(damage::Damage::new(enemy::SHOT_DAMAGE, damage::DamageType::Bullet));
+            }
+        }
+    }
+
+    // 打死的敌人随机掉落弹药、血包，波次模式下还有钱
+    fn drop_loot(&mut self, position: glam::Vec3) {
+        let items = self.enemies.roll_loot(self.waves.is_some());
+        self.pickups.drop_loot(&mut self.world, &items, position + glam::Vec3::Y * 0.8);
+    }
+
+    // 处理受到的伤害，死亡后倒计时结束时重生
+    fn update_player(&mut self, dt: f32) {
+        let mut damage = std::mem::take(&mut *self.pending_damage.lock().unwrap());
+        damage.extend(self.hazards.update(dt, self.camera.position));
+        let mut taken: Vec<_> = damage.into_iter().map(|damage| (self.player.take_damage(damage), true)).collect();
+        // 燃烧的掉血每帧都有，不再留痕迹和晃镜头
+        taken.extend(self.player.update_status(dt).map(|burn| (burn, false)));
+        let feet = self.camera.position - glam::Vec3::Y * 1.8;
+        for ((damage, outcome), effects) in taken {
+            let amount = damage.amount;
+            match outcome {
+                player::DamageOutcome::Ignored => {}
+                player::DamageOutcome::Hurt => {
+                    if effects {
+                        self.damage_effects(damage.kind, feet, self.camera.position, 0.3);
+                        self.tilt.hit(amount);
+                    }
+                    self.events.emit(events::GameEvent::DamageTaken { amount });
+                }
+                player::DamageOutcome::Killed => {
+                    self.damage_effects(damage.kind, feet, self.camera.position, 0.6);
+                    println!("玩家死亡");
+                    self.death_position = self.camera.position;
+                    self.events.emit(events::GameEvent::DamageTaken { amount });
+                    self.events.emit(events::GameEvent::Death);
+                    let (killer, weapon) = self.last_attacker.take().unzip();
+                    if self.session.is_online() {
+                        self.session.report_death(killer, weapon);
+                        self.spectator.start(&self.camera, &self.session.remote_players());
+                    } else {
+                        let victim = (net::local_player_name(), hud::Side::Local);
+                        self.hud.push_kill(hud::Kill { killer: None, icon: icons::Icon::Skull, headshot: false, victim });
+                        if self.loadouts_enabled() {
+                            self.open_loadouts();
+                        }
+                    }
+                }
+            }
+        }
+        // 观战时按跳跃键才重生，单机时关掉出生配置界面才重生
+        if self.player.update(dt) && !self.spectator.active && !self.loadout_screen.open {
+            self.respawn();
+        }
+    }
+
+    // 从视线方向射击，子弹可以穿透较薄的墙体，在表面留下弹孔
+    fn fire(&mut self) {
+        let Some(weapon) = self.inventory.current_mut() else { return };
+        // 散布在开火之前取，第一枪不受这一枪扩大的散布影响
+        let spread = weapon.spread();
+        if !weapon.try_fire() {
+            return;
+        }
+        let stats = weapon.stats.clone();
+        dog::hear_gunshot(&mut self.world, &self.map, self.camera.position);
+        let origin = self.camera.position;
+        let dir = self.camera.forward();
+        let muzzle = tracers::muzzle(origin, dir);
+        self.casings.eject(stats.ammo, muzzle - dir * 0.15, dir);
+        self.weapon_fx.muzzle_flash(muzzle, dir);
+        // 霰弹枪的每发弹丸各算一次射击，命中率按弹丸统计
+        let pellets = stats.pellet_dirs(dir, spread);
+        self.recoil.kick(&stats, &mut self.camera);
+        for pellet in &pellets {
+            self.events.emit(events::GameEvent::ShotFired);
+            self.range.record_shot(dir, *pellet);
+        }
+        // 投射物只在本地模拟，打不到联机的其他玩家
+        if stats.projectile_speed > 0.0 {
+            for pellet in pellets {
+                projectile::spawn(&mut self.world, projectile::Launch {
+                    origin,
+                    velocity: pellet * stats.projectile_speed,
+                    gravity: stats.projectile_gravity,
+                    damage: stats.damage,
+                    damage_type: stats.damage_type,
+                    slot: stats.slot,
+                });
+            }
+            return;
+        }
+        let targets = ecs::hitboxes(&self.world, &self.world.healths);
+        for pellet in pellets {
+            let shot = weapon::hitscan(&stats, &self.colliders, &targets, origin, pellet);
+            let end = shot.end(origin, pellet, stats.range);
+            self.tracers.add(muzzle, end);
+            // 灯在天花板上，子弹打在灯后面的墙或天花板上时灯也碎
+            if let Some((light, _)) = self.light_fixtures.raycast(origin, pellet, end.distance(origin) + 0.1) {
+                self.break_light(light);
+            }
+            for impact in &shot.impacts {
+                self.decals.add_impact(impact);
+                if impact.kind == weapon::ImpactKind::Entry {
+                    self.weapon_fx.impact(impact.point, impact.normal);
+                }
+            }
+            if let Some(hit) = shot.hit {
+                self.hit_enemy(hit.target, damage::Damage::new(hit.damage, stats.damage_type), icons::weapon(stats.slot), hit.point, hit.normal, pellet);
+            }
+        }
+        self.shoot_players(&stats, dir);
+    }
+
+    // 子弹、投射物或手雷打中敌人：伤害、效果、击杀提示和掉落
+    fn hit_enemy(&mut self, target: usize, damage: damage::Damage, icon: icons::Icon, point: glam::Vec3, normal: glam::Vec3, dir: glam::Vec3) {
+        // 练习场的靶子只记统计，溅起火花
+        if let Some(hit) = self.range.hit(&mut self.world, target, damage, point) {
+            self.weapon_fx.impact(point, normal);
+            self.events.emit(events::GameEvent::ShotHit { headshot: hit.zone == practice::HitZone::Head });
+            return;
+        }
+        let Some(result) = self.enemies.damage(&mut self.world, target, damage, point, normal, dir) else { return };
+        self.damage_effects(damage.kind, result.position, point, if result.killed { 0.7 } else { 0.35 });
+        self.events.emit(events::GameEvent::ShotHit { headshot: result.headshot });
+        if !result.killed {
+            return;
+        }
+        self.events.emit(events::GameEvent::Kill { headshot: result.headshot });
+        self.hud.push_kill(hud::Kill {
+            killer: Some((net::local_player_name(), hud::Side::Local)),
+            icon,
+            headshot: result.headshot,
+            victim: ("ENEMY".to_string(), hud::Side::Enemy),
+        });
+        // 敌人的武器从手中甩出去
+        let dropped = self.enemies.roll_weapon_drop().and_then(|id| weapon::find_weapon(&self.weapon_defs, id)).cloned();
+        if let Some(stats) = dropped {
+            let velocity = dir * 1.5 + glam::Vec3::Y * 2.0;
+            self.pickups.drop(&mut self.world, pickup::PickupKind::Weapon(stats), result.position + glam::Vec3::Y * 1.0, velocity);
+        }
+        self.drop_loot(result.position);
+    }
+
+    // 投射物前进一步：打到墙上留下弹孔和火花，打到敌人和子弹一样结算
+    fn update_projectiles(&mut self, dt: f32) {
+        if self.world.projectiles.iter().next().is_none() {
+            return;
+        }
+        let targets = ecs::hitboxes(&self.world, &self.world.healths);
+        for hit in projectile::update_system(&mut self.world, dt, &self.colliders, &targets) {
+            match hit.target {
+                Some(target) => self.hit_enemy(target, damage::Damage::new(hit.damage, hit.damage_type), icons::weapon(hit.slot), hit.point, hit.normal, hit.dir),
+                None => {
+                    self.decals.add_impact(&weapon::Impact { point: hit.point, normal: hit.normal, kind: weapon::ImpactKind::Entry, damage: hit.damage });
+                    self.weapon_fx.impact(hit.point, hit.normal);
+                }
+            }
+        }
+    }
+
+    // 扔手雷，和投射物一样只在本地模拟，炸不到联机的其他玩家
+    fn throw_grenade(&mut self) {
+        if self.is_paused() || !self.player.is_alive() || self.spectator.active {
+            return;
+        }
+        self.grenades.throw(self.camera.position, self.camera.forward());
+    }
+
+    // 近战：打中实体时按近战伤害结算并卡肉一瞬间，打在墙上溅起火花。联机时打不到其他玩家
+    fn melee_attack(&mut self) {
+        if self.is_paused() || !self.player.is_alive() || self.spectator.active || !self.melee.swing() {
+            return;
+        }
+        self.player.end_protection();
+        let origin = self.camera.position;
+        let dir = self.camera.forward();
+        let targets = ecs::hitboxes(&self.world, &self.world.healths);
+        match melee::sweep(&self.colliders, &targets, origin, dir) {
+            melee::Strike::Target { target, point, normal } => {
+                self.hit_enemy(target, damage::Damage::new(melee::DAMAGE, damage::DamageType::Melee), icons::Icon::Knife, point, normal, dir);
+                self.slow_time(pacing::HIT_STOP);
+            }
+            melee::Strike::Wall { point, normal } => self.weapon_fx.impact(point, normal),
+            melee::Strike::Miss => {}
+        }
+    }
+
+    // 出生配置里选的手雷，没有出生配置时用破片手雷
+    fn refill_grenades(&mut self) {
+        let kind = self.loadout.as_ref().map_or(loadout::GrenadeKind::Frag, |loadout| loadout.grenade);
+        self.grenades.refill(kind);
+    }
+
+    fn update_grenades(&mut self, dt: f32) {
+        if self.grenades.is_empty() {
+            return;
+        }
+        for explosion in self.grenades.update(dt, &self.colliders) {
+            self.explode(&explosion);
+        }
+    }
+
+    // 手雷爆炸：范围内没有被墙挡住的敌人、靶子和玩家自己按距离受到伤害，画面按距离震动
+    fn explode(&mut self, explosion: &grenade::Explosion) {
+        let position = explosion.position;
+        let blast = explosion.blast;
+        println!("手雷爆炸: {} ({:.1}, {:.1}, {:.1})", explosion.kind.name(), position.x, position.y, position.z);
+        self.weapon_fx.explosion(position, grenade::flash_color(explosion.kind));
+        self.damage_effects(blast.damage_type, glam::Vec3::new(position.x, 0.0, position.z), position, 1.0);
+        dog::hear_gunshot(&mut self.world, &self.map, position);
+        for light in self.light_fixtures.in_blast(&self.colliders, position, blast.radius) {
+            self.break_light(light);
+        }
+
+        // 伤害按到包围盒最近点的距离算，挡不挡住按到包围盒中心的视线算
+        let hits: Vec<(usize, glam::Vec3, f32)> = ecs::hitboxes(&self.world, &self.world.healths)
+            .into_iter()
+            .filter_map(|(target, aabb)| {
+                let closest = position.clamp(aabb.min, aabb.max);
+                let amount = blast.damage * grenade::falloff(&blast, closest.distance(position));
+                let center = (aabb.min + aabb.max) * 0.5;
+                (amount > 0.0 && collision::count_occluders(&self.colliders, position, center) == 0).then_some((target, closest, amount))
+            })
+            .collect();
+        for (target, point, amount) in hits {
+            let dir = (point - position).try_normalize().unwrap_or(glam::Vec3::Y);
+            self.hit_enemy(target, damage::Damage::new(amount, blast.damage_type), icons::Icon::Grenade, point, -dir, dir);
+        }
+
+        // 玩家按身体（脚到眼睛的竖线）上最近的点算
+        let eye = self.camera.position;
+        let body = glam::Vec3::new(eye.x, position.y.clamp(eye.y - 1.8, eye.y), eye.z);
+        let distance = body.distance(position);
+        self.shake.add(grenade::shake(&blast, distance));
+        let amount = blast.damage * grenade::falloff(&blast, distance);
+        if self.player.is_alive() && amount > 0.0 && collision::count_occluders(&self.colliders, position, eye) == 0 {
+            self.pending_damage.lock().unwrap().push(damage::Damage::new(amount, blast.damage_type));
+        }
+    }
+
+    // 有警报面板刚拉响
+    fn alarm_raised(&mut self) {
+        self.hud.show_message("ALARM", [1.0, 0.15, 0.1, 1.0]);
+    }
+
+    // 响着的警报面板：警笛、天花板灯闪红光，到时间叫来增援
+    fn update_alarms(&mut self, dt: f32) {
+        for event in self.alarms.update(dt, |panel| self.enemies.alive_reinforcements(&self.world, panel)) {
+            match event {
+                alarm::AlarmEvent::Siren(position) => {
+                    let listener = audio::Listener::from_camera(&self.camera);
+                    self.audio.play_at(audio::SoundKind::Siren, position + glam::Vec3::Y * 1.8, &listener, &self.colliders);
+                }
+                alarm::AlarmEvent::Reinforce(panel, position) => {
+                    println!("警报叫来了增援");
+                    self.enemies.reinforce(&mut self.world, &self.map, panel, position, alarm::REINFORCEMENT_COUNT);
+                }
+            }
+        }
+        self.lighting.set_emergency(&self.queue, self.alarms.flash());
+    }
+
+    // 打坏一盏灯：灯管碎掉冒电火花，这盏灯不再照亮周围
+    fn break_light(&mut self, index: usize) {
+        let Some(position) = self.light_fixtures.break_fixture(index) else { return };
+        self.lighting.break_light(&self.queue, index);
+        self.weapon_fx.impact(position, glam::Vec3::NEG_Y);
+        self.particle_effects.arcs(position);
+        let listener = audio::Listener::from_camera(&self.camera);
+        self.audio.play_at(audio::SoundKind::Zap, position, &listener, &self.colliders);
+    }
+
+    // 联机时打其他玩家：客户端把开枪发给主机判定，主机自己开枪时按其他玩家的当前位置判定
+    fn shoot_players(&mut self, stats: &weapon::WeaponStats, dir: glam::Vec3) {
+        let origin = self.camera.position;
+        match &mut self.session {
+            net::Session::Offline => {}
+            net::Session::Client(client) => client.send_fire(&stats.id, origin, dir),
+            net::Session::Host(server) => {
+                let targets: Vec<(u32, collision::Aabb)> = server
+                    .remote_players()
+                    .into_iter()
+                    .filter(|(_, remote)| remote.alive)
+                    .map(|(player_id, remote)| (player_id, lag_comp::hitbox(remote.position())))
+                    .collect();
+                let host_id = server.host_id();
+                self.resolve_player_shot(host_id, stats, origin, dir, &targets);
+            }
+        }
+    }
+
+    // 主机判定一次对玩家的射击，墙体穿透和伤害衰减和打敌人一样
+    fn resolve_player_shot(&mut self, shooter: u32, stats: &weapon::WeaponStats, origin: glam::Vec3, dir: glam::Vec3, targets: &[(u32, collision::Aabb)]) {
+        let net::Session::Host(server) = &self.session else { return };
+        // 关掉友军伤害时玩家之间打不到，只能打机器人
+        let friendly_fire = server_config::current().friendly_fire;
+        let targets: Vec<(usize, collision::Aabb)> = targets
+            .iter()
+            .filter(|(player_id, _)| friendly_fire || server.is_bot(*player_id))
+            .map(|(player_id, aabb)| (*player_id as usize, *aabb))
+            .collect();
+        // 自己的射击在 fire 里已经画了曳光和枪口火光
+        let remote = shooter != server.host_id();
+        if remote {
+            self.weapon_fx.muzzle_flash(tracers::muzzle(origin, dir), dir);
+        }
+        // 霰弹枪的弹丸散布由主机决定
+        for pellet in stats.pellet_dirs(dir, stats.spread) {
+            let shot = weapon::hitscan(stats, &self.colliders, &targets, origin, pellet);
+            if remote {
+                self.tracers.add(tracers::muzzle(origin, dir), shot.end(origin, pellet, stats.range));
+            }
+            let net::Session::Host(server) = &mut self.session else { return };
+            // 命中率只算对玩家的射击
+            server.record_shot(shooter, shot.hit.is_some());
+            let Some(hit) = shot.hit else { continue };
+            let target = hit.target as u32;
+            if target == server.host_id() {
+                self.last_attacker = Some((shooter, stats.id.clone()));
+                self.pending_damage.lock().unwrap().push(damage::Damage::new(hit.damage, stats.damage_type));
+            } else {
+                server.damage_player(target, hit.damage, shooter, &stats.id);
+            }
+        }
+    }
+
+    // 机器人在出生点和重生点出现（脚下的位置）
+    fn bot_spawns(&self) -> Vec<glam::Vec3> {
+        self.map.respawn_cells().into_iter().map(|(col, row)| self.map.cell_center(col, row)).collect()
+    }
+
+    // 击杀提示：自己的名字高亮，武器图标按武器类型，不认识的武器（mod 里的）和没有攻击者时用骷髅
+    fn push_kill(&mut self, kill: &net::KillInfo) {
+        let local = self.session.local_player_id();
+        let side = |player_id: u32| if Some(player_id) == local { hud::Side::Local } else { hud::Side::Player };
+        let icon = kill
+            .weapon
+            .as_ref()
+            .and_then(|id| weapon::find_weapon(&self.weapon_defs, id))
+            .map_or(icons::Icon::Skull, |stats| icons::weapon(stats.slot));
+        let killer = kill.killer.zip(kill.killer_name.clone()).map(|(player_id, name)| (name, side(player_id)));
+        let victim = (kill.victim_name.clone(), side(kill.victim));
+        self.hud.push_kill(hud::Kill { killer, icon, headshot: false, victim });
+    }
+
+    // 用手柄时按设置的强度辅助瞄准，用鼠标时要在设置里另外打开
+    fn aim_assist(&self) -> Option<aim_assist::Assist> {
+        let mouse = self.input_device == input::Device::KeyboardMouse;
+        if mouse && !self.settings.mouse_aim_assist {
+            return None;
+        }
+        let targets: Vec<collision::Aabb> = ecs::hitboxes(&self.world, &self.world.healths).into_iter().map(|(_, aabb)| aabb).collect();
+        aim_assist::compute(
+            self.settings.aim_assist,
+            &self.colliders,
+            &targets,
+            self.camera.position,
+            self.camera.yaw,
+            self.camera.pitch,
+            mouse,
+        )
+    }
+
+    // AI 调试面板：准星对着的敌人，路线画成调试线
+    fn update_inspector(&mut self) {
+        let inspection = self
+            .aim
+            .filter(|aim| self.ai_inspector.open && aim.kind == crosshair::AimKind::Enemy)
+            .and_then(|aim| self.enemies.inspect(&self.world, &self.map, &self.colliders, aim.id, self.camera.position));
+        self.ai_inspector.update(inspection);
+        let path = self.ai_inspector.path();
+        self.inspector_model = (path.len() >= 2).then(|| model::create_polyline(&self.device, path, ai_debug::PATH_COLOR));
+    }
+
+    // 屏幕中心射线检测，每帧只做一次
+    fn update_aim(&mut self) {
+        if !self.player.is_alive() {
+            self.aim = None;
+            return;
+        }
+        let enemies = ecs::hitboxes(&self.world, &self.world.healths).into_iter().map(|(id, aabb)| (crosshair::AimKind::Enemy, id, aabb));
+        let pickups = ecs::hitboxes(&self.world, &self.world.pickups)
+            .into_iter()
+            .map(|(id, aabb)| (crosshair::AimKind::Interactable(crosshair::Interaction::Pickup), id, aabb));
+        let doors = ecs::hitboxes(&self.world, &self.world.doors)
+            .into_iter()
+            .map(|(id, aabb)| (crosshair::AimKind::Interactable(crosshair::Interaction::Door), id, aabb));
+        let alarms = self
+            .alarms
+            .aim_boxes()
+            .into_iter()
+            .map(|(id, aabb)| (crosshair::AimKind::Interactable(crosshair::Interaction::Alarm), id, aabb));
+        // 狗和其他玩家算友军，准星对着时变绿
+        let dogs = ecs::hitboxes(&self.world, &self.world.dogs).into_iter().map(|(id, aabb)| (crosshair::AimKind::Friendly, id, aabb));
+        let teammates = self
+            .other_players()
+            .into_iter()
+            .filter(|(_, remote)| remote.alive)
+            .map(|(player_id, remote)| (crosshair::AimKind::Friendly, player_id as usize, lag_comp::hitbox(remote.position())));
+        let candidates: Vec<crosshair::AimCandidate> = enemies
+            .chain(dogs)
+            .chain(teammates)
+            .chain(pickups)
+            .chain(doors)
+            .chain(alarms)
+            .map(|(kind, id, aabb)| crosshair::AimCandidate { kind, id, aabb })
+            .collect();
+        self.aim = crosshair::cast(&self.colliders, &candidates, self.camera.position, self.camera.forward());
+    }
+
+    // 描边的方块：准星对着的拾取物或门，以及波次模式里剩下的最后几个敌人；夜视仪开着时高亮敌人和其他玩家
+    // 敌人画进 id 纹理的是和画面上一样的贴图面片
+    fn update_outlines(&mut self, alpha: f32, camera_position: glam::Vec3) {
+        let mut interactable = outline::Shapes::new();
+        let mut marked = outline::Shapes::new();
+        let mut bright = outline::Shapes::new();
+        if self.settings.outlines.enabled {
+            match self.aim {
+                Some(crosshair::AimTarget {
+                    kind: crosshair::AimKind::Interactable(crosshair::Interaction::Pickup | crosshair::Interaction::Door),
+                    id,
+                    ..
+                }) => {
+                    if let Some(entity) = self.world.resolve(id) {
+                        ecs::entity_boxes(&self.world, entity, alpha, &mut interactable.boxes);
+                    }
+                }
+                Some(crosshair::AimTarget { kind: crosshair::AimKind::Interactable(crosshair::Interaction::Alarm), id, .. }) => {
+                    self.alarms.panel_boxes(id, false, &mut interactable.boxes);
+                }
+                _ => {}
+            }
+            if self.waves.as_ref().is_some_and(|waves| waves.marks_enemies(self.enemies.remaining(&self.world))) {
+                marked.sprites = enemy_sprites::quads(&self.world, alpha, camera_position);
+            }
+        }
+        if self.night_vision.is_on() {
+            bright.sprites = enemy_sprites::quads(&self.world, alpha, camera_position);
+            for (_, remote) in self.other_players().into_iter().filter(|(_, remote)| remote.alive) {
+                enemy::push_player(&mut bright.boxes, remote.position() - glam::Vec3::Y * lag_comp::EYE_HEIGHT, remote.yaw);
+            }
+        }
+        self.outline.set_shapes(&self.device, &interactable, &marked, &bright);
+    }
+
+    // 使用准星对准的物体：捡起拾取物或开关门
+    fn interact(&mut self) {
+        let Some(target) = self.aim else { return };
+        match target.kind {
+            crosshair::AimKind::Interactable(crosshair::Interaction::Pickup) => {
+                // 护甲、血和弹药满了时留在地上
+                let full = match pickup::get(&self.world, target.id).map(|pickup| &pickup.kind) {
+                    Some(pickup::PickupKind::Armor(_)) if self.player.armor_full() => Some(("ARMOR FULL", [0.25, 0.55, 1.0, 1.0])),
+                    Some(pickup::PickupKind::Health(_)) if self.player.health_full() => Some(("HEALTH FULL", [0.9, 0.2, 0.2, 1.0])),
+                    Some(pickup::PickupKind::Ammo(_)) if !self.inventory.can_add_ammo() => Some(("AMMO FULL", [0.9, 0.9, 0.9, 1.0])),
+                    _ => None,
+                };
+                if let Some((message, color)) = full {
+                    self.hud.show_message(message, color);
+                    return;
+                }
+                match self.pickups.take(&mut self.world, target.id) {
+                    Some(pickup::PickupKind::Weapon(stats)) => {
+                        println!("捡起武器: {}", stats.name);
+                        // 同一栏位原来的武器放到地上
+                        if let Some(old) = self.inventory.add(stats) {
+                            self.throw_weapon(old, 1.0);
+                        }
+                    }
+                    Some(pickup::PickupKind::Keycard(key)) => {
+                        println!("捡起钥匙卡: {}", key.name());
+                        self.inventory.add_keycard(key);
+                        self.hud.show_message(&format!("{} KEYCARD", key.name()), key.ui_color());
+                    }
+                    Some(pickup::PickupKind::Armor(amount)) => {
+                        println!("捡起护甲: {:.0}", amount);
+                        self.player.add_armor(amount);
+                    }
+                    Some(pickup::PickupKind::Ammo(magazines)) => {
+                        println!("捡起弹药: {} 个弹匣", magazines);
+                        self.inventory.add_ammo(magazines);
+                    }
+                    Some(pickup::PickupKind::Health(amount)) => {
+                        println!("捡起血包: {:.0}", amount);
+                        self.player.heal(amount);
+                    }
+                    Some(pickup::PickupKind::Currency(amount)) => {
+                        println!("捡起钱: ${}", amount);
+                        if let Some(waves) = &mut self.waves {
+                            waves.add_money(amount);
+                        }
+                    }
+                    None => {}
+                }
+                self.aim = None;
+            }
+            crosshair::AimKind::Interactable(crosshair::Interaction::Door) => {
+                let inventory = &self.inventory;
+                if let Some(door::DoorUse::Locked(key)) = door::use_door(&mut self.world, target.id, |key| inventory.has_keycard(key)) {
+                    self.hud.show_message(&format!("REQUIRES {} KEYCARD", key.name()), key.ui_color());
+                }
+            }
+            crosshair::AimKind::Interactable(crosshair::Interaction::Alarm) if self.alarms.disable(target.id) && !self.alarms.is_ringing() => {
+                self.hud.show_message("ALARM DISABLED", [0.3, 0.9, 0.4, 1.0]);
+            }
+            _ => {}
+        }
+    }
+
+    // 准星下方的交互提示，锁门显示需要的钥匙卡
+    fn interaction_prompt(&self) -> Option<(String, [f32; 4])> {
+        let target = self.aim?;
+        let key_name = self.input_config.describe(input::Action::Use, self.input_device);
+        let prompt_color = [1.0, 0.85, 0.2, 0.95];
+        match target.kind {
+            crosshair::AimKind::Interactable(crosshair::Interaction::Pickup) => {
+                let pickup = pickup::get(&self.world, target.id)?;
+                Some((format!("{} PICK UP {}", key_name, pickup.kind.name()), prompt_color))
+            }
+            crosshair::AimKind::Interactable(crosshair::Interaction::Door) => {
+                let door = door::get(&self.world, target.id)?;
+                match door.lock {
+                    Some(key) if !self.inventory.has_keycard(key) => {
+                        Some((format!("LOCKED - REQUIRES {} KEYCARD", key.name()), key.ui_color()))
+                    }
+                    Some(key) => Some((format!("{} UNLOCK WITH {} KEYCARD", key_name, key.name()), key.ui_color())),
+                    None if door.is_open() => Some((format!("{} CLOSE DOOR", key_name), prompt_color)),
+                    None => Some((format!("{} OPEN DOOR", key_name), prompt_color)),
+                }
+            }
+            crosshair::AimKind::Interactable(crosshair::Interaction::Alarm) if self.alarms.is_disabled(target.id) => {
+                Some(("ALARM DISABLED".to_string(), [0.6, 0.6, 0.6, 0.95]))
+            }
+            crosshair::AimKind::Interactable(crosshair::Interaction::Alarm) => Some((format!("{} DISABLE ALARM", key_name), prompt_color)),
+            _ => None,
+        }
+    }
+
+    // 动画事件分给订阅的系统：门开关自己的碰撞体，声音系统播放对应的声音
+    fn dispatch_anim_events(&mut self) {
+        let events = self.anim_events.drain();
+        if events.is_empty() {
+            return;
+        }
+        let listener = audio::Listener::from_camera(&self.camera);
+        for event in &events {
+            door::on_event(&mut self.world, event);
+            if let Some(sound) = audio::event_sound(event.tag) {
+                self.audio.play_at(sound, event.position, &listener, &self.colliders);
+            }
+        }
+    }
+
+    fn refresh_colliders(&mut self) {
+        self.colliders.clear();
+        self.colliders.extend(self.level.wall_colliders.iter().cloned());
+        self.colliders.extend(door::colliders(&self.world));
+        self.colliders.extend(self.alarms.colliders());
+    }
+
+    // 数字键选栏位或者按顺序切换，switch 返回是否真的换了武器
+    fn switch_weapon(&mut self, switch: impl FnOnce(&mut inventory::Inventory) -> bool) {
+        if !self.player.is_alive() || !switch(&mut self.inventory) {
+            return;
+        }
+        if let Some(weapon) = self.inventory.current() {
+            println!("切换武器: {}", weapon.stats.name);
+        }
+    }
+
+    fn drop_weapon(&mut self) {
+        if !self.player.is_alive() {
+            return;
+        }
+        if let Some(stats) = self.inventory.drop_current() {
+            println!("丢下武器: {}", stats.name);
+            self.throw_weapon(stats, 3.0);
+        }
+    }
+
+    // 从胸口高度朝视线方向抛出
+    fn throw_weapon(&mut self, stats: weapon::WeaponStats, speed: f32) {
+        let forward = self.camera.forward();
+        let position = self.camera.position - glam::Vec3::Y * 0.5 + forward * 0.4;
+        self.pickups.drop(&mut self.world, pickup::PickupKind::Weapon(stats), position, forward * speed + glam::Vec3::Y * 1.5);
+    }
+
+    // 受伤的实体脚下留下血迹（关闭血腥内容时不显示）
+    fn splatter(&mut self, position: glam::Vec3, size: f32) {
+        if self.settings.gore {
+            self.decals.add_splatter(position, size);
+        }
+    }
+
+    // 按伤害类型的痕迹和声音：子弹和近战留血迹，爆炸和火焰留焦痕，火焰冒火星，电击溅电火花。
+    // ground 是脚下的位置，point 是命中点
+    fn damage_effects(&mut self, kind: damage::DamageType, ground: glam::Vec3, point: glam::Vec3, size: f32) {
+        let sound = match kind {
+            damage::DamageType::Bullet | damage::DamageType::Melee => {
+                self.splatter(ground, size);
+                return;
+            }
+            damage::DamageType::Explosive => {
+                self.decals.add_scorch(ground, size * 2.0);
+                audio::SoundKind::Blast
+            }
+            damage::DamageType::Fire => {
+                self.decals.add_scorch(ground, size);
+                self.particle_effects.embers(point);
+                audio::SoundKind::Burn
+            }
+            damage::DamageType::Electric => {
+                self.particle_effects.arcs(point);
+                audio::SoundKind::Zap
+            }
+        };
+        let listener = audio::Listener::from_camera(&self.camera);
+        self.audio.play_at(sound, point, &listener, &self.colliders);
+    }
+
+    // 重生时按档案里的天赋（波次模式下加上这一局买的）设置武器和体力
+    fn apply_perks(&mut self) {
+        let mut progression = self.profile.lock().unwrap().progression.clone();
+        if let Some(waves) = &self.waves {
+            progression = waves.boosted(&progression);
+        } else if let Some(loadout) = &self.loadout {
+            progression = loadout.boosted(&progression);
+        }
+        self.inventory.set_perks(progression.weapon_perks());
+        self.player.set_stamina_scale(progression.stamina_scale());
+    }
+
+    // 在离威胁最远的重生点复活
+    fn respawn(&mut self) {
+        // 远离活着的敌人；没有敌人时远离死亡位置，避免在原地重生
+        let mut threats = self.enemies.alive_positions(&self.world);
+        if threats.is_empty() {
+            threats.push(self.death_position);
+        }
+        let spawn = nav::farthest_spawn(&self.map, &threats);
+        println!("重生于 ({:.1}, {:.1})", spawn.x, spawn.z);
+        self.camera.position = spawn + glam::Vec3::Y * 1.8;
+        self.camera.pitch = 0.0;
+        self.tilt.reset();
+        self.shake.reset();
+        self.recoil.reset();
+        self.player.respawn();
+        if let Some(loadout) = self.loadout.as_ref().filter(|_| self.waves.is_none()) {
+            self.inventory.equip(loadout.weapons(&self.weapon_defs));
+        }
+        self.apply_perks();
+        self.inventory.refill();
+        self.refill_grenades();
+        self.melee.reset();
+    }
+
+    // 联机时各自的模拟速度要一致，不缩放时间
+    fn slow_time(&mut self, effect: pacing::TimeEffect) {
+        if !self.session.is_online() && self.demo.is_none() {
+            self.time_scale.push(effect);
+        }
+    }
+
+    // 波次模式：购买时间结束时刷出下一波，敌人全部死掉时进入购买阶段
+    fn update_waves(&mut self, dt: f32) {
+        let Some(waves) = &mut self.waves else { return };
+        match waves.update(dt, self.enemies.remaining(&self.world)) {
+            Some(waves::WaveUpdate::Started { wave, enemies }) => {
+                println!("第 {} 波开始，{} 个敌人", wave, enemies);
+                self.enemies.spawn_wave(enemies);
+                self.shop.open = false;
+                self.hud.show_message(&format!("WAVE {}", wave), [1.0, 0.85, 0.2, 1.0]);
+            }
+            Some(waves::WaveUpdate::Cleared { wave, reward }) => {
+                println!("第 {} 波结束，奖励 ${}", wave, reward);
+                self.slow_time(pacing::FINAL_KILL);
+                self.events.emit(events::GameEvent::WaveCleared { wave });
+                let shop = self.input_config.describe(input::Action::Shop, self.input_device);
+                self.hud.push_toast(&format!("WAVE {} CLEARED", wave), &format!("+${}   {} shop", reward, shop));
+            }
+            None => {}
+        }
+    }
+
+    // 触发区域检测；教程完成后自动进入正式地图
+    fn update_tutorial(&mut self, dt: f32) {
+        for id in self.triggers.update(self.camera.position) {
+            self.range.trigger(&mut self.world, id);
+            if self.alarms.trip(id) {
+                self.alarm_raised();
+            }
+            self.events.emit(events::GameEvent::TriggerEntered { id });
+        }
+        if let Some(timer) = &mut self.tutorial_exit_timer {
+            *timer -= dt;
+            if *timer <= 0.0 {
+                self.load_map(map::DEFAULT_MAP);
+                self.events.emit(events::GameEvent::MatchStarted);
+            }
+        }
+    }
+
+    // 处理这一帧的事件：推进教程，记入档案，并定期保存
+    fn process_events(&mut self, dt: f32) {
+        let events = self.events.drain();
+        for event in &events {
+            if self.tutorial.handle_event(event) && !self.tutorial.is_active() {
+                println!("教程完成");
+                self.hud.push_toast("TUTORIAL COMPLETE", "Entering the parking garage...");
+                self.tutorial_exit_timer = Some(TUTORIAL_EXIT_DELAY);
+            }
+        }
+
+        let mut profile = self.profile.lock().unwrap();
+        if !self.menu.open {
+            profile.add_play_time(dt as f64);
+        }
+        let mut save = false;
+        for event in &events {
+            profile.record(event, &self.map.name);
+            if let Some(waves) = &mut self.waves {
+                waves.record(event);
+            }
+            save |= *event == events::GameEvent::MatchEnded;
+            let mut xp = progression::event_xp(event);
+            for achievement in self.achievements.evaluate(event, &mut profile) {
+                println!("解锁成就: {} - {}", achievement.name, achievement.description);
+                self.hud.push_toast(&format!("ACHIEVEMENT: {}", achievement.name), &achievement.description);
+                xp += progression::ACHIEVEMENT_XP;
+                save = true;
+            }
+            if let Some(level) = profile.progression.award(xp) {
+                println!("升到 {} 级", level);
+                self.hud.push_toast(&format!("LEVEL {}", level), "perk point available in STATISTICS > PERKS");
+                save = true;
+            }
+        }
+        self.profile_save_timer -= dt;
+        if save || self.profile_save_timer <= 0.0 {
+            self.profile_save_timer = PROFILE_SAVE_INTERVAL;
+            profile.save(&paths::profile());
+        }
+    }
+
+    // 退出游戏前：断开联机，结束当前一局并保存档案
+    fn shutdown(&mut self) {
+        self.session.close();
+        self.events.emit(events::GameEvent::MatchEnded);
+        self.process_events(0.0);
+    }
+    
+    // 脚步声和环境声源
+    fn update_audio(&mut self, previous_position: glam::Vec3, dt: f32) {
+        let listener = audio::Listener::from_camera(&self.camera);
+
+        if self.camera_controller.is_grounded() {
+            let moved = (self.camera.position - previous_position) * glam::Vec3::new(1.0, 0.0, 1.0);
+            self.footstep_distance += moved.length();
+            if self.footstep_distance >= FOOTSTEP_STRIDE {
+                self.footstep_distance = 0.0;
+                let feet = self.camera.position - glam::Vec3::new(0.0, 1.6, 0.0);
+                self.audio.play_at(audio::SoundKind::Footstep, feet, &listener, &self.colliders);
+            }
+        }
+
+        for emitter in &mut self.ambient_emitters {
+            if emitter.update(dt) {
+                self.audio.play_at(emitter.kind, emitter.position, &listener, &self.colliders);
+            }
+        }
+    }
+
+    // 地图材质的颜色（墙体颜色由 HTTP 线程修改），锁坏了就不更新
+    fn wall_color_uniform(&self) -> Option<bindings::MaterialUniform> {
+        let color = self.wall_color.lock().ok()?;
+        Some(bindings::MaterialUniform::new([color.r as f32, color.g as f32, color.b as f32]))
+    }
+    
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let output = self.surface.get_current_texture()?;
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Encoder"),
+        });
+
+        // 每帧的 uniform 和动态数据合并成一次上传
+        self.uploader.begin(&self.device);
+        let lighting = self.lighting.prepare(
+            &self.queue,
+            &mut encoder,
+            &mut self.uploader,
+            &self.view_camera,
+            self.config.width,
+            self.config.height,
+            self.settings.lighting,
+        );
+        let frame = bindings::FrameUniform::new(self.camera_uniform, self.start_time.elapsed().as_secs_f32(), lighting);
+        self.uploader.write(&self.queue, &mut encoder, &self.frame.buffer, 0, &[frame]);
+        if let Some(wall_color) = self.wall_color_uniform() {
+            self.uploader.write(&self.queue, &mut encoder, &self.level_material.buffer, 0, &[wall_color]);
+        }
+        self.particle_effects.simulate(&self.queue, &mut encoder, &mut self.uploader);
+        self.tracers.upload(&self.queue, &mut encoder, &mut self.uploader);
+        self.weapon_fx.upload(&self.queue, &mut encoder, &mut self.uploader, &self.view_camera);
+        
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: self.post.scene_view().unwrap_or(&view),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(CLEAR_COLOR),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(texture::Texture::DEPTH_CLEAR),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            
+            // 在 render 方法中
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(0, &self.frame.bind_group, &[]);
+            render_pass.set_bind_group(1, &self.level_material.bind_group, &[]);
+            render_pass.set_bind_group(2, &self.object_bind_group, &[]);
+            
+            // 不透明物体按管线和材质排好序再画
+            let mut draw_list = draw_list::DrawList::new();
+            if let Some(batch) = &self.level.static_batch {
+                draw_list.push_batch(batch);
+            }
+            for model in self.dynamic_model.iter().chain(&self.dog_model).chain(&self.debug_model).chain(&self.inspector_model) {
+                draw_list.push(draw_list::Pipeline::Scene, draw_list::Material::Level, model);
+            }
+            if let Some(model) = self.decals.model() {
+                draw_list.push(draw_list::Pipeline::Scene, draw_list::Material::DecalAtlas, model);
+            }
+            if let Some(model) = &self.enemy_sprite_model {
+                draw_list.push(draw_list::Pipeline::Scene, draw_list::Material::EnemySprites, model);
+            }
+            let resources = draw_list::Resources {
+                scene: &self.render_pipeline,
+                double_sided: self.level.cutouts.double_sided_pipeline(),
+                level: &self.level_material.bind_group,
+                decal_atlas: &self.decal_material.bind_group,
+                enemy_sprites: &self.enemy_sprite_material.bind_group,
+                floor_markings: self.level.floor_markings.bind_group(),
+                cutouts: self.level.cutouts.bind_groups(),
+            };
+            draw_list.execute(&mut render_pass, &resources);
+
+            // 不透明物体之后再画半透明物体，它们不写深度，互相之间按距离排好了顺序
+            render_pass.set_pipeline(&self.transparent_pipeline);
+            render_pass.set_bind_group(1, &self.level_material.bind_group, &[]);
+            self.level.transparent.draw(&mut render_pass);
+            self.particle_effects.draw(&mut render_pass, &self.frame.bind_group);
+            self.tracers.draw(&mut render_pass, &self.frame.bind_group);
+            self.weapon_fx.draw(&mut render_pass, &self.frame.bind_group);
+        }
+        let view_proj = self.view_camera.calc_projection(self.config.width as f32 / self.config.height as f32) * self.view_camera.calc_view();
+        self.post.apply(&self.queue, &mut encoder, &mut self.uploader, view_proj, self.view_camera.position, &view);
+        self.outline.render(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            &mut self.uploader,
+            view_proj,
+            &self.settings.outlines,
+            (self.config.width, self.config.height),
+            &self.depth_texture.view,
+            &view,
+            &self.enemy_sprite_material.bind_group,
+        );
+        
+        // 叠加绘制菜单和联机状态
+        let mut ui_batch = ui::UiBatch::new(self.config.width, self.config.height);
+        if let Some(status) = self.session.status_text() {
+            ui_batch.text(10.0, 10.0, 2.0, [1.0, 1.0, 1.0, 0.9], &status);
+        }
+        if let Some(benchmark) = &self.benchmark {
+            ui_batch.text(10.0, 10.0, 2.0, [1.0, 1.0, 0.3, 0.9], &benchmark.progress_text());
+        }
+        if let Some(test) = &self.determinism {
+            ui_batch.text(10.0, 10.0, 2.0, [1.0, 1.0, 0.3, 0.9], &test.progress_text());
+        }
+        ui_batch.append(&self.minimap_batch);
+        self.profiler.draw(&mut ui_batch);
+        self.raw_input.draw(&mut ui_batch);
+        self.net_graph.draw(&mut ui_batch, &self.net_conditions);
+        self.ai_inspector.draw(&mut ui_batch);
+        self.player.draw(&mut ui_batch, self.spectator.active, self.settings.status_overlays);
+        if self.player.is_alive() && !self.spectator.active {
+            self.exposure.draw(&mut ui_batch);
+        }
+        if !self.spectator.active {
+            self.night_vision.draw(&mut ui_batch);
+        }
+        if self.spectator.active {
+            let name = self.spectator.followed().map(|player_id| self.player_name(player_id));
+            let jump = self.input_config.describe(input::Action::Jump, self.input_device);
+            let join = match (&self.demo, self.player.respawn_in()) {
+                (Some(demo), _) => format!("{} {}", jump, if demo.paused { "play" } else { "pause" }),
+                (None, Some(seconds)) if seconds > 0.0 => format!("respawn in {:.0}", seconds.ceil()),
+                (None, Some(_)) => format!("{} respawn", jump),
+                (None, None) => format!("{} join", jump),
+            };
+            let keys = spectate::SpectateKeys {
+                next: self.input_config.describe(input::Action::Fire, self.input_device),
+                previous: self.input_config.describe(input::Action::Reload, self.input_device),
+                free: self.input_config.describe(input::Action::Use, self.input_device),
+                join,
+            };
+            self.spectator.draw(&mut ui_batch, name.as_deref(), &keys);
+        } else if self.player.is_alive() && !self.menu.open && !self.inventory_screen.open && !self.shop.open && !self.loadout_screen.open {
+            crosshair::draw(&mut ui_batch, self.aim.as_ref());
+        }
+        if let Some((prompt, color)) = self.interaction_prompt().filter(|_| !self.spectator.active) {
+            ui_batch.text_centered(ui_batch.width() / 2.0, ui_batch.height() / 2.0 + 24.0, 2.0, color, &prompt);
+        }
+        self.inventory.draw_ammo(&mut ui_batch);
+        self.grenades.draw(&mut ui_batch);
+        self.range.draw(&mut ui_batch);
+        self.tutorial.draw(&mut ui_batch, &self.input_config, self.input_device);
+        if let Some(demo) = &self.demo {
+            demo.draw(&mut ui_batch);
+        }
+        if let Some(waves) = self.waves.as_ref().filter(|_| !self.menu.open) {
+            let shop_key = self.input_config.describe(input::Action::Shop, self.input_device);
+            waves.draw(&mut ui_batch, self.enemies.remaining(&self.world), &shop_key);
+            if self.shop.open {
+                let progression = self.profile.lock().unwrap().progression.clone();
+                let detail = |item: &shop::ShopItem| match &item.good {
+                    shop::ShopGood::Weapon { weapon } if self.inventory.has_weapon(weapon) => "OWNED".to_string(),
+                    shop::ShopGood::Perk { perk } => format!("RANK {}/{}", waves.perk_rank(&progression, *perk), progression::MAX_RANK),
+                    _ => String::new(),
+                };
+                self.shop.draw(&mut ui_batch, waves.money(), waves.buy_time().unwrap_or(0.0), detail);
+            }
+        }
+        if self.inventory_screen.open {
+            self.inventory_screen.draw(&mut ui_batch, &self.inventory, self.is_paused());
+        }
+        if self.loadout_screen.open && !self.menu.open {
+            let profile = self.profile.lock().unwrap();
+            self.loadout_screen.draw(&mut ui_batch, &profile.loadouts, &self.weapon_defs, self.player.respawn_in());
+        }
+        if !self.menu.open && !self.inventory_screen.open && !self.shop.open && !self.loadout_screen.open {
+            self.touch.draw(&mut ui_batch);
+        }
+        self.chat.draw(&mut ui_batch);
+        self.hud.draw(&mut ui_batch);
+        if let Some(screen) = self.match_summary.as_ref().filter(|_| !self.menu.open) {
+            screen.draw(&mut ui_batch, self.session.local_player_id());
+        } else if self.session.is_online() && self.held_actions.contains(&input::Action::Scoreboard) && !self.menu.open {
+            let title = self.session.status_text().unwrap_or_default();
+            scoreboard::draw(&mut ui_batch, &title, &self.session.scores(), self.session.local_player_id());
+        }
+        if self.menu.open {
+            let profile = self.profile.lock().unwrap();
+            self.menu.draw(
+                &mut ui_batch,
+                self.session.is_online(),
+                &profile,
+                &self.settings,
+                &self.input_config,
+                self.input_device,
+                self.active_gamepad.as_ref().map(|(guid, name)| (guid.as_str(), name.as_str())),
+                &self.map.name,
+                self.achievements.total(),
+            );
+        }
+        self.ui_renderer.render(&self.device, &self.queue, &mut encoder, &mut self.uploader, &view, &ui_batch);
+        self.uploader.finish(&self.queue);
+        
+        self.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+        
+        Ok(())
+    }
+}
+
+// 背景色（场景之外的地方，反射探针也用它）
+const CLEAR_COLOR: wgpu::Color = wgpu::Color { r: 0.1, g: 0.2, b: 0.3, a: 1.0 };
+
+// 地图的环境粒子：玩家周围飘着的灰尘，停车场入口处飘进来的雨，场景危害上的火星和电火花
+fn particle_emitters(map: &map::Map, hazards: &hazard::HazardSystem) -> Vec<gpu_particles::Emitter> {
+    use gpu_particles::{Emitter, ParticleKind};
+    let half_cell = glam::Vec3::new(map::CELL_SIZE / 2.0, 0.0, map::CELL_SIZE / 2.0);
+    let mut emitters: Vec<Emitter> = map
+        .emitters
+        .iter()
+        .map(|emitter| match *emitter {
+            // 相对于相机：眼睛下方 1.5 到上方 2
+            map::EmitterSpawn::Dust { radius, rate } => {
+                Emitter::new(ParticleKind::Dust, glam::Vec3::new(-radius, -1.5, -radius), glam::Vec3::new(radius, 2.0, radius), rate, true)
+            }
+            map::EmitterSpawn::Rain { from, to, rate } => {
+                let ceiling = glam::Vec3::Y * (map::WALL_HEIGHT - 0.05);
+                let min = map.cell_center(from.0, from.1) - half_cell + ceiling;
+                let max = map.cell_center(to.0, to.1) + half_cell + ceiling;
+                Emitter::new(ParticleKind::Rain, min, max, rate, false)
+            }
+        })
+        .collect();
+    emitters.extend(hazards.particle_emitters());
+    emitters
+}
+
+// 地图的环境声源：停车场漏水的管道，还有场景危害的声音
+fn ambient_emitters(map: &map::Map, hazards: &hazard::HazardSystem) -> Vec<audio::AmbientEmitter> {
+    let mut emitters = Vec::new();
+    if map.name == map::DEFAULT_MAP {
+        // 内部墙体2后面漏水的管道
+        emitters.push(audio::AmbientEmitter::new(glam::Vec3::new(6.0, 3.8, 10.0), audio::SoundKind::Drip, 1.7));
+        // 入口附近
+        emitters.push(audio::AmbientEmitter::new(glam::Vec3::new(-12.0, 3.8, -15.0), audio::SoundKind::Drip, 2.3));
+    }
+    emitters.extend(hazards.sound_emitters());
+    emitters
+}
@@ -0,0 +1,1225 @@
+use std::path::{Path, PathBuf};
+
+use glam::Vec3;
+
+use crate::assets;
+use crate::schema;
+
+// 地图：文本格式的格子地图，每个字符是一个 1x1 米的格子
+// 文件由头部（key: value）和 "---" 之后的格子组成，见 maps/*.map
+// 头部的 version 是地图格式的版本；加载时检查格子和出生点，以及重生点、敌人、触发区域和拾取物能不能从出生点走到
+
+// 地图格式的版本
+const MAP_VERSION: u32 = 1;
+// 预制件里还可以引用预制件，最多嵌套几层
+const MAX_PREFAB_DEPTH: usize = 4;
+
+pub const CELL_SIZE: f32 = 1.0;
+pub const WALL_HEIGHT: f32 = 4.0;
+
+// 一个车位宽 3 格、深 5 格
+const BAY_WIDTH: usize = 3;
+const BAY_DEPTH: usize = 5;
+// 天花板灯的默认间距（格子）
+const LIGHT_SPACING: usize = 6;
+
+pub const DEFAULT_MAP: &str = "parking_garage";
+pub const TUTORIAL_MAP: &str = "tutorial";
+pub const RANGE_MAP: &str = "range";
+
+// 内置地图，磁盘上找不到 maps/ 目录时使用
+const BUILTIN_MAPS: &[(&str, &str)] = &[
+    ("parking_garage", include_str!("../maps/parking_garage.map")),
+    ("tutorial", include_str!("../maps/tutorial.map")),
+    ("light_stress", include_str!("../maps/light_stress.map")),
+    ("range", include_str!("../maps/range.map")),
+];
+
+// 预制件：可以重复使用的一块地图（格子加上车、拾取物等），地图头部用 prefab: 名字 列 行 [角度] 盖到地图上，
+// 角度是顺时针转的度数（0/90/180/270）。格式和地图一样，格子里的空格表示不改动地图原来的格子。先从 maps/prefabs/ 读取，找不到时使用内置的
+const BUILTIN_PREFABS: &[(&str, &str)] = &[
+    ("door_frame", include_str!("../maps/prefabs/door_frame.map")),
+    ("parking_row", include_str!("../maps/prefabs/parking_row.map")),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cell {
+    // 地图外，不可到达
+    Void,
+    Floor,
+    Wall,
+    Spawn,
+    // 死亡后的重生点（出生点也可以作为重生点）
+    Respawn,
+    // 敌人出生点
+    EnemySpawn,
+    // 触发区域（小写字母），本身也是地面
+    Trigger(char),
+    // 门：D 为普通门，数字 1-3 为需要对应颜色钥匙卡的锁门
+    Door(Option<KeyColor>),
+    // 玻璃隔断（不可通过，半透明）
+    Glass,
+    // 地面上的积水（可以走）
+    Water,
+    // 铁丝网（不可通过，镂空）
+    Fence,
+    // 排水沟格栅（可以走）
+    Grate,
+    // 承重柱（不可通过）
+    Pillar,
+    // 停车位，相连的格子组成一排车位（可以走）
+    Parking,
+    // 车道上的箭头（可以走）
+    Lane(Direction),
+}
+
+// 地图上的方向：上是行号减小的方向（-z）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    fn from_name(name: &str) -> Option<Direction> {
+        match name {
+            "up" => Some(Direction::Up),
+            "down" => Some(Direction::Down),
+            "left" => Some(Direction::Left),
+            "right" => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    // 顺时针转 90 度（行号往下增加）
+    fn turned(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    pub fn vector(self) -> Vec3 {
+        match self {
+            Direction::Up => Vec3::NEG_Z,
+            Direction::Down => Vec3::Z,
+            Direction::Left => Vec3::NEG_X,
+            Direction::Right => Vec3::X,
+        }
+    }
+}
+
+impl Cell {
+    fn from_char(c: char) -> Option<Cell> {
+        match c {
+            ' ' => Some(Cell::Void),
+            '.' => Some(Cell::Floor),
+            '#' => Some(Cell::Wall),
+            'S' => Some(Cell::Spawn),
+            'R' => Some(Cell::Respawn),
+            'E' => Some(Cell::EnemySpawn),
+            'a'..='z' => Some(Cell::Trigger(c)),
+            'D' => Some(Cell::Door(None)),
+            'G' => Some(Cell::Glass),
+            '~' => Some(Cell::Water),
+            'F' => Some(Cell::Fence),
+            '=' => Some(Cell::Grate),
+            'O' => Some(Cell::Pillar),
+            'P' => Some(Cell::Parking),
+            '^' => Some(Cell::Lane(Direction::Up)),
+            'V' => Some(Cell::Lane(Direction::Down)),
+            '<' => Some(Cell::Lane(Direction::Left)),
+            '>' => Some(Cell::Lane(Direction::Right)),
+            '1'..='3' => KeyColor::from_level(c as u8 - b'0').map(|key| Cell::Door(Some(key))),
+            _ => None,
+        }
+    }
+
+    pub fn is_walkable(self) -> bool {
+        !matches!(self, Cell::Wall | Cell::Void | Cell::Glass | Cell::Fence | Cell::Pillar)
+    }
+
+    // 墙、玻璃、铁丝网和门，门、玻璃和铁丝网沿着两边的实心格子排列
+    fn is_solid(self) -> bool {
+        matches!(self, Cell::Wall | Cell::Glass | Cell::Fence | Cell::Door(_))
+    }
+}
+
+// 钥匙卡颜色，也是门的锁等级
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyColor {
+    Red,
+    Blue,
+    Yellow,
+}
+
+impl KeyColor {
+    fn from_level(level: u8) -> Option<KeyColor> {
+        match level {
+            1 => Some(KeyColor::Red),
+            2 => Some(KeyColor::Blue),
+            3 => Some(KeyColor::Yellow),
+            _ => None,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<KeyColor> {
+        match name {
+            "red" => Some(KeyColor::Red),
+            "blue" => Some(KeyColor::Blue),
+            "yellow" => Some(KeyColor::Yellow),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyColor::Red => "RED",
+            KeyColor::Blue => "BLUE",
+            KeyColor::Yellow => "YELLOW",
+        }
+    }
+
+    // 界面上使用的颜色
+    pub fn ui_color(self) -> [f32; 4] {
+        let [r, g, b] = self.color();
+        [r, g, b, 1.0]
+    }
+
+    pub fn color(self) -> [f32; 3] {
+        match self {
+            KeyColor::Red => [0.9, 0.15, 0.1],
+            KeyColor::Blue => [0.15, 0.35, 0.95],
+            KeyColor::Yellow => [0.95, 0.8, 0.1],
+        }
+    }
+}
+
+// 教程等脚本化提示的完成条件
+#[derive(Debug, Clone, PartialEq)]
+pub enum PromptCondition {
+    EnterTrigger(char),
+    Action(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub condition: PromptCondition,
+    pub text: String,
+}
+
+// 地图上放置的拾取物：武器、钥匙卡或护甲（加多少护甲）
+#[derive(Debug, Clone)]
+pub enum PickupItem {
+    Weapon(String),
+    Keycard(KeyColor),
+    Armor(f32),
+}
+
+#[derive(Debug, Clone)]
+pub struct PickupSpawn {
+    pub item: PickupItem,
+    pub col: usize,
+    pub row: usize,
+}
+
+// 场景危害（着火的地面、漏电的电线），id 是 data/hazards.json 里的名字
+#[derive(Debug, Clone)]
+pub struct HazardSpawn {
+    pub id: String,
+    pub col: usize,
+    pub row: usize,
+}
+
+// 粒子区域
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmitterSpawn {
+    // 玩家周围飘着的灰尘：水平方向的半径和每秒粒子数
+    Dust { radius: f32, rate: f32 },
+    // 从天花板往下滴的雨水：两个角的格子（包含两端）和每秒粒子数
+    Rain { from: (usize, usize), to: (usize, usize), rate: f32 },
+}
+
+// 练习场的靶子怎么动
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetMotion {
+    // 平躺在地上，定时弹起来，打中后倒下
+    PopUp,
+    // 一直立着，沿 direction 来回滑动 distance 格
+    Slide { direction: Direction, distance: usize },
+}
+
+// 警报面板：所在格子和绊线（走进去就拉响警报的触发区域）
+#[derive(Debug, Clone)]
+pub struct AlarmSpawn {
+    pub col: usize,
+    pub row: usize,
+    pub tripwires: Vec<char>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TargetSpawn {
+    pub motion: TargetMotion,
+    pub col: usize,
+    pub row: usize,
+}
+
+// 停着的车：车漆颜色名、所在格子（车的中心）和朝向
+#[derive(Debug, Clone)]
+pub struct CarSpawn {
+    pub paint: String,
+    pub col: usize,
+    pub row: usize,
+    // 车头朝 x 轴（否则朝 z 轴）
+    pub along_x: bool,
+}
+
+// 地图头部标注的地面标记
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MarkingKind {
+    Arrow,
+    // 无障碍车位的轮椅标志
+    Handicap,
+    // 车位编号
+    Number(u32),
+}
+
+#[derive(Debug, Clone)]
+pub struct MarkingSpawn {
+    pub kind: MarkingKind,
+    pub col: usize,
+    pub row: usize,
+    // 标记的上方（箭头的指向、文字的顶部）朝向哪里
+    pub facing: Direction,
+}
+
+// 墙体线段（与 create_wall 的参数一致）
+#[derive(Debug, Clone, Copy)]
+pub struct WallSegment {
+    pub start: [f32; 3],
+    pub end: [f32; 3],
+}
+
+// 触发区域的包围盒
+#[derive(Debug, Clone, Copy)]
+pub struct TriggerZone {
+    pub id: char,
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+pub struct Map {
+    pub name: String,
+    pub title: String,
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<Cell>,
+    pub prompts: Vec<Prompt>,
+    pub pickups: Vec<PickupSpawn>,
+    pub cars: Vec<CarSpawn>,
+    pub markings: Vec<MarkingSpawn>,
+    // 在地图里闲逛的狗的出生格子
+    pub dogs: Vec<(usize, usize)>,
+    pub hazards: Vec<HazardSpawn>,
+    // 练习场的靶子，和走上去清零练习场统计的触发区域
+    pub targets: Vec<TargetSpawn>,
+    pub range_resets: Vec<char>,
+    pub alarms: Vec<AlarmSpawn>,
+    // --benchmark 时相机飞过的格子，按顺序连成一圈
+    pub camera_path: Vec<(usize, usize)>,
+    // 调色用的 LUT（luts/ 目录下的名字），没有时不调色
+    pub lut: Option<String>,
+    // 天花板灯的间距（格子）
+    pub light_spacing: usize,
+    // 环境光亮度。有这一项时天花板灯才是点光源，没有时整张地图不打光（全亮）
+    pub ambient: Option<f32>,
+    pub emitters: Vec<EmitterSpawn>,
+}
+
+impl Map {
+    // 先从 maps/ 目录读取，找不到时使用内置地图
+    pub fn load(name: &str) -> Result<Map, String> {
+        let path = path(name);
+        match assets::read_to_string(&path) {
+            Ok(text) => Map::parse(name, &text).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(_) => {
+                let (_, text) = BUILTIN_MAPS
+                    .iter()
+                    .find(|(builtin, _)| *builtin == name)
+                    .ok_or_else(|| format!("map not found: {}", name))?;
+                Map::parse(name, text)
+            }
+        }
+    }
+
+    pub fn parse(name: &str, text: &str) -> Result<Map, String> {
+        let map = Map::parse_layout(name, text, 0)?;
+        let (width, height) = (map.width, map.height);
+        // 一次列出所有问题
+        let mut errors = Vec::new();
+        let Some(spawn) = map.find(|cell| cell == Cell::Spawn) else {
+            return Err("map has no spawn point 'S'".to_string());
+        };
+        if let Some(pickup) = map.pickups.iter().find(|pickup| !map.cell(pickup.col, pickup.row).is_walkable()) {
+            errors.push(format!("pickup at {} {} is not on the floor", pickup.col, pickup.row));
+        }
+        if let Some(car) = map.cars.iter().find(|car| !map.cell(car.col, car.row).is_walkable()) {
+            errors.push(format!("car at {} {} is not on the floor", car.col, car.row));
+        }
+        if let Some(marking) = map.markings.iter().find(|marking| !map.cell(marking.col, marking.row).is_walkable()) {
+            errors.push(format!("marking at {} {} is not on the floor", marking.col, marking.row));
+        }
+        if let Some((col, row)) = map.dogs.iter().find(|(col, row)| !map.cell(*col, *row).is_walkable()) {
+            errors.push(format!("dog at {} {} is not on the floor", col, row));
+        }
+        if let Some(hazard) = map.hazards.iter().find(|hazard| !map.cell(hazard.col, hazard.row).is_walkable()) {
+            errors.push(format!("hazard at {} {} is not on the floor", hazard.col, hazard.row));
+        }
+        if let Some(alarm) = map.alarms.iter().find(|alarm| !map.cell(alarm.col, alarm.row).is_walkable()) {
+            errors.push(format!("alarm at {} {} is not on the floor", alarm.col, alarm.row));
+        }
+        if let Some((col, row)) = map.camera_path.iter().find(|(col, row)| !map.cell(*col, *row).is_walkable()) {
+            errors.push(format!("camera path point at {} {} is not on the floor", col, row));
+        }
+        for target in &map.targets {
+            // 滑动的靶子经过的每个格子都要能走
+            let (steps, (dx, dz)) = match target.motion {
+                TargetMotion::PopUp => (0, (0, 0)),
+                TargetMotion::Slide { direction, distance } => {
+                    let vector = direction.vector();
+                    (distance, (vector.x as isize, vector.z as isize))
+                }
+            };
+            let blocked = (0..=steps).any(|step| {
+                let col = target.col.wrapping_add_signed(dx * step as isize);
+                let row = target.row.wrapping_add_signed(dz * step as isize);
+                !map.cell(col, row).is_walkable()
+            });
+            if blocked {
+                errors.push(format!("target at {} {} is not on the floor", target.col, target.row));
+            }
+        }
+        let zones = map.trigger_zones();
+        if let Some(id) = map.range_resets.iter().find(|id| !zones.iter().any(|zone| zone.id == **id)) {
+            errors.push(format!("range reset trigger '{}' is not in the map", id));
+        }
+        if let Some(id) = map.alarms.iter().flat_map(|alarm| &alarm.tripwires).find(|id| !zones.iter().any(|zone| zone.id == **id)) {
+            errors.push(format!("alarm tripwire '{}' is not in the map", id));
+        }
+
+        // 从出生点出发能走到的格子（门都当作能通过）
+        let reachable = map.reachable_from(spawn);
+        let mut reported = Vec::new();
+        for row in 0..height {
+            for col in 0..width {
+                let cell = map.cell(col, row);
+                let what = match cell {
+                    Cell::Respawn => "respawn point".to_string(),
+                    Cell::EnemySpawn => "enemy spawn".to_string(),
+                    // 每个触发区域只报一次
+                    Cell::Trigger(id) if !reported.contains(&cell) => format!("trigger zone '{}'", id),
+                    _ => continue,
+                };
+                if !reachable[row * width + col] {
+                    reported.push(cell);
+                    errors.push(at_cell(text, col, row, format!("{} at row {}, col {} is unreachable from the spawn point", what, row, col)));
+                }
+            }
+        }
+        for pickup in &map.pickups {
+            if map.cell(pickup.col, pickup.row).is_walkable() && !reachable[pickup.row * width + pickup.col] {
+                errors.push(format!("pickup at {} {} is unreachable from the spawn point", pickup.col, pickup.row));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors.join("\n"));
+        }
+        Ok(map)
+    }
+
+    // 头部和格子，盖上引用的预制件，不做检查（预制件本身没有出生点）
+    fn parse_layout(name: &str, text: &str, depth: usize) -> Result<Map, String> {
+        let mut title = name.to_string();
+        let mut prompts = Vec::new();
+        let mut pickups = Vec::new();
+        let mut cars = Vec::new();
+        let mut markings = Vec::new();
+        let mut dogs = Vec::new();
+        let mut hazards = Vec::new();
+        let mut targets = Vec::new();
+        let mut range_resets = Vec::new();
+        let mut alarms = Vec::new();
+        let mut camera_path = Vec::new();
+        let mut lut = None;
+        let mut light_spacing = LIGHT_SPACING;
+        let mut ambient = None;
+        let mut emitters = Vec::new();
+        let mut version = None;
+        let mut prefabs = Vec::new();
+        let mut lines = text.lines().enumerate();
+
+        // 头部
+        for (line_no, line) in lines.by_ref() {
+            let line = line.trim();
+            if line == "---" {
+                break;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let at_line = |e: String| format!("line {}: {}{}", line_no + 1, e, schema::line_context(text, line_no + 1, None));
+            let (key, value) = line.split_once(':').ok_or_else(|| at_line("expected 'key: value'".to_string()))?;
+            let value = value.trim();
+            match key.trim() {
+                "version" => {
+                    let found = value.parse().map_err(|_| at_line(format!("invalid version '{}'", value)))?;
+                    schema::check_version(Some(found), MAP_VERSION).map_err(at_line)?;
+                    version = Some(found);
+                }
+                "title" => title = value.to_string(),
+                "prompt" => prompts.push(parse_prompt(value).map_err(at_line)?),
+                "pickup" | "keycard" | "armor" => pickups.push(parse_pickup(key.trim(), value).map_err(at_line)?),
+                "car" => cars.push(parse_car(value).map_err(at_line)?),
+                "marking" => markings.push(parse_marking(value).map_err(at_line)?),
+                "dog" => dogs.push(parse_cell(value).map_err(at_line)?),
+                "hazard" => hazards.push(parse_hazard(value).map_err(at_line)?),
+                "target" => targets.push(parse_target(value).map_err(at_line)?),
+                "range_reset" => range_resets.push(parse_trigger_id(value).map_err(at_line)?),
+                "alarm" => alarms.push(parse_alarm(value).map_err(at_line)?),
+                "camera_path" => camera_path.push(parse_cell(value).map_err(at_line)?),
+                "prefab" => prefabs.push((parse_prefab(value).map_err(at_line)?, line_no + 1)),
+                "lut" => lut = Some(value.to_string()),
+                "light_spacing" => {
+                    light_spacing = value
+                        .parse()
+                        .ok()
+                        .filter(|spacing| *spacing > 0)
+                        .ok_or_else(|| at_line(format!("invalid light spacing '{}'", value)))?
+                }
+                "ambient" => ambient = Some(value.parse().map_err(|_| at_line(format!("invalid ambient '{}'", value)))?),
+                "dust" => emitters.push(parse_dust(value).map_err(at_line)?),
+                "rain" => emitters.push(parse_rain(value).map_err(at_line)?),
+                other => return Err(at_line(format!("unknown key '{}'", other))),
+            }
+        }
+        if version.is_none() {
+            eprintln!("地图 {} 没有写格式版本 (version)，按第 {} 版读取", name, MAP_VERSION);
+        }
+
+        // 格子
+        let rows: Vec<(usize, &str)> = lines.collect();
+        let height = rows.len();
+        let width = rows.iter().map(|(_, row)| row.chars().count()).max().unwrap_or(0);
+        if width == 0 || height == 0 {
+            return Err("map has no cells".to_string());
+        }
+        let mut cells = Vec::with_capacity(width * height);
+        for (row_index, (_, row)) in rows.iter().enumerate() {
+            let mut count = 0;
+            for (col, c) in row.chars().enumerate() {
+                let cell = Cell::from_char(c)
+                    .ok_or_else(|| at_cell(text, col, row_index, format!("unknown cell code '{}' at row {}, col {}", c, row_index, col)))?;
+                cells.push(cell);
+                count += 1;
+            }
+            // 较短的行用地图外补齐
+            cells.extend(std::iter::repeat_n(Cell::Void, width - count));
+        }
+
+        let mut map = Map { name: name.to_string(), title, width, height, cells, prompts, pickups, cars, markings, dogs, hazards, targets, range_resets, alarms, camera_path, lut, light_spacing, ambient, emitters };
+        for ((prefab, col, row, turns), line) in prefabs {
+            let at_line = |e: String| format!("line {}: prefab '{}': {}{}", line, prefab, e, schema::line_context(text, line, None));
+            if depth >= MAX_PREFAB_DEPTH {
+                return Err(at_line("prefabs are nested too deeply".to_string()));
+            }
+            let mut stamp = Map::parse_layout(&prefab, &load_prefab(&prefab).map_err(at_line)?, depth + 1).map_err(at_line)?;
+            for _ in 0..turns {
+                stamp = stamp.turned();
+            }
+            map.stamp(&stamp, col, row).map_err(at_line)?;
+        }
+        Ok(map)
+    }
+
+    // 顺时针转 90 度：原来的 (col, row) 转到 (height - 1 - row, col)，车道箭头、车和标记的朝向跟着转
+    fn turned(self) -> Map {
+        let height = self.height;
+        let turn = move |(col, row): (usize, usize)| (height - 1 - row, col);
+        let mut cells = vec![Cell::Void; self.width * self.height];
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let (x, y) = turn((col, row));
+                cells[y * height + x] = match self.cell(col, row) {
+                    Cell::Lane(direction) => Cell::Lane(direction.turned()),
+                    cell => cell,
+                };
+            }
+        }
+        let motion = |motion: TargetMotion| match motion {
+            TargetMotion::Slide { direction, distance } => TargetMotion::Slide { direction: direction.turned(), distance },
+            motion => motion,
+        };
+        let emitter = |emitter: EmitterSpawn| match emitter {
+            EmitterSpawn::Rain { from, to, rate } => {
+                let (a, b) = (turn(from), turn(to));
+                EmitterSpawn::Rain { from: (a.0.min(b.0), a.1.min(b.1)), to: (a.0.max(b.0), a.1.max(b.1)), rate }
+            }
+            dust => dust,
+        };
+        Map {
+            width: height,
+            height: self.width,
+            cells,
+            pickups: self.pickups.into_iter().map(|pickup| PickupSpawn { col: height - 1 - pickup.row, row: pickup.col, ..pickup }).collect(),
+            cars: self.cars.into_iter().map(|car| CarSpawn { col: height - 1 - car.row, row: car.col, along_x: !car.along_x, ..car }).collect(),
+            markings: self
+                .markings
+                .into_iter()
+                .map(|marking| MarkingSpawn { col: height - 1 - marking.row, row: marking.col, facing: marking.facing.turned(), ..marking })
+                .collect(),
+            dogs: self.dogs.into_iter().map(turn).collect(),
+            hazards: self.hazards.into_iter().map(|hazard| HazardSpawn { col: height - 1 - hazard.row, row: hazard.col, ..hazard }).collect(),
+            targets: self.targets.into_iter().map(|target| TargetSpawn { col: height - 1 - target.row, row: target.col, motion: motion(target.motion) }).collect(),
+            alarms: self.alarms.into_iter().map(|alarm| AlarmSpawn { col: height - 1 - alarm.row, row: alarm.col, ..alarm }).collect(),
+            camera_path: self.camera_path.into_iter().map(turn).collect(),
+            emitters: self.emitters.into_iter().map(emitter).collect(),
+            ..self
+        }
+    }
+
+    // 把预制件盖到地图上，左上角在 (col, row)
+    fn stamp(&mut self, prefab: &Map, col: usize, row: usize) -> Result<(), String> {
+        if col + prefab.width > self.width || row + prefab.height > self.height {
+            return Err(format!("{}x{} at {} {} does not fit in the map", prefab.width, prefab.height, col, row));
+        }
+        for y in 0..prefab.height {
+            for x in 0..prefab.width {
+                let cell = prefab.cell(x, y);
+                if cell != Cell::Void {
+                    self.cells[(row + y) * self.width + col + x] = cell;
+                }
+            }
+        }
+        self.prompts.extend(prefab.prompts.iter().cloned());
+        self.pickups.extend(prefab.pickups.iter().map(|pickup| PickupSpawn { col: pickup.col + col, row: pickup.row + row, ..pickup.clone() }));
+        self.cars.extend(prefab.cars.iter().map(|car| CarSpawn { col: car.col + col, row: car.row + row, ..car.clone() }));
+        self.markings.extend(prefab.markings.iter().map(|marking| MarkingSpawn { col: marking.col + col, row: marking.row + row, ..marking.clone() }));
+        self.dogs.extend(prefab.dogs.iter().map(|(x, y)| (x + col, y + row)));
+        self.hazards.extend(prefab.hazards.iter().map(|hazard| HazardSpawn { col: hazard.col + col, row: hazard.row + row, ..hazard.clone() }));
+        self.targets.extend(prefab.targets.iter().map(|target| TargetSpawn { col: target.col + col, row: target.row + row, ..target.clone() }));
+        self.range_resets.extend(prefab.range_resets.iter().copied());
+        self.alarms.extend(prefab.alarms.iter().map(|alarm| AlarmSpawn { col: alarm.col + col, row: alarm.row + row, ..alarm.clone() }));
+        self.emitters.extend(prefab.emitters.iter().map(|emitter| match *emitter {
+            EmitterSpawn::Rain { from, to, rate } => EmitterSpawn::Rain { from: (from.0 + col, from.1 + row), to: (to.0 + col, to.1 + row), rate },
+            dust => dust,
+        }));
+        Ok(())
+    }
+
+    // 门、玻璃或铁丝网左右的实心格子比上下多时沿 x 轴排列（隔断的一端可以是空地），
+    // 一样多时要求左右两边都是实心格子，否则沿 z 轴
+    pub fn runs_along_x(&self, col: usize, row: usize) -> bool {
+        let solid = |col: usize, row: usize| self.cell(col, row).is_solid() as u32;
+        let horizontal = solid(col.wrapping_sub(1), row) + solid(col + 1, row);
+        let vertical = solid(col, row.wrapping_sub(1)) + solid(col, row + 1);
+        horizontal > vertical || (horizontal == vertical && horizontal == 2)
+    }
+
+    // 每个玻璃格子一块玻璃（半透明物体按块排序）
+    pub fn glass_panes(&self) -> Vec<WallSegment> {
+        self.partition_segments(Cell::Glass)
+    }
+
+    // 每个铁丝网格子一段铁丝网
+    pub fn fence_segments(&self) -> Vec<WallSegment> {
+        self.partition_segments(Cell::Fence)
+    }
+
+    // 玻璃、铁丝网这类隔断每格一段。墙体线段止于格子中心，
+    // 所以挨着墙的一端要延长到墙格子的中心
+    fn partition_segments(&self, kind: Cell) -> Vec<WallSegment> {
+        let mut panes = Vec::new();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if self.cell(col, row) != kind {
+                    continue;
+                }
+                let center = self.cell_center(col, row);
+                let (axis, before, after) = if self.runs_along_x(col, row) {
+                    (Vec3::X, self.cell(col - 1, row), self.cell(col + 1, row))
+                } else {
+                    (Vec3::Z, self.cell(col, row.wrapping_sub(1)), self.cell(col, row + 1))
+                };
+                let extend = |neighbor: Cell| match neighbor {
+                    Cell::Wall => CELL_SIZE,
+                    // 门板本身已经延伸到这个格子的中心
+                    Cell::Door(_) => 0.0,
+                    _ => CELL_SIZE / 2.0,
+                };
+                let start = center - axis * extend(before);
+                let end = center + axis * extend(after);
+                panes.push(WallSegment { start: start.to_array(), end: end.to_array() });
+            }
+        }
+        panes
+    }
+
+    pub fn water_cells(&self) -> Vec<(usize, usize)> {
+        self.cells_of(Cell::Water)
+    }
+
+    pub fn grate_cells(&self) -> Vec<(usize, usize)> {
+        self.cells_of(Cell::Grate)
+    }
+
+    pub fn pillar_cells(&self) -> Vec<(usize, usize)> {
+        self.cells_of(Cell::Pillar)
+    }
+
+    // 车道箭头：格子中心和箭头指向
+    pub fn lane_arrows(&self) -> Vec<(Vec3, Vec3)> {
+        let mut arrows = Vec::new();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if let Cell::Lane(direction) = self.cell(col, row) {
+                    arrows.push((self.cell_center(col, row), direction.vector()));
+                }
+            }
+        }
+        arrows
+    }
+
+    // 车道：从每个箭头格子沿箭头的轴线向两边延伸到不能走的格子为止，重复的只保留一条
+    pub fn lane_runs(&self) -> Vec<WallSegment> {
+        let mut runs: Vec<WallSegment> = Vec::new();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let Cell::Lane(direction) = self.cell(col, row) else {
+                    continue;
+                };
+                let (dc, dr) = match direction {
+                    Direction::Up | Direction::Down => (0, 1),
+                    Direction::Left | Direction::Right => (1, 0),
+                };
+                let walkable = |col: usize, row: usize| self.cell(col, row).is_walkable();
+                let (mut first, mut last) = ((col, row), (col, row));
+                while walkable(first.0.wrapping_sub(dc), first.1.wrapping_sub(dr)) {
+                    first = (first.0 - dc, first.1 - dr);
+                }
+                while walkable(last.0 + dc, last.1 + dr) {
+                    last = (last.0 + dc, last.1 + dr);
+                }
+                let run = WallSegment {
+                    start: self.cell_center(first.0, first.1).to_array(),
+                    end: self.cell_center(last.0, last.1).to_array(),
+                };
+                if !runs.iter().any(|other| other.start == run.start && other.end == run.end) {
+                    runs.push(run);
+                }
+            }
+        }
+        runs
+    }
+
+    // 天花板灯的位置：每隔 light_spacing 个格子一盏，只挂在能走到的格子上方
+    pub fn light_cells(&self) -> Vec<(usize, usize)> {
+        let offset = self.light_spacing / 2;
+        let mut cells = Vec::new();
+        for row in (offset..self.height).step_by(self.light_spacing) {
+            for col in (offset..self.width).step_by(self.light_spacing) {
+                if self.cell(col, row).is_walkable() {
+                    cells.push((col, row));
+                }
+            }
+        }
+        cells
+    }
+
+    // 车位之间的分隔线（地面上的线段）。相连的停车位格子取包围盒作为一排车位，
+    // 长度为车位深度的一边是车头方向，另一边每个车位宽度画一条线，两端都有
+    pub fn parking_lines(&self) -> Vec<WallSegment> {
+        let mut lines = Vec::new();
+        let mut visited = vec![false; self.cells.len()];
+        for index in 0..self.cells.len() {
+            if visited[index] || self.cells[index] != Cell::Parking {
+                continue;
+            }
+            let (min, max) = self.flood_fill(index, &mut visited);
+            // 包围盒的边缘（世界坐标）
+            let half = Vec3::new(CELL_SIZE / 2.0, 0.0, CELL_SIZE / 2.0);
+            let (low, high) = (self.cell_center(min.0, min.1) - half, self.cell_center(max.0, max.1) + half);
+            let (cols, rows) = (max.0 - min.0 + 1, max.1 - min.1 + 1);
+            // 车位沿 x 排开时分隔线沿 z
+            let along_x = rows == BAY_DEPTH || (cols != BAY_DEPTH && cols >= rows);
+            let (length, axis, across) = if along_x { (cols, Vec3::X, Vec3::Z) } else { (rows, Vec3::Z, Vec3::X) };
+            let bays = (length / BAY_WIDTH).max(1);
+            let step = (high - low).dot(axis) / bays as f32;
+            for bay in 0..=bays {
+                let start = low + axis * (step * bay as f32);
+                let end = start + across * (high - low).dot(across);
+                lines.push(WallSegment { start: start.to_array(), end: end.to_array() });
+            }
+        }
+        lines
+    }
+
+    // 从 start 出发走得到的格子（上下左右相邻的可走格子）
+    fn reachable_from(&self, start: (usize, usize)) -> Vec<bool> {
+        let mut reachable = vec![false; self.cells.len()];
+        let mut stack = vec![start];
+        reachable[start.1 * self.width + start.0] = true;
+        while let Some((col, row)) = stack.pop() {
+            let neighbors = [(col.wrapping_sub(1), row), (col + 1, row), (col, row.wrapping_sub(1)), (col, row + 1)];
+            for (col, row) in neighbors {
+                if self.cell(col, row).is_walkable() && !reachable[row * self.width + col] {
+                    reachable[row * self.width + col] = true;
+                    stack.push((col, row));
+                }
+            }
+        }
+        reachable
+    }
+
+    // 从 index 开始标记相连的同类格子，返回包围盒的左上和右下格子
+    fn flood_fill(&self, index: usize, visited: &mut [bool]) -> ((usize, usize), (usize, usize)) {
+        let kind = self.cells[index];
+        let mut stack = vec![index];
+        visited[index] = true;
+        let (mut min, mut max) = ((usize::MAX, usize::MAX), (0, 0));
+        while let Some(index) = stack.pop() {
+            let (col, row) = (index % self.width, index / self.width);
+            min = (min.0.min(col), min.1.min(row));
+            max = (max.0.max(col), max.1.max(row));
+            let neighbors = [(col.wrapping_sub(1), row), (col + 1, row), (col, row.wrapping_sub(1)), (col, row + 1)];
+            for (col, row) in neighbors {
+                if self.cell(col, row) == kind && !visited[row * self.width + col] {
+                    visited[row * self.width + col] = true;
+                    stack.push(row * self.width + col);
+                }
+            }
+        }
+        (min, max)
+    }
+
+    fn cells_of(&self, kind: Cell) -> Vec<(usize, usize)> {
+        let mut cells = Vec::new();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if self.cell(col, row) == kind {
+                    cells.push((col, row));
+                }
+            }
+        }
+        cells
+    }
+
+    // 所有门的格子和锁
+    pub fn doors(&self) -> Vec<(usize, usize, Option<KeyColor>)> {
+        let mut doors = Vec::new();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if let Cell::Door(lock) = self.cell(col, row) {
+                    doors.push((col, row, lock));
+                }
+            }
+        }
+        doors
+    }
+
+    pub fn cell(&self, col: usize, row: usize) -> Cell {
+        if col < self.width && row < self.height {
+            self.cells[row * self.width + col]
+        } else {
+            Cell::Void
+        }
+    }
+
+    fn find(&self, predicate: impl Fn(Cell) -> bool) -> Option<(usize, usize)> {
+        let index = self.cells.iter().position(|cell| predicate(*cell))?;
+        Some((index % self.width, index / self.width))
+    }
+
+    // 格子中心的世界坐标（地图中心在原点）
+    pub fn cell_center(&self, col: usize, row: usize) -> Vec3 {
+        Vec3::new(
+            (col as f32 - (self.width - 1) as f32 / 2.0) * CELL_SIZE,
+            0.0,
+            (row as f32 - (self.height - 1) as f32 / 2.0) * CELL_SIZE,
+        )
+    }
+
+    pub fn spawn_point(&self) -> Vec3 {
+        let (col, row) = self.find(|cell| cell == Cell::Spawn).unwrap_or((0, 0));
+        self.cell_center(col, row)
+    }
+
+    // 所有可以重生的格子（出生点和重生点）
+    pub fn respawn_cells(&self) -> Vec<(usize, usize)> {
+        (0..self.cells.len())
+            .filter(|index| matches!(self.cells[*index], Cell::Spawn | Cell::Respawn))
+            .map(|index| (index % self.width, index / self.width))
+            .collect()
+    }
+
+    pub fn enemy_spawns(&self) -> Vec<Vec3> {
+        (0..self.cells.len())
+            .filter(|index| self.cells[*index] == Cell::EnemySpawn)
+            .map(|index| self.cell_center(index % self.width, index / self.width))
+            .collect()
+    }
+
+    // 世界坐标所在的格子
+    pub fn world_to_cell(&self, position: Vec3) -> Option<(usize, usize)> {
+        let col = (position.x / CELL_SIZE + (self.width - 1) as f32 / 2.0).round();
+        let row = (position.z / CELL_SIZE + (self.height - 1) as f32 / 2.0).round();
+        if col < 0.0 || row < 0.0 || col as usize >= self.width || row as usize >= self.height {
+            return None;
+        }
+        Some((col as usize, row as usize))
+    }
+
+    // 把连续的墙格子合并成墙体线段：先横向，再纵向，长度为 1 的片段跳过
+    // （单独的墙格子会作为纵向的短墙生成）
+    pub fn wall_segments(&self) -> Vec<WallSegment> {
+        let mut segments = Vec::new();
+        let is_wall = |col: usize, row: usize| self.cell(col, row) == Cell::Wall;
+
+        for row in 0..self.height {
+            let mut col = 0;
+            while col < self.width {
+                if !is_wall(col, row) {
+                    col += 1;
+                    continue;
+                }
+                let start = col;
+                while col + 1 < self.width && is_wall(col + 1, row) {
+                    col += 1;
+                }
+                if col > start {
+                    segments.push(self.segment((start, row), (col, row)));
+                }
+                col += 1;
+            }
+        }
+
+        for col in 0..self.width {
+            let mut row = 0;
+            while row < self.height {
+                if !is_wall(col, row) {
+                    row += 1;
+                    continue;
+                }
+                let start = row;
+                while row + 1 < self.height && is_wall(col, row + 1) {
+                    row += 1;
+                }
+                let isolated = row == start
+                    && !is_wall(col.wrapping_sub(1), row)
+                    && !is_wall(col + 1, row);
+                if row > start || isolated {
+                    segments.push(self.segment((col, start), (col, row)));
+                }
+                row += 1;
+            }
+        }
+
+        segments
+    }
+
+    fn segment(&self, from: (usize, usize), to: (usize, usize)) -> WallSegment {
+        let mut start = self.cell_center(from.0, from.1);
+        let mut end = self.cell_center(to.0, to.1);
+        // 单独的墙格子：生成一段 1 米长的短墙
+        if from == to {
+            start.z -= CELL_SIZE / 2.0;
+            end.z += CELL_SIZE / 2.0;
+        }
+        WallSegment { start: start.to_array(), end: end.to_array() }
+    }
+
+    // 每种触发字母的所有格子合成一个包围盒
+    pub fn trigger_zones(&self) -> Vec<TriggerZone> {
+        let mut zones: Vec<TriggerZone> = Vec::new();
+        let half = Vec3::new(CELL_SIZE / 2.0, 0.0, CELL_SIZE / 2.0);
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if let Cell::Trigger(id) = self.cell(col, row) {
+                    let center = self.cell_center(col, row);
+                    let (min, max) = (center - half, center + half + Vec3::Y * WALL_HEIGHT);
+                    match zones.iter_mut().find(|zone| zone.id == id) {
+                        Some(zone) => {
+                            zone.min = zone.min.min(min);
+                            zone.max = zone.max.max(max);
+                        }
+                        None => zones.push(TriggerZone { id, min, max }),
+                    }
+                }
+            }
+        }
+        zones
+    }
+
+    // 所有触发区域格子（用于在地面上画标记）
+    pub fn trigger_cells(&self) -> Vec<(usize, usize)> {
+        let mut cells = Vec::new();
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if let Cell::Trigger(_) = self.cell(col, row) {
+                    cells.push((col, row));
+                }
+            }
+        }
+        cells
+    }
+}
+
+// "enter a | 文字" 或 "action fire | 文字"
+fn parse_prompt(value: &str) -> Result<Prompt, String> {
+    let (condition, text) = value.split_once('|').ok_or("prompt needs 'condition | text'")?;
+    let mut parts = condition.split_whitespace();
+    let condition = match (parts.next(), parts.next()) {
+        (Some("enter"), Some(id)) if id.len() == 1 && id.chars().all(|c| c.is_ascii_lowercase()) => {
+            PromptCondition::EnterTrigger(id.chars().next().unwrap_or('a'))
+        }
+        (Some("action"), Some(action)) => PromptCondition::Action(action.to_string()),
+        _ => return Err(format!("unknown prompt condition '{}'", condition.trim())),
+    };
+    Ok(Prompt { condition, text: text.trim().to_string() })
+}
+
+// "pickup: rifle 10 5"（武器名、列、行）、"keycard: red 10 5" 或 "armor: 50 10 5"
+fn parse_pickup(key: &str, value: &str) -> Result<PickupSpawn, String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [name, col, row] = parts[..] else {
+        return Err(format!("{} needs 'name col row'", key));
+    };
+    let item = match key {
+        "keycard" => PickupItem::Keycard(KeyColor::from_name(name).ok_or_else(|| format!("unknown keycard color '{}'", name))?),
+        "armor" => match name.parse::<f32>() {
+            Ok(amount) if amount > 0.0 && amount.is_finite() => PickupItem::Armor(amount),
+            _ => return Err(format!("invalid armor amount '{}'", name)),
+        },
+        _ => PickupItem::Weapon(name.to_string()),
+    };
+    let col = col.parse().map_err(|_| format!("invalid column '{}'", col))?;
+    let row = row.parse().map_err(|_| format!("invalid row '{}'", row))?;
+    Ok(PickupSpawn { item, col, row })
+}
+
+// "car: red 10 5 x"（车漆颜色、列、行、车头朝向 x 或 z）
+fn parse_car(value: &str) -> Result<CarSpawn, String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [paint, col, row, axis] = parts[..] else {
+        return Err("car needs 'paint col row x|z'".to_string());
+    };
+    let along_x = match axis {
+        "x" => true,
+        "z" => false,
+        _ => return Err(format!("invalid car axis '{}'", axis)),
+    };
+    let col = col.parse().map_err(|_| format!("invalid column '{}'", col))?;
+    let row = row.parse().map_err(|_| format!("invalid row '{}'", row))?;
+    Ok(CarSpawn { paint: paint.to_string(), col, row, along_x })
+}
+
+// "marking: 12 3 15 up"（arrow、handicap 或车位编号，列、行、标记上方的朝向）
+fn parse_marking(value: &str) -> Result<MarkingSpawn, String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [kind, col, row, facing] = parts[..] else {
+        return Err("marking needs 'kind col row up|down|left|right'".to_string());
+    };
+    let kind = match kind {
+        "arrow" => MarkingKind::Arrow,
+        "handicap" => MarkingKind::Handicap,
+        number => MarkingKind::Number(number.parse().map_err(|_| format!("unknown marking '{}'", number))?),
+    };
+    let facing = Direction::from_name(facing).ok_or_else(|| format!("invalid marking direction '{}'", facing))?;
+    let col = col.parse().map_err(|_| format!("invalid column '{}'", col))?;
+    let row = row.parse().map_err(|_| format!("invalid row '{}'", row))?;
+    Ok(MarkingSpawn { kind, col, row, facing })
+}
+
+// "hazard: fire 10 5"（data/hazards.json 里的名字、列、行）
+fn parse_hazard(value: &str) -> Result<HazardSpawn, String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [id, col, row] = parts[..] else {
+        return Err("hazard needs 'id col row'".to_string());
+    };
+    let col = col.parse().map_err(|_| format!("invalid column '{}'", col))?;
+    let row = row.parse().map_err(|_| format!("invalid row '{}'", row))?;
+    Ok(HazardSpawn { id: id.to_string(), col, row })
+}
+
+// "target: popup 6 3" 或 "target: slide 2 8 right 10"（弹起或滑动、列、行，滑动的方向和格数）
+fn parse_target(value: &str) -> Result<TargetSpawn, String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let (motion, col, row) = match parts[..] {
+        ["popup", col, row] => (TargetMotion::PopUp, col, row),
+        ["slide", col, row, direction, distance] => {
+            let direction = Direction::from_name(direction).ok_or_else(|| format!("invalid target direction '{}'", direction))?;
+            let distance = distance.parse().ok().filter(|distance| *distance > 0).ok_or_else(|| format!("invalid target distance '{}'", distance))?;
+            (TargetMotion::Slide { direction, distance }, col, row)
+        }
+        _ => return Err("target needs 'popup col row' or 'slide col row up|down|left|right cells'".to_string()),
+    };
+    let col = col.parse().map_err(|_| format!("invalid column '{}'", col))?;
+    let row = row.parse().map_err(|_| format!("invalid row '{}'", row))?;
+    Ok(TargetSpawn { motion, col, row })
+}
+
+// "range_reset: r"（触发区域的小写字母）
+fn parse_trigger_id(value: &str) -> Result<char, String> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(id @ 'a'..='z'), None) => Ok(id),
+        _ => Err(format!("invalid trigger '{}'", value)),
+    }
+}
+
+// "alarm: 15 9" 或 "alarm: 15 9 a b"（列、行，后面是绊线的触发区域）
+fn parse_alarm(value: &str) -> Result<AlarmSpawn, String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [col, row, ref tripwires @ ..] = parts[..] else {
+        return Err("alarm needs 'col row [tripwire...]'".to_string());
+    };
+    let col = col.parse().map_err(|_| format!("invalid column '{}'", col))?;
+    let row = row.parse().map_err(|_| format!("invalid row '{}'", row))?;
+    let tripwires = tripwires.iter().map(|id| parse_trigger_id(id)).collect::<Result<_, _>>()?;
+    Ok(AlarmSpawn { col, row, tripwires })
+}
+
+// "dust: 6 120"（半径、每秒粒子数）
+fn parse_dust(value: &str) -> Result<EmitterSpawn, String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [radius, rate] = parts[..] else {
+        return Err("dust needs 'radius rate'".to_string());
+    };
+    let radius = radius.parse().ok().filter(|radius| *radius > 0.0).ok_or_else(|| format!("invalid dust radius '{}'", radius))?;
+    Ok(EmitterSpawn::Dust { radius, rate: parse_rate(rate)? })
+}
+
+// "rain: 1 4 4 6 2000"（一个角的列、行，对角的列、行，每秒粒子数）
+fn parse_rain(value: &str) -> Result<EmitterSpawn, String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [from_col, from_row, to_col, to_row, rate] = parts[..] else {
+        return Err("rain needs 'col row col row rate'".to_string());
+    };
+    let column = |col: &str| col.parse::<usize>().map_err(|_| format!("invalid column '{}'", col));
+    let row = |row: &str| row.parse::<usize>().map_err(|_| format!("invalid row '{}'", row));
+    let (from, to) = ((column(from_col)?, row(from_row)?), (column(to_col)?, row(to_row)?));
+    Ok(EmitterSpawn::Rain { from: (from.0.min(to.0), from.1.min(to.1)), to: (from.0.max(to.0), from.1.max(to.1)), rate: parse_rate(rate)? })
+}
+
+fn parse_rate(value: &str) -> Result<f32, String> {
+    value.parse().ok().filter(|rate: &f32| *rate >= 0.0).ok_or_else(|| format!("invalid particle rate '{}'", value))
+}
+
+// 预制件的文本：先读 maps/prefabs/，再找内置的
+fn load_prefab(name: &str) -> Result<String, String> {
+    match assets::read_to_string(&prefab_path(name)) {
+        Ok(text) => Ok(text),
+        Err(_) => BUILTIN_PREFABS
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, text)| text.to_string())
+            .ok_or_else(|| "not found".to_string()),
+    }
+}
+
+pub fn path(name: &str) -> PathBuf {
+    Path::new("maps").join(format!("{}.map", name))
+}
+
+fn prefab_path(name: &str) -> PathBuf {
+    Path::new("maps").join("prefabs").join(format!("{}.map", name))
+}
+
+// 浏览器版启动时要下载的地图和预制件文件，以及内置地图的名字
+#[cfg(target_arch = "wasm32")]
+pub fn builtin_files() -> (Vec<PathBuf>, Vec<&'static str>) {
+    let names: Vec<&'static str> = BUILTIN_MAPS.iter().map(|(name, _)| *name).collect();
+    let files = names.iter().map(|name| path(name)).chain(BUILTIN_PREFABS.iter().map(|(name, _)| prefab_path(name))).collect();
+    (files, names)
+}
+
+// 格子在文件里的行号（从 1 开始）和那一行的内容
+fn at_cell(text: &str, col: usize, row: usize, e: String) -> String {
+    let header = text.lines().position(|line| line.trim() == "---").map_or(0, |index| index + 1);
+    let line = header + row + 1;
+    format!("line {}: {}{}", line, e, schema::line_context(text, line, Some(col + 1)))
+}
+
+// prefab: 名字 列 行 [角度]，返回顺时针转几个 90 度
+fn parse_prefab(value: &str) -> Result<(String, usize, usize, usize), String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let (name, col, row, angle) = match parts[..] {
+        [name, col, row] => (name, col, row, "0"),
+        [name, col, row, angle] => (name, col, row, angle),
+        _ => return Err("expected 'name col row [angle]'".to_string()),
+    };
+    let col = col.parse().map_err(|_| format!("invalid column '{}'", col))?;
+    let row = row.parse().map_err(|_| format!("invalid row '{}'", row))?;
+    let turns = match angle {
+        "0" => 0,
+        "90" => 1,
+        "180" => 2,
+        "270" => 3,
+        other => return Err(format!("invalid prefab angle '{}' (0, 90, 180 or 270)", other)),
+    };
+    Ok((name.to_string(), col, row, turns))
+}
+
+// "dog: 15 20"（列、行）
+fn parse_cell(value: &str) -> Result<(usize, usize), String> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [col, row] = parts[..] else {
+        return Err("expected 'col row'".to_string());
+    };
+    let col = col.parse().map_err(|_| format!("invalid column '{}'", col))?;
+    let row = row.parse().map_err(|_| format!("invalid row '{}'", row))?;
+    Ok((col, row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stamps_a_rotated_door_frame_into_a_vertical_wall() {
+        let text = "version: 1\nprefab: door_frame 2 1 90\n---\n#######\n#..#..#\n#..#..#\n#..#..#\n#######";
+        let map = Map::parse_layout("test", text, 0).unwrap();
+        // 门框转过来以后门在竖着的墙上，两边是触发区域
+        assert_eq!(map.cell(3, 2), Cell::Door(None));
+        assert_eq!(map.cell(2, 2), Cell::Trigger('d'));
+        assert_eq!(map.cell(4, 2), Cell::Trigger('d'));
+        assert!(!map.runs_along_x(3, 2));
+        // 预制件里的空格不改动原来的格子
+        assert_eq!((map.cell(2, 1), map.cell(3, 1), map.cell(4, 3)), (Cell::Floor, Cell::Wall, Cell::Floor));
+    }
+
+    #[test]
+    fn rotating_a_prefab_turns_its_cars_and_lane_arrows() {
+        let row = Map::parse_layout("parking_row", &load_prefab("parking_row").unwrap(), 1).unwrap().turned();
+        assert_eq!((row.width, row.height), (5, 11));
+        assert_eq!([row.cell(4, 0), row.cell(0, 10), row.cell(2, 5)], [Cell::Pillar, Cell::Pillar, Cell::Parking]);
+        let cars: Vec<_> = row.cars.iter().map(|car| (car.paint.as_str(), car.col, car.row, car.along_x)).collect();
+        assert_eq!(cars, [("red", 2, 2, true), ("silver", 2, 8, true)]);
+
+        let lane = Map::parse_layout("lane", "---\n>.\n..", 1).unwrap().turned();
+        assert_eq!(lane.cell(1, 0), Cell::Lane(Direction::Down));
+        // 转四次回到原样
+        let lane = lane.turned().turned().turned();
+        assert_eq!(lane.cell(0, 0), Cell::Lane(Direction::Right));
+    }
+
+    #[test]
+    fn builtin_maps_parse() {
+        for (name, text) in BUILTIN_MAPS {
+            Map::parse(name, text).unwrap_or_else(|e| panic!("{}: {}", name, e));
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::map::Map;
+use crate::schema;
+
+// 从图片导入地图：每个像素一个格子，颜色按图例换成格子字符（透明像素是地图外），
+// 写成 maps/<图片名>.map。默认图例是 data/map_legend.json，图片旁边有同名的 .legend.json 时用它
+
+// data/map_legend.json 的格式版本
+const LEGEND_VERSION: u32 = 1;
+
+#[derive(serde::Deserialize)]
+struct LegendEntry {
+    // #rrggbb
+    color: String,
+    cell: char,
+}
+
+#[derive(serde::Deserialize)]
+struct Legend {
+    cells: Vec<LegendEntry>,
+}
+
+fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+// 图例：颜色到格子字符
+fn load_legend(image_path: &Path) -> Result<HashMap<[u8; 3], char>, String> {
+    let custom = image_path.with_extension("legend.json");
+    let (source, text) = match std::fs::read_to_string(&custom) {
+        Ok(text) => (custom.display().to_string(), text),
+        Err(_) => ("data/map_legend.json".to_string(), include_str!("../data/map_legend.json").to_string()),
+    };
+    let legend: Legend = schema::parse_json(&text, LEGEND_VERSION).map_err(|e| format!("{}: {}", source, e))?;
+    let mut colors = HashMap::new();
+    for entry in legend.cells {
+        let color = parse_color(&entry.color).ok_or_else(|| format!("{}: invalid color '{}'", source, entry.color))?;
+        colors.insert(color, entry.cell);
+    }
+    Ok(colors)
+}
+
+// 把图片转成地图文本
+fn convert(image_path: &Path, name: &str) -> Result<String, String> {
+    let legend = load_legend(image_path)?;
+    let image = image::open(image_path).map_err(|e| format!("{}: {}", image_path.display(), e))?.to_rgba8();
+    let mut rows = Vec::with_capacity(image.height() as usize);
+    let mut unknown = Vec::new();
+    for y in 0..image.height() {
+        let mut row = String::with_capacity(image.width() as usize);
+        for x in 0..image.width() {
+            let [r, g, b, a] = image.get_pixel(x, y).0;
+            if a < 128 {
+                row.push(' ');
+                continue;
+            }
+            match legend.get(&[r, g, b]) {
+                Some(cell) => row.push(*cell),
+                None => {
+                    unknown.push(format!("pixel {} {}: color #{:02x}{:02x}{:02x} is not in the legend", x, y, r, g, b));
+                    row.push(' ');
+                }
+            }
+        }
+        rows.push(row.trim_end().to_string());
+    }
+    if !unknown.is_empty() {
+        // 整张图画错颜色时只列出前几个
+        let more = unknown.len().saturating_sub(10);
+        unknown.truncate(10);
+        if more > 0 {
+            unknown.push(format!("... and {} more", more));
+        }
+        return Err(unknown.join("\n"));
+    }
+    Ok(format!(
+        "# 从 {} 导入（--import-map-image）\n# 地图格式的版本\nversion: 1\ntitle: {}\n---\n{}\n",
+        image_path.display(),
+        name,
+        rows.join("\n")
+    ))
+}
+
+// --import-map-image 模式：转换一张图片，检查无误后存到 maps/ 下
+pub fn import_command(image_path: &Path) {
+    let Some(name) = image_path.file_stem().and_then(|stem| stem.to_str()) else {
+        eprintln!("无效的图片路径: {}", image_path.display());
+        return;
+    };
+    let text = match convert(image_path, name) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("无法导入 {}:\n{}", image_path.display(), e);
+            return;
+        }
+    };
+    // 和加载地图时一样检查出生点、能不能走到等
+    if let Err(e) = Map::parse(name, &text) {
+        eprintln!("导入的地图有问题，没有保存:\n{}", e);
+        return;
+    }
+    let path: PathBuf = Path::new("maps").join(format!("{}.map", name));
+    if path.exists() {
+        eprintln!("{} 已存在，没有覆盖", path.display());
+        return;
+    }
+    match std::fs::write(&path, text) {
+        Ok(()) => println!("地图已导入: {}（用 /map {} 加载）", path.display(), name),
+        Err(e) => eprintln!("保存地图失败 {}: {}", path.display(), e),
+    }
+}
@@ -0,0 +1,112 @@
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::map::Map;
+use crate::paths;
+
+// 局域网里分享自己做的地图：HTTP 接口（端口 3030）上
+//   POST /maps       上传 {"name": "地图名", "text": "地图文件内容"}，检查能解析后保存，返回地图信息
+//   GET  /maps       列出保存的所有地图
+//   GET  /maps/{id}  下载地图文件，放到 maps/ 或者 mod 的 maps/ 下就能玩
+// 地图存在用户数据目录的 shared_maps/ 下，id 是内容的哈希，同样的地图只存一份
+
+// 上传的地图文件最大字节数
+pub const MAX_UPLOAD: u64 = 256 * 1024;
+const MAX_NAME_LEN: usize = 32;
+
+// POST /maps 的请求体
+#[derive(Debug, serde::Deserialize)]
+pub struct Upload {
+    pub name: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SharedMap {
+    pub id: String,
+    pub name: String,
+    pub title: String,
+    pub width: usize,
+    pub height: usize,
+    pub size: usize,
+    // 上传时间（Unix 秒）
+    pub uploaded: u64,
+}
+
+// FNV-1a，每次运行和每个版本都一样（std 的哈希不保证）
+fn content_id(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3));
+    format!("{:016x}", hash)
+}
+
+// 地图名只能用小写字母、数字、- 和 _，和 maps/ 下的文件名一样
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= MAX_NAME_LEN && name.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+}
+
+// id 是 16 位十六进制，同时保证拼出来的路径不会跑出目录
+fn valid_id(id: &str) -> bool {
+    id.len() == 16 && id.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+// 检查并保存上传的地图，出错时返回给上传者看的原因
+pub fn upload(upload: Upload) -> Result<SharedMap, String> {
+    if !valid_name(&upload.name) {
+        return Err(format!("invalid map name '{}': use 1-{} of a-z, 0-9, - and _", upload.name, MAX_NAME_LEN));
+    }
+    if upload.text.len() as u64 > MAX_UPLOAD {
+        return Err(format!("map is larger than {} bytes", MAX_UPLOAD));
+    }
+    let map = Map::parse(&upload.name, &upload.text)?;
+    let id = content_id(&upload.text);
+    let dir = paths::shared_maps();
+    let meta_path = dir.join(format!("{}.json", id));
+    // 已经有人传过同样的内容
+    if let Some(existing) = read_meta(&meta_path) {
+        return Ok(existing);
+    }
+    let info = SharedMap {
+        id: id.clone(),
+        name: upload.name,
+        title: map.title,
+        width: map.width,
+        height: map.height,
+        size: upload.text.len(),
+        uploaded: SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+    };
+    let meta = serde_json::to_string_pretty(&info).map_err(|e| e.to_string())?;
+    let save = std::fs::create_dir_all(&dir)
+        .and_then(|_| std::fs::write(dir.join(format!("{}.map", id)), &upload.text))
+        .and_then(|_| std::fs::write(&meta_path, meta));
+    if let Err(e) = save {
+        eprintln!("无法保存分享的地图 {}: {}", dir.display(), e);
+        return Err("cannot store the map on the server".to_string());
+    }
+    println!("收到分享的地图 {} ({}，{}x{})", info.name, info.id, info.width, info.height);
+    Ok(info)
+}
+
+fn read_meta(path: &Path) -> Option<SharedMap> {
+    serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+// 所有分享的地图，新上传的在前
+pub fn list() -> Vec<SharedMap> {
+    let mut maps: Vec<SharedMap> = match std::fs::read_dir(paths::shared_maps()) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| read_meta(&path))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    maps.sort_by(|a, b| b.uploaded.cmp(&a.uploaded).then_with(|| a.name.cmp(&b.name)));
+    maps
+}
+
+pub fn download(id: &str) -> Option<String> {
+    if !valid_id(id) {
+        return None;
+    }
+    std::fs::read_to_string(paths::shared_maps().join(format!("{}.map", id))).ok()
+}
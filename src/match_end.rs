@@ -0,0 +1,184 @@
+use std::time::Duration;
+
+use instant::Instant;
+
+use crate::map;
+use crate::mods;
+use crate::net::Score;
+use crate::server_config;
+use crate::ui::UiBatch;
+
+// 联机比赛的结束和换图：主机上一局到了时间或者有人击杀数到了上限时（见 server.toml）结束，
+// 所有人看到这一局的总结（计分板和奖项），在轮换列表里的几张地图中投票，投票结束后一起加载得票最多的地图开始下一局
+
+pub const VOTE_DURATION: Duration = Duration::from_secs(15);
+// 投票的候选地图最多几张
+const MAX_CANDIDATES: usize = 4;
+// 命中率奖至少要开这么多枪
+const MIN_SHOTS_FOR_ACCURACY: u32 = 10;
+
+const WIDTH: f32 = 680.0;
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+const TITLE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.95];
+const TEXT_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 0.95];
+const DIM_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 0.9];
+const AWARD_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 0.95];
+const SELECTED_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.15];
+const VOTED_COLOR: [f32; 4] = [0.3, 1.0, 0.4, 0.95];
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Award {
+    pub title: String,
+    pub name: String,
+    pub value: String,
+}
+
+// 主机在比赛结束时发给所有人
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct MatchSummary {
+    pub map: String,
+    pub scores: Vec<Score>,
+    pub awards: Vec<Award>,
+    pub candidates: Vec<String>,
+}
+
+// 轮换列表：server.toml 里设置的，没有设置时是游戏自带的地图和启用的 mod 带来的地图
+pub fn rotation() -> Vec<String> {
+    let config = server_config::current();
+    if !config.rotation.is_empty() {
+        return config.rotation.clone();
+    }
+    let mut maps = vec![map::DEFAULT_MAP.to_string()];
+    for name in mods::maps() {
+        if !maps.contains(name) {
+            maps.push(name.clone());
+        }
+    }
+    maps
+}
+
+// 投票的候选：轮换列表里当前地图后面的几张，当前地图排在最后（再来一局）
+pub fn candidates(current: &str) -> Vec<String> {
+    let rotation = rotation();
+    let start = rotation.iter().position(|name| name == current).map_or(0, |index| index + 1);
+    let mut candidates: Vec<String> = (0..rotation.len())
+        .map(|i| rotation[(start + i) % rotation.len()].clone())
+        .filter(|name| name != current)
+        .take(MAX_CANDIDATES - 1)
+        .collect();
+    candidates.push(current.to_string());
+    candidates
+}
+
+// 得票最多的候选，一样多时排在前面的胜出，没有人投票时是第一个
+pub fn winner(candidates: &[String], votes: &[usize]) -> String {
+    let counts = count_votes(candidates.len(), votes);
+    let best = (0..candidates.len()).max_by_key(|i| (counts[*i], std::cmp::Reverse(*i))).unwrap_or(0);
+    candidates[best].clone()
+}
+
+pub fn count_votes(candidates: usize, votes: &[usize]) -> Vec<u32> {
+    let mut counts = vec![0; candidates];
+    for vote in votes.iter().filter(|vote| **vote < candidates) {
+        counts[*vote] += 1;
+    }
+    counts
+}
+
+// 击杀最多和命中率最高，没有人符合时不颁这个奖
+pub fn awards(scores: &[Score]) -> Vec<Award> {
+    let mut awards = Vec::new();
+    if let Some(best) = scores.iter().filter(|score| score.kills > 0).max_by_key(|score| (score.kills, std::cmp::Reverse(score.deaths))) {
+        awards.push(Award { title: "MOST KILLS".to_string(), name: best.name.clone(), value: best.kills.to_string() });
+    }
+    let accuracy = |score: &Score| score.hits as f32 / score.shots as f32;
+    if let Some(best) = scores.iter().filter(|score| score.shots >= MIN_SHOTS_FOR_ACCURACY).max_by(|a, b| accuracy(a).total_cmp(&accuracy(b))) {
+        awards.push(Award { title: "BEST ACCURACY".to_string(), name: best.name.clone(), value: format!("{:.0}%", accuracy(best) * 100.0) });
+    }
+    awards
+}
+
+// 比赛结束的画面，投票结束（换图）时关闭
+pub struct SummaryScreen {
+    summary: MatchSummary,
+    opened: Instant,
+    selected: usize,
+    voted: Option<usize>,
+    counts: Vec<u32>,
+}
+
+impl SummaryScreen {
+    pub fn new(summary: MatchSummary) -> Self {
+        let counts = vec![0; summary.candidates.len()];
+        Self { summary, opened: Instant::now(), selected: 0, voted: None, counts }
+    }
+
+    pub fn move_selection(&mut self, down: bool) {
+        let count = self.summary.candidates.len().max(1);
+        self.selected = if down { (self.selected + 1) % count } else { (self.selected + count - 1) % count };
+    }
+
+    // 投给选中的地图，index 为 None 时投给当前高亮的；返回投的是第几个
+    pub fn vote(&mut self, index: Option<usize>) -> Option<usize> {
+        let index = index.unwrap_or(self.selected);
+        if index >= self.summary.candidates.len() {
+            return None;
+        }
+        self.selected = index;
+        self.voted = Some(index);
+        Some(index)
+    }
+
+    pub fn set_counts(&mut self, counts: Vec<u32>) {
+        self.counts = counts;
+    }
+
+    pub fn draw(&self, ui: &mut UiBatch, local: Option<u32>) {
+        let scale = 2.0;
+        let row = UiBatch::line_height(scale) + 6.0;
+        let summary = &self.summary;
+        let lines = summary.scores.len() + summary.awards.len() + summary.candidates.len() + 8;
+        let height = row * lines as f32 + 40.0;
+        let x = ((ui.width() - WIDTH) / 2.0).round();
+        let mut y = ((ui.height() - height) / 2.0).max(10.0).round();
+        ui.rect(x, y, WIDTH, height, PANEL_COLOR);
+        y += 14.0;
+        ui.text_centered(x + WIDTH / 2.0, y, 3.0, TITLE_COLOR, "MATCH OVER");
+        y += UiBatch::line_height(3.0) + 10.0;
+
+        for award in &summary.awards {
+            let text = format!("{:<14} {} ({})", award.title, award.name.to_uppercase(), award.value);
+            ui.text(x + 20.0, y, scale, AWARD_COLOR, &text);
+            y += row;
+        }
+        y += row / 2.0;
+        ui.text(x + 20.0, y, scale, DIM_COLOR, &format!("{:<20} {:>6} {:>7} {:>9}", "PLAYER", "KILLS", "DEATHS", "ACCURACY"));
+        y += row;
+        for score in &summary.scores {
+            let accuracy = if score.shots == 0 { "-".to_string() } else { format!("{:.0}%", score.hits as f32 / score.shots as f32 * 100.0) };
+            let name: String = score.name.to_uppercase().chars().take(20).collect();
+            let color = if Some(score.player_id) == local { AWARD_COLOR } else { TEXT_COLOR };
+            ui.text(x + 20.0, y, scale, color, &format!("{:<20} {:>6} {:>7} {:>9}", name, score.kills, score.deaths, accuracy));
+            y += row;
+        }
+
+        y += row / 2.0;
+        let remaining = VOTE_DURATION.saturating_sub(self.opened.elapsed()).as_secs_f32().ceil();
+        ui.text(x + 20.0, y, scale, TITLE_COLOR, &format!("VOTE FOR THE NEXT MAP ({:.0})", remaining));
+        y += row;
+        for (i, name) in summary.candidates.iter().enumerate() {
+            if i == self.selected {
+                ui.rect(x + 12.0, y - 3.0, WIDTH - 24.0, row, SELECTED_COLOR);
+            }
+            let color = if self.voted == Some(i) { VOTED_COLOR } else { TEXT_COLOR };
+            let again = if *name == summary.map { " (again)" } else { "" };
+            let votes = self.counts.get(i).copied().unwrap_or(0);
+            ui.text(x + 20.0, y, scale, color, &format!("{}. {}{}", i + 1, name.to_uppercase(), again));
+            let votes = format!("{} vote{}", votes, if votes == 1 { "" } else { "s" });
+            ui.text(x + WIDTH - 20.0 - UiBatch::text_width(&votes, scale), y, scale, color, &votes);
+            y += row;
+        }
+        y += row / 2.0;
+        ui.text_centered(x + WIDTH / 2.0, y, scale, DIM_COLOR, "number keys or move + jump to vote");
+    }
+}
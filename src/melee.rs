@@ -0,0 +1,114 @@
+use glam::{Quat, Vec3};
+
+use crate::collision::{self, Aabb, WallCollider};
+
+// 近战：子弹打光时的后备手段。往前挥一下，在眼前一个扇形范围里找最近的、没有被墙挡住的实体，
+// 没有实体时检测扇形里的墙，打在墙上只溅起火花。不用弹药，没有声音，狗听不到
+
+// 挥动的距离（米）和左右展开的半角（弧度）
+pub const RANGE: f32 = 1.6;
+const ARC: f32 = 0.6;
+pub const DAMAGE: f32 = 50.0;
+// 两次挥动之间的间隔（秒）
+const COOLDOWN: f32 = 0.6;
+// 检测墙体时扇形里射线的数量
+const WALL_RAYS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strike {
+    Target { target: usize, point: Vec3, normal: Vec3 },
+    Wall { point: Vec3, normal: Vec3 },
+    Miss,
+}
+
+pub struct Melee {
+    cooldown: f32,
+}
+
+impl Melee {
+    pub fn new() -> Self {
+        Self { cooldown: 0.0 }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.cooldown = (self.cooldown - dt).max(0.0);
+    }
+
+    // 开始一次挥动，还在间隔里时返回 false
+    pub fn swing(&mut self) -> bool {
+        if self.cooldown > 0.0 {
+            return false;
+        }
+        self.cooldown = COOLDOWN;
+        true
+    }
+
+    pub fn reset(&mut self) {
+        self.cooldown = 0.0;
+    }
+}
+
+// 从 origin 往 forward 挥一下打到什么。实体按包围盒上离眼睛最近的点算距离和角度，取最近的一个
+pub fn sweep(colliders: &[WallCollider], targets: &[(usize, Aabb)], origin: Vec3, forward: Vec3) -> Strike {
+    let min_dot = ARC.cos();
+    let target = targets
+        .iter()
+        .filter_map(|(target, aabb)| {
+            let point = origin.clamp(aabb.min, aabb.max);
+            let offset = point - origin;
+            let distance = offset.length();
+            // 眼睛在包围盒里面时直接算打中
+            let dir = offset.try_normalize().unwrap_or(forward);
+            (distance <= RANGE && dir.dot(forward) >= min_dot && collision::count_occluders(colliders, origin, point) == 0).then_some((*target, point, dir, distance))
+        })
+        .min_by(|a, b| a.3.total_cmp(&b.3));
+    if let Some((target, point, dir, _)) = target {
+        return Strike::Target { target, point, normal: -dir };
+    }
+    (0..WALL_RAYS)
+        .filter_map(|i| {
+            let angle = ARC * (i as f32 / (WALL_RAYS - 1) as f32 * 2.0 - 1.0);
+            let dir = Quat::from_rotation_y(angle) * forward;
+            collision::raycast_all(colliders, origin, dir, RANGE).first().map(|hit| (origin + dir * hit.distance, hit.normal, hit.distance))
+        })
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map_or(Strike::Miss, |(point, normal, _)| Strike::Wall { point, normal })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(center: Vec3) -> (usize, Aabb) {
+        (1, Aabb { min: center - Vec3::new(0.3, 0.9, 0.3), max: center + Vec3::new(0.3, 0.9, 0.3) })
+    }
+
+    #[test]
+    fn hits_the_nearest_target_in_front() {
+        let origin = Vec3::new(0.0, 1.6, 0.0);
+        let targets = [target(Vec3::new(0.0, 0.9, -1.0)), (2, target(Vec3::new(0.0, 0.9, -1.5)).1)];
+        match sweep(&[], &targets, origin, Vec3::NEG_Z) {
+            Strike::Target { target, normal, .. } => {
+                assert_eq!(target, 1);
+                assert!(normal.z > 0.0);
+            }
+            strike => panic!("{:?}", strike),
+        }
+        // 背后和太远的都打不到
+        assert_eq!(sweep(&[], &targets, origin, Vec3::Z), Strike::Miss);
+        assert_eq!(sweep(&[], &[target(Vec3::new(0.0, 0.9, -3.0))], origin, Vec3::NEG_Z), Strike::Miss);
+    }
+
+    #[test]
+    fn walls_block_targets() {
+        let origin = Vec3::new(0.0, 1.6, 0.0);
+        let wall = WallCollider::new([-2.0, 0.0, -0.8], [2.0, 0.0, -0.8], 3.0, 0.1);
+        match sweep(&[wall], &[target(Vec3::new(0.0, 0.9, -1.3))], origin, Vec3::NEG_Z) {
+            Strike::Wall { point, normal } => {
+                assert!(point.z > -0.95 && point.z < -0.65);
+                assert!(normal.z > 0.9);
+            }
+            strike => panic!("{:?}", strike),
+        }
+    }
+}
@@ -0,0 +1,838 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use winit::event::VirtualKeyCode;
+
+use crate::axes::{AxisField, AxisTarget, GamepadAxes};
+use crate::demo;
+use crate::input::{Action, Device, InputConfig};
+use crate::mods::{self, Mod};
+use crate::net::{self, LanBrowser};
+use crate::profile::{self, Profile};
+use crate::progression::{self, Perk};
+use crate::settings::Settings;
+use crate::ui::{Nav, UiBatch};
+
+// 菜单颜色
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+const TITLE_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const DIM_COLOR: [f32; 4] = [0.55, 0.55, 0.55, 1.0];
+const HIGHLIGHT_COLOR: [f32; 4] = [0.2, 0.45, 0.9, 0.8];
+// 焦点项的边框
+const FOCUS_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+const ERROR_COLOR: [f32; 4] = [1.0, 0.35, 0.3, 1.0];
+
+// 列表的字号（项目多的主菜单和设置界面用小字），和列表第一行相对面板顶部的位置
+const LIST_SCALE: f32 = 3.0;
+const COMPACT_SCALE: f32 = 2.0;
+const LIST_TOP: f32 = 90.0;
+
+// 菜单操作的结果，交给 State 执行
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuAction {
+    // 第一次从主菜单开始游戏
+    Play,
+    Resume,
+    // 进入新手教程地图
+    Tutorial,
+    // 进入射击练习场
+    PracticeRange,
+    // 在默认地图上开始波次模式
+    Waves,
+    Host,
+    Join(SocketAddr),
+    Disconnect,
+    // 设置界面切换血腥内容、弹壳上限、后处理效果、光照方式、帧率上限、触屏按钮和鼠标平滑/加速
+    ToggleGore,
+    CycleShellCasings,
+    ToggleDepthOfField,
+    ToggleMotionBlur,
+    ToggleCameraTilt,
+    ToggleStatusOverlays,
+    CycleEffectQuality,
+    CycleLighting,
+    CycleFrameLimit,
+    ToggleTouchControls,
+    ToggleMouseSmoothing,
+    ToggleMouseAcceleration,
+    // 视角界面切换鼠标和右摇杆的灵敏度、反转垂直方向和辅助瞄准
+    CycleMouseSensitivityX,
+    CycleMouseSensitivityY,
+    ToggleInvertMouseY,
+    CycleStickSensitivityX,
+    CycleStickSensitivityY,
+    ToggleInvertStickY,
+    CycleAimAssist,
+    ToggleMouseAimAssist,
+    // 摇杆设置界面切换最近用的手柄某个轴的某一项
+    CycleAxis(AxisTarget, AxisField),
+    // mod 界面里开始 mods::maps() 中的第几张地图
+    PlayMap(usize),
+    // 回放 demo::list() 中的第几个录像
+    PlayDemo(usize),
+    // 用一个天赋点给天赋升一级
+    UnlockPerk(Perk),
+    Quit,
+}
+
+// 视角界面的各项，按显示顺序
+const LOOK_ACTIONS: [MenuAction; 8] = [
+    MenuAction::CycleMouseSensitivityX,
+    MenuAction::CycleMouseSensitivityY,
+    MenuAction::ToggleInvertMouseY,
+    MenuAction::CycleStickSensitivityX,
+    MenuAction::CycleStickSensitivityY,
+    MenuAction::ToggleInvertStickY,
+    MenuAction::CycleAimAssist,
+    MenuAction::ToggleMouseAimAssist,
+];
+
+// 按键界面里进入的子界面，列在按键列表下面
+const CONTROLS_ITEMS: [(&str, Screen); 2] = [("LOOK AND AIM >", Screen::Look), ("GAMEPAD AXES >", Screen::GamepadAxes)];
+
+// 设置界面的各项，按显示顺序
+const OPTION_ACTIONS: [MenuAction; 12] = [
+    MenuAction::ToggleGore,
+    MenuAction::CycleShellCasings,
+    MenuAction::ToggleDepthOfField,
+    MenuAction::ToggleMotionBlur,
+    MenuAction::ToggleCameraTilt,
+    MenuAction::ToggleStatusOverlays,
+    MenuAction::CycleEffectQuality,
+    MenuAction::CycleLighting,
+    MenuAction::CycleFrameLimit,
+    MenuAction::ToggleTouchControls,
+    MenuAction::ToggleMouseSmoothing,
+    MenuAction::ToggleMouseAcceleration,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Screen {
+    Main,
+    ServerBrowser,
+    JoinByIp,
+    Stats,
+    // 等级和天赋，从统计界面进入
+    Perks,
+    Controls,
+    // 视角灵敏度和手柄摇杆、扳机的设置，从按键界面进入
+    Look,
+    GamepadAxes,
+    Options,
+    // 启用或停用 mod，开始 mod 带来的地图
+    Mods,
+    // 主机录的比赛录像
+    Demos,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MainItem {
+    Play,
+    Tutorial,
+    Range,
+    Waves,
+    Host,
+    FindGames,
+    JoinByIp,
+    Disconnect,
+    Stats,
+    Controls,
+    Options,
+    Mods,
+    Demos,
+    Quit,
+}
+
+pub struct Menu {
+    pub open: bool,
+    in_game: bool,
+    screen: Screen,
+    selected: usize,
+    ip_input: String,
+    message: Option<String>,
+    browser: Option<LanBrowser>,
+    // mod 界面列出的 mod，进入界面时扫描
+    mod_list: Vec<Mod>,
+    // 录像界面列出的录像，进入界面时扫描
+    demo_list: Vec<PathBuf>,
+}
+
+impl Menu {
+    const MAX_IP_LEN: usize = 21; // "255.255.255.255:65535"
+
+    pub fn new() -> Self {
+        Self {
+            open: true,
+            in_game: false,
+            screen: Screen::Main,
+            selected: 0,
+            ip_input: String::new(),
+            message: None,
+            browser: None,
+            mod_list: Vec::new(),
+            demo_list: Vec::new(),
+        }
+    }
+
+    // 游戏中按 Esc 打开菜单
+    pub fn show(&mut self) {
+        self.open = true;
+        self.set_screen(Screen::Main);
+    }
+
+    // 不经过菜单直接进入游戏（--benchmark）
+    pub fn hide(&mut self) {
+        self.close();
+    }
+
+    // 在菜单中显示一条提示（例如连接失败）
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    fn set_screen(&mut self, screen: Screen) {
+        self.screen = screen;
+        self.selected = 0;
+        // 只有在服务器列表界面才监听广播
+        self.browser = if screen == Screen::ServerBrowser { Some(LanBrowser::new()) } else { None };
+        if screen == Screen::Mods {
+            self.mod_list = mods::scan();
+        }
+        if screen == Screen::Demos {
+            self.demo_list = demo::list();
+        }
+    }
+
+    fn main_items(&self, online: bool) -> Vec<MainItem> {
+        let mut items = vec![MainItem::Play];
+        if online {
+            items.push(MainItem::Disconnect);
+        } else {
+            items.push(MainItem::Tutorial);
+            items.push(MainItem::Range);
+            items.push(MainItem::Waves);
+            items.push(MainItem::Host);
+            items.push(MainItem::FindGames);
+            items.push(MainItem::JoinByIp);
+        }
+        items.push(MainItem::Stats);
+        items.push(MainItem::Controls);
+        items.push(MainItem::Options);
+        if !online {
+            items.push(MainItem::Mods);
+            items.push(MainItem::Demos);
+        }
+        items.push(MainItem::Quit);
+        items
+    }
+
+    fn item_count(&self, online: bool) -> usize {
+        match self.screen {
+            Screen::Main => self.main_items(online).len(),
+            Screen::ServerBrowser => self.browser.as_ref().map_or(0, |b| b.servers().len()),
+            Screen::Options => OPTION_ACTIONS.len(),
+            Screen::Controls => CONTROLS_ITEMS.len(),
+            Screen::Look => LOOK_ACTIONS.len(),
+            Screen::GamepadAxes => AxisTarget::ALL.len() * AxisField::ALL.len(),
+            Screen::Mods => self.mod_list.len() + mods::maps().len(),
+            Screen::Demos => self.demo_list.len(),
+            // 统计界面下面只有一项：进入天赋界面
+            Screen::Stats => 1,
+            Screen::Perks => Perk::ALL.len(),
+            Screen::JoinByIp => 0,
+        }
+    }
+
+    pub fn update(&mut self) {
+        if let Some(browser) = &mut self.browser {
+            browser.poll();
+            let count = browser.servers().len();
+            if self.selected >= count {
+                self.selected = count.saturating_sub(1);
+            }
+        }
+    }
+
+    fn close(&mut self) -> Option<MenuAction> {
+        self.open = false;
+        self.in_game = true;
+        self.set_screen(Screen::Main);
+        Some(MenuAction::Resume)
+    }
+
+    // 返回 None 且 handled 为 false 时表示按键没有被菜单使用
+    pub fn handle_key(&mut self, key: VirtualKeyCode, online: bool) -> (bool, Option<MenuAction>) {
+        let nav = match key {
+            VirtualKeyCode::Back if self.screen == Screen::JoinByIp => {
+                self.ip_input.pop();
+                return (true, None);
+            }
+            VirtualKeyCode::Up | VirtualKeyCode::W => Nav::Up,
+            VirtualKeyCode::Down | VirtualKeyCode::S => Nav::Down,
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => Nav::Select,
+            VirtualKeyCode::Escape => Nav::Back,
+            _ => return (false, None),
+        };
+        self.navigate(nav, online)
+    }
+
+    // 手柄的方向键、摇杆和 A/B 键。启动时的主菜单按 B 不会退出游戏
+    #[cfg(feature = "gamepad")]
+    pub fn handle_gamepad(&mut self, nav: Nav, online: bool) -> Option<MenuAction> {
+        self.navigate(nav, online).1
+    }
+
+    // 移动焦点或者执行焦点项，返回值同 handle_key
+    fn navigate(&mut self, nav: Nav, online: bool) -> (bool, Option<MenuAction>) {
+        let count = self.item_count(online);
+        match nav {
+            Nav::Up => {
+                if count > 0 {
+                    self.selected = (self.selected + count - 1) % count;
+                }
+                (true, None)
+            }
+            Nav::Down => {
+                if count > 0 {
+                    self.selected = (self.selected + 1) % count;
+                }
+                (true, None)
+            }
+            Nav::Select => (true, self.activate(online)),
+            Nav::Back => match self.screen {
+                Screen::Main if self.in_game => (true, self.close()),
+                // 启动时的主菜单按 Esc 直接退出游戏
+                Screen::Main => (false, None),
+                Screen::Look | Screen::GamepadAxes => {
+                    self.set_screen(Screen::Controls);
+                    (true, None)
+                }
+                Screen::Perks => {
+                    self.set_screen(Screen::Stats);
+                    (true, None)
+                }
+                _ => {
+                    self.set_screen(Screen::Main);
+                    (true, None)
+                }
+            },
+        }
+    }
+
+    // 触屏点了一下：点到某一项时选中并执行它，点到面板外面相当于返回
+    pub fn tap(&mut self, x: f32, y: f32, width: f32, height: f32, online: bool) -> Option<MenuAction> {
+        let (panel_x, panel_y, panel_w, panel_h) = panel_rect(width, height);
+        if x < panel_x || x > panel_x + panel_w || y < panel_y || y > panel_y + panel_h {
+            return self.navigate(Nav::Back, online).1;
+        }
+        let list_y = panel_y + LIST_TOP;
+        let (first_y, row) = match self.screen {
+            Screen::Look | Screen::Mods | Screen::Demos => (list_y - LIST_SCALE, UiBatch::line_height(LIST_SCALE)),
+            Screen::Main | Screen::Options => (list_y - COMPACT_SCALE, compact_row_height()),
+            Screen::ServerBrowser => (list_y + browser_row_height() - 4.0, browser_row_height()),
+            Screen::JoinByIp | Screen::Stats | Screen::Perks | Screen::Controls | Screen::GamepadAxes => return None,
+        };
+        if y < first_y {
+            return None;
+        }
+        let index = ((y - first_y) / row) as usize;
+        if index >= self.item_count(online) {
+            return None;
+        }
+        self.selected = index;
+        self.activate(online)
+    }
+
+    // 焦点项：高亮的底色加上边框
+    fn draw_focus(ui: &mut UiBatch, x: f32, y: f32, w: f32, h: f32) {
+        ui.rect(x, y, w, h, HIGHLIGHT_COLOR);
+        ui.rect_outline(x, y, w, h, 2.0, FOCUS_COLOR);
+    }
+
+    // 每个轴一行，各项一列，焦点在其中一格上
+    fn draw_axes(&self, ui: &mut UiBatch, x: f32, y: f32, axes: &GamepadAxes) {
+        let small = 2.0;
+        let row = UiBatch::line_height(small) + 6.0;
+        let columns = [("AXIS", 8), ("SOURCE", 11), ("INVERT", 8), ("DEAD", 6), ("SAT", 6), ("CURVE", 6)];
+        let char_width = UiBatch::text_width("0", small);
+        let mut column_x = Vec::new();
+        let mut cx = x;
+        for (title, width) in columns {
+            ui.text(cx, y, small, DIM_COLOR, title);
+            column_x.push(cx);
+            cx += width as f32 * char_width;
+        }
+        for (i, target) in AxisTarget::ALL.iter().enumerate() {
+            let binding = axes.get(*target);
+            let ry = y + (i + 1) as f32 * row;
+            ui.text(column_x[0], ry, small, TEXT_COLOR, target.name());
+            for (j, field) in AxisField::ALL.iter().enumerate() {
+                let cell_x = column_x[j + 1];
+                if self.selected == i * AxisField::ALL.len() + j {
+                    let cell_w = columns[j + 1].1 as f32 * char_width;
+                    Self::draw_focus(ui, cell_x - 4.0, ry - 4.0, cell_w - 4.0, row);
+                }
+                ui.text(cell_x, ry, small, TEXT_COLOR, &binding.describe(*field));
+            }
+        }
+    }
+
+    // 底部的操作提示：键鼠显示 keyboard，手柄显示确定键加上 verb 和返回键，verb 为空时只有返回键
+    fn hint(device: Device, keyboard: &str, verb: &str) -> String {
+        match device.menu_buttons() {
+            None => keyboard.to_string(),
+            Some((_, back)) if verb.is_empty() => format!("{} back", back),
+            Some((confirm, back)) => format!("{} {}   {} back", confirm, verb, back),
+        }
+    }
+
+    // 在 IP 输入框中输入字符
+    pub fn handle_char(&mut self, c: char) {
+        if self.screen == Screen::JoinByIp
+            && (c.is_ascii_digit() || c == '.' || c == ':')
+            && self.ip_input.len() < Self::MAX_IP_LEN
+        {
+            self.ip_input.push(c);
+        }
+    }
+
+    fn activate(&mut self, online: bool) -> Option<MenuAction> {
+        self.message = None;
+        match self.screen {
+            Screen::Main => match self.main_items(online).get(self.selected)? {
+                MainItem::Play if !self.in_game => {
+                    self.close();
+                    Some(MenuAction::Play)
+                }
+                MainItem::Play => self.close(),
+                MainItem::Tutorial => {
+                    self.close();
+                    Some(MenuAction::Tutorial)
+                }
+                MainItem::Range => {
+                    self.close();
+                    Some(MenuAction::PracticeRange)
+                }
+                MainItem::Waves => {
+                    self.close();
+                    Some(MenuAction::Waves)
+                }
+                MainItem::Host => {
+                    self.close();
+                    Some(MenuAction::Host)
+                }
+                MainItem::FindGames => {
+                    self.set_screen(Screen::ServerBrowser);
+                    None
+                }
+                MainItem::JoinByIp => {
+                    self.set_screen(Screen::JoinByIp);
+                    None
+                }
+                MainItem::Disconnect => Some(MenuAction::Disconnect),
+                MainItem::Stats => {
+                    self.set_screen(Screen::Stats);
+                    None
+                }
+                MainItem::Controls => {
+                    self.set_screen(Screen::Controls);
+                    None
+                }
+                MainItem::Options => {
+                    self.set_screen(Screen::Options);
+                    None
+                }
+                MainItem::Mods => {
+                    self.set_screen(Screen::Mods);
+                    None
+                }
+                MainItem::Demos => {
+                    self.set_screen(Screen::Demos);
+                    None
+                }
+                MainItem::Quit => Some(MenuAction::Quit),
+            },
+            Screen::ServerBrowser => {
+                let addr = self.browser.as_ref()?.servers().get(self.selected)?.addr;
+                self.close();
+                Some(MenuAction::Join(addr))
+            }
+            Screen::Stats => {
+                self.set_screen(Screen::Perks);
+                None
+            }
+            Screen::Perks => Perk::ALL.get(self.selected).map(|perk| MenuAction::UnlockPerk(*perk)),
+            Screen::Controls => {
+                self.set_screen(CONTROLS_ITEMS.get(self.selected)?.1);
+                None
+            }
+            Screen::Look => LOOK_ACTIONS.get(self.selected).copied(),
+            Screen::GamepadAxes => {
+                let fields = AxisField::ALL.len();
+                Some(MenuAction::CycleAxis(AxisTarget::ALL[self.selected / fields], AxisField::ALL[self.selected % fields]))
+            }
+            Screen::Options => OPTION_ACTIONS.get(self.selected).copied(),
+            // 前面是 mod 的开关，后面是 mod 带来的地图
+            Screen::Mods => match self.mod_list.get_mut(self.selected) {
+                Some(m) => {
+                    m.enabled = !m.enabled;
+                    mods::save_order(&self.mod_list);
+                    self.message = Some("restart the game to apply mod changes".to_string());
+                    None
+                }
+                None => {
+                    let index = self.selected - self.mod_list.len();
+                    self.close();
+                    Some(MenuAction::PlayMap(index))
+                }
+            },
+            Screen::Demos => {
+                self.demo_list.get(self.selected)?;
+                let index = self.selected;
+                self.close();
+                Some(MenuAction::PlayDemo(index))
+            }
+            Screen::JoinByIp => match net::parse_address(&self.ip_input) {
+                Some(addr) => {
+                    self.close();
+                    Some(MenuAction::Join(addr))
+                }
+                None => {
+                    self.message = Some(format!("invalid address: {}", self.ip_input));
+                    None
+                }
+            },
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &self,
+        ui: &mut UiBatch,
+        online: bool,
+        profile: &Profile,
+        settings: &Settings,
+        input: &InputConfig,
+        device: Device,
+        gamepad: Option<(&str, &str)>,
+        map: &str,
+        achievements_total: usize,
+    ) {
+        let scale = LIST_SCALE;
+        let line = UiBatch::line_height(scale);
+        let (panel_x, panel_y, panel_w, panel_h) = panel_rect(ui.width(), ui.height());
+        let center_x = ui.width() / 2.0;
+
+        ui.rect(panel_x, panel_y, panel_w, panel_h, PANEL_COLOR);
+
+        let title = match self.screen {
+            Screen::Main => "UNDERGROUND PARKING SHOOTER",
+            Screen::ServerBrowser => "LAN GAMES",
+            Screen::JoinByIp => "JOIN BY IP",
+            Screen::Stats => "STATISTICS",
+            Screen::Perks => "PERKS",
+            Screen::Controls => "CONTROLS",
+            Screen::Look => "LOOK AND AIM",
+            Screen::GamepadAxes => "GAMEPAD AXES",
+            Screen::Options => "OPTIONS",
+            Screen::Mods => "MODS",
+            Screen::Demos => "DEMOS",
+        };
+        ui.text_centered(center_x, panel_y + 20.0, 4.0, TITLE_COLOR, title);
+
+        let list_y = panel_y + LIST_TOP;
+        match self.screen {
+            Screen::Main => {
+                let scale = COMPACT_SCALE;
+                let line = compact_row_height();
+                for (i, item) in self.main_items(online).iter().enumerate() {
+                    let label = match item {
+                        MainItem::Play if self.in_game => "RESUME",
+                        MainItem::Play => "PLAY",
+                        MainItem::Tutorial => "TUTORIAL",
+                        MainItem::Range => "PRACTICE RANGE",
+                        MainItem::Waves => "WAVES",
+                        MainItem::Host => "HOST LAN GAME",
+                        MainItem::FindGames => "FIND LAN GAMES",
+                        MainItem::JoinByIp => "JOIN BY IP",
+                        MainItem::Disconnect => "LEAVE MULTIPLAYER",
+                        MainItem::Stats => "STATISTICS",
+                        MainItem::Controls => "CONTROLS",
+                        MainItem::Options => "OPTIONS",
+                        MainItem::Mods => "MODS",
+                        MainItem::Demos => "DEMOS",
+                        MainItem::Quit => "QUIT",
+                    };
+                    let y = list_y + i as f32 * line;
+                    if i == self.selected {
+                        Self::draw_focus(ui, panel_x + 20.0, y - scale, panel_w - 40.0, line);
+                    }
+                    ui.text_centered(center_x, y, scale, TEXT_COLOR, label);
+                }
+                let hint = Self::hint(device, "", "select");
+                ui.text(panel_x + 40.0, panel_y + panel_h - 40.0, 2.0, DIM_COLOR, &hint);
+            }
+            Screen::ServerBrowser => {
+                let small = 2.0;
+                let row = browser_row_height();
+                ui.text(panel_x + 30.0, list_y, small, DIM_COLOR, &format!(
+                    "{:<20} {:<16} {:<10} {}", "SERVER", "MAP", "MODE", "PLAYERS"
+                ));
+                match &self.browser {
+                    Some(browser) if browser.error().is_some() => {
+                        ui.text(panel_x + 30.0, list_y + row, small, ERROR_COLOR, browser.error().unwrap_or_default());
+                    }
+                    Some(browser) if browser.servers().is_empty() => {
+                        ui.text(panel_x + 30.0, list_y + row, small, DIM_COLOR, "searching for games on the local network...");
+                    }
+                    Some(browser) => {
+                        for (i, server) in browser.servers().iter().enumerate() {
+                            let y = list_y + (i + 1) as f32 * row;
+                            if i == self.selected {
+                                Self::draw_focus(ui, panel_x + 20.0, y - 4.0, panel_w - 40.0, row);
+                            }
+                            let info = &server.info;
+                            ui.text(panel_x + 30.0, y, small, TEXT_COLOR, &format!(
+                                "{:<20} {:<16} {:<10} {}/{}",
+                                truncate(&info.name, 20), truncate(&info.map, 16), truncate(&info.mode, 10),
+                                info.players, info.max_players
+                            ));
+                        }
+                    }
+                    None => {}
+                }
+                ui.text(panel_x + 30.0, panel_y + panel_h - 40.0, small, DIM_COLOR, &Self::hint(device, "ENTER join   ESC back", "join"));
+            }
+            Screen::JoinByIp => {
+                ui.text(panel_x + 40.0, list_y, 2.0, DIM_COLOR, &format!("address (default port {}):", net::GAME_PORT));
+                let box_y = list_y + 30.0;
+                ui.rect_outline(panel_x + 40.0, box_y, panel_w - 80.0, line + 10.0, 2.0, TEXT_COLOR);
+                ui.text(panel_x + 50.0, box_y + 8.0, scale, TEXT_COLOR, &format!("{}_", self.ip_input));
+                ui.text(panel_x + 40.0, panel_y + panel_h - 40.0, 2.0, DIM_COLOR, "ENTER connect   ESC back");
+            }
+            Screen::Stats => {
+                let y = draw_stats(ui, panel_x + 40.0, list_y, profile, map, achievements_total) + 10.0;
+                let row = UiBatch::line_height(2.0);
+                Self::draw_focus(ui, panel_x + 20.0, y - 4.0, panel_w - 40.0, row + 4.0);
+                let points = profile.progression.points();
+                let label = if points > 0 { format!("PERKS ({} POINTS) >", points) } else { "PERKS >".to_string() };
+                ui.text(panel_x + 40.0, y, 2.0, TEXT_COLOR, &label);
+                ui.text(panel_x + 40.0, panel_y + panel_h - 40.0, 2.0, DIM_COLOR, &Self::hint(device, "ENTER open   ESC back", "open"));
+            }
+            Screen::Perks => {
+                let progression = &profile.progression;
+                let (xp, needed) = progression.level_progress();
+                ui.text(panel_x + 40.0, list_y, 2.0, TEXT_COLOR, &format!(
+                    "LEVEL {}   XP {}/{}   POINTS {}", progression.level(), xp, needed, progression.points()
+                ));
+                let row = UiBatch::line_height(2.0);
+                let item_h = row * 2.0 + 12.0;
+                for (i, perk) in Perk::ALL.iter().enumerate() {
+                    let y = list_y + row * 2.0 + i as f32 * item_h;
+                    if i == self.selected {
+                        Self::draw_focus(ui, panel_x + 20.0, y - 4.0, panel_w - 40.0, item_h - 4.0);
+                    }
+                    let rank = progression.rank(*perk);
+                    let pips: String = (0..progression::MAX_RANK).map(|r| if r < rank { '#' } else { '-' }).collect();
+                    let color = if progression.can_unlock(*perk) { TEXT_COLOR } else { DIM_COLOR };
+                    ui.text(panel_x + 40.0, y, 2.0, color, &format!("{:<14} [{}]", perk.name(), pips));
+                    let detail = match perk.requires() {
+                        Some((required, at)) if progression.rank(required) < at => format!("requires {} {}", required.name(), at),
+                        _ => perk.description().to_string(),
+                    };
+                    ui.text(panel_x + 60.0, y + row, 2.0, DIM_COLOR, &detail);
+                }
+                ui.text(panel_x + 40.0, panel_y + panel_h - 40.0, 2.0, DIM_COLOR, &Self::hint(device, "ENTER unlock   ESC back", "unlock"));
+            }
+            Screen::Controls => {
+                let row = UiBatch::line_height(2.0);
+                let lines = draw_controls(ui, panel_x + 40.0, list_y, input, device);
+                for (i, (label, _)) in CONTROLS_ITEMS.iter().enumerate() {
+                    let y = list_y + (lines + 1 + i) as f32 * (row + 6.0);
+                    if i == self.selected {
+                        Self::draw_focus(ui, panel_x + 20.0, y - 4.0, panel_w - 40.0, row + 4.0);
+                    }
+                    ui.text(panel_x + 40.0, y, 2.0, TEXT_COLOR, label);
+                }
+                ui.text(panel_x + 40.0, panel_y + panel_h - 40.0, 2.0, DIM_COLOR, &Self::hint(device, "ENTER open   ESC back", "open"));
+            }
+            Screen::Look => {
+                let look = &settings.look;
+                let yes_no = |on: bool| if on { "YES" } else { "NO" };
+                for (i, action) in LOOK_ACTIONS.iter().enumerate() {
+                    let label = match action {
+                        MenuAction::CycleMouseSensitivityX => format!("MOUSE X  {:.2}", look.mouse_x),
+                        MenuAction::CycleMouseSensitivityY => format!("MOUSE Y  {:.2}", look.mouse_y),
+                        MenuAction::ToggleInvertMouseY => format!("INVERT MOUSE Y  {}", yes_no(look.invert_mouse_y)),
+                        MenuAction::CycleStickSensitivityX => format!("STICK X  {:.2}", look.stick_x),
+                        MenuAction::CycleStickSensitivityY => format!("STICK Y  {:.2}", look.stick_y),
+                        MenuAction::ToggleInvertStickY => format!("INVERT STICK Y  {}", yes_no(look.invert_stick_y)),
+                        MenuAction::CycleAimAssist => format!("AIM ASSIST  {}", settings.aim_assist.name()),
+                        MenuAction::ToggleMouseAimAssist => format!("MOUSE AIM ASSIST  {}", yes_no(settings.mouse_aim_assist)),
+                        _ => continue,
+                    };
+                    let y = list_y + i as f32 * line;
+                    if i == self.selected {
+                        Self::draw_focus(ui, panel_x + 20.0, y - scale, panel_w - 40.0, line);
+                    }
+                    ui.text_centered(center_x, y, scale, TEXT_COLOR, &label);
+                }
+                ui.text(panel_x + 40.0, panel_y + panel_h - 40.0, 2.0, DIM_COLOR, &Self::hint(device, "ENTER change   ESC back", "change"));
+            }
+            Screen::GamepadAxes => {
+                match gamepad {
+                    Some((guid, name)) => {
+                        ui.text(panel_x + 40.0, list_y, 2.0, DIM_COLOR, &truncate(&name.to_uppercase(), 48));
+                        self.draw_axes(ui, panel_x + 40.0, list_y + 30.0, &input.gamepad_axes(guid));
+                    }
+                    None => ui.text(panel_x + 40.0, list_y, 2.0, DIM_COLOR, "press a button on a controller"),
+                }
+                ui.text(panel_x + 40.0, panel_y + panel_h - 40.0, 2.0, DIM_COLOR, &Self::hint(device, "ENTER change   ESC back", "change"));
+            }
+            Screen::Options => {
+                // 设置项比较多，用按键界面那样的小字才放得下
+                let scale = COMPACT_SCALE;
+                let line = compact_row_height();
+                let on_off = |on: bool| if on { "ON" } else { "OFF" };
+                for (i, action) in OPTION_ACTIONS.iter().enumerate() {
+                    let label = match action {
+                        MenuAction::ToggleGore => format!("GORE  {}", on_off(settings.gore)),
+                        MenuAction::CycleShellCasings => match settings.shell_casings {
+                            0 => "SHELL CASINGS  OFF".to_string(),
+                            limit => format!("SHELL CASINGS  {}", limit),
+                        },
+                        MenuAction::ToggleDepthOfField => format!("DEPTH OF FIELD  {}", on_off(settings.depth_of_field)),
+                        MenuAction::ToggleMotionBlur => format!("MOTION BLUR  {}", on_off(settings.motion_blur)),
+                        MenuAction::ToggleCameraTilt => format!("CAMERA TILT  {}", on_off(settings.camera_tilt)),
+                        MenuAction::ToggleStatusOverlays => format!("STATUS OVERLAYS  {}", on_off(settings.status_overlays)),
+                        MenuAction::CycleEffectQuality => format!("EFFECT QUALITY  {}", settings.effect_quality.name()),
+                        MenuAction::CycleLighting => format!("LIGHTING  {}", settings.lighting.name()),
+                        MenuAction::CycleFrameLimit => format!("FPS LIMIT  {}", settings.frame_limit.name()),
+                        MenuAction::ToggleTouchControls => format!("TOUCH CONTROLS  {}", on_off(settings.touch_controls)),
+                        MenuAction::ToggleMouseSmoothing => format!("MOUSE SMOOTHING  {}", on_off(settings.mouse_smoothing)),
+                        MenuAction::ToggleMouseAcceleration => format!("MOUSE ACCELERATION  {}", on_off(settings.mouse_acceleration)),
+                        _ => continue,
+                    };
+                    let y = list_y + i as f32 * line;
+                    if i == self.selected {
+                        Self::draw_focus(ui, panel_x + 20.0, y - scale, panel_w - 40.0, line);
+                    }
+                    ui.text_centered(center_x, y, scale, TEXT_COLOR, &label);
+                }
+                ui.text(panel_x + 40.0, panel_y + panel_h - 40.0, 2.0, DIM_COLOR, &Self::hint(device, "ENTER toggle   ESC back", "toggle"));
+            }
+            Screen::Mods => {
+                if self.item_count(online) == 0 {
+                    ui.text(panel_x + 40.0, list_y, 2.0, DIM_COLOR, "no mods installed, put them in:");
+                    ui.text(panel_x + 40.0, list_y + 30.0, 2.0, DIM_COLOR, &truncate(&crate::paths::mods().display().to_string(), 52));
+                }
+                let on_off = |on: bool| if on { "ON" } else { "OFF" };
+                let labels = self.mod_list.iter()
+                    .map(|m| format!("{}  {}", truncate(&m.title.to_uppercase(), 24), on_off(m.enabled)))
+                    .chain(mods::maps().iter().map(|name| format!("PLAY {}", truncate(&name.to_uppercase(), 24))));
+                for (i, label) in labels.enumerate() {
+                    let y = list_y + i as f32 * line;
+                    if i == self.selected {
+                        Self::draw_focus(ui, panel_x + 20.0, y - scale, panel_w - 40.0, line);
+                    }
+                    ui.text_centered(center_x, y, scale, TEXT_COLOR, &label);
+                }
+                if let Some(m) = self.mod_list.get(self.selected).filter(|m| !m.description.is_empty()) {
+                    ui.text(panel_x + 40.0, panel_y + panel_h - 100.0, 2.0, DIM_COLOR, &truncate(&m.description, 52));
+                }
+                ui.text(panel_x + 40.0, panel_y + panel_h - 40.0, 2.0, DIM_COLOR, &Self::hint(device, "ENTER toggle   ESC back", "select"));
+            }
+            Screen::Demos => {
+                if self.demo_list.is_empty() {
+                    ui.text(panel_x + 40.0, list_y, 2.0, DIM_COLOR, "no demos yet, games you host are recorded");
+                }
+                for (i, path) in self.demo_list.iter().enumerate() {
+                    let name = path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().to_uppercase());
+                    let y = list_y + i as f32 * line;
+                    if i == self.selected {
+                        Self::draw_focus(ui, panel_x + 20.0, y - scale, panel_w - 40.0, line);
+                    }
+                    ui.text_centered(center_x, y, scale, TEXT_COLOR, &truncate(&name, 32));
+                }
+                ui.text(panel_x + 40.0, panel_y + panel_h - 40.0, 2.0, DIM_COLOR, &Self::hint(device, "ENTER play   ESC back", "play"));
+            }
+        }
+
+        if let Some(message) = &self.message {
+            ui.text_centered(center_x, panel_y + panel_h - 70.0, 2.0, ERROR_COLOR, message);
+        }
+    }
+}
+
+// 菜单面板的位置和大小，绘制和触屏点击共用
+fn panel_rect(width: f32, height: f32) -> (f32, f32, f32, f32) {
+    let panel_w = 720.0f32.min(width - 40.0);
+    let panel_h = 420.0f32.min(height - 40.0);
+    ((width - panel_w) / 2.0, (height - panel_h) / 2.0, panel_w, panel_h)
+}
+
+fn browser_row_height() -> f32 {
+    UiBatch::line_height(2.0) + 6.0
+}
+
+fn compact_row_height() -> f32 {
+    UiBatch::line_height(COMPACT_SCALE) + 4.0
+}
+
+// 每个操作当前的按键，分成两列。跟着最近用的设备切换成键鼠或者手柄的按键，返回占用的行数
+fn draw_controls(ui: &mut UiBatch, x: f32, y: f32, input: &InputConfig, device: Device) -> usize {
+    let small = 2.0;
+    let row = UiBatch::line_height(small);
+    let column_w = UiBatch::text_width(&" ".repeat(26), small);
+    let rows = Action::ALL.len().div_ceil(2);
+    let header = if device == Device::KeyboardMouse { "KEYBOARD" } else { "GAMEPAD" };
+    for column in 0..2 {
+        ui.text(x + column as f32 * column_w, y, small, DIM_COLOR, &format!("{:<13} {}", "ACTION", header));
+    }
+    for (i, action) in Action::ALL.iter().enumerate() {
+        let (column, line) = (i / rows, i % rows);
+        let name = action.name().replace('_', " ");
+        let text = format!("{:<13} {}", name, truncate(&input.describe(*action, device), 12));
+        ui.text(x + column as f32 * column_w, y + (line + 1) as f32 * row, small, TEXT_COLOR, &text);
+    }
+    rows + 1
+}
+
+// 终身统计和当前地图的排行榜
+// 返回下面空白处的 y
+fn draw_stats(ui: &mut UiBatch, x: f32, y: f32, profile: &Profile, map: &str, achievements_total: usize) -> f32 {
+    let small = 2.0;
+    let row = UiBatch::line_height(small);
+    let stats = &profile.stats;
+    let kd = if stats.deaths == 0 { stats.kills as f32 } else { stats.kills as f32 / stats.deaths as f32 };
+    let lines = [
+        format!("player      {}", profile.player_name),
+        format!("kills       {}   deaths {}   k/d {:.2}", stats.kills, stats.deaths, kd),
+        format!("accuracy    {:.1}%   headshots {}", stats.accuracy() * 100.0, stats.headshots),
+        format!("play time   {}   matches {}", profile::format_play_time(stats.play_time_secs), stats.matches_played),
+        format!("best wave   {}", stats.best_wave),
+        format!("achievements {}/{}", profile.achievements.len(), achievements_total),
+        format!("level       {}   xp {}", profile.progression.level(), profile.progression.xp),
+    ];
+    for (i, line) in lines.iter().enumerate() {
+        ui.text(x, y + i as f32 * row, small, TEXT_COLOR, line);
+    }
+
+    let board_y = y + (lines.len() as f32 + 1.0) * row;
+    ui.text(x, board_y, small, TITLE_COLOR, &format!("LEADERBOARD - {}", map));
+    let board = profile.leaderboard(map);
+    if board.is_empty() {
+        ui.text(x, board_y + row, small, DIM_COLOR, "no records yet");
+    }
+    for (i, entry) in board.iter().take(5).enumerate() {
+        ui.text(x, board_y + (i + 1) as f32 * row, small, TEXT_COLOR, &format!(
+            "{}. {:<16} kills {:<4} deaths {:<4} wave {:<3} acc {:.0}%",
+            i + 1, truncate(&entry.name, 16), entry.kills, entry.deaths, entry.wave, entry.accuracy * 100.0
+        ));
+    }
+    board_y + (board.len().clamp(1, 5) + 1) as f32 * row
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    text.chars().take(max).collect()
+}
@@ -0,0 +1,133 @@
+use glam::Vec3;
+
+use crate::gpu_memory;
+use crate::model::{Model, ModelVertex};
+
+// 网格构建器。所有面都按同一个约定输出：从外侧（朝外方向所指的一侧）看顶点是逆时针，
+// 与场景管线的 FrontFace::Ccw 和背面剔除一致。调用方按边依次给出四个角（顺时针、逆时针都可以）
+// 和这个面朝外的方向，构建器在需要时翻转顺序，新加的形状不会因为绕序写反而从某一侧消失。
+// 顶点的法向量取翻转后的绕序所朝的方向，着色器就不必再用屏幕空间导数去猜
+
+pub struct MeshBuilder {
+    pub vertices: Vec<ModelVertex>,
+    pub indices: Vec<u16>,
+}
+
+// 三角形 a-b-c 逆时针时朝向的一侧（未归一化）
+pub fn winding_normal(a: Vec3, b: Vec3, c: Vec3) -> Vec3 {
+    (b - a).cross(c - a)
+}
+
+impl MeshBuilder {
+    pub fn new() -> Self {
+        Self { vertices: Vec::new(), indices: Vec::new() }
+    }
+
+    // 纯色、没有纹理的四边形
+    #[cfg(test)]
+    pub fn solid_quad(&mut self, corners: [Vec3; 4], outward: Vec3, color: [f32; 3]) {
+        self.quad(corners, outward, color, 0.0, [[0.0, 0.0]; 4]);
+    }
+
+    pub fn quad(&mut self, corners: [Vec3; 4], outward: Vec3, color: [f32; 3], model_type: f32, tex_coords: [[f32; 2]; 4]) {
+        let face = winding_normal(corners[0], corners[1], corners[2]);
+        let (order, normal) = if face.dot(outward) < 0.0 { ([0, 3, 2, 1], -face) } else { ([0, 1, 2, 3], face) };
+        let base = self.vertices.len() as u16;
+        for i in order {
+            self.vertices.push(ModelVertex::new(corners[i], color, tex_coords[i], model_type).with_normal(normal.normalize_or_zero()));
+        }
+        self.indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    // 纯色、没有纹理的三角形（多边形的扇形、柱子的顶面）
+    pub fn triangle(&mut self, corners: [Vec3; 3], outward: Vec3, color: [f32; 3], model_type: f32) {
+        let face = winding_normal(corners[0], corners[1], corners[2]);
+        let (order, normal) = if face.dot(outward) < 0.0 { ([0, 2, 1], -face) } else { ([0, 1, 2], face) };
+        let base = self.vertices.len() as u16;
+        for i in order {
+            self.vertices.push(ModelVertex::new(corners[i], color, [0.0, 0.0], model_type).with_normal(normal.normalize_or_zero()));
+        }
+        self.indices.extend_from_slice(&[base, base + 1, base + 2]);
+    }
+
+    pub fn build(self, device: &wgpu::Device, name: &str, color: [f32; 3], is_wall: bool, category: gpu_memory::Category) -> Model {
+        Model::new(device, name, &self.vertices, &self.indices, color, is_wall, None, category)
+    }
+
+    // 每个三角形的中心和逆时针一侧的法向量
+    #[cfg(test)]
+    pub fn triangles(&self) -> impl Iterator<Item = (Vec3, Vec3)> + '_ {
+        self.indices.chunks(3).map(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(self.vertices[triangle[i] as usize].position));
+            ((a + b + c) / 3.0, winding_normal(a, b, c).normalize())
+        })
+    }
+
+    // 顶点的平均位置，凸形状的每个面都应该背对它
+    #[cfg(test)]
+    pub fn centroid(&self) -> Vec3 {
+        let sum: Vec3 = self.vertices.iter().map(|vertex| Vec3::from(vertex.position)).sum();
+        sum / self.vertices.len() as f32
+    }
+
+    // 凸形状的所有三角形都朝外
+    #[cfg(test)]
+    pub fn assert_outward(&self) {
+        let centroid = self.centroid();
+        for (center, normal) in self.triangles() {
+            assert!(normal.dot(center - centroid) > 0.0, "朝内的三角形: 中心 {:?} 法向量 {:?}", center, normal);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: [Vec3; 4] = [Vec3::ZERO, Vec3::X, Vec3::new(1.0, 0.0, 1.0), Vec3::Z];
+
+    #[test]
+    fn quad_faces_outward_for_either_corner_order() {
+        for outward in [Vec3::Y, Vec3::NEG_Y] {
+            for corners in [SQUARE, [SQUARE[0], SQUARE[3], SQUARE[2], SQUARE[1]]] {
+                let mut mesh = MeshBuilder::new();
+                mesh.solid_quad(corners, outward, [1.0; 3]);
+                for (_, normal) in mesh.triangles() {
+                    assert!(normal.dot(outward) > 0.99, "{:?} 的法向量 {:?}", outward, normal);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn flipped_quad_keeps_texture_coordinates_on_their_corners() {
+        let uvs = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        let mut mesh = MeshBuilder::new();
+        mesh.quad(SQUARE, Vec3::Y, [1.0; 3], 1.0, uvs);
+        for (corner, uv) in SQUARE.iter().zip(uvs) {
+            let vertex = mesh.vertices.iter().find(|vertex| Vec3::from(vertex.position) == *corner).unwrap();
+            assert_eq!(vertex.tex_coords, uv);
+        }
+    }
+
+    #[test]
+    fn vertex_normals_follow_the_outward_side() {
+        let mut mesh = MeshBuilder::new();
+        mesh.solid_quad(SQUARE, Vec3::NEG_Y, [1.0; 3]);
+        mesh.triangle([SQUARE[0], SQUARE[1], SQUARE[2]], Vec3::Y, [1.0; 3], 0.0);
+        assert!(mesh.vertices[..4].iter().all(|vertex| Vec3::from(vertex.normal) == Vec3::NEG_Y));
+        assert!(mesh.vertices[4..].iter().all(|vertex| Vec3::from(vertex.normal) == Vec3::Y));
+        for (_, normal) in mesh.triangles().skip(2) {
+            assert!(normal.dot(Vec3::Y) > 0.99);
+        }
+    }
+
+    #[test]
+    fn indices_continue_after_previous_quads() {
+        let mut mesh = MeshBuilder::new();
+        mesh.solid_quad(SQUARE, Vec3::Y, [1.0; 3]);
+        mesh.solid_quad(SQUARE, Vec3::NEG_Y, [1.0; 3]);
+        assert_eq!(mesh.vertices.len(), 8);
+        assert!(mesh.indices[6..].iter().all(|&index| (4..8).contains(&index)));
+    }
+}
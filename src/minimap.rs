@@ -0,0 +1,164 @@
+use glam::Vec3;
+
+use crate::camera::Camera;
+use crate::door::Door;
+use crate::map::{self, Cell, Map};
+use crate::ui::UiBatch;
+
+// 小地图：左上角显示地图的格子、触发区域、门、狗和玩家位置/朝向。
+// 有两种模式：把整张地图缩进固定大小的方框，或者固定比例、跟着玩家滚动的窗口（生成的大地图上看得清楚）
+
+const MINIMAP_SIZE: f32 = 200.0;
+// 滚动模式下每米多少像素
+const SCROLL_PIXELS_PER_METER: f32 = 8.0;
+const MARGIN: f32 = 16.0;
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const WALL_COLOR: [f32; 4] = [0.85, 0.85, 0.85, 0.9];
+const FLOOR_COLOR: [f32; 4] = [0.25, 0.25, 0.3, 0.6];
+const TRIGGER_COLOR: [f32; 4] = [1.0, 0.8, 0.1, 0.8];
+const DOOR_COLOR: [f32; 4] = [0.55, 0.6, 0.7, 0.9];
+const GLASS_COLOR: [f32; 4] = [0.6, 0.85, 1.0, 0.7];
+const WATER_COLOR: [f32; 4] = [0.2, 0.35, 0.6, 0.7];
+const FENCE_COLOR: [f32; 4] = [0.6, 0.6, 0.55, 0.8];
+const GRATE_COLOR: [f32; 4] = [0.12, 0.12, 0.14, 0.8];
+const PARKING_COLOR: [f32; 4] = [0.32, 0.32, 0.38, 0.6];
+const DOG_COLOR: [f32; 4] = [0.85, 0.55, 0.25, 1.0];
+const PLAYER_COLOR: [f32; 4] = [0.2, 1.0, 0.3, 1.0];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Hidden,
+    // 整张地图缩进方框
+    Full,
+    // 以玩家为中心滚动
+    Scrolling,
+}
+
+// 屏幕上显示的窗口（x, y, 宽, 高），超出的部分裁掉
+#[derive(Clone, Copy)]
+struct Window {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+impl Window {
+    // 和窗口求交以后再画，完全在外面的不画
+    fn rect(&self, ui: &mut UiBatch, x: f32, y: f32, w: f32, h: f32, color: [f32; 4]) {
+        let (left, top) = (x.max(self.x), y.max(self.y));
+        let (right, bottom) = ((x + w).min(self.x + self.width), (y + h).min(self.y + self.height));
+        if right > left && bottom > top {
+            ui.rect(left, top, right - left, bottom - top, color);
+        }
+    }
+}
+
+pub struct Minimap {
+    mode: Mode,
+}
+
+impl Minimap {
+    pub fn new() -> Self {
+        Self { mode: Mode::Hidden }
+    }
+
+    // 依次切换：关闭、整张地图、跟随玩家滚动
+    pub fn toggle(&mut self) {
+        self.mode = match self.mode {
+            Mode::Hidden => Mode::Full,
+            Mode::Full => Mode::Scrolling,
+            Mode::Scrolling => Mode::Hidden,
+        };
+    }
+
+    pub fn draw(&self, ui: &mut UiBatch, map: &Map, doors: &[Door], dogs: &[Vec3], camera: &Camera) {
+        // 联机状态文字在左上角，小地图放在它下面
+        let (x, y) = (MARGIN, MARGIN + 30.0);
+        let (map_width, map_height) = (map.width as f32, map.height as f32);
+        // 格子坐标系（可以在格子之间）下玩家的位置
+        let to_grid = |world_x: f32, world_z: f32| {
+            (world_x / map::CELL_SIZE + (map.width - 1) as f32 / 2.0 + 0.5, world_z / map::CELL_SIZE + (map.height - 1) as f32 / 2.0 + 0.5)
+        };
+        // cell 是每格的像素数，(origin_x, origin_y) 是第 0 行第 0 列格子左上角在屏幕上的位置
+        let (cell, window, origin_x, origin_y) = match self.mode {
+            Mode::Hidden => return,
+            Mode::Full => {
+                let cell = (MINIMAP_SIZE / map.width.max(map.height) as f32).floor().max(1.0);
+                (cell, Window { x, y, width: cell * map_width, height: cell * map_height }, x, y)
+            }
+            Mode::Scrolling => {
+                let cell = SCROLL_PIXELS_PER_METER * map::CELL_SIZE;
+                // 地图比窗口小的方向窗口缩到地图大小；窗口不超出地图边界，走到边上时玩家不在正中间
+                let (width, height) = (MINIMAP_SIZE.min(cell * map_width), MINIMAP_SIZE.min(cell * map_height));
+                let (col, row) = to_grid(camera.position.x, camera.position.z);
+                let scroll_x = (col * cell - width / 2.0).clamp(0.0, cell * map_width - width);
+                let scroll_y = (row * cell - height / 2.0).clamp(0.0, cell * map_height - height);
+                (cell, Window { x, y, width, height }, x - scroll_x, y - scroll_y)
+            }
+        };
+
+        ui.rect(window.x - 4.0, window.y - 4.0, window.width + 8.0, window.height + 8.0, BACKGROUND_COLOR);
+        // 只画窗口里能看到的格子
+        let first_col = ((window.x - origin_x) / cell).floor().max(0.0) as usize;
+        let first_row = ((window.y - origin_y) / cell).floor().max(0.0) as usize;
+        let last_col = (((window.x + window.width - origin_x) / cell).ceil() as usize).min(map.width);
+        let last_row = (((window.y + window.height - origin_y) / cell).ceil() as usize).min(map.height);
+        for row in first_row..last_row {
+            for col in first_col..last_col {
+                let color = match map.cell(col, row) {
+                    Cell::Wall | Cell::Pillar => WALL_COLOR,
+                    Cell::Parking => PARKING_COLOR,
+                    Cell::Trigger(_) => TRIGGER_COLOR,
+                    Cell::Glass => GLASS_COLOR,
+                    Cell::Water => WATER_COLOR,
+                    Cell::Fence => FENCE_COLOR,
+                    Cell::Grate => GRATE_COLOR,
+                    Cell::Floor | Cell::Spawn | Cell::Respawn | Cell::EnemySpawn | Cell::Door(_) | Cell::Lane(_) => FLOOR_COLOR,
+                    Cell::Void => continue,
+                };
+                window.rect(ui, origin_x + col as f32 * cell, origin_y + row as f32 * cell, cell, cell, color);
+            }
+        }
+
+        // 门：锁着的门用钥匙卡颜色画成带白边的方块
+        for door in doors {
+            let (dx, dy) = (origin_x + door.col as f32 * cell, origin_y + door.row as f32 * cell);
+            match door.lock {
+                Some(key) => {
+                    window.rect(ui, dx - 1.0, dy - 1.0, cell + 2.0, cell + 2.0, [1.0, 1.0, 1.0, 0.9]);
+                    window.rect(ui, dx, dy, cell, cell, key.ui_color());
+                }
+                None if door.is_open() => {}
+                None => window.rect(ui, dx, dy, cell, cell, DOOR_COLOR),
+            }
+        }
+
+        // 世界坐标到屏幕
+        let to_screen = |world_x: f32, world_z: f32| {
+            let (col, row) = to_grid(world_x, world_z);
+            (origin_x + col * cell, origin_y + row * cell)
+        };
+        let dot = (cell * 1.5).max(4.0);
+        // 狗比玩家的点小一些
+        for dog in dogs {
+            let (dx, dy) = to_screen(dog.x, dog.z);
+            let size = dot * 0.7;
+            window.rect(ui, dx - size / 2.0, dy - size / 2.0, size, size, DOG_COLOR);
+        }
+
+        // 玩家位置
+        let (px, pz) = (camera.position.x, camera.position.z);
+        let (sx, sy) = to_screen(px, pz);
+        window.rect(ui, sx - dot / 2.0, sy - dot / 2.0, dot, dot, PLAYER_COLOR);
+
+        // 朝向：沿视线方向画几个小点
+        let (dir_x, dir_z) = (-camera.yaw.sin(), -camera.yaw.cos());
+        for i in 1..=3 {
+            let distance = i as f32 * 1.2;
+            let (hx, hy) = to_screen(px + dir_x * distance, pz + dir_z * distance);
+            let size = dot * 0.6;
+            window.rect(ui, hx - size / 2.0, hy - size / 2.0, size, size, PLAYER_COLOR);
+        }
+    }
+}
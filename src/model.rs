@@ -1,547 +1,449 @@
-use wgpu::util::DeviceExt;
-use glam::{Vec3, Mat4};
-
-#[repr(C)]
-#[derive(Copy, Clone, Debug)]
-pub struct ModelVertex {
-    position: [f32; 3],
-    color: [f32; 3],
-    tex_coords: [f32; 2],  // 添加纹理坐标
-    model_type: f32,
-}
-
-// 手动实现 bytemuck traits
-unsafe impl bytemuck::Pod for ModelVertex {}
-unsafe impl bytemuck::Zeroable for ModelVertex {}
-
-impl ModelVertex {
-    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
-        wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<ModelVertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                // position
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                // color
-                wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                // tex_coords
-                wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
-                    shader_location: 2,
-                    format: wgpu::VertexFormat::Float32x2,
-                },
-                // model_type
-                wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
-                    shader_location: 3,
-                    format: wgpu::VertexFormat::Float32,
-                },
-            ],
-        }
-    }
-}
-
-// 在文件开头添加
-use crate::texture::Texture;
-
-// 修改 Model 结构体
-pub struct Model {
-    pub name: String,
-    pub vertex_buffer: wgpu::Buffer,
-    pub index_buffer: wgpu::Buffer,
-    pub num_indices: u32,
-    pub color: [f32; 3],
-    pub model_type: u32,
-    pub texture: Option<Texture>,  // 添加纹理字段
-}
-
-// 修改 Model::new 方法
-impl Model {
-    pub fn new(
-        device: &wgpu::Device,
-        name: &str,
-        vertices: &[ModelVertex],
-        indices: &[u16],
-        color: [f32; 3],
-        is_wall: bool,
-        texture: Option<Texture>,  // 添加纹理参数
-    ) -> Self {
-        let vertex_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("{} Vertex Buffer", name)),
-                contents: bytemuck::cast_slice(vertices),
-                usage: wgpu::BufferUsages::VERTEX,
-            }
-        );
-        
-        let index_buffer = device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("{} Index Buffer", name)),
-                contents: bytemuck::cast_slice(indices),
-                usage: wgpu::BufferUsages::INDEX,
-            }
-        );
-        
-        Self {
-            name: name.to_string(),
-            vertex_buffer,
-            index_buffer,
-            num_indices: indices.len() as u32,
-            color,
-            model_type: if is_wall { 1 } else { 0 },
-            texture,  // 添加纹理
-        }
-    }
-
-    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
-    }
-}
-
-// Create a checkerboard pattern for floor or ceiling
-// 修改创建棋盘的函数
-fn create_checkerboard(
-    device: &wgpu::Device,
-    name: &str,
-    size: f32,
-    tile_size: f32,
-    height: f32,
-    color1: [f32; 3],
-    color2: [f32; 3],
-    is_ceiling: bool, // 添加参数控制朝向
-) -> Model {
-    let mut vertices = Vec::new();
-    let mut indices = Vec::new();
-    let tiles = (size / tile_size) as i32;
-    
-    for x in -tiles..=tiles {
-        for z in -tiles..=tiles {
-            let x0 = x as f32 * tile_size;
-            let z0 = z as f32 * tile_size;
-            let x1 = x0 + tile_size;
-            let z1 = z0 + tile_size;
-            
-            let color = if (x + z) % 2 == 0 { color1 } else { color2 };
-            let base_idx = vertices.len() as u16;
-
-            // 根据是否为天花板调整顶点顺序
-            if is_ceiling {
-                vertices.push(ModelVertex { position: [x0, height, z0], color, model_type: 0.0, tex_coords: [0.0, 0.0]  });
-                vertices.push(ModelVertex { position: [x1, height, z0], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-                vertices.push(ModelVertex { position: [x1, height, z1], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-                vertices.push(ModelVertex { position: [x0, height, z1], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-            } else {
-                vertices.push(ModelVertex { position: [x0, height, z0], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-                vertices.push(ModelVertex { position: [x0, height, z1], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-                vertices.push(ModelVertex { position: [x1, height, z1], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-                vertices.push(ModelVertex { position: [x1, height, z0], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-            }
-            
-            indices.extend_from_slice(&[
-                base_idx, base_idx + 1, base_idx + 2,
-                base_idx, base_idx + 2, base_idx + 3,
-            ]);
-        }
-    }
-        
-    Model::new(device, name, &vertices, &indices, [0.0, 0.0, 0.0], false, None)
-}
-
-// Create a wall with thickness
-// 修改创建墙体的函数
-fn create_wall(
-    device: &wgpu::Device,
-    start: [f32; 3],
-    end: [f32; 3],
-    height: f32,
-    color: [f32; 3],
-) -> Model {
-
-    let mut vertices = Vec::new();
-    let mut indices = Vec::new();
-    
-    // Calculate wall direction and length
-    let dx = end[0] - start[0];
-    let dz = end[2] - start[2];
-    
-    // Define wall thickness
-    let thickness = 0.3; // 30cm thickness
-    
-    // Calculate normal vector to the wall (perpendicular)
-    let length = (dx*dx + dz*dz).sqrt();
-    let nx = -dz / length;
-    let nz = dx / length;
-    
-    // Calculate the four corners of the front face
-    let front_bl = [start[0], 0.0, start[2]];
-    let front_br = [end[0], 0.0, end[2]];
-    let front_tr = [end[0], height, end[2]];
-    let front_tl = [start[0], height, start[2]];
-    
-    // Calculate the four corners of the back face (offset by thickness in normal direction)
-    let back_bl = [start[0] + nx * thickness, 0.0, start[2] + nz * thickness];
-    let back_br = [end[0] + nx * thickness, 0.0, end[2] + nz * thickness];
-    let back_tr = [end[0] + nx * thickness, height, end[2] + nz * thickness];
-    let back_tl = [start[0] + nx * thickness, height, start[2] + nz * thickness];
-    
-    // Add all 8 vertices
-    // 在 create_wall 函数中修改顶点创建部分
-    // Front face vertices
-    vertices.push(ModelVertex { position: front_bl, color, tex_coords: [0.0, 1.0], model_type: 1.0 });
-    vertices.push(ModelVertex { position: front_br, color, tex_coords: [1.0, 1.0], model_type: 1.0 });
-    vertices.push(ModelVertex { position: front_tr, color, tex_coords: [1.0, 0.0], model_type: 1.0 });
-    vertices.push(ModelVertex { position: front_tl, color, tex_coords: [0.0, 0.0], model_type: 1.0 });
-    
-    // Back face vertices
-    vertices.push(ModelVertex { position: back_bl, color, tex_coords: [0.0, 1.0], model_type: 1.0 });
-    vertices.push(ModelVertex { position: back_br, color, tex_coords: [1.0, 1.0], model_type: 1.0 });
-    vertices.push(ModelVertex { position: back_tr, color, tex_coords: [1.0, 0.0], model_type: 1.0 });
-    vertices.push(ModelVertex { position: back_tl, color, tex_coords: [0.0, 0.0], model_type: 1.0 });
-    
-    // Add indices for all six faces (each face has two triangles)
-    let base_idx = 0;
-    
-    // Front face (0,1,2,3)
-    indices.push(base_idx);
-    indices.push(base_idx + 2);
-    indices.push(base_idx + 1);
-    indices.push(base_idx);
-    indices.push(base_idx + 3);
-    indices.push(base_idx + 2);
-    
-    // Back face (4,5,6,7)
-    indices.push(base_idx + 4);
-    indices.push(base_idx + 5);
-    indices.push(base_idx + 6);
-    indices.push(base_idx + 4);
-    indices.push(base_idx + 6);
-    indices.push(base_idx + 7);
-    
-    // Top face (3,2,6,7)
-    indices.push(base_idx + 3);
-    indices.push(base_idx + 6);
-    indices.push(base_idx + 2);
-    indices.push(base_idx + 3);
-    indices.push(base_idx + 7);
-    indices.push(base_idx + 6);
-    
-    // Bottom face (0,1,5,4)
-    indices.push(base_idx);
-    indices.push(base_idx + 1);
-    indices.push(base_idx + 5);
-    indices.push(base_idx);
-    indices.push(base_idx + 5);
-    indices.push(base_idx + 4);
-    
-    // Left face (0,3,7,4)
-    indices.push(base_idx);
-    indices.push(base_idx + 7);
-    indices.push(base_idx + 3);
-    indices.push(base_idx);
-    indices.push(base_idx + 4);
-    indices.push(base_idx + 7);
-    
-    // Right face (1,2,6,5)
-    indices.push(base_idx + 1);
-    indices.push(base_idx + 6);
-    indices.push(base_idx + 5);
-    indices.push(base_idx + 1);
-    indices.push(base_idx + 2);
-    indices.push(base_idx + 6);
-
-    Model::new(device, "wall", &vertices, &indices, [0.5, 0.5, 0.5], true, None)
-}
-
-// Create a wall edge (black outline)
-fn create_wall_edge(
-    device: &wgpu::Device,
-    start: [f32; 3],
-    end: [f32; 3],
-    height: f32,
-    wall_thickness: f32,
-) -> Model {
-    let mut vertices = Vec::new();
-    let mut indices = Vec::new();
-    
-    // Define edge thickness (slightly larger than the wall)
-    let edge_thickness = 0.05; // 5cm thickness for the edge
-    let edge_offset = 0.02; // 2cm offset to make edges visible from all angles
-    
-    // Calculate wall direction and length
-    let dx = end[0] - start[0];
-    let dz = end[2] - start[2];
-    
-    // Calculate normal vector to the wall (perpendicular)
-    let length = (dx*dx + dz*dz).sqrt();
-    let nx = -dz / length;
-    let nz = dx / length;
-    
-    // Calculate tangent vector (along the wall)
-    let tx = dx / length;
-    let tz = dz / length;
-    
-    // Black color for all edges
-    let color = [0.0, 0.0, 0.0];
-    
-    // Create vertices for the vertical edges (4 corners)
-    
-    // Front-left vertical edge - make it protrude in all directions
-    let fl_base_idx = vertices.len() as u16;
-    vertices.push(ModelVertex { position: [start[0] - edge_thickness - tx * edge_offset, 0.0, start[2] - edge_thickness - tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    vertices.push(ModelVertex { position: [start[0] + edge_thickness - tx * edge_offset, 0.0, start[2] + edge_thickness - tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    vertices.push(ModelVertex { position: [start[0] + edge_thickness - tx * edge_offset, height, start[2] + edge_thickness - tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    vertices.push(ModelVertex { position: [start[0] - edge_thickness - tx * edge_offset, height, start[2] - edge_thickness - tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    
-    // Add indices for the front-left vertical edge - ensure correct winding order for visibility
-    indices.push(fl_base_idx);
-    indices.push(fl_base_idx + 1);
-    indices.push(fl_base_idx + 2);
-    indices.push(fl_base_idx);
-    indices.push(fl_base_idx + 2);
-    indices.push(fl_base_idx + 3);
-    
-    // Front-right vertical edge - make it protrude in all directions
-    let fr_base_idx = vertices.len() as u16;
-    vertices.push(ModelVertex { position: [end[0] - edge_thickness + tx * edge_offset, 0.0, end[2] - edge_thickness + tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    vertices.push(ModelVertex { position: [end[0] + edge_thickness + tx * edge_offset, 0.0, end[2] + edge_thickness + tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    vertices.push(ModelVertex { position: [end[0] + edge_thickness + tx * edge_offset, height, end[2] + edge_thickness + tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    vertices.push(ModelVertex { position: [end[0] - edge_thickness + tx * edge_offset, height, end[2] - edge_thickness + tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    
-    // Add indices for the front-right vertical edge - ensure correct winding order for visibility
-    indices.push(fr_base_idx);
-    indices.push(fr_base_idx + 1);
-    indices.push(fr_base_idx + 2);
-    indices.push(fr_base_idx);
-    indices.push(fr_base_idx + 2);
-    indices.push(fr_base_idx + 3);
-    
-    // Back-left vertical edge (for walls with thickness) - make it protrude in all directions
-    let bl_base_idx = vertices.len() as u16;
-    vertices.push(ModelVertex { position: [start[0] + nx * wall_thickness - edge_thickness - tx * edge_offset, 0.0, start[2] + nz * wall_thickness - edge_thickness - tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    vertices.push(ModelVertex { position: [start[0] + nx * wall_thickness + edge_thickness - tx * edge_offset, 0.0, start[2] + nz * wall_thickness + edge_thickness - tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    vertices.push(ModelVertex { position: [start[0] + nx * wall_thickness + edge_thickness - tx * edge_offset, height, start[2] + nz * wall_thickness + edge_thickness - tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    vertices.push(ModelVertex { position: [start[0] + nx * wall_thickness - edge_thickness - tx * edge_offset, height, start[2] + nz * wall_thickness - edge_thickness - tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    
-    // Add indices for the back-left vertical edge - ensure correct winding order for visibility
-    indices.push(bl_base_idx);
-    indices.push(bl_base_idx + 1);
-    indices.push(bl_base_idx + 2);
-    indices.push(bl_base_idx);
-    indices.push(bl_base_idx + 2);
-    indices.push(bl_base_idx + 3);
-    
-    // Back-right vertical edge (for walls with thickness) - make it protrude in all directions
-    let br_base_idx = vertices.len() as u16;
-    vertices.push(ModelVertex { position: [end[0] + nx * wall_thickness - edge_thickness + tx * edge_offset, 0.0, end[2] + nz * wall_thickness - edge_thickness + tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    vertices.push(ModelVertex { position: [end[0] + nx * wall_thickness + edge_thickness + tx * edge_offset, 0.0, end[2] + nz * wall_thickness + edge_thickness + tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    vertices.push(ModelVertex { position: [end[0] + nx * wall_thickness + edge_thickness + tx * edge_offset, height, end[2] + nz * wall_thickness + edge_thickness + tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    vertices.push(ModelVertex { position: [end[0] + nx * wall_thickness - edge_thickness + tx * edge_offset, height, end[2] + nz * wall_thickness - edge_thickness + tz * edge_offset], color, model_type: 0.0, tex_coords: [0.0, 0.0] });
-    
-    // 在 create_wall_edge 函数末尾添加缺少的索引
-    // Add indices for the back-right vertical edge
-    indices.push(br_base_idx);
-    indices.push(br_base_idx + 1);
-    indices.push(br_base_idx + 2);
-    indices.push(br_base_idx);
-    indices.push(br_base_idx + 2);
-    indices.push(br_base_idx + 3);
-    
-    Model::new(device, "wall_edge", &vertices, &indices, [0.0, 0.0, 0.0], false, None)
-}
-
-// Create the entire parking garage
-// 修改函数签名，使用引用而不是所有权
-pub fn create_parking_garage(device: &wgpu::Device, dog_texture: &Texture) -> Vec<Model> {
-    let mut models = Vec::new();
-    
-    // Define colors
-    let floor_color1 = [0.0, 0.0, 0.0]; // Pure black
-    let floor_color2 = [1.0, 1.0, 1.0]; // Pure white
-    let ceiling_color1 = [0.5, 0.5, 1.0]; // Light blue
-    let ceiling_color2 = [1.0, 1.0, 1.0]; // White
-    let wall_color = [1.0, 1.0, 1.0]; // Pure white
-    
-    // Create floor (black and white checkerboard)
-    let floor = create_checkerboard(
-        device,
-        "floor",
-        50.0, // size
-        2.0,  // tile size
-        0.0,  // height (at ground level)
-        floor_color1,
-        floor_color2,
-        false
-    );
-    models.push(floor);
-    
-    // Create ceiling (blue and white checkerboard)
-    let ceiling = create_checkerboard(
-        device,
-        "ceiling",
-        50.0, // size
-        2.0,  // tile size
-        4.0,  // height (ceiling height)
-        ceiling_color1,
-        ceiling_color2,
-        true
-    );
-    models.push(ceiling);
-    
-    // Create walls for a rectangular parking garage
-    let garage_width = 30.0;
-    let garage_length = 40.0;
-    let wall_height = 4.0;
-    
-    // Define wall thickness for edge creation
-    let wall_thickness = 0.3;
-    
-    // Front wall (with a gap for entrance)
-    let front_wall1 = create_wall(
-        device,
-        [-garage_width/2.0, 0.0, -garage_length/2.0],
-        [-5.0, 0.0, -garage_length/2.0],
-        wall_height,
-        wall_color,
-    );
-    models.push(front_wall1);
-    
-    // Add black edge to front wall 1
-    let front_edge1 = create_wall_edge(
-        device,
-        [-garage_width/2.0, 0.0, -garage_length/2.0],
-        [-5.0, 0.0, -garage_length/2.0],
-        wall_height,
-        wall_thickness,
-    );
-    models.push(front_edge1);
-    
-    let front_wall2 = create_wall(
-        device,
-        [5.0, 0.0, -garage_length/2.0],
-        [garage_width/2.0, 0.0, -garage_length/2.0],
-        wall_height,
-        wall_color,
-    );
-    models.push(front_wall2);
-    
-    // Add black edge to front wall 2
-    let front_edge2 = create_wall_edge(
-        device,
-        [5.0, 0.0, -garage_length/2.0],
-        [garage_width/2.0, 0.0, -garage_length/2.0],
-        wall_height,
-        wall_thickness,
-    );
-    models.push(front_edge2);
-    
-    // Back wall
-    let back_wall = create_wall(
-        device,
-        [-garage_width/2.0, 0.0, garage_length/2.0],
-        [garage_width/2.0, 0.0, garage_length/2.0],
-        wall_height,
-        wall_color,
-    );
-    models.push(back_wall);
-    
-    // Add black edge to back wall
-    let back_edge = create_wall_edge(
-        device,
-        [-garage_width/2.0, 0.0, garage_length/2.0],
-        [garage_width/2.0, 0.0, garage_length/2.0],
-        wall_height,
-        wall_thickness,
-    );
-    models.push(back_edge);
-    
-    // Left wall
-    let left_wall = create_wall(
-        device,
-        [-garage_width/2.0, 0.0, -garage_length/2.0],
-        [-garage_width/2.0, 0.0, garage_length/2.0],
-        wall_height,
-        wall_color,
-    );
-    models.push(left_wall);
-    
-    // Add black edge to left wall
-    let left_edge = create_wall_edge(
-        device,
-        [-garage_width/2.0, 0.0, -garage_length/2.0],
-        [-garage_width/2.0, 0.0, garage_length/2.0],
-        wall_height,
-        wall_thickness,
-    );
-    models.push(left_edge);
-    
-    // Right wall
-    let right_wall = create_wall(
-        device,
-        [garage_width/2.0, 0.0, -garage_length/2.0],
-        [garage_width/2.0, 0.0, garage_length/2.0],
-        wall_height,
-        wall_color,
-    );
-    models.push(right_wall);
-    
-    // Add black edge to right wall
-    let right_edge = create_wall_edge(
-        device,
-        [garage_width/2.0, 0.0, -garage_length/2.0],
-        [garage_width/2.0, 0.0, garage_length/2.0],
-        wall_height,
-        wall_thickness,
-    );
-    models.push(right_edge);
-    
-    // Add some interior walls to make it more interesting
-    let interior_wall1 = create_wall(
-        device,
-        [-10.0, 0.0, 0.0],
-        [10.0, 0.0, 0.0],
-        wall_height,
-        wall_color,
-    );
-    models.push(interior_wall1);
-    
-    // Add black edge to interior wall 1
-    let interior_edge1 = create_wall_edge(
-        device,
-        [-10.0, 0.0, 0.0],
-        [10.0, 0.0, 0.0],
-        wall_height,
-        wall_thickness,
-    );
-    models.push(interior_edge1);
-    
-    let interior_wall2 = create_wall(
-        device,
-        [0.0, 0.0, 5.0],
-        [0.0, 0.0, 15.0],
-        wall_height,
-        wall_color,
-    );
-    models.push(interior_wall2);
-    
-    // Add black edge to interior wall 2
-    let interior_edge2 = create_wall_edge(
-        device,
-        [0.0, 0.0, 5.0],
-        [0.0, 0.0, 15.0],
-        wall_height,
-        wall_thickness,
-    );
-    models.push(interior_edge2);
-    
-    models
-}
\ No newline at end of file
+use glam::{Mat4, Vec2, Vec3};
+
+use crate::geometry::{self, TexCoords};
+use crate::gpu_memory;
+use crate::mesh::MeshBuilder;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct ModelVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub tex_coords: [f32; 2],  // 添加纹理坐标
+    pub model_type: f32,
+    // 为零时着色器用屏幕空间导数求面的法向量
+    pub normal: [f32; 3],
+}
+
+// 手动实现 bytemuck traits
+unsafe impl bytemuck::Pod for ModelVertex {}
+unsafe impl bytemuck::Zeroable for ModelVertex {}
+
+impl ModelVertex {
+    pub fn new(position: glam::Vec3, color: [f32; 3], tex_coords: [f32; 2], model_type: f32) -> Self {
+        Self { position: position.to_array(), color, tex_coords, model_type, normal: [0.0; 3] }
+    }
+
+    pub fn with_normal(self, normal: Vec3) -> Self {
+        Self { normal: normal.to_array(), ..self }
+    }
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ModelVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                // position
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                // color
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+                // tex_coords
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // model_type
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32,
+                },
+                // normal
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 9]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x3,
+                },
+            ],
+        }
+    }
+}
+
+// 在文件开头添加
+use crate::texture::Texture;
+use crate::map::{self, Map};
+use crate::decals::{self, Decal};
+
+// 修改 Model 结构体
+#[allow(dead_code)] // name/color/texture 目前只作记录用，渲染时尚未读取
+pub struct Model {
+    pub name: String,
+    pub vertex_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    pub index_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    pub num_indices: u32,
+    pub color: [f32; 3],
+    pub model_type: u32,
+    pub texture: Option<Texture>,  // 添加纹理字段
+}
+
+// 修改 Model::new 方法
+impl Model {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        name: &str,
+        vertices: &[ModelVertex],
+        indices: &[u16],
+        color: [f32; 3],
+        is_wall: bool,
+        texture: Option<Texture>,  // 添加纹理参数
+        category: gpu_memory::Category, // 显存统计的分类
+    ) -> Self {
+        let vertex_buffer = gpu_memory::buffer_init(
+            device,
+            category,
+            &wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{} Vertex Buffer", name)),
+                contents: bytemuck::cast_slice(vertices),
+                // 静态合批时要从这里复制
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_SRC,
+            }
+        );
+        
+        let index_buffer = gpu_memory::buffer_init(
+            device,
+            category,
+            &wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{} Index Buffer", name)),
+                contents: bytemuck::cast_slice(indices),
+                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_SRC,
+            }
+        );
+        
+        Self {
+            name: name.to_string(),
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            color,
+            model_type: if is_wall { 1 } else { 0 },
+            texture,  // 添加纹理
+        }
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
+
+// Create a checkerboard pattern for floor or ceiling
+// 地板朝上，天花板朝下
+fn checkerboard_mesh(size: f32, tile_size: f32, height: f32, color1: [f32; 3], color2: [f32; 3], is_ceiling: bool) -> MeshBuilder {
+    let mut mesh = MeshBuilder::new();
+    let tiles = (size / tile_size) as i32;
+
+    for x in -tiles..=tiles {
+        for z in -tiles..=tiles {
+            let center = Vec3::new((x as f32 + 0.5) * tile_size, height, (z as f32 + 0.5) * tile_size);
+            let color = if (x + z) % 2 == 0 { color1 } else { color2 };
+            geometry::plane(&mut mesh, center, Vec2::splat(tile_size), !is_ceiling, color, 0.0, TexCoords::NONE);
+        }
+    }
+    mesh
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_checkerboard(
+    device: &wgpu::Device,
+    name: &str,
+    size: f32,
+    tile_size: f32,
+    height: f32,
+    color1: [f32; 3],
+    color2: [f32; 3],
+    is_ceiling: bool, // 添加参数控制朝向
+) -> Model {
+    checkerboard_mesh(size, tile_size, height, color1, color2, is_ceiling).build(device, name, [0.0, 0.0, 0.0], false, gpu_memory::Category::Map)
+}
+
+// 墙体的方向：沿墙的方向和从正面指向背面的方向
+fn wall_axes(start: Vec3, end: Vec3) -> (Vec3, Vec3) {
+    let tangent = (end - start).normalize();
+    (tangent, Vec3::new(-tangent.z, 0.0, tangent.x))
+}
+
+// Create a wall with thickness
+// 正面在墙格子的线上，背面沿法向量偏移一个厚度，每个面都铺满一张纹理
+fn wall_mesh(start: Vec3, end: Vec3, height: f32, thickness: f32, color: [f32; 3]) -> MeshBuilder {
+    let mut mesh = MeshBuilder::new();
+    let (tangent, normal) = wall_axes(start, end);
+    let center = (start + end) / 2.0 + normal * (thickness / 2.0) + Vec3::Y * (height / 2.0);
+    let transform = geometry::box_transform(center, tangent * (end - start).length(), Vec3::Y * height, normal * thickness);
+    geometry::cuboid(&mut mesh, transform, color, 1.0, TexCoords::FULL);
+    mesh
+}
+
+fn create_wall(
+    device: &wgpu::Device,
+    start: [f32; 3],
+    end: [f32; 3],
+    height: f32,
+    color: [f32; 3],
+) -> Model {
+    let thickness = 0.3; // 30cm thickness
+    wall_mesh(Vec3::from(start), Vec3::from(end), height, thickness, color).build(device, "wall", [0.5, 0.5, 0.5], true, gpu_memory::Category::Map)
+}
+
+// Create a wall edge (black outline)
+// 墙的四条竖边各是一根细方柱，比墙稍微突出一点，从哪个方向看都能看到
+fn wall_edge_mesh(start: Vec3, end: Vec3, height: f32, wall_thickness: f32) -> MeshBuilder {
+    let mut mesh = MeshBuilder::new();
+    let edge_thickness = 0.05; // 5cm thickness for the edge
+    let edge_offset = 0.02; // 2cm offset to make edges visible from all angles
+    let color = [0.0, 0.0, 0.0];
+
+    let (tangent, normal) = wall_axes(start, end);
+    let back = normal * wall_thickness;
+    let corners = [
+        start - tangent * edge_offset,
+        end + tangent * edge_offset,
+        start + back - tangent * edge_offset,
+        end + back + tangent * edge_offset,
+    ];
+    for corner in corners {
+        let transform = geometry::box_transform(corner + Vec3::Y * (height / 2.0), tangent * edge_thickness * 2.0, Vec3::Y * height, normal * edge_thickness * 2.0);
+        geometry::cuboid(&mut mesh, transform, color, 0.0, TexCoords::NONE);
+    }
+    mesh
+}
+
+fn create_wall_edge(
+    device: &wgpu::Device,
+    start: [f32; 3],
+    end: [f32; 3],
+    height: f32,
+    wall_thickness: f32,
+) -> Model {
+    wall_edge_mesh(Vec3::from(start), Vec3::from(end), height, wall_thickness).build(device, "wall_edge", [0.0, 0.0, 0.0], false, gpu_memory::Category::Map)
+}
+
+// 半透明物体：model_type 3，不透明度放在纹理坐标里。半透明物体不剔除背面，所以一个面就够了
+
+// 玻璃板：位于墙体碰撞体厚度的中间
+pub fn create_glass_pane(device: &wgpu::Device, segment: &map::WallSegment, thickness: f32) -> Model {
+    let (start, end) = (Vec3::from(segment.start), Vec3::from(segment.end));
+    let (_, normal) = wall_axes(start, end);
+    let (color, alpha) = ([0.6, 0.8, 0.9], 0.3);
+    let center = (start + end) / 2.0 + normal * (thickness / 2.0) + Vec3::Y * (map::WALL_HEIGHT / 2.0);
+    let mut mesh = MeshBuilder::new();
+    geometry::quad(&mut mesh, center, (end - start) / 2.0, Vec3::Y * (map::WALL_HEIGHT / 2.0), color, 3.0, TexCoords::Constant([alpha, 0.0]));
+    mesh.build(device, "glass", color, false, gpu_memory::Category::Map)
+}
+
+// 积水：略高于地面的半透明方块
+pub fn create_water(device: &wgpu::Device, center: Vec3, size: f32) -> Model {
+    let (color, alpha) = ([0.15, 0.3, 0.45], 0.55);
+    let mut mesh = MeshBuilder::new();
+    geometry::plane(&mut mesh, Vec3::new(center.x, 0.008, center.z), Vec2::splat(size), true, color, 3.0, TexCoords::Constant([alpha, 0.0]));
+    mesh.build(device, "water", color, false, gpu_memory::Category::Map)
+}
+
+// 柱子的半径，碰撞体是外接的正方形
+pub const PILLAR_RADIUS: f32 = 0.35;
+
+// 所有柱子合并成一个模型：灰色的混凝土圆柱，底部一圈黄色的防撞漆
+fn pillars_mesh(centers: &[Vec3]) -> MeshBuilder {
+    let mut mesh = MeshBuilder::new();
+    for &center in centers {
+        geometry::cylinder(&mut mesh, center, center + Vec3::Y * map::WALL_HEIGHT, PILLAR_RADIUS, 16, [0.62, 0.62, 0.64]);
+        geometry::cylinder(&mut mesh, center, center + Vec3::Y, PILLAR_RADIUS + 0.02, 16, [0.9, 0.72, 0.08]);
+    }
+    mesh
+}
+
+// 地面上的方形标记（略高于地面，避免深度冲突）
+fn create_floor_marker(device: &wgpu::Device, center: Vec3, size: f32, color: [f32; 3]) -> Model {
+    let mut mesh = MeshBuilder::new();
+    geometry::plane(&mut mesh, Vec3::new(center.x, 0.02, center.z), Vec2::splat(size), true, color, 0.0, TexCoords::NONE);
+    mesh.build(device, "floor_marker", [0.0, 0.0, 0.0], false, gpu_memory::Category::Map)
+}
+
+// 贴花：纯色贴花是朝向法向量的八边形，图集贴花是带纹理的方形（model_type 2，着色器里按透明度裁剪）
+pub fn create_decals<'a>(device: &wgpu::Device, decals: impl Iterator<Item = &'a Decal>) -> Model {
+    const SIDES: u32 = 8;
+    let mut mesh = MeshBuilder::new();
+    for decal in decals {
+        // 在表面上取两个互相垂直的方向，tangent × bitangent = normal（逆时针朝外）
+        let helper = if decal.normal.y.abs() > 0.9 { Vec3::X } else { Vec3::Y };
+        let tangent = helper.cross(decal.normal).normalize();
+        let bitangent = decal.normal.cross(tangent);
+        let (sin, cos) = decal.rotation.sin_cos();
+        let (tangent, bitangent) = ((tangent * cos + bitangent * sin) * decal.radius, (bitangent * cos - tangent * sin) * decal.radius);
+
+        match decal.atlas_cell {
+            Some(cell) => {
+                let (uv_min, uv_max) = decals::atlas_uv(cell);
+                geometry::quad(&mut mesh, decal.position, tangent, bitangent, decal.color, 2.0, TexCoords::Rect(uv_min, uv_max));
+            }
+            None => geometry::disc(&mut mesh, decal.position, tangent, bitangent, SIDES, decal.color),
+        }
+    }
+    mesh.build(device, "decals", [0.0, 0.0, 0.0], false, gpu_memory::Category::Decals)
+}
+
+// 动态物体（敌人、尸体、粒子）用的立方体实例，transform 作用于中心在原点的单位立方体
+#[derive(Debug, Clone, Copy)]
+pub struct BoxInstance {
+    pub transform: Mat4,
+    pub color: [f32; 3],
+}
+
+// 把一组立方体合并成一个模型，每帧重新生成
+pub fn create_boxes(device: &wgpu::Device, boxes: &[BoxInstance]) -> Model {
+    box_model(device, "boxes", gpu_memory::Category::Dynamic, boxes.iter().map(|instance| (instance, 0.0, [0.0, 0.0])))
+}
+
+// 有光泽的立方体（车漆、车窗等）：model_type 4，反射强度放在 tex_coords.x
+pub fn create_glossy_boxes(device: &wgpu::Device, boxes: &[(BoxInstance, f32)]) -> Model {
+    box_model(device, "glossy_boxes", gpu_memory::Category::Map, boxes.iter().map(|(instance, reflectivity)| (instance, 4.0, [*reflectivity, 0.0])))
+}
+
+fn box_model<'a>(device: &wgpu::Device, name: &str, category: gpu_memory::Category, boxes: impl Iterator<Item = (&'a BoxInstance, f32, [f32; 2])>) -> Model {
+    // u16 索引的上限
+    let max_boxes = u16::MAX as usize / 24;
+    let mut mesh = MeshBuilder::new();
+    for (instance, model_type, tex_coords) in boxes.take(max_boxes) {
+        geometry::cuboid(&mut mesh, instance.transform, instance.color, model_type, TexCoords::Constant(tex_coords));
+    }
+    mesh.build(device, name, [0.0, 0.0, 0.0], false, category)
+}
+
+// 碰撞体的线框（F3 调试显示），每帧重新生成
+pub fn create_collider_outlines(device: &wgpu::Device, colliders: &[crate::collision::WallCollider]) -> Model {
+    // u16 索引的上限，每个线框十二条棱
+    let max_boxes = u16::MAX as usize / (24 * 12);
+    let mut mesh = MeshBuilder::new();
+    for collider in colliders.iter().take(max_boxes) {
+        geometry::line_box(&mut mesh, collider.box_transform(), 0.03, [0.1, 1.0, 0.3]);
+    }
+    mesh.build(device, "collider_outlines", [0.0, 0.0, 0.0], false, gpu_memory::Category::Dynamic)
+}
+
+// 依次连起来的折线（AI 调试面板里敌人的路线），每帧重新生成
+pub fn create_polyline(device: &wgpu::Device, points: &[Vec3], color: [f32; 3]) -> Model {
+    let mut mesh = MeshBuilder::new();
+    for pair in points.windows(2) {
+        geometry::line(&mut mesh, pair[0], pair[1], 0.04, color);
+    }
+    mesh.build(device, "polyline", [0.0, 0.0, 0.0], false, gpu_memory::Category::Dynamic)
+}
+
+// Create the entire parking garage
+// 修改函数签名，使用引用而不是所有权
+pub fn create_parking_garage(device: &wgpu::Device, map: &Map, _dog_texture: &Texture) -> Vec<Model> {
+    let mut models = Vec::new();
+    
+    // Define colors
+    let floor_color1 = [0.0, 0.0, 0.0]; // Pure black
+    let floor_color2 = [1.0, 1.0, 1.0]; // Pure white
+    let ceiling_color1 = [0.5, 0.5, 1.0]; // Light blue
+    let ceiling_color2 = [1.0, 1.0, 1.0]; // White
+    let wall_color = [1.0, 1.0, 1.0]; // Pure white
+    
+    // Create floor (black and white checkerboard)
+    let floor = create_checkerboard(
+        device,
+        "floor",
+        50.0, // size
+        2.0,  // tile size
+        0.0,  // height (at ground level)
+        floor_color1,
+        floor_color2,
+        false
+    );
+    models.push(floor);
+    
+    // Create ceiling (blue and white checkerboard)
+    let ceiling = create_checkerboard(
+        device,
+        "ceiling",
+        50.0, // size
+        2.0,  // tile size
+        4.0,  // height (ceiling height)
+        ceiling_color1,
+        ceiling_color2,
+        true
+    );
+    models.push(ceiling);
+    
+    // 墙体来自地图文件的墙格子
+    let wall_height = map::WALL_HEIGHT;
+    
+    // Define wall thickness for edge creation
+    let wall_thickness = 0.3;
+    
+    for segment in map.wall_segments() {
+        models.push(create_wall(device, segment.start, segment.end, wall_height, wall_color));
+        
+        // Add black edge to the wall
+        models.push(create_wall_edge(device, segment.start, segment.end, wall_height, wall_thickness));
+    }
+    
+    let pillars: Vec<Vec3> = map.pillar_cells().into_iter().map(|(col, row)| map.cell_center(col, row)).collect();
+    if !pillars.is_empty() {
+        models.push(pillars_mesh(&pillars).build(device, "pillars", [0.0, 0.0, 0.0], false, gpu_memory::Category::Map));
+    }
+
+    // 触发区域在地面上画黄色标记
+    let marker_color = [1.0, 0.8, 0.1];
+    for (col, row) in map.trigger_cells() {
+        models.push(create_floor_marker(device, map.cell_center(col, row), map::CELL_SIZE * 0.8, marker_color));
+    }
+    
+    models
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 各个方向的墙，包括斜的
+    const WALL_DIRECTIONS: [Vec3; 6] = [
+        Vec3::X,
+        Vec3::NEG_X,
+        Vec3::Z,
+        Vec3::NEG_Z,
+        Vec3::new(1.0, 0.0, 1.0),
+        Vec3::new(-3.0, 0.0, 1.0),
+    ];
+
+    #[test]
+    fn wall_faces_point_outward() {
+        for direction in WALL_DIRECTIONS {
+            let start = Vec3::new(2.0, 0.0, -1.0);
+            wall_mesh(start, start + direction * 4.0, 4.0, 0.3, [1.0; 3]).assert_outward();
+        }
+    }
+
+    #[test]
+    fn wall_edge_posts_point_outward() {
+        for direction in WALL_DIRECTIONS {
+            let start = Vec3::new(-1.0, 0.0, 3.0);
+            let mesh = wall_edge_mesh(start, start + direction * 2.0, 4.0, 0.3);
+            // 每根柱子单独检查：六个面，24 个顶点
+            for (vertices, indices) in mesh.vertices.chunks(24).zip(mesh.indices.chunks(36)) {
+                let first = indices.iter().min().copied().unwrap();
+                let post = MeshBuilder { vertices: vertices.to_vec(), indices: indices.iter().map(|index| index - first).collect() };
+                post.assert_outward();
+            }
+        }
+    }
+
+    #[test]
+    fn floor_faces_up_and_ceiling_faces_down() {
+        let floor = checkerboard_mesh(4.0, 2.0, 0.0, [0.0; 3], [1.0; 3], false);
+        let ceiling = checkerboard_mesh(4.0, 2.0, 4.0, [0.0; 3], [1.0; 3], true);
+        assert!(floor.triangles().all(|(_, normal)| normal.dot(Vec3::Y) > 0.99));
+        assert!(ceiling.triangles().all(|(_, normal)| normal.dot(Vec3::NEG_Y) > 0.99));
+    }
+}
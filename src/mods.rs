@@ -0,0 +1,189 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::de::DeserializeOwned;
+
+use crate::{assets, paths, schema};
+
+// mod：用户数据目录的 mods/ 下每个目录是一个 mod，目录结构和游戏的资源一样：
+//   maps/*.map 和 maps/prefabs/    新地图或者替换原来的（地图里的触发区域就是关卡的脚本）
+//   data/weapons.json             新武器，和已有武器 id 相同时替换
+//   data/hazards.json             新的场景危害，同上
+//   data/shop.json                新商品，同上
+//   data/enemies.json 等数据文件   整个替换
+//   dog.png、luts/ 等纹理          整个替换
+// mod.json（可选）写菜单里显示的名字和说明。mods/load_order.json 记录加载顺序和每个 mod 是否启用，
+// 排在后面的 mod 覆盖前面的。启动时扫描一次，菜单里改了开关下次启动生效
+
+const MOD_INFO: &str = "mod.json";
+const LOAD_ORDER: &str = "load_order.json";
+// 这些文件是合并的（见 load_overlaid），不整个替换
+const MERGED: [&str; 3] = ["data/weapons.json", "data/hazards.json", "data/shop.json"];
+
+#[derive(Debug, Clone)]
+pub struct Mod {
+    // 目录名，load_order.json 里用它
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub enabled: bool,
+    root: PathBuf,
+}
+
+impl Mod {
+    // mod 里的这个文件，没有时返回 None
+    pub fn file(&self, path: &Path) -> Option<PathBuf> {
+        let file = self.root.join(path);
+        file.is_file().then_some(file)
+    }
+}
+
+#[derive(Default, serde::Deserialize)]
+struct ModInfo {
+    name: Option<String>,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct OrderEntry {
+    name: String,
+    enabled: bool,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct LoadOrder {
+    mods: Vec<OrderEntry>,
+}
+
+// 启动时启用的 mod，按加载顺序
+static ACTIVE: OnceLock<Vec<Mod>> = OnceLock::new();
+
+// 启动时调用一次；没调用过（比如 --pack）时不加载任何 mod
+pub fn init() {
+    let active: Vec<Mod> = scan().into_iter().filter(|m| m.enabled).collect();
+    if !active.is_empty() {
+        let names: Vec<&str> = active.iter().map(|m| m.id.as_str()).collect();
+        println!("加载 mod: {}", names.join(", "));
+    }
+    let _ = ACTIVE.set(active);
+}
+
+pub fn active() -> &'static [Mod] {
+    ACTIVE.get_or_init(Vec::new)
+}
+
+// 合并的数据文件：游戏自带的列表加上启用的 mod 里的，key 相同时后加载的替换前面的。
+// F 是文件的格式，list 从里面取出列表；解析失败的文件跳过，日志里用 what 说明是什么定义
+pub fn load_overlaid<F: DeserializeOwned, T>(
+    path: &str,
+    builtin: &'static str,
+    version: u32,
+    what: &str,
+    list: impl Fn(F) -> Vec<T>,
+    key: impl Fn(&T) -> &str,
+) -> Vec<T> {
+    let path = Path::new(path);
+    let text = assets::read_to_string_or_builtin(path, builtin);
+    let mut items = match schema::parse_json::<F>(&text, version) {
+        Ok(file) => list(file),
+        Err(e) => {
+            eprintln!("{}定义解析失败 ({}): {}", what, path.display(), e);
+            Vec::new()
+        }
+    };
+    for (m, file) in active().iter().filter_map(|m| Some((m, m.file(path)?))) {
+        let parsed = std::fs::read_to_string(&file)
+            .map_err(|e| e.to_string())
+            .and_then(|text| schema::parse_json::<F>(&text, version));
+        match parsed {
+            Ok(parsed) => {
+                for item in list(parsed) {
+                    match items.iter_mut().find(|existing| key(existing) == key(&item)) {
+                        Some(existing) => *existing = item,
+                        None => items.push(item),
+                    }
+                }
+            }
+            Err(e) => eprintln!("mod {} 的{}定义解析失败 ({}): {}", m.id, what, file.display(), e),
+        }
+    }
+    items
+}
+
+// 启用的 mod 里最后一个有这个文件的，用来替换游戏自带的
+pub fn find(path: &Path) -> Option<PathBuf> {
+    if MERGED.iter().any(|merged| Path::new(merged) == path) {
+        return None;
+    }
+    active().iter().rev().find_map(|m| m.file(path))
+}
+
+// 启用的 mod 带来的地图（maps/ 下的 .map），按名字排序
+pub fn maps() -> &'static [String] {
+    static MAPS: OnceLock<Vec<String>> = OnceLock::new();
+    MAPS.get_or_init(|| {
+        let mut names: Vec<String> = active()
+            .iter()
+            .filter_map(|m| std::fs::read_dir(m.root.join("maps")).ok())
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                (path.extension()? == "map").then(|| path.file_stem()?.to_str().map(str::to_string))?
+            })
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    })
+}
+
+// mods/ 下所有的 mod，按 load_order.json 的顺序；新放进来的排在最后，默认启用
+pub fn scan() -> Vec<Mod> {
+    let dir = paths::mods();
+    let order: LoadOrder = std::fs::read_to_string(dir.join(LOAD_ORDER))
+        .ok()
+        .and_then(|text| {
+            serde_json::from_str(&text).map_err(|e| eprintln!("mod 加载顺序解析失败 ({}): {}", LOAD_ORDER, e)).ok()
+        })
+        .unwrap_or_default();
+    let mut found: Vec<String> = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    found.sort();
+    let mut mods = Vec::new();
+    for entry in &order.mods {
+        if let Some(index) = found.iter().position(|name| *name == entry.name) {
+            mods.push(load_mod(&dir, found.remove(index), entry.enabled));
+        }
+    }
+    mods.extend(found.into_iter().map(|name| load_mod(&dir, name, true)));
+    mods
+}
+
+fn load_mod(dir: &Path, id: String, enabled: bool) -> Mod {
+    let root = dir.join(&id);
+    let info: ModInfo = match std::fs::read_to_string(root.join(MOD_INFO)) {
+        Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+            eprintln!("mod {} 的 {} 解析失败: {}", id, MOD_INFO, e);
+            ModInfo::default()
+        }),
+        Err(_) => ModInfo::default(),
+    };
+    Mod { title: info.name.unwrap_or_else(|| id.clone()), description: info.description, id, enabled, root }
+}
+
+// 保存加载顺序和开关
+pub fn save_order(mods: &[Mod]) {
+    let order = LoadOrder { mods: mods.iter().map(|m| OrderEntry { name: m.id.clone(), enabled: m.enabled }).collect() };
+    let path = paths::mods().join(LOAD_ORDER);
+    let result = serde_json::to_string_pretty(&order).map_err(|e| e.to_string()).and_then(|text| std::fs::write(&path, text).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        eprintln!("无法保存 mod 加载顺序 {}: {}", path.display(), e);
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::VecDeque;
+
+use glam::Vec3;
+
+use crate::collision::{self, WallCollider};
+use crate::map::{self, Map};
+
+// 导航：基于地图格子的距离场（从若干起点出发的 BFS 步数，绕开墙体）。
+// 格子路线只能横竖走，沿着走会在格子中心之间拐来拐去；string_pull 把路线拉直，
+// 空地上直接斜着走过去，能不能直线走过去用碰撞体的射线检测
+
+pub struct DistanceField {
+    width: usize,
+    // 每个格子到最近起点的步数，不可到达为 None
+    distances: Vec<Option<u32>>,
+}
+
+impl DistanceField {
+    pub fn compute(map: &Map, sources: &[(usize, usize)]) -> Self {
+        let mut distances = vec![None; map.width * map.height];
+        let mut queue = VecDeque::new();
+        for &(col, row) in sources {
+            if map.cell(col, row).is_walkable() && distances[row * map.width + col].is_none() {
+                distances[row * map.width + col] = Some(0);
+                queue.push_back((col, row));
+            }
+        }
+
+        while let Some((col, row)) = queue.pop_front() {
+            let distance = distances[row * map.width + col].unwrap_or(0) + 1;
+            let neighbours = [
+                (col.wrapping_sub(1), row),
+                (col + 1, row),
+                (col, row.wrapping_sub(1)),
+                (col, row + 1),
+            ];
+            for (next_col, next_row) in neighbours {
+                // cell() 对越界坐标返回 Void，不可行走
+                if !map.cell(next_col, next_row).is_walkable() {
+                    continue;
+                }
+                let index = next_row * map.width + next_col;
+                if distances[index].is_none() {
+                    distances[index] = Some(distance);
+                    queue.push_back((next_col, next_row));
+                }
+            }
+        }
+
+        Self { width: map.width, distances }
+    }
+
+    // 从世界坐标计算（起点不在地图内时忽略）
+    pub fn from_positions(map: &Map, positions: &[Vec3]) -> Self {
+        let sources: Vec<_> = positions.iter().filter_map(|p| map.world_to_cell(*p)).collect();
+        Self::compute(map, &sources)
+    }
+
+    pub fn distance(&self, col: usize, row: usize) -> Option<u32> {
+        self.distances.get(row * self.width + col).copied().flatten()
+    }
+
+    // 从 from 格子沿距离减小的方向走到最近起点经过的格子（包括两端），走不到时为空
+    pub fn path(&self, from: (usize, usize)) -> Vec<(usize, usize)> {
+        let Some(mut here) = self.distance(from.0, from.1) else {
+            return Vec::new();
+        };
+        let mut path = vec![from];
+        let (mut col, mut row) = from;
+        while here > 0 {
+            let neighbours = [(col.wrapping_sub(1), row), (col + 1, row), (col, row.wrapping_sub(1)), (col, row + 1)];
+            let Some((next, distance)) = neighbours
+                .into_iter()
+                .filter_map(|(col, row)| Some(((col, row), self.distance(col, row)?)))
+                .filter(|(_, distance)| *distance < here)
+                .min_by_key(|(_, distance)| *distance)
+            else {
+                break;
+            };
+            (col, row) = next;
+            here = distance;
+            path.push(next);
+        }
+        path
+    }
+}
+
+// 选择离威胁（敌人）最远的重生点；与威胁不连通的重生点最安全
+pub fn farthest_spawn(map: &Map, threats: &[Vec3]) -> Vec3 {
+    let field = DistanceField::from_positions(map, threats);
+    map.respawn_cells()
+        .into_iter()
+        .max_by_key(|&(col, row)| field.distance(col, row).unwrap_or(u32::MAX))
+        .map(|(col, row)| map.cell_center(col, row))
+        .unwrap_or_else(|| map.spawn_point())
+}
+
+// 身体半径为 radius 的东西能不能从 from 直线走到 to：离地 radius 高的中线和左右两边各一条射线都不碰墙，
+// 路过的格子都能走（地图外和不能走的格子没有碰撞体挡着）
+pub fn walkable_line(map: &Map, colliders: &[WallCollider], from: Vec3, to: Vec3, radius: f32) -> bool {
+    let delta = Vec3::new(to.x - from.x, 0.0, to.z - from.z);
+    let distance = delta.length();
+    if distance <= f32::EPSILON {
+        return true;
+    }
+    let dir = delta / distance;
+    let steps = (distance / (map::CELL_SIZE * 0.5)).ceil() as usize;
+    let cells_open = (0..=steps).all(|i| {
+        let point = from + delta * (i as f32 / steps as f32);
+        map.world_to_cell(point).is_some_and(|(col, row)| map.cell(col, row).is_walkable())
+    });
+    if !cells_open {
+        return false;
+    }
+    let side = Vec3::Y.cross(dir) * radius;
+    let origin = Vec3::new(from.x, radius, from.z);
+    [Vec3::ZERO, side, -side].into_iter().all(|offset| collision::raycast_all(colliders, origin + offset, dir, distance).is_empty())
+}
+
+// points[0] 能直线走到的最远的点的编号，一个都走不到时为 1（至少往下一个点走）
+pub fn shortcut(points: &[Vec3], clear: impl Fn(Vec3, Vec3) -> bool) -> usize {
+    (2..points.len()).rev().find(|&i| clear(points[0], points[i])).unwrap_or(1)
+}
+
+// 拉直路线（string pulling）：从起点开始每次直接走到能直线走过去的最远的点，跳过中间的拐角
+pub fn string_pull(points: &[Vec3], clear: impl Fn(Vec3, Vec3) -> bool) -> Vec<Vec3> {
+    let mut result: Vec<Vec3> = points.first().copied().into_iter().collect();
+    let mut anchor = 0;
+    while anchor + 1 < points.len() {
+        anchor += shortcut(&points[anchor..], &clear);
+        result.push(points[anchor]);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pulls_the_string_around_a_corner() {
+        // L 形的格子路线，拐角内侧 (0.5, 0.5) 到 (1, 1) 的方块挡着
+        let points: Vec<Vec3> = [(0.0, 0.0), (0.0, 1.0), (0.0, 2.0), (1.0, 2.0), (2.0, 2.0)]
+            .into_iter()
+            .map(|(x, z)| Vec3::new(x, 0.0, z))
+            .collect();
+        let blocked = |a: Vec3, b: Vec3| (0..=20).any(|i| {
+            let p = a.lerp(b, i as f32 / 20.0);
+            p.x > 0.5 && p.x < 1.0 && p.z > 0.5 && p.z < 1.0
+        });
+        let pulled = string_pull(&points, |a, b| !blocked(a, b));
+        assert_eq!(pulled, [points[0], points[3], points[4]]);
+        // 没有东西挡着时直接走到终点
+        assert_eq!(string_pull(&points, |_, _| true), [points[0], points[4]]);
+        assert_eq!(shortcut(&points, |_, _| false), 1);
+    }
+}
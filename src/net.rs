@@ -0,0 +1,1655 @@
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::Duration;
+
+use glam::Vec3;
+use instant::Instant;
+
+use crate::anti_cheat::{Guard, Verdict};
+use crate::bots::Bots;
+use crate::collision::Aabb;
+use crate::demo::{self, DemoEvent, DemoHeader};
+use crate::lag_comp;
+use crate::loadout::Loadout;
+use crate::match_end::{self, MatchSummary};
+use crate::net_sim::{Conditions, DelayQueue};
+use crate::server_config;
+use crate::snapshot::{self, PlayerState, Snapshot, SnapshotHistory};
+
+// 局域网联机：UDP 广播发现 + 简单的连接握手，双方定期互相 ping 测量延迟和丢包。
+// 连上以后客户端定期把自己的状态发给主机，主机把所有玩家的状态打成增量快照发回（见 snapshot.rs）。
+// 主机离开时剩下的玩家里延迟最低的接着当主机，其他人带着原来的编号重新加入它
+
+pub const GAME_PORT: u16 = 27015;
+pub const DISCOVERY_PORT: u16 = 27016;
+pub const MAX_PLAYERS: u32 = 16;
+
+const BEACON_INTERVAL: Duration = Duration::from_secs(1);
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+const HELLO_RETRY_INTERVAL: Duration = Duration::from_millis(500);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+const PING_INTERVAL: Duration = Duration::from_millis(250);
+// 超过这个时间没有回应的 ping 算丢包
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+// 丢包率按最近这么多次 ping 计算
+const LOSS_WINDOW: usize = 40;
+// 超过这个时间没收到广播的服务器从列表中移除
+const SERVER_EXPIRY: Duration = Duration::from_secs(3);
+// 快照和客户端状态的发送间隔，每秒 20 次
+const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(50);
+// 快照是二进制的数据包，用第一个字节和 JSON 数据包（总是以 { 或 " 开头）区分
+const SNAPSHOT_TAG: u8 = 0x01;
+
+// 新主机的快照编号往后跳这么多，比所有客户端手里的快照都新
+const MIGRATION_TICK_GAP: u32 = 20;
+// 玩家列表里的名字截短，16 个人的列表要装进一个数据包
+const PEER_NAME_LEN: usize = 16;
+
+const MAX_PACKET_SIZE: usize = 1400;
+const MAX_CHAT_LEN: usize = 200;
+
+// 服务器对外公布的信息（广播和握手时都会带上）
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct ServerInfo {
+    pub name: String,
+    pub map: String,
+    pub mode: String,
+    pub players: u32,
+    pub max_players: u32,
+    pub port: u16,
+}
+
+// 主机告诉客户端的其他玩家，选新主机用
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+struct PeerInfo {
+    player_id: u32,
+    name: String,
+    addr: SocketAddr,
+    // 主机测到的往返时间（毫秒），还没测出来时为 None
+    ping_ms: Option<u32>,
+}
+
+// 延迟最低的当新主机，延迟一样时编号小的优先。所有客户端拿到的是同一份列表，选出来的结果一样
+fn elect(peers: &[PeerInfo]) -> Option<&PeerInfo> {
+    peers.iter().min_by_key(|peer| (peer.ping_ms.unwrap_or(u32::MAX), peer.player_id))
+}
+
+// 击杀提示：主机判定以后广播给所有人，killer 为 None 时是被地图或者调试伤害打死的
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct KillInfo {
+    pub killer: Option<u32>,
+    pub killer_name: Option<String>,
+    pub victim: u32,
+    pub victim_name: String,
+    pub weapon: Option<String>,
+}
+
+// 计分板上的一行，主机统计以后定期发给客户端
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct Score {
+    pub player_id: u32,
+    pub name: String,
+    pub kills: u32,
+    pub deaths: u32,
+    // 主机测到的往返时间，主机自己为 None
+    pub ping_ms: Option<u32>,
+    pub host: bool,
+    pub bot: bool,
+    // 对玩家开的枪和打中的次数，算命中率
+    pub shots: u32,
+    pub hits: u32,
+}
+
+// 一个玩家这局的击杀、死亡和命中数
+#[derive(Clone, Copy, Debug, Default)]
+struct Tally {
+    kills: u32,
+    deaths: u32,
+    shots: u32,
+    hits: u32,
+}
+
+// 比赛结束以后的换图投票，player_id -> 投的第几个候选
+struct Vote {
+    candidates: Vec<String>,
+    votes: HashMap<u32, usize>,
+    ends: Instant,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+enum Packet {
+    Beacon(ServerInfo),
+    Hello { name: String },
+    Welcome { player_id: u32, info: ServerInfo },
+    Reject { reason: String },
+    Heartbeat,
+    // 收到 Ping 立刻用同样的编号回 Pong
+    Ping { seq: u32 },
+    Pong { seq: u32 },
+    Bye,
+    // 客户端发给主机的聊天内容
+    ChatSend { text: String },
+    // 主机转发给所有客户端的聊天消息
+    Chat { player_id: u32, name: String, text: String },
+    // 客户端自己的状态，顺带确认收到的最新快照
+    PlayerState { ack: Option<u32>, state: PlayerState },
+    // 主机发给客户端的快照，不走 JSON
+    #[serde(skip)]
+    Snapshot(Vec<u8>),
+    // 客户端开枪，tick 是开枪时看到的最新快照，主机按那时的位置判定命中
+    Fire { tick: Option<u32>, weapon: String, origin: [f32; 3], dir: [f32; 3] },
+    // 主机通知被打中的客户端
+    Damage { amount: f32, shooter: u32, weapon: String },
+    // 客户端告诉主机自己死了，killer 是最后打中自己的玩家
+    Died { killer: Option<u32>, weapon: Option<String> },
+    // 主机广播的击杀
+    Kill(KillInfo),
+    // 主机定期发给客户端的玩家列表和这局已经进行了多久（秒），投票中为 None
+    Peers { peers: Vec<PeerInfo>, match_elapsed: Option<f32> },
+    // 主机定期发给客户端的计分板
+    Scores { scores: Vec<Score> },
+    // 比赛结束，开始投票
+    MatchEnd(MatchSummary),
+    // 客户端投票，choice 是第几个候选
+    Vote { choice: usize },
+    // 主机广播每个候选的票数
+    Votes { counts: Vec<u32> },
+    // 投票结束，所有人加载这张地图开始下一局
+    ChangeMap { map: String },
+    // 主机迁移以后客户端带着原来的编号加入新主机
+    Rejoin { player_id: u32, name: String },
+    // 客户端进场前发来选的出生配置，主机检查以后把允许的版本发回去
+    Loadout(Loadout),
+}
+
+// 网络层产生的事件，交给 State 处理
+#[derive(Debug)]
+pub enum NetEvent {
+    PlayerJoined { player_id: u32, name: String },
+    PlayerLeft { player_id: u32, name: String },
+    Connected { player_id: u32, info: ServerInfo },
+    Disconnected { reason: String },
+    Chat { player_id: u32, name: String, text: String },
+    // 主机收到客户端开枪，targets 是退回到客户端看到的那一刻的其他玩家
+    PlayerFired { player_id: u32, weapon: String, origin: Vec3, dir: Vec3, targets: Vec<(u32, Aabb)> },
+    // 主机收到客户端进场前选的出生配置
+    LoadoutRequested { player_id: u32, loadout: Loadout },
+    // 客户端收到主机检查过的出生配置，可以进场了
+    LoadoutApproved(Loadout),
+    // 客户端被别的玩家打中
+    Damaged { amount: f32, shooter: u32, weapon: String },
+    // 有玩家被击杀（击杀提示用）
+    Kill(KillInfo),
+    // 比赛结束，显示总结和投票
+    MatchEnded(MatchSummary),
+    Votes(Vec<u32>),
+    // 投票结束，换地图开始下一局
+    ChangeMap { map: String },
+    // 主机离开了，换成别的玩家当主机
+    HostMigrated { player_id: u32, name: String },
+    // 主机离开了，自己接着当主机
+    BecameHost,
+}
+
+fn encode_packet(packet: &Packet) -> Option<Vec<u8>> {
+    if let Packet::Snapshot(data) = packet {
+        let mut bytes = Vec::with_capacity(data.len() + 1);
+        bytes.push(SNAPSHOT_TAG);
+        bytes.extend_from_slice(data);
+        return Some(bytes);
+    }
+    match serde_json::to_vec(packet) {
+        Ok(bytes) => Some(bytes),
+        Err(e) => {
+            eprintln!("数据包序列化失败: {}", e);
+            None
+        }
+    }
+}
+
+fn send_packet(socket: &UdpSocket, addr: SocketAddr, packet: &Packet) {
+    if let Some(bytes) = encode_packet(packet) {
+        send_bytes(socket, addr, &bytes);
+    }
+}
+
+fn send_bytes(socket: &UdpSocket, addr: SocketAddr, bytes: &[u8]) {
+    if let Err(e) = socket.send_to(bytes, addr) {
+        eprintln!("网络发送失败 {}: {}", addr, e);
+    }
+}
+
+fn parse_packet(addr: SocketAddr, bytes: &[u8]) -> Option<Packet> {
+    if let Some((&SNAPSHOT_TAG, data)) = bytes.split_first() {
+        return Some(Packet::Snapshot(data.to_vec()));
+    }
+    match serde_json::from_slice(bytes) {
+        Ok(packet) => Some(packet),
+        Err(e) => {
+            eprintln!("忽略无法解析的数据包 {}: {}", addr, e);
+            None
+        }
+    }
+}
+
+// 读出套接字上所有待处理的数据包（非阻塞）
+fn recv_packets(socket: &UdpSocket) -> Vec<(SocketAddr, Packet)> {
+    recv_bytes(socket).into_iter().filter_map(|(addr, bytes)| Some((addr, parse_packet(addr, &bytes)?))).collect()
+}
+
+fn recv_bytes(socket: &UdpSocket) -> Vec<(SocketAddr, Vec<u8>)> {
+    let mut packets = Vec::new();
+    let mut buf = [0u8; MAX_PACKET_SIZE];
+    loop {
+        match socket.recv_from(&mut buf) {
+            Ok((len, addr)) => packets.push((addr, buf[..len].to_vec())),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+            // Windows 上对端关闭时会收到 ConnectionReset，忽略即可
+            Err(e) if e.kind() == io::ErrorKind::ConnectionReset => continue,
+            Err(e) => {
+                eprintln!("网络接收失败: {}", e);
+                break;
+            }
+        }
+    }
+    packets
+}
+
+// 游戏连接用的套接字。打开网络模拟时收发的数据包先在队列里等一会儿（或者被丢掉）
+struct Link {
+    socket: UdpSocket,
+    conditions: Conditions,
+    outgoing: DelayQueue,
+    incoming: DelayQueue,
+}
+
+impl Link {
+    fn new(socket: UdpSocket, conditions: Conditions) -> Self {
+        Self { socket, conditions, outgoing: DelayQueue::new(), incoming: DelayQueue::new() }
+    }
+
+    fn send(&mut self, addr: SocketAddr, packet: &Packet) {
+        if !self.conditions.is_active() {
+            send_packet(&self.socket, addr, packet);
+            return;
+        }
+        if let Some(bytes) = encode_packet(packet) {
+            self.outgoing.push(&self.conditions, Instant::now(), addr, bytes);
+        }
+    }
+
+    // 收到的数据包，同时送出到时间的数据包
+    fn recv(&mut self) -> Vec<(SocketAddr, Packet)> {
+        if !self.conditions.is_active() {
+            return recv_packets(&self.socket);
+        }
+        let now = Instant::now();
+        for (addr, bytes) in self.outgoing.due(now) {
+            send_bytes(&self.socket, addr, &bytes);
+        }
+        for (addr, bytes) in recv_bytes(&self.socket) {
+            self.incoming.push(&self.conditions, now, addr, bytes);
+        }
+        self.incoming.due(now).into_iter().filter_map(|(addr, bytes)| Some((addr, parse_packet(addr, &bytes)?))).collect()
+    }
+}
+
+// 和一个对端之间的连接质量
+#[derive(Debug, Clone, Copy)]
+pub struct PeerStats {
+    // 平滑后的往返时间，还没有收到过回应时为 None
+    pub ping_ms: Option<f32>,
+    // 最近一段时间的丢包率，0 到 1
+    pub loss: f32,
+    // 距离上次收到对方数据包的时间
+    pub last_packet_ms: f32,
+    // 最近一个快照的大小（字节），主机是发给这个客户端的，客户端是收到的
+    pub snapshot_bytes: Option<usize>,
+}
+
+// 定期发送 ping，根据回应算出往返时间和丢包率
+struct PingTracker {
+    next_seq: u32,
+    last_ping: Option<Instant>,
+    pending: Vec<(u32, Instant)>,
+    // 最近的 ping 是否丢了
+    results: VecDeque<bool>,
+    rtt_ms: Option<f32>,
+}
+
+impl PingTracker {
+    fn new() -> Self {
+        Self { next_seq: 0, last_ping: None, pending: Vec::new(), results: VecDeque::new(), rtt_ms: None }
+    }
+
+    fn record(&mut self, lost: bool) {
+        if self.results.len() == LOSS_WINDOW {
+            self.results.pop_front();
+        }
+        self.results.push_back(lost);
+    }
+
+    // 到时间了返回要发的 ping 编号，同时把超时的 ping 记为丢包
+    fn tick(&mut self, now: Instant) -> Option<u32> {
+        let (expired, pending): (Vec<_>, Vec<_>) = self.pending.drain(..).partition(|(_, sent)| now.duration_since(*sent) > PING_TIMEOUT);
+        self.pending = pending;
+        for _ in expired {
+            self.record(true);
+        }
+        if self.last_ping.is_some_and(|last| now.duration_since(last) < PING_INTERVAL) {
+            return None;
+        }
+        let seq = self.next_seq;
+        self.next_seq = self.next_seq.wrapping_add(1);
+        self.last_ping = Some(now);
+        self.pending.push((seq, now));
+        Some(seq)
+    }
+
+    fn pong(&mut self, seq: u32, now: Instant) {
+        let Some(index) = self.pending.iter().position(|(pending, _)| *pending == seq) else { return };
+        let (_, sent) = self.pending.remove(index);
+        let rtt = now.duration_since(sent).as_secs_f32() * 1000.0;
+        self.rtt_ms = Some(self.rtt_ms.map_or(rtt, |smoothed| smoothed + (rtt - smoothed) * 0.2));
+        self.record(false);
+    }
+
+    fn stats(&self, last_seen: Instant, now: Instant) -> PeerStats {
+        let lost = self.results.iter().filter(|lost| **lost).count();
+        PeerStats {
+            ping_ms: self.rtt_ms,
+            loss: if self.results.is_empty() { 0.0 } else { lost as f32 / self.results.len() as f32 },
+            last_packet_ms: now.duration_since(last_seen).as_secs_f32() * 1000.0,
+            snapshot_bytes: None,
+        }
+    }
+}
+
+pub fn local_player_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "Player".to_string())
+}
+
+struct RemotePlayer {
+    player_id: u32,
+    name: String,
+    last_seen: Instant,
+    ping: PingTracker,
+    // 检查客户端发来的状态和射击，里面记着最后一次通过检查的状态
+    guard: Guard,
+    // 客户端确认收到的最新快照，增量快照以它为基准
+    acked: Option<u32>,
+    snapshot_bytes: Option<usize>,
+    tally: Tally,
+}
+
+// 主机端：接受客户端连接并定期在局域网广播自己
+pub struct NetServer {
+    link: Link,
+    info: ServerInfo,
+    clients: HashMap<SocketAddr, RemotePlayer>,
+    // 主机自己的编号，最初的主机是 0，迁移过来的主机沿用它当客户端时的编号
+    host_id: u32,
+    next_player_id: u32,
+    last_beacon: Option<Instant>,
+    last_heartbeat: Instant,
+    host_state: Option<PlayerState>,
+    snapshots: SnapshotHistory,
+    next_tick: u32,
+    last_snapshot: Instant,
+    rewind: lag_comp::History,
+    // 在 poll 以外产生的事件（射击检查踢出的玩家），下次 poll 时交出去
+    pending_events: Vec<NetEvent>,
+    // 这局比赛的录像，打不开文件时不录
+    demo: Option<demo::Recorder>,
+    // 主机玩家自己的名字，击杀提示用
+    host_name: String,
+    host_tally: Tally,
+    match_started: Instant,
+    vote: Option<Vote>,
+    // 补人数的机器人和它们的计分
+    bots: Bots,
+    bot_tallies: HashMap<u32, Tally>,
+    last_poll: Instant,
+}
+
+impl NetServer {
+    pub fn host(name: &str, map: &str, mode: &str, conditions: Conditions) -> io::Result<Self> {
+        let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, GAME_PORT))?;
+        socket.set_nonblocking(true)?;
+        socket.set_broadcast(true)?;
+
+        println!("局域网主机已启动，端口 {}", GAME_PORT);
+        let demo = demo::Recorder::start(DemoHeader::new(map, mode, 0, &local_player_name()));
+
+        Ok(Self {
+            link: Link::new(socket, conditions),
+            info: ServerInfo {
+                name: name.to_string(),
+                map: map.to_string(),
+                mode: mode.to_string(),
+                players: 1, // 主机自己
+                max_players: MAX_PLAYERS,
+                port: GAME_PORT,
+            },
+            clients: HashMap::new(),
+            host_id: 0,
+            next_player_id: 1, // 0 号留给主机
+            last_beacon: None,
+            last_heartbeat: Instant::now(),
+            host_state: None,
+            snapshots: SnapshotHistory::new(),
+            next_tick: 0,
+            last_snapshot: Instant::now(),
+            rewind: lag_comp::History::new(),
+            pending_events: Vec::new(),
+            demo,
+            host_name: local_player_name(),
+            host_tally: Tally::default(),
+            match_started: Instant::now(),
+            vote: None,
+            bots: Bots::new(),
+            bot_tallies: HashMap::new(),
+            last_poll: Instant::now(),
+        })
+    }
+
+    // 客户端在主机离开后接手：沿用它的套接字（其他人从玩家列表里知道地址）
+    fn promote(client: NetClient, player_id: u32, info: ServerInfo) -> Self {
+        if let Err(e) = client.link.socket.set_broadcast(true) {
+            eprintln!("无法打开广播，局域网里看不到这个主机: {}", e);
+        }
+        let mut server = Self::take_over(client, player_id, info, Instant::now());
+        println!("主机已离开，由本机接手，端口 {}", server.info.port);
+        // 接手后录一个新的录像，玩家已经在场上，先记下他们的名字
+        server.demo = demo::Recorder::start(DemoHeader::new(&server.info.map, &server.info.mode, player_id, &server.host_name));
+        if let Some(demo) = &mut server.demo {
+            for remote in server.clients.values() {
+                demo.event(&DemoEvent::PlayerJoined { player_id: remote.player_id, name: remote.name.clone() });
+            }
+        }
+        server
+    }
+
+    // promote 里不碰广播和录像文件的部分：其他玩家先按最后一个快照里的位置放进来，等他们重新加入
+    fn take_over(client: NetClient, player_id: u32, mut info: ServerInfo, now: Instant) -> Self {
+        if let Ok(addr) = client.link.socket.local_addr() {
+            info.port = addr.port();
+        }
+        info.name = client.name.clone();
+
+        let last_snapshot = client.snapshots.latest();
+        // 计分板接着原来主机的算
+        let tally = |player_id: u32| {
+            client.scores.iter().find(|score| score.player_id == player_id).map_or(Tally::default(), |score| Tally {
+                kills: score.kills,
+                deaths: score.deaths,
+                shots: score.shots,
+                hits: score.hits,
+            })
+        };
+        let mut clients = HashMap::new();
+        for peer in client.peers.iter().filter(|peer| peer.player_id != player_id) {
+            let mut guard = Guard::new(now);
+            let state = last_snapshot.and_then(|snapshot| snapshot.players().into_iter().find(|(id, _)| *id == peer.player_id));
+            if let Some((_, state)) = state {
+                guard.check_move(&peer.name, state, now);
+            }
+            let remote = RemotePlayer {
+                player_id: peer.player_id,
+                name: peer.name.clone(),
+                last_seen: now,
+                ping: PingTracker::new(),
+                guard,
+                acked: None,
+                snapshot_bytes: None,
+                tally: tally(peer.player_id),
+            };
+            clients.insert(peer.addr, remote);
+        }
+        info.players = clients.len() as u32 + 1;
+        let next_player_id = clients.values().map(|remote: &RemotePlayer| remote.player_id).chain([player_id]).max().unwrap_or(0) + 1;
+
+        Self {
+            link: client.link,
+            info,
+            clients,
+            host_id: player_id,
+            next_player_id,
+            last_beacon: None,
+            last_heartbeat: now,
+            host_state: client.local_state,
+            snapshots: SnapshotHistory::new(),
+            next_tick: last_snapshot.map_or(0, |snapshot| snapshot.tick.wrapping_add(MIGRATION_TICK_GAP)),
+            last_snapshot: now,
+            rewind: lag_comp::History::new(),
+            pending_events: Vec::new(),
+            demo: None,
+            host_name: client.name.clone(),
+            host_tally: tally(player_id),
+            // 比赛时间接着原来主机的算；投票中途主机离开的话直接开始新的一局
+            match_started: client.match_started.unwrap_or(now),
+            vote: None,
+            bots: Bots::new(),
+            bot_tallies: HashMap::new(),
+            last_poll: now,
+        }
+    }
+
+    pub fn info(&self) -> &ServerInfo {
+        &self.info
+    }
+
+    pub fn host_id(&self) -> u32 {
+        self.host_id
+    }
+
+    // 新玩家加入，或者主机迁移后老玩家重新加入（requested 是原来的编号）
+    fn accept(&mut self, addr: SocketAddr, name: String, requested: Option<u32>, now: Instant, events: &mut Vec<NetEvent>) {
+        // 重复的 Hello（客户端重传）、新主机里已经预留了位置的老玩家，直接再回一次 Welcome
+        if let Some(client) = self.clients.get_mut(&addr) {
+            client.last_seen = now;
+            let welcome = Packet::Welcome { player_id: client.player_id, info: self.info.clone() };
+            self.link.send(addr, &welcome);
+            return;
+        }
+        if self.info.players >= self.info.max_players {
+            self.link.send(addr, &Packet::Reject { reason: "server full".to_string() });
+            return;
+        }
+        // 原来的编号已经被占用时当新玩家
+        let taken = |id: u32| id == self.host_id || self.clients.values().any(|client| client.player_id == id);
+        let player_id = match requested {
+            Some(id) if !taken(id) => id,
+            _ => self.next_player_id,
+        };
+        self.next_player_id = self.next_player_id.max(player_id + 1);
+        self.clients.insert(
+            addr,
+            RemotePlayer {
+                player_id,
+                name: name.clone(),
+                last_seen: now,
+                ping: PingTracker::new(),
+                guard: Guard::new(now),
+                acked: None,
+                snapshot_bytes: None,
+                tally: Tally::default(),
+            },
+        );
+        self.info.players = self.clients.len() as u32 + 1;
+        self.link.send(addr, &Packet::Welcome { player_id, info: self.info.clone() });
+        events.push(NetEvent::PlayerJoined { player_id, name });
+    }
+
+    pub fn poll(&mut self) -> Vec<NetEvent> {
+        let mut events = std::mem::take(&mut self.pending_events);
+        let now = Instant::now();
+
+        for (addr, packet) in self.link.recv() {
+            match packet {
+                Packet::Hello { name } => self.accept(addr, name, None, now, &mut events),
+                Packet::Rejoin { player_id, name } => self.accept(addr, name, Some(player_id), now, &mut events),
+                Packet::Heartbeat => {
+                    if let Some(client) = self.clients.get_mut(&addr) {
+                        client.last_seen = now;
+                    }
+                }
+                Packet::Ping { seq } => {
+                    if let Some(client) = self.clients.get_mut(&addr) {
+                        client.last_seen = now;
+                        self.link.send(addr, &Packet::Pong { seq });
+                    }
+                }
+                Packet::Pong { seq } => {
+                    if let Some(client) = self.clients.get_mut(&addr) {
+                        client.last_seen = now;
+                        client.ping.pong(seq, now);
+                    }
+                }
+                Packet::Bye => {
+                    if let Some(client) = self.clients.remove(&addr) {
+                        events.push(NetEvent::PlayerLeft { player_id: client.player_id, name: client.name });
+                    }
+                }
+                Packet::ChatSend { text } => {
+                    let Some(client) = self.clients.get_mut(&addr) else { continue };
+                    client.last_seen = now;
+                    let text: String = text.chars().take(MAX_CHAT_LEN).collect();
+                    let (player_id, name) = (client.player_id, client.name.clone());
+                    self.broadcast(&Packet::Chat { player_id, name: name.clone(), text: text.clone() });
+                    events.push(NetEvent::Chat { player_id, name, text });
+                }
+                Packet::PlayerState { ack, state } => {
+                    let Some(client) = self.clients.get_mut(&addr) else { continue };
+                    client.last_seen = now;
+                    // 乱序到达的旧确认不能让基准往回退
+                    if ack > client.acked {
+                        client.acked = ack;
+                    }
+                    if let Verdict::Kick(violation) = client.guard.check_move(&client.name, state, now) {
+                        self.kick(addr, violation.name());
+                    }
+                }
+                Packet::Fire { tick, weapon, origin, dir } => {
+                    let Some(client) = self.clients.get_mut(&addr) else { continue };
+                    client.last_seen = now;
+                    let player_id = client.player_id;
+                    let targets = self.rewind.rewind(tick, player_id);
+                    let (origin, dir) = (Vec3::from(origin), Vec3::from(dir).normalize_or_zero());
+                    events.push(NetEvent::PlayerFired { player_id, weapon, origin, dir, targets });
+                }
+                Packet::Loadout(loadout) => {
+                    let Some(client) = self.clients.get_mut(&addr) else { continue };
+                    client.last_seen = now;
+                    events.push(NetEvent::LoadoutRequested { player_id: client.player_id, loadout });
+                }
+                Packet::Vote { choice } => {
+                    let Some(client) = self.clients.get_mut(&addr) else { continue };
+                    client.last_seen = now;
+                    let player_id = client.player_id;
+                    self.cast_vote(player_id, choice, &mut events);
+                }
+                Packet::Died { killer, weapon } => {
+                    let Some(client) = self.clients.get_mut(&addr) else { continue };
+                    client.last_seen = now;
+                    let victim = client.player_id;
+                    if let Some(kill) = self.broadcast_kill(killer, victim, weapon) {
+                        events.push(NetEvent::Kill(kill));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // 清理超时的客户端
+        let timed_out: Vec<SocketAddr> = self.clients.iter()
+            .filter(|(_, client)| now.duration_since(client.last_seen) > PEER_TIMEOUT)
+            .map(|(addr, _)| *addr)
+            .collect();
+        for addr in timed_out {
+            if let Some(client) = self.clients.remove(&addr) {
+                events.push(NetEvent::PlayerLeft { player_id: client.player_id, name: client.name });
+            }
+        }
+        self.info.players = self.clients.len() as u32 + 1;
+        self.update_bots(now, &mut events);
+
+        if now.duration_since(self.last_heartbeat) >= HEARTBEAT_INTERVAL {
+            self.last_heartbeat = now;
+            self.broadcast(&Packet::Heartbeat);
+            let peers = self
+                .clients
+                .iter()
+                .map(|(addr, client)| PeerInfo {
+                    player_id: client.player_id,
+                    name: client.name.chars().take(PEER_NAME_LEN).collect(),
+                    addr: *addr,
+                    ping_ms: client.ping.rtt_ms.map(|ping| ping.round() as u32),
+                })
+                .collect();
+            let match_elapsed = self.vote.is_none().then(|| now.duration_since(self.match_started).as_secs_f32());
+            self.broadcast(&Packet::Peers { peers, match_elapsed });
+            self.broadcast(&Packet::Scores { scores: self.scores() });
+        }
+        let pings: Vec<(SocketAddr, u32)> =
+            self.clients.iter_mut().filter_map(|(addr, client)| Some((*addr, client.ping.tick(now)?))).collect();
+        for (addr, seq) in pings {
+            self.link.send(addr, &Packet::Ping { seq });
+        }
+
+        if now.duration_since(self.last_snapshot) >= SNAPSHOT_INTERVAL {
+            self.last_snapshot = now;
+            self.send_snapshots();
+        }
+        self.update_match(now, &mut events);
+
+        // 广播不经过网络模拟
+        if self.last_beacon.is_none_or(|t| now.duration_since(t) >= BEACON_INTERVAL) {
+            self.last_beacon = Some(now);
+            let broadcast = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::BROADCAST, DISCOVERY_PORT));
+            send_packet(&self.link.socket, broadcast, &Packet::Beacon(self.info.clone()));
+        }
+
+        if let Some(demo) = &mut self.demo {
+            for event in &events {
+                let recorded = match event {
+                    NetEvent::PlayerJoined { player_id, name } => DemoEvent::PlayerJoined { player_id: *player_id, name: name.clone() },
+                    NetEvent::PlayerLeft { player_id, name } => DemoEvent::PlayerLeft { player_id: *player_id, name: name.clone() },
+                    NetEvent::Chat { name, text, .. } => DemoEvent::Chat { name: name.clone(), text: text.clone() },
+                    NetEvent::Kill(kill) => DemoEvent::Kill(kill.clone()),
+                    _ => continue,
+                };
+                demo.event(&recorded);
+            }
+        }
+        events
+    }
+
+    // 每个客户端收到相对它确认过的快照的增量，确认的快照太旧（不在历史里）时收到完整的快照
+    fn send_snapshots(&mut self) {
+        let mut players: Vec<(u32, PlayerState)> = self.clients.values().filter_map(|client| Some((client.player_id, client.guard.state()?))).collect();
+        if let Some(state) = self.host_state {
+            players.push((self.host_id, state));
+        }
+        players.extend(self.bots.states());
+        self.rewind.record(self.next_tick, &players);
+        let snapshot = Snapshot::new(self.next_tick, &players);
+        self.next_tick = self.next_tick.wrapping_add(1);
+        let mut packets = Vec::new();
+        for (addr, client) in &mut self.clients {
+            let baseline = client.acked.and_then(|tick| self.snapshots.get(tick));
+            let bytes = snapshot::encode(&snapshot, baseline);
+            client.snapshot_bytes = Some(bytes.len());
+            packets.push((*addr, Packet::Snapshot(bytes)));
+        }
+        for (addr, packet) in packets {
+            self.link.send(addr, &packet);
+        }
+        if let Some(demo) = &mut self.demo {
+            demo.snapshot(&snapshot);
+        }
+        self.snapshots.push(snapshot);
+    }
+
+    // 主机玩家自己的状态，每帧更新
+    pub fn set_local_player(&mut self, state: PlayerState) {
+        self.host_state = Some(state);
+    }
+
+    // 其他玩家（包括机器人）的最新状态
+    pub fn remote_players(&self) -> Vec<(u32, PlayerState)> {
+        let mut players: Vec<(u32, PlayerState)> = self.clients.values().filter_map(|client| Some((client.player_id, client.guard.state()?))).collect();
+        players.extend(self.bots.states());
+        players
+    }
+
+    // 人数不够 bot_fill 时补机器人，人来了再让出位置
+    fn update_bots(&mut self, now: Instant, events: &mut Vec<NetEvent>) {
+        let dt = now.duration_since(self.last_poll).as_secs_f32();
+        self.last_poll = now;
+        let wanted = (server_config::current().bot_fill as usize).saturating_sub(self.clients.len() + 1);
+        let (joined, left) = self.bots.fill(wanted, &mut self.next_player_id);
+        for (player_id, name) in joined {
+            events.push(NetEvent::PlayerJoined { player_id, name });
+        }
+        for (player_id, name) in left {
+            self.bot_tallies.remove(&player_id);
+            events.push(NetEvent::PlayerLeft { player_id, name });
+        }
+        self.bots.update(dt);
+    }
+
+    // 地图的出生点和重生点（脚下的位置），机器人在这些地方出现
+    pub fn set_bot_spawns(&mut self, spawns: Vec<Vec3>) {
+        self.bots.set_spawns(spawns);
+    }
+
+    pub fn is_bot(&self, player_id: u32) -> bool {
+        self.bots.contains(player_id)
+    }
+
+    // 客户端开枪前检查射速和开枪位置，返回 false 时这一枪不算
+    pub fn check_fire(&mut self, player_id: u32, origin: Vec3, fire_interval: f32) -> bool {
+        let now = Instant::now();
+        let Some((addr, client)) = self.clients.iter_mut().find(|(_, client)| client.player_id == player_id) else { return false };
+        match client.guard.check_fire(&client.name, origin, fire_interval, now) {
+            Verdict::Accept => true,
+            Verdict::Reject(_) => false,
+            Verdict::Kick(violation) => {
+                let addr = *addr;
+                self.kick(addr, violation.name());
+                false
+            }
+        }
+    }
+
+    fn kick(&mut self, addr: SocketAddr, reason: &str) {
+        let Some(client) = self.clients.remove(&addr) else { return };
+        self.link.send(addr, &Packet::Reject { reason: format!("kicked: {}", reason) });
+        self.info.players = self.clients.len() as u32 + 1;
+        self.pending_events.push(NetEvent::PlayerLeft { player_id: client.player_id, name: client.name });
+    }
+
+    // 主机判定某个客户端被打中了
+    pub fn damage_player(&mut self, player_id: u32, amount: f32, shooter: u32, weapon: &str) {
+        if self.bots.contains(player_id) {
+            if self.bots.damage(player_id, amount) {
+                if let Some(kill) = self.broadcast_kill(Some(shooter), player_id, Some(weapon.to_string())) {
+                    self.pending_events.push(NetEvent::Kill(kill));
+                }
+            }
+            return;
+        }
+        let Some(addr) = self.clients.iter().find(|(_, client)| client.player_id == player_id).map(|(addr, _)| *addr) else { return };
+        self.link.send(addr, &Packet::Damage { amount, shooter, weapon: weapon.to_string() });
+    }
+
+    // 把检查过的出生配置发回给客户端
+    pub fn send_loadout(&mut self, player_id: u32, loadout: &Loadout) {
+        let Some(addr) = self.clients.iter().find(|(_, client)| client.player_id == player_id).map(|(addr, _)| *addr) else { return };
+        self.link.send(addr, &Packet::Loadout(loadout.clone()));
+    }
+
+    fn name_of(&self, player_id: u32) -> Option<String> {
+        if player_id == self.host_id {
+            return Some(self.host_name.clone());
+        }
+        if let Some(name) = self.bots.name(player_id) {
+            return Some(name.to_string());
+        }
+        self.clients.values().find(|client| client.player_id == player_id).map(|client| client.name.clone())
+    }
+
+    fn tally_mut(&mut self, player_id: u32) -> Option<&mut Tally> {
+        if player_id == self.host_id {
+            return Some(&mut self.host_tally);
+        }
+        if self.bots.contains(player_id) {
+            return Some(self.bot_tallies.entry(player_id).or_default());
+        }
+        self.clients.values_mut().find(|client| client.player_id == player_id).map(|client| &mut client.tally)
+    }
+
+    // 有玩家死亡（主机自己，或者客户端报告的），记到计分板上并广播给所有人
+    fn broadcast_kill(&mut self, killer: Option<u32>, victim: u32, weapon: Option<String>) -> Option<KillInfo> {
+        let victim_name = self.name_of(victim)?;
+        let killer_name = killer.and_then(|killer| self.name_of(killer));
+        if let Some(tally) = self.tally_mut(victim) {
+            tally.deaths += 1;
+        }
+        // 自杀不算击杀
+        if let Some(tally) = killer.filter(|killer| *killer != victim).and_then(|killer| self.tally_mut(killer)) {
+            tally.kills += 1;
+        }
+        let kill = KillInfo { killer, killer_name, victim, victim_name, weapon };
+        self.broadcast(&Packet::Kill(kill.clone()));
+        Some(kill)
+    }
+
+    // 主机玩家自己死了，下次 poll 时自己也收到击杀事件
+    pub fn report_death(&mut self, killer: Option<u32>, weapon: Option<String>) {
+        if let Some(kill) = self.broadcast_kill(killer, self.host_id, weapon) {
+            self.pending_events.push(NetEvent::Kill(kill));
+        }
+    }
+
+    fn broadcast(&mut self, packet: &Packet) {
+        let addrs: Vec<SocketAddr> = self.clients.keys().copied().collect();
+        for addr in addrs {
+            self.link.send(addr, packet);
+        }
+    }
+
+    // 每个客户端的名字和连接质量
+    pub fn stats(&self) -> Vec<(String, PeerStats)> {
+        let now = Instant::now();
+        let mut stats: Vec<(u32, String, PeerStats)> = self
+            .clients
+            .values()
+            .map(|client| {
+                let stats = PeerStats { snapshot_bytes: client.snapshot_bytes, ..client.ping.stats(client.last_seen, now) };
+                (client.player_id, client.name.clone(), stats)
+            })
+            .collect();
+        stats.sort_by_key(|(player_id, _, _)| *player_id);
+        stats.into_iter().map(|(_, name, stats)| (name, stats)).collect()
+    }
+
+    // 计分板：主机自己和所有客户端
+    pub fn scores(&self) -> Vec<Score> {
+        let host = Score {
+            player_id: self.host_id,
+            name: self.host_name.clone(),
+            kills: self.host_tally.kills,
+            deaths: self.host_tally.deaths,
+            ping_ms: None,
+            host: true,
+            bot: false,
+            shots: self.host_tally.shots,
+            hits: self.host_tally.hits,
+        };
+        let clients = self.clients.values().map(|client| Score {
+            player_id: client.player_id,
+            name: client.name.chars().take(PEER_NAME_LEN).collect(),
+            kills: client.tally.kills,
+            deaths: client.tally.deaths,
+            ping_ms: client.ping.rtt_ms.map(|ping| ping.round() as u32),
+            host: false,
+            bot: false,
+            shots: client.tally.shots,
+            hits: client.tally.hits,
+        });
+        let bots = self.bots.states().into_iter().filter_map(|(player_id, _)| {
+            let tally = self.bot_tallies.get(&player_id).copied().unwrap_or_default();
+            Some(Score {
+                player_id,
+                name: self.bots.name(player_id)?.to_string(),
+                kills: tally.kills,
+                deaths: tally.deaths,
+                ping_ms: None,
+                host: false,
+                bot: true,
+                shots: tally.shots,
+                hits: tally.hits,
+            })
+        });
+        std::iter::once(host).chain(clients).chain(bots).collect()
+    }
+
+    // 主机判定的一次对玩家的射击
+    pub fn record_shot(&mut self, shooter: u32, hit: bool) {
+        if let Some(tally) = self.tally_mut(shooter) {
+            tally.shots += 1;
+            tally.hits += hit as u32;
+        }
+    }
+
+    // 到了时间或者有人击杀数到了上限时结束比赛开始投票，投票时间到了或者所有人都投了就换图
+    fn update_match(&mut self, now: Instant, events: &mut Vec<NetEvent>) {
+        match &self.vote {
+            None => {
+                let config = server_config::current();
+                let scores = self.scores();
+                let leader = scores.iter().map(|score| score.kills).max().unwrap_or(0);
+                let out_of_time = config.time_limit().is_some_and(|limit| now.duration_since(self.match_started) >= limit);
+                let score_reached = config.score_limit().is_some_and(|limit| leader >= limit);
+                if !out_of_time && !score_reached {
+                    return;
+                }
+                println!("比赛结束，开始投票");
+                let candidates = match_end::candidates(&self.info.map);
+                let mut ranked = scores.clone();
+                ranked.sort_by_key(|score| (std::cmp::Reverse(score.kills), score.deaths, score.player_id));
+                let summary = MatchSummary { map: self.info.map.clone(), awards: match_end::awards(&ranked), scores: ranked, candidates: candidates.clone() };
+                self.vote = Some(Vote { candidates, votes: HashMap::new(), ends: now + match_end::VOTE_DURATION });
+                self.broadcast(&Packet::MatchEnd(summary.clone()));
+                events.push(NetEvent::MatchEnded(summary));
+            }
+            Some(vote) => {
+                if now < vote.ends && vote.votes.len() <= self.clients.len() {
+                    return;
+                }
+                let votes: Vec<usize> = vote.votes.values().copied().collect();
+                let map = match_end::winner(&vote.candidates, &votes);
+                println!("投票结束，下一张地图 {}", map);
+                self.vote = None;
+                self.match_started = now;
+                self.host_tally = Tally::default();
+                for client in self.clients.values_mut() {
+                    client.tally = Tally::default();
+                }
+                self.bot_tallies.clear();
+                self.info.map = map.clone();
+                // 改过的模式从这一局开始
+                self.info.mode = server_config::current().mode.clone();
+                self.broadcast(&Packet::ChangeMap { map: map.clone() });
+                events.push(NetEvent::ChangeMap { map });
+            }
+        }
+    }
+
+    fn cast_vote(&mut self, player_id: u32, choice: usize, events: &mut Vec<NetEvent>) {
+        let Some(vote) = &mut self.vote else { return };
+        if choice >= vote.candidates.len() {
+            return;
+        }
+        vote.votes.insert(player_id, choice);
+        let votes: Vec<usize> = vote.votes.values().copied().collect();
+        let counts = match_end::count_votes(vote.candidates.len(), &votes);
+        self.broadcast(&Packet::Votes { counts: counts.clone() });
+        events.push(NetEvent::Votes(counts));
+    }
+
+    // 主机玩家自己投票，票数变化下次 poll 时交出去
+    pub fn vote(&mut self, choice: usize) {
+        let mut events = Vec::new();
+        self.cast_vote(self.host_id, choice, &mut events);
+        self.pending_events.extend(events);
+    }
+
+    // 主机玩家自己发的聊天
+    pub fn send_chat(&mut self, name: &str, text: &str) {
+        self.broadcast(&Packet::Chat { player_id: self.host_id, name: name.to_string(), text: text.to_string() });
+        if let Some(demo) = &mut self.demo {
+            demo.event(&DemoEvent::Chat { name: name.to_string(), text: text.to_string() });
+        }
+    }
+
+    // 退出时直接发出，不经过网络模拟的队列
+    pub fn shutdown(&mut self) {
+        for addr in self.clients.keys() {
+            send_packet(&self.link.socket, *addr, &Packet::Bye);
+        }
+        self.clients.clear();
+        // 关闭录像文件
+        self.demo = None;
+    }
+}
+
+enum ClientState {
+    Connecting { since: Instant, last_hello: Instant },
+    Connected { player_id: u32, info: ServerInfo },
+    // 主机离开了，正在加入选出来的新主机
+    Migrating { player_id: u32, info: ServerInfo, since: Instant, last_rejoin: Instant },
+    // 自己被选为新主机，等 Session 把它换成 NetServer
+    Promoted { player_id: u32, info: ServerInfo },
+}
+
+// 客户端：连接到一个主机
+pub struct NetClient {
+    link: Link,
+    server: SocketAddr,
+    name: String,
+    state: ClientState,
+    last_heartbeat: Instant,
+    last_seen: Instant,
+    ping: PingTracker,
+    local_state: Option<PlayerState>,
+    last_state_sent: Instant,
+    snapshots: SnapshotHistory,
+    snapshot_bytes: Option<usize>,
+    // 主机最近一次发来的玩家列表
+    peers: Vec<PeerInfo>,
+    // 主机最近一次发来的计分板
+    scores: Vec<Score>,
+    // 按主机发来的已进行时间推算的这局开始的时间，接手当主机时用
+    match_started: Option<Instant>,
+}
+
+impl NetClient {
+    pub fn connect(server: SocketAddr, name: &str, conditions: Conditions) -> io::Result<Self> {
+        let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_nonblocking(true)?;
+
+        let now = Instant::now();
+        let mut link = Link::new(socket, conditions);
+        link.send(server, &Packet::Hello { name: name.to_string() });
+        println!("正在连接 {}", server);
+
+        Ok(Self {
+            link,
+            server,
+            name: name.to_string(),
+            state: ClientState::Connecting { since: now, last_hello: now },
+            last_heartbeat: now,
+            last_seen: now,
+            ping: PingTracker::new(),
+            local_state: None,
+            last_state_sent: now,
+            snapshots: SnapshotHistory::new(),
+            snapshot_bytes: None,
+            peers: Vec::new(),
+            scores: Vec::new(),
+            match_started: None,
+        })
+    }
+
+    pub fn server_addr(&self) -> SocketAddr {
+        self.server
+    }
+
+    // 已连接时返回 (玩家编号, 服务器信息)，迁移主机的过程中也算连着
+    pub fn connection(&self) -> Option<(u32, &ServerInfo)> {
+        match &self.state {
+            ClientState::Connected { player_id, info }
+            | ClientState::Migrating { player_id, info, .. }
+            | ClientState::Promoted { player_id, info } => Some((*player_id, info)),
+            ClientState::Connecting { .. } => None,
+        }
+    }
+
+    pub fn is_migrating(&self) -> bool {
+        matches!(self.state, ClientState::Migrating { .. })
+    }
+
+    // 主机没了：选新主机，选中自己时等着被换成 NetServer，否则去加入新主机。
+    // 迁移中再调用是选出来的新主机也连不上，调用前已经把它从玩家列表里去掉了
+    fn migrate(&mut self, now: Instant, events: &mut Vec<NetEvent>) {
+        let (ClientState::Connected { player_id, info } | ClientState::Migrating { player_id, info, .. }) = &self.state else { return };
+        let (player_id, info) = (*player_id, info.clone());
+        let Some(host) = elect(&self.peers).cloned() else {
+            let reason = if self.is_migrating() { "host migration failed" } else { "server closed" };
+            events.push(NetEvent::Disconnected { reason: reason.to_string() });
+            return;
+        };
+        if host.player_id == player_id {
+            self.state = ClientState::Promoted { player_id, info };
+            events.push(NetEvent::BecameHost);
+            return;
+        }
+        println!("主机已离开，改为连接 {} ({})", host.name, host.addr);
+        self.server = host.addr;
+        self.last_seen = now;
+        self.ping = PingTracker::new();
+        self.link.send(self.server, &Packet::Rejoin { player_id, name: self.name.clone() });
+        self.state = ClientState::Migrating { player_id, info, since: now, last_rejoin: now };
+        events.push(NetEvent::HostMigrated { player_id: host.player_id, name: host.name });
+    }
+
+    pub fn poll(&mut self) -> Vec<NetEvent> {
+        let mut events = Vec::new();
+        let now = Instant::now();
+
+        for (addr, packet) in self.link.recv() {
+            if addr != self.server {
+                continue;
+            }
+            self.last_seen = now;
+            match packet {
+                Packet::Welcome { player_id, info } => match self.state {
+                    ClientState::Connecting { .. } => {
+                        events.push(NetEvent::Connected { player_id, info: info.clone() });
+                        self.state = ClientState::Connected { player_id, info };
+                    }
+                    // 新主机的快照编号从头算，旧的快照不能当基准
+                    ClientState::Migrating { .. } => {
+                        println!("已加入新主机，玩家编号 {}", player_id);
+                        self.snapshots = SnapshotHistory::new();
+                        self.state = ClientState::Connected { player_id, info };
+                    }
+                    _ => {}
+                },
+                Packet::Reject { reason } => {
+                    events.push(NetEvent::Disconnected { reason });
+                }
+                Packet::Bye => match self.state {
+                    ClientState::Connected { .. } => self.migrate(now, &mut events),
+                    _ => events.push(NetEvent::Disconnected { reason: "server closed".to_string() }),
+                },
+                Packet::Peers { peers, match_elapsed } => {
+                    self.peers = peers;
+                    let elapsed = match_elapsed.and_then(|elapsed| Duration::try_from_secs_f32(elapsed).ok());
+                    self.match_started = elapsed.and_then(|elapsed| now.checked_sub(elapsed));
+                }
+                Packet::Scores { scores } => self.scores = scores,
+                Packet::MatchEnd(summary) => events.push(NetEvent::MatchEnded(summary)),
+                Packet::Votes { counts } => events.push(NetEvent::Votes(counts)),
+                Packet::ChangeMap { map } => {
+                    if let ClientState::Connected { info, .. } = &mut self.state {
+                        info.map = map.clone();
+                    }
+                    events.push(NetEvent::ChangeMap { map });
+                }
+                Packet::Chat { player_id, name, text } => {
+                    events.push(NetEvent::Chat { player_id, name, text });
+                }
+                Packet::Ping { seq } => self.link.send(self.server, &Packet::Pong { seq }),
+                Packet::Pong { seq } => self.ping.pong(seq, now),
+                Packet::Snapshot(bytes) => self.receive_snapshot(&bytes),
+                Packet::Damage { amount, shooter, weapon } => events.push(NetEvent::Damaged { amount, shooter, weapon }),
+                Packet::Kill(kill) => events.push(NetEvent::Kill(kill)),
+                Packet::Loadout(loadout) => events.push(NetEvent::LoadoutApproved(loadout)),
+                _ => {}
+            }
+        }
+
+        match &mut self.state {
+            ClientState::Connecting { since, last_hello } => {
+                if now.duration_since(*since) > CONNECT_TIMEOUT {
+                    events.push(NetEvent::Disconnected { reason: "connection timed out".to_string() });
+                } else if now.duration_since(*last_hello) >= HELLO_RETRY_INTERVAL {
+                    *last_hello = now;
+                    self.link.send(self.server, &Packet::Hello { name: self.name.clone() });
+                }
+            }
+            ClientState::Migrating { since, last_rejoin, player_id, .. } => {
+                if now.duration_since(*since) > CONNECT_TIMEOUT {
+                    // 玩家列表可能是旧的，选出来的新主机也已经走了：去掉它换下一个。
+                    // 其他客户端去掉的是同一个，选出来的还是同一个
+                    let failed = self.server;
+                    println!("新主机 {} 没有回应，换下一个", failed);
+                    self.peers.retain(|peer| peer.addr != failed);
+                    self.migrate(now, &mut events);
+                } else if now.duration_since(*last_rejoin) >= HELLO_RETRY_INTERVAL {
+                    *last_rejoin = now;
+                    let rejoin = Packet::Rejoin { player_id: *player_id, name: self.name.clone() };
+                    self.link.send(self.server, &rejoin);
+                }
+            }
+            ClientState::Promoted { .. } => {}
+            ClientState::Connected { .. } => {
+                if now.duration_since(self.last_seen) > PEER_TIMEOUT {
+                    self.migrate(now, &mut events);
+                } else if now.duration_since(self.last_heartbeat) >= HEARTBEAT_INTERVAL {
+                    self.last_heartbeat = now;
+                    self.link.send(self.server, &Packet::Heartbeat);
+                }
+                if let Some(seq) = self.ping.tick(now) {
+                    self.link.send(self.server, &Packet::Ping { seq });
+                }
+                if let Some(state) = self.local_state {
+                    if now.duration_since(self.last_state_sent) >= SNAPSHOT_INTERVAL {
+                        self.last_state_sent = now;
+                        let ack = self.snapshots.latest().map(|snapshot| snapshot.tick);
+                        self.link.send(self.server, &Packet::PlayerState { ack, state });
+                    }
+                }
+            }
+        }
+
+        events
+    }
+
+    // 比已有的旧的快照（乱序到达）直接丢掉；基准已经不在了的增量也只能丢掉，等主机按新的确认重发
+    fn receive_snapshot(&mut self, bytes: &[u8]) {
+        match snapshot::decode(bytes, &self.snapshots) {
+            Ok(snapshot) => {
+                if self.snapshots.latest().is_none_or(|latest| snapshot.tick > latest.tick) {
+                    self.snapshot_bytes = Some(bytes.len());
+                    self.snapshots.push(snapshot);
+                }
+            }
+            Err(e) => eprintln!("无法解码快照: {:?}", e),
+        }
+    }
+
+    pub fn set_local_player(&mut self, state: PlayerState) {
+        self.local_state = Some(state);
+    }
+
+    // 最新快照里除了自己以外的玩家
+    pub fn remote_players(&self) -> Vec<(u32, PlayerState)> {
+        let Some((own_id, _)) = self.connection() else { return Vec::new() };
+        let Some(snapshot) = self.snapshots.latest() else { return Vec::new() };
+        snapshot.players().into_iter().filter(|(player_id, _)| *player_id != own_id).collect()
+    }
+
+    // 命中由主机判定，这里只报告开枪时看到的是哪个快照
+    pub fn send_fire(&mut self, weapon: &str, origin: Vec3, dir: Vec3) {
+        let tick = self.snapshots.latest().map(|snapshot| snapshot.tick);
+        let fire = Packet::Fire { tick, weapon: weapon.to_string(), origin: origin.to_array(), dir: dir.to_array() };
+        self.link.send(self.server, &fire);
+    }
+
+    pub fn send_chat(&mut self, text: &str) {
+        self.link.send(self.server, &Packet::ChatSend { text: text.to_string() });
+    }
+
+    pub fn send_loadout(&mut self, loadout: &Loadout) {
+        self.link.send(self.server, &Packet::Loadout(loadout.clone()));
+    }
+
+    pub fn send_vote(&mut self, choice: usize) {
+        self.link.send(self.server, &Packet::Vote { choice });
+    }
+
+    pub fn send_died(&mut self, killer: Option<u32>, weapon: Option<String>) {
+        self.link.send(self.server, &Packet::Died { killer, weapon });
+    }
+
+    // 连上以后和主机之间的连接质量
+    pub fn stats(&self) -> Option<PeerStats> {
+        self.connection()?;
+        Some(PeerStats { snapshot_bytes: self.snapshot_bytes, ..self.ping.stats(self.last_seen, Instant::now()) })
+    }
+
+    pub fn disconnect(&mut self) {
+        send_packet(&self.link.socket, self.server, &Packet::Bye);
+    }
+}
+
+pub struct DiscoveredServer {
+    pub addr: SocketAddr,
+    pub info: ServerInfo,
+    last_seen: Instant,
+}
+
+// 局域网服务器浏览器：监听发现端口上的广播
+pub struct LanBrowser {
+    socket: Option<UdpSocket>,
+    error: Option<String>,
+    servers: Vec<DiscoveredServer>,
+}
+
+impl LanBrowser {
+    pub fn new() -> Self {
+        let socket = UdpSocket::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT))
+            .and_then(|socket| socket.set_nonblocking(true).map(|_| socket));
+        match socket {
+            Ok(socket) => Self { socket: Some(socket), error: None, servers: Vec::new() },
+            Err(e) => Self {
+                socket: None,
+                error: Some(format!("cannot listen on port {}: {}", DISCOVERY_PORT, e)),
+                servers: Vec::new(),
+            },
+        }
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn servers(&self) -> &[DiscoveredServer] {
+        &self.servers
+    }
+
+    pub fn poll(&mut self) {
+        let now = Instant::now();
+        if let Some(socket) = &self.socket {
+            for (addr, packet) in recv_packets(socket) {
+                if let Packet::Beacon(info) = packet {
+                    // 广播的源端口就是游戏端口，但以公布的端口为准
+                    let addr = SocketAddr::new(addr.ip(), info.port);
+                    match self.servers.iter_mut().find(|s| s.addr == addr) {
+                        Some(server) => {
+                            server.info = info;
+                            server.last_seen = now;
+                        }
+                        None => self.servers.push(DiscoveredServer { addr, info, last_seen: now }),
+                    }
+                }
+            }
+        }
+        self.servers.retain(|s| now.duration_since(s.last_seen) <= SERVER_EXPIRY);
+    }
+}
+
+// 当前的联机会话，只有一个，不在乎几个变体大小不一样
+#[allow(clippy::large_enum_variant)]
+pub enum Session {
+    Offline,
+    Host(NetServer),
+    Client(NetClient),
+}
+
+impl Session {
+    pub fn poll(&mut self) -> Vec<NetEvent> {
+        match self {
+            Session::Offline => Vec::new(),
+            Session::Host(server) => server.poll(),
+            Session::Client(client) => {
+                let events = client.poll();
+                if let Session::Client(NetClient { state: ClientState::Promoted { .. }, .. }) = self {
+                    let Session::Client(client) = std::mem::replace(self, Session::Offline) else { unreachable!() };
+                    let ClientState::Promoted { player_id, ref info } = client.state else { unreachable!() };
+                    let info = info.clone();
+                    *self = Session::Host(NetServer::promote(client, player_id, info));
+                }
+                events
+            }
+        }
+    }
+
+    pub fn close(&mut self) {
+        match self {
+            Session::Offline => {}
+            Session::Host(server) => server.shutdown(),
+            Session::Client(client) => client.disconnect(),
+        }
+        *self = Session::Offline;
+    }
+
+    // 发送聊天消息，返回 true 表示需要本地直接显示（客户端等待主机回显）
+    pub fn send_chat(&mut self, name: &str, text: &str) -> bool {
+        match self {
+            Session::Offline => true,
+            Session::Host(server) => {
+                server.send_chat(name, text);
+                true
+            }
+            Session::Client(client) => {
+                client.send_chat(text);
+                false
+            }
+        }
+    }
+
+    // 网络诊断面板显示的每个对端：主机显示所有客户端，客户端显示主机
+    pub fn stats(&self) -> Vec<(String, PeerStats)> {
+        match self {
+            Session::Offline => Vec::new(),
+            Session::Host(server) => server.stats(),
+            Session::Client(client) => client.stats().map(|stats| vec![("HOST".to_string(), stats)]).unwrap_or_default(),
+        }
+    }
+
+    pub fn set_local_player(&mut self, state: PlayerState) {
+        match self {
+            Session::Offline => {}
+            Session::Host(server) => server.set_local_player(state),
+            Session::Client(client) => client.set_local_player(state),
+        }
+    }
+
+    pub fn remote_players(&self) -> Vec<(u32, PlayerState)> {
+        match self {
+            Session::Offline => Vec::new(),
+            Session::Host(server) => server.remote_players(),
+            Session::Client(client) => client.remote_players(),
+        }
+    }
+
+    pub fn is_online(&self) -> bool {
+        !matches!(self, Session::Offline)
+    }
+
+    // 计分板，击杀多的在前，一样时死亡少的在前
+    pub fn scores(&self) -> Vec<Score> {
+        let mut scores = match self {
+            Session::Offline => Vec::new(),
+            Session::Host(server) => server.scores(),
+            Session::Client(client) => client.scores.clone(),
+        };
+        scores.sort_by_key(|score| (std::cmp::Reverse(score.kills), score.deaths, score.player_id));
+        scores
+    }
+
+    // 比赛结束后给下一张地图投票
+    pub fn vote(&mut self, choice: usize) {
+        match self {
+            Session::Offline => {}
+            Session::Host(server) => server.vote(choice),
+            Session::Client(client) => client.send_vote(choice),
+        }
+    }
+
+    // 自己的玩家编号，离线和还没连上时为 None
+    pub fn local_player_id(&self) -> Option<u32> {
+        match self {
+            Session::Offline => None,
+            Session::Host(server) => Some(server.host_id()),
+            Session::Client(client) => client.connection().map(|(player_id, _)| player_id),
+        }
+    }
+
+    // 自己死了：主机直接广播击杀，客户端报告给主机
+    pub fn report_death(&mut self, killer: Option<u32>, weapon: Option<String>) {
+        match self {
+            Session::Offline => {}
+            Session::Host(server) => server.report_death(killer, weapon),
+            Session::Client(client) => client.send_died(killer, weapon),
+        }
+    }
+
+    // 其他玩家的名字：主机从连接的客户端和机器人里找，客户端从主机发来的玩家列表和计分板里找
+    pub fn player_name(&self, player_id: u32) -> String {
+        let name = match self {
+            Session::Offline => None,
+            Session::Host(server) => server.name_of(player_id),
+            Session::Client(client) => client
+                .peers
+                .iter()
+                .find(|peer| peer.player_id == player_id)
+                .map(|peer| peer.name.clone())
+                // 机器人不在玩家列表里
+                .or_else(|| client.scores.iter().find(|score| score.player_id == player_id).map(|score| score.name.clone())),
+        };
+        name.unwrap_or_else(|| format!("player #{}", player_id))
+    }
+
+    // 显示在屏幕左上角的状态文字
+    pub fn status_text(&self) -> Option<String> {
+        match self {
+            Session::Offline => None,
+            Session::Host(server) => {
+                let info = server.info();
+                Some(format!("HOSTING {} ({}) - {}/{} players", info.map, info.mode, info.players, info.max_players))
+            }
+            Session::Client(client) => Some(match client.connection() {
+                Some(_) if client.is_migrating() => format!("HOST LEFT - MIGRATING to {}...", client.server_addr()),
+                Some((player_id, info)) => format!(
+                    "CONNECTED to {} - {} ({}) - player #{}",
+                    client.server_addr(), info.map, info.mode, player_id
+                ),
+                None => format!("CONNECTING to {}...", client.server_addr()),
+            }),
+        }
+    }
+}
+
+// 解析用户输入的地址，没有端口时使用默认游戏端口
+pub fn parse_address(input: &str) -> Option<SocketAddr> {
+    let input = input.trim();
+    if let Ok(addr) = input.parse::<SocketAddr>() {
+        return Some(addr);
+    }
+    input.parse::<Ipv4Addr>().ok().map(|ip| SocketAddr::V4(SocketAddrV4::new(ip, GAME_PORT)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(player_id: u32, ping_ms: Option<u32>, addr: SocketAddr) -> PeerInfo {
+        PeerInfo { player_id, name: format!("player{}", player_id), addr, ping_ms }
+    }
+
+    // 本机回环上的空套接字，当其他玩家的地址用
+    fn endpoint() -> (UdpSocket, SocketAddr) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        (socket, addr)
+    }
+
+    fn info() -> ServerInfo {
+        ServerInfo { name: "host".to_string(), map: "parking_garage".to_string(), mode: "deathmatch".to_string(), players: 4, max_players: MAX_PLAYERS, port: GAME_PORT }
+    }
+
+    // 已经连上 host 的客户端，主机最后发来的玩家列表是 peers
+    fn client(host: SocketAddr, player_id: u32, peers: Vec<PeerInfo>) -> NetClient {
+        let mut client = NetClient::connect(host, "me", Conditions::default()).unwrap();
+        client.state = ClientState::Connected { player_id, info: info() };
+        client.peers = peers;
+        client
+    }
+
+    // 让正在进行的迁移超时
+    fn expire(client: &mut NetClient) {
+        if let ClientState::Migrating { since, .. } = &mut client.state {
+            *since = Instant::now().checked_sub(CONNECT_TIMEOUT * 2).unwrap();
+        }
+    }
+
+    #[test]
+    fn election_prefers_low_ping_then_low_id_then_unmeasured() {
+        let addr = "127.0.0.1:1".parse().unwrap();
+        let mut peers = vec![peer(1, None, addr), peer(5, Some(40), addr), peer(3, Some(90), addr), peer(2, Some(40), addr), peer(0, None, addr)];
+        let mut order = Vec::new();
+        while let Some(host) = elect(&peers).map(|host| host.player_id) {
+            order.push(host);
+            peers.retain(|peer| peer.player_id != host);
+        }
+        assert_eq!(order, [2, 5, 3, 0, 1]);
+    }
+
+    #[test]
+    fn take_over_seeds_players_from_the_last_snapshot() {
+        let ((_old, old), (_a, a), (_b, b)) = (endpoint(), endpoint(), endpoint());
+        let mut me = client(old, 2, vec![peer(2, Some(10), a), peer(4, Some(30), b)]);
+        let state = PlayerState::new(Vec3::new(3.0, 1.8, -2.0), 0.5, 0.0, 80.0, 10.0, true);
+        me.snapshots.push(Snapshot::new(30, &[(4, state)]));
+        me.scores = vec![Score { player_id: 4, name: "player4".to_string(), kills: 3, deaths: 1, ping_ms: Some(30), host: false, bot: false, shots: 9, hits: 4 }];
+        let expected = me.snapshots.latest().unwrap().players()[0].1;
+
+        let server = NetServer::take_over(me, 2, info(), Instant::now());
+        assert_eq!(server.host_id(), 2);
+        assert_eq!(server.clients.len(), 1);
+        let remote = &server.clients[&b];
+        assert_eq!(remote.player_id, 4);
+        assert_eq!(remote.guard.state(), Some(expected));
+        assert_eq!((remote.tally.kills, remote.tally.deaths, remote.tally.shots, remote.tally.hits), (3, 1, 9, 4));
+        assert_eq!(server.next_tick, 30 + MIGRATION_TICK_GAP);
+        assert_eq!(server.next_player_id, 5);
+        assert_eq!(server.info.players, 2);
+    }
+
+    #[test]
+    fn match_clock_keeps_running_after_migration() {
+        let elapsed_after_take_over = |match_elapsed: Option<f32>| {
+            let (old_socket, old) = endpoint();
+            let mut me = client(old, 2, Vec::new());
+            let own = SocketAddr::from((Ipv4Addr::LOCALHOST, me.link.socket.local_addr().unwrap().port()));
+            send_packet(&old_socket, own, &Packet::Peers { peers: Vec::new(), match_elapsed });
+            me.poll();
+            let server = NetServer::take_over(me, 2, info(), Instant::now());
+            Instant::now().duration_since(server.match_started).as_secs_f32()
+        };
+        assert!((elapsed_after_take_over(Some(90.0)) - 90.0).abs() < 1.0);
+        // 投票中主机离开：新的一局从头开始
+        assert!(elapsed_after_take_over(None) < 1.0);
+    }
+
+    #[test]
+    fn rejoin_with_a_taken_id_gets_a_new_one() {
+        let ((_old, old), (_a, a), (_b, b), (_c, c), (_d, d)) = (endpoint(), endpoint(), endpoint(), endpoint(), endpoint());
+        let me = client(old, 1, vec![peer(1, Some(10), c), peer(4, Some(30), a)]);
+        let mut server = NetServer::take_over(me, 1, info(), Instant::now());
+        let now = Instant::now();
+        let mut events = Vec::new();
+        let joined = |events: &mut Vec<NetEvent>| match events.pop() {
+            Some(NetEvent::PlayerJoined { player_id, .. }) => Some(player_id),
+            _ => None,
+        };
+
+        // 预留了位置的老玩家拿回原来的编号，不算新加入
+        server.accept(a, "player4".to_string(), Some(4), now, &mut events);
+        assert!(events.is_empty());
+        assert_eq!(server.clients[&a].player_id, 4);
+        // 别的地址要已经被占用的编号（预留的、新主机自己的）时当新玩家
+        server.accept(b, "impostor".to_string(), Some(4), now, &mut events);
+        assert_eq!(joined(&mut events), Some(5));
+        server.accept(d, "impostor".to_string(), Some(1), now, &mut events);
+        assert_eq!(joined(&mut events), Some(6));
+        // 没被占用的编号照给，之后的新玩家排在它后面
+        let ((_e, e), (_f, f)) = (endpoint(), endpoint());
+        server.accept(e, "player9".to_string(), Some(9), now, &mut events);
+        assert_eq!(joined(&mut events), Some(9));
+        server.accept(f, "newcomer".to_string(), None, now, &mut events);
+        assert_eq!(joined(&mut events), Some(10));
+    }
+
+    #[test]
+    fn migration_skips_an_elected_host_that_is_gone_too() {
+        let ((_old, old), (_gone, gone), (_next, next)) = (endpoint(), endpoint(), endpoint());
+        let own = "127.0.0.1:2".parse().unwrap();
+        let mut me = client(old, 3, vec![peer(1, Some(10), gone), peer(2, Some(20), next), peer(3, Some(30), own)]);
+        let mut events = Vec::new();
+        me.migrate(Instant::now(), &mut events);
+        assert!(matches!(events[..], [NetEvent::HostMigrated { player_id: 1, .. }]));
+        assert_eq!(me.server_addr(), gone);
+
+        // 1 号在原来的主机之前就走了：换成 2 号
+        expire(&mut me);
+        let events = me.poll();
+        assert!(matches!(events[..], [NetEvent::HostMigrated { player_id: 2, .. }]), "{:?}", events);
+        assert_eq!(me.server_addr(), next);
+        assert!(me.is_migrating());
+
+        // 2 号也连不上：轮到自己当主机
+        expire(&mut me);
+        let events = me.poll();
+        assert!(matches!(events[..], [NetEvent::BecameHost]), "{:?}", events);
+        assert!(matches!(me.state, ClientState::Promoted { player_id: 3, .. }));
+
+        // 列表里的人都连不上时才放弃
+        let mut alone = client(old, 3, vec![peer(1, Some(10), gone)]);
+        alone.migrate(Instant::now(), &mut Vec::new());
+        expire(&mut alone);
+        let events = alone.poll();
+        assert!(matches!(&events[..], [NetEvent::Disconnected { reason }] if reason == "host migration failed"), "{:?}", events);
+    }
+}
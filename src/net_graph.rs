@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+
+use crate::net::PeerStats;
+use crate::net_sim::Conditions;
+use crate::ui::UiBatch;
+
+// F7 网络诊断面板：每个对端的延迟、丢包、距离上次收到数据包的时间和最近一个快照的大小，下面是最近一段时间的延迟曲线。
+// 有多个客户端时曲线画的是最差的那个
+
+// 每隔多久记一个点（秒）和记录的点数，一共 20 秒
+const SAMPLE_INTERVAL: f32 = 0.1;
+const HISTORY: usize = 200;
+const GRAPH_WIDTH: f32 = 780.0;
+const GRAPH_HEIGHT: f32 = 100.0;
+// 曲线顶端对应的延迟（毫秒）
+const GRAPH_RANGE_MS: f32 = 300.0;
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const TEXT_COLOR: [f32; 4] = [0.9, 1.0, 0.9, 0.95];
+const DIM_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 0.9];
+const GOOD_COLOR: [f32; 4] = [0.3, 1.0, 0.4, 0.9];
+const FAIR_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 0.9];
+const BAD_COLOR: [f32; 4] = [1.0, 0.3, 0.25, 0.9];
+
+fn ping_color(ping_ms: f32) -> [f32; 4] {
+    if ping_ms < 80.0 {
+        GOOD_COLOR
+    } else if ping_ms < 160.0 {
+        FAIR_COLOR
+    } else {
+        BAD_COLOR
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    ping_ms: Option<f32>,
+    loss: f32,
+}
+
+pub struct NetGraph {
+    pub open: bool,
+    peers: Vec<(String, PeerStats)>,
+    samples: VecDeque<Sample>,
+    since_sample: f32,
+}
+
+impl NetGraph {
+    pub fn new() -> Self {
+        Self { open: false, peers: Vec::new(), samples: VecDeque::with_capacity(HISTORY), since_sample: 0.0 }
+    }
+
+    // 每帧调用，peers 来自 Session::stats
+    pub fn update(&mut self, dt: f32, peers: Vec<(String, PeerStats)>) {
+        if !self.open {
+            return;
+        }
+        self.since_sample += dt;
+        if self.since_sample >= SAMPLE_INTERVAL {
+            self.since_sample = 0.0;
+            if self.samples.len() == HISTORY {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(Sample {
+                ping_ms: peers.iter().filter_map(|(_, stats)| stats.ping_ms).reduce(f32::max),
+                loss: peers.iter().map(|(_, stats)| stats.loss).fold(0.0, f32::max),
+            });
+        }
+        self.peers = peers;
+    }
+
+    pub fn draw(&self, ui: &mut UiBatch, conditions: &Conditions) {
+        if !self.open {
+            return;
+        }
+        let line = UiBatch::line_height(2.0);
+        let (x, y) = (10.0, ui.height() - GRAPH_HEIGHT - line * (self.peers.len().max(1) as f32 + 3.0) - 60.0);
+        let height = GRAPH_HEIGHT + line * (self.peers.len().max(1) as f32 + 2.0) + 16.0;
+        ui.rect(x - 6.0, y - 6.0, GRAPH_WIDTH + 12.0, height, PANEL_COLOR);
+
+        let title = if conditions.is_active() {
+            format!("NET  SIM {:.0} MS +-{:.0}  LOSS {:.0}%", conditions.latency_ms, conditions.jitter_ms, conditions.loss * 100.0)
+        } else {
+            "NET".to_string()
+        };
+        ui.text(x, y, 2.0, TEXT_COLOR, &title);
+        if self.peers.is_empty() {
+            ui.text(x, y + line, 2.0, DIM_COLOR, "NOT CONNECTED");
+        }
+        for (i, (name, stats)) in self.peers.iter().enumerate() {
+            let ping = stats.ping_ms.map_or("--".to_string(), |ping| format!("{:.0}", ping));
+            let snapshot = stats.snapshot_bytes.map_or("--".to_string(), |bytes| bytes.to_string());
+            let text = format!(
+                "{:<12} PING {:>4} MS  LOSS {:>3.0}%  LAST {:>4.0} MS  SNAP {:>4} B",
+                name.chars().take(12).collect::<String>(),
+                ping,
+                stats.loss * 100.0,
+                stats.last_packet_ms,
+                snapshot
+            );
+            let color = stats.ping_ms.map_or(DIM_COLOR, ping_color);
+            ui.text(x, y + line * (i + 1) as f32, 2.0, color, &text);
+        }
+
+        // 延迟的柱状图，有丢包的时间点在底部画红色
+        let bottom = y + height - 16.0;
+        let bar = GRAPH_WIDTH / HISTORY as f32;
+        for (i, sample) in self.samples.iter().enumerate() {
+            let bx = x + i as f32 * bar;
+            if let Some(ping) = sample.ping_ms {
+                let bar_height = (ping / GRAPH_RANGE_MS).min(1.0) * GRAPH_HEIGHT;
+                ui.rect(bx, bottom - bar_height, bar.max(1.0), bar_height, ping_color(ping));
+            }
+            if sample.loss > 0.0 {
+                ui.rect(bx, bottom, bar.max(1.0), 4.0, BAD_COLOR);
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use instant::Instant;
+
+// 测试用的网络条件模拟：在收发两个方向上给数据包加延迟、抖动和丢包，本机联机也能测出网络不好时的表现。
+// 用命令行参数打开：--net-latency 毫秒 --net-jitter 毫秒 --net-loss 百分比。
+// 延迟是往返的，收和发各加一半；丢包率在每个方向上分别计算
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Conditions {
+    pub latency_ms: f32,
+    pub jitter_ms: f32,
+    // 0 到 1
+    pub loss: f32,
+}
+
+impl Conditions {
+    pub fn from_args(args: &[String]) -> Self {
+        let value = |flag: &str| {
+            let index = args.iter().position(|arg| arg == flag)?;
+            match args.get(index + 1).map(|value| value.parse::<f32>()) {
+                Some(Ok(value)) if value >= 0.0 => Some(value),
+                _ => {
+                    eprintln!("{} 后面需要一个非负的数字", flag);
+                    None
+                }
+            }
+        };
+        let conditions = Self {
+            latency_ms: value("--net-latency").unwrap_or(0.0),
+            jitter_ms: value("--net-jitter").unwrap_or(0.0),
+            loss: (value("--net-loss").unwrap_or(0.0) / 100.0).min(1.0),
+        };
+        if conditions.is_active() {
+            println!("{}", conditions.describe());
+        }
+        conditions
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.latency_ms > 0.0 || self.jitter_ms > 0.0 || self.loss > 0.0
+    }
+
+    pub fn describe(&self) -> String {
+        format!("模拟网络: 延迟 {:.0} ms ±{:.0} ms，丢包 {:.0}%", self.latency_ms, self.jitter_ms, self.loss * 100.0)
+    }
+
+    // 一个数据包在一个方向上的延迟，None 表示丢掉
+    fn sample(&self) -> Option<Duration> {
+        if fastrand::f32() < self.loss {
+            return None;
+        }
+        let jitter = (fastrand::f32() * 2.0 - 1.0) * self.jitter_ms;
+        let ms = (self.latency_ms / 2.0 + jitter / 2.0).max(0.0);
+        Some(Duration::from_secs_f32(ms / 1000.0))
+    }
+}
+
+// 等待送出（或者等待交给游戏）的数据包。抖动大时后发的可能先到，和真实网络一样
+pub struct DelayQueue {
+    packets: Vec<(Instant, SocketAddr, Vec<u8>)>,
+}
+
+impl DelayQueue {
+    pub fn new() -> Self {
+        Self { packets: Vec::new() }
+    }
+
+    // 按模拟的条件排队，丢掉的包直接扔掉
+    pub fn push(&mut self, conditions: &Conditions, now: Instant, addr: SocketAddr, bytes: Vec<u8>) {
+        if let Some(delay) = conditions.sample() {
+            self.packets.push((now + delay, addr, bytes));
+        }
+    }
+
+    // 取出已经到时间的数据包，按到达时间排序
+    pub fn due(&mut self, now: Instant) -> Vec<(SocketAddr, Vec<u8>)> {
+        let (mut due, waiting): (Vec<_>, Vec<_>) = self.packets.drain(..).partition(|(at, _, _)| *at <= now);
+        self.packets = waiting;
+        due.sort_by_key(|(at, _, _)| *at);
+        due.into_iter().map(|(_, addr, bytes)| (addr, bytes)).collect()
+    }
+}
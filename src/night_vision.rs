@@ -0,0 +1,87 @@
+use crate::ui::UiBatch;
+
+// 夜视仪：按 N 开关。开着时后处理把画面提亮成绿色并加上颗粒噪点，敌人和其他玩家画成高亮的（见 outline.rs），
+// 还有持续的电流嗡嗡声。电池开着时耗电，关掉后慢慢充回来，用完自动关闭
+
+// 满电能开多久、从没电充满要多久（秒）
+const BATTERY_TIME: f32 = 60.0;
+const RECHARGE_TIME: f32 = 90.0;
+// 电量低于这个比例时打不开
+const MIN_BATTERY: f32 = 0.1;
+// 嗡嗡声一段的长度，和 audio.rs 里生成的一样长，放完一段接着放下一段
+const HUM_LENGTH: f32 = 1.0;
+
+const BAR_COLOR: [f32; 4] = [0.35, 1.0, 0.45, 0.9];
+const LOW_COLOR: [f32; 4] = [0.95, 0.2, 0.15, 0.9];
+
+// update 的结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NightVisionUpdate {
+    // 该放下一段嗡嗡声了
+    Hum,
+    // 电池用完，自动关闭了
+    Depleted,
+}
+
+pub struct NightVision {
+    on: bool,
+    // 0..1
+    battery: f32,
+    hum_timer: f32,
+}
+
+impl NightVision {
+    pub fn new() -> Self {
+        Self { on: false, battery: 1.0, hum_timer: 0.0 }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    // 电量不够时打不开，返回 false
+    pub fn toggle(&mut self) -> bool {
+        if !self.on && self.battery < MIN_BATTERY {
+            return false;
+        }
+        self.on = !self.on;
+        self.hum_timer = 0.0;
+        true
+    }
+
+    // 重生和换地图时关掉并充满电
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn update(&mut self, dt: f32) -> Option<NightVisionUpdate> {
+        if !self.on {
+            self.battery = (self.battery + dt / RECHARGE_TIME).min(1.0);
+            return None;
+        }
+        self.battery -= dt / BATTERY_TIME;
+        if self.battery <= 0.0 {
+            self.battery = 0.0;
+            self.on = false;
+            return Some(NightVisionUpdate::Depleted);
+        }
+        self.hum_timer -= dt;
+        if self.hum_timer > 0.0 {
+            return None;
+        }
+        self.hum_timer += HUM_LENGTH;
+        Some(NightVisionUpdate::Hum)
+    }
+
+    // 护甲条右边的电量条，满电且关着时不画
+    pub fn draw(&self, ui: &mut UiBatch) {
+        if !self.on && self.battery >= 1.0 {
+            return;
+        }
+        let (x, y, w, h) = (450.0, ui.height() - 40.0, 100.0, 20.0);
+        ui.rect(x, y, w, h, [0.0, 0.0, 0.0, 0.6]);
+        let color = if self.battery < MIN_BATTERY { LOW_COLOR } else { BAR_COLOR };
+        ui.rect(x, y, w * self.battery, h, [color[0], color[1], color[2], if self.on { color[3] } else { 0.45 }]);
+        ui.text(x + 6.0, y + 3.0, 2.0, [1.0, 1.0, 1.0, 1.0], "NV");
+    }
+}
@@ -0,0 +1,372 @@
+use glam::Mat4;
+
+use crate::gpu_memory;
+use crate::mesh::MeshBuilder;
+use crate::model::{self, BoxInstance, Model, ModelVertex};
+use crate::texture;
+use crate::upload::FrameUploader;
+
+// 描边：准星瞄着的可交互物体（拾取物、门）和被标记的敌人（波次模式剩下的最后几个）。
+// 要描边的方块和敌人的贴图面片按编号画进一张屏幕大小的 id 纹理（编号写在顶点颜色的红色通道里），
+// 面片按贴图的透明度裁剪，轮廓和画面上的敌人一样，
+// 再用全屏三角形在编号为 0、但线宽以内有编号的像素上画出这个编号的颜色，叠加在最终画面上。
+// 描边不做深度测试，被墙挡住的敌人也能看到轮廓。
+// 夜视仪开着时敌人和其他玩家也画进 id 纹理的绿色通道（做深度测试，只有看得见的部分），整片涂成高亮
+
+// 线宽（像素），按 720p 的画面算，高分辨率时等比加粗
+const WIDTH: f32 = 2.0;
+const REFERENCE_HEIGHT: f32 = 720.0;
+// 红色通道是描边的编号，绿色通道是高亮
+const ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg8Unorm;
+// 夜视仪里高亮的颜色
+const BRIGHT_COLOR: [f32; 4] = [0.85, 1.0, 0.85, 0.9];
+
+// 描边的种类，也是 id 纹理里的编号
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutlineKind {
+    Interactable = 1,
+    Marked = 2,
+}
+
+// 描边颜色，保存在设置文件里（RGBA）
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct OutlineColors {
+    pub enabled: bool,
+    pub interactable: [f32; 4],
+    pub marked: [f32; 4],
+}
+
+impl Default for OutlineColors {
+    fn default() -> Self {
+        Self { enabled: true, interactable: [1.0, 0.85, 0.2, 0.9], marked: [1.0, 0.25, 0.2, 0.9] }
+    }
+}
+
+// 一组要画进 id 纹理的形状：方块，和用敌人贴图（enemy_sprites.rs）的面片
+pub struct Shapes {
+    pub boxes: Vec<BoxInstance>,
+    pub sprites: MeshBuilder,
+}
+
+impl Shapes {
+    pub fn new() -> Self {
+        Self { boxes: Vec::new(), sprites: MeshBuilder::new() }
+    }
+}
+
+// 画进 id 纹理的模型，面片要绑定贴图裁剪，和方块分开画
+struct IdModels {
+    boxes: Option<Model>,
+    sprites: Option<Model>,
+}
+
+impl IdModels {
+    // 每组形状的顶点颜色换成它的编号
+    fn new(device: &wgpu::Device, shapes: &[(&Shapes, [f32; 3])]) -> Self {
+        let boxes: Vec<BoxInstance> = shapes
+            .iter()
+            .flat_map(|(shapes, color)| shapes.boxes.iter().map(|instance| BoxInstance { transform: instance.transform, color: *color }))
+            .collect();
+        let mut sprites = MeshBuilder::new();
+        for (shapes, color) in shapes {
+            let offset = sprites.vertices.len() as u16;
+            sprites.vertices.extend(shapes.sprites.vertices.iter().map(|vertex| ModelVertex { color: *color, ..*vertex }));
+            sprites.indices.extend(shapes.sprites.indices.iter().map(|index| index + offset));
+        }
+        Self {
+            boxes: (!boxes.is_empty()).then(|| model::create_boxes(device, &boxes)),
+            sprites: (!sprites.vertices.is_empty()).then(|| sprites.build(device, "outline_sprites", [0.0; 3], false, gpu_memory::Category::Dynamic)),
+        }
+    }
+
+    fn empty() -> Self {
+        Self { boxes: None, sprites: None }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.boxes.is_none() && self.sprites.is_none()
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct OutlineUniform {
+    view_proj: [[f32; 4]; 4],
+    colors: [[f32; 4]; 4],
+    // x 线宽（像素） yz 画面尺寸（像素）
+    params: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for OutlineUniform {}
+unsafe impl bytemuck::Zeroable for OutlineUniform {}
+
+// 屏幕大小的 id 纹理和读它的绑定组，窗口大小变化时重建
+struct Target {
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    size: (u32, u32),
+    _texture: gpu_memory::Tracked<wgpu::Texture>,
+}
+
+pub struct Outline {
+    id_pipeline: wgpu::RenderPipeline,
+    bright_pipeline: wgpu::RenderPipeline,
+    sprite_id_pipeline: wgpu::RenderPipeline,
+    sprite_bright_pipeline: wgpu::RenderPipeline,
+    outline_pipeline: wgpu::RenderPipeline,
+    // 画 id 时只用 uniform，描边时再加上 id 纹理
+    outline_layout: wgpu::BindGroupLayout,
+    id_bind_group: wgpu::BindGroup,
+    uniform_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    target: Option<Target>,
+    // 这一帧要描边和高亮的形状，都没有时什么都不做
+    ids: IdModels,
+    bright: IdModels,
+}
+
+impl Outline {
+    // material_layout 是场景的材质绑定组布局，画敌人面片时绑定敌人贴图的材质
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, material_layout: &wgpu::BindGroupLayout) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Outline Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("outline.wgsl").into()),
+        });
+        let uniform_entry = wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let id_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[uniform_entry],
+            label: Some("outline_id_bind_group_layout"),
+        });
+        let outline_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                uniform_entry,
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("outline_bind_group_layout"),
+        });
+        let uniform_buffer = gpu_memory::buffer_init(device, gpu_memory::Category::Frame, &wgpu::util::BufferInitDescriptor {
+            label: Some("outline_uniform"),
+            contents: bytemuck::cast_slice(&[<OutlineUniform as bytemuck::Zeroable>::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let id_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &id_layout,
+            entries: &[wgpu::BindGroupEntry { binding: 0, resource: uniform_buffer.as_entire_binding() }],
+            label: Some("outline_id_bind_group"),
+        });
+
+        let id_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Outline Id Pipeline Layout"),
+            bind_group_layouts: &[&id_layout],
+            push_constant_ranges: &[],
+        });
+        // 描边的物体不做深度测试，高亮的只画看得见的部分；两者的 id 在同一个通道里时留下大的
+        let id_pipeline = create_id_pipeline(device, &id_pipeline_layout, &shader, "fs_ids", wgpu::CompareFunction::Always);
+        let bright_pipeline = create_id_pipeline(device, &id_pipeline_layout, &shader, "fs_ids", wgpu::CompareFunction::GreaterEqual);
+        let sprite_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Outline Sprite Id Pipeline Layout"),
+            bind_group_layouts: &[&id_layout, material_layout],
+            push_constant_ranges: &[],
+        });
+        let sprite_id_pipeline = create_id_pipeline(device, &sprite_pipeline_layout, &shader, "fs_sprite_ids", wgpu::CompareFunction::Always);
+        let sprite_bright_pipeline = create_id_pipeline(device, &sprite_pipeline_layout, &shader, "fs_sprite_ids", wgpu::CompareFunction::GreaterEqual);
+
+        let outline_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Outline Pipeline Layout"),
+            bind_group_layouts: &[&outline_layout],
+            push_constant_ranges: &[],
+        });
+        let outline_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Outline Pipeline"),
+            layout: Some(&outline_pipeline_layout),
+            vertex: wgpu::VertexState { module: &shader, entry_point: "vs_fullscreen", buffers: &[] },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_outline",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            id_pipeline,
+            bright_pipeline,
+            sprite_id_pipeline,
+            sprite_bright_pipeline,
+            outline_pipeline,
+            outline_layout,
+            id_bind_group,
+            uniform_buffer,
+            target: None,
+            ids: IdModels::empty(),
+            bright: IdModels::empty(),
+        }
+    }
+
+    // 每帧调用：准星对着的可交互物体和被标记的敌人描边，bright 是夜视仪里高亮的敌人和玩家
+    pub fn set_shapes(&mut self, device: &wgpu::Device, interactable: &Shapes, marked: &Shapes, bright: &Shapes) {
+        let id = |kind: OutlineKind| [kind as u8 as f32 / 255.0, 0.0, 0.0];
+        self.ids = IdModels::new(device, &[(interactable, id(OutlineKind::Interactable)), (marked, id(OutlineKind::Marked))]);
+        self.bright = IdModels::new(device, &[(bright, [0.0, 1.0, 0.0])]);
+    }
+
+    // 卸载地图时调用，形状引用的实体已经不在了
+    pub fn clear(&mut self) {
+        self.ids = IdModels::empty();
+        self.bright = IdModels::empty();
+    }
+
+    fn ensure_target(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        if self.target.as_ref().is_none_or(|target| target.size != (width, height)) {
+            let texture = gpu_memory::texture(device, gpu_memory::Category::Textures, &wgpu::TextureDescriptor {
+                label: Some("outline_ids"),
+                size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: ID_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.outline_layout,
+                entries: &[
+                    wgpu::BindGroupEntry { binding: 0, resource: self.uniform_buffer.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&view) },
+                ],
+                label: Some("outline_bind_group"),
+            });
+            self.target = Some(Target { view, bind_group, size: (width, height), _texture: texture });
+        }
+    }
+
+    // 场景和后处理画完后调用，描边和高亮叠加到 output 上。depth_view 是场景的深度，sprite_material 是敌人贴图的材质
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        uploader: &mut FrameUploader,
+        view_proj: Mat4,
+        colors: &OutlineColors,
+        size: (u32, u32),
+        depth_view: &wgpu::TextureView,
+        output: &wgpu::TextureView,
+        sprite_material: &wgpu::BindGroup,
+    ) {
+        if self.ids.is_empty() && self.bright.is_empty() {
+            return;
+        }
+        let (width, height) = size;
+        let uniform = OutlineUniform {
+            view_proj: view_proj.to_cols_array_2d(),
+            colors: [[0.0; 4], colors.interactable, colors.marked, BRIGHT_COLOR],
+            params: [(WIDTH * height as f32 / REFERENCE_HEIGHT).round().max(1.0), width as f32, height as f32, 0.0],
+        };
+        uploader.write(queue, encoder, &self.uniform_buffer, 0, &[uniform]);
+        self.ensure_target(device, width, height);
+        let Some(target) = &self.target else { return };
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Outline Id Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT), store: true },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Load, store: true }),
+                    stencil_ops: None,
+                }),
+            });
+            render_pass.set_bind_group(0, &self.id_bind_group, &[]);
+            render_pass.set_bind_group(1, sprite_material, &[]);
+            let passes = [
+                (&self.ids.boxes, &self.id_pipeline),
+                (&self.ids.sprites, &self.sprite_id_pipeline),
+                (&self.bright.boxes, &self.bright_pipeline),
+                (&self.bright.sprites, &self.sprite_bright_pipeline),
+            ];
+            for (model, pipeline) in passes {
+                if let Some(model) = model {
+                    render_pass.set_pipeline(pipeline);
+                    model.draw(&mut render_pass);
+                }
+            }
+        }
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Outline Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: true },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.outline_pipeline);
+        render_pass.set_bind_group(0, &target.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_id_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    fragment_entry: &str,
+    depth_compare: wgpu::CompareFunction,
+) -> wgpu::RenderPipeline {
+    let max = wgpu::BlendComponent { src_factor: wgpu::BlendFactor::One, dst_factor: wgpu::BlendFactor::One, operation: wgpu::BlendOperation::Max };
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Outline Id Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState { module: shader, entry_point: "vs_ids", buffers: &[ModelVertex::desc()] },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: fragment_entry,
+            targets: &[Some(wgpu::ColorTargetState {
+                format: ID_FORMAT,
+                blend: Some(wgpu::BlendState { color: max, alpha: wgpu::BlendComponent::REPLACE }),
+                write_mask: wgpu::ColorWrites::RED | wgpu::ColorWrites::GREEN,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::Texture::DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
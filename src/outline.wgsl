@@ -0,0 +1,111 @@
+// 描边：先把要描边的物体（方块和敌人的贴图面片）的编号画进 id 纹理的红色通道，再在编号为 0 但附近有编号的像素上画这个编号的颜色。
+// 绿色通道是夜视仪里高亮的物体，整片涂上高亮的颜色
+
+struct OutlineUniform {
+    view_proj: mat4x4<f32>,
+    // 下标是编号，0 不用，3 是高亮的颜色
+    colors: array<vec4<f32>, 4>,
+    // x 线宽（像素） yz 画面尺寸（像素）
+    params: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> outline: OutlineUniform;
+@group(0) @binding(1)
+var t_ids: texture_2d<f32>;
+// 敌人贴图，场景的材质绑定组
+@group(1) @binding(0)
+var t_sprite: texture_2d<f32>;
+@group(1) @binding(1)
+var s_sprite: sampler;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    // 红色通道是编号 / 255，绿色通道是是否高亮
+    @location(1) color: vec3<f32>,
+    @location(2) tex_coords: vec2<f32>,
+};
+
+struct IdOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) id: vec2<f32>,
+    @location(1) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_ids(in: VertexInput) -> IdOutput {
+    var out: IdOutput;
+    out.clip_position = outline.view_proj * vec4<f32>(in.position, 1.0);
+    out.id = in.color.rg;
+    out.tex_coords = in.tex_coords;
+    return out;
+}
+
+@fragment
+fn fs_ids(in: IdOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(in.id, 0.0, 0.0);
+}
+
+// 敌人的面片：和场景里的镂空材质一样按贴图的透明度裁剪，只留下身体
+@fragment
+fn fs_sprite_ids(in: IdOutput) -> @location(0) vec4<f32> {
+    if textureSample(t_sprite, s_sprite, in.tex_coords).a < 0.5 {
+        discard;
+    }
+    return vec4<f32>(in.id, 0.0, 0.0);
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// 盖住整个屏幕的三角形
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn ids_at(pixel: vec2<i32>) -> vec2<f32> {
+    let size = vec2<i32>(outline.params.yz);
+    return textureLoad(t_ids, clamp(pixel, vec2<i32>(0), size - 1), 0).rg;
+}
+
+fn id_at(pixel: vec2<i32>) -> i32 {
+    return i32(round(ids_at(pixel).r * 255.0));
+}
+
+@fragment
+fn fs_outline(in: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(in.clip_position.xy);
+    let here = ids_at(pixel);
+    let bright = here.g > 0.5;
+    // 描边的物体内部不画描边，高亮的照样涂
+    if here.r > 0.0 {
+        if bright {
+            return outline.colors[3];
+        }
+        discard;
+    }
+    // 八个方向上线宽以内的像素，编号大的优先（敌人盖过拾取物）
+    let width = max(i32(outline.params.x), 1);
+    var id = 0;
+    for (var ring = 1; ring <= width; ring++) {
+        for (var dy = -1; dy <= 1; dy++) {
+            for (var dx = -1; dx <= 1; dx++) {
+                id = max(id, id_at(pixel + vec2<i32>(dx, dy) * ring));
+            }
+        }
+    }
+    if id != 0 {
+        return outline.colors[min(id, 2)];
+    }
+    if bright {
+        return outline.colors[3];
+    }
+    discard;
+}
@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use instant::Instant;
+
+use crate::settings::FrameLimit;
+
+// 帧率控制：游戏逻辑固定每秒 TICK_RATE 次，渲染按显示器刷新率（或设置里的上限）进行。
+// 每帧把经过的时间累加起来，够几次就模拟几次，剩下不足一次的部分作为插值比例，
+// 渲染时在上一次和这一次模拟的结果之间插值，144Hz 的显示器上画面流畅，游戏速度也不会变快。
+// 卡肉和慢动作通过全局时间缩放实现：累加的时间乘上缩放，模拟次数变少，插值照常进行
+
+pub const TICK_RATE: u32 = 60;
+pub const TICK: Duration = Duration::from_nanos(1_000_000_000 / TICK_RATE as u64);
+// 卡顿后最多追赶几次，再多的时间直接丢掉，避免越追越慢
+const MAX_TICKS_PER_FRAME: u32 = 5;
+// 离目标时间还剩这么多时不再 sleep，改为空转（sleep 的精度只有一两毫秒）
+const SPIN_MARGIN: Duration = Duration::from_micros(2000);
+
+pub struct Frame {
+    // 这一帧要执行的模拟次数，可能是 0
+    pub ticks: u32,
+    // 在上一次和最新一次模拟之间的位置，0 到 1
+    pub alpha: f32,
+    // 距上一帧的时间，给鼠标视角这类每帧都要更新的东西用
+    pub dt: Duration,
+}
+
+// 一次时间缩放效果：先保持最慢的速度，再平滑地恢复到正常速度，时间都是真实时间（秒）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeEffect {
+    pub scale: f32,
+    pub hold: f32,
+    pub ramp: f32,
+}
+
+// 近战命中时的卡肉：停住一瞬间
+pub const HIT_STOP: TimeEffect = TimeEffect { scale: 0.0, hold: 0.07, ramp: 0.0 };
+// 一波的最后一个敌人死掉时的慢动作
+pub const FINAL_KILL: TimeEffect = TimeEffect { scale: 0.25, hold: 0.5, ramp: 1.0 };
+
+impl TimeEffect {
+    // 开始后 elapsed 秒时的速度，结束后为 None
+    fn scale_at(&self, elapsed: f32) -> Option<f32> {
+        if elapsed < self.hold {
+            return Some(self.scale);
+        }
+        let t = (elapsed - self.hold) / self.ramp.max(1e-6);
+        (t < 1.0).then_some(self.scale + (1.0 - self.scale) * t * t * (3.0 - 2.0 * t))
+    }
+}
+
+// 正在进行的时间缩放效果，同时有几个时取最慢的，到时间自动恢复
+pub struct TimeScale {
+    effects: Vec<(TimeEffect, f32)>,
+}
+
+impl TimeScale {
+    pub fn new() -> Self {
+        Self { effects: Vec::new() }
+    }
+
+    pub fn push(&mut self, effect: TimeEffect) {
+        self.effects.push((effect, 0.0));
+    }
+
+    // 换地图等时立即恢复正常速度
+    pub fn reset(&mut self) {
+        self.effects.clear();
+    }
+
+    // 每帧用真实时间调用
+    pub fn update(&mut self, dt: f32) {
+        for (_, elapsed) in &mut self.effects {
+            *elapsed += dt;
+        }
+        self.effects.retain(|(effect, elapsed)| effect.scale_at(*elapsed).is_some());
+    }
+
+    pub fn current(&self) -> f32 {
+        self.effects.iter().filter_map(|(effect, elapsed)| effect.scale_at(*elapsed)).fold(1.0, f32::min)
+    }
+}
+
+pub struct FramePacer {
+    last: Instant,
+    accumulator: Duration,
+}
+
+impl FramePacer {
+    pub fn new() -> Self {
+        Self { last: Instant::now(), accumulator: Duration::ZERO }
+    }
+
+    // 一帧开始时调用，time_scale 是游戏时间相对真实时间的速度
+    pub fn advance(&mut self, time_scale: f32) -> Frame {
+        let now = Instant::now();
+        let dt = now - self.last;
+        self.last = now;
+        self.accumulator += dt.mul_f32(time_scale.max(0.0));
+        let mut ticks = 0;
+        while self.accumulator >= TICK {
+            self.accumulator -= TICK;
+            ticks += 1;
+        }
+        if ticks > MAX_TICKS_PER_FRAME {
+            ticks = MAX_TICKS_PER_FRAME;
+            self.accumulator = Duration::ZERO;
+        }
+        let alpha = self.accumulator.as_secs_f32() / TICK.as_secs_f32();
+        Frame { ticks, alpha, dt }
+    }
+
+    // 一帧结束时调用：没到下一帧的时间就等着。先 sleep，最后一小段空转，准确对上目标帧率
+    pub fn wait(&self, frame_time: Option<Duration>) {
+        // 浏览器里不能阻塞，由 requestAnimationFrame 控制帧率
+        if cfg!(target_arch = "wasm32") {
+            return;
+        }
+        let Some(frame_time) = frame_time else {
+            return;
+        };
+        let deadline = self.last + frame_time;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return;
+            }
+            let remaining = deadline - now;
+            if remaining > SPIN_MARGIN {
+                std::thread::sleep(remaining - SPIN_MARGIN);
+            } else {
+                std::hint::spin_loop();
+            }
+        }
+    }
+}
+
+// 每帧的时间。跟随显示器时用它的刷新率，拿不到刷新率时交给垂直同步；不限制时返回 None
+pub fn frame_time(limit: FrameLimit, refresh_millihertz: Option<u32>) -> Option<Duration> {
+    let fps = match limit {
+        FrameLimit::Display => refresh_millihertz.filter(|&mhz| mhz > 0)? as f64 / 1000.0,
+        FrameLimit::Unlimited => return None,
+        FrameLimit::Fps60 => 60.0,
+        FrameLimit::Fps120 => 120.0,
+        FrameLimit::Fps144 => 144.0,
+        FrameLimit::Fps240 => 240.0,
+    };
+    Some(Duration::from_secs_f64(1.0 / fps))
+}
+
+// 跟随显示器时用默认的呈现方式（通常是垂直同步）；指定帧率或不限制时尽量选不等垂直同步的方式，
+// 否则 60Hz 显示器上设成 144 也只有 60 帧
+pub fn present_mode(limit: FrameLimit, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+    if limit != FrameLimit::Display {
+        for mode in [wgpu::PresentMode::Mailbox, wgpu::PresentMode::Immediate] {
+            if supported.contains(&mode) {
+                return mode;
+            }
+        }
+    }
+    supported[0]
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+// 发布用的资源包：把地图、调色表、纹理、声音和数据文件打成一个 trae-shooting.pak，发布时只要程序和这一个文件。
+// --pack [输出路径] 在当前目录下打包。读取资源时先找散装的文件（方便改地图和做 mod），没有再从包里读（见 assets.rs）。
+// 格式（小端）：
+//   "TRAEPAK1", 文件数 u32,
+//   每个文件 [路径长度 u16, 路径 (UTF-8, / 分隔), 偏移 u64, 存储大小 u32, 原始大小 u32, 压缩方式 u8],
+//   之后是各个文件的数据，偏移从数据区开头算起
+
+pub const PAK_NAME: &str = "trae-shooting.pak";
+const MAGIC: &[u8; 8] = b"TRAEPAK1";
+// 打进包里的目录和文件
+const PACK_ROOTS: [&str; 5] = ["maps", "luts", "data", "sounds", "dog.png"];
+const METHOD_STORE: u8 = 0;
+const METHOD_DEFLATE: u8 = 1;
+const COMPRESSION_LEVEL: u8 = 9;
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    offset: u64,
+    stored_size: u32,
+    size: u32,
+    method: u8,
+}
+
+pub struct Pak {
+    path: PathBuf,
+    file: Mutex<File>,
+    entries: HashMap<String, Entry>,
+    data_start: u64,
+}
+
+// 包里的路径：相对路径，用 / 分隔，去掉 ./
+fn key(path: &Path) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(parts.join("/"))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl Pak {
+    pub fn open(path: &Path) -> io::Result<Pak> {
+        let mut file = File::open(path)?;
+        let mut magic = [0u8; 8];
+        file.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid(format!("{} 不是资源包", path.display())));
+        }
+        let mut read = |count: usize| -> io::Result<Vec<u8>> {
+            let mut bytes = vec![0u8; count];
+            file.read_exact(&mut bytes)?;
+            Ok(bytes)
+        };
+        let count = u32::from_le_bytes(read(4)?.try_into().unwrap());
+        let mut entries = HashMap::new();
+        for _ in 0..count {
+            let len = u16::from_le_bytes(read(2)?.try_into().unwrap()) as usize;
+            let name = String::from_utf8(read(len)?).map_err(|e| invalid(e.to_string()))?;
+            let fields = read(8 + 4 + 4 + 1)?;
+            let entry = Entry {
+                offset: u64::from_le_bytes(fields[0..8].try_into().unwrap()),
+                stored_size: u32::from_le_bytes(fields[8..12].try_into().unwrap()),
+                size: u32::from_le_bytes(fields[12..16].try_into().unwrap()),
+                method: fields[16],
+            };
+            entries.insert(name, entry);
+        }
+        let data_start = file.stream_position()?;
+        Ok(Pak { path: path.to_path_buf(), file: Mutex::new(file), entries, data_start })
+    }
+
+    // 包里没有这个文件时返回 None
+    pub fn read(&self, path: &Path) -> Option<io::Result<Vec<u8>>> {
+        let entry = *self.entries.get(&key(path)?)?;
+        Some(self.read_entry(entry).map_err(|e| io::Error::new(e.kind(), format!("{} 里的 {}: {}", self.path.display(), path.display(), e))))
+    }
+
+    fn read_entry(&self, entry: Entry) -> io::Result<Vec<u8>> {
+        let mut stored = vec![0u8; entry.stored_size as usize];
+        {
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(self.data_start + entry.offset))?;
+            file.read_exact(&mut stored)?;
+        }
+        match entry.method {
+            METHOD_STORE => Ok(stored),
+            METHOD_DEFLATE => miniz_oxide::inflate::decompress_to_vec_with_limit(&stored, entry.size as usize)
+                .map_err(|e| invalid(format!("解压失败: {:?}", e.status))),
+            method => Err(invalid(format!("未知的压缩方式 {}", method))),
+        }
+    }
+}
+
+// 程序旁边或者当前目录下的资源包，只打开一次
+pub fn installed() -> Option<&'static Pak> {
+    static PAK: OnceLock<Option<Pak>> = OnceLock::new();
+    PAK.get_or_init(|| {
+        let beside_exe = std::env::current_exe().ok().and_then(|exe| exe.parent().map(|dir| dir.join(PAK_NAME)));
+        let path = beside_exe.into_iter().chain([PathBuf::from(PAK_NAME)]).find(|path| path.is_file())?;
+        match Pak::open(&path) {
+            Ok(pak) => {
+                println!("使用资源包 {}（{} 个文件）", path.display(), pak.entries.len());
+                Some(pak)
+            }
+            Err(e) => {
+                eprintln!("无法打开资源包 {}: {}", path.display(), e);
+                None
+            }
+        }
+    })
+    .as_ref()
+}
+
+// 目录下所有的文件，按路径排序，打出来的包每次都一样
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut children: Vec<PathBuf> = std::fs::read_dir(path)?.map(|entry| entry.map(|entry| entry.path())).collect::<io::Result<_>>()?;
+    children.sort();
+    for child in children {
+        collect_files(&child, files)?;
+    }
+    Ok(())
+}
+
+// 把 files 打成资源包，返回 (文件数, 原始大小, 包的大小)
+fn write_pak(files: &[PathBuf], output: &Path) -> io::Result<(usize, u64, u64)> {
+    let mut index = Vec::new();
+    let mut data = Vec::new();
+    let mut total = 0u64;
+    for path in files {
+        let name = key(path).ok_or_else(|| invalid(format!("{} 不能放进资源包", path.display())))?;
+        let bytes = std::fs::read(path)?;
+        let compressed = miniz_oxide::deflate::compress_to_vec(&bytes, COMPRESSION_LEVEL);
+        // 已经压缩过的（png）再压也不会变小，原样存
+        let (method, stored) = if compressed.len() < bytes.len() { (METHOD_DEFLATE, compressed) } else { (METHOD_STORE, bytes.clone()) };
+        index.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        index.extend_from_slice(name.as_bytes());
+        index.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        index.extend_from_slice(&(stored.len() as u32).to_le_bytes());
+        index.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        index.push(method);
+        data.extend_from_slice(&stored);
+        total += bytes.len() as u64;
+    }
+    let mut file = File::create(output)?;
+    file.write_all(MAGIC)?;
+    file.write_all(&(files.len() as u32).to_le_bytes())?;
+    file.write_all(&index)?;
+    file.write_all(&data)?;
+    Ok((files.len(), total, file.stream_position()?))
+}
+
+// --pack [输出路径]
+pub fn pack_command(output: Option<&str>) {
+    let output = Path::new(output.unwrap_or(PAK_NAME));
+    let mut files = Vec::new();
+    for root in PACK_ROOTS.iter().map(Path::new).filter(|root| root.exists()) {
+        if let Err(e) = collect_files(root, &mut files) {
+            eprintln!("无法读取 {}: {}", root.display(), e);
+            return;
+        }
+    }
+    match write_pak(&files, output) {
+        Ok((count, total, size)) => println!("已打包 {} 个文件到 {}，{} KB 压缩到 {} KB", count, output.display(), total / 1024, size / 1024),
+        Err(e) => eprintln!("打包失败 {}: {}", output.display(), e),
+    }
+}
@@ -0,0 +1,112 @@
+use glam::{Mat4, Quat, Vec3};
+
+use crate::model::BoxInstance;
+
+// CPU 粒子：血雾、碎块等，受重力影响并在地面上弹跳
+
+const MAX_PARTICLES: usize = 1024;
+const GRAVITY: f32 = 9.8;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Particle {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub size: Vec3,
+    pub rotation: Quat,
+    // 旋转轴乘以角速度（弧度/秒）
+    pub spin: Vec3,
+    pub color: [f32; 3],
+    pub life: f32,
+    pub max_life: f32,
+}
+
+// 一次喷射的参数
+#[derive(Debug, Clone, Copy)]
+pub struct Burst {
+    pub count: usize,
+    pub direction: Vec3,
+    pub speed: f32,
+    // 方向的随机扩散（0 为完全沿 direction，1 为各个方向）
+    pub spread: f32,
+    pub size: f32,
+    pub life: f32,
+    pub color: [f32; 3],
+}
+
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+}
+
+impl ParticleSystem {
+    pub fn new() -> Self {
+        Self { particles: Vec::new() }
+    }
+
+    pub fn spawn(&mut self, particle: Particle) {
+        if self.particles.len() >= MAX_PARTICLES {
+            // 满了就替换最早的粒子
+            self.particles.remove(0);
+        }
+        self.particles.push(particle);
+    }
+
+    pub fn burst(&mut self, position: Vec3, burst: Burst) {
+        for _ in 0..burst.count {
+            let random = random_unit_vector();
+            let direction = (burst.direction * (1.0 - burst.spread) + random * burst.spread).normalize_or_zero();
+            let speed = burst.speed * (0.5 + fastrand::f32() * 0.5);
+            let size = burst.size * (0.6 + fastrand::f32() * 0.8);
+            self.spawn(Particle {
+                position,
+                velocity: direction * speed,
+                size: Vec3::splat(size),
+                rotation: Quat::IDENTITY,
+                spin: random_unit_vector() * 10.0,
+                color: burst.color,
+                life: burst.life * (0.7 + fastrand::f32() * 0.6),
+                max_life: burst.life,
+            });
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for particle in &mut self.particles {
+            particle.life -= dt;
+            particle.velocity.y -= GRAVITY * dt;
+            particle.position += particle.velocity * dt;
+            particle.rotation = Quat::from_scaled_axis(particle.spin * dt) * particle.rotation;
+
+            // 落地后弹一下并减速
+            let floor = particle.size.y * 0.5;
+            if particle.position.y < floor {
+                particle.position.y = floor;
+                particle.velocity.y = -particle.velocity.y * 0.3;
+                particle.velocity.x *= 0.6;
+                particle.velocity.z *= 0.6;
+                particle.spin *= 0.5;
+            }
+        }
+        self.particles.retain(|particle| particle.life > 0.0);
+    }
+
+    // 生命末尾缩小消失
+    pub fn boxes(&self, out: &mut Vec<BoxInstance>) {
+        for particle in &self.particles {
+            let shrink = (particle.life / (particle.max_life * 0.3)).min(1.0);
+            out.push(BoxInstance {
+                transform: Mat4::from_scale_rotation_translation(particle.size * shrink, particle.rotation, particle.position),
+                color: particle.color,
+            });
+        }
+    }
+}
+
+pub fn random_unit_vector() -> Vec3 {
+    loop {
+        let v = Vec3::new(fastrand::f32() * 2.0 - 1.0, fastrand::f32() * 2.0 - 1.0, fastrand::f32() * 2.0 - 1.0);
+        let length_squared = v.length_squared();
+        if length_squared > 1e-4 && length_squared <= 1.0 {
+            return v / length_squared.sqrt();
+        }
+    }
+}
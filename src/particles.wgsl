@@ -0,0 +1,87 @@
+// GPU 粒子的绘制：每个实例是绘制列表中的一个粒子，六个顶点组成一个朝向相机的面片，
+// 运动快的粒子（雨丝、火花）沿速度方向拉长
+
+// 场景的每帧绑定组，只用到开头的相机部分
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    view_position: vec4<f32>,
+};
+
+struct Particle {
+    position: vec3<f32>,
+    life: f32,
+    velocity: vec3<f32>,
+    max_life: f32,
+    color: vec3<f32>,
+    size: f32,
+    kind: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var<storage, read> particles: array<Particle>;
+@group(1) @binding(1)
+var<storage, read> alive: array<u32>;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) corner: vec2<f32>,
+};
+
+const KIND_DUST: u32 = 0u;
+const KIND_RAIN: u32 = 1u;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, @builtin(instance_index) instance_index: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+    let p = particles[alive[instance_index]];
+
+    let to_camera = normalize(camera.view_position.xyz - p.position);
+    let speed = length(p.velocity);
+    let streak = p.kind != KIND_DUST && speed > 0.5;
+    // 拉长的粒子以速度方向为竖直轴，其他粒子以世界的上方为竖直轴
+    let axis = select(vec3<f32>(0.0, 1.0, 0.0), p.velocity / max(speed, 0.001), streak);
+    var right = cross(axis, to_camera);
+    // 正对着竖直轴看时换一个方向
+    right = select(normalize(right), vec3<f32>(1.0, 0.0, 0.0), length(right) < 0.001);
+    let up = select(normalize(cross(to_camera, right)), axis, streak);
+    let half_length = select(p.size, max(p.size, speed * 0.015), streak);
+
+    let position = p.position + right * corner.x * p.size + up * corner.y * half_length;
+
+    // 生命的最后 30% 逐渐消失
+    let fade = clamp(p.life / (p.max_life * 0.3), 0.0, 1.0);
+    var alpha = 1.0;
+    if (p.kind == KIND_DUST) {
+        alpha = 0.35;
+    } else if (p.kind == KIND_RAIN) {
+        alpha = 0.45;
+    }
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(position, 1.0);
+    out.color = vec4<f32>(p.color, alpha * fade);
+    out.corner = corner;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // 圆形的粒子，边缘柔和
+    let distance = length(in.corner);
+    if (distance > 1.0) {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb, in.color.a * (1.0 - distance * distance));
+}
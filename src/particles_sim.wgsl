@@ -0,0 +1,88 @@
+// GPU 粒子模拟：每个线程更新粒子池中的一个粒子，
+// 存活的粒子编号追加到绘制列表，绘制列表的长度就是间接绘制的实例数
+
+struct Particle {
+    position: vec3<f32>,
+    life: f32,
+    velocity: vec3<f32>,
+    max_life: f32,
+    color: vec3<f32>,
+    size: f32,
+    kind: u32,
+};
+
+struct SimParams {
+    dt: f32,
+    gravity: f32,
+    count: u32,
+    _padding: u32,
+};
+
+struct DrawArgs {
+    vertex_count: u32,
+    instance_count: atomic<u32>,
+    first_vertex: u32,
+    first_instance: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> params: SimParams;
+@group(0) @binding(1)
+var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2)
+var<storage, read_write> alive: array<u32>;
+@group(0) @binding(3)
+var<storage, read_write> draw_args: DrawArgs;
+
+// 与 gpu_particles.rs 中的 ParticleKind 对应
+const KIND_DUST: u32 = 0u;
+const KIND_RAIN: u32 = 1u;
+const KIND_EMBER: u32 = 3u;
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if (index >= params.count) {
+        return;
+    }
+    var p = particles[index];
+    if (p.life <= 0.0) {
+        return;
+    }
+    p.life -= params.dt;
+
+    if (p.kind == KIND_DUST) {
+        // 灰尘几乎不受重力，慢慢飘
+        p.velocity.y -= params.gravity * 0.01 * params.dt;
+        p.velocity *= max(1.0 - 0.2 * params.dt, 0.0);
+        p.position += p.velocity * params.dt;
+    } else if (p.kind == KIND_RAIN) {
+        // 雨滴落地就消失
+        p.velocity.y -= params.gravity * params.dt;
+        p.position += p.velocity * params.dt;
+        if (p.position.y <= 0.0) {
+            p.life = 0.0;
+        }
+    } else if (p.kind == KIND_EMBER) {
+        // 火星被热气托着往上飘，慢慢减速
+        p.velocity.y += params.gravity * 0.02 * params.dt;
+        p.velocity *= max(1.0 - 1.5 * params.dt, 0.0);
+        p.position += p.velocity * params.dt;
+    } else {
+        // 火花落地后弹起并减速
+        p.velocity.y -= params.gravity * params.dt;
+        p.position += p.velocity * params.dt;
+        if (p.position.y < p.size) {
+            p.position.y = p.size;
+            p.velocity.y = -p.velocity.y * 0.4;
+            p.velocity.x *= 0.7;
+            p.velocity.z *= 0.7;
+        }
+    }
+
+    particles[index] = p;
+    if (p.life > 0.0) {
+        let slot = atomicAdd(&draw_args.instance_count, 1u);
+        alive[slot] = index;
+    }
+}
@@ -0,0 +1,173 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// 设置、存档、截图和日志放在系统规定的目录里：
+//   Linux    $XDG_CONFIG_HOME、$XDG_DATA_HOME、$XDG_STATE_HOME（默认 ~/.config、~/.local/share、~/.local/state）
+//   Windows  %APPDATA%，日志在 %LOCALAPPDATA%
+//   macOS    ~/Library/Application Support，日志在 ~/Library/Logs
+// 加 --portable 时全部放在程序旁边。以前放在当前目录下的文件第一次启动时搬过去
+
+const APP_DIR: &str = "trae-shooting";
+
+struct Dirs {
+    // 设置和按键配置
+    config: PathBuf,
+    // 玩家档案、性能测试报告、截图和 mod
+    data: PathBuf,
+    logs: PathBuf,
+}
+
+static DIRS: OnceLock<Dirs> = OnceLock::new();
+
+// 在读取任何设置之前调用；没调用过（比如 --bake-lighting）时用系统目录，不迁移旧文件
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init(args: &[String]) {
+    let portable = args.iter().any(|arg| arg == "--portable");
+    let dirs = DIRS.get_or_init(|| resolve(portable));
+    for dir in [&dirs.config, &dirs.data, &dirs.logs, &mods()] {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            eprintln!("无法创建目录 {}: {}", dir.display(), e);
+        }
+    }
+    migrate_legacy();
+    println!(
+        "设置保存在 {}，存档在 {}，截图在 {}，mod 在 {}，日志在 {}",
+        dirs.config.display(),
+        dirs.data.display(),
+        screenshots().display(),
+        mods().display(),
+        dirs.logs.display()
+    );
+}
+
+fn dirs() -> &'static Dirs {
+    DIRS.get_or_init(|| resolve(false))
+}
+
+pub fn settings() -> PathBuf {
+    dirs().config.join("settings.json")
+}
+
+pub fn input_config() -> PathBuf {
+    dirs().config.join("input.json")
+}
+
+// 联机主机的比赛规则（见 server_config.rs）
+pub fn server_config() -> PathBuf {
+    dirs().config.join(crate::server_config::FILE_NAME)
+}
+
+pub fn profile() -> PathBuf {
+    dirs().data.join("profile.json")
+}
+
+pub fn benchmarks() -> PathBuf {
+    dirs().data.join("benchmarks")
+}
+
+// --determinism 模式的结果（见 determinism.rs）
+pub fn determinism() -> PathBuf {
+    dirs().data.join("determinism")
+}
+
+pub fn screenshots() -> PathBuf {
+    dirs().data.join("screenshots")
+}
+
+pub fn mods() -> PathBuf {
+    dirs().data.join("mods")
+}
+
+// 主机录的比赛录像（见 demo.rs）
+pub fn demos() -> PathBuf {
+    dirs().data.join("demos")
+}
+
+// HTTP 接口上别人分享的地图（见 map_share.rs）
+#[cfg(feature = "http-server")]
+pub fn shared_maps() -> PathBuf {
+    dirs().data.join("shared_maps")
+}
+
+fn resolve(portable: bool) -> Dirs {
+    if portable {
+        return portable_dirs();
+    }
+    platform_dirs().unwrap_or_else(|| {
+        eprintln!("找不到用户目录，设置和存档放在程序旁边");
+        portable_dirs()
+    })
+}
+
+// 程序所在的目录，拿不到时用当前目录
+fn portable_dirs() -> Dirs {
+    let base = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)).unwrap_or_else(|| PathBuf::from("."));
+    Dirs { config: base.clone(), data: base.clone(), logs: base.join("logs") }
+}
+
+// 环境变量里的目录，按规范只认绝对路径
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name).map(PathBuf::from).filter(|path| path.is_absolute())
+}
+
+#[cfg(target_os = "windows")]
+fn platform_dirs() -> Option<Dirs> {
+    let roaming = env_dir("APPDATA")?.join(APP_DIR);
+    let local = env_dir("LOCALAPPDATA").map_or_else(|| roaming.clone(), |dir| dir.join(APP_DIR));
+    Some(Dirs { config: roaming.clone(), data: roaming, logs: local.join("logs") })
+}
+
+#[cfg(target_os = "macos")]
+fn platform_dirs() -> Option<Dirs> {
+    let library = env_dir("HOME")?.join("Library");
+    let support = library.join("Application Support").join(APP_DIR);
+    Some(Dirs { config: support.clone(), data: support, logs: library.join("Logs").join(APP_DIR) })
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn platform_dirs() -> Option<Dirs> {
+    let home = env_dir("HOME");
+    let xdg = |name: &str, fallback: &str| env_dir(name).or_else(|| home.as_ref().map(|home| home.join(fallback))).map(|dir| dir.join(APP_DIR));
+    Some(Dirs {
+        config: xdg("XDG_CONFIG_HOME", ".config")?,
+        data: xdg("XDG_DATA_HOME", ".local/share")?,
+        logs: xdg("XDG_STATE_HOME", ".local/state")?.join("logs"),
+    })
+}
+
+// 浏览器里没有文件系统，保持原来的相对路径
+#[cfg(not(any(unix, target_os = "windows")))]
+fn platform_dirs() -> Option<Dirs> {
+    Some(Dirs { config: PathBuf::from("."), data: PathBuf::from("."), logs: PathBuf::from("logs") })
+}
+
+// 以前的版本把文件放在当前目录下。新位置还没有文件时搬过去，已经有了就不动
+#[cfg(not(target_arch = "wasm32"))]
+fn migrate_legacy() {
+    let moves = [
+        ("settings.json", settings()),
+        ("input.json", input_config()),
+        ("profile.json", profile()),
+        ("benchmarks", benchmarks()),
+    ];
+    for (legacy, target) in moves {
+        let legacy = Path::new(legacy);
+        if !legacy.exists() || target.exists() || same_file(legacy, &target) {
+            continue;
+        }
+        // 跨文件系统时不能改名，复制过去（只有单个文件需要）
+        let result = std::fs::rename(legacy, &target).or_else(|_| std::fs::copy(legacy, &target).map(|_| ()));
+        match result {
+            Ok(()) => println!("已把 {} 迁移到 {}", legacy.display(), target.display()),
+            Err(e) => eprintln!("无法把 {} 迁移到 {}: {}", legacy.display(), target.display(), e),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.parent().and_then(|parent| parent.canonicalize().ok())) {
+        (Ok(a), Some(parent)) => b.file_name().is_some_and(|name| a == parent.join(name)),
+        _ => false,
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+
+use glam::{Mat4, Quat, Vec3};
+
+use crate::ecs::{Body, Collider, Entity, Render, Transform, World};
+use crate::loot::LootItem;
+use crate::map::{KeyColor, Map, PickupItem};
+use crate::model::BoxInstance;
+use crate::weapon::{self, WeaponSlot, WeaponStats};
+
+// 地上的拾取物：武器（地图中放置的、玩家丢下的和敌人掉落的）、钥匙卡、护甲，以及敌人随机掉落的弹药、血包和钱，
+// 落地时有简单的物理（重力、弹跳、撞墙）。拾取物是 ecs 里的实体
+
+const RADIUS: f32 = 0.3;
+const THICKNESS: f32 = 0.1;
+// 地上最多保留的拾取物数量，超过时最早丢下的消失（地图放置的不算，敌人的掉落物算）
+const MAX_DROPPED: usize = 24;
+
+const BODY_COLOR: [f32; 3] = [0.15, 0.15, 0.17];
+const GRIP_COLOR: [f32; 3] = [0.35, 0.25, 0.12];
+const VEST_COLOR: [f32; 3] = [0.12, 0.2, 0.35];
+const PLATE_COLOR: [f32; 3] = [0.3, 0.55, 1.0];
+const AMMO_COLOR: [f32; 3] = [0.3, 0.35, 0.15];
+const MEDKIT_COLOR: [f32; 3] = [0.9, 0.9, 0.88];
+const CROSS_COLOR: [f32; 3] = [0.85, 0.1, 0.1];
+const CASH_COLOR: [f32; 3] = [0.3, 0.65, 0.3];
+// 掉落物弹出去的速度：向上和向外（随机方向）
+const POP_UP: f32 = 3.0;
+const POP_OUT: f32 = 1.5;
+
+#[derive(Debug, Clone)]
+pub enum PickupKind {
+    Weapon(WeaponStats),
+    Keycard(KeyColor),
+    Armor(f32),
+    // 弹匣数
+    Ammo(u32),
+    Health(f32),
+    Currency(u32),
+}
+
+impl PickupKind {
+    // 显示在拾取提示中的名字
+    pub fn name(&self) -> String {
+        match self {
+            PickupKind::Weapon(stats) => stats.name.to_uppercase(),
+            PickupKind::Keycard(key) => format!("{} KEYCARD", key.name()),
+            PickupKind::Armor(amount) => format!("ARMOR +{:.0}", amount),
+            PickupKind::Ammo(_) => "AMMO".to_string(),
+            PickupKind::Health(amount) => format!("HEALTH +{:.0}", amount),
+            PickupKind::Currency(amount) => format!("${}", amount),
+        }
+    }
+}
+
+// 拾取物组件：实体另外有 Transform、Render、Collider 和 Body（物理见 ecs::physics_system）
+pub struct Pickup {
+    pub kind: PickupKind,
+}
+
+// 地上的拾取物，按丢下的先后记下玩家和敌人丢下的，数量超过上限时清理最早的
+pub struct PickupManager {
+    dropped: VecDeque<Entity>,
+}
+
+impl PickupManager {
+    // 放置地图上定义的拾取物，未知的武器名只打印警告
+    pub fn new(world: &mut World, map: &Map, weapons: &[WeaponStats]) -> Self {
+        for spawn in &map.pickups {
+            let kind = match &spawn.item {
+                PickupItem::Weapon(id) => match weapon::find_weapon(weapons, id) {
+                    Some(stats) => PickupKind::Weapon(stats.clone()),
+                    None => {
+                        eprintln!("地图 {} 中的拾取物使用了未知的武器: {}", map.name, id);
+                        continue;
+                    }
+                },
+                PickupItem::Keycard(key) => PickupKind::Keycard(*key),
+                PickupItem::Armor(amount) => PickupKind::Armor(*amount),
+            };
+            let center = map.cell_center(spawn.col, spawn.row);
+            spawn_pickup(world, kind, Vec3::new(center.x, THICKNESS * 0.5, center.z), Vec3::ZERO, 0.0);
+        }
+        Self { dropped: VecDeque::new() }
+    }
+
+    // 敌人的掉落物从 position 向四周弹出去
+    pub fn drop_loot(&mut self, world: &mut World, items: &[LootItem], position: Vec3) {
+        for item in items {
+            let kind = match *item {
+                LootItem::Nothing => continue,
+                LootItem::Ammo { magazines } => PickupKind::Ammo(magazines),
+                LootItem::Health { amount } => PickupKind::Health(amount),
+                LootItem::Currency { amount } => PickupKind::Currency(amount),
+            };
+            let angle = fastrand::f32() * std::f32::consts::TAU;
+            let out = Vec3::new(angle.cos(), 0.0, angle.sin()) * POP_OUT * (0.5 + fastrand::f32());
+            self.drop(world, kind, position, out + Vec3::Y * POP_UP * (0.8 + 0.4 * fastrand::f32()));
+        }
+    }
+
+    // 丢下一个拾取物（武器或者掉落物），velocity 为抛出的初速度
+    pub fn drop(&mut self, world: &mut World, kind: PickupKind, position: Vec3, velocity: Vec3) {
+        let entity = spawn_pickup(world, kind, position, velocity, (fastrand::f32() - 0.5) * 12.0);
+        self.dropped.push_back(entity);
+        if self.dropped.len() > MAX_DROPPED {
+            if let Some(oldest) = self.dropped.pop_front() {
+                world.despawn(oldest);
+            }
+        }
+    }
+
+    // 捡起后从地上移除，index 是准星检测的编号
+    pub fn take(&mut self, world: &mut World, index: usize) -> Option<PickupKind> {
+        let entity = world.resolve(index)?;
+        let pickup = world.pickups.remove(entity)?;
+        world.despawn(entity);
+        self.dropped.retain(|dropped| *dropped != entity);
+        Some(pickup.kind)
+    }
+}
+
+pub fn get(world: &World, index: usize) -> Option<&Pickup> {
+    world.pickups.get(world.resolve(index)?)
+}
+
+fn spawn_pickup(world: &mut World, kind: PickupKind, position: Vec3, velocity: Vec3, spin: f32) -> Entity {
+    let entity = world.spawn();
+    world.transforms.insert(entity, Transform { position, yaw: fastrand::f32() * std::f32::consts::TAU });
+    world.renders.insert(entity, Render { boxes: boxes(&kind) });
+    // 包围盒比模型稍大一些，容易瞄准
+    let half = Vec3::new(0.45, 0.25, 0.45);
+    world.colliders.insert(entity, Collider { min: -half, max: half });
+    world.bodies.insert(entity, Body { velocity, spin, radius: RADIUS, floor: THICKNESS * 0.5 });
+    world.pickups.insert(entity, Pickup { kind });
+    entity
+}
+
+// 武器为枪身加握把（主武器和重武器更长），钥匙卡为一块彩色的卡片，护甲为平放的背心上一块发亮的插板，
+// 弹药为一个小弹药箱，血包为白色盒子上一个红十字，钱为一叠绿色的钞票
+fn boxes(kind: &PickupKind) -> Vec<BoxInstance> {
+    let length = match kind {
+        PickupKind::Weapon(stats) if stats.slot != WeaponSlot::Secondary => 0.8,
+        PickupKind::Weapon(_) => 0.35,
+        PickupKind::Keycard(key) => {
+            return vec![BoxInstance {
+                transform: Mat4::from_scale_rotation_translation(
+                    Vec3::new(0.3, 0.03, 0.2),
+                    Quat::IDENTITY,
+                    -Vec3::Y * (THICKNESS * 0.5 - 0.015),
+                ),
+                color: key.color(),
+            }];
+        }
+        PickupKind::Ammo(_) => {
+            return vec![flat_box(Vec3::new(0.35, THICKNESS * 1.6, 0.2), THICKNESS * 0.3, AMMO_COLOR)];
+        }
+        PickupKind::Health(_) => {
+            return vec![
+                flat_box(Vec3::new(0.35, THICKNESS * 1.2, 0.3), THICKNESS * 0.1, MEDKIT_COLOR),
+                flat_box(Vec3::new(0.2, 0.01, 0.06), THICKNESS * 0.7, CROSS_COLOR),
+                flat_box(Vec3::new(0.06, 0.01, 0.2), THICKNESS * 0.7, CROSS_COLOR),
+            ];
+        }
+        PickupKind::Currency(_) => {
+            return vec![flat_box(Vec3::new(0.3, THICKNESS * 0.5, 0.15), -THICKNESS * 0.25, CASH_COLOR)];
+        }
+        PickupKind::Armor(_) => {
+            return vec![
+                BoxInstance {
+                    transform: Mat4::from_scale_rotation_translation(Vec3::new(0.45, THICKNESS * 0.6, 0.55), Quat::IDENTITY, -Vec3::Y * THICKNESS * 0.2),
+                    color: VEST_COLOR,
+                },
+                BoxInstance {
+                    transform: Mat4::from_scale_rotation_translation(Vec3::new(0.25, THICKNESS * 0.4, 0.3), Quat::IDENTITY, Vec3::Y * THICKNESS * 0.3),
+                    color: PLATE_COLOR,
+                },
+            ];
+        }
+    };
+    vec![
+        BoxInstance {
+            transform: Mat4::from_scale_rotation_translation(Vec3::new(length, THICKNESS * 0.6, 0.08), Quat::IDENTITY, Vec3::Y * THICKNESS * 0.2),
+            color: BODY_COLOR,
+        },
+        BoxInstance {
+            transform: Mat4::from_scale_rotation_translation(
+                Vec3::new(0.08, THICKNESS, 0.16),
+                Quat::IDENTITY,
+                Vec3::new(-length * 0.25, 0.0, 0.1),
+            ),
+            color: GRIP_COLOR,
+        },
+    ]
+}
+
+// 平放在地上的盒子，y 为中心相对实体原点的高度
+fn flat_box(size: Vec3, y: f32, color: [f32; 3]) -> BoxInstance {
+    BoxInstance { transform: Mat4::from_scale_rotation_translation(size, Quat::IDENTITY, Vec3::Y * y), color }
+}
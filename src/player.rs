@@ -0,0 +1,316 @@
+use crate::damage::{Damage, Resistances, StatusEffects};
+use crate::icons::Icon;
+use crate::ui::UiBatch;
+
+// 玩家生命值和护甲、冲刺的体力、死亡/重生和重生保护，以及燃烧、电击这些持续状态。
+// 生效中的状态在生命值上方显示成带倒计时的图标，可以选择同时在屏幕边缘发光
+
+pub const MAX_HEALTH: f32 = 100.0;
+const MAX_ARMOR: f32 = 100.0;
+// 护甲先挡下这个比例的伤害，挡下多少扣多少护甲，护甲不够时剩下的都扣生命值
+const ARMOR_ABSORB: f32 = 0.6;
+// 冲刺每秒消耗、不冲刺时每秒恢复的体力。用完以后要恢复到 STAMINA_RECOVER 才能再冲刺
+const MAX_STAMINA: f32 = 100.0;
+const STAMINA_DRAIN: f32 = 25.0;
+const STAMINA_REGEN: f32 = 20.0;
+const STAMINA_RECOVER: f32 = 30.0;
+// 死亡后多久重生（秒）
+const RESPAWN_DELAY: f32 = 3.0;
+// 重生保护时间（秒），期间不受伤害
+const SPAWN_PROTECTION: f32 = 3.0;
+// 死亡时画面变黑、重生时从黑色恢复的时间（秒）
+const FADE_OUT: f32 = 0.6;
+const FADE_IN: f32 = 1.0;
+// 状态图标的大小和间距（像素）
+const STATUS_ICON_SIZE: f32 = 32.0;
+const STATUS_SPACING: f32 = 48.0;
+// 屏幕边缘发光的宽度
+const OVERLAY_EDGE: f32 = 18.0;
+
+// HUD 上显示的状态
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Burning,
+    Stunned,
+    Protected,
+    SprintBlocked,
+}
+
+impl Status {
+    fn icon(self) -> Icon {
+        match self {
+            Status::Burning => Icon::Flame,
+            Status::Stunned => Icon::Bolt,
+            Status::Protected => Icon::Shield,
+            Status::SprintBlocked => Icon::NoSprint,
+        }
+    }
+
+    fn color(self) -> [f32; 4] {
+        match self {
+            Status::Burning => [1.0, 0.55, 0.1, 1.0],
+            Status::Stunned => [0.55, 0.75, 1.0, 1.0],
+            Status::Protected => [0.3, 0.9, 1.0, 1.0],
+            Status::SprintBlocked => [0.85, 0.85, 0.85, 1.0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DamageOutcome {
+    // 重生保护中或已经死亡
+    Ignored,
+    Hurt,
+    Killed,
+}
+
+pub struct Player {
+    pub health: f32,
+    pub armor: f32,
+    stamina: f32,
+    // 体力上限，重生时按天赋设置
+    max_stamina: f32,
+    exhausted: bool,
+    // 各伤害类型的倍数
+    pub resistances: Resistances,
+    status: StatusEffects,
+    // 死亡后到重生的剩余时间，活着时为 None
+    respawn_timer: Option<f32>,
+    protection: f32,
+    fade_in: f32,
+    // 用于护盾闪烁效果
+    time: f32,
+}
+
+impl Player {
+    pub fn new() -> Self {
+        Self {
+            health: MAX_HEALTH,
+            armor: 0.0,
+            stamina: MAX_STAMINA,
+            max_stamina: MAX_STAMINA,
+            exhausted: false,
+            resistances: Resistances::default(),
+            status: StatusEffects::default(),
+            respawn_timer: None,
+            protection: 0.0,
+            fade_in: 0.0,
+            time: 0.0,
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.respawn_timer.is_none()
+    }
+
+    pub fn is_protected(&self) -> bool {
+        self.protection > 0.0
+    }
+
+    // 电击僵直时不能移动
+    pub fn is_stunned(&self) -> bool {
+        self.status.is_stunned()
+    }
+
+    // 护甲满了时不能再捡
+    pub fn armor_full(&self) -> bool {
+        self.armor >= MAX_ARMOR
+    }
+
+    pub fn add_armor(&mut self, amount: f32) {
+        self.armor = (self.armor + amount).min(MAX_ARMOR);
+    }
+
+    pub fn health_full(&self) -> bool {
+        self.health >= MAX_HEALTH
+    }
+
+    pub fn heal(&mut self, amount: f32) {
+        if self.is_alive() {
+            self.health = (self.health + amount).min(MAX_HEALTH);
+        }
+    }
+
+    // 天赋加的体力上限倍数，重生时设置
+    pub fn set_stamina_scale(&mut self, scale: f32) {
+        self.max_stamina = MAX_STAMINA * scale;
+        self.stamina = self.max_stamina;
+    }
+
+    pub fn update_stamina(&mut self, dt: f32, sprinting: bool) {
+        if sprinting {
+            self.stamina = (self.stamina - STAMINA_DRAIN * dt).max(0.0);
+            self.exhausted |= self.stamina <= 0.0;
+        } else {
+            self.stamina = (self.stamina + STAMINA_REGEN * dt).min(self.max_stamina);
+            self.exhausted &= self.stamina < STAMINA_RECOVER;
+        }
+    }
+
+    // 体力用完、被炸到或者电击以后喘不过气，暂时不能冲刺
+    pub fn sprint_blocked(&self) -> bool {
+        self.exhausted || self.status.winded() > 0.0
+    }
+
+    // 还要多久才能再冲刺
+    fn sprint_blocked_for(&self) -> f32 {
+        let recover = if self.exhausted { (STAMINA_RECOVER - self.stamina) / STAMINA_REGEN } else { 0.0 };
+        recover.max(self.status.winded())
+    }
+
+    // 生效中的状态和剩余的秒数
+    pub fn statuses(&self) -> Vec<(Status, f32)> {
+        [
+            (Status::Burning, self.status.burning()),
+            (Status::Stunned, self.status.stunned()),
+            (Status::Protected, self.protection),
+            (Status::SprintBlocked, self.sprint_blocked_for()),
+        ]
+        .into_iter()
+        .filter(|(_, remaining)| *remaining > 0.0)
+        .collect()
+    }
+
+    // 返回抗性折算后实际受到的伤害
+    pub fn take_damage(&mut self, damage: Damage) -> (Damage, DamageOutcome) {
+        if !self.is_alive() || self.is_protected() {
+            return (damage, DamageOutcome::Ignored);
+        }
+        let damage = self.resistances.apply(damage);
+        self.status.apply(damage);
+        (damage, self.lose_health(damage.amount))
+    }
+
+    // 燃烧这一帧的掉血，没有在燃烧时为 None
+    pub fn update_status(&mut self, dt: f32) -> Option<(Damage, DamageOutcome)> {
+        let burn = self.resistances.apply(self.status.update(dt)?);
+        Some((burn, self.lose_health(burn.amount)))
+    }
+
+    fn lose_health(&mut self, amount: f32) -> DamageOutcome {
+        if !self.is_alive() || self.is_protected() || amount <= 0.0 {
+            return DamageOutcome::Ignored;
+        }
+        let absorbed = (amount * ARMOR_ABSORB).min(self.armor);
+        self.armor -= absorbed;
+        self.health = (self.health - (amount - absorbed)).max(0.0);
+        if self.health > 0.0 {
+            return DamageOutcome::Hurt;
+        }
+        self.respawn_timer = Some(RESPAWN_DELAY);
+        DamageOutcome::Killed
+    }
+
+    // 返回 true 表示该重生了（由调用者选择重生点后调用 respawn）
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.time += dt;
+        self.protection = (self.protection - dt).max(0.0);
+        self.fade_in = (self.fade_in - dt).max(0.0);
+        match &mut self.respawn_timer {
+            Some(timer) => {
+                *timer -= dt;
+                *timer <= 0.0
+            }
+            None => false,
+        }
+    }
+
+    // 离重生倒计时结束还有多久，活着时为 None
+    pub fn respawn_in(&self) -> Option<f32> {
+        self.respawn_timer.map(|timer| timer.max(0.0))
+    }
+
+    pub fn respawn(&mut self) {
+        self.health = MAX_HEALTH;
+        self.armor = 0.0;
+        self.stamina = self.max_stamina;
+        self.exhausted = false;
+        self.status = StatusEffects::default();
+        self.respawn_timer = None;
+        self.protection = SPAWN_PROTECTION;
+        self.fade_in = FADE_IN;
+    }
+
+    // 开火时提前结束重生保护
+    pub fn end_protection(&mut self) {
+        self.protection = 0.0;
+    }
+
+    // 观战时不画生命值和死亡画面，重生倒计时由观战的提示显示。overlays 为设置里的屏幕边缘发光
+    pub fn draw(&self, ui: &mut UiBatch, spectating: bool, overlays: bool) {
+        if spectating {
+            return;
+        }
+        let (width, height) = (ui.width(), ui.height());
+        let statuses = self.statuses();
+
+        // 屏幕边缘：燃烧时橙色，僵直时蓝白色闪，重生保护时青色闪烁
+        if overlays {
+            for (status, _) in &statuses {
+                let alpha = match status {
+                    Status::Burning => 0.2 + 0.08 * (self.time * 11.0).sin(),
+                    Status::Stunned => 0.1 + 0.15 * (self.time * 31.0).sin().abs(),
+                    Status::Protected => 0.12 + 0.18 * (0.5 + 0.5 * (self.time * 8.0).sin()),
+                    Status::SprintBlocked => continue,
+                };
+                let [r, g, b, _] = status.color();
+                edge_glow(ui, [r, g, b, alpha]);
+            }
+        }
+        if self.is_protected() {
+            ui.text_centered(width / 2.0, 40.0, 2.0, Status::Protected.color(), &format!("SPAWN PROTECTION {:.1}", self.protection));
+        }
+
+        // 生命值
+        let (bar_x, bar_y, bar_w, bar_h) = (20.0, height - 40.0, 240.0, 20.0);
+        ui.rect(bar_x, bar_y, bar_w, bar_h, [0.0, 0.0, 0.0, 0.6]);
+        let fraction = self.health / MAX_HEALTH;
+        let bar_color = if fraction > 0.3 { [0.2, 0.85, 0.3, 0.9] } else { [0.95, 0.2, 0.15, 0.9] };
+        ui.rect(bar_x, bar_y, bar_w * fraction, bar_h, bar_color);
+        ui.text(bar_x + 6.0, bar_y + 3.0, 2.0, [1.0, 1.0, 1.0, 1.0], &format!("{:.0}", self.health));
+
+        // 护甲在生命值右边，没有护甲时只有空槽
+        let (armor_x, armor_w) = (bar_x + bar_w + 10.0, 160.0);
+        ui.rect(armor_x, bar_y, armor_w, bar_h, [0.0, 0.0, 0.0, 0.6]);
+        ui.rect(armor_x, bar_y, armor_w * self.armor / MAX_ARMOR, bar_h, [0.25, 0.55, 1.0, 0.9]);
+        ui.text(armor_x + 6.0, bar_y + 3.0, 2.0, [1.0, 1.0, 1.0, 1.0], &format!("{:.0}", self.armor));
+
+        // 体力是生命值下面的细条，用完时变灰
+        let stamina_color = if self.exhausted { [0.5, 0.5, 0.5, 0.8] } else { [0.95, 0.8, 0.2, 0.8] };
+        ui.rect(bar_x, bar_y + bar_h + 4.0, bar_w * self.stamina / self.max_stamina, 4.0, stamina_color);
+
+        // 生命值上方一排状态图标，下面是剩余秒数
+        let icon_y = bar_y - STATUS_ICON_SIZE - 24.0;
+        for (i, (status, remaining)) in statuses.iter().enumerate() {
+            let x = bar_x + i as f32 * STATUS_SPACING;
+            ui.rect(x - 2.0, icon_y - 2.0, STATUS_ICON_SIZE + 4.0, STATUS_ICON_SIZE + 20.0, [0.0, 0.0, 0.0, 0.5]);
+            // 快结束时闪烁
+            let alpha = if *remaining < 1.0 { 0.5 + 0.5 * (self.time * 12.0).cos().abs() } else { 1.0 };
+            let [r, g, b, _] = status.color();
+            ui.icon(x, icon_y, STATUS_ICON_SIZE, status.icon(), [r, g, b, alpha]);
+            ui.text_centered(x + STATUS_ICON_SIZE / 2.0, icon_y + STATUS_ICON_SIZE + 2.0, 2.0, [0.9, 0.9, 0.9, 1.0], &format!("{:.1}", remaining));
+        }
+
+        // 死亡时变黑，重生时从黑色恢复
+        let black = match self.respawn_timer {
+            Some(timer) => ((RESPAWN_DELAY - timer) / FADE_OUT).min(1.0),
+            None => self.fade_in / FADE_IN,
+        };
+        if black > 0.0 {
+            ui.rect(0.0, 0.0, width, height, [0.0, 0.0, 0.0, black]);
+        }
+        if let Some(timer) = self.respawn_timer {
+            ui.text_centered(width / 2.0, height / 2.0 - 30.0, 5.0, [0.9, 0.15, 0.1, 1.0], "YOU DIED");
+            ui.text_centered(width / 2.0, height / 2.0 + 30.0, 2.0, [0.8, 0.8, 0.8, 1.0], &format!("respawning in {:.0}", timer.max(0.0).ceil()));
+        }
+    }
+}
+
+// 沿屏幕四边画一圈半透明的颜色
+fn edge_glow(ui: &mut UiBatch, color: [f32; 4]) {
+    let (width, height) = (ui.width(), ui.height());
+    ui.rect(0.0, 0.0, width, OVERLAY_EDGE, color);
+    ui.rect(0.0, height - OVERLAY_EDGE, width, OVERLAY_EDGE, color);
+    ui.rect(0.0, OVERLAY_EDGE, OVERLAY_EDGE, height - OVERLAY_EDGE * 2.0, color);
+    ui.rect(width - OVERLAY_EDGE, OVERLAY_EDGE, OVERLAY_EDGE, height - OVERLAY_EDGE * 2.0, color);
+}
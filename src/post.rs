@@ -0,0 +1,480 @@
+use glam::{Mat4, Vec3};
+
+use crate::assets;
+use crate::collision::{self, WallCollider};
+use crate::gpu_memory;
+use crate::map;
+use crate::settings::{EffectQuality, Settings};
+use crate::upload::FrameUploader;
+
+// 后处理：景深、相机运动模糊和 LUT 调色。打开任意一个时场景先画进一张屏幕大小的纹理，
+// 再用全屏三角形依次处理，最后一步直接画到交换链上；都关闭时不创建纹理，场景照旧直接画到屏幕。
+// 景深的对焦距离来自屏幕中心的射线检测，平滑过渡；运动模糊只考虑相机的运动，
+// 用深度重建像素的世界坐标，投影到上一帧求出屏幕上的速度。
+// 调色由地图指定的 3D LUT 决定，地图没有指定时就是中性的，不加这一步。
+// 夜视仪开着时最后再把画面变成带噪点和暗角的绿色
+
+// 对焦距离的变化速度（每秒接近目标的比例）
+const FOCUS_RATE: f32 = 6.0;
+// 对焦距离前后这么远以内完全清晰（米）
+const IN_FOCUS_RANGE: f32 = 0.5;
+const APERTURE: f32 = 1.5;
+// 最大模糊半径占画面高度的比例
+const MAX_COC: f32 = 0.012;
+// 快门：速度的倍数，最大长度占画面高度的比例
+const SHUTTER: f32 = 0.5;
+const MAX_MOTION: f32 = 0.04;
+// 夜视仪：亮度放大倍数和噪点强度
+const NIGHT_VISION_GAIN: f32 = 6.0;
+const NIGHT_VISION_GRAIN: f32 = 0.25;
+// 射线什么都没碰到时的对焦距离
+const MAX_FOCUS_DISTANCE: f32 = 100.0;
+// 一帧内相机移动超过这个距离（重生、切换地图）时不模糊
+const TELEPORT_DISTANCE: f32 = 2.0;
+
+// 内置 LUT，磁盘上找不到 luts/ 目录时使用。图片是 N 张 N×N 的切片横向排开（N²×N），
+// 切片内 x 为红、y 为绿，第几张切片为蓝；luts/neutral.png 是不改变颜色的模板
+const BUILTIN_LUTS: &[(&str, &[u8])] = &[
+    ("neutral", include_bytes!("../luts/neutral.png")),
+    ("cold_fluorescent", include_bytes!("../luts/cold_fluorescent.png")),
+    ("warm_sodium", include_bytes!("../luts/warm_sodium.png")),
+];
+
+fn lut_path(name: &str) -> std::path::PathBuf {
+    std::path::Path::new("luts").join(format!("{}.png", name))
+}
+
+// 浏览器版启动时要下载的 LUT 文件
+#[cfg(target_arch = "wasm32")]
+pub fn builtin_lut_files() -> Vec<std::path::PathBuf> {
+    BUILTIN_LUTS.iter().map(|(name, _)| lut_path(name)).collect()
+}
+
+// 先从 luts/ 目录读取，找不到时使用内置的；返回边长和 RGBA 数据
+fn load_lut(name: &str) -> Result<(u32, image::RgbaImage), String> {
+    let path = lut_path(name);
+    let image = match assets::read(&path) {
+        Ok(bytes) => image::load_from_memory(&bytes).map_err(|e| format!("{}: {}", path.display(), e))?,
+        Err(_) => {
+            let (_, bytes) = BUILTIN_LUTS.iter().find(|(builtin, _)| *builtin == name).ok_or_else(|| format!("LUT not found: {}", name))?;
+            image::load_from_memory(bytes).map_err(|e| e.to_string())?
+        }
+    };
+    let size = image.height();
+    if size < 2 || image.width() != size * size {
+        return Err(format!("LUT {} should be {}x{}, got {}x{}", name, size * size, size, image.width(), image.height()));
+    }
+    Ok((size, image.to_rgba8()))
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct PostUniform {
+    inv_view_proj: [[f32; 4]; 4],
+    prev_view_proj: [[f32; 4]; 4],
+    camera_position: [f32; 4],
+    dof: [f32; 4],
+    motion: [f32; 4],
+    quality: [f32; 4],
+    // x 放大倍数 y 噪点的随机种子 z 噪点强度
+    night_vision: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for PostUniform {}
+unsafe impl bytemuck::Zeroable for PostUniform {}
+
+// 景深和运动模糊的采样数
+fn sample_counts(quality: EffectQuality) -> (u32, u32) {
+    match quality {
+        EffectQuality::Low => (8, 4),
+        EffectQuality::Medium => (16, 8),
+        EffectQuality::High => (32, 16),
+    }
+}
+
+// 屏幕中心射线碰到的最近距离：瞄准的物体、墙体，或者不在碰撞体里的地面和天花板
+pub fn focus_target(colliders: &[WallCollider], aim_distance: Option<f32>, origin: Vec3, dir: Vec3) -> f32 {
+    let wall = collision::raycast_all(colliders, origin, dir, MAX_FOCUS_DISTANCE).first().map_or(MAX_FOCUS_DISTANCE, |hit| hit.distance);
+    let plane = if dir.y < -1e-3 {
+        origin.y / -dir.y
+    } else if dir.y > 1e-3 {
+        (map::WALL_HEIGHT - origin.y) / dir.y
+    } else {
+        MAX_FOCUS_DISTANCE
+    };
+    wall.min(plane).min(aim_distance.unwrap_or(MAX_FOCUS_DISTANCE))
+}
+
+// 场景画面和中间结果两张纹理，以及分别以它们为输入的绑定组
+struct Targets {
+    scene_view: wgpu::TextureView,
+    from_scene: wgpu::BindGroup,
+    intermediate_view: wgpu::TextureView,
+    from_intermediate: wgpu::BindGroup,
+    // 两张纹理本身，视图和绑定组引用着它们，留在这里计入显存统计
+    _textures: [gpu_memory::Tracked<wgpu::Texture>; 2],
+}
+
+pub struct PostProcess {
+    layout: wgpu::BindGroupLayout,
+    lut_layout: wgpu::BindGroupLayout,
+    depth_of_field_pipeline: wgpu::RenderPipeline,
+    motion_blur_pipeline: wgpu::RenderPipeline,
+    grading_pipeline: wgpu::RenderPipeline,
+    night_vision_pipeline: wgpu::RenderPipeline,
+    sampler: wgpu::Sampler,
+    uniform_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    format: wgpu::TextureFormat,
+    targets: Option<Targets>,
+    size: (u32, u32),
+    depth_of_field: bool,
+    motion_blur: bool,
+    night_vision: bool,
+    quality: EffectQuality,
+    // 当前地图的 LUT，None 为中性（不调色）
+    lut: Option<(gpu_memory::Tracked<wgpu::Texture>, wgpu::BindGroup)>,
+    // 平滑后的对焦距离
+    focus_distance: f32,
+    // 上一帧的视图投影矩阵和相机位置
+    previous: Option<(Mat4, Vec3)>,
+    // 每帧加一，噪点每帧不一样
+    frame: u32,
+}
+
+impl PostProcess {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Post Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("post.wgsl").into()),
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        // 深度按不可过滤的浮点纹理读，GL 后端不支持对深度纹理 textureLoad
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("post_bind_group_layout"),
+        });
+        // 调色多一组绑定：3D LUT 纹理和线性采样器
+        let lut_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D3,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("lut_bind_group_layout"),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let grading_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grading Pipeline Layout"),
+            bind_group_layouts: &[&layout, &lut_layout],
+            push_constant_ranges: &[],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform_buffer = gpu_memory::buffer_init(device, gpu_memory::Category::Frame, &wgpu::util::BufferInitDescriptor {
+            label: Some("post_uniform"),
+            contents: bytemuck::cast_slice(&[<PostUniform as bytemuck::Zeroable>::zeroed()]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        Self {
+            depth_of_field_pipeline: create_pipeline(device, &pipeline_layout, &shader, format, "fs_depth_of_field"),
+            motion_blur_pipeline: create_pipeline(device, &pipeline_layout, &shader, format, "fs_motion_blur"),
+            grading_pipeline: create_pipeline(device, &grading_pipeline_layout, &shader, format, "fs_color_grading"),
+            night_vision_pipeline: create_pipeline(device, &pipeline_layout, &shader, format, "fs_night_vision"),
+            layout,
+            lut_layout,
+            sampler,
+            uniform_buffer,
+            format,
+            targets: None,
+            size: (1, 1),
+            depth_of_field: false,
+            motion_blur: false,
+            night_vision: false,
+            quality: EffectQuality::Medium,
+            lut: None,
+            focus_distance: 10.0,
+            previous: None,
+            frame: 0,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.depth_of_field || self.motion_blur || self.night_vision || self.lut.is_some()
+    }
+
+    // 夜视仪开关，之后要重新 configure
+    pub fn set_night_vision(&mut self, on: bool) {
+        self.night_vision = on;
+    }
+
+    // 换地图时调用，之后要重新 configure（是否需要离屏纹理可能变了）。读取失败时不调色
+    pub fn set_lut(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, name: Option<&str>) {
+        self.lut = name.and_then(|name| match load_lut(name) {
+            Ok((size, image)) => Some(self.create_lut(device, queue, size, &image)),
+            Err(e) => {
+                eprintln!("无法加载调色 LUT: {}", e);
+                None
+            }
+        });
+    }
+
+    fn create_lut(&self, device: &wgpu::Device, queue: &wgpu::Queue, size: u32, image: &image::RgbaImage) -> (gpu_memory::Tracked<wgpu::Texture>, wgpu::BindGroup) {
+        let extent = wgpu::Extent3d { width: size, height: size, depth_or_array_layers: size };
+        let texture = gpu_memory::texture(device, gpu_memory::Category::Textures, &wgpu::TextureDescriptor {
+            label: Some("color_grading_lut"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        // 横向排开的切片：每一层从第 layer 张切片开始，一行跨过整张图
+        for layer in 0..size {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x: 0, y: 0, z: layer },
+                },
+                image,
+                wgpu::ImageDataLayout {
+                    offset: (layer * size * 4) as u64,
+                    bytes_per_row: Some(size * size * 4),
+                    rows_per_image: Some(size),
+                },
+                wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 1 },
+            );
+        }
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.lut_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&self.sampler) },
+            ],
+            label: Some("lut_bind_group"),
+        });
+        (texture, bind_group)
+    }
+
+    // 设置变化或窗口大小变化时调用，深度纹理重新创建后绑定组也要重建
+    pub fn configure(&mut self, device: &wgpu::Device, settings: &Settings, width: u32, height: u32, depth_view: &wgpu::TextureView) {
+        self.depth_of_field = settings.depth_of_field;
+        self.motion_blur = settings.motion_blur;
+        self.quality = settings.effect_quality;
+        self.size = (width, height);
+        self.targets = self.enabled().then(|| {
+            let scene = self.create_target(device, width, height, "post_scene");
+            let intermediate = self.create_target(device, width, height, "post_intermediate");
+            let (scene_view, intermediate_view) = (scene.create_view(&Default::default()), intermediate.create_view(&Default::default()));
+            Targets {
+                from_scene: self.bind_group(device, &scene_view, depth_view),
+                from_intermediate: self.bind_group(device, &intermediate_view, depth_view),
+                scene_view,
+                intermediate_view,
+                _textures: [scene, intermediate],
+            }
+        });
+    }
+
+    fn create_target(&self, device: &wgpu::Device, width: u32, height: u32, label: &str) -> gpu_memory::Tracked<wgpu::Texture> {
+        gpu_memory::texture(device, gpu_memory::Category::Textures, &wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    }
+
+    fn bind_group(&self, device: &wgpu::Device, color: &wgpu::TextureView, depth: &wgpu::TextureView) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(color) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&self.sampler) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(depth) },
+                wgpu::BindGroupEntry { binding: 3, resource: self.uniform_buffer.as_entire_binding() },
+            ],
+            label: Some("post_bind_group"),
+        })
+    }
+
+    // 场景要画到的纹理，后处理关闭时为 None（直接画到屏幕）
+    pub fn scene_view(&self) -> Option<&wgpu::TextureView> {
+        self.targets.as_ref().map(|targets| &targets.scene_view)
+    }
+
+    // 对焦距离慢慢移向屏幕中心射线碰到的最近距离
+    pub fn update_focus(&mut self, dt: f32, focus_target: f32) {
+        self.focus_distance += (focus_target - self.focus_distance) * (FOCUS_RATE * dt).min(1.0);
+    }
+
+    // 场景画完后调用：依次执行打开的效果，最后一步输出到 output
+    pub fn apply(
+        &mut self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        uploader: &mut FrameUploader,
+        view_proj: Mat4,
+        camera_position: Vec3,
+        output: &wgpu::TextureView,
+    ) {
+        let Some(targets) = &self.targets else {
+            return;
+        };
+        let (width, height) = self.size;
+        let (dof_samples, motion_samples) = sample_counts(self.quality);
+        // 传送时上一帧的矩阵没有意义，当作相机没有动
+        let prev_view_proj = match self.previous {
+            Some((matrix, position)) if position.distance(camera_position) <= TELEPORT_DISTANCE => matrix,
+            _ => view_proj,
+        };
+        let height_px = height as f32;
+        let uniform = PostUniform {
+            inv_view_proj: view_proj.inverse().to_cols_array_2d(),
+            prev_view_proj: prev_view_proj.to_cols_array_2d(),
+            camera_position: camera_position.extend(1.0).to_array(),
+            dof: [self.focus_distance, IN_FOCUS_RANGE, APERTURE, MAX_COC * height_px],
+            motion: [SHUTTER, MAX_MOTION * height_px, 0.0, 0.0],
+            quality: [dof_samples as f32, motion_samples as f32, width as f32, height as f32],
+            night_vision: [NIGHT_VISION_GAIN, (self.frame % 64) as f32, NIGHT_VISION_GRAIN, 0.0],
+        };
+        self.frame = self.frame.wrapping_add(1);
+        uploader.write(queue, encoder, &self.uniform_buffer, 0, &[uniform]);
+        self.previous = Some((view_proj, camera_position));
+
+        let mut passes = Vec::new();
+        if self.depth_of_field {
+            passes.push((&self.depth_of_field_pipeline, None));
+        }
+        if self.motion_blur {
+            passes.push((&self.motion_blur_pipeline, None));
+        }
+        if let Some((_, lut)) = &self.lut {
+            passes.push((&self.grading_pipeline, Some(lut)));
+        }
+        if self.night_vision {
+            passes.push((&self.night_vision_pipeline, None));
+        }
+        // 场景纹理和中间结果轮流作为输入和输出，最后一步输出到屏幕
+        for (i, (pipeline, lut)) in passes.iter().enumerate() {
+            let (input, next) = if i % 2 == 0 {
+                (&targets.from_scene, &targets.intermediate_view)
+            } else {
+                (&targets.from_intermediate, &targets.scene_view)
+            };
+            let target = if i + 1 == passes.len() { output } else { next };
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Post Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: true },
+                })],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, input, &[]);
+            if let Some(lut) = lut {
+                render_pass.set_bind_group(1, lut, &[]);
+            }
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    entry_point: &str,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Post Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
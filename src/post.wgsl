@@ -0,0 +1,133 @@
+// 后处理：景深、相机运动模糊、调色和夜视仪，都是对整屏的一个三角形采样上一步的画面和场景深度
+
+struct PostUniform {
+    inv_view_proj: mat4x4<f32>,
+    // 上一帧的视图投影矩阵（运动模糊用）
+    prev_view_proj: mat4x4<f32>,
+    camera_position: vec4<f32>,
+    // x 对焦距离 y 清晰范围 z 光圈 w 最大模糊半径（像素）
+    dof: vec4<f32>,
+    // x 快门（速度的倍数） y 最大长度（像素）
+    motion: vec4<f32>,
+    // x 景深采样数 y 运动模糊采样数 zw 画面尺寸（像素）
+    quality: vec4<f32>,
+    // x 亮度放大倍数 y 噪点的随机种子 z 噪点强度
+    night_vision: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var t_color: texture_2d<f32>;
+@group(0) @binding(1)
+var s_color: sampler;
+@group(0) @binding(2)
+var t_depth: texture_2d<f32>;
+@group(0) @binding(3)
+var<uniform> post: PostUniform;
+
+// 调色用的 3D LUT
+@group(1) @binding(0)
+var t_lut: texture_3d<f32>;
+@group(1) @binding(1)
+var s_lut: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// 盖住整个屏幕的三角形
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+// 像素对应的世界坐标。反向深度下清屏值 0 在无限远处，稍微往近处挪一点
+fn world_position(uv: vec2<f32>) -> vec3<f32> {
+    let size = vec2<i32>(post.quality.zw);
+    let pixel = clamp(vec2<i32>(uv * post.quality.zw), vec2<i32>(0), size - 1);
+    let depth = max(textureLoad(t_depth, pixel, 0).r, 1e-6);
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let world = post.inv_view_proj * ndc;
+    return world.xyz / world.w;
+}
+
+// 弥散圆半径（像素）：离对焦距离越远越大，清晰范围以内为 0
+fn circle_of_confusion(uv: vec2<f32>) -> f32 {
+    let distance = length(world_position(uv) - post.camera_position.xyz);
+    let defocus = max(abs(distance - post.dof.x) - post.dof.y, 0.0) / max(distance, 0.01);
+    return clamp(defocus * post.dof.z, 0.0, 1.0) * post.dof.w;
+}
+
+@fragment
+fn fs_depth_of_field(in: VertexOutput) -> @location(0) vec4<f32> {
+    let center_coc = circle_of_confusion(in.uv);
+    let texel = 1.0 / post.quality.zw;
+    var sum = textureSample(t_color, s_color, in.uv).rgb;
+    var weight = 1.0;
+    // 黄金角螺旋上的采样点；样本自己的弥散圆盖得住中心像素才算进来，清晰的前景不会渗到背景里
+    let count = i32(post.quality.x);
+    for (var i = 0; i < count; i = i + 1) {
+        let t = (f32(i) + 0.5) / f32(count);
+        let radius = sqrt(t) * center_coc;
+        let angle = f32(i) * 2.39996;
+        let uv = in.uv + vec2<f32>(cos(angle), sin(angle)) * radius * texel;
+        let sample_coc = circle_of_confusion(uv);
+        let w = clamp(sample_coc - radius + 1.0, 0.0, 1.0);
+        sum = sum + textureSample(t_color, s_color, uv).rgb * w;
+        weight = weight + w;
+    }
+    return vec4<f32>(sum / weight, 1.0);
+}
+
+@fragment
+fn fs_motion_blur(in: VertexOutput) -> @location(0) vec4<f32> {
+    // 这个像素上一帧在屏幕上的位置，只考虑相机的运动
+    let previous = post.prev_view_proj * vec4<f32>(world_position(in.uv), 1.0);
+    let previous_uv = vec2<f32>(previous.x / previous.w * 0.5 + 0.5, 0.5 - previous.y / previous.w * 0.5);
+    var velocity = (in.uv - previous_uv) * post.quality.zw * post.motion.x;
+    let length_px = length(velocity);
+    if (length_px > post.motion.y) {
+        velocity = velocity * (post.motion.y / length_px);
+    }
+    let step = velocity / post.quality.zw;
+    let count = i32(post.quality.y);
+    var sum = vec3<f32>(0.0);
+    for (var i = 0; i < count; i = i + 1) {
+        let t = (f32(i) + 0.5) / f32(count) - 0.5;
+        sum = sum + textureSample(t_color, s_color, in.uv + step * t).rgb;
+    }
+    return vec4<f32>(sum / f32(count), 1.0);
+}
+
+@fragment
+fn fs_color_grading(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = clamp(textureSample(t_color, s_color, in.uv).rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    // 采样到格子中心，两端的格子不会和边缘混合
+    let size = f32(textureDimensions(t_lut).x);
+    let coord = color * ((size - 1.0) / size) + 0.5 / size;
+    return vec4<f32>(textureSample(t_lut, s_lut, coord).rgb, 1.0);
+}
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+
+@fragment
+fn fs_night_vision(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_color, s_color, in.uv).rgb;
+    // 亮度放大后压回 0..1，暗处提得多、亮处不会过曝
+    let luma = dot(color, vec3<f32>(0.299, 0.587, 0.114));
+    let boosted = 1.0 - exp(-luma * post.night_vision.x);
+    // 每个像素每帧不同的颗粒噪点
+    let pixel = floor(in.uv * post.quality.zw);
+    let grain = (hash(pixel + vec2<f32>(post.night_vision.y * 17.0, post.night_vision.y * 31.0)) - 0.5) * post.night_vision.z;
+    // 镜筒的暗角
+    let offset = in.uv - vec2<f32>(0.5);
+    let vignette = clamp(1.0 - dot(offset, offset) * 1.6, 0.0, 1.0);
+    let level = clamp(boosted + grain, 0.0, 1.0) * vignette;
+    return vec4<f32>(level * vec3<f32>(0.35, 1.0, 0.45), 1.0);
+}
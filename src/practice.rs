@@ -0,0 +1,345 @@
+use std::collections::VecDeque;
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use glam::{Mat4, Quat, Vec2, Vec3};
+
+use crate::damage::{Damage, DamageType};
+use crate::ecs::{Collider, Health, Render, Transform, World};
+use crate::enemy::HEADSHOT_MULTIPLIER;
+use crate::map::{Map, TargetMotion, CELL_SIZE};
+use crate::model::BoxInstance;
+use crate::ui::UiBatch;
+
+// 练习场：地图头部 "target:" 放的靶子是没有 AI 的实体（Transform、Render、Collider、Health），
+// 子弹和投射物照常打中它们，按命中高度分成头、身体、腿三个区域。
+// 弹起的靶子打中后倒下，过一会儿再立起来；滑动的靶子一直来回移动。
+// 屏幕右边显示伤害、每秒伤害、命中率和最近几枪的散布，走上 "range_reset:" 的触发区域清零统计
+
+// 靶子的尺寸，和敌人差不多高
+const HALF_WIDTH: f32 = 0.35;
+const HEIGHT: f32 = 1.75;
+const THICKNESS: f32 = 0.05;
+// 高于这个高度的命中算头，低于 LEGS_TOP 的算腿（脚底为原点）
+const HEAD_BOTTOM: f32 = 1.45;
+const LEGS_TOP: f32 = 0.8;
+// 立起来和倒下的速度（每秒转过的比例）
+const RAISE_SPEED: f32 = 4.0;
+// 倒下后多久重新立起来（秒）
+const POP_UP_DELAY: (f32, f32) = (1.5, 3.0);
+// 滑动的速度（米/秒）
+const SLIDE_SPEED: f32 = 2.0;
+// 立起来超过这个比例时才能被打中
+const HITTABLE_RAISE: f32 = 0.9;
+// 两次命中间隔超过这个时间（秒）时重新开始计算每秒伤害
+const BURST_GAP: f32 = 2.0;
+// 计算每秒伤害的最短时间，只打了一枪时不会得到很大的数
+const MIN_BURST_TIME: f32 = 0.5;
+// 散布图里保留的弹丸数，和图的边缘对应的角度（弧度）
+const PATTERN_SIZE: usize = 32;
+const PATTERN_RANGE: f32 = 0.1;
+// 靶子的生命值只用来让命中检测找到它，每次命中后补满
+const TARGET_HEALTH: f32 = 100.0;
+
+const STAND_COLOR: [f32; 3] = [0.2, 0.2, 0.22];
+const LEGS_COLOR: [f32; 3] = [0.75, 0.68, 0.5];
+const BODY_COLOR: [f32; 3] = [0.9, 0.88, 0.8];
+const HEAD_COLOR: [f32; 3] = [0.85, 0.2, 0.15];
+
+const TITLE_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const HINT_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+const GRID_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.25];
+const DOT_COLOR: [f32; 3] = [1.0, 0.4, 0.3];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HitZone {
+    Head,
+    Body,
+    Legs,
+}
+
+impl HitZone {
+    // 命中点相对脚底的高度
+    fn from_height(height: f32) -> Self {
+        if height >= HEAD_BOTTOM {
+            HitZone::Head
+        } else if height >= LEGS_TOP {
+            HitZone::Body
+        } else {
+            HitZone::Legs
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            HitZone::Head => 0,
+            HitZone::Body => 1,
+            HitZone::Legs => 2,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            HitZone::Head => "HEAD",
+            HitZone::Body => "BODY",
+            HitZone::Legs => "LEGS",
+        }
+    }
+}
+
+// 一次打中靶子：区域和算过爆头倍数的伤害
+#[derive(Debug, Clone, Copy)]
+pub struct TargetHit {
+    pub zone: HitZone,
+    pub amount: f32,
+}
+
+// 靶子组件
+#[derive(Debug, Clone, Copy)]
+pub struct Target {
+    motion: TargetMotion,
+    // 出生的位置，滑动的靶子从这里出发
+    origin: Vec3,
+    // 立起来的程度，0 是平躺，1 是立直
+    raise: f32,
+    up: bool,
+    // 倒下的弹起靶子离重新立起来的时间
+    timer: f32,
+    // 滑动的靶子在来回一趟里的相位（弧度）
+    phase: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Stats {
+    shots: u32,
+    hits: [u32; 3],
+    damage: f32,
+    last: Option<TargetHit>,
+    // 这一轮连续射击的伤害、开始和最后一次命中的时间
+    burst_damage: f32,
+    burst_start: f32,
+    last_hit: f32,
+    time: f32,
+}
+
+impl Stats {
+    fn hit_count(&self) -> u32 {
+        self.hits.iter().sum()
+    }
+
+    fn dps(&self) -> f32 {
+        if self.burst_damage <= 0.0 {
+            return 0.0;
+        }
+        self.burst_damage / (self.last_hit - self.burst_start).max(MIN_BURST_TIME)
+    }
+}
+
+pub struct PracticeRange {
+    // 地图上没有靶子时什么都不显示
+    active: bool,
+    resets: Vec<char>,
+    stats: Stats,
+    // 最近的弹丸相对准星的偏移（弧度，x 向右、y 向上）
+    pattern: VecDeque<Vec2>,
+}
+
+impl PracticeRange {
+    pub fn new(world: &mut World, map: &Map) -> Self {
+        let facing = map.spawn_point();
+        for spawn in &map.targets {
+            let origin = map.cell_center(spawn.col, spawn.row);
+            // 靶面朝着出生点
+            let to_spawn = facing - origin;
+            let yaw = to_spawn.x.atan2(to_spawn.z);
+            let target = Target { motion: spawn.motion, origin, raise: 1.0, up: true, timer: 0.0, phase: 0.0 };
+            let entity = world.spawn();
+            world.transforms.insert(entity, Transform { position: origin, yaw });
+            world.renders.insert(entity, Render { boxes: target_boxes(1.0) });
+            world.colliders.insert(entity, target_collider());
+            world.healths.insert(entity, Health { current: TARGET_HEALTH });
+            world.targets.insert(entity, target);
+        }
+        Self { active: !map.targets.is_empty(), resets: map.range_resets.clone(), stats: Stats::default(), pattern: VecDeque::new() }
+    }
+
+    // 开了一枪（霰弹枪每发弹丸一次），aim 和 pellet 是准星和弹丸的方向
+    pub fn record_shot(&mut self, aim: Vec3, pellet: Vec3) {
+        if !self.active {
+            return;
+        }
+        self.stats.shots += 1;
+        let right = aim.cross(Vec3::Y).try_normalize().unwrap_or(Vec3::X);
+        let up = right.cross(aim);
+        self.pattern.push_back(Vec2::new(pellet.dot(right).asin(), pellet.dot(up).asin()));
+        if self.pattern.len() > PATTERN_SIZE {
+            self.pattern.pop_front();
+        }
+    }
+
+    // 打中编号为 index 的命中盒，不是靶子时返回 None 交给敌人处理
+    pub fn hit(&mut self, world: &mut World, index: usize, damage: Damage, point: Vec3) -> Option<TargetHit> {
+        let entity = world.resolve(index)?;
+        let target = world.targets.get_mut(entity)?;
+        let position = world.transforms.get(entity)?.position;
+        let zone = HitZone::from_height(point.y - position.y);
+        let headshot = damage.kind == DamageType::Bullet && zone == HitZone::Head;
+        let amount = if headshot { damage.amount * HEADSHOT_MULTIPLIER } else { damage.amount };
+        if target.motion == TargetMotion::PopUp {
+            target.up = false;
+            target.timer = POP_UP_DELAY.0 + fastrand::f32() * (POP_UP_DELAY.1 - POP_UP_DELAY.0);
+        }
+        if let Some(health) = world.healths.get_mut(entity) {
+            health.current = TARGET_HEALTH;
+        }
+
+        let hit = TargetHit { zone, amount };
+        let stats = &mut self.stats;
+        if stats.burst_damage <= 0.0 || stats.time - stats.last_hit > BURST_GAP {
+            stats.burst_damage = 0.0;
+            stats.burst_start = stats.time;
+        }
+        stats.burst_damage += amount;
+        stats.last_hit = stats.time;
+        stats.damage += amount;
+        stats.hits[zone.index()] += 1;
+        stats.last = Some(hit);
+        Some(hit)
+    }
+
+    // 走进了触发区域，是清零按钮时清零
+    pub fn trigger(&mut self, world: &mut World, id: char) {
+        if self.resets.contains(&id) {
+            println!("练习场统计已清零");
+            self.reset(world);
+        }
+    }
+
+    // 清零统计和散布，倒下的靶子全部立起来
+    fn reset(&mut self, world: &mut World) {
+        self.stats = Stats::default();
+        self.pattern.clear();
+        for (_, target) in world.targets.iter_mut() {
+            target.up = true;
+        }
+    }
+
+    pub fn update(&mut self, world: &mut World, dt: f32) {
+        if !self.active {
+            return;
+        }
+        self.stats.time += dt;
+        let mut changed = Vec::new();
+        for (entity, target) in world.targets.iter_mut() {
+            match target.motion {
+                TargetMotion::PopUp if !target.up => {
+                    target.timer -= dt;
+                    target.up = target.timer <= 0.0;
+                }
+                TargetMotion::PopUp => {}
+                TargetMotion::Slide { direction, distance } => {
+                    let length = distance as f32 * CELL_SIZE;
+                    target.phase = (target.phase + dt * SLIDE_SPEED * PI / length) % (2.0 * PI);
+                    let offset = direction.vector() * length * (0.5 - 0.5 * target.phase.cos());
+                    if let Some(transform) = world.transforms.get_mut(entity) {
+                        transform.position = target.origin + offset;
+                    }
+                }
+            }
+            let goal = if target.up { 1.0 } else { 0.0 };
+            if target.raise != goal {
+                let step = RAISE_SPEED * dt;
+                target.raise = if target.up { (target.raise + step).min(1.0) } else { (target.raise - step).max(0.0) };
+                changed.push((entity, target.raise));
+            }
+        }
+        // 转动时重新生成模型，只有基本立直时才能被打中
+        for (entity, raise) in changed {
+            if let Some(render) = world.renders.get_mut(entity) {
+                render.boxes = target_boxes(raise);
+            }
+            if raise >= HITTABLE_RAISE {
+                world.colliders.insert(entity, target_collider());
+            } else {
+                world.colliders.remove(entity);
+            }
+        }
+    }
+
+    // 屏幕右边的统计面板和散布图
+    pub fn draw(&self, ui: &mut UiBatch) {
+        if !self.active {
+            return;
+        }
+        let width = 230.0;
+        let x = ui.width() - width - 10.0;
+        let top = 160.0;
+        let scale = 2.0;
+        let line = UiBatch::line_height(scale) + 4.0;
+        let stats = &self.stats;
+        let accuracy = (stats.hit_count() * 100).checked_div(stats.shots).unwrap_or(0).min(100);
+        let mut lines = vec![
+            (TITLE_COLOR, "PRACTICE RANGE".to_string()),
+            (TEXT_COLOR, format!("DPS {:.0}", stats.dps())),
+            (TEXT_COLOR, format!("DAMAGE {:.0}", stats.damage)),
+            (TEXT_COLOR, format!("ACCURACY {}% {}/{}", accuracy, stats.hit_count(), stats.shots)),
+            (TEXT_COLOR, format!("HEAD {} BODY {} LEGS {}", stats.hits[0], stats.hits[1], stats.hits[2])),
+        ];
+        if let Some(last) = stats.last {
+            lines.push((TEXT_COLOR, format!("LAST {} {:.0}", last.zone.name(), last.amount)));
+        }
+        lines.push((HINT_COLOR, "STEP ON A PAD TO RESET".to_string()));
+
+        let size = width - 20.0;
+        let height = lines.len() as f32 * line + size + 30.0;
+        ui.rect(x, top, width, height, PANEL_COLOR);
+        let mut y = top + 10.0;
+        for (color, text) in &lines {
+            ui.text(x + 10.0, y, scale, *color, text);
+            y += line;
+        }
+
+        // 散布图：中心是准星，越新的弹丸越亮
+        let (left, y) = (x + 10.0, y + 10.0);
+        let center = Vec2::new(left + size / 2.0, y + size / 2.0);
+        ui.rect_outline(left, y, size, size, 1.0, GRID_COLOR);
+        ui.rect(center.x - 0.5, y, 1.0, size, GRID_COLOR);
+        ui.rect(left, center.y - 0.5, size, 1.0, GRID_COLOR);
+        let count = self.pattern.len();
+        for (i, offset) in self.pattern.iter().enumerate() {
+            let dot = center + Vec2::new(offset.x, -offset.y) / PATTERN_RANGE * (size / 2.0);
+            if dot.x < left || dot.x > left + size || dot.y < y || dot.y > y + size {
+                continue;
+            }
+            let alpha = 0.3 + 0.7 * (i + 1) as f32 / count as f32;
+            ui.rect(dot.x - 2.0, dot.y - 2.0, 4.0, 4.0, [DOT_COLOR[0], DOT_COLOR[1], DOT_COLOR[2], alpha]);
+        }
+    }
+}
+
+// 立起来时的命中盒，倒下时没有
+fn target_collider() -> Collider {
+    let half = Vec3::new(HALF_WIDTH, 0.0, HALF_WIDTH);
+    Collider { min: -half, max: half + Vec3::Y * HEIGHT }
+}
+
+// 底座和靶面（腿、身体、头三个区域不同颜色），靶面绕底边向后倒下
+fn target_boxes(raise: f32) -> Vec<BoxInstance> {
+    let hinge = Mat4::from_quat(Quat::from_rotation_x(-(1.0 - raise) * FRAC_PI_2));
+    let parts = [
+        (Vec3::new(0.0, LEGS_TOP / 2.0, 0.0), Vec3::new(0.4, LEGS_TOP, THICKNESS), LEGS_COLOR),
+        (Vec3::new(0.0, (LEGS_TOP + HEAD_BOTTOM) / 2.0, 0.0), Vec3::new(2.0 * HALF_WIDTH * 0.85, HEAD_BOTTOM - LEGS_TOP, THICKNESS), BODY_COLOR),
+        (Vec3::new(0.0, (HEAD_BOTTOM + HEIGHT) / 2.0, 0.0), Vec3::new(0.3, HEIGHT - HEAD_BOTTOM, THICKNESS), HEAD_COLOR),
+    ];
+    let mut boxes = vec![BoxInstance {
+        transform: Mat4::from_scale_rotation_translation(Vec3::new(0.8, 0.05, 0.3), Quat::IDENTITY, Vec3::new(0.0, 0.025, 0.0)),
+        color: STAND_COLOR,
+    }];
+    boxes.extend(parts.into_iter().map(|(center, size, color)| BoxInstance {
+        transform: hinge * Mat4::from_scale_rotation_translation(size, Quat::IDENTITY, center),
+        color,
+    }));
+    boxes
+}
+
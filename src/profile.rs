@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use instant::SystemTime;
+
+use crate::events::GameEvent;
+use crate::loadout::Loadouts;
+use crate::progression::Progression;
+
+// 玩家档案：终身统计、本地排行榜、经验天赋和出生配置，保存在 profile.json 中（所在目录见 paths.rs）
+
+const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct LifetimeStats {
+    pub kills: u64,
+    pub deaths: u64,
+    pub headshots: u64,
+    pub shots_fired: u64,
+    pub shots_hit: u64,
+    pub play_time_secs: f64,
+    pub best_wave: u32,
+    pub matches_played: u64,
+}
+
+impl LifetimeStats {
+    // 命中率（0..1）
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            0.0
+        } else {
+            self.shots_hit as f32 / self.shots_fired as f32
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub kills: u32,
+    pub deaths: u32,
+    pub wave: u32,
+    pub accuracy: f32,
+    // Unix 时间戳（秒）
+    pub timestamp: u64,
+}
+
+// 当前这一局的统计，结束时提交到排行榜
+#[derive(Debug, Clone, Default)]
+pub struct MatchStats {
+    pub kills: u32,
+    pub deaths: u32,
+    pub shots_fired: u32,
+    pub shots_hit: u32,
+    pub wave: u32,
+}
+
+impl MatchStats {
+    pub fn accuracy(&self) -> f32 {
+        if self.shots_fired == 0 {
+            0.0
+        } else {
+            self.shots_hit as f32 / self.shots_fired as f32
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Profile {
+    pub player_name: String,
+    pub stats: LifetimeStats,
+    // 地图名 -> 排行榜（按击杀数、波数排序）
+    pub leaderboards: BTreeMap<String, Vec<LeaderboardEntry>>,
+    // 已解锁的成就 id -> 解锁时间（Unix 时间戳）
+    pub achievements: BTreeMap<String, u64>,
+    // 经验和解锁的天赋
+    pub progression: Progression,
+    // 出生配置的预设和选中的是哪一套
+    pub loadouts: Loadouts,
+    #[serde(skip)]
+    pub current_match: Option<MatchStats>,
+}
+
+impl Profile {
+    pub fn load(path: &Path, player_name: &str) -> Self {
+        let mut profile = match std::fs::read_to_string(path) {
+            Ok(text) => match serde_json::from_str::<Profile>(&text) {
+                Ok(profile) => profile,
+                Err(e) => {
+                    eprintln!("档案文件 {} 解析失败，使用新档案: {}", path.display(), e);
+                    Profile::default()
+                }
+            },
+            // 第一次运行时没有档案文件
+            Err(_) => Profile::default(),
+        };
+        if profile.player_name.is_empty() {
+            profile.player_name = player_name.to_string();
+        }
+        profile
+    }
+
+    pub fn save(&self, path: &Path) {
+        match serde_json::to_string_pretty(self) {
+            Ok(text) => {
+                if let Err(e) = std::fs::write(path, text) {
+                    eprintln!("保存档案失败 {}: {}", path.display(), e);
+                }
+            }
+            Err(e) => eprintln!("档案序列化失败: {}", e),
+        }
+    }
+
+    pub fn add_play_time(&mut self, secs: f64) {
+        self.stats.play_time_secs += secs;
+    }
+
+    // 根据游戏事件更新统计，map 为当前地图名
+    pub fn record(&mut self, event: &GameEvent, map: &str) {
+        match event {
+            GameEvent::MatchStarted => {
+                self.finish_match(map);
+                self.stats.matches_played += 1;
+                self.current_match = Some(MatchStats::default());
+            }
+            GameEvent::MatchEnded => self.finish_match(map),
+            GameEvent::ShotFired => {
+                self.stats.shots_fired += 1;
+                if let Some(current) = &mut self.current_match {
+                    current.shots_fired += 1;
+                }
+            }
+            GameEvent::ShotHit { .. } => {
+                self.stats.shots_hit += 1;
+                if let Some(current) = &mut self.current_match {
+                    current.shots_hit += 1;
+                }
+            }
+            GameEvent::Kill { headshot } => {
+                self.stats.kills += 1;
+                if *headshot {
+                    self.stats.headshots += 1;
+                }
+                if let Some(current) = &mut self.current_match {
+                    current.kills += 1;
+                }
+            }
+            GameEvent::Death => {
+                self.stats.deaths += 1;
+                if let Some(current) = &mut self.current_match {
+                    current.deaths += 1;
+                }
+            }
+            GameEvent::WaveCleared { wave } => {
+                self.stats.best_wave = self.stats.best_wave.max(*wave);
+                if let Some(current) = &mut self.current_match {
+                    current.wave = current.wave.max(*wave);
+                }
+            }
+            GameEvent::DamageTaken { .. }
+            | GameEvent::TriggerEntered { .. }
+            | GameEvent::ActionPerformed { .. } => {}
+        }
+    }
+
+    // 结束当前这一局，有成绩的话写入排行榜
+    fn finish_match(&mut self, map: &str) {
+        let Some(current) = self.current_match.take() else { return };
+        if current.kills == 0 && current.wave == 0 {
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let board = self.leaderboards.entry(map.to_string()).or_default();
+        board.push(LeaderboardEntry {
+            name: self.player_name.clone(),
+            kills: current.kills,
+            deaths: current.deaths,
+            wave: current.wave,
+            accuracy: current.accuracy(),
+            timestamp,
+        });
+        board.sort_by(|a, b| b.kills.cmp(&a.kills).then(b.wave.cmp(&a.wave)));
+        board.truncate(LEADERBOARD_SIZE);
+    }
+
+    pub fn leaderboard(&self, map: &str) -> &[LeaderboardEntry] {
+        self.leaderboards.get(map).map_or(&[], |board| board.as_slice())
+    }
+}
+
+// 把秒数格式化为 "12h 34m"
+pub fn format_play_time(secs: f64) -> String {
+    let minutes = (secs / 60.0) as u64;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use crate::gpu_memory;
+use crate::ui::UiBatch;
+
+// F4 性能面板：帧时间和按类别的显存占用（见 gpu_memory.rs）
+
+// 帧时间的平滑系数，数字不会每帧乱跳
+const SMOOTHING: f32 = 0.05;
+const TEXT_COLOR: [f32; 4] = [0.9, 1.0, 0.9, 0.95];
+
+pub struct Profiler {
+    pub open: bool,
+    // 平滑后的帧时间（毫秒）
+    frame_ms: f32,
+    // 最近一段时间里最慢的一帧，慢慢回落
+    worst_ms: f32,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self { open: false, frame_ms: 0.0, worst_ms: 0.0 }
+    }
+
+    pub fn record(&mut self, dt: Duration) {
+        let ms = dt.as_secs_f32() * 1000.0;
+        self.frame_ms += (ms - self.frame_ms) * SMOOTHING;
+        self.worst_ms = ms.max(self.worst_ms - self.worst_ms * SMOOTHING);
+    }
+
+    pub fn draw(&self, ui: &mut UiBatch) {
+        if !self.open {
+            return;
+        }
+        let memory = gpu_memory::snapshot();
+        let (x, y, line) = (10.0, 40.0, 18.0);
+        ui.rect(x - 6.0, y - 6.0, 300.0, line * (memory.len() as f32 + 3.0) + 8.0, [0.0, 0.0, 0.0, 0.6]);
+        let fps = if self.frame_ms > 0.0 { 1000.0 / self.frame_ms } else { 0.0 };
+        ui.text(x, y, 2.0, TEXT_COLOR, &format!("FRAME {:.2} MS  {:.0} FPS", self.frame_ms, fps));
+        ui.text(x, y + line, 2.0, TEXT_COLOR, &format!("WORST {:.2} MS", self.worst_ms));
+        ui.text(x, y + line * 2.0, 2.0, TEXT_COLOR, &format!("GPU MEMORY {}", gpu_memory::format_bytes(gpu_memory::total_bytes())));
+        for (i, (category, count, bytes)) in memory.iter().enumerate() {
+            let text = format!("  {:<10}{:>10} {:>5}", category.name().to_uppercase(), gpu_memory::format_bytes(*bytes), count);
+            ui.text(x, y + line * (i as f32 + 3.0), 2.0, TEXT_COLOR, &text);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+
+use crate::events::GameEvent;
+use crate::weapon::WeaponPerks;
+
+// 经验和等级：击杀、打过一波、解锁成就给经验，每升一级得到一个天赋点。
+// 天赋在菜单里用天赋点解锁，保存在档案里，重生时加到武器和玩家身上
+
+// 升到下一级要的经验比上一级多这么多（1→2 级 500，2→3 级 1000……）
+const LEVEL_XP: u64 = 500;
+const KILL_XP: u64 = 100;
+const HEADSHOT_XP: u64 = 50;
+const WAVE_XP: u64 = 250;
+pub const ACHIEVEMENT_XP: u64 = 200;
+pub const MAX_RANK: u32 = 3;
+
+// 每一级的加成
+const RELOAD_PER_RANK: f32 = 0.15;
+const STAMINA_PER_RANK: f32 = 0.25;
+const MAGAZINE_PER_RANK: f32 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Perk {
+    FastReload,
+    Stamina,
+    BiggerMags,
+}
+
+impl Perk {
+    pub const ALL: [Perk; 3] = [Perk::FastReload, Perk::Stamina, Perk::BiggerMags];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Perk::FastReload => "FAST RELOAD",
+            Perk::Stamina => "STAMINA",
+            Perk::BiggerMags => "BIGGER MAGS",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Perk::FastReload => "reload 15% faster per rank",
+            Perk::Stamina => "25% more sprint stamina per rank",
+            Perk::BiggerMags => "20% bigger magazines per rank",
+        }
+    }
+
+    // 树上的前置天赋和需要的等级
+    pub fn requires(self) -> Option<(Perk, u32)> {
+        match self {
+            Perk::FastReload | Perk::Stamina => None,
+            Perk::BiggerMags => Some((Perk::FastReload, 1)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Progression {
+    pub xp: u64,
+    // 天赋 -> 等级
+    pub perks: BTreeMap<Perk, u32>,
+}
+
+impl Progression {
+    // 从 1 级开始
+    pub fn level(&self) -> u32 {
+        let mut level = 1;
+        while self.xp >= xp_for_level(level + 1) {
+            level += 1;
+        }
+        level
+    }
+
+    // 当前等级内已有的经验和升到下一级要的经验
+    pub fn level_progress(&self) -> (u64, u64) {
+        let level = self.level();
+        let start = xp_for_level(level);
+        (self.xp - start, xp_for_level(level + 1) - start)
+    }
+
+    // 加经验，返回升到的新等级（没升级时为 None）
+    pub fn award(&mut self, xp: u64) -> Option<u32> {
+        let before = self.level();
+        self.xp += xp;
+        let after = self.level();
+        (after > before).then_some(after)
+    }
+
+    pub fn rank(&self, perk: Perk) -> u32 {
+        self.perks.get(&perk).copied().unwrap_or(0)
+    }
+
+    // 还没用掉的天赋点
+    pub fn points(&self) -> u32 {
+        (self.level() - 1).saturating_sub(self.perks.values().sum())
+    }
+
+    pub fn can_unlock(&self, perk: Perk) -> bool {
+        let prerequisite = perk.requires().is_none_or(|(required, rank)| self.rank(required) >= rank);
+        self.points() > 0 && self.rank(perk) < MAX_RANK && prerequisite
+    }
+
+    // 用一个天赋点给天赋升一级
+    pub fn unlock(&mut self, perk: Perk) -> bool {
+        if !self.can_unlock(perk) {
+            return false;
+        }
+        *self.perks.entry(perk).or_insert(0) += 1;
+        true
+    }
+
+    pub fn weapon_perks(&self) -> WeaponPerks {
+        WeaponPerks {
+            reload_scale: 1.0 - RELOAD_PER_RANK * self.rank(Perk::FastReload) as f32,
+            magazine_scale: 1.0 + MAGAZINE_PER_RANK * self.rank(Perk::BiggerMags) as f32,
+        }
+    }
+
+    pub fn stamina_scale(&self) -> f32 {
+        1.0 + STAMINA_PER_RANK * self.rank(Perk::Stamina) as f32
+    }
+}
+
+// 到达 level 级要的总经验
+fn xp_for_level(level: u32) -> u64 {
+    let level = level.max(1) as u64;
+    LEVEL_XP * level * (level - 1) / 2
+}
+
+// 事件给的经验
+pub fn event_xp(event: &GameEvent) -> u64 {
+    match event {
+        GameEvent::Kill { headshot } => KILL_XP + if *headshot { HEADSHOT_XP } else { 0 },
+        GameEvent::WaveCleared { .. } => WAVE_XP,
+        _ => 0,
+    }
+}
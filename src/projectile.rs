@@ -0,0 +1,142 @@
+use glam::{Mat4, Quat, Vec3};
+
+use crate::collision::{Aabb, WallCollider};
+use crate::damage::DamageType;
+use crate::ecs::{Render, Transform, World};
+use crate::map;
+use crate::model::BoxInstance;
+use crate::weapon::WeaponSlot;
+
+// 飞行的投射物（钉枪的钉子等）：有速度、重力和寿命，每次模拟沿这一步的线段检测墙体、地面、天花板和目标，
+// 碰到就停下并报告命中。每个投射物是一个实体（位置在 Transform 里，模型是沿飞行方向的细长方块），
+// 同时存在的数量有上限，满了时替换最快消失的那个
+
+// 同时存在的投射物上限
+const MAX_PROJECTILES: usize = 256;
+// 飞这么久还没碰到东西就消失（秒）
+const LIFETIME: f32 = 4.0;
+const SIZE: Vec3 = Vec3::new(0.03, 0.03, 0.22);
+const COLOR: [f32; 3] = [0.75, 0.75, 0.7];
+
+#[derive(Debug, Clone, Copy)]
+pub struct Projectile {
+    velocity: Vec3,
+    gravity: f32,
+    life: f32,
+    damage: f32,
+    damage_type: DamageType,
+    slot: WeaponSlot,
+}
+
+// 投射物的发射参数
+#[derive(Debug, Clone, Copy)]
+pub struct Launch {
+    pub origin: Vec3,
+    pub velocity: Vec3,
+    pub gravity: f32,
+    pub damage: f32,
+    pub damage_type: DamageType,
+    // 发射它的武器的栏位，击杀提示的图标用
+    pub slot: WeaponSlot,
+}
+
+// 一次命中：target 为调用者传入的目标编号，打到墙、地面或天花板时为 None
+#[derive(Debug, Clone, Copy)]
+pub struct ProjectileHit {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub dir: Vec3,
+    pub target: Option<usize>,
+    pub damage: f32,
+    pub damage_type: DamageType,
+    pub slot: WeaponSlot,
+}
+
+pub fn spawn(world: &mut World, launch: Launch) {
+    if world.projectiles.iter().count() >= MAX_PROJECTILES {
+        if let Some((oldest, _)) = world.projectiles.iter().min_by(|(_, a), (_, b)| a.life.total_cmp(&b.life)) {
+            world.despawn(oldest);
+        }
+    }
+    let projectile = Projectile {
+        velocity: launch.velocity,
+        gravity: launch.gravity,
+        life: LIFETIME,
+        damage: launch.damage,
+        damage_type: launch.damage_type,
+        slot: launch.slot,
+    };
+    let entity = world.spawn();
+    world.transforms.insert(entity, Transform { position: launch.origin, yaw: 0.0 });
+    world.renders.insert(entity, Render { boxes: vec![model(launch.velocity)] });
+    world.projectiles.insert(entity, projectile);
+}
+
+// 所有投射物前进一步，返回这一步里的命中（没有命中时不分配）。命中或者到时间的投射物删掉
+pub fn update_system(world: &mut World, dt: f32, colliders: &[WallCollider], targets: &[(usize, Aabb)]) -> Vec<ProjectileHit> {
+    let mut hits = Vec::new();
+    let mut finished = Vec::new();
+    let World { transforms, renders, projectiles, .. } = world;
+    for (entity, projectile) in projectiles.iter_mut() {
+        let Some(transform) = transforms.get_mut(entity) else { continue };
+        projectile.life -= dt;
+        if projectile.life <= 0.0 {
+            finished.push(entity);
+            continue;
+        }
+        projectile.velocity.y -= projectile.gravity * dt;
+        let step = projectile.velocity * dt;
+        let length = step.length();
+        if length <= f32::EPSILON {
+            continue;
+        }
+        let dir = step / length;
+        match sweep(colliders, targets, transform.position, dir, length) {
+            Some((distance, normal, target)) => {
+                hits.push(ProjectileHit {
+                    point: transform.position + dir * distance,
+                    normal,
+                    dir,
+                    target,
+                    damage: projectile.damage,
+                    damage_type: projectile.damage_type,
+                    slot: projectile.slot,
+                });
+                finished.push(entity);
+            }
+            None => {
+                transform.position += step;
+                if let Some(render) = renders.get_mut(entity) {
+                    render.boxes[0] = model(projectile.velocity);
+                }
+            }
+        }
+    }
+    for entity in finished {
+        world.despawn(entity);
+    }
+    hits
+}
+
+// 沿飞行方向摆放的细长方块
+fn model(velocity: Vec3) -> BoxInstance {
+    let rotation = Quat::from_rotation_arc(Vec3::Z, velocity.try_normalize().unwrap_or(Vec3::Z));
+    BoxInstance { transform: Mat4::from_scale_rotation_translation(SIZE, rotation, Vec3::ZERO), color: COLOR }
+}
+
+// 这一步线段上最近的碰撞：距离、法向量和碰到的目标
+fn sweep(colliders: &[WallCollider], targets: &[(usize, Aabb)], origin: Vec3, dir: Vec3, length: f32) -> Option<(f32, Vec3, Option<usize>)> {
+    let surface = if dir.y < -1e-6 {
+        Some(((0.0 - origin.y) / dir.y, Vec3::Y))
+    } else if dir.y > 1e-6 {
+        Some(((map::WALL_HEIGHT - origin.y) / dir.y, Vec3::NEG_Y))
+    } else {
+        None
+    };
+    let surface = surface.filter(|(distance, _)| *distance <= length).map(|(distance, normal)| (distance, normal, None));
+    let wall = colliders.iter().filter_map(|collider| collider.raycast(origin, dir, length)).map(|hit| (hit.distance, hit.normal, None));
+    let target = targets
+        .iter()
+        .filter_map(|(id, aabb)| aabb.raycast_normal(origin, dir, length).map(|(distance, normal)| (distance, normal, Some(*id))));
+    surface.into_iter().chain(wall).chain(target).min_by(|a, b| a.0.total_cmp(&b.0))
+}
@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+
+use crate::ui::UiBatch;
+
+// F6 原始输入面板：把每一帧收到的鼠标移动画成柱状图，用来检查鼠标的输入是不是原始输入。
+// 原始输入的移动量是整数，没有被系统加速过；帧率变化时同样的手部动作得到同样的总移动量
+
+// 记录的帧数
+const HISTORY: usize = 180;
+const GRAPH_WIDTH: f32 = 360.0;
+const GRAPH_HEIGHT: f32 = 120.0;
+// 柱子的高度对应的移动量，超出的画到顶
+const GRAPH_RANGE: f32 = 40.0;
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const TEXT_COLOR: [f32; 4] = [0.9, 1.0, 0.9, 0.95];
+const X_COLOR: [f32; 4] = [1.0, 0.45, 0.35, 0.9];
+const Y_COLOR: [f32; 4] = [0.35, 0.75, 1.0, 0.9];
+const AXIS_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.3];
+
+// 一帧里收到的鼠标移动
+#[derive(Debug, Clone, Copy, Default)]
+struct Frame {
+    dx: f32,
+    dy: f32,
+    events: u32,
+    // 有不是整数的移动量，说明经过了系统的缩放或者加速
+    fractional: bool,
+}
+
+pub struct RawInputGraph {
+    pub open: bool,
+    current: Frame,
+    frames: VecDeque<(Frame, f32)>,
+}
+
+impl RawInputGraph {
+    pub fn new() -> Self {
+        Self { open: false, current: Frame::default(), frames: VecDeque::with_capacity(HISTORY) }
+    }
+
+    // 每次收到 DeviceEvent::MouseMotion 时调用
+    pub fn record(&mut self, dx: f64, dy: f64) {
+        if !self.open {
+            return;
+        }
+        self.current.dx += dx as f32;
+        self.current.dy += dy as f32;
+        self.current.events += 1;
+        self.current.fractional |= dx.fract() != 0.0 || dy.fract() != 0.0;
+    }
+
+    // 每个渲染帧结束一次，dt 是这一帧的时长（秒）
+    pub fn end_frame(&mut self, dt: f32) {
+        if !self.open {
+            return;
+        }
+        if self.frames.len() == HISTORY {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((std::mem::take(&mut self.current), dt));
+    }
+
+    pub fn draw(&self, ui: &mut UiBatch) {
+        if !self.open {
+            return;
+        }
+        let line = UiBatch::line_height(2.0);
+        let x = ui.width() - GRAPH_WIDTH - 20.0;
+        let y = 40.0;
+        ui.rect(x - 6.0, y - 6.0, GRAPH_WIDTH + 12.0, GRAPH_HEIGHT + line * 4.0 + 16.0, PANEL_COLOR);
+
+        let seconds: f32 = self.frames.iter().map(|(_, dt)| dt).sum();
+        let events: u32 = self.frames.iter().map(|(frame, _)| frame.events).sum();
+        let rate = if seconds > 0.0 { events as f32 / seconds } else { 0.0 };
+        let largest = self.frames.iter().map(|(frame, _)| frame.dx.abs().max(frame.dy.abs())).fold(0.0, f32::max);
+        let fractional = self.frames.iter().any(|(frame, _)| frame.fractional);
+        ui.text(x, y, 2.0, TEXT_COLOR, &format!("MOUSE  {:.0} EVENTS/S  MAX {:.0}", rate, largest));
+        let last = self.frames.back().map(|(frame, _)| frame.events).unwrap_or(0);
+        ui.text(x, y + line, 2.0, TEXT_COLOR, &format!("LAST FRAME {} EVENTS", last));
+        let verdict = if fractional { "SCALED INPUT (NOT RAW)" } else { "RAW COUNTS" };
+        ui.text(x, y + line * 2.0, 2.0, if fractional { X_COLOR } else { TEXT_COLOR }, verdict);
+
+        // 中线以上是正的移动量，x 和 y 各占半根柱子
+        let top = y + line * 3.0 + 4.0;
+        let middle = top + GRAPH_HEIGHT / 2.0;
+        ui.rect(x, middle, GRAPH_WIDTH, 1.0, AXIS_COLOR);
+        let bar = GRAPH_WIDTH / HISTORY as f32;
+        for (i, (frame, _)) in self.frames.iter().enumerate() {
+            let bx = x + i as f32 * bar;
+            for (value, color, offset) in [(frame.dx, X_COLOR, 0.0), (frame.dy, Y_COLOR, bar / 2.0)] {
+                let height = (value / GRAPH_RANGE).clamp(-1.0, 1.0) * GRAPH_HEIGHT / 2.0;
+                let (by, bh) = if height >= 0.0 { (middle - height, height) } else { (middle, -height) };
+                ui.rect(bx + offset, by, bar / 2.0, bh, color);
+            }
+        }
+        ui.text(x, top + GRAPH_HEIGHT + 4.0, 2.0, X_COLOR, "X");
+        ui.text(x + 20.0, top + GRAPH_HEIGHT + 4.0, 2.0, Y_COLOR, "Y");
+    }
+}
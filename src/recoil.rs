@@ -0,0 +1,50 @@
+use crate::camera::Camera;
+use crate::weapon::WeaponStats;
+
+// 后坐力：每次开枪把视角往上（和随机往左右）抬一点，之后按武器的恢复速度慢慢回到原来的位置。
+// 抬多少、恢复多快写在 data/weapons.json 里
+
+pub struct Recoil {
+    // 还没恢复的俯仰和偏航（弧度）
+    pitch: f32,
+    yaw: f32,
+    // 最近一次开枪的武器的恢复速度（弧度/秒）
+    recovery: f32,
+}
+
+impl Recoil {
+    pub fn new() -> Self {
+        Self { pitch: 0.0, yaw: 0.0, recovery: 0.0 }
+    }
+
+    // 换地图、重生时不再恢复之前的后坐力
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn kick(&mut self, stats: &WeaponStats, camera: &mut Camera) {
+        let pitch = stats.recoil_pitch.to_radians();
+        let yaw = (fastrand::f32() * 2.0 - 1.0) * stats.recoil_yaw.to_radians();
+        self.pitch += pitch;
+        self.yaw += yaw;
+        self.recovery = stats.recoil_recovery.to_radians();
+        camera.pitch += pitch;
+        camera.yaw += yaw;
+        camera.clamp_pitch();
+    }
+
+    // 以固定的速度往回转，俯仰和偏航同时恢复完
+    pub fn update(&mut self, dt: f32, camera: &mut Camera) {
+        let remaining = self.pitch.hypot(self.yaw);
+        if remaining <= f32::EPSILON {
+            return;
+        }
+        let scale = (self.recovery * dt / remaining).min(1.0);
+        let (pitch, yaw) = (self.pitch * scale, self.yaw * scale);
+        self.pitch -= pitch;
+        self.yaw -= yaw;
+        camera.pitch -= pitch;
+        camera.yaw -= yaw;
+        camera.clamp_pitch();
+    }
+}
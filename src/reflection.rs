@@ -0,0 +1,247 @@
+use glam::{Mat4, Vec3};
+
+use crate::bindings::{Environment, FrameBinding, FrameUniform, Layouts};
+use crate::camera::CameraUniform;
+use crate::gpu_memory;
+use crate::lighting::Lighting;
+use crate::map::{self, Map};
+use crate::model::{self, Model};
+use crate::texture;
+
+// 环境反射探针：加载地图时在出生点附近把静态场景渲染进一个小立方体贴图，
+// 有光泽的表面（车漆、车窗、镀铬）按反射方向采样。采样前用房间的包围盒做视差校正：
+// 先求反射光线与包围盒的交点，再从探针位置看向这个交点，近处的表面不会像在无限远处
+
+// 立方体贴图每个面的边长
+const PROBE_SIZE: u32 = 64;
+// 探针离地的高度
+const PROBE_HEIGHT: f32 = 2.0;
+// 拍摄时的裁剪面
+const CAPTURE_NEAR: f32 = 0.1;
+const CAPTURE_FAR: f32 = 100.0;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct ProbeUniform {
+    position: [f32; 4],
+    box_min: [f32; 4],
+    box_max: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for ProbeUniform {}
+unsafe impl bytemuck::Zeroable for ProbeUniform {}
+
+fn create_cube_texture(device: &wgpu::Device, size: u32, format: wgpu::TextureFormat, label: &str) -> gpu_memory::Tracked<wgpu::Texture> {
+    gpu_memory::texture(device, gpu_memory::Category::Textures, &wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d { width: size, height: size, depth_or_array_layers: 6 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    })
+}
+
+pub struct ReflectionProbe {
+    cube: gpu_memory::Tracked<wgpu::Texture>,
+    cube_view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    uniform_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    capture_pipeline: wgpu::RenderPipeline,
+    depth_view: wgpu::TextureView,
+    // 拍摄时每帧绑定里的环境贴图。立方体贴图本身正在被渲染，不能同时被采样，换成一个全黑的
+    black_view: wgpu::TextureView,
+    // depth_view 和 black_view 的纹理
+    _depth: gpu_memory::Tracked<wgpu::Texture>,
+    _black: gpu_memory::Tracked<wgpu::Texture>,
+}
+
+fn cube_view(cube: &wgpu::Texture) -> wgpu::TextureView {
+    cube.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::Cube),
+        ..Default::default()
+    })
+}
+
+impl ReflectionProbe {
+    pub fn new(
+        device: &wgpu::Device,
+        pipeline_layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let cube = create_cube_texture(device, PROBE_SIZE, format, "reflection_probe");
+        let black = create_cube_texture(device, 1, format, "reflection_probe_black");
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform_buffer = gpu_memory::buffer_init(device, gpu_memory::Category::Frame, &wgpu::util::BufferInitDescriptor {
+            label: Some("reflection_probe_uniform"),
+            contents: bytemuck::cast_slice(&[ProbeUniform { position: [0.0; 4], box_min: [0.0; 4], box_max: [0.0; 4] }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let depth = gpu_memory::texture(device, gpu_memory::Category::Textures, &wgpu::TextureDescriptor {
+            label: Some("reflection_probe_depth"),
+            size: wgpu::Extent3d { width: PROBE_SIZE, height: PROBE_SIZE, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: texture::Texture::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let depth_view = depth.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self {
+            cube_view: cube_view(&cube),
+            cube,
+            sampler,
+            uniform_buffer,
+            capture_pipeline: create_capture_pipeline(device, pipeline_layout, shader, format),
+            depth_view,
+            black_view: cube_view(&black),
+            _depth: depth,
+            _black: black,
+        }
+    }
+
+    // 场景的每帧绑定组引用探针的立方体贴图
+    pub fn environment(&self) -> Environment<'_> {
+        Environment { view: &self.cube_view, sampler: &self.sampler, probe: &self.uniform_buffer }
+    }
+
+    // 把静态场景渲染进立方体贴图，加载地图后调用一次（在光照加载了这张地图之后）
+    #[allow(clippy::too_many_arguments)]
+    pub fn capture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layouts: &Layouts,
+        map: &Map,
+        material_bind_group: &wgpu::BindGroup,
+        object_bind_group: &wgpu::BindGroup,
+        models: &[Model],
+        clear_color: wgpu::Color,
+        lighting: &Lighting,
+    ) {
+        let position = map.spawn_point() + Vec3::Y * PROBE_HEIGHT;
+        // 整张地图作为房间的包围盒
+        let half = Vec3::new(map.width as f32, 0.0, map.height as f32) * (map::CELL_SIZE / 2.0);
+        let uniform = ProbeUniform {
+            position: position.extend(1.0).to_array(),
+            box_min: Vec3::new(-half.x, 0.0, -half.z).extend(0.0).to_array(),
+            box_max: Vec3::new(half.x, map::WALL_HEIGHT, half.z).extend(0.0).to_array(),
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+        // 与场景相同的反向深度：近、远裁剪面对调
+        let projection = Mat4::perspective_lh(std::f32::consts::FRAC_PI_2, 1.0, CAPTURE_FAR, CAPTURE_NEAR);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Reflection Capture Encoder"),
+        });
+        // 六个面各自的每帧绑定，光照的资源换地图时会重新创建，所以每次拍摄时现建
+        let black_environment = Environment { view: &self.black_view, sampler: &self.sampler, probe: &self.uniform_buffer };
+        for (face, (forward, up)) in face_directions().into_iter().enumerate() {
+            let frame = FrameBinding::new(device, layouts, &black_environment, &lighting.resources(), &format!("reflection_face_frame_{}", face));
+            let view_proj = projection * Mat4::look_at_lh(position, position + forward, up);
+            let uniform = FrameUniform::new(CameraUniform::from_view_proj(view_proj, position), 0.0, lighting.capture_uniform());
+            queue.write_buffer(&frame.buffer, 0, bytemuck::cast_slice(&[uniform]));
+
+            let view = self.cube.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_array_layer: face as u32,
+                array_layer_count: Some(1),
+                ..Default::default()
+            });
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Reflection Capture Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Clear(clear_color), store: true },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Clear(texture::Texture::DEPTH_CLEAR), store: true }),
+                    stencil_ops: None,
+                }),
+            });
+            render_pass.set_pipeline(&self.capture_pipeline);
+            render_pass.set_bind_group(0, &frame.bind_group, &[]);
+            render_pass.set_bind_group(1, material_bind_group, &[]);
+            render_pass.set_bind_group(2, object_bind_group, &[]);
+            for model in models {
+                model.draw(&mut render_pass);
+            }
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+        println!("反射探针: ({:.1}, {:.1}, {:.1})", position.x, position.y, position.z);
+    }
+}
+
+// 立方体贴图六个面（+X -X +Y -Y +Z -Z）的朝向和上方向。贴图的采样约定是左手系，
+// 所以用左手系的观察矩阵，画出来的图不需要再翻转
+fn face_directions() -> [(Vec3, Vec3); 6] {
+    [
+        (Vec3::X, Vec3::Y),
+        (Vec3::NEG_X, Vec3::Y),
+        (Vec3::Y, Vec3::NEG_Z),
+        (Vec3::NEG_Y, Vec3::Z),
+        (Vec3::Z, Vec3::Y),
+        (Vec3::NEG_Z, Vec3::Y),
+    ]
+}
+
+// 与场景管线相同，但左手系的投影把三角形镜像了，正面的绕序变成顺时针
+fn create_capture_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Reflection Capture Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[model::ModelVertex::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Cw,
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::Texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: texture::Texture::DEPTH_COMPARE,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
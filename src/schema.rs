@@ -0,0 +1,42 @@
+use serde::de::DeserializeOwned;
+
+// 数据文件（地图、data/enemies.json、data/weapons.json）的格式版本和出错时的提示。
+// 每个文件都写明自己的格式版本，比程序支持的版本新时拒绝读取；出错时给出行号和那一行的内容
+
+#[derive(serde::Deserialize)]
+struct Versioned {
+    version: Option<u32>,
+}
+
+// 文件的格式版本是否能读。没有写版本的旧文件按第 1 版处理
+pub fn check_version(version: Option<u32>, supported: u32) -> Result<(), String> {
+    match version {
+        None => Ok(()),
+        Some(0) => Err("invalid format version 0".to_string()),
+        Some(version) if version > supported => {
+            Err(format!("format version {} is newer than the supported version {}", version, supported))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+// 出错那一行的内容，column 有值时在下面用 ^ 标出位置（都从 1 开始）
+pub fn line_context(text: &str, line: usize, column: Option<usize>) -> String {
+    let Some(content) = text.lines().nth(line.saturating_sub(1)) else {
+        return String::new();
+    };
+    let mut context = format!("\n    {}", content);
+    if let Some(column) = column {
+        let indent: String = content.chars().take(column.saturating_sub(1)).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+        context.push_str(&format!("\n    {}^", indent));
+    }
+    context
+}
+
+// 读取带 "version" 字段的 JSON 数据文件
+pub fn parse_json<T: DeserializeOwned>(text: &str, supported: u32) -> Result<T, String> {
+    let with_context = |e: serde_json::Error| format!("{}{}", e, line_context(text, e.line(), Some(e.column())));
+    let versioned: Versioned = serde_json::from_str(text).map_err(with_context)?;
+    check_version(versioned.version, supported)?;
+    serde_json::from_str(text).map_err(with_context)
+}
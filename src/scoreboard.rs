@@ -0,0 +1,57 @@
+use crate::net::Score;
+use crate::ui::UiBatch;
+
+// 联机时按住计分板键（默认 Tab）显示的计分板：每个玩家这局的击杀、死亡和延迟。
+// 击杀和死亡由主机统计（见 net.rs），和玩家列表一起定期发给客户端
+
+const WIDTH: f32 = 620.0;
+const SCALE: f32 = 2.0;
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.7];
+const TITLE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.95];
+const TEXT_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 0.95];
+const DIM_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 0.9];
+const LOCAL_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 0.95];
+const LOCAL_ROW_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 0.12];
+// 各列的左边（相对面板）
+const KILLS_X: f32 = 330.0;
+const DEATHS_X: f32 = 420.0;
+const PING_X: f32 = 520.0;
+
+// scores 已经排好序，local 是自己的编号，用来高亮自己那一行
+pub fn draw(ui: &mut UiBatch, title: &str, scores: &[Score], local: Option<u32>) {
+    let row = UiBatch::line_height(SCALE) + 8.0;
+    let height = row * (scores.len().max(1) as f32 + 2.0) + 20.0;
+    let x = ((ui.width() - WIDTH) / 2.0).round();
+    let y = ((ui.height() - height) / 3.0).round();
+    ui.rect(x, y, WIDTH, height, PANEL_COLOR);
+    ui.text_centered(x + WIDTH / 2.0, y + 10.0, SCALE, TITLE_COLOR, title);
+
+    let mut line_y = y + 10.0 + row;
+    let columns = [(16.0, "PLAYER"), (KILLS_X, "KILLS"), (DEATHS_X, "DEATHS"), (PING_X, "PING")];
+    for (column_x, label) in columns {
+        ui.text(x + column_x, line_y, SCALE, DIM_COLOR, label);
+    }
+    line_y += row;
+    if scores.is_empty() {
+        ui.text(x + 16.0, line_y, SCALE, DIM_COLOR, "waiting for the host...");
+    }
+    for score in scores {
+        let own = Some(score.player_id) == local;
+        if own {
+            ui.rect(x + 8.0, line_y - 4.0, WIDTH - 16.0, row, LOCAL_ROW_COLOR);
+        }
+        let color = if own { LOCAL_COLOR } else { TEXT_COLOR };
+        let name: String = score.name.to_uppercase().chars().take(24).collect();
+        let ping = match score.ping_ms {
+            _ if score.host => "HOST".to_string(),
+            _ if score.bot => "BOT".to_string(),
+            Some(ping_ms) => ping_ms.to_string(),
+            None => "-".to_string(),
+        };
+        ui.text(x + 16.0, line_y, SCALE, color, &name);
+        ui.text(x + KILLS_X, line_y, SCALE, color, &score.kills.to_string());
+        ui.text(x + DEATHS_X, line_y, SCALE, color, &score.deaths.to_string());
+        ui.text(x + PING_X, line_y, SCALE, color, &ping);
+        line_y += row;
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+use crate::map;
+use crate::paths;
+
+// 联机主机的比赛规则，放在设置目录的 server.toml 里，没有这个文件时用默认值：
+//
+//   rotation = ["parking_garage", "my_map"]   # 换图投票的轮换列表，空的时候用自带的和 mod 的地图
+//   mode = "sandbox"                          # 局域网广播时公布的模式
+//   time_limit_minutes = 10                   # 一局的时间，0 为不限
+//   score_limit = 20                          # 有人击杀数到了就结束，0 为不限
+//   friendly_fire = true                      # 玩家之间能不能互相伤害，关掉时一起打机器人
+//   bot_fill = 0                              # 人数不够时用机器人补到这么多人
+//   allowed_weapons = ["rifle", "pistol"]     # 出生配置能选的武器，空的时候都能选
+//   admin_tokens = ["change-me"]              # POST /admin/reload 要带 Authorization: Bearer <token>
+//
+// 创建主机时读取，改了以后可以用 POST /admin/reload 重新加载，时间和击杀上限、友军伤害、机器人数和允许的武器马上生效，
+// 轮换列表和模式从下一局开始生效
+
+pub const FILE_NAME: &str = "server.toml";
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+// 写错的键直接报错，不然改了规则却没生效也看不出来
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub rotation: Vec<String>,
+    pub mode: String,
+    pub time_limit_minutes: f32,
+    pub score_limit: u32,
+    pub friendly_fire: bool,
+    pub bot_fill: u32,
+    pub allowed_weapons: Vec<String>,
+    #[serde(skip_serializing)]
+    pub admin_tokens: Vec<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            rotation: Vec::new(),
+            mode: "sandbox".to_string(),
+            time_limit_minutes: 10.0,
+            score_limit: 20,
+            friendly_fire: true,
+            bot_fill: 0,
+            allowed_weapons: Vec::new(),
+            admin_tokens: Vec::new(),
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut config: ServerConfig = toml::from_str(text).map_err(|e| e.to_string())?;
+        if !config.time_limit_minutes.is_finite() || config.time_limit_minutes < 0.0 {
+            return Err(format!("time_limit_minutes must be 0 or more, got {}", config.time_limit_minutes));
+        }
+        if config.mode.trim().is_empty() {
+            return Err("mode must not be empty".to_string());
+        }
+        if config.bot_fill > crate::net::MAX_PLAYERS {
+            return Err(format!("bot_fill must be at most {}", crate::net::MAX_PLAYERS));
+        }
+        // 加载不了的地图不放进轮换，免得投出来以后所有人都卡住
+        config.rotation.retain(|name| match map::Map::load(name) {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("{} 里的地图 {} 无法加载，不放进轮换: {}", FILE_NAME, name, e);
+                false
+            }
+        });
+        config.admin_tokens.retain(|token| !token.is_empty());
+        Ok(config)
+    }
+
+    pub fn time_limit(&self) -> Option<Duration> {
+        (self.time_limit_minutes > 0.0).then(|| Duration::from_secs_f32(self.time_limit_minutes * 60.0))
+    }
+
+    pub fn score_limit(&self) -> Option<u32> {
+        (self.score_limit > 0).then_some(self.score_limit)
+    }
+
+    // Authorization 请求头里的 token 是不是管理员的
+    #[cfg(feature = "http-server")]
+    pub fn is_admin(&self, authorization: Option<&str>) -> bool {
+        let Some(token) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else { return false };
+        self.admin_tokens.iter().any(|admin| admin == token.trim())
+    }
+}
+
+static CURRENT: OnceLock<RwLock<Arc<ServerConfig>>> = OnceLock::new();
+
+fn current_lock() -> &'static RwLock<Arc<ServerConfig>> {
+    CURRENT.get_or_init(|| RwLock::new(Arc::new(load().unwrap_or_else(|e| {
+        eprintln!("{}，使用默认的比赛规则", e);
+        ServerConfig::default()
+    }))))
+}
+
+pub fn current() -> Arc<ServerConfig> {
+    current_lock().read().unwrap().clone()
+}
+
+// 没有文件时是默认规则，文件有错时返回错误
+fn load() -> Result<ServerConfig, String> {
+    let path = paths::server_config();
+    match std::fs::read_to_string(&path) {
+        Ok(text) => ServerConfig::parse(&text).map_err(|e| format!("{} 解析失败: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ServerConfig::default()),
+        Err(e) => Err(format!("无法读取 {}: {}", path.display(), e)),
+    }
+}
+
+// 重新读取 server.toml，出错时保留原来的规则
+#[cfg(feature = "http-server")]
+pub fn reload() -> Result<Arc<ServerConfig>, String> {
+    let config = Arc::new(load()?);
+    *current_lock().write().unwrap() = config.clone();
+    println!("已重新加载比赛规则 {}", paths::server_config().display());
+    Ok(config)
+}
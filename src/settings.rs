@@ -0,0 +1,228 @@
+use std::path::Path;
+
+use crate::camera;
+use crate::casings;
+use crate::outline;
+
+// 游戏设置（画面内容等），保存在 settings.json 中（所在目录见 paths.rs）
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Settings {
+    // 关闭后没有血迹、血雾和碎尸
+    pub gore: bool,
+    // 相机的近、远裁剪面（米）
+    pub near_plane: f32,
+    pub far_plane: f32,
+    // 后处理：景深（对焦到屏幕中心看着的物体）和相机运动模糊，默认关闭
+    pub depth_of_field: bool,
+    pub motion_blur: bool,
+    // 平移和受伤时画面歪一下、附近爆炸时画面震动，容易晕的玩家可以关掉
+    pub camera_tilt: bool,
+    // 燃烧、僵直、重生保护时屏幕边缘发光
+    pub status_overlays: bool,
+    // 第三人称相机贴墙时收放的弹簧系数，只能在设置文件里改
+    pub camera_spring: f32,
+    pub effect_quality: EffectQuality,
+    // 多光源的渲染方式
+    pub lighting: LightingPath,
+    // 帧率上限，游戏逻辑的速度不受影响
+    pub frame_limit: FrameLimit,
+    // 触屏时显示虚拟摇杆和按钮
+    pub touch_controls: bool,
+    // 鼠标平滑和鼠标加速，默认关闭（原始输入）
+    pub mouse_smoothing: bool,
+    pub mouse_acceleration: bool,
+    // 鼠标和右摇杆的视角灵敏度、反转垂直方向
+    pub look: camera::LookSensitivity,
+    // 手柄的辅助瞄准强度；用鼠标时默认不辅助
+    pub aim_assist: AimAssistLevel,
+    pub mouse_aim_assist: bool,
+    // 准星对着的可交互物体和被标记的敌人的描边，颜色只能在设置文件里改
+    pub outlines: outline::OutlineColors,
+    // 地上最多留几个弹壳，0 为落地就消失
+    pub shell_casings: usize,
+}
+
+// 后处理的采样数
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EffectQuality {
+    Low,
+    Medium,
+    High,
+}
+
+impl EffectQuality {
+    pub fn next(self) -> Self {
+        match self {
+            EffectQuality::Low => EffectQuality::Medium,
+            EffectQuality::Medium => EffectQuality::High,
+            EffectQuality::High => EffectQuality::Low,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EffectQuality::Low => "LOW",
+            EffectQuality::Medium => "MEDIUM",
+            EffectQuality::High => "HIGH",
+        }
+    }
+}
+
+// forward 每个像素遍历全部光源；clustered 先按屏幕分块和深度分层把光源分到簇里，只算自己簇里的
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LightingPath {
+    Forward,
+    Clustered,
+}
+
+impl LightingPath {
+    pub fn next(self) -> Self {
+        match self {
+            LightingPath::Forward => LightingPath::Clustered,
+            LightingPath::Clustered => LightingPath::Forward,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LightingPath::Forward => "FORWARD",
+            LightingPath::Clustered => "CLUSTERED",
+        }
+    }
+}
+
+// display 跟随显示器的刷新率，unlimited 不限制
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameLimit {
+    Display,
+    Unlimited,
+    Fps60,
+    Fps120,
+    Fps144,
+    Fps240,
+}
+
+impl FrameLimit {
+    pub fn next(self) -> Self {
+        match self {
+            FrameLimit::Display => FrameLimit::Fps60,
+            FrameLimit::Fps60 => FrameLimit::Fps120,
+            FrameLimit::Fps120 => FrameLimit::Fps144,
+            FrameLimit::Fps144 => FrameLimit::Fps240,
+            FrameLimit::Fps240 => FrameLimit::Unlimited,
+            FrameLimit::Unlimited => FrameLimit::Display,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FrameLimit::Display => "DISPLAY",
+            FrameLimit::Unlimited => "UNLIMITED",
+            FrameLimit::Fps60 => "60",
+            FrameLimit::Fps120 => "120",
+            FrameLimit::Fps144 => "144",
+            FrameLimit::Fps240 => "240",
+        }
+    }
+}
+
+// 辅助瞄准的强度，具体的数值在 aim_assist.rs
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AimAssistLevel {
+    Off,
+    Low,
+    Medium,
+    High,
+}
+
+impl AimAssistLevel {
+    pub fn next(self) -> Self {
+        match self {
+            AimAssistLevel::Off => AimAssistLevel::Low,
+            AimAssistLevel::Low => AimAssistLevel::Medium,
+            AimAssistLevel::Medium => AimAssistLevel::High,
+            AimAssistLevel::High => AimAssistLevel::Off,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AimAssistLevel::Off => "OFF",
+            AimAssistLevel::Low => "LOW",
+            AimAssistLevel::Medium => "MEDIUM",
+            AimAssistLevel::High => "HIGH",
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            gore: true,
+            near_plane: camera::DEFAULT_NEAR,
+            far_plane: camera::DEFAULT_FAR,
+            depth_of_field: false,
+            motion_blur: false,
+            camera_tilt: true,
+            status_overlays: true,
+            camera_spring: camera::DEFAULT_BOOM_SPRING,
+            effect_quality: EffectQuality::Medium,
+            lighting: LightingPath::Clustered,
+            frame_limit: FrameLimit::Display,
+            touch_controls: true,
+            mouse_smoothing: false,
+            mouse_acceleration: false,
+            look: camera::LookSensitivity::default(),
+            aim_assist: AimAssistLevel::Medium,
+            mouse_aim_assist: false,
+            outlines: outline::OutlineColors::default(),
+            shell_casings: casings::DEFAULT_LIMIT,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(text) => match serde_json::from_str::<Settings>(&text) {
+                Ok(settings) => settings.validated(),
+                Err(e) => {
+                    eprintln!("设置文件 {} 解析失败，使用默认设置: {}", path.display(), e);
+                    Settings::default()
+                }
+            },
+            Err(_) => Settings::default(),
+        }
+    }
+
+    // 裁剪面必须为正，远裁剪面在近裁剪面之外，否则用默认值
+    fn validated(mut self) -> Self {
+        if !(self.near_plane > 0.0 && self.far_plane > self.near_plane) {
+            eprintln!("裁剪面设置不合法 (near {}, far {})，使用默认值", self.near_plane, self.far_plane);
+            self.near_plane = camera::DEFAULT_NEAR;
+            self.far_plane = camera::DEFAULT_FAR;
+        }
+        if !(self.camera_spring > 0.0 && self.camera_spring.is_finite()) {
+            eprintln!("相机弹簧系数 {} 不合法，使用默认值", self.camera_spring);
+            self.camera_spring = camera::DEFAULT_BOOM_SPRING;
+        }
+        self
+    }
+
+    pub fn save(&self, path: &Path) {
+        match serde_json::to_string_pretty(self) {
+            Ok(text) => {
+                if let Err(e) = std::fs::write(path, text) {
+                    eprintln!("保存设置失败 {}: {}", path.display(), e);
+                }
+            }
+            Err(e) => eprintln!("设置序列化失败: {}", e),
+        }
+    }
+}
@@ -1,70 +1,222 @@
-// Vertex shader
-
-struct CameraUniform {
-    view_proj: mat4x4<f32>,
-    view_position: vec4<f32>,
-};
-
-@group(0) @binding(0)
-var<uniform> camera: CameraUniform;
-
-struct VertexInput {
-    @location(0) position: vec3<f32>,
-    @location(1) color: vec3<f32>,
-    @location(2) tex_coords: vec2<f32>,
-    @location(3) model_type: f32,
-};
-
-struct VertexOutput {
-    @builtin(position) clip_position: vec4<f32>,
-    @location(0) color: vec3<f32>,
-    @location(1) tex_coords: vec2<f32>,
-    @location(2) model_type: f32,
-};
-
-// 添加一个新的 uniform 缓冲区用于墙体颜色
-struct WallColor {
-    color: vec3<f32>,
-    _padding: f32,
-};
-
-@group(1) @binding(0)
-var<uniform> wall_color: WallColor;
-
-// 将纹理绑定移到条件判断外部
-@group(2) @binding(0)
-var t_diffuse: texture_2d<f32>;
-@group(2) @binding(1)
-var s_diffuse: sampler;
-
-@vertex
-fn vs_main(
-    model: VertexInput,
-) -> VertexOutput {
-    var out: VertexOutput;
-    out.clip_position = camera.view_proj * vec4<f32>(model.position, 1.0);
-    out.color = model.color;
-    out.tex_coords = model.tex_coords;
-    out.model_type = model.model_type;
-    return out;
-}
-
-@fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    // 修改着色器逻辑，避免条件性纹理采样
-    var color = in.color;
-    var alpha = 1.0;
-    
-    // 对所有片段都进行纹理采样，但只在需要时使用结果
-    let tex_color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
-    
-    if (in.model_type > 0.5) {
-        color = wall_color.color;
-        if (tex_color.a > 0.1) {
-            color = color * tex_color.rgb;
-            alpha = tex_color.a;
-        }
-    }
-    
-    return vec4<f32>(color, alpha);
+// Vertex shader
+
+// 第 0 组：每帧的数据
+// 光照参数，见 lighting.rs
+struct Lighting {
+    // 视线方向
+    forward: vec4<f32>,
+    // x 近裁剪面 y ln(远/近) zw 画面尺寸（像素）
+    clusters: vec4<f32>,
+    // 簇的数量（xyz）和每簇在列表里占的长度
+    grid: vec4<u32>,
+    ambient: f32,
+    light_count: u32,
+    clustered: u32,
+    // 是否使用光照贴图
+    baked: u32,
+    // 光照贴图覆盖的范围：最小角（w 为亮度的量程）和尺寸
+    lightmap_min: vec4<f32>,
+    lightmap_size: vec4<f32>,
+};
+
+struct FrameUniform {
+    view_proj: mat4x4<f32>,
+    view_position: vec4<f32>,
+    time: f32,
+    lighting: Lighting,
+};
+
+@group(0) @binding(0)
+var<uniform> frame: FrameUniform;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) tex_coords: vec2<f32>,
+    @location(3) model_type: f32,
+    // 网格构建器给出的面法向量，为零时在片段着色器里用屏幕空间导数求
+    @location(4) normal: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) model_type: f32,
+    @location(3) world_position: vec3<f32>,
+    @location(4) normal: vec3<f32>,
+};
+
+// 环境反射探针：立方体贴图和用于视差校正的房间包围盒，也在每帧的组里
+struct ReflectionProbe {
+    position: vec4<f32>,
+    box_min: vec4<f32>,
+    box_max: vec4<f32>,
+};
+
+@group(0) @binding(1)
+var t_environment: texture_cube<f32>;
+@group(0) @binding(2)
+var s_environment: sampler;
+@group(0) @binding(3)
+var<uniform> probe: ReflectionProbe;
+
+// 点光源和分簇的结果：每簇先是光源数量，后面是光源的下标
+struct PointLight {
+    position: vec3<f32>,
+    radius: f32,
+    color: vec3<f32>,
+    intensity: f32,
+};
+
+@group(0) @binding(4)
+var<storage, read> lights: array<PointLight>;
+@group(0) @binding(5)
+var<storage, read> clusters: array<u32>;
+// 烘焙的光照贴图：六个方向的辐照度在 y 方向上依次叠放
+@group(0) @binding(6)
+var t_lightmap: texture_3d<f32>;
+@group(0) @binding(7)
+var s_lightmap: sampler;
+
+// 第 1 组：材质的纹理和参数（墙体颜色等）
+struct MaterialUniform {
+    color: vec4<f32>,
+};
+
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+@group(1) @binding(2)
+var<uniform> material: MaterialUniform;
+
+// 第 2 组：物体的模型矩阵和颜色
+struct ObjectUniform {
+    model: mat4x4<f32>,
+    color: vec4<f32>,
+};
+
+@group(2) @binding(0)
+var<uniform> object: ObjectUniform;
+
+// 反射光线与包围盒的交点，返回从探针看向交点的方向
+fn parallax_correct(position: vec3<f32>, direction: vec3<f32>) -> vec3<f32> {
+    let to_max = (probe.box_max.xyz - position) / direction;
+    let to_min = (probe.box_min.xyz - position) / direction;
+    let far = max(to_max, to_min);
+    let distance = min(min(far.x, far.y), far.z);
+    return position + direction * distance - probe.position.xyz;
+}
+
+// 一个点光源的漫反射：到半径处平滑地衰减到 0
+fn point_light(light: PointLight, position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let to_light = light.position - position;
+    let distance = length(to_light);
+    let window = clamp(1.0 - pow(distance / light.radius, 4.0), 0.0, 1.0);
+    let falloff = window * window / (1.0 + distance * distance / 9.0);
+    let lambert = max(dot(normal, to_light / max(distance, 1e-4)), 0.0);
+    return light.color * light.intensity * falloff * lambert;
+}
+
+// 光照贴图里某个方向的辐照度；y 限制在这个方向的几层里，不会混到相邻方向
+fn lightmap_face(uvw: vec3<f32>, face: u32) -> vec3<f32> {
+    let layers = f32(textureDimensions(t_lightmap).y) / 6.0;
+    let y = (clamp(uvw.y * layers, 0.5, layers - 0.5) + f32(face) * layers) / (layers * 6.0);
+    return textureSampleLevel(t_lightmap, s_lightmap, vec3<f32>(uvw.x, y, uvw.z), 0.0).rgb;
+}
+
+// 烘焙的静态光照：沿法向量稍微挪开表面再采样，六个方向按法向量各分量的平方混合
+fn baked_light(position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let params = frame.lighting;
+    let uvw = (position + normal * 0.25 - params.lightmap_min.xyz) / params.lightmap_size.xyz;
+    let weight = normal * normal;
+    var total = vec3<f32>(0.0);
+    total = total + weight.x * lightmap_face(uvw, select(1u, 0u, normal.x > 0.0));
+    total = total + weight.y * lightmap_face(uvw, select(3u, 2u, normal.y > 0.0));
+    total = total + weight.z * lightmap_face(uvw, select(5u, 4u, normal.z > 0.0));
+    return total * params.lightmap_min.w;
+}
+
+// 环境光加上各光源；分簇时只遍历像素所在簇的光源
+fn lighting(position: vec3<f32>, normal: vec3<f32>, pixel: vec2<f32>) -> vec3<f32> {
+    let params = frame.lighting;
+    var total = vec3<f32>(params.ambient);
+    if (params.baked != 0u) {
+        total = total + baked_light(position, normal);
+    }
+    if (params.clustered == 0u) {
+        for (var i = 0u; i < params.light_count; i = i + 1u) {
+            total = total + point_light(lights[i], position, normal);
+        }
+        return total;
+    }
+    let depth = max(dot(position - frame.view_position.xyz, params.forward.xyz), params.clusters.x);
+    let slice = min(u32(max(log(depth / params.clusters.x) / params.clusters.y * f32(params.grid.z), 0.0)), params.grid.z - 1u);
+    let tile = min(vec2<u32>(pixel / params.clusters.zw * vec2<f32>(params.grid.xy)), params.grid.xy - 1u);
+    let base = ((slice * params.grid.y + tile.y) * params.grid.x + tile.x) * params.grid.w;
+    let count = clusters[base];
+    for (var i = 0u; i < count; i = i + 1u) {
+        total = total + point_light(lights[clusters[base + 1u + i]], position, normal);
+    }
+    return total;
+}
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let world_position = object.model * vec4<f32>(model.position, 1.0);
+    out.clip_position = frame.view_proj * world_position;
+    out.color = model.color * object.color.rgb;
+    out.tex_coords = model.tex_coords;
+    out.model_type = model.model_type;
+    out.world_position = world_position.xyz;
+    out.normal = (object.model * vec4<f32>(model.normal, 0.0)).xyz;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // 修改着色器逻辑，避免条件性纹理采样
+    var color = in.color;
+    var alpha = 1.0;
+    
+    // 对所有片段都进行纹理采样，但只在需要时使用结果
+    let tex_color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+
+    // 环境反射同样每个片段都采样。顶点没有法向量时由屏幕空间导数求出，都翻到朝向相机的一面
+    let view_dir = normalize(frame.view_position.xyz - in.world_position);
+    let derived_normal = normalize(cross(dpdx(in.world_position), dpdy(in.world_position)));
+    let has_normal = dot(in.normal, in.normal) > 0.25;
+    let face_normal = select(derived_normal, normalize(in.normal), has_normal);
+    let normal = select(face_normal, -face_normal, dot(face_normal, view_dir) < 0.0);
+    let reflected = reflect(-view_dir, normal);
+    let env_color = textureSample(t_environment, s_environment, parallax_correct(in.world_position, reflected)).rgb;
+    
+    if (in.model_type > 3.5) {
+        // 有光泽的表面：反射强度放在 tex_coords.x 中，斜着看时更强（菲涅尔），为 0 时没有反射
+        let reflectivity = in.tex_coords.x;
+        let fresnel = pow(1.0 - max(dot(normal, view_dir), 0.0), 5.0);
+        color = mix(in.color, env_color, reflectivity * (1.0 + (1.0 - reflectivity) * fresnel));
+    } else if (in.model_type > 2.5) {
+        // 半透明纯色（玻璃、积水等）：不透明度放在 tex_coords.x 中
+        alpha = in.tex_coords.x;
+    } else if (in.model_type > 1.5) {
+        // 镂空材质（贴花图集、铁丝网、格栅）：顶点颜色着色，透明部分裁剪掉
+        if (tex_color.a < 0.5) {
+            discard;
+        }
+        color = in.color * tex_color.rgb;
+    } else if (in.model_type > 0.5) {
+        color = material.color.rgb;
+        if (tex_color.a > 0.1) {
+            color = color * tex_color.rgb;
+            alpha = tex_color.a;
+        }
+    }
+
+    color = color * lighting(in.world_position, normal, in.clip_position.xy);
+    return vec4<f32>(color, alpha);
 }
\ No newline at end of file
@@ -0,0 +1,190 @@
+use crate::mods;
+use crate::progression::Perk;
+use crate::ui::{Nav, UiBatch};
+
+// 波次模式购买阶段的商店：商品和价格定义在 data/shop.json，mod 可以覆盖或者添加。
+// 键盘和手柄上下选择、确定购买，鼠标指到哪一行选中哪一行、点击购买，点到面板外面关闭
+
+// data/shop.json 的格式版本
+const SHOP_VERSION: u32 = 1;
+
+const PANEL_COLOR: [f32; 4] = [0.05, 0.05, 0.1, 0.9];
+const HEADER_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+const TEXT_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const DIM_COLOR: [f32; 4] = [0.55, 0.55, 0.6, 1.0];
+const HIGHLIGHT_COLOR: [f32; 4] = [0.25, 0.35, 0.6, 0.9];
+const MONEY_COLOR: [f32; 4] = [0.45, 0.9, 0.4, 1.0];
+const ERROR_COLOR: [f32; 4] = [1.0, 0.4, 0.3, 1.0];
+
+const PANEL_W: f32 = 600.0;
+const ROW_H: f32 = 34.0;
+// 第一行相对面板顶部的位置，和最后一行下面留给提示文字的高度
+const LIST_TOP: f32 = 96.0;
+const FOOTER_H: f32 = 70.0;
+const SCALE: f32 = 2.0;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ShopGood {
+    // data/weapons.json 中的武器 id
+    Weapon { weapon: String },
+    // 手上所有武器的弹匣和备弹装满
+    Ammo,
+    Armor { amount: f32 },
+    // 这一局的天赋升一级
+    Perk { perk: Perk },
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ShopItem {
+    pub id: String,
+    pub name: String,
+    pub price: u32,
+    #[serde(flatten)]
+    pub good: ShopGood,
+}
+
+#[derive(serde::Deserialize)]
+struct ShopFile {
+    items: Vec<ShopItem>,
+}
+
+// 游戏自带的商品加上启用的 mod 里的，id 相同时后加载的替换前面的
+pub fn load_items() -> Vec<ShopItem> {
+    mods::load_overlaid(
+        "data/shop.json",
+        include_str!("../data/shop.json"),
+        SHOP_VERSION,
+        "商店",
+        |file: ShopFile| file.items,
+        |item| &item.id,
+    )
+}
+
+// 在商店里选了什么
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShopChoice {
+    // items 中的第几个
+    Buy(usize),
+    StartWave,
+}
+
+pub struct ShopScreen {
+    pub open: bool,
+    pub items: Vec<ShopItem>,
+    // 最后一行是 START WAVE
+    selected: usize,
+    // 上一次购买的结果，出错时为红色
+    message: Option<(String, bool)>,
+}
+
+impl ShopScreen {
+    pub fn new() -> Self {
+        Self { open: false, items: load_items(), selected: 0, message: None }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.message = None;
+    }
+
+    pub fn set_message(&mut self, message: &str, error: bool) {
+        self.message = Some((message.to_string(), error));
+    }
+
+    fn rows(&self) -> usize {
+        self.items.len() + 1
+    }
+
+    fn choice(&self, row: usize) -> Option<ShopChoice> {
+        if row < self.items.len() {
+            Some(ShopChoice::Buy(row))
+        } else {
+            (row == self.items.len()).then_some(ShopChoice::StartWave)
+        }
+    }
+
+    pub fn navigate(&mut self, nav: Nav) -> Option<ShopChoice> {
+        let count = self.rows();
+        match nav {
+            Nav::Up => self.selected = (self.selected + count - 1) % count,
+            Nav::Down => self.selected = (self.selected + 1) % count,
+            Nav::Select => return self.choice(self.selected),
+            Nav::Back => self.open = false,
+        }
+        None
+    }
+
+    // 面板的位置和大小，绘制和鼠标共用
+    fn panel_rect(&self, width: f32, height: f32) -> (f32, f32, f32, f32) {
+        let panel_w = PANEL_W.min(width - 20.0);
+        let panel_h = LIST_TOP + ROW_H * self.rows() as f32 + FOOTER_H;
+        ((width - panel_w) / 2.0, ((height - panel_h) / 2.0).max(10.0), panel_w, panel_h)
+    }
+
+    // 鼠标下面的行
+    fn row_at(&self, x: f32, y: f32, width: f32, height: f32) -> Option<usize> {
+        let (panel_x, panel_y, panel_w, _) = self.panel_rect(width, height);
+        let top = panel_y + LIST_TOP;
+        if x < panel_x || x > panel_x + panel_w || y < top {
+            return None;
+        }
+        let row = ((y - top) / ROW_H) as usize;
+        (row < self.rows()).then_some(row)
+    }
+
+    pub fn hover(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        if let Some(row) = self.row_at(x, y, width, height) {
+            self.selected = row;
+        }
+    }
+
+    // 点到某一行时选中并执行它，点到面板外面关闭商店
+    pub fn click(&mut self, x: f32, y: f32, width: f32, height: f32) -> Option<ShopChoice> {
+        let (panel_x, panel_y, panel_w, panel_h) = self.panel_rect(width, height);
+        if x < panel_x || x > panel_x + panel_w || y < panel_y || y > panel_y + panel_h {
+            self.open = false;
+            return None;
+        }
+        let row = self.row_at(x, y, width, height)?;
+        self.selected = row;
+        self.choice(row)
+    }
+
+    // detail 是每件商品右边价格前面的说明（已拥有、天赋等级等）
+    pub fn draw(&self, ui: &mut UiBatch, money: u32, buy_time: f32, detail: impl Fn(&ShopItem) -> String) {
+        let (panel_x, panel_y, panel_w, panel_h) = self.panel_rect(ui.width(), ui.height());
+        ui.rect(0.0, 0.0, ui.width(), ui.height(), [0.0, 0.0, 0.0, 0.4]);
+        ui.rect(panel_x, panel_y, panel_w, panel_h, PANEL_COLOR);
+        ui.text_centered(ui.width() / 2.0, panel_y + 16.0, 3.0, TEXT_COLOR, "SHOP");
+        ui.text(panel_x + 24.0, panel_y + 56.0, SCALE, MONEY_COLOR, &format!("${}", money));
+        let timer = format!("NEXT WAVE IN {}", buy_time.ceil() as u32);
+        ui.text(panel_x + panel_w - 24.0 - UiBatch::text_width(&timer, SCALE), panel_y + 56.0, SCALE, DIM_COLOR, &timer);
+
+        let text_y = (ROW_H - UiBatch::line_height(SCALE)) / 2.0 + 3.0;
+        let right = panel_x + panel_w - 24.0;
+        for row in 0..self.rows() {
+            let y = panel_y + LIST_TOP + row as f32 * ROW_H;
+            if row == self.selected {
+                ui.rect(panel_x + 12.0, y, panel_w - 24.0, ROW_H, HIGHLIGHT_COLOR);
+            }
+            let Some(item) = self.items.get(row) else {
+                ui.text(panel_x + 24.0, y + text_y, SCALE, HEADER_COLOR, "START WAVE");
+                continue;
+            };
+            let affordable = item.price <= money;
+            ui.text(panel_x + 24.0, y + text_y, SCALE, if affordable { TEXT_COLOR } else { DIM_COLOR }, &item.name.to_uppercase());
+            let price = format!("${}", item.price);
+            let price_w = UiBatch::text_width("$00000", SCALE);
+            ui.text(right - UiBatch::text_width(&price, SCALE), y + text_y, SCALE, if affordable { MONEY_COLOR } else { DIM_COLOR }, &price);
+            let detail = detail(item);
+            ui.text(right - price_w - 16.0 - UiBatch::text_width(&detail, SCALE), y + text_y, SCALE, DIM_COLOR, &detail);
+        }
+
+        let footer_y = panel_y + panel_h - FOOTER_H + 12.0;
+        if let Some((message, error)) = &self.message {
+            ui.text(panel_x + 24.0, footer_y, SCALE, if *error { ERROR_COLOR } else { TEXT_COLOR }, message);
+        }
+        ui.text(panel_x + 24.0, footer_y + 28.0, SCALE, DIM_COLOR, "ENTER/CLICK buy   B/ESC close");
+    }
+}
@@ -0,0 +1,406 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use glam::Vec3;
+
+// 联机时主机定期把所有玩家的状态打成快照发给客户端。位置和角度量化成整数，
+// 每个客户端只收到和它确认过的上一个快照相比变了的玩家和字段；还没确认过任何快照时发完整的。
+// 二进制格式（小端）：
+//   tick u32, 基准 tick u32（没有基准时为 NO_BASELINE）,
+//   变化的玩家数 u8, 每个玩家 [编号 u16, 字段位掩码 u8, 掩码里的字段...],
+//   离开的玩家数 u8, 每个玩家 [编号 u16]
+
+// 位置精度 1/64 米，范围 ±512 米
+const POSITION_SCALE: f32 = 64.0;
+// 朝向一圈分成 65536 份，俯仰 ±90 度映射到 i16
+const YAW_STEPS: f32 = 65536.0;
+const PITCH_SCALE: f32 = i16::MAX as f32 / std::f32::consts::FRAC_PI_2;
+const NO_BASELINE: u32 = u32::MAX;
+// 快照历史保留的个数，客户端的确认落后超过这么多个快照时重新发完整的
+const HISTORY: usize = 32;
+
+// 字段位掩码
+const FIELD_X: u8 = 1 << 0;
+const FIELD_Y: u8 = 1 << 1;
+const FIELD_Z: u8 = 1 << 2;
+const FIELD_YAW: u8 = 1 << 3;
+const FIELD_PITCH: u8 = 1 << 4;
+const FIELD_HEALTH: u8 = 1 << 5;
+const FIELD_FLAGS: u8 = 1 << 6;
+const FIELD_ARMOR: u8 = 1 << 7;
+const ALL_FIELDS: u8 = 0xff;
+
+const FLAG_ALIVE: u8 = 1 << 0;
+
+// 一个玩家的状态，position 是眼睛的位置
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct PlayerState {
+    pub position: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+    pub health: f32,
+    pub armor: f32,
+    pub alive: bool,
+}
+
+impl PlayerState {
+    pub fn new(position: Vec3, yaw: f32, pitch: f32, health: f32, armor: f32, alive: bool) -> Self {
+        Self { position: position.to_array(), yaw, pitch, health, armor, alive }
+    }
+
+    pub fn position(&self) -> Vec3 {
+        Vec3::from(self.position)
+    }
+}
+
+// 量化后的状态，比较是否变化和编码都用它，解码出来和编码前完全一样
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Quantized {
+    position: [i16; 3],
+    yaw: u16,
+    pitch: i16,
+    health: u8,
+    armor: u8,
+    flags: u8,
+}
+
+impl Quantized {
+    fn new(state: &PlayerState) -> Self {
+        let position = state.position.map(|value| (value * POSITION_SCALE).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16);
+        let turns = state.yaw.rem_euclid(std::f32::consts::TAU) / std::f32::consts::TAU;
+        Self {
+            position,
+            yaw: ((turns * YAW_STEPS).round() as u32 % 65536) as u16,
+            pitch: (state.pitch.clamp(-std::f32::consts::FRAC_PI_2, std::f32::consts::FRAC_PI_2) * PITCH_SCALE).round() as i16,
+            health: state.health.round().clamp(0.0, 255.0) as u8,
+            armor: state.armor.round().clamp(0.0, 255.0) as u8,
+            flags: if state.alive { FLAG_ALIVE } else { 0 },
+        }
+    }
+
+    fn state(&self) -> PlayerState {
+        PlayerState {
+            position: self.position.map(|value| value as f32 / POSITION_SCALE),
+            yaw: self.yaw as f32 / YAW_STEPS * std::f32::consts::TAU,
+            pitch: self.pitch as f32 / PITCH_SCALE,
+            health: self.health as f32,
+            armor: self.armor as f32,
+            alive: self.flags & FLAG_ALIVE != 0,
+        }
+    }
+
+    // 和基准相比变了的字段
+    fn changed(&self, base: &Quantized) -> u8 {
+        let mut mask = 0;
+        for (i, bit) in [FIELD_X, FIELD_Y, FIELD_Z].into_iter().enumerate() {
+            if self.position[i] != base.position[i] {
+                mask |= bit;
+            }
+        }
+        if self.yaw != base.yaw {
+            mask |= FIELD_YAW;
+        }
+        if self.pitch != base.pitch {
+            mask |= FIELD_PITCH;
+        }
+        if self.health != base.health {
+            mask |= FIELD_HEALTH;
+        }
+        if self.flags != base.flags {
+            mask |= FIELD_FLAGS;
+        }
+        if self.armor != base.armor {
+            mask |= FIELD_ARMOR;
+        }
+        mask
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub tick: u32,
+    players: BTreeMap<u16, Quantized>,
+}
+
+impl Snapshot {
+    // 玩家编号超出 u16 的部分会被截掉，局域网里不会有这么多人进出
+    pub fn new(tick: u32, players: &[(u32, PlayerState)]) -> Self {
+        Self { tick, players: players.iter().map(|(id, state)| (*id as u16, Quantized::new(state))).collect() }
+    }
+
+    // 量化以后的玩家状态
+    pub fn players(&self) -> Vec<(u32, PlayerState)> {
+        self.players.iter().map(|(id, quantized)| (*id as u32, quantized.state())).collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    Truncated,
+    // 引用的基准快照已经不在历史里了
+    MissingBaseline(u32),
+    // 基准里没有的玩家必须带上全部字段
+    IncompletePlayer(u16),
+    TooManyPlayers,
+}
+
+// baseline 是对方确认收到过的快照，None 时编码完整的快照
+pub fn encode(snapshot: &Snapshot, baseline: Option<&Snapshot>) -> Vec<u8> {
+    let empty = BTreeMap::new();
+    let base_players = baseline.map_or(&empty, |baseline| &baseline.players);
+    let mut bytes = Vec::with_capacity(16 + snapshot.players.len() * 16);
+    bytes.extend_from_slice(&snapshot.tick.to_le_bytes());
+    bytes.extend_from_slice(&baseline.map_or(NO_BASELINE, |baseline| baseline.tick).to_le_bytes());
+
+    let changed: Vec<(u16, u8, &Quantized)> = snapshot
+        .players
+        .iter()
+        .map(|(id, player)| (*id, base_players.get(id).map_or(ALL_FIELDS, |base| player.changed(base)), player))
+        .filter(|(_, mask, _)| *mask != 0)
+        .take(u8::MAX as usize)
+        .collect();
+    bytes.push(changed.len() as u8);
+    for (id, mask, player) in changed {
+        bytes.extend_from_slice(&id.to_le_bytes());
+        bytes.push(mask);
+        for (i, bit) in [FIELD_X, FIELD_Y, FIELD_Z].into_iter().enumerate() {
+            if mask & bit != 0 {
+                bytes.extend_from_slice(&player.position[i].to_le_bytes());
+            }
+        }
+        if mask & FIELD_YAW != 0 {
+            bytes.extend_from_slice(&player.yaw.to_le_bytes());
+        }
+        if mask & FIELD_PITCH != 0 {
+            bytes.extend_from_slice(&player.pitch.to_le_bytes());
+        }
+        if mask & FIELD_HEALTH != 0 {
+            bytes.push(player.health);
+        }
+        if mask & FIELD_FLAGS != 0 {
+            bytes.push(player.flags);
+        }
+        if mask & FIELD_ARMOR != 0 {
+            bytes.push(player.armor);
+        }
+    }
+
+    let removed: Vec<u16> = base_players.keys().filter(|id| !snapshot.players.contains_key(id)).copied().take(u8::MAX as usize).collect();
+    bytes.push(removed.len() as u8);
+    for id in removed {
+        bytes.extend_from_slice(&id.to_le_bytes());
+    }
+    bytes
+}
+
+// 按顺序读取小端整数
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        if self.bytes.len() < N {
+            return Err(DecodeError::Truncated);
+        }
+        let (head, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        Ok(head.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn i16(&mut self) -> Result<i16, DecodeError> {
+        Ok(i16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+}
+
+// history 里找增量快照引用的基准
+pub fn decode(bytes: &[u8], history: &SnapshotHistory) -> Result<Snapshot, DecodeError> {
+    let mut reader = Reader { bytes };
+    let tick = reader.u32()?;
+    let base_tick = reader.u32()?;
+    let mut players = if base_tick == NO_BASELINE {
+        BTreeMap::new()
+    } else {
+        history.get(base_tick).ok_or(DecodeError::MissingBaseline(base_tick))?.players.clone()
+    };
+
+    for _ in 0..reader.u8()? {
+        let id = reader.u16()?;
+        let mask = reader.u8()?;
+        let mut player = match players.get(&id) {
+            Some(player) => *player,
+            None if mask == ALL_FIELDS => Quantized { position: [0; 3], yaw: 0, pitch: 0, health: 0, armor: 0, flags: 0 },
+            None => return Err(DecodeError::IncompletePlayer(id)),
+        };
+        for (i, bit) in [FIELD_X, FIELD_Y, FIELD_Z].into_iter().enumerate() {
+            if mask & bit != 0 {
+                player.position[i] = reader.i16()?;
+            }
+        }
+        if mask & FIELD_YAW != 0 {
+            player.yaw = reader.u16()?;
+        }
+        if mask & FIELD_PITCH != 0 {
+            player.pitch = reader.i16()?;
+        }
+        if mask & FIELD_HEALTH != 0 {
+            player.health = reader.u8()?;
+        }
+        if mask & FIELD_FLAGS != 0 {
+            player.flags = reader.u8()?;
+        }
+        if mask & FIELD_ARMOR != 0 {
+            player.armor = reader.u8()?;
+        }
+        players.insert(id, player);
+        if players.len() > u8::MAX as usize {
+            return Err(DecodeError::TooManyPlayers);
+        }
+    }
+    for _ in 0..reader.u8()? {
+        players.remove(&reader.u16()?);
+    }
+    Ok(Snapshot { tick, players })
+}
+
+// 最近的快照，主机用来找客户端确认过的基准，客户端用来解码增量
+pub struct SnapshotHistory {
+    snapshots: VecDeque<Snapshot>,
+}
+
+impl SnapshotHistory {
+    pub fn new() -> Self {
+        Self { snapshots: VecDeque::with_capacity(HISTORY) }
+    }
+
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.snapshots.len() == HISTORY {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    pub fn get(&self, tick: u32) -> Option<&Snapshot> {
+        self.snapshots.iter().find(|snapshot| snapshot.tick == tick)
+    }
+
+    pub fn latest(&self) -> Option<&Snapshot> {
+        self.snapshots.back()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(x: f32, yaw: f32) -> PlayerState {
+        PlayerState::new(Vec3::new(x, 1.8, -x * 0.5), yaw, 0.2, 100.0, 50.0, true)
+    }
+
+    fn full_lobby(tick: u32) -> Snapshot {
+        let players: Vec<(u32, PlayerState)> = (0..16).map(|id| (id, player(id as f32 * 3.7 - 20.0, id as f32 * 0.4))).collect();
+        Snapshot::new(tick, &players)
+    }
+
+    #[test]
+    fn full_snapshot_round_trips() {
+        let snapshot = full_lobby(7);
+        let decoded = decode(&encode(&snapshot, None), &SnapshotHistory::new()).unwrap();
+        assert_eq!(decoded, snapshot);
+    }
+
+    #[test]
+    fn delta_round_trips_against_the_baseline() {
+        let base = full_lobby(1);
+        let mut players = base.players();
+        players[3].1.position[0] += 0.5;
+        players[9].1.yaw += 1.0;
+        players[12].1.alive = false;
+        players[12].1.health = 0.0;
+        players[7].1.armor = 12.0;
+        let next = Snapshot::new(2, &players);
+
+        let mut history = SnapshotHistory::new();
+        history.push(base.clone());
+        let decoded = decode(&encode(&next, Some(&base)), &history).unwrap();
+        assert_eq!(decoded, next);
+    }
+
+    #[test]
+    fn players_joining_and_leaving_survive_a_delta() {
+        let base = Snapshot::new(1, &[(0, player(1.0, 0.0)), (1, player(2.0, 0.0)), (2, player(3.0, 0.0))]);
+        let next = Snapshot::new(2, &[(0, player(1.0, 0.0)), (2, player(3.5, 0.0)), (5, player(-4.0, 2.0))]);
+
+        let mut history = SnapshotHistory::new();
+        history.push(base.clone());
+        let decoded = decode(&encode(&next, Some(&base)), &history).unwrap();
+        assert_eq!(decoded, next);
+    }
+
+    #[test]
+    fn unchanged_players_cost_nothing() {
+        let base = full_lobby(1);
+        let same = Snapshot { tick: 2, players: base.players.clone() };
+        // 只有头部：两个 tick 和两个计数
+        assert_eq!(encode(&same, Some(&base)).len(), 10);
+
+        let mut players = base.players();
+        players[0].1.position[2] += 1.0;
+        let moved = Snapshot::new(3, &players);
+        // 编号、掩码和一个坐标
+        assert_eq!(encode(&moved, Some(&base)).len(), 10 + 2 + 1 + 2);
+    }
+
+    #[test]
+    fn sixteen_players_fit_in_a_small_packet() {
+        let bytes = encode(&full_lobby(1), None);
+        // 每个新玩家 16 字节
+        assert!(bytes.len() <= 10 + 16 * 16, "{} bytes", bytes.len());
+    }
+
+    #[test]
+    fn quantization_stays_within_precision() {
+        let state = PlayerState::new(Vec3::new(123.456, 2.345, -300.01), -1.0, -0.7, 57.4, 33.6, true);
+        let decoded = Snapshot::new(1, &[(4, state)]).players()[0].1;
+        assert!((decoded.position() - state.position()).abs().max_element() <= 0.5 / POSITION_SCALE);
+        let yaw_error = (decoded.yaw - state.yaw.rem_euclid(std::f32::consts::TAU)).abs();
+        assert!(yaw_error < std::f32::consts::TAU / YAW_STEPS);
+        assert!((decoded.pitch - state.pitch).abs() < 1.0 / PITCH_SCALE);
+        assert_eq!(decoded.health, 57.0);
+        assert_eq!(decoded.armor, 34.0);
+    }
+
+    #[test]
+    fn missing_baseline_and_truncated_packets_are_rejected() {
+        let base = full_lobby(1);
+        let bytes = encode(&full_lobby(2), Some(&base));
+        assert_eq!(decode(&bytes, &SnapshotHistory::new()), Err(DecodeError::MissingBaseline(1)));
+
+        let full = encode(&base, None);
+        for len in [0, 3, 9, full.len() - 1] {
+            assert_eq!(decode(&full[..len], &SnapshotHistory::new()), Err(DecodeError::Truncated));
+        }
+    }
+
+    #[test]
+    fn history_drops_old_snapshots() {
+        let mut history = SnapshotHistory::new();
+        for tick in 0..HISTORY as u32 + 5 {
+            history.push(Snapshot::new(tick, &[]));
+        }
+        assert!(history.get(4).is_none());
+        assert_eq!(history.get(5).map(|snapshot| snapshot.tick), Some(5));
+        assert_eq!(history.latest().map(|snapshot| snapshot.tick), Some(HISTORY as u32 + 4));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::camera::Camera;
+use crate::snapshot::PlayerState;
+use crate::ui::UiBatch;
+
+// 联机时的观战：死亡后等待重生时，以及中途加入的玩家在进场之前。
+// 可以跟随其他玩家看他们的第一人称视角（位置和视角来自快照），也可以切换成自由相机在地图里飞。
+// 开火键换下一个玩家，换弹键换上一个，使用键切换自由相机，跳跃键进场（死亡时要等重生倒计时结束）
+
+// 快照 20 次每秒，跟随时相机向目标平滑靠近，数值越大跟得越紧
+const FOLLOW_SHARPNESS: f32 = 15.0;
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+const DIM_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 0.8];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    Free,
+    Player(u32),
+}
+
+pub struct Spectator {
+    pub active: bool,
+    target: Target,
+    // 观战时实际看的相机：自由相机时由玩家控制，跟随时追着目标玩家
+    camera: Camera,
+}
+
+impl Spectator {
+    pub fn new() -> Self {
+        Self { active: false, target: Target::Free, camera: Camera::new((0.0, 0.0, 0.0), 0.0, 0.0) }
+    }
+
+    // 从 camera（死亡的位置或出生点）开始观战，有其他玩家时先跟随第一个
+    pub fn start(&mut self, camera: &Camera, players: &[(u32, PlayerState)]) {
+        self.active = true;
+        self.camera = camera.clone();
+        self.target = Target::Free;
+        self.cycle(players, true);
+    }
+
+    pub fn stop(&mut self) {
+        self.active = false;
+    }
+
+    // 跟随的玩家，自由相机时为 None
+    pub fn followed(&self) -> Option<u32> {
+        match self.target {
+            Target::Player(player_id) if self.active => Some(player_id),
+            _ => None,
+        }
+    }
+
+    // 自由相机时可以移动和转动视角
+    pub fn free_camera_mut(&mut self) -> Option<&mut Camera> {
+        (self.active && self.target == Target::Free).then_some(&mut self.camera)
+    }
+
+    pub fn toggle_free(&mut self, players: &[(u32, PlayerState)]) {
+        match self.target {
+            Target::Free => self.cycle(players, true),
+            Target::Player(_) => self.target = Target::Free,
+        }
+    }
+
+    // 按编号换到下一个（或上一个）活着的玩家，没有人可以跟随时用自由相机
+    pub fn cycle(&mut self, players: &[(u32, PlayerState)], forward: bool) {
+        let mut alive: Vec<u32> = players.iter().filter(|(_, state)| state.alive).map(|(player_id, _)| *player_id).collect();
+        alive.sort_unstable();
+        if alive.is_empty() {
+            self.target = Target::Free;
+            return;
+        }
+        let next = match self.target {
+            Target::Player(current) if forward => alive.iter().find(|id| **id > current).or(alive.first()),
+            Target::Player(current) => alive.iter().rev().find(|id| **id < current).or(alive.last()),
+            Target::Free if forward => alive.first(),
+            Target::Free => alive.last(),
+        };
+        self.target = next.map_or(Target::Free, |player_id| Target::Player(*player_id));
+    }
+
+    // 每帧更新跟随的相机，返回这一帧观战看到的画面。跟随的玩家死亡或离开时换下一个
+    pub fn update(&mut self, players: &[(u32, PlayerState)], dt: f32) -> Camera {
+        if let Target::Player(player_id) = self.target {
+            match players.iter().find(|(id, state)| *id == player_id && state.alive) {
+                Some((_, state)) => {
+                    let t = 1.0 - (-FOLLOW_SHARPNESS * dt).exp();
+                    let target = state.position();
+                    // 自由相机转过的角度不带过来
+                    self.camera.roll = 0.0;
+                    // 离得太远（刚换目标、对方重生）时直接跳过去
+                    if self.camera.position.distance(target) > 5.0 {
+                        self.camera.position = target;
+                        self.camera.yaw = state.yaw;
+                        self.camera.pitch = state.pitch;
+                    } else {
+                        self.camera.position = self.camera.position.lerp(target, t);
+                        self.camera.yaw += wrap_angle(state.yaw - self.camera.yaw) * t;
+                        self.camera.pitch += (state.pitch - self.camera.pitch) * t;
+                    }
+                }
+                None => self.cycle(players, true),
+            }
+        }
+        self.camera.clone()
+    }
+
+    // 屏幕上方显示在看谁，下方是操作提示
+    pub fn draw(&self, ui: &mut UiBatch, name: Option<&str>, keys: &SpectateKeys) {
+        let (width, height) = (ui.width(), ui.height());
+        let title = match (self.target, name) {
+            (Target::Player(_), Some(name)) => format!("SPECTATING {}", name.to_uppercase()),
+            _ => "FREE CAMERA".to_string(),
+        };
+        ui.text_centered(width / 2.0, 60.0, 3.0, TEXT_COLOR, &title);
+        let hint = format!("{} next   {} previous   {} free camera   {}", keys.next, keys.previous, keys.free, keys.join);
+        ui.text_centered(width / 2.0, height - 80.0, 2.0, DIM_COLOR, &hint);
+    }
+}
+
+// 提示里显示的按键名（按当前设备和按键绑定），join 是跳跃键那一项的完整提示（进场、重生倒计时或者回放的暂停）
+pub struct SpectateKeys {
+    pub next: String,
+    pub previous: String,
+    pub free: String,
+    pub join: String,
+}
+
+// 把角度差换到 (-PI, PI]，转向时走近的那一边
+pub fn wrap_angle(angle: f32) -> f32 {
+    let tau = std::f32::consts::TAU;
+    angle - tau * ((angle + std::f32::consts::PI) / tau).floor()
+}
@@ -0,0 +1,101 @@
+use glam::Vec3;
+
+use crate::collision::{self, WallCollider};
+use crate::lighting::PointLight;
+use crate::ui::UiBatch;
+
+// 潜行：玩家身上有多亮（0 到 1），按附近没坏、中间没有墙挡着的灯算，衰减和着色器里的点光源一样。
+// 敌人能发现玩家的距离按亮度在 enemy::sight_range 里插值，打坏灯、贴着阴影走时敌人要走得很近才发现。
+// 不打光的地图（没有设置 ambient）到处都算全亮。HUD 上在护甲右边显示一个亮度条
+
+// 身上这么亮算全亮（正下方一盏灯大约是 0.4）
+const FULL_EXPOSURE: f32 = 0.3;
+// 从眼睛往下多少是检测亮度的点（胸口）
+const CHEST_DROP: f32 = 0.5;
+// 显示的亮度每秒最多变化多少，走过灯下时不会一闪
+const EXPOSURE_RATE: f32 = 3.0;
+// 低于这个亮度时 HUD 显示 HIDDEN
+const HIDDEN_BELOW: f32 = 0.25;
+
+// 一盏灯照到 position 的亮度，和 shader.wgsl 的 point_light 一样衰减（不算朝向）
+fn light_at(light: &PointLight, position: Vec3) -> f32 {
+    let distance = Vec3::from(light.position).distance(position);
+    let window = (1.0 - (distance / light.radius).powi(4)).clamp(0.0, 1.0);
+    light.intensity * window * window / (1.0 + distance * distance / 9.0)
+}
+
+// 眼睛在 eye 处的玩家有多亮。lights 为空（不打光的地图）时为 1
+pub fn exposure(lights: &[PointLight], colliders: &[WallCollider], eye: Vec3) -> f32 {
+    if lights.is_empty() {
+        return 1.0;
+    }
+    let chest = eye - Vec3::Y * CHEST_DROP;
+    let light: f32 = lights
+        .iter()
+        .filter(|light| light.intensity > 0.0)
+        .map(|light| (light, light_at(light, chest)))
+        .filter(|(light, amount)| *amount > 0.0 && collision::count_occluders(colliders, Vec3::from(light.position), chest) == 0)
+        .map(|(_, amount)| amount)
+        .sum();
+    (light / FULL_EXPOSURE).clamp(0.0, 1.0)
+}
+
+// 本地玩家的亮度，显示的值慢慢跟上
+pub struct Exposure {
+    value: f32,
+    // 地图打光时才显示
+    lit: bool,
+}
+
+impl Exposure {
+    pub fn new() -> Self {
+        Self { value: 1.0, lit: false }
+    }
+
+    pub fn update(&mut self, lights: &[PointLight], colliders: &[WallCollider], eye: Vec3, dt: f32) {
+        let target = exposure(lights, colliders, eye);
+        let step = EXPOSURE_RATE * dt;
+        self.value += (target - self.value).clamp(-step, step);
+        self.lit = !lights.is_empty();
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    // 护甲条右边：越亮条越长、颜色越白，够暗时写 HIDDEN
+    pub fn draw(&self, ui: &mut UiBatch) {
+        if !self.lit {
+            return;
+        }
+        let (x, y, w, h) = (450.0, ui.height() - 40.0, 100.0, 20.0);
+        ui.rect(x, y, w, h, [0.0, 0.0, 0.0, 0.6]);
+        let shade = 0.35 + 0.65 * self.value;
+        ui.rect(x, y, w * self.value.max(0.03), h, [shade, shade, shade * 0.8, 0.9]);
+        let (label, color) = if self.value < HIDDEN_BELOW { ("HIDDEN", [0.5, 0.8, 1.0, 1.0]) } else { ("LIGHT", [0.1, 0.1, 0.1, 1.0]) };
+        ui.text(x + 6.0, y + 3.0, 2.0, color, label);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lamp(x: f32, intensity: f32) -> PointLight {
+        PointLight { position: [x, 3.2, 0.0], radius: 12.0, color: [1.0; 3], intensity }
+    }
+
+    #[test]
+    fn lights_walls_and_broken_lamps() {
+        let eye = Vec3::new(0.0, 1.8, 0.0);
+        assert_eq!(exposure(&[], &[], eye), 1.0);
+        assert_eq!(exposure(&[lamp(0.0, 0.6)], &[], eye), 1.0);
+        // 打坏的灯和墙后面的灯都不算
+        assert_eq!(exposure(&[lamp(0.0, 0.0)], &[], eye), 0.0);
+        let wall = WallCollider::new([2.0, 0.0, -5.0], [2.0, 0.0, 5.0], 4.0, 0.2);
+        assert_eq!(exposure(&[lamp(6.0, 0.6)], &[wall], eye), 0.0);
+        // 远处的灯只照亮一点
+        let far = exposure(&[lamp(8.0, 0.6)], &[], eye);
+        assert!(far > 0.0 && far < 0.5, "{}", far);
+    }
+}
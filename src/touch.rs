@@ -0,0 +1,186 @@
+use glam::Vec2;
+use winit::event::{Touch, TouchPhase};
+
+use crate::input::Action;
+use crate::ui::UiBatch;
+
+// 触屏操作（平板电脑）：屏幕左半边按下的地方出现虚拟摇杆控制移动，右半边拖动转视角，
+// 右下角是开火、跳跃、使用和冲刺按钮。收到第一次触摸后才显示这些按钮，用键鼠和手柄时看不到
+
+// 摇杆推到底的距离（像素）
+const STICK_RADIUS: f32 = 70.0;
+const KNOB_SIZE: f32 = 44.0;
+const FIRE_SIZE: f32 = 110.0;
+const BUTTON_SIZE: f32 = 72.0;
+const MARGIN: f32 = 30.0;
+// 拖动视角的灵敏度，相对于同样距离的鼠标移动
+const LOOK_SCALE: f64 = 0.6;
+// 按钮和摇杆的颜色
+const OUTLINE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.45];
+const FILL_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.15];
+const PRESSED_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 0.45];
+const LABEL_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.8];
+
+// 触摸产生的操作，由 State 转换成和键鼠相同的输入
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TouchInput {
+    Press(Action),
+    Release(Action),
+    // 虚拟摇杆的位置，x 向右、y 向前，长度不超过 1
+    Move(Vec2),
+    // 拖动视角的距离（和鼠标移动的单位相同）
+    Look(f64, f64),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Button {
+    action: Action,
+    label: &'static str,
+    x: f32,
+    y: f32,
+    size: f32,
+}
+
+impl Button {
+    fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.x && point.x <= self.x + self.size && point.y >= self.y && point.y <= self.y + self.size
+    }
+}
+
+// 某个手指正在做的事
+#[derive(Debug, Clone, Copy)]
+enum Finger {
+    Stick { origin: Vec2, current: Vec2 },
+    Look { last: Vec2 },
+    Button(Action),
+}
+
+pub struct TouchControls {
+    // 设置里可以关掉
+    pub enabled: bool,
+    // 收到过触摸，开始显示按钮
+    active: bool,
+    fingers: Vec<(u64, Finger)>,
+}
+
+impl TouchControls {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, active: false, fingers: Vec::new() }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.active
+    }
+
+    // 处理一个触摸事件，width 和 height 是窗口的大小（像素）
+    pub fn handle(&mut self, touch: &Touch, width: f32, height: f32) -> Vec<TouchInput> {
+        let mut out = Vec::new();
+        if !self.enabled {
+            return out;
+        }
+        self.active = true;
+        let point = Vec2::new(touch.location.x as f32, touch.location.y as f32);
+        match touch.phase {
+            TouchPhase::Started => {
+                let finger = match buttons(width, height).iter().find(|button| button.contains(point)) {
+                    Some(button) => {
+                        out.push(TouchInput::Press(button.action));
+                        Finger::Button(button.action)
+                    }
+                    // 左半边同时只有一个摇杆，多出来的手指也用来转视角
+                    None if point.x < width / 2.0 && !self.fingers.iter().any(|(_, f)| matches!(f, Finger::Stick { .. })) => {
+                        Finger::Stick { origin: point, current: point }
+                    }
+                    None => Finger::Look { last: point },
+                };
+                self.fingers.push((touch.id, finger));
+            }
+            TouchPhase::Moved => {
+                let Some((_, finger)) = self.fingers.iter_mut().find(|(id, _)| *id == touch.id) else {
+                    return out;
+                };
+                match finger {
+                    Finger::Stick { origin, current } => {
+                        *current = point;
+                        out.push(TouchInput::Move(stick_vector(*origin, *current)));
+                    }
+                    Finger::Look { last } => {
+                        let delta = point - *last;
+                        *last = point;
+                        out.push(TouchInput::Look(delta.x as f64 * LOOK_SCALE, delta.y as f64 * LOOK_SCALE));
+                    }
+                    Finger::Button(_) => {}
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                let Some(index) = self.fingers.iter().position(|(id, _)| *id == touch.id) else {
+                    return out;
+                };
+                match self.fingers.remove(index).1 {
+                    Finger::Stick { .. } => out.push(TouchInput::Move(Vec2::ZERO)),
+                    Finger::Button(action) => out.push(TouchInput::Release(action)),
+                    Finger::Look { .. } => {}
+                }
+            }
+        }
+        out
+    }
+
+    // 打开菜单时松开所有手指
+    pub fn release_all(&mut self) -> Vec<TouchInput> {
+        self.fingers
+            .drain(..)
+            .filter_map(|(_, finger)| match finger {
+                Finger::Stick { .. } => Some(TouchInput::Move(Vec2::ZERO)),
+                Finger::Button(action) => Some(TouchInput::Release(action)),
+                Finger::Look { .. } => None,
+            })
+            .collect()
+    }
+
+    pub fn draw(&self, ui: &mut UiBatch) {
+        if !self.is_active() {
+            return;
+        }
+        for button in buttons(ui.width(), ui.height()) {
+            let pressed = self.fingers.iter().any(|(_, finger)| matches!(finger, Finger::Button(action) if *action == button.action));
+            ui.rect(button.x, button.y, button.size, button.size, if pressed { PRESSED_COLOR } else { FILL_COLOR });
+            ui.rect_outline(button.x, button.y, button.size, button.size, 2.0, OUTLINE_COLOR);
+            let text_y = button.y + (button.size - UiBatch::line_height(2.0)) / 2.0;
+            ui.text_centered(button.x + button.size / 2.0, text_y, 2.0, LABEL_COLOR, button.label);
+        }
+        for (_, finger) in &self.fingers {
+            if let Finger::Stick { origin, current } = finger {
+                let knob = *origin + stick_vector(*origin, *current) * Vec2::new(1.0, -1.0) * STICK_RADIUS;
+                let base = STICK_RADIUS * 2.0;
+                ui.rect_outline(origin.x - STICK_RADIUS, origin.y - STICK_RADIUS, base, base, 2.0, OUTLINE_COLOR);
+                ui.rect(knob.x - KNOB_SIZE / 2.0, knob.y - KNOB_SIZE / 2.0, KNOB_SIZE, KNOB_SIZE, PRESSED_COLOR);
+            }
+        }
+    }
+}
+
+// 摇杆的位置：屏幕坐标的 y 向下，向上推是向前
+fn stick_vector(origin: Vec2, current: Vec2) -> Vec2 {
+    let offset = (current - origin) / STICK_RADIUS;
+    Vec2::new(offset.x, -offset.y).clamp_length_max(1.0)
+}
+
+// 右下角的按钮：开火最大，在最右下；其余三个围在它的左边和上边
+fn buttons(width: f32, height: f32) -> [Button; 4] {
+    let fire_x = width - MARGIN - FIRE_SIZE;
+    let fire_y = height - MARGIN - FIRE_SIZE;
+    let gap = 16.0;
+    [
+        Button { action: Action::Fire, label: "FIRE", x: fire_x, y: fire_y, size: FIRE_SIZE },
+        Button { action: Action::Jump, label: "JUMP", x: fire_x - gap - BUTTON_SIZE, y: height - MARGIN - BUTTON_SIZE, size: BUTTON_SIZE },
+        Button { action: Action::Sprint, label: "RUN", x: fire_x + FIRE_SIZE - BUTTON_SIZE, y: fire_y - gap - BUTTON_SIZE, size: BUTTON_SIZE },
+        Button {
+            action: Action::Use,
+            label: "USE",
+            x: fire_x - gap - BUTTON_SIZE,
+            y: fire_y - gap - BUTTON_SIZE,
+            size: BUTTON_SIZE,
+        },
+    ]
+}
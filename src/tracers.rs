@@ -0,0 +1,190 @@
+use glam::Vec3;
+
+use crate::gpu_memory;
+use crate::texture;
+use crate::upload::FrameUploader;
+
+// 即时命中射击的曳光：从枪口到子弹停下的位置画一条朝向相机的细长亮线，外面套一层宽而暗的光晕，叠加混合，几帧内淡出。
+// 亮线的尾巴随时间往命中点收，看得出子弹飞的方向。在半透明物体和粒子之后画，不写深度
+
+// 同时存在的曳光上限，满了时替换最老的
+const MAX_TRACERS: usize = 128;
+// 从出现到完全消失（秒）
+const LIFETIME: f32 = 0.15;
+const WIDTH: f32 = 0.012;
+const COLOR: [f32; 3] = [1.0, 0.85, 0.55];
+// 光晕的宽度、颜色和相对亮线的亮度
+const GLOW_WIDTH: f32 = 0.05;
+const GLOW_COLOR: [f32; 3] = [1.0, 0.55, 0.2];
+const GLOW_ALPHA: f32 = 0.35;
+// 每个曳光的实例数：光晕和亮线
+const INSTANCES_PER_TRACER: usize = 2;
+
+// 曳光的起点：视线右下方、稍微往前一点，大致是持枪的位置
+pub fn muzzle(eye: Vec3, dir: Vec3) -> Vec3 {
+    let right = dir.cross(Vec3::Y).normalize_or_zero();
+    eye + right * 0.15 - Vec3::Y * 0.1 + dir * 0.3
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Tracer {
+    start: Vec3,
+    end: Vec3,
+    age: f32,
+}
+
+// 与 tracers.wgsl 里的 TracerInput 对应，每个曳光两个实例
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct TracerInstance {
+    start: [f32; 3],
+    width: f32,
+    end: [f32; 3],
+    // 已经乘上了淡出
+    color: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for TracerInstance {}
+unsafe impl bytemuck::Zeroable for TracerInstance {}
+
+pub struct Tracers {
+    tracers: Vec<Tracer>,
+    pipeline: wgpu::RenderPipeline,
+    instance_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    // 这一帧上传了几个实例
+    count: u32,
+}
+
+impl Tracers {
+    pub fn new(device: &wgpu::Device, frame_layout: &wgpu::BindGroupLayout, format: wgpu::TextureFormat) -> Self {
+        let instance_buffer = gpu_memory::buffer(device, gpu_memory::Category::Particles, &wgpu::BufferDescriptor {
+            label: Some("Tracer Instances"),
+            size: (MAX_TRACERS * INSTANCES_PER_TRACER * std::mem::size_of::<TracerInstance>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Tracer Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("tracers.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Tracer Pipeline Layout"),
+            bind_group_layouts: &[frame_layout],
+            push_constant_ranges: &[],
+        });
+        // 叠加混合、不写深度、不剔除背面
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Tracer Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<TracerInstance>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32, 2 => Float32x3, 3 => Float32x4],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::SrcAlpha,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent::OVER,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: texture::Texture::DEPTH_COMPARE,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+        Self { tracers: Vec::with_capacity(MAX_TRACERS), pipeline, instance_buffer, count: 0 }
+    }
+
+    pub fn add(&mut self, start: Vec3, end: Vec3) {
+        let tracer = Tracer { start, end, age: 0.0 };
+        if self.tracers.len() < MAX_TRACERS {
+            self.tracers.push(tracer);
+        } else if let Some(oldest) = self.tracers.iter_mut().max_by(|a, b| a.age.total_cmp(&b.age)) {
+            *oldest = tracer;
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.tracers.retain_mut(|tracer| {
+            tracer.age += dt;
+            tracer.age < LIFETIME
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.tracers.clear();
+    }
+
+    // 在渲染通道之前上传这一帧的实例
+    pub fn upload(&mut self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, uploader: &mut FrameUploader) {
+        self.count = (self.tracers.len() * INSTANCES_PER_TRACER) as u32;
+        if self.tracers.is_empty() {
+            return;
+        }
+        let instances: Vec<TracerInstance> = self
+            .tracers
+            .iter()
+            .flat_map(|tracer| {
+                let t = tracer.age / LIFETIME;
+                let fade = (1.0 - t) * (1.0 - t);
+                let glow = TracerInstance {
+                    start: tracer.start.to_array(),
+                    width: GLOW_WIDTH,
+                    end: tracer.end.to_array(),
+                    color: [GLOW_COLOR[0], GLOW_COLOR[1], GLOW_COLOR[2], fade * GLOW_ALPHA],
+                };
+                let core = TracerInstance {
+                    start: tracer.start.lerp(tracer.end, t).to_array(),
+                    width: WIDTH,
+                    end: tracer.end.to_array(),
+                    color: [COLOR[0], COLOR[1], COLOR[2], fade],
+                };
+                [glow, core]
+            })
+            .collect();
+        uploader.write(queue, encoder, &self.instance_buffer, 0, &instances);
+    }
+
+    // 在粒子之后绘制，会替换管线和第 0 组绑定
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, frame_bind_group: &'a wgpu::BindGroup) {
+        if self.count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, frame_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.count);
+    }
+}
@@ -0,0 +1,54 @@
+// 曳光：每个实例是一条从起点到终点的线段，六个顶点组成朝向相机的细长面片，叠加混合
+
+// 场景的每帧绑定组，只用到开头的相机部分
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    view_position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct TracerInput {
+    @location(0) start: vec3<f32>,
+    @location(1) width: f32,
+    @location(2) end: vec3<f32>,
+    // 已经乘上了淡出
+    @location(3) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    // 横向 -1..1，边缘变暗
+    @location(1) across: f32,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, tracer: TracerInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+    let point = mix(tracer.start, tracer.end, corner.y);
+    let axis = tracer.end - tracer.start;
+    var right = cross(axis, camera.view_position.xyz - point);
+    right = select(normalize(right), vec3<f32>(1.0, 0.0, 0.0), length(right) < 0.0001);
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(point + right * corner.x * tracer.width, 1.0);
+    out.color = tracer.color;
+    out.across = corner.x;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let edge = 1.0 - abs(in.across);
+    return vec4<f32>(in.color.rgb, in.color.a * edge * edge);
+}
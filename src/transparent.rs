@@ -0,0 +1,106 @@
+use glam::Vec3;
+
+use crate::map::{self, Map};
+use crate::model::{self, Model};
+use crate::texture;
+
+// 半透明物体（玻璃、积水等）：在不透明物体之后单独绘制，
+// 开启混合、不写深度，每帧按到相机的距离从远到近排序
+
+pub struct TransparentItem {
+    // 排序用的中心点
+    pub center: Vec3,
+    pub model: Model,
+}
+
+pub struct TransparentSet {
+    items: Vec<TransparentItem>,
+    // 本帧的绘制顺序（items 的下标）
+    order: Vec<usize>,
+}
+
+impl TransparentSet {
+    pub fn new(items: Vec<TransparentItem>) -> Self {
+        let order = (0..items.len()).collect();
+        Self { items, order }
+    }
+
+    // 地图中的玻璃隔断和积水
+    pub fn from_map(device: &wgpu::Device, map: &Map, glass_thickness: f32) -> Self {
+        let mut items = Vec::new();
+        for pane in map.glass_panes() {
+            let center = (Vec3::from(pane.start) + Vec3::from(pane.end)) / 2.0 + Vec3::Y * map::WALL_HEIGHT / 2.0;
+            items.push(TransparentItem { center, model: model::create_glass_pane(device, &pane, glass_thickness) });
+        }
+        for (col, row) in map.water_cells() {
+            let center = map.cell_center(col, row);
+            items.push(TransparentItem { center, model: model::create_water(device, center, map::CELL_SIZE) });
+        }
+        Self::new(items)
+    }
+
+    // 从远到近排序，后画的近处物体混合在远处物体之上
+    pub fn sort(&mut self, camera_position: Vec3) {
+        let items = &self.items;
+        self.order.sort_by(|a, b| {
+            let da = items[*a].center.distance_squared(camera_position);
+            let db = items[*b].center.distance_squared(camera_position);
+            db.total_cmp(&da)
+        });
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        for index in &self.order {
+            self.items[*index].model.draw(render_pass);
+        }
+    }
+}
+
+// 与场景管线使用同样的着色器和布局，区别是 Alpha 混合、不写深度、不剔除背面
+pub fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Transparent Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[model::ModelVertex::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: texture::Texture::DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: texture::Texture::DEPTH_COMPARE,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
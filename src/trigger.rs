@@ -0,0 +1,33 @@
+use glam::Vec3;
+
+use crate::map::TriggerZone;
+
+// 触发区域：玩家进入区域时发出一次事件，离开后再进入会再次触发
+
+pub struct TriggerSystem {
+    zones: Vec<TriggerZone>,
+    // 当前玩家所在的区域
+    inside: Vec<char>,
+}
+
+impl TriggerSystem {
+    pub fn new(zones: Vec<TriggerZone>) -> Self {
+        Self { zones, inside: Vec::new() }
+    }
+
+    // 返回这一帧新进入的区域
+    pub fn update(&mut self, position: Vec3) -> Vec<char> {
+        let mut entered = Vec::new();
+        for zone in &self.zones {
+            let is_inside = position.cmpge(zone.min).all() && position.cmple(zone.max).all();
+            let was_inside = self.inside.contains(&zone.id);
+            if is_inside && !was_inside {
+                self.inside.push(zone.id);
+                entered.push(zone.id);
+            } else if !is_inside && was_inside {
+                self.inside.retain(|id| *id != zone.id);
+            }
+        }
+        entered
+    }
+}
@@ -0,0 +1,60 @@
+use crate::events::GameEvent;
+use crate::input::{Action, Device, InputConfig};
+use crate::map::{Prompt, PromptCondition};
+use crate::ui::UiBatch;
+
+// 教程：地图文件里的 prompt 按顺序显示，完成条件由事件总线（触发区域、按键操作）驱动
+
+const PROMPT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const STEP_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+
+pub struct Tutorial {
+    prompts: Vec<Prompt>,
+    step: usize,
+}
+
+impl Tutorial {
+    pub fn new(prompts: Vec<Prompt>) -> Self {
+        Self { prompts, step: 0 }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.step < self.prompts.len()
+    }
+
+    // 处理一个事件，当前步骤完成时返回 true
+    pub fn handle_event(&mut self, event: &GameEvent) -> bool {
+        let Some(prompt) = self.prompts.get(self.step) else { return false };
+        let done = match (&prompt.condition, event) {
+            (PromptCondition::EnterTrigger(id), GameEvent::TriggerEntered { id: entered }) => id == entered,
+            (PromptCondition::Action(name), GameEvent::ActionPerformed { action }) => {
+                Action::from_name(name) == Some(*action)
+            }
+            _ => false,
+        };
+        if done {
+            self.step += 1;
+        }
+        done
+    }
+
+    // 在屏幕下方居中显示当前提示，按键名取自当前的按键绑定，用手柄时是手柄的按键
+    pub fn draw(&self, ui: &mut UiBatch, input: &InputConfig, device: Device) {
+        let Some(prompt) = self.prompts.get(self.step) else { return };
+        let text = input.format_prompt(&prompt.text, device);
+        let scale = 3.0;
+        let width = UiBatch::text_width(&text, scale) + 40.0;
+        let height = UiBatch::line_height(scale) * 2.0 + 20.0;
+        let x = (ui.width() - width) / 2.0;
+        let y = ui.height() - height - 60.0;
+        ui.rect(x, y, width, height, [0.0, 0.0, 0.0, 0.7]);
+        ui.text_centered(
+            ui.width() / 2.0,
+            y + 10.0,
+            2.0,
+            STEP_COLOR,
+            &format!("TUTORIAL {}/{}", self.step + 1, self.prompts.len()),
+        );
+        ui.text_centered(ui.width() / 2.0, y + 10.0 + UiBatch::line_height(scale), scale, PROMPT_COLOR, &text);
+    }
+}
@@ -0,0 +1,325 @@
+use crate::font;
+use crate::gpu_memory;
+use crate::icons::{self, Icon};
+use crate::texture;
+use crate::upload::FrameUploader;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct UiVertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+// 手动实现 bytemuck traits
+unsafe impl bytemuck::Pod for UiVertex {}
+unsafe impl bytemuck::Zeroable for UiVertex {}
+
+impl UiVertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<UiVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                // position
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // tex_coords
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                // color
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+// 界面操作，键盘和手柄都转换成这几种，菜单和物品栏用焦点（当前选中的一项）来导航
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Nav {
+    Up,
+    Down,
+    Select,
+    Back,
+}
+
+// 一帧内要画的界面元素，坐标以像素为单位（左上角为原点）
+pub struct UiBatch {
+    width: f32,
+    height: f32,
+    vertices: Vec<UiVertex>,
+}
+
+impl UiBatch {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width: width as f32,
+            height: height as f32,
+            vertices: Vec::new(),
+        }
+    }
+
+    // 把另一批（例如任务线程上画好的小地图）接在后面
+    pub fn append(&mut self, other: &UiBatch) {
+        self.vertices.extend_from_slice(&other.vertices);
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    // 像素坐标转换到 NDC
+    fn to_ndc(&self, x: f32, y: f32) -> [f32; 2] {
+        [x / self.width * 2.0 - 1.0, 1.0 - y / self.height * 2.0]
+    }
+
+    // 画一个贴图矩形，纹理坐标为图集中的范围
+    fn quad(&mut self, x: f32, y: f32, w: f32, h: f32, (uv_min, uv_max): ([f32; 2], [f32; 2]), color: [f32; 4]) {
+        let tl = (self.to_ndc(x, y), uv_min);
+        let tr = (self.to_ndc(x + w, y), [uv_max[0], uv_min[1]]);
+        let br = (self.to_ndc(x + w, y + h), uv_max);
+        let bl = (self.to_ndc(x, y + h), [uv_min[0], uv_max[1]]);
+        for (position, tex_coords) in [tl, bl, br, tl, br, tr] {
+            self.vertices.push(UiVertex { position, tex_coords, color });
+        }
+    }
+
+    // 画一个实心矩形
+    pub fn rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: [f32; 4]) {
+        self.quad(x, y, w, h, (icons::SOLID_UV, icons::SOLID_UV), color);
+    }
+
+    // 画一个图标，size 为边长（像素）
+    pub fn icon(&mut self, x: f32, y: f32, size: f32, icon: Icon, color: [f32; 4]) {
+        self.quad(x, y, size, size, icon.uv(), color);
+    }
+
+    // 画一个空心矩形边框
+    pub fn rect_outline(&mut self, x: f32, y: f32, w: f32, h: f32, thickness: f32, color: [f32; 4]) {
+        self.rect(x, y, w, thickness, color);
+        self.rect(x, y + h - thickness, w, thickness, color);
+        self.rect(x, y, thickness, h, color);
+        self.rect(x + w - thickness, y, thickness, h, color);
+    }
+
+    // 用点阵字体画文字，scale 为每个点的像素大小
+    pub fn text(&mut self, x: f32, y: f32, scale: f32, color: [f32; 4], text: &str) {
+        let mut cursor = x;
+        for c in text.chars() {
+            let glyph = font::glyph(c);
+            for col in 0..font::GLYPH_WIDTH {
+                for row in 0..font::GLYPH_HEIGHT {
+                    if font::pixel(glyph, col, row) {
+                        self.rect(
+                            cursor + col as f32 * scale,
+                            y + row as f32 * scale,
+                            scale,
+                            scale,
+                            color,
+                        );
+                    }
+                }
+            }
+            cursor += Self::char_advance(scale);
+        }
+    }
+
+    // 文字居中绘制
+    pub fn text_centered(&mut self, center_x: f32, y: f32, scale: f32, color: [f32; 4], text: &str) {
+        let w = Self::text_width(text, scale);
+        self.text(center_x - w / 2.0, y, scale, color, text);
+    }
+
+    pub fn char_advance(scale: f32) -> f32 {
+        (font::GLYPH_WIDTH + 1) as f32 * scale
+    }
+
+    pub fn line_height(scale: f32) -> f32 {
+        (font::GLYPH_HEIGHT + 3) as f32 * scale
+    }
+
+    pub fn text_width(text: &str, scale: f32) -> f32 {
+        text.chars().count() as f32 * Self::char_advance(scale)
+    }
+}
+
+// 界面渲染器：在场景之后用单独的 pass 叠加绘制
+pub struct UiRenderer {
+    pipeline: wgpu::RenderPipeline,
+    // 图标图集
+    bind_group: wgpu::BindGroup,
+    vertex_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    capacity: usize,
+}
+
+impl UiRenderer {
+    const INITIAL_CAPACITY: usize = 6 * 4096;
+
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("UI Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("ui.wgsl").into()),
+        });
+
+        let atlas = texture::Texture::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(icons::generate_atlas()),
+            Some("icon_atlas"),
+        ).expect("无法创建图标图集");
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("ui_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&atlas.sampler),
+                },
+            ],
+            label: Some("ui_bind_group"),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("UI Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("UI Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[UiVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let vertex_buffer = Self::create_vertex_buffer(device, Self::INITIAL_CAPACITY);
+
+        Self {
+            pipeline,
+            bind_group,
+            vertex_buffer,
+            capacity: Self::INITIAL_CAPACITY,
+        }
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, capacity: usize) -> gpu_memory::Tracked<wgpu::Buffer> {
+        gpu_memory::buffer(device, gpu_memory::Category::Ui, &wgpu::BufferDescriptor {
+            label: Some("UI Vertex Buffer"),
+            size: (capacity * std::mem::size_of::<UiVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        uploader: &mut FrameUploader,
+        view: &wgpu::TextureView,
+        batch: &UiBatch,
+    ) {
+        if batch.is_empty() {
+            return;
+        }
+
+        // 顶点数超过容量时按两倍扩容
+        if batch.vertices.len() > self.capacity {
+            while self.capacity < batch.vertices.len() {
+                self.capacity *= 2;
+            }
+            self.vertex_buffer = Self::create_vertex_buffer(device, self.capacity);
+        }
+        uploader.write(queue, encoder, &self.vertex_buffer, 0, &batch.vertices);
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("UI Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..batch.vertices.len() as u32, 0..1);
+    }
+}
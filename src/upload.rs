@@ -0,0 +1,80 @@
+use crate::gpu_memory;
+// 每帧数据的上传：相机、墙体颜色、粒子参数、UI 顶点等先拼进一块内存，
+// 帧末用一次 write_buffer 写进常驻的暂存缓冲区，再由命令编码器复制到各自的目标缓冲区。
+// 复制命令在写入时就记录下来，所以一帧之内暂存缓冲区不能换；
+// 放不下的数据直接 write_buffer，下一帧开始时再把暂存缓冲区扩大
+
+const INITIAL_CAPACITY: u64 = 64 * 1024;
+
+pub struct FrameUploader {
+    staging: gpu_memory::Tracked<wgpu::Buffer>,
+    capacity: u64,
+    data: Vec<u8>,
+    // 本帧放不下的字节数
+    overflow: u64,
+}
+
+impl FrameUploader {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            staging: create_staging(device, INITIAL_CAPACITY),
+            capacity: INITIAL_CAPACITY,
+            data: Vec::with_capacity(INITIAL_CAPACITY as usize),
+            overflow: 0,
+        }
+    }
+
+    // 每帧记录命令之前调用
+    pub fn begin(&mut self, device: &wgpu::Device) {
+        if self.overflow > 0 {
+            let needed = self.capacity + self.overflow;
+            while self.capacity < needed {
+                self.capacity *= 2;
+            }
+            println!("上传暂存区扩大到 {} KB", self.capacity / 1024);
+            self.staging = create_staging(device, self.capacity);
+            self.overflow = 0;
+        }
+        self.data.clear();
+    }
+
+    // 目标缓冲区需要 COPY_DST，偏移和长度都要按 4 字节对齐
+    pub fn write<T: bytemuck::Pod>(
+        &mut self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::Buffer,
+        offset: u64,
+        values: &[T],
+    ) {
+        let bytes: &[u8] = bytemuck::cast_slice(values);
+        if bytes.is_empty() {
+            return;
+        }
+        let start = self.data.len() as u64;
+        let size = bytes.len() as u64;
+        if start + size > self.capacity {
+            self.overflow += size;
+            queue.write_buffer(target, offset, bytes);
+            return;
+        }
+        self.data.extend_from_slice(bytes);
+        encoder.copy_buffer_to_buffer(&self.staging, start, target, offset, size);
+    }
+
+    // 在提交命令之前调用
+    pub fn finish(&mut self, queue: &wgpu::Queue) {
+        if !self.data.is_empty() {
+            queue.write_buffer(&self.staging, 0, &self.data);
+        }
+    }
+}
+
+fn create_staging(device: &wgpu::Device, size: u64) -> gpu_memory::Tracked<wgpu::Buffer> {
+    gpu_memory::buffer(device, gpu_memory::Category::Frame, &wgpu::BufferDescriptor {
+        label: Some("Frame Upload Staging Buffer"),
+        size,
+        usage: wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
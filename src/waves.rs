@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use crate::events::GameEvent;
+use crate::progression::{self, Perk, Progression};
+use crate::ui::UiBatch;
+
+// 波次模式：敌人一波一波地出现，打完一波进入购买阶段，用击杀赚的钱在商店里买武器、弹药、护甲和天赋。
+// 购买时间到了或者在商店里选 START WAVE 开始下一波。这一局买的天赋不进档案，叠加在档案的天赋上
+
+// 第一波之前和每两波之间的购买时间（秒）
+const BUY_TIME: f32 = 30.0;
+const START_MONEY: u32 = 500;
+const KILL_MONEY: u32 = 100;
+const HEADSHOT_MONEY: u32 = 50;
+// 打完一波的奖励：基础加上每波递增
+const WAVE_MONEY: u32 = 300;
+const WAVE_MONEY_PER_WAVE: u32 = 100;
+// 每波的敌人数
+const BASE_ENEMIES: usize = 3;
+const ENEMIES_PER_WAVE: usize = 2;
+// 一波只剩这么几个敌人时给它们描边，不用满地图找
+const MARKED_ENEMIES: usize = 3;
+
+const TEXT_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const MONEY_COLOR: [f32; 4] = [0.45, 0.9, 0.4, 1.0];
+const TITLE_COLOR: [f32; 4] = [1.0, 0.85, 0.2, 1.0];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    // 剩余的购买时间
+    Buy(f32),
+    Fight,
+}
+
+// update 的结果，由调用者刷出敌人和发出事件
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WaveUpdate {
+    // 第几波开始，要刷出多少敌人
+    Started { wave: u32, enemies: usize },
+    Cleared { wave: u32, reward: u32 },
+}
+
+pub struct WaveMode {
+    wave: u32,
+    phase: Phase,
+    money: u32,
+    // 这一局在商店买的天赋等级
+    perks: BTreeMap<Perk, u32>,
+}
+
+impl WaveMode {
+    pub fn new() -> Self {
+        Self { wave: 0, phase: Phase::Buy(BUY_TIME), money: START_MONEY, perks: BTreeMap::new() }
+    }
+
+    pub fn is_buy_phase(&self) -> bool {
+        matches!(self.phase, Phase::Buy(_))
+    }
+
+    pub fn money(&self) -> u32 {
+        self.money
+    }
+
+    // 剩余的购买时间，战斗中为 None
+    pub fn buy_time(&self) -> Option<f32> {
+        match self.phase {
+            Phase::Buy(remaining) => Some(remaining),
+            Phase::Fight => None,
+        }
+    }
+
+    // 剩下的敌人是否描边显示
+    pub fn marks_enemies(&self, enemies_left: usize) -> bool {
+        self.phase == Phase::Fight && enemies_left <= MARKED_ENEMIES
+    }
+
+    // 钱够时扣钱并返回 true
+    pub fn spend(&mut self, price: u32) -> bool {
+        if !self.is_buy_phase() || self.money < price {
+            return false;
+        }
+        self.money -= price;
+        true
+    }
+
+    // 捡到敌人掉的钱
+    pub fn add_money(&mut self, amount: u32) {
+        self.money += amount;
+    }
+
+    // 击杀给钱
+    pub fn record(&mut self, event: &GameEvent) {
+        if let GameEvent::Kill { headshot } = event {
+            self.money += KILL_MONEY + if *headshot { HEADSHOT_MONEY } else { 0 };
+        }
+    }
+
+    // 提前结束购买时间
+    pub fn ready(&mut self) {
+        if let Phase::Buy(remaining) = &mut self.phase {
+            *remaining = 0.0;
+        }
+    }
+
+    // enemies_left 是还活着和还没刷出来的敌人数
+    pub fn update(&mut self, dt: f32, enemies_left: usize) -> Option<WaveUpdate> {
+        match &mut self.phase {
+            Phase::Buy(remaining) => {
+                *remaining -= dt;
+                if *remaining > 0.0 {
+                    return None;
+                }
+                self.wave += 1;
+                self.phase = Phase::Fight;
+                Some(WaveUpdate::Started { wave: self.wave, enemies: BASE_ENEMIES + ENEMIES_PER_WAVE * (self.wave as usize - 1) })
+            }
+            Phase::Fight if enemies_left == 0 => {
+                let reward = WAVE_MONEY + WAVE_MONEY_PER_WAVE * (self.wave - 1);
+                self.money += reward;
+                self.phase = Phase::Buy(BUY_TIME);
+                Some(WaveUpdate::Cleared { wave: self.wave, reward })
+            }
+            Phase::Fight => None,
+        }
+    }
+
+    // 档案里的和这一局买的加起来的天赋等级
+    pub fn perk_rank(&self, progression: &Progression, perk: Perk) -> u32 {
+        (progression.rank(perk) + self.perks.get(&perk).copied().unwrap_or(0)).min(progression::MAX_RANK)
+    }
+
+    // 加上这一局买的天赋，没有升级（已经满级）时返回 false
+    pub fn add_perk(&mut self, progression: &Progression, perk: Perk) -> bool {
+        if self.perk_rank(progression, perk) >= progression::MAX_RANK {
+            return false;
+        }
+        *self.perks.entry(perk).or_insert(0) += 1;
+        true
+    }
+
+    // 叠加了这一局天赋的档案天赋，用来设置武器和体力
+    pub fn boosted(&self, progression: &Progression) -> Progression {
+        let mut boosted = progression.clone();
+        for perk in Perk::ALL {
+            boosted.perks.insert(perk, self.perk_rank(progression, perk));
+        }
+        boosted
+    }
+
+    // 屏幕上方：第几波、剩下的敌人或者购买倒计时，以及钱
+    pub fn draw(&self, ui: &mut UiBatch, enemies_left: usize, shop_key: &str) {
+        let center = ui.width() / 2.0;
+        let (title, detail) = match self.phase {
+            Phase::Buy(remaining) => (
+                format!("WAVE {} IN {}", self.wave + 1, remaining.ceil() as u32),
+                format!("{} SHOP", shop_key),
+            ),
+            Phase::Fight => (format!("WAVE {}", self.wave), format!("{} ENEMIES LEFT", enemies_left)),
+        };
+        ui.text_centered(center, 70.0, 3.0, TITLE_COLOR, &title);
+        ui.text_centered(center, 104.0, 2.0, TEXT_COLOR, &detail);
+        ui.text_centered(center, 128.0, 2.0, MONEY_COLOR, &format!("${}", self.money));
+    }
+}
@@ -0,0 +1,411 @@
+use glam::Vec3;
+
+use crate::collision::{self, Aabb, WallCollider};
+use crate::damage::DamageType;
+use crate::map;
+use crate::mods;
+
+// 武器：属性定义在 data/weapons.json，射击为即时命中（hitscan），写了投射物速度的武器发射飞行的投射物（见 projectile.rs）
+
+// 武器栏位：每个栏位只能放一把武器
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeaponSlot {
+    Primary,
+    Secondary,
+    // 霰弹枪等重武器
+    Heavy,
+}
+
+impl WeaponSlot {
+    pub const COUNT: usize = 3;
+
+    // 物品栏里的位置，也是切换武器的数字键减一
+    pub fn index(self) -> usize {
+        match self {
+            WeaponSlot::Primary => 0,
+            WeaponSlot::Secondary => 1,
+            WeaponSlot::Heavy => 2,
+        }
+    }
+}
+
+// 弹药种类，右下角弹药数旁边显示
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmmoType {
+    #[default]
+    Rifle,
+    Pistol,
+    Shells,
+    Nails,
+}
+
+impl AmmoType {
+    pub fn label(self) -> &'static str {
+        match self {
+            AmmoType::Rifle => "5.56",
+            AmmoType::Pistol => "9MM",
+            AmmoType::Shells => "12GA",
+            AmmoType::Nails => "NAILS",
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct WeaponStats {
+    // 地图文件和敌人配置中引用武器用的名字
+    pub id: String,
+    pub name: String,
+    pub slot: WeaponSlot,
+    pub damage: f32,
+    // 伤害类型，没写时是子弹
+    #[serde(default)]
+    pub damage_type: DamageType,
+    // 两次射击的最短间隔（秒）
+    pub fire_interval: f32,
+    // 散布：弹道偏离准星的最大角度（度），没写时完全准确
+    #[serde(default)]
+    pub spread: f32,
+    // 连续射击时散布扩大：每枪增加多少、最多增加多少、每秒恢复多少（度）
+    #[serde(default)]
+    pub spread_per_shot: f32,
+    #[serde(default)]
+    pub max_spread: f32,
+    #[serde(default)]
+    pub spread_recovery: f32,
+    // 后坐力：每枪视角上抬多少、左右随机偏多少，恢复的速度（度、度/秒），见 recoil.rs
+    #[serde(default)]
+    pub recoil_pitch: f32,
+    #[serde(default)]
+    pub recoil_yaw: f32,
+    #[serde(default)]
+    pub recoil_recovery: f32,
+    // 一次射击打出几发弹丸（霰弹枪），每发分别计算散布和伤害，没写时是一发
+    #[serde(default = "default_pellets")]
+    pub pellets: u32,
+    #[serde(default)]
+    pub ammo: AmmoType,
+    pub range: f32,
+    // 伤害衰减：falloff_start 之后线性降低，到 falloff_end 时为 min_damage_scale 倍
+    pub falloff_start: f32,
+    pub falloff_end: f32,
+    pub min_damage_scale: f32,
+    // 穿透力：子弹最多能穿过的墙体总厚度（米，沿射线方向计算）
+    pub penetration: f32,
+    // 每穿过一面墙后剩余的伤害比例
+    pub penetration_damage_scale: f32,
+    // 弹匣容量和换弹时间（秒），没写弹匣时不用换弹
+    #[serde(default)]
+    pub magazine: u32,
+    #[serde(default)]
+    pub reload_time: f32,
+    // 最多带多少备弹，换弹时从备弹里装；没写时备弹不限
+    #[serde(default)]
+    pub reserve: u32,
+    // 投射物的初速（米/秒）和受到的重力，没写速度时是即时命中
+    #[serde(default)]
+    pub projectile_speed: f32,
+    #[serde(default)]
+    pub projectile_gravity: f32,
+}
+
+fn default_pellets() -> u32 {
+    1
+}
+
+impl WeaponStats {
+    // 每发弹丸在散布范围内随机偏离瞄准方向，散布角（度）内均匀分布
+    pub fn pellet_dirs(&self, dir: Vec3, spread: f32) -> Vec<Vec3> {
+        (0..self.pellets.max(1)).map(|_| spread_dir(dir, spread.to_radians())).collect()
+    }
+
+    // 距离衰减后的伤害（不考虑穿透）
+    pub fn damage_at(&self, distance: f32) -> f32 {
+        let span = (self.falloff_end - self.falloff_start).max(f32::EPSILON);
+        let t = ((distance - self.falloff_start) / span).clamp(0.0, 1.0);
+        self.damage * (1.0 + (self.min_damage_scale - 1.0) * t)
+    }
+}
+
+// data/weapons.json 的格式版本
+const WEAPONS_VERSION: u32 = 1;
+
+#[derive(serde::Deserialize)]
+struct WeaponsFile {
+    weapons: Vec<WeaponStats>,
+}
+
+// 游戏自带的武器加上启用的 mod 里的，id 相同时后加载的替换前面的
+pub fn load_weapons() -> Vec<WeaponStats> {
+    mods::load_overlaid(
+        "data/weapons.json",
+        include_str!("../data/weapons.json"),
+        WEAPONS_VERSION,
+        "武器",
+        |file: WeaponsFile| file.weapons,
+        |weapon| &weapon.id,
+    )
+}
+
+// 在以 dir 为轴、半角为 angle 的圆锥里随机取一个方向
+fn spread_dir(dir: Vec3, angle: f32) -> Vec3 {
+    if angle <= 0.0 {
+        return dir;
+    }
+    let (right, up) = dir.any_orthonormal_pair();
+    let cos = 1.0 - fastrand::f32() * (1.0 - angle.cos());
+    let sin = (1.0 - cos * cos).sqrt();
+    let turn = fastrand::f32() * std::f32::consts::TAU;
+    (dir * cos + (right * turn.cos() + up * turn.sin()) * sin).normalize()
+}
+
+pub fn find_weapon<'a>(weapons: &'a [WeaponStats], id: &str) -> Option<&'a WeaponStats> {
+    weapons.iter().find(|weapon| weapon.id == id)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImpactKind {
+    // 子弹打进表面
+    Entry,
+    // 子弹穿透后从墙体另一面射出
+    Exit,
+}
+
+// 子弹在表面留下的弹孔
+#[derive(Debug, Clone, Copy)]
+pub struct Impact {
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub kind: ImpactKind,
+    // 到达这一点时子弹的剩余伤害
+    pub damage: f32,
+}
+
+// 天赋对武器的加成（倍数），重生时按档案里的天赋设置，丢到地上的武器还是原来的属性
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeaponPerks {
+    pub reload_scale: f32,
+    pub magazine_scale: f32,
+}
+
+impl Default for WeaponPerks {
+    fn default() -> Self {
+        Self { reload_scale: 1.0, magazine_scale: 1.0 }
+    }
+}
+
+pub struct Weapon {
+    pub stats: WeaponStats,
+    perks: WeaponPerks,
+    cooldown: f32,
+    ammo: u32,
+    reserve: u32,
+    // 换弹剩余的时间，没在换弹时为 0
+    reloading: f32,
+    // 连续射击扩大的散布（度）
+    bloom: f32,
+}
+
+// 切换到一把武器以后要过这么久才能开火（秒）
+const RAISE_TIME: f32 = 0.35;
+
+impl Weapon {
+    pub fn new(stats: WeaponStats, perks: WeaponPerks) -> Self {
+        let mut weapon = Self { stats, perks, cooldown: 0.0, ammo: 0, reserve: 0, reloading: 0.0, bloom: 0.0 };
+        weapon.refill();
+        weapon
+    }
+
+    // 换了天赋，弹匣变小时多出来的子弹丢掉
+    pub fn set_perks(&mut self, perks: WeaponPerks) {
+        self.perks = perks;
+        self.ammo = self.ammo.min(self.magazine());
+    }
+
+    // 弹匣和备弹装满，本来就是满的时返回 false
+    pub fn refill(&mut self) -> bool {
+        if self.ammo >= self.magazine() && self.reserve >= self.stats.reserve {
+            return false;
+        }
+        self.ammo = self.magazine();
+        self.reserve = self.stats.reserve;
+        self.reloading = 0.0;
+        true
+    }
+
+    // 不限备弹时也算满的
+    pub fn reserve_full(&self) -> bool {
+        self.reserve >= self.stats.reserve
+    }
+
+    // 补充备弹，不超过上限
+    pub fn add_reserve(&mut self, rounds: u32) {
+        self.reserve = (self.reserve + rounds).min(self.stats.reserve);
+    }
+
+    // 备弹数，不限备弹时为 None
+    pub fn reserve(&self) -> Option<u32> {
+        (self.stats.reserve > 0).then_some(self.reserve)
+    }
+
+    // 加上天赋以后的弹匣容量，0 为不用换弹
+    pub fn magazine(&self) -> u32 {
+        (self.stats.magazine as f32 * self.perks.magazine_scale).round() as u32
+    }
+
+    pub fn ammo(&self) -> u32 {
+        self.ammo
+    }
+
+    pub fn is_reloading(&self) -> bool {
+        self.reloading > 0.0
+    }
+
+    // 切换到这把武器：举枪的时间内不能开火，收起时没换完的弹匣重新换
+    pub fn raise(&mut self) {
+        self.cooldown = self.cooldown.max(RAISE_TIME);
+        if self.is_reloading() {
+            self.reloading = 0.0;
+            self.reload();
+        }
+    }
+
+    // 现在的散布（度），下一枪按它偏离
+    pub fn spread(&self) -> f32 {
+        self.stats.spread + self.bloom
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.cooldown = (self.cooldown - dt).max(0.0);
+        self.bloom = (self.bloom - self.stats.spread_recovery * dt).max(0.0);
+        if self.reloading > 0.0 {
+            self.reloading -= dt;
+            if self.reloading <= 0.0 {
+                self.reloading = 0.0;
+                let loaded = match self.reserve() {
+                    Some(reserve) => (self.magazine() - self.ammo).min(reserve),
+                    None => self.magazine() - self.ammo,
+                };
+                self.ammo += loaded;
+                if self.reserve().is_some() {
+                    self.reserve -= loaded;
+                }
+            }
+        }
+    }
+
+    // 开始换弹，弹匣是满的、没有备弹或者正在换弹时返回 false
+    pub fn reload(&mut self) -> bool {
+        if self.magazine() == 0 || self.ammo >= self.magazine() || self.is_reloading() || self.reserve() == Some(0) {
+            return false;
+        }
+        self.reloading = (self.stats.reload_time * self.perks.reload_scale).max(0.01);
+        true
+    }
+
+    // 冷却结束、弹匣里有子弹时开火，返回是否成功。打空了自动换弹
+    pub fn try_fire(&mut self) -> bool {
+        if self.cooldown > 0.0 || self.is_reloading() {
+            return false;
+        }
+        if self.magazine() > 0 {
+            if self.ammo == 0 {
+                self.reload();
+                return false;
+            }
+            self.ammo -= 1;
+            if self.ammo == 0 {
+                self.reload();
+            }
+        }
+        self.cooldown = self.stats.fire_interval;
+        self.bloom = (self.bloom + self.stats.spread_per_shot).min(self.stats.max_spread);
+        true
+    }
+}
+
+// 命中的目标（敌人等），target 为调用者传入的目标编号，normal 是子弹打进去的那个面的法向量
+#[derive(Debug, Clone, Copy)]
+pub struct TargetHit {
+    pub target: usize,
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub damage: f32,
+}
+
+pub struct Shot {
+    pub impacts: Vec<Impact>,
+    pub hit: Option<TargetHit>,
+}
+
+impl Shot {
+    // 子弹停下的位置：打中的目标、最后一个弹孔，什么都没碰到时是射程的尽头
+    pub fn end(&self, origin: Vec3, dir: Vec3, range: f32) -> Vec3 {
+        match (&self.hit, self.impacts.last()) {
+            (Some(hit), _) => hit.point,
+            (None, Some(impact)) => impact.point,
+            (None, None) => origin + dir * range,
+        }
+    }
+}
+
+// 即时命中射击：沿射线依次穿过墙体，每穿过一面墙伤害按比例降低，
+// 累计穿过的厚度超过武器的穿透力时子弹停下；地面、天花板和目标不可穿透
+pub fn hitscan(stats: &WeaponStats, colliders: &[WallCollider], targets: &[(usize, Aabb)], origin: Vec3, dir: Vec3) -> Shot {
+    let mut impacts = Vec::new();
+
+    // 地面或天花板
+    let surface = if dir.y < -1e-6 {
+        Some(((0.0 - origin.y) / dir.y, Vec3::Y))
+    } else if dir.y > 1e-6 {
+        Some(((map::WALL_HEIGHT - origin.y) / dir.y, Vec3::NEG_Y))
+    } else {
+        None
+    }
+    .filter(|(distance, _)| *distance <= stats.range);
+    let surface_distance = surface.map_or(stats.range, |(distance, _)| distance);
+
+    // 最近的目标
+    let target = targets
+        .iter()
+        .filter_map(|(id, aabb)| aabb.raycast_normal(origin, dir, surface_distance).map(|(distance, normal)| (*id, distance, normal)))
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    let max_distance = target.map_or(surface_distance, |(_, distance, _)| distance);
+
+    let mut scale = 1.0;
+    let mut penetrated = 0.0;
+    for hit in collision::raycast_all(colliders, origin, dir, max_distance) {
+        impacts.push(Impact {
+            point: origin + dir * hit.distance,
+            normal: hit.normal,
+            kind: ImpactKind::Entry,
+            damage: stats.damage_at(hit.distance) * scale,
+        });
+        penetrated += hit.exit_distance - hit.distance;
+        if penetrated > stats.penetration || hit.exit_distance > max_distance {
+            return Shot { impacts, hit: None };
+        }
+        scale *= stats.penetration_damage_scale;
+        impacts.push(Impact {
+            point: origin + dir * hit.exit_distance,
+            normal: hit.exit_normal,
+            kind: ImpactKind::Exit,
+            damage: stats.damage_at(hit.exit_distance) * scale,
+        });
+    }
+
+    if let Some((target, distance, normal)) = target {
+        let hit = TargetHit { target, point: origin + dir * distance, normal, damage: stats.damage_at(distance) * scale };
+        return Shot { impacts, hit: Some(hit) };
+    }
+    if let Some((distance, normal)) = surface {
+        impacts.push(Impact {
+            point: origin + dir * distance,
+            normal,
+            kind: ImpactKind::Entry,
+            damage: stats.damage_at(distance) * scale,
+        });
+    }
+    Shot { impacts, hit: None }
+}
@@ -0,0 +1,246 @@
+use glam::Vec3;
+
+use crate::camera::Camera;
+use crate::gpu_memory;
+use crate::particles;
+use crate::texture;
+use crate::upload::FrameUploader;
+
+// 开枪、弹着点和爆炸的粒子：枪口火光、子弹打在墙上溅出的火花和扬起的灰尘、手雷的火球和烟。
+// 数量少、寿命短，在 CPU 上模拟，每帧把活着的粒子展开成朝向相机的面片写进自己的顶点缓冲区，
+// 和界面、小地图一样用 alpha 混合画。不用计算着色器，所有显卡上效果一样
+
+// 同时存在的粒子上限，满了时替换最快消失的
+const MAX_SPRITES: usize = 512;
+const GRAVITY: f32 = 9.8;
+
+const SPARK_COUNT: usize = 8;
+const DUST_COUNT: usize = 3;
+const EXPLOSION_SPARK_COUNT: usize = 24;
+const SMOKE_COUNT: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+struct Sprite {
+    position: Vec3,
+    velocity: Vec3,
+    // 半径，每秒增大 growth
+    size: f32,
+    growth: f32,
+    gravity: f32,
+    // 每秒损失的速度比例
+    drag: f32,
+    color: [f32; 4],
+    life: f32,
+    max_life: f32,
+}
+
+impl Sprite {
+    fn new(position: Vec3, velocity: Vec3, size: f32, color: [f32; 4], life: f32) -> Self {
+        Self { position, velocity, size, growth: 0.0, gravity: 0.0, drag: 0.0, color, life, max_life: life }
+    }
+}
+
+// 与 weapon_fx.wgsl 里的 VertexInput 对应
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct SpriteVertex {
+    position: [f32; 3],
+    corner: [f32; 2],
+    color: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for SpriteVertex {}
+unsafe impl bytemuck::Zeroable for SpriteVertex {}
+
+// 两个三角形组成的面片
+const CORNERS: [[f32; 2]; 6] = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]];
+
+pub struct WeaponEffects {
+    sprites: Vec<Sprite>,
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: gpu_memory::Tracked<wgpu::Buffer>,
+    // 每帧重新填的顶点，留着避免反复分配
+    vertices: Vec<SpriteVertex>,
+    vertex_count: u32,
+}
+
+impl WeaponEffects {
+    pub fn new(device: &wgpu::Device, frame_layout: &wgpu::BindGroupLayout, format: wgpu::TextureFormat) -> Self {
+        let vertex_buffer = gpu_memory::buffer(device, gpu_memory::Category::Particles, &wgpu::BufferDescriptor {
+            label: Some("Weapon Effect Vertices"),
+            size: (MAX_SPRITES * CORNERS.len() * std::mem::size_of::<SpriteVertex>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Weapon Effect Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("weapon_fx.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Weapon Effect Pipeline Layout"),
+            bind_group_layouts: &[frame_layout],
+            push_constant_ranges: &[],
+        });
+        // alpha 混合、不写深度、不剔除背面
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Weapon Effect Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<SpriteVertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x4],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: texture::Texture::DEPTH_COMPARE,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+        Self {
+            sprites: Vec::with_capacity(MAX_SPRITES),
+            pipeline,
+            vertex_buffer,
+            vertices: Vec::with_capacity(MAX_SPRITES * CORNERS.len()),
+            vertex_count: 0,
+        }
+    }
+
+    fn spawn(&mut self, sprite: Sprite) {
+        if self.sprites.len() < MAX_SPRITES {
+            self.sprites.push(sprite);
+        } else if let Some(oldest) = self.sprites.iter_mut().min_by(|a, b| a.life.total_cmp(&b.life)) {
+            *oldest = sprite;
+        }
+    }
+
+    // 枪口火光：一个大的亮团和几个往前喷的小火星，只亮一两帧
+    pub fn muzzle_flash(&mut self, position: Vec3, dir: Vec3) {
+        self.spawn(Sprite::new(position, Vec3::ZERO, 0.07 + fastrand::f32() * 0.03, [1.0, 0.85, 0.5, 1.0], 0.05));
+        self.spawn(Sprite::new(position + dir * 0.06, dir * 2.0, 0.045, [1.0, 0.65, 0.25, 0.9], 0.05));
+        for _ in 0..3 {
+            let direction = (dir + particles::random_unit_vector() * 0.35).normalize_or_zero();
+            self.spawn(Sprite::new(position, direction * (4.0 + fastrand::f32() * 3.0), 0.008, [1.0, 0.8, 0.4, 1.0], 0.06));
+        }
+    }
+
+    // 子弹打在墙上：沿法向量溅出的火花，和慢慢散开、变淡的灰尘
+    pub fn impact(&mut self, position: Vec3, normal: Vec3) {
+        self.spawn(Sprite::new(position + normal * 0.01, Vec3::ZERO, 0.04, [1.0, 0.9, 0.6, 0.9], 0.04));
+        for _ in 0..SPARK_COUNT {
+            let direction = (normal + particles::random_unit_vector() * 0.7).normalize_or_zero();
+            let mut spark = Sprite::new(position, direction * (2.0 + fastrand::f32() * 4.0), 0.01, [1.0, 0.75, 0.3, 1.0], 0.2 + fastrand::f32() * 0.25);
+            spark.gravity = GRAVITY;
+            self.spawn(spark);
+        }
+        for _ in 0..DUST_COUNT {
+            let direction = (normal + particles::random_unit_vector() * 0.5).normalize_or_zero();
+            let shade = 0.55 + fastrand::f32() * 0.15;
+            let mut dust = Sprite::new(position + normal * 0.03, direction * (0.4 + fastrand::f32() * 0.6), 0.03, [shade, shade * 0.95, shade * 0.85, 0.45], 0.6 + fastrand::f32() * 0.4);
+            dust.growth = 0.15;
+            dust.gravity = 0.3;
+            dust.drag = 2.5;
+            self.spawn(dust);
+        }
+    }
+
+    // 手雷爆炸：一团很快长大又暗下去的火球，四面八方飞出去的火星，和慢慢升起、散开的烟
+    pub fn explosion(&mut self, position: Vec3, color: [f32; 3]) {
+        let [r, g, b] = color;
+        let mut flash = Sprite::new(position, Vec3::ZERO, 0.4, [r, g, b, 1.0], 0.15);
+        flash.growth = 6.0;
+        self.spawn(flash);
+        for _ in 0..EXPLOSION_SPARK_COUNT {
+            let direction = particles::random_unit_vector();
+            let mut spark = Sprite::new(position, direction * (5.0 + fastrand::f32() * 7.0), 0.015, [r, g * 0.9, b * 0.8, 1.0], 0.3 + fastrand::f32() * 0.4);
+            spark.gravity = GRAVITY;
+            spark.drag = 1.0;
+            self.spawn(spark);
+        }
+        for _ in 0..SMOKE_COUNT {
+            let offset = particles::random_unit_vector() * 0.4;
+            let shade = 0.25 + fastrand::f32() * 0.15;
+            let mut smoke = Sprite::new(position + offset, offset * 2.0 + Vec3::Y * 0.6, 0.3, [shade, shade, shade, 0.5], 1.5 + fastrand::f32());
+            smoke.growth = 0.8;
+            smoke.gravity = -0.2;
+            smoke.drag = 1.5;
+            self.spawn(smoke);
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.sprites.retain_mut(|sprite| {
+            sprite.life -= dt;
+            sprite.velocity.y -= sprite.gravity * dt;
+            sprite.velocity *= (1.0 - sprite.drag * dt).max(0.0);
+            sprite.position += sprite.velocity * dt;
+            sprite.size += sprite.growth * dt;
+            sprite.life > 0.0
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.sprites.clear();
+    }
+
+    // 在渲染通道之前按相机朝向展开面片并上传
+    pub fn upload(&mut self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, uploader: &mut FrameUploader, camera: &Camera) {
+        self.vertices.clear();
+        let forward = camera.forward();
+        let right = forward.cross(Vec3::Y).try_normalize().unwrap_or(Vec3::X);
+        let up = right.cross(forward);
+        for sprite in &self.sprites {
+            // 生命的后半段逐渐消失
+            let fade = (sprite.life / (sprite.max_life * 0.5)).min(1.0);
+            let color = [sprite.color[0], sprite.color[1], sprite.color[2], sprite.color[3] * fade];
+            for corner in CORNERS {
+                let position = sprite.position + (right * corner[0] + up * corner[1]) * sprite.size;
+                self.vertices.push(SpriteVertex { position: position.to_array(), corner, color });
+            }
+        }
+        self.vertex_count = self.vertices.len() as u32;
+        if !self.vertices.is_empty() {
+            uploader.write(queue, encoder, &self.vertex_buffer, 0, &self.vertices);
+        }
+    }
+
+    // 在曳光之后绘制，会替换管线和第 0 组绑定
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, frame_bind_group: &'a wgpu::BindGroup) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, frame_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
@@ -0,0 +1,42 @@
+// 武器效果的粒子：顶点已经在 CPU 上展开成朝向相机的面片，这里只做变换和圆形的柔和边缘
+
+// 场景的每帧绑定组，只用到开头的相机部分
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    view_position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    // 面片内的位置，-1..1
+    @location(1) corner: vec2<f32>,
+    // 已经乘上了淡出
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) corner: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(in.position, 1.0);
+    out.corner = in.corner;
+    out.color = in.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = length(in.corner);
+    if (distance > 1.0) {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb, in.color.a * (1.0 - distance * distance));
+}
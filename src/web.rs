@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use winit::platform::web::WindowExtWebSys;
+use winit::window::Window;
+
+use crate::{assets, lightmap, map, post};
+
+// 浏览器版（wasm32）：把 panic 和日志输出到浏览器控制台，用 fetch 下载资源文件后启动游戏。
+// 没有 HTTP 调试接口和手柄（用 --no-default-features 编译），联机、设置和档案的保存不可用
+
+// main 调用，游戏在浏览器的事件循环里异步运行
+pub fn start() {
+    console_error_panic_hook::set_once();
+    if let Err(e) = console_log::init_with_level(log::Level::Info) {
+        web_sys::console::error_1(&format!("无法初始化日志: {}", e).into());
+    }
+    wasm_bindgen_futures::spawn_local(async {
+        assets::preload(asset_files()).await;
+        crate::run(Vec::new()).await;
+    });
+}
+
+// 内置地图和它们的光照贴图、预制件和 LUT。服务器上有的文件会代替内置的版本
+fn asset_files() -> Vec<PathBuf> {
+    let (mut files, maps) = map::builtin_files();
+    files.extend(maps.iter().map(|name| lightmap::path(name)));
+    files.extend(post::builtin_lut_files());
+    files
+}
+
+// 把 winit 创建的 canvas 放进页面里 id 为 game 的元素，没有这个元素时放在 body 里
+pub fn attach_canvas(window: &Window) {
+    let canvas = window.canvas();
+    let document = web_sys::window().and_then(|window| window.document()).expect("没有 document 对象");
+    let parent: web_sys::Element = match document.get_element_by_id("game") {
+        Some(element) => element,
+        None => document.body().expect("页面没有 body").into(),
+    };
+    parent.append_child(&canvas).expect("无法把 canvas 加入页面");
+}